@@ -0,0 +1,903 @@
+//! # Module: `arrow`
+//!
+//! Lecture et écriture du format IPC d'Apache Arrow, celui des fichiers `.arrow` et `.feather` (version 2).
+//!
+//! Un flux IPC est une suite de messages : le schéma, puis des lots de lignes (*record batches*),
+//! précédés des dictionnaires des colonnes encodées par dictionnaire. Chaque message est formé de
+//! métadonnées FlatBuffers (module [`flatbuffers`](crate::flatbuffers)) et d'un corps : les tampons
+//! de chaque colonne (validité, positions, valeurs), bout à bout. Un fichier encadre ce flux par la
+//! chaîne `ARROW1` et le termine par un pied de page qui donne la position de chaque lot.
+//!
+//! Les tampons compressés (LZ4, ZSTD) et les types imbriqués autres que les listes (structures,
+//! unions, tables associatives) ne sont pas pris en charge.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::columnar::{Array, DataType, Field, RecordBatch, Values};
+use crate::error::DbError;
+use crate::flatbuffers::{Builder, Table, Value};
+use crate::npy::f16_to_f32;
+
+/// Chaîne magique au début et à la fin d'un fichier.
+const MAGIC: &[u8] = b"ARROW1";
+
+/// Marque qui précède la taille des métadonnées d'un message.
+const CONTINUATION: u32 = 0xFFFF_FFFF;
+
+/// Version des métadonnées écrites (V5).
+const VERSION: i16 = 4;
+
+/// Types d'en-tête de message (union `MessageHeader`).
+const SCHEMA: u8 = 1;
+const DICTIONARY_BATCH: u8 = 2;
+const RECORD_BATCH: u8 = 3;
+
+/// Types de colonne (union `Type`) ; leur rang donne aussi leur nom dans les erreurs.
+const TYPE_NAMES: [&str; 27] = [
+    "NONE", "Null", "Int", "FloatingPoint", "Binary", "Utf8", "Bool", "Decimal", "Date", "Time", "Timestamp", "Interval",
+    "List", "Struct", "Union", "FixedSizeBinary", "FixedSizeList", "Map", "Duration", "LargeBinary", "LargeUtf8",
+    "LargeList", "RunEndEncoded", "BinaryView", "Utf8View", "ListView", "LargeListView",
+];
+const NULL: u8 = 1;
+const INT: u8 = 2;
+const FLOATING_POINT: u8 = 3;
+const BINARY: u8 = 4;
+const UTF8: u8 = 5;
+const BOOL: u8 = 6;
+const DATE: u8 = 8;
+const TIME: u8 = 9;
+const TIMESTAMP: u8 = 10;
+const LIST: u8 = 12;
+const FIXED_SIZE_BINARY: u8 = 15;
+const FIXED_SIZE_LIST: u8 = 16;
+const DURATION: u8 = 18;
+const LARGE_BINARY: u8 = 19;
+const LARGE_UTF8: u8 = 20;
+const LARGE_LIST: u8 = 21;
+
+/// Taille des structures `FieldNode`, `Buffer` et `Block`.
+const NODE_SIZE: usize = 16;
+const BUFFER_SIZE: usize = 16;
+const BLOCK_SIZE: usize = 24;
+
+/// Alignement des tampons dans le corps d'un message.
+const ALIGNMENT: usize = 8;
+
+/// Clé des métadonnées d'une colonne qui nomme son type d'extension, et celui des colonnes JSON.
+const EXTENSION_NAME: &str = "ARROW:extension:name";
+const JSON_EXTENSION: &str = "arrow.json";
+
+/// Profondeur maximale des listes imbriquées, pour qu'un schéma corrompu n'épuise pas la pile.
+const MAX_DEPTH: usize = 32;
+
+/// # Énumération: `ArrowType`
+///
+/// Le type d'une colonne, avec ce qu'il faut pour lire ses tampons.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArrowType {
+    Null,
+    Bool,
+    /// Entier (date et heure comprises) sur `bytes` octets.
+    Int { bytes: usize, signed: bool },
+    Float { bytes: usize },
+    /// Texte ou octets ; positions sur 8 octets si `large`.
+    Utf8 { large: bool },
+    Binary { large: bool },
+    FixedSizeBinary(usize),
+    List { large: bool },
+    FixedSizeList(usize),
+}
+
+/// # Structure: `ArrowField`
+///
+/// Une colonne du schéma, telle que décrite par les métadonnées.
+#[derive(Debug, Clone)]
+struct ArrowField {
+    name: String,
+    nullable: bool,
+    arrow_type: ArrowType,
+    children: Vec<ArrowField>,
+    /// Pour une colonne encodée par dictionnaire, le numéro du dictionnaire et le type des indices.
+    dictionary: Option<(i64, ArrowType)>,
+    /// Si la colonne est du texte de l'extension `arrow.json`.
+    json: bool,
+}
+
+impl ArrowField {
+    fn read(table: Table, depth: usize) -> Result<Self, DbError> {
+        if depth > MAX_DEPTH {
+            return Err(corrupted("schéma trop imbriqué"));
+        }
+        let name = table.string(0)?.unwrap_or_default().to_string();
+        let children = table.tables(5)?.into_iter().map(|child| ArrowField::read(child, depth + 1)).collect::<Result<Vec<_>, _>>()?;
+        let type_id = table.u8(2)?;
+        let data_type = arrow_type(type_id, table.table(3)?).ok_or_else(|| {
+            let type_name = TYPE_NAMES.get(type_id as usize).copied().unwrap_or("inconnu");
+            DbError::Unsupported(format!("colonne '{}' : type Arrow {} non pris en charge", name, type_name))
+        })?;
+        if matches!(data_type, ArrowType::List { .. } | ArrowType::FixedSizeList(_)) && children.len() != 1 {
+            return Err(corrupted(&format!("la liste '{}' doit avoir un seul type d'éléments", name)));
+        }
+        // Rien ne borne alors le nombre de lignes, que rien ne stocke.
+        if matches!(data_type, ArrowType::FixedSizeList(_)) && children[0].arrow_type == ArrowType::Null && children[0].dictionary.is_none() {
+            return Err(DbError::Unsupported(format!("colonne '{}' : liste de taille fixe de valeurs nulles", name)));
+        }
+        let dictionary = match table.table(4)? {
+            Some(encoding) => {
+                let index_type = match encoding.table(1)? {
+                    Some(index) => arrow_type(INT, Some(index)),
+                    None => Some(ArrowType::Int { bytes: 4, signed: true }),
+                };
+                Some((encoding.i64(0, 0)?, index_type.ok_or_else(|| corrupted("type d'indices de dictionnaire invalide"))?))
+            }
+            None => None,
+        };
+        let mut json = false;
+        for entry in table.tables(6)? {
+            json |= entry.string(0)? == Some(EXTENSION_NAME) && entry.string(1)? == Some(JSON_EXTENSION);
+        }
+        Ok(ArrowField {
+            name,
+            nullable: table.bool(1)?,
+            arrow_type: data_type,
+            children,
+            dictionary,
+            json: json && matches!(data_type, ArrowType::Utf8 { .. }),
+        })
+    }
+
+    /// La colonne telle que vue par [`columnar`](crate::columnar).
+    fn field(&self) -> Field {
+        let data_type = match self.arrow_type {
+            ArrowType::Null => DataType::Null,
+            ArrowType::Bool => DataType::Bool,
+            ArrowType::Int { .. } => DataType::Int,
+            ArrowType::Float { bytes } if bytes <= 4 => DataType::Float32,
+            ArrowType::Float { .. } => DataType::Float64,
+            ArrowType::Utf8 { .. } if self.json => DataType::Json,
+            ArrowType::Utf8 { .. } => DataType::Utf8,
+            ArrowType::Binary { .. } | ArrowType::FixedSizeBinary(_) => DataType::Binary,
+            ArrowType::List { .. } => DataType::List(Box::new(self.children[0].field())),
+            ArrowType::FixedSizeList(size) => DataType::FixedSizeList(Box::new(self.children[0].field()), size),
+        };
+        Field::new(&self.name, data_type, self.nullable)
+    }
+}
+
+/// Le type décrit par la table `table` de l'union `Type`, ou `None` s'il n'est pas pris en charge.
+fn arrow_type(type_id: u8, table: Option<Table>) -> Option<ArrowType> {
+    let integer = |slot, default| table.map_or(Ok(default), |table| table.i32(slot, default)).unwrap_or(default);
+    let short = |slot, default| table.map_or(Ok(default), |table| table.i16(slot, default)).unwrap_or(default);
+    let width = |bits: i32| matches!(bits, 8 | 16 | 32 | 64).then_some(bits as usize / 8);
+    Some(match type_id {
+        NULL => ArrowType::Null,
+        BOOL => ArrowType::Bool,
+        INT => ArrowType::Int {
+            bytes: width(integer(0, 0))?,
+            signed: table.is_some_and(|table| table.bool(1).unwrap_or(false)),
+        },
+        FLOATING_POINT => ArrowType::Float { bytes: [2, 4, 8].get(short(0, 0) as usize).copied()? },
+        // Jours sur 4 octets, ou millisecondes (par défaut) sur 8.
+        DATE => ArrowType::Int { bytes: if short(0, 1) == 0 { 4 } else { 8 }, signed: true },
+        TIME => ArrowType::Int { bytes: width(integer(1, 32))?, signed: true },
+        TIMESTAMP | DURATION => ArrowType::Int { bytes: 8, signed: true },
+        UTF8 => ArrowType::Utf8 { large: false },
+        LARGE_UTF8 => ArrowType::Utf8 { large: true },
+        BINARY => ArrowType::Binary { large: false },
+        LARGE_BINARY => ArrowType::Binary { large: true },
+        FIXED_SIZE_BINARY => ArrowType::FixedSizeBinary(usize::try_from(integer(0, 0)).ok()?),
+        LIST => ArrowType::List { large: false },
+        LARGE_LIST => ArrowType::List { large: true },
+        FIXED_SIZE_LIST => ArrowType::FixedSizeList(usize::try_from(integer(0, 0)).ok().filter(|&size| size > 0)?),
+        _ => return None,
+    })
+}
+
+/// # Structure: `ArrowReader`
+///
+/// Lit un fichier ou un flux IPC, un lot de lignes à la fois.
+pub struct ArrowReader<R> {
+    reader: R,
+    arrow_fields: Vec<ArrowField>,
+    fields: Vec<Field>,
+    /// Les dictionnaires lus, par numéro.
+    dictionaries: HashMap<i64, Array>,
+    /// Pour un fichier, la position des lots qui restent à lire ; `None` pour un flux, lu dans l'ordre.
+    blocks: Option<VecDeque<u64>>,
+}
+
+impl<R: Read + Seek> ArrowReader<R> {
+    /// Lit le schéma : dans le pied de page pour un fichier, au début pour un flux.
+    ///
+    /// # Retour
+    /// - `Result<ArrowReader<R>, DbError>`: [`DbError::Corrupted`] si le fichier n'est pas un fichier ou un flux
+    ///   Arrow valide, ou [`DbError::Unsupported`] s'il utilise un type ou une variante non pris en charge.
+    pub fn open(mut reader: R) -> Result<Self, DbError> {
+        let mut magic = [0u8; 6];
+        let is_file = reader.read_exact(&mut magic).is_ok() && magic == MAGIC;
+        let (schema, blocks, dictionaries) = if is_file {
+            let length = reader.seek(SeekFrom::End(-10))?;
+            let mut tail = [0u8; 10];
+            reader.read_exact(&mut tail)?;
+            if &tail[4..] != MAGIC {
+                return Err(corrupted("fin de fichier absente"));
+            }
+            let footer_length = i32::from_le_bytes(tail[..4].try_into().unwrap());
+            let footer_start = length
+                .checked_sub(footer_length as u64)
+                .filter(|_| footer_length > 0)
+                .ok_or_else(|| corrupted("pied de page invalide"))?;
+            reader.seek(SeekFrom::Start(footer_start))?;
+            let mut footer = vec![0; footer_length as usize];
+            reader.read_exact(&mut footer)?;
+            let footer_table = Table::root(&footer)?;
+            let schema = read_schema(footer_table.table(1)?.ok_or_else(|| corrupted("schéma absent du pied de page"))?)?;
+            let offsets = |slot| -> Result<VecDeque<u64>, DbError> {
+                Ok(footer_table.structs(slot, BLOCK_SIZE)?.iter().map(|block| u64::from_le_bytes(block[..8].try_into().unwrap())).collect())
+            };
+            (schema, Some(offsets(3)?), offsets(2)?)
+        } else {
+            reader.seek(SeekFrom::Start(0))?;
+            let (header_type, metadata, _) = read_message(&mut reader)?.ok_or_else(|| corrupted("flux vide"))?;
+            if header_type != SCHEMA {
+                return Err(corrupted("le flux doit commencer par le schéma"));
+            }
+            let message = Table::root(&metadata)?;
+            (read_schema(message.table(2)?.ok_or_else(|| corrupted("schéma absent"))?)?, None, VecDeque::new())
+        };
+
+        let mut arrow_reader = ArrowReader {
+            reader,
+            fields: schema.iter().map(ArrowField::field).collect(),
+            arrow_fields: schema,
+            dictionaries: HashMap::new(),
+            blocks,
+        };
+        for offset in dictionaries {
+            arrow_reader.reader.seek(SeekFrom::Start(offset))?;
+            match read_message(&mut arrow_reader.reader)? {
+                Some((DICTIONARY_BATCH, metadata, body)) => arrow_reader.read_dictionary(&metadata, &body)?,
+                _ => return Err(corrupted("dictionnaire attendu")),
+            }
+        }
+        Ok(arrow_reader)
+    }
+
+    /// Les colonnes du fichier.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Lit le lot de lignes suivant.
+    ///
+    /// # Retour
+    /// - `Result<Option<RecordBatch>, DbError>`: Le lot, `None` après le dernier, ou une erreur de lecture.
+    pub fn next_batch(&mut self) -> Result<Option<RecordBatch>, DbError> {
+        loop {
+            if let Some(blocks) = &mut self.blocks {
+                match blocks.pop_front() {
+                    Some(offset) => self.reader.seek(SeekFrom::Start(offset))?,
+                    None => return Ok(None),
+                };
+            }
+            match read_message(&mut self.reader)? {
+                Some((RECORD_BATCH, metadata, body)) => {
+                    let batch = Table::root(&metadata)?.table(2)?.ok_or_else(|| corrupted("lot sans description"))?;
+                    let columns = self.read_columns(batch, &body, &self.arrow_fields)?;
+                    let rows = usize::try_from(batch.i64(0, 0)?).map_err(|_| corrupted("nombre de lignes invalide"))?;
+                    if columns.iter().any(|column| column.len() != rows) {
+                        return Err(corrupted("colonnes de longueurs différentes"));
+                    }
+                    return Ok(Some(RecordBatch { rows, columns }));
+                }
+                Some((DICTIONARY_BATCH, metadata, body)) if self.blocks.is_none() => self.read_dictionary(&metadata, &body)?,
+                Some((header_type, ..)) => return Err(corrupted(&format!("message de type {} inattendu", header_type))),
+                None if self.blocks.is_none() => return Ok(None),
+                None => return Err(corrupted("lot attendu")),
+            }
+        }
+    }
+
+    fn read_dictionary(&mut self, metadata: &[u8], body: &[u8]) -> Result<(), DbError> {
+        let dictionary = Table::root(metadata)?.table(2)?.ok_or_else(|| corrupted("dictionnaire sans description"))?;
+        let id = dictionary.i64(0, 0)?;
+        let field = find_dictionary(&self.arrow_fields, id).ok_or_else(|| corrupted(&format!("dictionnaire {} inconnu", id)))?;
+        let field = ArrowField { dictionary: None, ..field.clone() };
+        let batch = dictionary.table(1)?.ok_or_else(|| corrupted("dictionnaire sans données"))?;
+        let values = self.read_columns(batch, body, std::slice::from_ref(&field))?.remove(0);
+        if dictionary.bool(2)? {
+            return Err(DbError::Unsupported("dictionnaires incrémentaux (delta)".to_string()));
+        }
+        self.dictionaries.insert(id, values);
+        Ok(())
+    }
+
+    fn read_columns(&self, batch: Table, body: &[u8], fields: &[ArrowField]) -> Result<Vec<Array>, DbError> {
+        if batch.table(3)?.is_some() {
+            return Err(DbError::Unsupported("tampons compressés (LZ4, ZSTD) : écrire le fichier sans compression".to_string()));
+        }
+        let mut body = Body {
+            body,
+            nodes: batch.structs(1, NODE_SIZE)?.into(),
+            buffers: batch.structs(2, BUFFER_SIZE)?.into(),
+        };
+        fields.iter().map(|field| body.array(field, &self.dictionaries)).collect()
+    }
+}
+
+/// La colonne encodée par le dictionnaire `id`, à n'importe quelle profondeur du schéma.
+fn find_dictionary(fields: &[ArrowField], id: i64) -> Option<&ArrowField> {
+    fields.iter().find_map(|field| match field.dictionary {
+        Some((dictionary, _)) if dictionary == id => Some(field),
+        _ => find_dictionary(&field.children, id),
+    })
+}
+
+fn read_schema(schema: Table) -> Result<Vec<ArrowField>, DbError> {
+    if schema.i16(0, 0)? != 0 {
+        return Err(DbError::Unsupported("fichier gros-boutiste".to_string()));
+    }
+    schema.tables(1)?.into_iter().map(|field| ArrowField::read(field, 0)).collect()
+}
+
+/// Un message : son type, ses métadonnées et son corps.
+type Message = (u8, Vec<u8>, Vec<u8>);
+
+/// Lit un message, ou `None` à la fin du flux.
+fn read_message(reader: &mut impl Read) -> Result<Option<Message>, DbError> {
+    let mut prefix = [0u8; 4];
+    if !read_all(reader, &mut prefix)? {
+        return Ok(None);
+    }
+    if u32::from_le_bytes(prefix) == CONTINUATION && !read_all(reader, &mut prefix)? {
+        return Ok(None);
+    }
+    // Sans la marque, les quatre octets sont directement la taille (format antérieur à la version 0.15).
+    let length = i32::from_le_bytes(prefix);
+    if length <= 0 {
+        return Ok(None);
+    }
+    let metadata = read_exactly(reader, length as u64)?;
+    let message = Table::root(&metadata)?;
+    let header_type = message.u8(1)?;
+    let body_length = message.i64(3, 0)?;
+    if body_length < 0 {
+        return Err(corrupted("taille de corps invalide"));
+    }
+    let body = read_exactly(reader, body_length as u64)?;
+    Ok(Some((header_type, metadata, body)))
+}
+
+/// Remplit `buffer`, ou retourne faux si la lecture s'arrête tout de suite (fin du flux).
+fn read_all(reader: &mut impl Read, buffer: &mut [u8]) -> Result<bool, DbError> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..])? {
+            0 if read == 0 => return Ok(false),
+            0 => return Err(corrupted("message tronqué")),
+            count => read += count,
+        }
+    }
+    Ok(true)
+}
+
+/// Lit `length` octets, sans les réserver d'avance sur la foi d'une taille peut-être corrompue.
+fn read_exactly(reader: &mut impl Read, length: u64) -> Result<Vec<u8>, DbError> {
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != length {
+        return Err(corrupted("message tronqué"));
+    }
+    Ok(bytes)
+}
+
+/// Le corps d'un message et les descriptions de ses colonnes et tampons, lus dans l'ordre.
+struct Body<'a> {
+    body: &'a [u8],
+    nodes: VecDeque<&'a [u8]>,
+    buffers: VecDeque<&'a [u8]>,
+}
+
+impl<'a> Body<'a> {
+    /// Le nombre de lignes et de valeurs nulles de la colonne suivante.
+    fn node(&mut self) -> Result<(usize, usize), DbError> {
+        let node = self.nodes.pop_front().ok_or_else(|| corrupted("description de colonne manquante"))?;
+        let length = i64::from_le_bytes(node[..8].try_into().unwrap());
+        let nulls = i64::from_le_bytes(node[8..].try_into().unwrap());
+        if length < 0 || nulls < 0 {
+            return Err(corrupted("nombre de lignes invalide"));
+        }
+        Ok((length as usize, nulls as usize))
+    }
+
+    fn buffer(&mut self) -> Result<&'a [u8], DbError> {
+        let buffer = self.buffers.pop_front().ok_or_else(|| corrupted("tampon manquant"))?;
+        let offset = i64::from_le_bytes(buffer[..8].try_into().unwrap());
+        let length = i64::from_le_bytes(buffer[8..].try_into().unwrap());
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .and_then(|(offset, length)| self.body.get(offset..offset.checked_add(length)?))
+            .ok_or_else(|| corrupted("tampon hors du corps du message"))
+    }
+
+    fn array(&mut self, field: &ArrowField, dictionaries: &HashMap<i64, Array>) -> Result<Array, DbError> {
+        let (length, nulls) = self.node()?;
+        if field.arrow_type == ArrowType::Null && field.dictionary.is_none() {
+            return Ok(Array::new(Values::Null(length)));
+        }
+        let validity = self.buffer()?;
+        let validity = match nulls {
+            0 => None,
+            _ => Some(bits(validity, length)?),
+        };
+
+        if let Some((id, index_type)) = field.dictionary {
+            let Values::Int(indices) = self.values(index_type, length, field, dictionaries)? else {
+                unreachable!("les indices sont des entiers");
+            };
+            let dictionary = dictionaries.get(&id).ok_or_else(|| corrupted(&format!("dictionnaire {} absent", id)))?;
+            let indices = indices
+                .iter()
+                .enumerate()
+                .map(|(row, &index)| match usize::try_from(index) {
+                    Ok(index) if index < dictionary.len() => Ok(index),
+                    // L'indice d'une ligne nulle peut être quelconque.
+                    _ if validity.as_ref().is_some_and(|validity| !validity[row]) => Ok(0),
+                    _ => Err(corrupted(&format!("indice {} hors du dictionnaire", index))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if dictionary.len() == 0 {
+                return Ok(Array { validity: Some(vec![false; length]), values: Values::Null(length) });
+            }
+            let mut array = dictionary.take(&indices);
+            if let Some(validity) = validity {
+                let dictionary_validity = array.validity.unwrap_or_else(|| vec![true; length]);
+                array.validity = Some(validity.iter().zip(dictionary_validity).map(|(&row, value)| row && value).collect());
+            }
+            return Ok(array);
+        }
+        Ok(Array { validity, values: self.values(field.arrow_type, length, field, dictionaries)? })
+    }
+
+    /// Les valeurs d'une colonne de type `arrow_type`, après son tampon de validité.
+    fn values(&mut self, arrow_type: ArrowType, length: usize, field: &ArrowField, dictionaries: &HashMap<i64, Array>) -> Result<Values, DbError> {
+        Ok(match arrow_type {
+            ArrowType::Null => Values::Null(length),
+            ArrowType::Bool => Values::Bool(bits(self.buffer()?, length)?),
+            ArrowType::Int { bytes, signed } => {
+                let data = fixed(self.buffer()?, length, bytes)?;
+                Values::Int(
+                    data.chunks_exact(bytes)
+                        .map(|value| {
+                            let mut buffer = [0u8; 8];
+                            buffer[..bytes].copy_from_slice(value);
+                            if signed && value[bytes - 1] & 0x80 != 0 {
+                                buffer[bytes..].fill(0xff);
+                            }
+                            i64::from_le_bytes(buffer)
+                        })
+                        .collect(),
+                )
+            }
+            ArrowType::Float { bytes } => {
+                let data = fixed(self.buffer()?, length, bytes)?;
+                Values::Float(match bytes {
+                    2 => data.chunks_exact(2).map(|value| f16_to_f32(u16::from_le_bytes(value.try_into().unwrap())) as f64).collect(),
+                    4 => data.chunks_exact(4).map(|value| f32::from_le_bytes(value.try_into().unwrap()) as f64).collect(),
+                    _ => data.chunks_exact(8).map(|value| f64::from_le_bytes(value.try_into().unwrap())).collect(),
+                })
+            }
+            ArrowType::Utf8 { large } | ArrowType::Binary { large } => {
+                let offsets = offsets(self.buffer()?, length, large)?;
+                let data = self.buffer()?;
+                let slices = offsets.windows(2).map(|window| data.get(window[0]..window[1]).ok_or_else(|| corrupted("position hors des données")));
+                if matches!(arrow_type, ArrowType::Utf8 { .. }) {
+                    Values::Utf8(
+                        slices
+                            .map(|slice| String::from_utf8(slice?.to_vec()).map_err(|_| corrupted(&format!("texte invalide dans '{}'", field.name))))
+                            .collect::<Result<_, _>>()?,
+                    )
+                } else {
+                    Values::Binary(slices.map(|slice| Ok(slice?.to_vec())).collect::<Result<_, DbError>>()?)
+                }
+            }
+            ArrowType::FixedSizeBinary(width) => {
+                let data = fixed(self.buffer()?, length, width)?;
+                Values::Binary((0..length).map(|row| data[row * width..(row + 1) * width].to_vec()).collect())
+            }
+            ArrowType::List { large } => {
+                let offsets = offsets(self.buffer()?, length, large)?;
+                let values = self.array(&field.children[0], dictionaries)?;
+                if offsets.last().is_some_and(|&end| end > values.len()) {
+                    return Err(corrupted(&format!("position hors des éléments de '{}'", field.name)));
+                }
+                Values::List { offsets, values: Box::new(values) }
+            }
+            ArrowType::FixedSizeList(size) => {
+                let values = self.array(&field.children[0], dictionaries)?;
+                if length.checked_mul(size).is_none_or(|items| values.len() < items) {
+                    return Err(corrupted(&format!("éléments manquants dans '{}'", field.name)));
+                }
+                Values::List { offsets: (0..=length).map(|row| row * size).collect(), values: Box::new(values) }
+            }
+        })
+    }
+}
+
+/// Les `length` premiers bits d'un tampon, du bit de poids faible au bit de poids fort de chaque octet.
+fn bits(buffer: &[u8], length: usize) -> Result<Vec<bool>, DbError> {
+    if buffer.len() * 8 < length {
+        return Err(corrupted("tampon de bits trop court"));
+    }
+    Ok((0..length).map(|index| buffer[index / 8] >> (index % 8) & 1 == 1).collect())
+}
+
+fn fixed(buffer: &[u8], length: usize, width: usize) -> Result<&[u8], DbError> {
+    length
+        .checked_mul(width)
+        .and_then(|size| buffer.get(..size))
+        .ok_or_else(|| corrupted("tampon de valeurs trop court"))
+}
+
+/// Les `length + 1` positions d'une colonne de texte ou de listes, croissantes.
+fn offsets(buffer: &[u8], length: usize, large: bool) -> Result<Vec<usize>, DbError> {
+    let width = if large { 8 } else { 4 };
+    let offsets: Vec<i64> = fixed(buffer, length + 1, width)?
+        .chunks_exact(width)
+        .map(|value| match large {
+            true => i64::from_le_bytes(value.try_into().unwrap()),
+            false => i32::from_le_bytes(value.try_into().unwrap()) as i64,
+        })
+        .collect();
+    if offsets.first().is_some_and(|&first| first < 0) || offsets.windows(2).any(|window| window[0] > window[1]) {
+        return Err(corrupted("positions décroissantes"));
+    }
+    Ok(offsets.into_iter().map(|offset| offset as usize).collect())
+}
+
+/// # Structure: `ArrowWriter`
+///
+/// Écrit un fichier IPC, un lot de lignes à la fois.
+pub struct ArrowWriter<W: Write> {
+    writer: W,
+    /// Nombre d'octets écrits.
+    position: u64,
+    fields: Vec<Field>,
+    /// Les structures `Block` du pied de page, une par lot écrit.
+    blocks: Vec<u8>,
+}
+
+impl<W: Write> ArrowWriter<W> {
+    /// Écrit le début du fichier et le schéma.
+    pub fn new(writer: W, fields: &[Field]) -> Result<Self, DbError> {
+        let mut arrow_writer = ArrowWriter {
+            writer,
+            position: 0,
+            fields: fields.to_vec(),
+            blocks: Vec::new(),
+        };
+        arrow_writer.write_bytes(b"ARROW1\0\0")?;
+        let message = message(SCHEMA, schema(fields), 0);
+        arrow_writer.write_message(&message, &[])?;
+        Ok(arrow_writer)
+    }
+
+    /// Écrit un lot de lignes, dont les colonnes suivent le schéma.
+    pub fn write_batch(&mut self, batch: &RecordBatch) -> Result<(), DbError> {
+        let mut body = EncodedBody::default();
+        for (field, array) in self.fields.iter().zip(&batch.columns) {
+            body.encode(field, array)?;
+        }
+        let record_batch = Builder::new()
+            .add(0, Value::I64(batch.rows as i64))
+            .add(1, Value::Structs { bytes: body.nodes, size: NODE_SIZE, align: 8 })
+            .add(2, Value::Structs { bytes: body.buffers, size: BUFFER_SIZE, align: 8 });
+        let message = message(RECORD_BATCH, record_batch, body.body.len());
+
+        let offset = self.position;
+        let metadata_length = self.write_message(&message, &body.body)?;
+        self.blocks.extend((offset as i64).to_le_bytes());
+        self.blocks.extend((metadata_length as i32).to_le_bytes());
+        self.blocks.extend([0; 4]);
+        self.blocks.extend((body.body.len() as i64).to_le_bytes());
+        Ok(())
+    }
+
+    /// Écrit la fin du flux, puis le pied de page.
+    pub fn finish(mut self) -> Result<(), DbError> {
+        self.write_bytes(&CONTINUATION.to_le_bytes())?;
+        self.write_bytes(&0u32.to_le_bytes())?;
+        let footer = Builder::new()
+            .add(0, Value::I16(VERSION))
+            .add(1, Value::Table(schema(&self.fields)))
+            .add(2, Value::Structs { bytes: Vec::new(), size: BLOCK_SIZE, align: 8 })
+            .add(3, Value::Structs { bytes: std::mem::take(&mut self.blocks), size: BLOCK_SIZE, align: 8 })
+            .finish();
+        self.write_bytes(&footer)?;
+        self.write_bytes(&(footer.len() as i32).to_le_bytes())?;
+        self.write_bytes(MAGIC)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Écrit un message et retourne la taille de ses métadonnées, préfixe compris.
+    fn write_message(&mut self, metadata: &Builder, body: &[u8]) -> Result<usize, DbError> {
+        let metadata = metadata.finish();
+        self.write_bytes(&CONTINUATION.to_le_bytes())?;
+        self.write_bytes(&(metadata.len() as i32).to_le_bytes())?;
+        self.write_bytes(&metadata)?;
+        self.write_bytes(body)?;
+        Ok(8 + metadata.len())
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), DbError> {
+        self.writer.write_all(bytes)?;
+        self.position += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+fn message(header_type: u8, header: Builder, body_length: usize) -> Builder {
+    Builder::new()
+        .add(0, Value::I16(VERSION))
+        .add(1, Value::U8(header_type))
+        .add(2, Value::Table(header))
+        .add(3, Value::I64(body_length as i64))
+}
+
+fn schema(fields: &[Field]) -> Builder {
+    Builder::new()
+        .add(0, Value::I16(0))
+        .add(1, Value::Tables(fields.iter().map(field).collect()))
+}
+
+fn field(field: &Field) -> Builder {
+    let (type_id, type_table, children) = match &field.data_type {
+        DataType::Null => (NULL, Builder::new(), Vec::new()),
+        DataType::Bool => (BOOL, Builder::new(), Vec::new()),
+        DataType::Int => (INT, Builder::new().add(0, Value::I32(64)).add(1, Value::Bool(true)), Vec::new()),
+        DataType::Float32 => (FLOATING_POINT, Builder::new().add(0, Value::I16(1)), Vec::new()),
+        DataType::Float64 => (FLOATING_POINT, Builder::new().add(0, Value::I16(2)), Vec::new()),
+        DataType::Utf8 | DataType::Json => (UTF8, Builder::new(), Vec::new()),
+        DataType::Binary => (BINARY, Builder::new(), Vec::new()),
+        DataType::List(item) => (LIST, Builder::new(), vec![self::field(item)]),
+        DataType::FixedSizeList(item, size) => (FIXED_SIZE_LIST, Builder::new().add(0, Value::I32(*size as i32)), vec![self::field(item)]),
+    };
+    let builder = Builder::new()
+        .add(0, Value::String(field.name.clone()))
+        .add(1, Value::Bool(field.nullable))
+        .add(2, Value::U8(type_id))
+        .add(3, Value::Table(type_table))
+        .add(5, Value::Tables(children));
+    match field.data_type {
+        DataType::Json => builder.add(
+            6,
+            Value::Tables(vec![Builder::new()
+                .add(0, Value::String(EXTENSION_NAME.to_string()))
+                .add(1, Value::String(JSON_EXTENSION.to_string()))]),
+        ),
+        _ => builder,
+    }
+}
+
+/// Le corps d'un lot en cours d'encodage, avec les structures `FieldNode` et `Buffer` qui le décrivent.
+#[derive(Default)]
+struct EncodedBody {
+    body: Vec<u8>,
+    nodes: Vec<u8>,
+    buffers: Vec<u8>,
+}
+
+impl EncodedBody {
+    fn encode(&mut self, field: &Field, array: &Array) -> Result<(), DbError> {
+        let length = array.len();
+        let nulls = array.validity.as_ref().map_or(0, |validity| validity.iter().filter(|&&valid| !valid).count());
+        self.nodes.extend((length as i64).to_le_bytes());
+        self.nodes.extend((nulls as i64).to_le_bytes());
+        if field.data_type == DataType::Null {
+            return Ok(());
+        }
+        match &array.validity {
+            Some(validity) if nulls > 0 => self.buffer(&pack_bits(validity)),
+            _ => self.buffer(&[]),
+        }
+        match (&field.data_type, &array.values) {
+            (DataType::Bool, Values::Bool(values)) => self.buffer(&pack_bits(values)),
+            (DataType::Int, Values::Int(values)) => self.buffer(&values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<_>>()),
+            (DataType::Float32, Values::Float(values)) => {
+                self.buffer(&values.iter().flat_map(|&value| (value as f32).to_le_bytes()).collect::<Vec<_>>())
+            }
+            (DataType::Float64, Values::Float(values)) => self.buffer(&values.iter().flat_map(|value| value.to_le_bytes()).collect::<Vec<_>>()),
+            (DataType::Utf8 | DataType::Json, Values::Utf8(values)) => self.variable(values.iter().map(String::as_bytes))?,
+            (DataType::Binary, Values::Binary(values)) => self.variable(values.iter().map(Vec::as_slice))?,
+            (DataType::List(item), Values::List { offsets, values }) => {
+                self.buffer(&offsets.iter().flat_map(|&offset| (offset as i32).to_le_bytes()).collect::<Vec<_>>());
+                self.encode(item, values)?;
+            }
+            (DataType::FixedSizeList(item, _), Values::List { values, .. }) => self.encode(item, values)?,
+            _ => return Err(DbError::InvalidSchema(format!("colonne '{}' : valeurs d'un autre type que {:?}", field.name, field.data_type))),
+        }
+        Ok(())
+    }
+
+    /// Les positions puis les octets d'une colonne de texte ou d'octets.
+    fn variable<'a>(&mut self, values: impl Iterator<Item = &'a [u8]>) -> Result<(), DbError> {
+        let mut offsets = vec![0i32];
+        let mut data = Vec::new();
+        for value in values {
+            data.extend_from_slice(value);
+            offsets.push(i32::try_from(data.len()).map_err(|_| DbError::Unsupported("plus de 2 Gio de texte dans un lot".to_string()))?);
+        }
+        self.buffer(&offsets.iter().flat_map(|offset| offset.to_le_bytes()).collect::<Vec<_>>());
+        self.buffer(&data);
+        Ok(())
+    }
+
+    fn buffer(&mut self, bytes: &[u8]) {
+        self.buffers.extend((self.body.len() as i64).to_le_bytes());
+        self.buffers.extend((bytes.len() as i64).to_le_bytes());
+        self.body.extend_from_slice(bytes);
+        self.body.resize(self.body.len().next_multiple_of(ALIGNMENT), 0);
+    }
+}
+
+fn pack_bits(values: &[bool]) -> Vec<u8> {
+    let mut bytes = vec![0u8; values.len().div_ceil(8)];
+    for (index, _) in values.iter().enumerate().filter(|(_, &value)| value) {
+        bytes[index / 8] |= 1 << (index % 8);
+    }
+    bytes
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("fichier Arrow : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn fields() -> Vec<Field> {
+        vec![
+            Field::new("null", DataType::Null, true),
+            Field::new("bool", DataType::Bool, true),
+            Field::new("int", DataType::Int, false),
+            Field::new("f32", DataType::Float32, false),
+            Field::new("f64", DataType::Float64, true),
+            Field::new("text", DataType::Utf8, true),
+            Field::new("json", DataType::Json, true),
+            Field::new("bytes", DataType::Binary, false),
+            Field::new("list", DataType::List(Box::new(Field::new("item", DataType::Utf8, true))), true),
+            Field::new("vector", DataType::FixedSizeList(Box::new(Field::new("item", DataType::Float32, false)), 2), false),
+        ]
+    }
+
+    fn batch(rows: usize) -> RecordBatch {
+        let validity = (rows > 0).then(|| (0..rows).map(|row| row % 3 != 1).collect::<Vec<_>>());
+        RecordBatch {
+            rows,
+            columns: vec![
+                Array::new(Values::Null(rows)),
+                Array { validity: validity.clone(), values: Values::Bool((0..rows).map(|row| row % 2 == 0).collect()) },
+                Array::new(Values::Int((0..rows as i64).map(|row| row * -1_000_000_007).collect())),
+                Array::new(Values::Float((0..rows).map(|row| row as f64 / 4.0).collect())),
+                Array { validity: validity.clone(), values: Values::Float((0..rows).map(|row| row as f64 * 1e300).collect()) },
+                Array { validity: validity.clone(), values: Values::Utf8((0..rows).map(|row| "é".repeat(row)).collect()) },
+                Array::new(Values::Utf8((0..rows).map(|row| format!(r#"{{"p":{}}}"#, row)).collect())),
+                Array::new(Values::Binary((0..rows).map(|row| vec![row as u8; row]).collect())),
+                Array {
+                    validity,
+                    values: Values::List {
+                        offsets: (0..=rows).map(|row| row * (row + 1) / 2).collect(),
+                        values: Box::new(Array::new(Values::Utf8((0..rows * (rows + 1) / 2).map(|item| item.to_string()).collect()))),
+                    },
+                },
+                Array::new(Values::List {
+                    offsets: (0..=rows).map(|row| 2 * row).collect(),
+                    values: Box::new(Array::new(Values::Float((0..2 * rows).map(|item| item as f64).collect()))),
+                }),
+            ],
+        }
+    }
+
+    fn file(batches: &[RecordBatch]) -> Vec<u8> {
+        let mut file = Vec::new();
+        let mut writer = ArrowWriter::new(&mut file, &fields()).unwrap();
+        for batch in batches {
+            writer.write_batch(batch).unwrap();
+        }
+        writer.finish().unwrap();
+        file
+    }
+
+    fn read(bytes: &[u8]) -> Result<Vec<RecordBatch>, DbError> {
+        let mut reader = ArrowReader::open(Cursor::new(bytes))?;
+        let mut batches = Vec::new();
+        while let Some(batch) = reader.next_batch()? {
+            batches.push(batch);
+        }
+        Ok(batches)
+    }
+
+    #[test]
+    fn reads_files_and_streams_it_writes() {
+        let batches = vec![batch(5), batch(0), batch(9)];
+        let bytes = file(&batches);
+        let reader = ArrowReader::open(Cursor::new(&bytes)).unwrap();
+        assert_eq!(reader.fields(), fields());
+        assert_eq!(read(&bytes).unwrap(), batches);
+
+        // Le flux est le fichier sans sa chaîne magique ni son pied de page.
+        let footer = i32::from_le_bytes(bytes[bytes.len() - 10..bytes.len() - 6].try_into().unwrap()) as usize;
+        let stream = &bytes[8..bytes.len() - 10 - footer];
+        assert_eq!(read(stream).unwrap(), batches);
+    }
+
+    #[test]
+    fn reads_the_reference_file() {
+        let bytes = include_bytes!("../testdata/reference.arrow");
+        let reader = ArrowReader::open(Cursor::new(bytes)).unwrap();
+        let item = Field::new("item", DataType::Float32, true);
+        assert_eq!(
+            reader.fields(),
+            [
+                Field::new("id", DataType::Utf8, true),
+                Field::new("vector", DataType::FixedSizeList(Box::new(item), 3), true),
+                Field::new("rank", DataType::Int, true),
+                Field::new("tag", DataType::Utf8, true),
+                Field::new("score", DataType::Float64, true),
+            ]
+        );
+        let batches = read(bytes).unwrap();
+        assert_eq!(batches.iter().map(|batch| batch.rows).collect::<Vec<_>>(), [3, 2]);
+        assert_eq!(batches[0].columns[3].validity, Some(vec![true, false, true]));
+    }
+
+    #[test]
+    fn truncated_or_corrupted_files_never_panic() {
+        let bytes = file(&[batch(4)]);
+        for length in 0..bytes.len() {
+            assert!(read(&bytes[..length]).is_err(), "{} octets", length);
+        }
+        for position in 0..bytes.len() {
+            for byte in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[position] = byte;
+                let _ = read(&corrupted);
+            }
+        }
+    }
+
+    #[test]
+    fn columns_must_have_the_length_of_the_batch() {
+        let mut short = batch(4);
+        short.rows = 6;
+        assert!(matches!(read(&file(&[short])), Err(DbError::Corrupted(_))));
+    }
+
+    #[test]
+    fn sizes_that_overflow_are_corrupted() {
+        assert!(fixed(&[0; 16], usize::MAX, 8).is_err());
+        assert!(fixed(&[0; 16], usize::MAX / 2 + 1, 2).is_err());
+        assert!(offsets(&[0; 16], i64::MAX as usize, false).is_err());
+        assert!(bits(&[0; 2], 17).is_err());
+    }
+
+    #[test]
+    fn schemas_nested_too_deeply_are_corrupted() {
+        let nested = |depth| {
+            let mut data_type = DataType::Float32;
+            for _ in 0..depth {
+                data_type = DataType::List(Box::new(Field::new("item", data_type, true)));
+            }
+            let bytes = schema(&[Field::new("vector", data_type, true)]).finish();
+            read_schema(Table::root(&bytes).unwrap()).map(|fields| fields[0].field())
+        };
+        assert!(nested(MAX_DEPTH).is_ok());
+        assert!(matches!(nested(MAX_DEPTH + 1), Err(DbError::Corrupted(_))));
+    }
+}
@@ -0,0 +1,199 @@
+//! # Module: `bq`
+//!
+//! Quantification binaire des vecteurs d'une [`Collection`](crate::Collection) : chaque coordonnée
+//! n'est plus représentée que par son signe, sur un bit. Un vecteur de dimension 1024 tient alors
+//! sur 128 octets, trente-deux fois moins que ses `f32`.
+//!
+//! Une recherche se fait en deux passes. La première compare le code de la requête à celui de chaque
+//! document par distance de Hamming (nombre de signes différents, compté avec `count_ones` sur des mots
+//! de 64 bits) et retient `k × oversampling` candidats. La seconde reclasse ces candidats avec la
+//! [`Metric`] de la collection sur leurs vecteurs d'origine : les scores retournés sont exacts,
+//! seul le choix des candidats est approché.
+//!
+//! Le signe d'une coordonnée ne conserve l'essentiel de l'information que pour des vecteurs de grande
+//! dimension, centrés sur l'origine, comparés par similarité cosinus ; un facteur de suréchantillonnage
+//! plus grand compense une approximation moins fidèle.
+
+use crate::metric::Metric;
+use crate::quantizer::{self, Codes};
+use crate::vectors::VectorStore;
+use crate::{DocumentId, Vector};
+
+/// # Structure: `BqParams`
+///
+/// Paramètres d'un [`BinaryQuantizer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BqParams {
+    /// Facteur de suréchantillonnage : les `k × oversampling` documents les plus proches de la requête
+    /// par distance de Hamming sont reclassés avec leurs vecteurs d'origine (au moins 1).
+    pub oversampling: f32,
+}
+
+impl Default for BqParams {
+    fn default() -> Self {
+        BqParams { oversampling: 4.0 }
+    }
+}
+
+/// # Structure: `BinaryQuantizer`
+///
+/// Le code binaire de chaque document d'une collection.
+pub struct BinaryQuantizer {
+    params: BqParams,
+    metric: Metric,
+    dimension: usize,
+    /// Le code de chaque document : un bit par coordonnée, à 1 si elle est strictement positive.
+    codes: Codes,
+}
+
+impl BinaryQuantizer {
+    /// Encode chaque document de `vectors`. Il n'y a rien à apprendre : seule la dimension,
+    /// celle du premier vecteur, fixe la taille des codes. Sur une collection vide, c'est le premier
+    /// document encodé ensuite qui la fixe.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let bq = BinaryQuantizer::build(BqParams { oversampling: 8.0 }, Metric::Cosine, &vectors);
+    /// ```
+    pub fn build(params: BqParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let dimension = vectors.values().next().map_or(0, <[f32]>::len);
+        let mut quantizer = BinaryQuantizer {
+            params,
+            metric,
+            dimension,
+            codes: Codes::new(dimension.div_ceil(8)),
+        };
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        for key in keys {
+            quantizer.insert(key, vectors);
+        }
+        quantizer
+    }
+
+    /// Les paramètres du quantificateur.
+    pub fn params(&self) -> BqParams {
+        self.params
+    }
+
+    /// Indique si la dimension des codes est connue, c'est-à-dire si au moins un document a été encodé.
+    pub fn is_trained(&self) -> bool {
+        self.dimension > 0
+    }
+
+    /// Taille du code d'un document, en octets.
+    pub fn code_bytes(&self) -> usize {
+        self.codes.size()
+    }
+
+    /// Mémoire occupée par le quantificateur, en octets : les codes, l'identifiant et la position
+    /// de chaque document.
+    pub fn memory_bytes(&self) -> usize {
+        self.codes.memory_bytes()
+    }
+
+    /// Encode le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    /// Le code d'un document déjà encodé est remplacé.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        let vector = match vectors.get(&key) {
+            Some(vector) => vector,
+            None => return,
+        };
+        if !self.is_trained() {
+            self.dimension = vector.len();
+            self.codes = Codes::new(self.dimension.div_ceil(8));
+        }
+        self.codes.insert(key, &encode(vector));
+    }
+
+    /// Retire le code d'un document.
+    pub fn remove(&mut self, key: &DocumentId) {
+        self.codes.remove(key);
+    }
+
+    /// Retient les `k × oversampling` documents dont le code est le plus proche de celui de la requête,
+    /// puis les reclasse avec leurs vecteurs d'origine et retourne les `k` meilleurs.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `vectors`: Les vecteurs de la collection, utilisés pour le reclassement.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score exact, du plus proche au plus éloigné selon la métrique.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &VectorStore,
+        accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>,
+    ) -> Vector {
+        let candidates = ((k as f32 * self.params.oversampling.max(1.0)).ceil() as usize).max(k);
+        let query_code = encode(query);
+        let hits = self
+            .codes
+            .top_k(Metric::Hamming, candidates, accept, |code| hamming(&query_code, code) as f32);
+        quantizer::rescore(self.metric, query, k, hits, vectors)
+    }
+}
+
+/// Le code binaire d'un vecteur : le bit `i % 8` de l'octet `i / 8` vaut 1 si la coordonnée `i` est positive.
+fn encode(vector: &[f32]) -> Vec<u8> {
+    let mut code = vec![0u8; vector.len().div_ceil(8)];
+    for (i, value) in vector.iter().enumerate() {
+        if *value > 0.0 {
+            code[i / 8] |= 1 << (i % 8);
+        }
+    }
+    code
+}
+
+/// Le nombre de bits qui diffèrent entre deux codes de même taille, compté par mots de 64 bits.
+fn hamming(code1: &[u8], code2: &[u8]) -> u32 {
+    let mut words1 = code1.chunks_exact(8);
+    let mut words2 = code2.chunks_exact(8);
+    let mut distance = 0;
+    for (word1, word2) in words1.by_ref().zip(words2.by_ref()) {
+        let word1 = u64::from_le_bytes(word1.try_into().unwrap());
+        let word2 = u64::from_le_bytes(word2.try_into().unwrap());
+        distance += (word1 ^ word2).count_ones();
+    }
+    for (byte1, byte2) in words1.remainder().iter().zip(words2.remainder()) {
+        distance += (byte1 ^ byte2).count_ones();
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn hamming_counts_differing_signs() {
+        let vector1: Vec<f32> = (0..75).map(|i| if i % 3 == 0 { 1.0 } else { -1.0 }).collect();
+        let mut vector2 = vector1.clone();
+        for i in [0, 7, 8, 63, 64, 74] {
+            vector2[i] = -vector2[i];
+        }
+        assert_eq!(encode(&vector1).len(), 10);
+        assert_eq!(hamming(&encode(&vector1), &encode(&vector1)), 0);
+        assert_eq!(hamming(&encode(&vector1), &encode(&vector2)), 6);
+    }
+
+    #[test]
+    fn empty_quantizer_takes_the_dimension_of_the_first_document() {
+        let mut vectors = VectorStore::default();
+        let mut quantizer = BinaryQuantizer::build(BqParams::default(), Metric::Cosine, &vectors);
+        assert!(!quantizer.is_trained());
+
+        let key = Uuid::from_u128(1);
+        vectors.insert(key, vec![1.0; 12]).unwrap();
+        quantizer.insert(key, &vectors);
+        assert!(quantizer.is_trained());
+        assert_eq!(quantizer.code_bytes(), 2);
+        assert_eq!(quantizer.search(&[1.0; 12], 1, &vectors, None)[0].0, key);
+    }
+}
@@ -0,0 +1,1504 @@
+//! # Module: `cli`
+//!
+//! L'interface en ligne de commande : chaque commande peut être passée en argument du programme
+//! (`projet --data base search docs 1,1,1 -k 3`) ou saisie dans une session interactive, lancée
+//! quand aucune commande n'est donnée. La sortie est colorée pour le terminal, ou en JSON
+//! (un document par commande) avec l'option `--json`.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use colored::*;
+use uuid::Uuid;
+
+use crate::bq::BqParams;
+use crate::columnar::{TableFormat, TableOptions};
+use crate::error::DbError;
+use crate::filter::Filter;
+use crate::json::{self, object};
+use crate::hnsw::HnswParams;
+use crate::index::{IndexConfig, VectorIndex};
+use crate::ivf::IvfParams;
+use crate::lsh::LshParams;
+use crate::metric::Metric;
+use crate::npy;
+use crate::payload::{Payload, PayloadDisplay, Value};
+use crate::pq::PqParams;
+use crate::quantizer::QuantizerConfig;
+use crate::server;
+use crate::simd;
+use crate::sq::{Calibration, ScalarBits, SqParams};
+use crate::texmex::{self, GroundTruth, QuerySet, VecsFormat};
+use crate::transfer::{ErrorPolicy, Format, ImportOptions};
+use crate::{Collection, CollectionConfig, Database, DocumentId, ItemStatus, SearchOptions, SearchResult};
+
+/// Le résultat d'une commande : un message ou une erreur à afficher.
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+/// Nombre de résultats renvoyés par `search` sans l'option `-k`.
+const DEFAULT_K: usize = 10;
+
+/// Nombre de documents utilisés comme requêtes pour mesurer le rappel d'une collection quantifiée.
+const REPORT_QUERIES: usize = 100;
+
+const USAGE: &str = "\
+Utilisation : projet [--data RÉPERTOIRE] [--json] [COMMANDE]
+
+Sans COMMANDE, une session interactive est ouverte. Sans --data, la base est en mémoire.
+
+Commandes :
+  create <collection> [--metric cosine|dot|euclidean|manhattan|hamming] [--dimension N] [--normalize] [--mmap]
+  insert <collection> <vecteur> [--id UUID] [--payload JSON]
+  get <collection> <id>
+  delete <collection> [id...]          supprime des documents en une seule opération, ou la collection
+                                       sans id
+  search <collection> <vecteur> [-k N] [--threshold SEUIL] [--filter FILTRE] [--with-payload] [--exact]
+                                       avec --threshold, tous les documents qui atteignent le seuil
+                                       (au plus N si -k est donné)
+  search-batch <collection> <fichier> [-k N] [--filter FILTRE] [--with-payload] [--exact]
+                                       une requête (un vecteur) par ligne du fichier
+  list
+  stats [collection]
+  index <collection> hnsw|ivf|lsh|none [options]
+                                       construit, remplace ou supprime l'index de recherche approximative,
+                                       reconstruit à chaque ouverture de la base ; options :
+                                       hnsw : [--m M] [--ef-construction N] [--ef-search N]
+                                       ivf : [--nlist N] [--nprobe N] [--sample N]
+                                       lsh : [--bits N] [--tables N] [--probes N]
+  index <collection> tune [--ef-search N] [--nprobe N]
+                                       règle les recherches sans reconstruire l'index ; --nprobe est
+                                       le nombre de listes IVF ou de seaux LSH visités par table
+  index <collection> rebuild           reconstruit l'index avec ses paramètres (réapprend les
+                                       centroïdes IVF après une dérive des données)
+  payload-index <collection> <champ>   indexe un champ des métadonnées (champ.imbriqué) pour accélérer
+                                       les filtres sélectifs (==, IN)
+  quantize <collection> pq|int8|int4|binary|none [-k N] [options]
+                                       quantifie les vecteurs, puis mesure la mémoire utilisée
+                                       et le rappel des N plus proches voisins ; options :
+                                       pq : [--subspaces M] [--centroids K] [--rerank N] [--sample N]
+                                       int8, int4 : [--global] [--oversampling F]
+                                       binary : [--oversampling F] (4 par défaut)
+  snapshot                             écrit un instantané de la base et vide le journal (avec --data)
+  import <collection> <fichier> [--format csv|jsonl|parquet|arrow] [--on-error abort|skip] [--batch N]
+         [--id-column NOM] [--vector-column NOM]
+                                       importe des documents au fil de l'eau, N par lot (1000 par défaut) ;
+                                       avec skip, les lignes invalides sont ignorées et signalées ;
+                                       les colonnes id et vector par défaut (Parquet et Arrow)
+  export <collection> <fichier> [--format csv|jsonl|parquet|arrow] [--batch N] [--id-column NOM] [--vector-column NOM]
+                                       avec Parquet et Arrow, N lignes par lot (1000 par défaut)
+  load <collection> <base.fvecs|.bvecs|.ivecs> [--limit N]
+                                       charge au plus N vecteurs d'un jeu de données TEXMEX ;
+                                       le vecteur à la position i devient le document d'identifiant i
+  bench <collection> <requêtes.fvecs> <vérité.ivecs> [-k N] [--limit N]
+                                       mesure le rappel@N et la vitesse de search sur au plus
+                                       --limit requêtes, d'après les voisins exacts du fichier .ivecs
+  import-npy <collection> <vecteurs.npy> [--ids FICHIER]
+                                       charge une matrice NumPy float32 ou float16 ; les identifiants
+                                       sont lus dans FICHIER, un par ligne, ou dans un tableau .npy ;
+                                       sans --ids, la ligne i devient le document d'identifiant i
+  import-npy <collection> <archive.npz> [--ids NOM] [--vectors NOM]
+                                       charge les tableaux NOM.npy de l'archive (ids et vectors par défaut)
+  export-npy <collection> <vecteurs.npy> [--ids FICHIER]
+                                       exporte les vecteurs en float32 et leurs identifiants dans
+                                       FICHIER (<vecteurs>.ids par défaut), un par ligne
+  serve [--addr ADRESSE] [--workers N]  (hors session interactive)
+  help
+  quit
+
+Un vecteur s'écrit 1,2,3 ou [1,2,3]. Sans --format, un fichier .csv est lu en CSV, un fichier .parquet en Parquet,
+un fichier .arrow ou .feather en Arrow IPC, tout autre en JSON Lines.
+Les fichiers JSON Lines contiennent un document par ligne :
+{\"id\": \"...\", \"vector\": [1, 2, 3], \"payload\": {\"client\": \"Dupont\"}}
+Les fichiers CSV ont une ligne d'en-tête : id (facultatif), le vecteur dans une colonne vector
+ou dans les colonnes v0, v1, etc., puis une colonne par champ des métadonnées. Les fichiers Parquet
+et Arrow ont une colonne de vecteurs (des listes de nombres), une colonne d'identifiants facultative
+et une colonne par champ des métadonnées.";
+
+/// Point d'entrée de l'interface : analyse les options globales, ouvre la base puis exécute
+/// la commande donnée, ou la session interactive.
+///
+/// # Paramètres
+/// - `args`: Les arguments du programme, sans le nom de l'exécutable.
+///
+/// # Retour
+/// - `i32`: Le code de sortie du processus (`0` en cas de succès).
+///
+/// # Exemple
+///
+/// ```
+/// std::process::exit(cli::run(std::env::args().skip(1).collect()));
+/// ```
+pub fn run(args: Vec<String>) -> i32 {
+    let mut data = None;
+    let mut json_output = false;
+    let mut rest = args.as_slice();
+    loop {
+        match rest {
+            [flag, tail @ ..] if flag == "--json" => {
+                json_output = true;
+                rest = tail;
+            }
+            [flag, path, tail @ ..] if flag == "--data" => {
+                data = Some(path.clone());
+                rest = tail;
+            }
+            // L'aide s'affiche seule : la base n'est pas ouverte et aucune commande n'est exécutée.
+            [flag, ..] if flag == "--help" || flag == "-h" => {
+                println!("{}", USAGE);
+                return 0;
+            }
+            _ => break,
+        }
+    }
+
+    let db = match &data {
+        Some(path) => Database::open(path),
+        None => Ok(Database::new()),
+    };
+    let mut session = match db {
+        Ok(db) => Session { db, json_output },
+        Err(error) => {
+            report_error(json_output, &error.to_string());
+            return 1;
+        }
+    };
+
+    match rest.first().map(String::as_str) {
+        None => {
+            session.repl();
+            0
+        }
+        Some("serve") => match serve(session.db, &rest[1..]) {
+            Ok(()) => 0,
+            Err(error) => {
+                report_error(json_output, &error.to_string());
+                1
+            }
+        },
+        Some(_) => match session.execute(rest) {
+            Ok(output) => {
+                session.print(&output);
+                0
+            }
+            Err(error) => {
+                report_error(json_output, &error.to_string());
+                1
+            }
+        },
+    }
+}
+
+/// Affiche une erreur : en rouge sur la sortie d'erreur, ou en `{"error": ...}` sur la sortie standard.
+fn report_error(json_output: bool, message: &str) {
+    if json_output {
+        println!("{}", object([("error", Value::from(message))]));
+    } else {
+        eprintln!("{}", format!("Erreur : {}", message).red().bold());
+    }
+}
+
+/// Lance le serveur HTTP sur la base ouverte : `serve [--addr ADRESSE] [--workers N]`.
+fn serve(db: Database, args: &[String]) -> CliResult<()> {
+    let args = Args::parse(args, &["--addr", "--workers"], &[])?;
+    args.expect_positional(0)?;
+    let addr = args.option("--addr").unwrap_or("127.0.0.1:8080");
+    let workers = match args.option("--workers") {
+        Some(workers) => workers.parse().map_err(|_| "--workers attend un entier")?,
+        None => thread::available_parallelism().map_or(4, |n| n.get()),
+    };
+
+    let listener = TcpListener::bind(addr)?;
+    println!(
+        "{} {}",
+        "Serveur à l'écoute sur".bold().bright_green(),
+        format!("http://{}", listener.local_addr()?).bright_white()
+    );
+    server::serve(listener, db, workers);
+    Ok(())
+}
+
+/// # Structure: `Session`
+///
+/// La base de données ouverte et le mode d'affichage choisi, partagés par les commandes successives.
+struct Session {
+    db: Database,
+    json_output: bool,
+}
+
+/// # Énumération: `Output`
+///
+/// Ce que produit une commande réussie, affiché en couleurs ou en JSON par [`Session::print`].
+enum Output {
+    /// Une modification réussie : un message pour le terminal et sa description JSON.
+    Done { message: String, json: Value },
+    /// La liste des collections (`list`).
+    Collections(Vec<CollectionStats>),
+    /// Les statistiques détaillées des collections (`stats`).
+    Stats(Vec<CollectionStats>),
+    /// Un document (`get`).
+    Document { id: DocumentId, vector: Vec<f32>, payload: Payload },
+    /// Les résultats d'une recherche (`search`).
+    Hits(SearchResult),
+    /// Les résultats de chaque requête d'une recherche groupée (`search-batch`).
+    Batch(Vec<SearchResult>),
+    /// Le texte d'aide (`help`).
+    Help,
+}
+
+impl Output {
+    /// La description JSON du résultat, affichée avec l'option `--json`.
+    fn json(&self) -> Value {
+        match self {
+            Output::Done { json, .. } => json.clone(),
+            Output::Collections(stats) => object([(
+                "collections",
+                Value::Array(stats.iter().map(|stats| stats.json(false)).collect()),
+            )]),
+            Output::Stats(stats) => object([
+                ("collections", Value::Array(stats.iter().map(|stats| stats.json(true)).collect())),
+                ("simd", Value::from(simd::backend().name())),
+                ("documents", Value::Number(stats.iter().map(|stats| stats.documents).sum::<usize>() as f64)),
+            ]),
+            Output::Document { id, vector, payload } => object([
+                ("id", Value::from(id.to_string())),
+                ("vector", json::vector(vector)),
+                ("payload", Value::Object(payload.clone())),
+            ]),
+            Output::Hits(results) => json::search_result(results),
+            Output::Batch(results) => object([("results", Value::Array(results.iter().map(json::search_result).collect()))]),
+            Output::Help => Value::from(USAGE),
+        }
+    }
+}
+
+/// # Structure: `CollectionStats`
+///
+/// Les caractéristiques d'une collection affichées par `list` et `stats`.
+struct CollectionStats {
+    name: String,
+    metric: Metric,
+    dimension: Option<usize>,
+    normalize: bool,
+    /// Les vecteurs sont dans un fichier projeté en mémoire.
+    mapped: bool,
+    documents: usize,
+    /// Nombre de documents dont les métadonnées ne sont pas vides.
+    with_payload: usize,
+    /// Place occupée par les vecteurs, en octets.
+    vector_bytes: usize,
+    /// Nom du type d'index, s'il y en a un.
+    index: Option<&'static str>,
+    /// Pour un index IVF : le nombre de listes et leur facteur de déséquilibre.
+    ivf_lists: Option<(usize, f64)>,
+    /// Pour un index LSH : le nombre de tables et de bits par signature.
+    lsh_tables: Option<(usize, usize)>,
+    /// Si la collection est quantifiée : le type de quantification et la taille du code d'un document, en octets.
+    quantization: Option<(&'static str, usize)>,
+}
+
+impl CollectionStats {
+    fn new(name: &str, collection: &Collection) -> Self {
+        let vector_bytes = collection
+            .keys()
+            .filter_map(|key| collection.get(key))
+            .map(std::mem::size_of_val)
+            .sum();
+        let with_payload = collection
+            .keys()
+            .filter(|key| collection.get_payload(key).is_some_and(|payload| !payload.is_empty()))
+            .count();
+        CollectionStats {
+            name: name.to_string(),
+            metric: collection.metric,
+            dimension: collection.dimension,
+            normalize: collection.normalize,
+            mapped: collection.data.is_mapped(),
+            documents: collection.len(),
+            with_payload,
+            vector_bytes,
+            index: collection.index.as_ref().map(VectorIndex::name),
+            ivf_lists: match &collection.index {
+                Some(VectorIndex::Ivf(index)) => Some((index.nlist(), index.imbalance())),
+                _ => None,
+            },
+            lsh_tables: match &collection.index {
+                Some(VectorIndex::Lsh(index)) => Some((index.tables(), index.bits())),
+                _ => None,
+            },
+            quantization: collection
+                .quantizer
+                .as_ref()
+                .map(|quantizer| (quantizer.name(), quantizer.code_bytes())),
+        }
+    }
+
+    /// Décrit la collection en JSON ; `detailed` ajoute les champs propres à `stats`.
+    fn json(&self, detailed: bool) -> Value {
+        let mut json = Payload::from([
+            ("name".to_string(), Value::from(self.name.as_str())),
+            ("metric".to_string(), Value::from(self.metric.name())),
+            ("dimension".to_string(), self.dimension.map_or(Value::Null, |dimension| Value::Number(dimension as f64))),
+            ("documents".to_string(), Value::Number(self.documents as f64)),
+        ]);
+        if detailed {
+            json.insert("normalize".to_string(), Value::Bool(self.normalize));
+            json.insert("mapped".to_string(), Value::Bool(self.mapped));
+            json.insert("with_payload".to_string(), Value::Number(self.with_payload as f64));
+            json.insert("vector_bytes".to_string(), Value::Number(self.vector_bytes as f64));
+            json.insert("index".to_string(), self.index.map_or(Value::Null, Value::from));
+            if let Some((nlist, imbalance)) = self.ivf_lists {
+                json.insert("nlist".to_string(), Value::Number(nlist as f64));
+                json.insert("imbalance".to_string(), Value::Number(imbalance));
+            }
+            if let Some((tables, bits)) = self.lsh_tables {
+                json.insert("tables".to_string(), Value::Number(tables as f64));
+                json.insert("bits".to_string(), Value::Number(bits as f64));
+            }
+            json.insert("quantization".to_string(), self.quantization.map_or(Value::Null, |(name, _)| Value::from(name)));
+            if let Some((_, code_bytes)) = self.quantization {
+                json.insert("code_bytes".to_string(), Value::Number(code_bytes as f64));
+            }
+        }
+        Value::Object(json)
+    }
+}
+
+impl Session {
+    /// Lit les commandes sur l'entrée standard jusqu'à `quit` ou la fin de l'entrée.
+    fn repl(&mut self) {
+        if !self.json_output {
+            println!("{}", "\n=== Bienvenue dans le Moteur de Recherche Documentaire ===".bold().truecolor(135, 206, 250));
+            println!("{}\n", "Tapez 'help' pour la liste des commandes, 'quit' pour quitter.".bright_black());
+        }
+
+        let stdin = io::stdin();
+        let mut line = String::new();
+        loop {
+            if !self.json_output {
+                print!("{} ", "vdb>".bold().bright_cyan());
+                let _ = io::stdout().flush();
+            }
+            line.clear();
+            match stdin.lock().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+
+            let tokens = match split_line(&line) {
+                Ok(tokens) => tokens,
+                Err(error) => {
+                    report_error(self.json_output, &error);
+                    continue;
+                }
+            };
+            let result = match tokens.first().map(String::as_str) {
+                None => continue,
+                Some("quit" | "exit") => break,
+                Some("serve") => Err("la commande 'serve' n'est pas disponible en session interactive".into()),
+                Some(_) => self.execute(&tokens),
+            };
+            match result {
+                Ok(output) => self.print(&output),
+                Err(error) => report_error(self.json_output, &error.to_string()),
+            }
+        }
+    }
+
+    /// Exécute une commande (son nom suivi de ses arguments).
+    fn execute(&mut self, tokens: &[String]) -> CliResult<Output> {
+        let (command, args) = tokens.split_first().ok_or("commande manquante")?;
+        match command.as_str() {
+            "create" => self.create(&Args::parse(args, &["--metric", "--dimension"], &["--normalize", "--mmap"])?),
+            "insert" => self.insert(&Args::parse(args, &["--id", "--payload"], &[])?),
+            "get" => self.get(&Args::parse(args, &[], &[])?),
+            "delete" => self.delete(&Args::parse(args, &[], &[])?),
+            "search" => self.search(&Args::parse(args, &["-k", "--threshold", "--filter"], &["--with-payload", "--exact"])?),
+            "search-batch" => self.search_batch(&Args::parse(args, &["-k", "--filter"], &["--with-payload", "--exact"])?),
+            "list" => {
+                Args::parse(args, &[], &[])?.expect_positional(0)?;
+                Ok(Output::Collections(self.stats_of(None)?))
+            }
+            "stats" => {
+                let args = Args::parse(args, &[], &[])?;
+                if args.positional.len() > 1 {
+                    return Err("usage : stats [collection]".into());
+                }
+                Ok(Output::Stats(self.stats_of(args.positional.first().map(String::as_str))?))
+            }
+            "index" => self.index(&Args::parse(
+                args,
+                &["--m", "--ef-construction", "--ef-search", "--nlist", "--nprobe", "--sample", "--bits", "--tables", "--probes"],
+                &[],
+            )?),
+            "payload-index" => self.payload_index(&Args::parse(args, &[], &[])?),
+            "snapshot" => self.snapshot(&Args::parse(args, &[], &[])?),
+            "quantize" => self.quantize(&Args::parse(
+                args,
+                &["--subspaces", "--centroids", "--rerank", "--sample", "--oversampling", "-k"],
+                &["--global"],
+            )?),
+            "import" => self.import(&Args::parse(
+                args,
+                &["--format", "--on-error", "--batch", "--id-column", "--vector-column"],
+                &[],
+            )?),
+            "export" => self.export(&Args::parse(args, &["--format", "--batch", "--id-column", "--vector-column"], &[])?),
+            "load" => self.load(&Args::parse(args, &["--limit"], &[])?),
+            "import-npy" => self.import_npy(&Args::parse(args, &["--ids", "--vectors"], &[])?),
+            "export-npy" => self.export_npy(&Args::parse(args, &["--ids"], &[])?),
+            "bench" => self.bench(&Args::parse(args, &["-k", "--limit"], &[])?),
+            "help" => Ok(Output::Help),
+            _ => Err(format!("commande inconnue '{}' (voir 'help')", command).into()),
+        }
+    }
+
+    fn create(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(1)?;
+        let name = &args.positional[0];
+        let metric = match args.option("--metric") {
+            Some(metric) => metric.parse()?,
+            None => Metric::default(),
+        };
+        let dimension = match args.option("--dimension") {
+            Some(dimension) => match dimension.parse() {
+                Ok(dimension) if dimension > 0 => Some(dimension),
+                _ => return Err("--dimension attend un entier strictement positif".into()),
+            },
+            None => None,
+        };
+        let normalize = args.flag("--normalize");
+        let mapped = args.flag("--mmap");
+        self.db.add_collection(name.clone(), CollectionConfig { metric, dimension, normalize, mapped })?;
+        Ok(Output::Done {
+            message: format!("Collection '{}' créée ({}).", name, metric),
+            json: json::collection_info(name, self.db.get_collection(name)?),
+        })
+    }
+
+    fn insert(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let key = match args.option("--id") {
+            Some(id) => parse_id(id)?,
+            None => Uuid::new_v4(),
+        };
+        let vector = parse_vector(&args.positional[1])?;
+        let payload = match args.option("--payload") {
+            Some(text) => match json::parse(text)? {
+                Value::Object(payload) => payload,
+                _ => return Err("--payload attend un objet JSON".into()),
+            },
+            None => Payload::new(),
+        };
+        self.db.add_or_update(&args.positional[0], key, vector, payload)?;
+        Ok(Output::Done {
+            message: format!("Document {} enregistré.", key),
+            json: object([("id", Value::from(key.to_string()))]),
+        })
+    }
+
+    fn get(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let key = parse_id(&args.positional[1])?;
+        let collection = self.db.get_collection(&args.positional[0])?;
+        let vector = collection
+            .get(&key)
+            .ok_or_else(|| format!("le document '{}' n'existe pas", key))?;
+        Ok(Output::Document {
+            id: key,
+            vector: vector.to_vec(),
+            payload: collection.get_payload(&key).cloned().unwrap_or_default(),
+        })
+    }
+
+    fn delete(&mut self, args: &Args) -> CliResult<Output> {
+        match args.positional.as_slice() {
+            [name] => {
+                self.db.drop_collection(name)?;
+                Ok(Output::Done {
+                    message: format!("Collection '{}' supprimée.", name),
+                    json: object([("deleted", Value::from(name.as_str()))]),
+                })
+            }
+            [name, id] => {
+                let key = parse_id(id)?;
+                if self.db.get_collection(name)?.get(&key).is_none() {
+                    return Err(format!("le document '{}' n'existe pas", key).into());
+                }
+                self.db.remove(name, &key)?;
+                Ok(Output::Done {
+                    message: format!("Document {} supprimé.", key),
+                    json: object([("deleted", Value::from(key.to_string()))]),
+                })
+            }
+            [name, ids @ ..] if !ids.is_empty() => {
+                let keys = ids.iter().map(|id| parse_id(id)).collect::<CliResult<Vec<_>>>()?;
+                let statuses = self.db.remove_many(name, &keys)?;
+                let (mut deleted, mut not_found) = (Vec::new(), Vec::new());
+                for (key, status) in keys.iter().zip(statuses) {
+                    let list = if status == ItemStatus::Removed { &mut deleted } else { &mut not_found };
+                    list.push(Value::from(key.to_string()));
+                }
+                Ok(Output::Done {
+                    message: format!("{} document(s) supprimé(s), {} introuvable(s).", deleted.len(), not_found.len()),
+                    json: object([("deleted", Value::Array(deleted)), ("not_found", Value::Array(not_found))]),
+                })
+            }
+            _ => Err("usage : delete <collection> [id...]".into()),
+        }
+    }
+
+    fn search(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let query = parse_vector(&args.positional[1])?;
+        let k = match args.option("-k") {
+            Some(k) => Some(k.parse().map_err(|_| "-k attend un entier positif")?),
+            None => None,
+        };
+        let threshold = match args.option("--threshold") {
+            Some(threshold) => match threshold.parse::<f32>() {
+                Ok(threshold) if threshold.is_finite() => Some(threshold),
+                _ => return Err("--threshold attend un nombre".into()),
+            },
+            None => None,
+        };
+        let filter = args.option("--filter").map(Filter::parse).transpose()?;
+        let options = SearchOptions {
+            filter: filter.as_ref(),
+            exact: args.flag("--exact"),
+            with_payload: args.flag("--with-payload"),
+        };
+
+        let name = &args.positional[0];
+        Ok(Output::Hits(match threshold {
+            Some(threshold) => self.db.search_range(name, &query, threshold, k, options)?,
+            None => self.db.search_in_collection(name, &query, k.unwrap_or(DEFAULT_K), options)?,
+        }))
+    }
+
+    /// Recherche groupée : lit un vecteur par ligne du fichier donné, puis les recherche tous ensemble.
+    fn search_batch(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let k = match args.option("-k") {
+            Some(k) => k.parse().map_err(|_| "-k attend un entier positif")?,
+            None => DEFAULT_K,
+        };
+        let filter = args.option("--filter").map(Filter::parse).transpose()?;
+
+        let reader = BufReader::new(File::open(&args.positional[1])?);
+        let mut queries = Vec::new();
+        let mut line_numbers = Vec::new();
+        for (number, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            queries.push(parse_vector(&line).map_err(|error| format!("ligne {} : {}", number + 1, error))?);
+            line_numbers.push(number + 1);
+        }
+
+        let options = SearchOptions {
+            filter: filter.as_ref(),
+            exact: args.flag("--exact"),
+            with_payload: args.flag("--with-payload"),
+        };
+        let results = self.db.search_batch(&args.positional[0], &queries, k, options).map_err(|error| match error {
+            DbError::InvalidQuery { index, error } => format!("ligne {} : {}", line_numbers[index], error).into(),
+            error => Box::<dyn Error>::from(error),
+        })?;
+        Ok(Output::Batch(results))
+    }
+
+    fn stats_of(&self, name: Option<&str>) -> CliResult<Vec<CollectionStats>> {
+        let names = match name {
+            Some(name) => vec![name],
+            None => self.db.collection_names(),
+        };
+        names
+            .into_iter()
+            .map(|name| Ok(CollectionStats::new(name, self.db.get_collection(name)?)))
+            .collect()
+    }
+
+    /// Importe un fichier CSV, JSON Lines, Parquet ou Arrow (voir [`Database::import`] et [`Database::import_table`]).
+    /// Le format est donné par `--format` ou, à défaut, par l'extension du fichier ; avec `--on-error skip`,
+    /// les lignes invalides sont ignorées.
+    fn import(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, path) = (&args.positional[0], &args.positional[1]);
+        let on_error = match args.option("--on-error") {
+            None | Some("abort") => ErrorPolicy::Abort,
+            Some("skip") => ErrorPolicy::Skip,
+            Some(other) => return Err(format!("politique d'erreur inconnue '{}' (abort ou skip)", other).into()),
+        };
+        let batch_size = args.count("--batch")?.unwrap_or(ImportOptions::default().batch_size);
+        let format = file_format(args, path)?;
+        self.db.get_collection(name)?;
+
+        let report = match format {
+            FileFormat::Text(format) => {
+                let options = ImportOptions { format, on_error, batch_size };
+                self.db.import(name, BufReader::new(File::open(path)?), options)?
+            }
+            FileFormat::Table(format) => {
+                let options = table_options(args, format, on_error, batch_size);
+                self.db.import_table(name, BufReader::new(File::open(path)?), &options)?
+            }
+        };
+        let mut message = format!("{} document(s) importé(s) dans '{}'.", report.imported, name);
+        if report.skipped > 0 {
+            message += &format!(" {} ligne(s) ignorée(s) :", report.skipped);
+            for (line, error) in &report.errors {
+                message += &format!("\n  ligne {} : {}", line, error);
+            }
+            if report.skipped > report.errors.len() {
+                message += &format!("\n  ... et {} autre(s)", report.skipped - report.errors.len());
+            }
+        }
+        let errors = report
+            .errors
+            .iter()
+            .map(|(line, error)| object([("line", Value::Number(*line as f64)), ("error", Value::from(error.as_str()))]))
+            .collect();
+        Ok(Output::Done {
+            message,
+            json: object([
+                ("imported", Value::Number(report.imported as f64)),
+                ("format", Value::from(format.name())),
+                ("skipped", Value::Number(report.skipped as f64)),
+                ("errors", Value::Array(errors)),
+            ]),
+        })
+    }
+
+    /// Construit, règle ou supprime l'index de recherche approximative d'une collection
+    /// (voir [`Database::set_index`] et [`Database::tune_index`]).
+    fn index(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let name = &args.positional[0];
+        let config = match args.positional[1].as_str() {
+            "hnsw" => {
+                let defaults = HnswParams::default();
+                IndexConfig::Hnsw(HnswParams {
+                    m: args.count_at_least("--m", 2)?.unwrap_or(defaults.m),
+                    ef_construction: args.count_at_least("--ef-construction", 1)?.unwrap_or(defaults.ef_construction),
+                    ef_search: args.count_at_least("--ef-search", 1)?.unwrap_or(defaults.ef_search),
+                })
+            }
+            "ivf" => {
+                let defaults = IvfParams::default();
+                IndexConfig::Ivf(IvfParams {
+                    nlist: args.count_at_least("--nlist", 1)?.unwrap_or(defaults.nlist),
+                    nprobe: args.count_at_least("--nprobe", 1)?.unwrap_or(defaults.nprobe),
+                    sample_size: args.count_at_least("--sample", 1)?.or(defaults.sample_size),
+                    iterations: defaults.iterations,
+                })
+            }
+            "lsh" => {
+                let defaults = LshParams::default();
+                let bits = args.count("--bits")?.unwrap_or(defaults.bits);
+                if !(1..=64).contains(&bits) {
+                    return Err("--bits attend un entier entre 1 et 64".into());
+                }
+                IndexConfig::Lsh(LshParams {
+                    bits,
+                    tables: args.count_at_least("--tables", 1)?.unwrap_or(defaults.tables),
+                    probes: args.count_at_least("--probes", 1)?.unwrap_or(defaults.probes),
+                })
+            }
+            "tune" => {
+                let (ef_search, nprobe) = (args.count_at_least("--ef-search", 1)?, args.count_at_least("--nprobe", 1)?);
+                if ef_search.is_none() && nprobe.is_none() {
+                    return Err("usage : index <collection> tune [--ef-search N] [--nprobe N]".into());
+                }
+                if self.db.get_collection(name)?.index.is_none() {
+                    return Err(format!("la collection '{}' n'a pas d'index à régler", name).into());
+                }
+                self.db.tune_index(name, ef_search, nprobe)?;
+                return Ok(Output::Done {
+                    message: format!("Collection '{}' : réglages de recherche de l'index modifiés.", name),
+                    json: object([
+                        ("ef_search", ef_search.map_or(Value::Null, |value| Value::Number(value as f64))),
+                        ("nprobe", nprobe.map_or(Value::Null, |value| Value::Number(value as f64))),
+                    ]),
+                });
+            }
+            "rebuild" => self
+                .db
+                .rebuild_index(name)?
+                .ok_or_else(|| format!("la collection '{}' n'a pas d'index à reconstruire", name))?,
+            "none" => {
+                self.db.set_index(name, None)?;
+                return Ok(Output::Done {
+                    message: format!("Collection '{}' : index supprimé.", name),
+                    json: object([("index", Value::Null)]),
+                });
+            }
+            other => return Err(format!("index inconnu '{}' (hnsw, ivf, lsh, tune, rebuild ou none)", other).into()),
+        };
+
+        // `rebuild` a déjà reconstruit l'index.
+        if args.positional[1] != "rebuild" {
+            self.db.set_index(name, Some(config))?;
+        }
+        let documents = self.db.get_collection(name)?.len();
+        Ok(Output::Done {
+            message: format!("Collection '{}' : index {} construit sur {} document(s).", name, config.name(), documents),
+            json: object([
+                ("index", Value::from(config.name())),
+                ("documents", Value::Number(documents as f64)),
+            ]),
+        })
+    }
+
+    /// Quantifie les vecteurs d'une collection (`pq`, `int8`, `int4` ou `binary`) ou supprime la quantification (`none`),
+    /// puis mesure la mémoire occupée par les codes et le rappel de la recherche sur les codes.
+    fn quantize(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let name = &args.positional[0];
+        let k = args.count("-k")?.unwrap_or(DEFAULT_K);
+        let oversampling = match args.option("--oversampling") {
+            Some(factor) => match factor.parse::<f32>() {
+                Ok(factor) if factor.is_finite() && factor >= 1.0 => Some(factor),
+                _ => return Err("--oversampling attend un nombre supérieur ou égal à 1".into()),
+            },
+            None => None,
+        };
+        let config = match args.positional[1].as_str() {
+            "pq" => {
+                let defaults = PqParams::default();
+                let centroids = args.count("--centroids")?.unwrap_or(defaults.centroids);
+                if !(1..=256).contains(&centroids) {
+                    return Err("--centroids attend un entier entre 1 et 256".into());
+                }
+                QuantizerConfig::Product(PqParams {
+                    subspaces: args.count("--subspaces")?.unwrap_or(defaults.subspaces).max(1),
+                    centroids,
+                    sample_size: args.count("--sample")?.or(defaults.sample_size),
+                    iterations: defaults.iterations,
+                    rerank: args.count("--rerank")?,
+                })
+            }
+            bits @ ("int8" | "int4") => QuantizerConfig::Scalar(SqParams {
+                bits: if bits == "int8" { ScalarBits::Int8 } else { ScalarBits::Int4 },
+                calibration: if args.flag("--global") { Calibration::Global } else { Calibration::PerDimension },
+                oversampling,
+            }),
+            "binary" => QuantizerConfig::Binary(BqParams {
+                oversampling: oversampling.unwrap_or(BqParams::default().oversampling),
+            }),
+            "none" => {
+                self.db.set_quantizer(name, None)?;
+                return Ok(Output::Done {
+                    message: format!("Collection '{}' : quantification supprimée.", name),
+                    json: object([("quantization", Value::Null)]),
+                });
+            }
+            other => return Err(format!("quantification inconnue '{}' (pq, int8, int4, binary ou none)", other).into()),
+        };
+        self.db.set_quantizer(name, Some(config))?;
+
+        let report = match self.db.get_collection(name)?.quantization_report(REPORT_QUERIES, k) {
+            Some(report) => report,
+            // Un quantificateur binaire installé sur une collection vide prend la dimension du premier document.
+            None => {
+                return Ok(Output::Done {
+                    message: format!("Collection '{}' : quantification {} installée, la collection est vide.", name, config.name()),
+                    json: object([("quantization", Value::from(config.name()))]),
+                })
+            }
+        };
+        Ok(Output::Done {
+            message: format!(
+                "Collection '{}' quantifiée : {} octets par document au lieu de {}, {} octets au total ; \
+                 rappel@{} : {:.3} sur {} requête(s).",
+                name, report.code_bytes, report.vector_bytes, report.memory_bytes, report.k, report.recall, report.queries
+            ),
+            json: object([
+                ("quantization", Value::from(report.quantization)),
+                ("code_bytes", Value::Number(report.code_bytes as f64)),
+                ("vector_bytes", Value::Number(report.vector_bytes as f64)),
+                ("memory_bytes", Value::Number(report.memory_bytes as f64)),
+                ("k", Value::Number(report.k as f64)),
+                ("queries", Value::Number(report.queries as f64)),
+                ("recall", Value::Number(report.recall)),
+            ]),
+        })
+    }
+
+    /// Indexe un champ des métadonnées d'une collection (voir [`Database::create_payload_index`]).
+    fn payload_index(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, field) = (&args.positional[0], &args.positional[1]);
+        self.db.create_payload_index(name, field)?;
+        Ok(Output::Done {
+            message: format!("Collection '{}' : champ '{}' indexé.", name, field),
+            json: object([("field", Value::from(field.as_str()))]),
+        })
+    }
+
+    /// Écrit un instantané de la base et vide le journal (voir [`Database::snapshot`]).
+    fn snapshot(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(0)?;
+        let written = self.db.snapshot()?;
+        Ok(Output::Done {
+            message: if written {
+                "Instantané écrit, journal vidé.".to_string()
+            } else {
+                "Base en mémoire : aucun instantané à écrire.".to_string()
+            },
+            json: object([("snapshot", Value::Bool(written))]),
+        })
+    }
+
+    /// Exporte une collection en CSV ou JSON Lines, un document par ligne, ou en Parquet ou Arrow,
+    /// triés par identifiant (voir [`Database::export`] et [`Database::export_table`]).
+    fn export(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, path) = (&args.positional[0], &args.positional[1]);
+        let format = file_format(args, path)?;
+        let batch_size = args.count("--batch")?.unwrap_or(ImportOptions::default().batch_size);
+        self.db.get_collection(name)?;
+
+        let writer = BufWriter::new(File::create(path)?);
+        let exported = match format {
+            FileFormat::Text(format) => self.db.export(name, writer, format)?,
+            FileFormat::Table(format) => {
+                let options = table_options(args, format, ErrorPolicy::Abort, batch_size);
+                self.db.export_table(name, writer, &options)?
+            }
+        };
+        Ok(Output::Done {
+            message: format!("{} document(s) exporté(s) vers '{}'.", exported, path),
+            json: object([
+                ("exported", Value::Number(exported as f64)),
+                ("format", Value::from(format.name())),
+            ]),
+        })
+    }
+
+    /// Charge dans une collection la base d'un jeu de données TEXMEX (voir [`Database::load_vecs`]).
+    fn load(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, path) = (&args.positional[0], &args.positional[1]);
+        let format = vecs_format(path)?;
+        self.db.get_collection(name)?;
+
+        let loaded = self.db.load_vecs(name, BufReader::new(File::open(path)?), format, args.count("--limit")?)?;
+        Ok(Output::Done {
+            message: format!("{} vecteur(s) chargé(s) dans '{}'.", loaded, name),
+            json: object([("loaded", Value::Number(loaded as f64))]),
+        })
+    }
+
+    /// Charge une matrice `.npy`, avec ses identifiants s'ils sont donnés, ou une archive `.npz`.
+    fn import_npy(&mut self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, path) = (&args.positional[0], &args.positional[1]);
+        self.db.get_collection(name)?;
+
+        let loaded = match Path::new(path).extension().and_then(|extension| extension.to_str()) {
+            Some("npz") => {
+                let ids = args.option("--ids").unwrap_or(npy::NPZ_IDS);
+                let vectors = args.option("--vectors").unwrap_or(npy::NPZ_VECTORS);
+                self.db.import_npz(name, BufReader::new(File::open(path)?), ids, vectors)?
+            }
+            Some("npy") => {
+                if args.option("--vectors").is_some() {
+                    return Err("--vectors ne s'applique qu'à une archive .npz".into());
+                }
+                let ids = match args.option("--ids") {
+                    Some(ids) if ids.ends_with(".npy") => Some(npy::read_ids(BufReader::new(File::open(ids)?))?),
+                    Some(ids) => Some(npy::read_id_lines(BufReader::new(File::open(ids)?))?),
+                    None => None,
+                };
+                self.db.import_npy(name, BufReader::new(File::open(path)?), ids)?
+            }
+            _ => return Err(format!("'{}' : extension .npy ou .npz attendue", path).into()),
+        };
+        Ok(Output::Done {
+            message: format!("{} vecteur(s) chargé(s) dans '{}'.", loaded, name),
+            json: object([("loaded", Value::Number(loaded as f64))]),
+        })
+    }
+
+    /// Exporte les vecteurs d'une collection dans une matrice `.npy`, et leurs identifiants à côté.
+    fn export_npy(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(2)?;
+        let (name, path) = (&args.positional[0], &args.positional[1]);
+        let ids_path = match args.option("--ids") {
+            Some(ids) => PathBuf::from(ids),
+            None => Path::new(path).with_extension("ids"),
+        };
+        self.db.get_collection(name)?;
+
+        let vectors = BufWriter::new(File::create(path)?);
+        let ids = BufWriter::new(File::create(&ids_path)?);
+        let exported = self.db.export_npy(name, vectors, ids)?;
+        Ok(Output::Done {
+            message: format!("{} vecteur(s) exporté(s) dans '{}' (identifiants dans '{}').", exported, path, ids_path.display()),
+            json: object([
+                ("exported", Value::Number(exported as f64)),
+                ("ids", Value::from(ids_path.display().to_string().as_str())),
+            ]),
+        })
+    }
+
+    /// Mesure le rappel et la vitesse de la recherche d'une collection chargée par `load`,
+    /// d'après les requêtes et les voisins exacts d'un jeu de données TEXMEX (voir [`texmex::evaluate`]).
+    fn bench(&self, args: &Args) -> CliResult<Output> {
+        args.expect_positional(3)?;
+        let name = &args.positional[0];
+        let (queries_path, truth_path) = (&args.positional[1], &args.positional[2]);
+        let k = args.count("-k")?.unwrap_or(DEFAULT_K);
+        let limit = args.count("--limit")?;
+        let collection = self.db.get_collection(name)?;
+
+        let queries = QuerySet::load(BufReader::new(File::open(queries_path)?), vecs_format(queries_path)?, limit)?;
+        if vecs_format(truth_path)? != VecsFormat::Ivecs {
+            return Err(format!("'{}' : les voisins exacts se lisent dans un fichier .ivecs", truth_path).into());
+        }
+        let truth = GroundTruth::load(BufReader::new(File::open(truth_path)?), limit)?;
+        let report = texmex::evaluate(collection, &queries, &truth, k)?;
+        let latency = report.elapsed.as_secs_f64() * 1_000.0 / report.queries.max(1) as f64;
+        Ok(Output::Done {
+            message: format!(
+                "Collection '{}' : rappel@{} : {:.3} sur {} requête(s) ; {:.0} requêtes/s ({:.3} ms par requête).",
+                name,
+                report.k,
+                report.recall,
+                report.queries,
+                report.queries_per_second(),
+                latency
+            ),
+            json: object([
+                ("k", Value::Number(report.k as f64)),
+                ("queries", Value::Number(report.queries as f64)),
+                ("recall", Value::Number(report.recall)),
+                ("queries_per_second", Value::Number(report.queries_per_second())),
+                ("latency_ms", Value::Number(latency)),
+            ]),
+        })
+    }
+
+    /// Affiche le résultat d'une commande, en couleurs ou en JSON selon le mode de la session.
+    fn print(&self, output: &Output) {
+        if self.json_output {
+            println!("{}", output.json());
+            return;
+        }
+
+        match output {
+            Output::Done { message, .. } => println!("{}", message.bright_green()),
+            Output::Collections(stats) if stats.is_empty() => println!("{}", "Aucune collection.".yellow()),
+            Output::Collections(stats) => {
+                println!("{}", "Collections :".bright_blue().bold());
+                for stats in stats {
+                    println!(
+                        "- {} {}",
+                        stats.name.bright_white().bold(),
+                        format!("({}, {}, {} document(s))", stats.metric, describe_dimension(stats.dimension), stats.documents)
+                            .bright_black()
+                    );
+                }
+            }
+            Output::Stats(stats) => {
+                for stats in stats {
+                    println!("{}", format!("Collection '{}'", stats.name).bright_blue().bold());
+                    println!("  {} {}", "Métrique :".bright_magenta(), stats.metric);
+                    println!(
+                        "  {} {}",
+                        "Dimension :".bright_magenta(),
+                        stats.dimension.map_or("libre".to_string(), |dimension| dimension.to_string())
+                    );
+                    println!("  {} {}", "Normalisation :".bright_magenta(), if stats.normalize { "oui" } else { "non" });
+                    println!(
+                        "  {} {}",
+                        "Stockage :".bright_magenta(),
+                        if stats.mapped { "fichier projeté en mémoire" } else { "en mémoire" }
+                    );
+                    println!("  {} {}", "Documents :".bright_magenta(), stats.documents);
+                    println!("  {} {}", "Avec métadonnées :".bright_magenta(), stats.with_payload);
+                    println!("  {} {} octets", "Vecteurs :".bright_magenta(), stats.vector_bytes);
+                    let index = match (stats.index, stats.ivf_lists, stats.lsh_tables) {
+                        (Some(_), Some((nlist, imbalance)), _) => {
+                            format!("IVF ({} listes, déséquilibre {:.2})", nlist, imbalance)
+                        }
+                        (Some(_), _, Some((tables, bits))) => format!("LSH ({} tables de {} bits)", tables, bits),
+                        (Some(name), None, None) => name.to_uppercase(),
+                        (None, _, _) => "aucun".to_string(),
+                    };
+                    println!("  {} {}", "Index :".bright_magenta(), index);
+                    let quantization = stats.quantization.map_or("aucune".to_string(), |(name, code_bytes)| {
+                        format!("{} ({} octets par document)", name.to_uppercase(), code_bytes)
+                    });
+                    println!("  {} {}", "Quantification :".bright_magenta(), quantization);
+                }
+                let total: usize = stats.iter().map(|stats| stats.documents).sum();
+                println!(
+                    "{}",
+                    format!("{} collection(s), {} document(s) au total.", stats.len(), total).bold()
+                );
+                println!("{} {}", "Instructions vectorielles :".bright_magenta(), simd::backend());
+            }
+            Output::Document { id, vector, payload } => {
+                println!("{} {}", "Document ID:".bright_magenta(), id.to_string().bright_white());
+                println!("{} {:?}", "Vecteur:".bright_magenta(), vector);
+                println!("{} {}", "Métadonnées:".bright_magenta(), PayloadDisplay(payload).to_string().bright_black());
+            }
+            Output::Hits(results) if results.hits.is_empty() => println!("{}", "Aucun résultat.".yellow()),
+            Output::Hits(results) => print_hits(results),
+            Output::Batch(results) => {
+                for (i, results) in results.iter().enumerate() {
+                    println!("{}", format!("Requête {} :", i + 1).bright_blue().bold());
+                    print_hits(results);
+                }
+            }
+            Output::Help => println!("{}", USAGE),
+        }
+    }
+}
+
+fn print_hits(results: &SearchResult) {
+    let label = format!("- {}:", results.metric);
+    for (i, (key, score)) in results.hits.iter().enumerate() {
+        let payload = results
+            .payloads
+            .as_ref()
+            .and_then(|payloads| payloads.get(i))
+            .map(|payload| format!(" {}", PayloadDisplay(payload)))
+            .unwrap_or_default();
+        println!(
+            "{} {} {} {:.4}{}",
+            "Document ID:".bright_magenta(),
+            key.to_string().bright_white(),
+            label.bright_magenta(),
+            score,
+            payload.bright_black()
+        );
+    }
+}
+
+fn describe_dimension(dimension: Option<usize>) -> String {
+    match dimension {
+        Some(dimension) => format!("dimension {}", dimension),
+        None => "dimension libre".to_string(),
+    }
+}
+
+fn parse_id(id: &str) -> CliResult<DocumentId> {
+    Uuid::parse_str(id).map_err(|_| format!("identifiant de document invalide '{}'", id).into())
+}
+
+/// Le format d'un fichier d'import ou d'export : texte, lu ligne par ligne, ou en colonnes.
+#[derive(Debug, Clone, Copy)]
+enum FileFormat {
+    Text(Format),
+    Table(TableFormat),
+}
+
+impl FileFormat {
+    fn name(self) -> &'static str {
+        match self {
+            FileFormat::Text(format) => format.name(),
+            FileFormat::Table(format) => format.name(),
+        }
+    }
+}
+
+/// Le format d'un fichier d'import ou d'export : celui de l'option `--format`, sinon celui de son extension.
+fn file_format(args: &Args, path: &str) -> CliResult<FileFormat> {
+    match args.option("--format") {
+        Some(name) => Format::parse(name)
+            .map(FileFormat::Text)
+            .or_else(|| TableFormat::parse(name).map(FileFormat::Table))
+            .ok_or_else(|| format!("format inconnu '{}' (csv, jsonl, parquet ou arrow)", name).into()),
+        None => Ok(TableFormat::from_path(Path::new(path))
+            .map(FileFormat::Table)
+            .unwrap_or_else(|| FileFormat::Text(Format::from_path(Path::new(path))))),
+    }
+}
+
+/// Les options d'import ou d'export d'un fichier Parquet ou Arrow, avec les colonnes données par
+/// `--id-column` et `--vector-column`.
+fn table_options(args: &Args, format: TableFormat, on_error: ErrorPolicy, batch_size: usize) -> TableOptions {
+    let defaults = TableOptions::default();
+    TableOptions {
+        format,
+        id_column: args.option("--id-column").map_or(defaults.id_column, str::to_string),
+        vector_column: args.option("--vector-column").map_or(defaults.vector_column, str::to_string),
+        on_error,
+        batch_size,
+    }
+}
+
+/// Le format d'un fichier TEXMEX, d'après son extension.
+fn vecs_format(path: &str) -> CliResult<VecsFormat> {
+    VecsFormat::from_path(Path::new(path))
+        .ok_or_else(|| format!("'{}' : extension .fvecs, .bvecs ou .ivecs attendue", path).into())
+}
+
+/// Lit un vecteur écrit `1,2,3` ou `[1, 2, 3]`.
+fn parse_vector(text: &str) -> CliResult<Vec<f32>> {
+    let text = text.trim();
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(text);
+    inner
+        .split(',')
+        .map(|value| value.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("vecteur invalide '{}'", text).into())
+}
+
+/// Découpe une ligne saisie en mots, comme un shell : les guillemets simples protègent leur contenu
+/// tel quel, les guillemets doubles acceptent les échappements `\"` et `\\`.
+fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            '\'' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("guillemet simple non fermé".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => current.push(c),
+                            Some(c) => {
+                                current.push('\\');
+                                current.push(c);
+                            }
+                            None => return Err("guillemet double non fermé".to_string()),
+                        },
+                        Some(c) => current.push(c),
+                        None => return Err("guillemet double non fermé".to_string()),
+                    }
+                }
+            }
+            c => {
+                in_token = true;
+                current.push(c);
+            }
+        }
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// # Structure: `Args`
+///
+/// Les arguments d'une commande : les valeurs positionnelles, les options suivies d'une valeur
+/// (`--metric cosine`) et les options booléennes (`--exact`).
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+    flags: HashSet<String>,
+}
+
+impl Args {
+    /// Sépare les arguments ; une option absente des listes `valued` et `flags` est refusée.
+    fn parse(args: &[String], valued: &[&str], flags: &[&str]) -> CliResult<Args> {
+        let mut parsed = Args {
+            positional: Vec::new(),
+            options: HashMap::new(),
+            flags: HashSet::new(),
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if valued.contains(&arg.as_str()) {
+                let value = args.next().ok_or_else(|| format!("valeur manquante pour l'option {}", arg))?;
+                parsed.options.insert(arg.clone(), value.clone());
+            } else if flags.contains(&arg.as_str()) {
+                parsed.flags.insert(arg.clone());
+            } else if arg.starts_with('-') && arg[1..].starts_with(|c: char| c.is_ascii_alphabetic() || c == '-') {
+                return Err(format!("option inconnue '{}'", arg).into());
+            } else {
+                parsed.positional.push(arg.clone());
+            }
+        }
+        Ok(parsed)
+    }
+
+    fn expect_positional(&self, count: usize) -> CliResult<()> {
+        if self.positional.len() == count {
+            Ok(())
+        } else {
+            Err(format!("{} argument(s) attendu(s), {} reçu(s) (voir 'help')", count, self.positional.len()).into())
+        }
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    /// La valeur entière d'une option, si elle est donnée.
+    fn count(&self, name: &str) -> CliResult<Option<usize>> {
+        self.option(name)
+            .map(|value| value.parse().map_err(|_| format!("{} attend un entier positif", name).into()))
+            .transpose()
+    }
+
+    /// La valeur entière d'une option, si elle est donnée, qui doit valoir au moins `minimum`.
+    fn count_at_least(&self, name: &str, minimum: usize) -> CliResult<Option<usize>> {
+        match self.count(name)? {
+            Some(value) if value < minimum => Err(format!("{} attend un entier d'au moins {}", name, minimum).into()),
+            value => Ok(value),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    /// Exécute une ligne de commande dans la session et retourne la description JSON du résultat.
+    fn run_line(session: &mut Session, line: &str) -> CliResult<Value> {
+        let tokens = split_line(line)?;
+        session.execute(&tokens).map(|output| output.json())
+    }
+
+    fn id(n: u128) -> String {
+        Uuid::from_u128(n).to_string()
+    }
+
+    #[test]
+    fn lines_are_split_like_a_shell() {
+        assert_eq!(split_line("  search  docs 1,2 ").unwrap(), strings(&["search", "docs", "1,2"]));
+        assert_eq!(
+            split_line(r#"insert docs 1,2 --payload '{"a": "b c"}'"#).unwrap(),
+            strings(&["insert", "docs", "1,2", "--payload", r#"{"a": "b c"}"#])
+        );
+        assert_eq!(split_line(r#"say "a \"b\" \\ \n" x"#).unwrap(), strings(&["say", r#"a "b" \ \n"#, "x"]));
+        // Des guillemets vides forment un mot vide ; ils peuvent aussi être collés à un mot.
+        assert_eq!(split_line(r#"a "" b'c d'e"#).unwrap(), strings(&["a", "", "bc de"]));
+        assert!(split_line("").unwrap().is_empty());
+        assert_eq!(split_line("a 'b").unwrap_err(), "guillemet simple non fermé");
+        assert_eq!(split_line(r#"a "b\"#).unwrap_err(), "guillemet double non fermé");
+    }
+
+    #[test]
+    fn arguments_are_sorted_into_positional_values_options_and_flags() {
+        let args = Args::parse(&strings(&["docs", "-k", "3", "--exact", "-1,2"]), &["-k"], &["--exact"]).unwrap();
+        assert_eq!(args.positional, strings(&["docs", "-1,2"]));
+        assert_eq!(args.count("-k").unwrap(), Some(3));
+        assert!(args.flag("--exact") && !args.flag("--with-payload"));
+        assert_eq!(args.option("--filter"), None);
+        assert!(args.expect_positional(2).is_ok());
+        assert_eq!(args.expect_positional(1).unwrap_err().to_string(), "1 argument(s) attendu(s), 2 reçu(s) (voir 'help')");
+
+        let error = |args: &[&str]| Args::parse(&strings(args), &["-k"], &["--exact"]).err().map(|error| error.to_string());
+        assert_eq!(error(&["docs", "--verbose"]), Some("option inconnue '--verbose'".to_string()));
+        assert_eq!(error(&["docs", "-x"]), Some("option inconnue '-x'".to_string()));
+        assert_eq!(error(&["docs", "-k"]), Some("valeur manquante pour l'option -k".to_string()));
+
+        let args = Args::parse(&strings(&["-k", "-3", "--m", "1"]), &["-k", "--m"], &[]).unwrap();
+        assert_eq!(args.count("-k").unwrap_err().to_string(), "-k attend un entier positif");
+        assert_eq!(args.count_at_least("--m", 1).unwrap(), Some(1));
+        assert_eq!(args.count_at_least("--m", 2).unwrap_err().to_string(), "--m attend un entier d'au moins 2");
+        assert_eq!(args.count_at_least("--ef-search", 1).unwrap(), None);
+    }
+
+    #[test]
+    fn vectors_are_read_with_or_without_brackets() {
+        assert_eq!(parse_vector("1,2.5,-3").unwrap(), [1.0, 2.5, -3.0]);
+        assert_eq!(parse_vector(" [1, 2e-1] ").unwrap(), [1.0, 0.2]);
+        assert_eq!(parse_vector("[1,x]").unwrap_err().to_string(), "vecteur invalide '[1,x]'");
+        assert!(parse_vector("").is_err());
+        assert!(parse_vector("1,,2").is_err());
+    }
+
+    #[test]
+    fn file_format_comes_from_the_option_then_the_extension() {
+        let format = |args: &[&str], path: &str| {
+            let args = Args::parse(&strings(args), &["--format"], &[]).unwrap();
+            file_format(&args, path).map(FileFormat::name).map_err(|error| error.to_string())
+        };
+        assert_eq!(format(&[], "docs.csv"), Ok("csv"));
+        assert_eq!(format(&[], "docs.CSV"), Ok("csv"));
+        assert_eq!(format(&[], "docs.parquet"), Ok("parquet"));
+        assert_eq!(format(&[], "docs.feather"), Ok("arrow"));
+        assert_eq!(format(&[], "docs.arrow"), Ok("arrow"));
+        assert_eq!(format(&[], "docs.txt"), Ok("jsonl"));
+        assert_eq!(format(&[], "docs"), Ok("jsonl"));
+        assert_eq!(format(&["--format", "csv"], "docs.jsonl"), Ok("csv"));
+        assert_eq!(format(&["--format", "ndjson"], "docs.csv"), Ok("jsonl"));
+        assert_eq!(format(&["--format", "Parquet"], "docs.csv"), Ok("parquet"));
+        assert_eq!(
+            format(&["--format", "xml"], "docs.xml"),
+            Err("format inconnu 'xml' (csv, jsonl, parquet ou arrow)".to_string())
+        );
+    }
+
+    #[test]
+    fn commands_describe_their_results_in_json() {
+        let mut session = Session { db: Database::new(), json_output: true };
+        let created = run_line(&mut session, "create docs --metric euclidean --dimension 2").unwrap();
+        assert_eq!(created.to_string(), r#"{"dimension":2,"documents":0,"index":null,"mapped":false,"metric":"euclidean","name":"docs","normalize":false,"payload_index":[]}"#);
+        let mapped = run_line(&mut session, "create projetee --mmap").unwrap();
+        assert!(matches!(&mapped, Value::Object(fields) if fields["mapped"] == Value::Bool(true)));
+        run_line(&mut session, "delete projetee").unwrap();
+
+        for n in 1..=3 {
+            let line = format!(r#"insert docs {},0 --id {} --payload '{{"n": {}}}'"#, n, id(n), n);
+            assert_eq!(run_line(&mut session, &line).unwrap().to_string(), format!(r#"{{"id":"{}"}}"#, id(n)));
+        }
+        assert_eq!(
+            run_line(&mut session, &format!("get docs {}", id(2))).unwrap().to_string(),
+            format!(r#"{{"id":"{}","payload":{{"n":2}},"vector":[2,0]}}"#, id(2))
+        );
+        assert_eq!(
+            run_line(&mut session, r#"search docs 0,0 --threshold 3 --filter "n >= 2" --with-payload"#).unwrap().to_string(),
+            format!(
+                r#"{{"hits":[{{"id":"{}","payload":{{"n":2}},"score":2}},{{"id":"{}","payload":{{"n":3}},"score":3}}],"metric":"euclidean"}}"#,
+                id(2),
+                id(3)
+            )
+        );
+        assert_eq!(
+            run_line(&mut session, "list").unwrap().to_string(),
+            r#"{"collections":[{"dimension":2,"documents":3,"metric":"euclidean","name":"docs"}]}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs hnsw --m 8").unwrap().to_string(),
+            r#"{"documents":3,"index":"hnsw"}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs tune --ef-search 20").unwrap().to_string(),
+            r#"{"ef_search":20,"nprobe":null}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs ivf --nlist 2").unwrap().to_string(),
+            r#"{"documents":3,"index":"ivf"}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs tune --nprobe 2").unwrap().to_string(),
+            r#"{"ef_search":null,"nprobe":2}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs rebuild").unwrap().to_string(),
+            r#"{"documents":3,"index":"ivf"}"#
+        );
+        assert_eq!(
+            run_line(&mut session, "index docs lsh --bits 4 --tables 2").unwrap().to_string(),
+            r#"{"documents":3,"index":"lsh"}"#
+        );
+        assert_eq!(run_line(&mut session, "payload-index docs n").unwrap().to_string(), r#"{"field":"n"}"#);
+        assert_eq!(
+            run_line(&mut session, &format!("delete docs {}", id(1))).unwrap().to_string(),
+            format!(r#"{{"deleted":"{}"}}"#, id(1))
+        );
+        assert_eq!(
+            run_line(&mut session, &format!("delete docs {} {}", id(2), id(9))).unwrap().to_string(),
+            format!(r#"{{"deleted":["{}"],"not_found":["{}"]}}"#, id(2), id(9))
+        );
+        assert_eq!(run_line(&mut session, "snapshot").unwrap().to_string(), r#"{"snapshot":false}"#);
+        assert_eq!(run_line(&mut session, "help").unwrap(), Value::from(USAGE));
+    }
+
+    #[test]
+    fn invalid_commands_are_reported() {
+        let mut session = Session { db: Database::new(), json_output: false };
+        run_line(&mut session, "create docs --dimension 2").unwrap();
+        run_line(&mut session, "insert docs 1,0").unwrap();
+        let error = |session: &mut Session, line: &str| run_line(session, line).unwrap_err().to_string();
+
+        assert_eq!(error(&mut session, "frobnicate"), "commande inconnue 'frobnicate' (voir 'help')");
+        assert_eq!(error(&mut session, "create docs --dimension 0"), "--dimension attend un entier strictement positif");
+        assert!(error(&mut session, "insert docs 1,2,3").contains("dimension"));
+        assert_eq!(error(&mut session, "insert docs 1,0 --payload [1]"), "--payload attend un objet JSON");
+        assert_eq!(error(&mut session, "get docs pas-un-uuid"), "identifiant de document invalide 'pas-un-uuid'");
+        assert_eq!(error(&mut session, "search docs 1,0 --threshold inf"), "--threshold attend un nombre");
+        assert!(error(&mut session, "search absent 1,0").contains("absent"));
+        assert_eq!(error(&mut session, "index docs hnsw --m 1"), "--m attend un entier d'au moins 2");
+        assert_eq!(error(&mut session, "index docs ivf --nlist 0"), "--nlist attend un entier d'au moins 1");
+        assert_eq!(error(&mut session, "index docs lsh --tables 0"), "--tables attend un entier d'au moins 1");
+        assert_eq!(error(&mut session, "index docs lsh --probes 0"), "--probes attend un entier d'au moins 1");
+        assert_eq!(error(&mut session, "index docs lsh --bits 65"), "--bits attend un entier entre 1 et 64");
+        assert_eq!(error(&mut session, "index docs arbre"), "index inconnu 'arbre' (hnsw, ivf, lsh, tune, rebuild ou none)");
+        assert_eq!(error(&mut session, "index docs tune --nprobe 2"), "la collection 'docs' n'a pas d'index à régler");
+        assert_eq!(error(&mut session, "index docs rebuild"), "la collection 'docs' n'a pas d'index à reconstruire");
+        assert_eq!(error(&mut session, "quantize docs pq --centroids 300"), "--centroids attend un entier entre 1 et 256");
+        assert_eq!(error(&mut session, "quantize docs arbre"), "quantification inconnue 'arbre' (pq, int8, int4, binary ou none)");
+        // Les commandes refusées n'ont rien modifié.
+        assert_eq!(session.db.get_collection("docs").unwrap().len(), 1);
+        assert!(session.db.get_collection("docs").unwrap().index.is_none());
+        assert!(session.db.get_collection("docs").unwrap().quantizer.is_none());
+    }
+
+    #[test]
+    fn quantize_goes_through_the_database() {
+        let mut session = Session { db: Database::new(), json_output: false };
+        run_line(&mut session, "create docs --dimension 2").unwrap();
+        assert_eq!(run_line(&mut session, "quantize docs pq").unwrap_err().to_string(), "la collection 'docs' est vide");
+        assert!(session.db.get_collection("docs").unwrap().quantizer.is_none());
+        assert_eq!(run_line(&mut session, "quantize docs binary").unwrap().to_string(), r#"{"quantization":"binary"}"#);
+        assert!(session.db.get_collection("docs").unwrap().quantizer.is_some());
+
+        for n in 1..=8 {
+            run_line(&mut session, &format!("insert docs {},1 --id {}", n, id(n))).unwrap();
+        }
+        let report = match run_line(&mut session, "quantize docs pq --subspaces 2 --centroids 4 --rerank 8 -k 2").unwrap() {
+            Value::Object(report) => report,
+            other => panic!("{}", other),
+        };
+        assert_eq!(report["quantization"], Value::from("pq"));
+        assert_eq!(report["recall"], Value::Number(1.0));
+        let config = session.db.get_collection("docs").unwrap().quantizer.as_ref().map(|quantizer| quantizer.config());
+        assert!(matches!(config, Some(QuantizerConfig::Product(PqParams { subspaces: 2, centroids: 4, rerank: Some(8), .. }))));
+
+        run_line(&mut session, "quantize docs int4 --global --oversampling 2").unwrap();
+        let config = session.db.get_collection("docs").unwrap().quantizer.as_ref().map(|quantizer| quantizer.config());
+        let params = SqParams { bits: ScalarBits::Int4, calibration: Calibration::Global, oversampling: Some(2.0) };
+        assert_eq!(config, Some(QuantizerConfig::Scalar(params)));
+        assert_eq!(
+            run_line(&mut session, "quantize docs int8 --oversampling 0.5").unwrap_err().to_string(),
+            "--oversampling attend un nombre supérieur ou égal à 1"
+        );
+
+        assert_eq!(run_line(&mut session, "quantize docs none").unwrap().to_string(), r#"{"quantization":null}"#);
+        assert!(session.db.get_collection("docs").unwrap().quantizer.is_none());
+    }
+
+    #[test]
+    fn help_stops_before_opening_the_database() {
+        let directory = std::env::temp_dir().join(format!("projet-cli-{}", Uuid::new_v4()));
+        let data = directory.to_string_lossy().to_string();
+        assert_eq!(run(strings(&["--data", &data, "--help", "create", "docs"])), 0);
+        assert!(!directory.exists());
+        assert_eq!(run(strings(&["-h"])), 0);
+    }
+}
@@ -0,0 +1,735 @@
+//! # Module: `columnar`
+//!
+//! Import et export des documents d'une collection dans des fichiers tabulaires en colonnes :
+//! Apache Parquet (module [`parquet`](crate::parquet)) et le format IPC d'Apache Arrow
+//! (module [`arrow`](crate::arrow), fichiers `.arrow` et `.feather`).
+//!
+//! Ces fichiers sont découpés en lots de lignes (les *record batches* d'Arrow, les *row groups* de
+//! Parquet), lus et écrits un par un : seul un lot est en mémoire. Une colonne contient les vecteurs
+//! (une liste de nombres par ligne), une colonne facultative les identifiants (des UUID, en texte ou
+//! sur 16 octets, ou des entiers, l'entier `n` devenant [`dataset_id(n)`](crate::texmex::dataset_id)),
+//! et chacune des autres colonnes est un champ des métadonnées ; une valeur nulle est un champ absent.
+//!
+//! À l'export, les vecteurs sont une liste de `float32` de taille fixe, les identifiants du texte,
+//! et chaque champ des métadonnées une colonne dont le type dépend de ses valeurs : booléen, `float64`,
+//! texte, ou liste de `float64` ou de textes. Un champ dont les valeurs sont de types différents, ou
+//! sont des objets, est une colonne JSON (type logique `JSON` de Parquet, extension `arrow.json` d'Arrow) :
+//! chaque valeur y est écrite en texte JSON, et relue telle quelle, objets imbriqués compris.
+
+use std::io::{Read, Seek, Write};
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::arrow::{ArrowReader, ArrowWriter};
+use crate::error::DbError;
+use crate::json;
+use crate::parquet::{ParquetReader, ParquetWriter};
+use crate::payload::{Payload, Value};
+use crate::texmex::dataset_id;
+use crate::transfer::{ErrorPolicy, ImportOptions};
+use crate::{Document, DocumentId};
+
+/// # Énumération: `TableFormat`
+///
+/// Le format d'un fichier tabulaire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TableFormat {
+    #[default]
+    Parquet,
+    /// Le format IPC d'Arrow : fichier (`.arrow`, `.feather`) ou flux (`.arrows`), lus l'un comme l'autre ;
+    /// l'export écrit un fichier.
+    Arrow,
+}
+
+impl TableFormat {
+    /// Le format désigné par son nom : `parquet`, ou `arrow` (aussi `feather` et `ipc`).
+    pub fn parse(name: &str) -> Option<TableFormat> {
+        match name.to_ascii_lowercase().as_str() {
+            "parquet" => Some(TableFormat::Parquet),
+            "arrow" | "feather" | "ipc" => Some(TableFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    /// Le format d'un fichier d'après son extension, s'il s'agit d'un fichier tabulaire.
+    pub fn from_path(path: &Path) -> Option<TableFormat> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "parquet" | "pq" => Some(TableFormat::Parquet),
+            "arrow" | "arrows" | "feather" | "ipc" => Some(TableFormat::Arrow),
+            _ => None,
+        }
+    }
+
+    /// Le nom du format, tel qu'accepté par [`TableFormat::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            TableFormat::Parquet => "parquet",
+            TableFormat::Arrow => "arrow",
+        }
+    }
+}
+
+/// # Structure: `TableOptions`
+///
+/// Paramètres d'un import ou d'un export tabulaire
+/// ([`Database::import_table`](crate::Database::import_table), [`Database::export_table`](crate::Database::export_table)).
+#[derive(Debug, Clone)]
+pub struct TableOptions {
+    /// Le format du fichier.
+    pub format: TableFormat,
+    /// La colonne des identifiants ; à l'import, si le fichier n'en a pas, chaque document reçoit un nouvel identifiant.
+    pub id_column: String,
+    /// La colonne des vecteurs.
+    pub vector_column: String,
+    /// Ce que fait l'import d'une ligne invalide.
+    pub on_error: ErrorPolicy,
+    /// Nombre de documents enregistrés ensemble à l'import, et nombre de lignes par lot à l'export (au moins 1).
+    pub batch_size: usize,
+}
+
+impl Default for TableOptions {
+    fn default() -> Self {
+        TableOptions {
+            format: TableFormat::default(),
+            id_column: "id".to_string(),
+            vector_column: "vector".to_string(),
+            on_error: ErrorPolicy::default(),
+            batch_size: ImportOptions::default().batch_size,
+        }
+    }
+}
+
+/// # Énumération: `DataType`
+///
+/// Le type des valeurs d'une colonne. À la lecture, les entiers de toutes tailles (dates et horodatages
+/// compris) sont des `Int`, et les nombres à virgule sur 16 ou 32 bits des `Float32` ; dans tous les cas,
+/// les valeurs sont rangées en `i64` ou en `f64` (voir [`Values`]).
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataType {
+    Null,
+    Bool,
+    Int,
+    Float32,
+    Float64,
+    Utf8,
+    /// Du texte JSON, une valeur par ligne, rangé dans des [`Values::Utf8`].
+    Json,
+    Binary,
+    List(Box<Field>),
+    FixedSizeList(Box<Field>, usize),
+}
+
+impl DataType {
+    /// Le type des éléments, pour une liste.
+    pub fn item(&self) -> Option<&Field> {
+        match self {
+            DataType::List(item) | DataType::FixedSizeList(item, _) => Some(item),
+            _ => None,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, DataType::Int | DataType::Float32 | DataType::Float64)
+    }
+}
+
+/// # Structure: `Field`
+///
+/// Une colonne d'un schéma.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub data_type: DataType,
+    pub nullable: bool,
+}
+
+impl Field {
+    pub fn new(name: &str, data_type: DataType, nullable: bool) -> Self {
+        Field { name: name.to_string(), data_type, nullable }
+    }
+}
+
+/// # Énumération: `Values`
+///
+/// Les valeurs d'une colonne, une par ligne, y compris pour les lignes nulles (une valeur quelconque).
+#[derive(Debug, Clone, PartialEq)]
+pub enum Values {
+    /// Une colonne dont toutes les valeurs sont nulles, avec son nombre de lignes.
+    Null(usize),
+    Bool(Vec<bool>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Utf8(Vec<String>),
+    Binary(Vec<Vec<u8>>),
+    /// Les éléments de la ligne `i` sont ceux de `values` entre `offsets[i]` et `offsets[i + 1]`.
+    List { offsets: Vec<usize>, values: Box<Array> },
+}
+
+/// # Structure: `Array`
+///
+/// Une colonne d'un lot de lignes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array {
+    /// Pour chaque ligne, si sa valeur est présente ; `None` si elles le sont toutes.
+    pub validity: Option<Vec<bool>>,
+    pub values: Values,
+}
+
+impl Array {
+    /// Une colonne sans valeur nulle.
+    pub fn new(values: Values) -> Self {
+        Array { validity: None, values }
+    }
+
+    pub fn len(&self) -> usize {
+        match &self.values {
+            Values::Null(length) => *length,
+            Values::Bool(values) => values.len(),
+            Values::Int(values) => values.len(),
+            Values::Float(values) => values.len(),
+            Values::Utf8(values) => values.len(),
+            Values::Binary(values) => values.len(),
+            Values::List { offsets, .. } => offsets.len() - 1,
+        }
+    }
+
+    pub fn is_valid(&self, index: usize) -> bool {
+        !matches!(self.values, Values::Null(_)) && self.validity.as_ref().is_none_or(|validity| validity[index])
+    }
+
+    /// Les lignes de positions `indices`, dans cet ordre (pour les colonnes encodées par dictionnaire).
+    pub fn take(&self, indices: &[usize]) -> Array {
+        let validity = self.validity.as_ref().map(|validity| indices.iter().map(|&index| validity[index]).collect());
+        let values = match &self.values {
+            Values::Null(_) => Values::Null(indices.len()),
+            Values::Bool(values) => Values::Bool(indices.iter().map(|&index| values[index]).collect()),
+            Values::Int(values) => Values::Int(indices.iter().map(|&index| values[index]).collect()),
+            Values::Float(values) => Values::Float(indices.iter().map(|&index| values[index]).collect()),
+            Values::Utf8(values) => Values::Utf8(indices.iter().map(|&index| values[index].clone()).collect()),
+            Values::Binary(values) => Values::Binary(indices.iter().map(|&index| values[index].clone()).collect()),
+            Values::List { offsets, values } => {
+                let items: Vec<usize> = indices.iter().flat_map(|&index| offsets[index]..offsets[index + 1]).collect();
+                let mut taken = vec![0];
+                for &index in indices {
+                    taken.push(taken.last().unwrap() + offsets[index + 1] - offsets[index]);
+                }
+                Values::List { offsets: taken, values: Box::new(values.take(&items)) }
+            }
+        };
+        Array { validity, values }
+    }
+
+    /// La valeur de la ligne `index` comme valeur de métadonnée, ou `None` si elle est nulle.
+    fn value(&self, index: usize) -> Option<Value> {
+        if !self.is_valid(index) {
+            return None;
+        }
+        Some(match &self.values {
+            Values::Null(_) => return None,
+            Values::Bool(values) => Value::Bool(values[index]),
+            Values::Int(values) => Value::Number(values[index] as f64),
+            Values::Float(values) if values[index].is_finite() => Value::Number(values[index]),
+            Values::Float(_) => return None,
+            Values::Utf8(values) => Value::from(values[index].as_str()),
+            Values::Binary(values) => Value::from(String::from_utf8_lossy(&values[index]).as_ref()),
+            Values::List { offsets, values } => {
+                Value::Array((offsets[index]..offsets[index + 1]).map(|item| values.value(item).unwrap_or(Value::Null)).collect())
+            }
+        })
+    }
+}
+
+/// # Structure: `RecordBatch`
+///
+/// Un lot de lignes : une colonne par champ du schéma, toutes de `rows` lignes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordBatch {
+    pub rows: usize,
+    pub columns: Vec<Array>,
+}
+
+/// Le fichier lu par un [`TableReader`].
+enum Source<R> {
+    Arrow(ArrowReader<R>),
+    Parquet(ParquetReader<R>),
+}
+
+/// # Structure: `TableReader`
+///
+/// Lit les documents d'un fichier Parquet ou Arrow, un lot de lignes à la fois.
+pub struct TableReader<R> {
+    source: Source<R>,
+    /// Le rang de la colonne des identifiants, si le fichier en a une.
+    id: Option<usize>,
+    vector: usize,
+    /// Le rang et le nom des colonnes des métadonnées, et si elles contiennent du JSON.
+    payload: Vec<(usize, String, bool)>,
+    batch: RecordBatch,
+    /// La position, dans le lot, de la prochaine ligne à lire.
+    index: usize,
+    /// Nombre de lignes lues dans les lots précédents.
+    row: usize,
+}
+
+impl<R: Read + Seek> TableReader<R> {
+    /// Lit le schéma du fichier et vérifie qu'il contient la colonne des vecteurs, et celle des identifiants
+    /// avec un type qui convient si elle existe.
+    ///
+    /// # Paramètres
+    /// - `reader`: Le contenu du fichier.
+    /// - `options`: Le format du fichier et le nom des colonnes des identifiants et des vecteurs.
+    ///
+    /// # Retour
+    /// - `Result<TableReader<R>, DbError>`: [`DbError::InvalidSchema`] si les colonnes ne conviennent pas,
+    ///   [`DbError::Unsupported`] ou [`DbError::Corrupted`] si le fichier est illisible.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut reader = TableReader::new(File::open("embeddings.parquet")?, &TableOptions::default())?;
+    /// while let Some((row, document)) = reader.next_document()? {
+    ///     // Utiliser le document
+    /// }
+    /// ```
+    pub fn new(reader: R, options: &TableOptions) -> Result<Self, DbError> {
+        let source = match options.format {
+            TableFormat::Arrow => Source::Arrow(ArrowReader::open(reader)?),
+            TableFormat::Parquet => Source::Parquet(ParquetReader::open(reader)?),
+        };
+        let fields = match &source {
+            Source::Arrow(reader) => reader.fields(),
+            Source::Parquet(reader) => reader.fields(),
+        };
+        let position = |name: &str| fields.iter().position(|field| field.name == name);
+
+        let vector = position(&options.vector_column).ok_or_else(|| {
+            let names: Vec<&str> = fields.iter().map(|field| field.name.as_str()).collect();
+            DbError::InvalidSchema(format!("colonne des vecteurs '{}' absente (colonnes : {})", options.vector_column, names.join(", ")))
+        })?;
+        if !fields[vector].data_type.item().is_some_and(|item| item.data_type.is_number()) {
+            return Err(DbError::InvalidSchema(format!(
+                "la colonne '{}' doit contenir des listes de nombres, pas {:?}",
+                options.vector_column, fields[vector].data_type
+            )));
+        }
+        let id = position(&options.id_column);
+        if let Some(id) = id {
+            if !matches!(fields[id].data_type, DataType::Utf8 | DataType::Binary | DataType::Int) {
+                return Err(DbError::InvalidSchema(format!(
+                    "la colonne '{}' doit contenir des UUID (texte ou 16 octets) ou des entiers, pas {:?}",
+                    options.id_column, fields[id].data_type
+                )));
+            }
+        }
+        let payload = fields
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != vector && Some(index) != id)
+            .map(|(index, field)| (index, field.name.clone(), field.data_type == DataType::Json))
+            .collect();
+        Ok(TableReader {
+            source,
+            id,
+            vector,
+            payload,
+            batch: RecordBatch { rows: 0, columns: Vec::new() },
+            index: 0,
+            row: 0,
+        })
+    }
+
+    /// Lit le document suivant, en lisant le lot suivant si besoin.
+    ///
+    /// # Retour
+    /// - `Result<Option<(usize, Document)>, DbError>`: Le numéro de la ligne (à partir de 1) et son document,
+    ///   `None` à la fin du fichier, [`DbError::InvalidLine`] si la ligne est invalide (la ligne suivante
+    ///   peut encore être lue), ou une erreur de lecture.
+    pub fn next_document(&mut self) -> Result<Option<(usize, Document)>, DbError> {
+        while self.index == self.batch.rows {
+            let batch = match &mut self.source {
+                Source::Arrow(reader) => reader.next_batch()?,
+                Source::Parquet(reader) => reader.next_batch()?,
+            };
+            match batch {
+                Some(batch) => {
+                    self.row += self.batch.rows;
+                    self.batch = batch;
+                    self.index = 0;
+                }
+                None => return Ok(None),
+            }
+        }
+        let index = self.index;
+        self.index += 1;
+        let line = self.row + index + 1;
+        self.document(index).map(|document| Some((line, document))).map_err(|message| DbError::InvalidLine { line, message })
+    }
+
+    fn document(&self, index: usize) -> Result<Document, String> {
+        let columns = &self.batch.columns;
+        let key = match self.id.map(|id| &columns[id]) {
+            Some(ids) if ids.is_valid(index) => match &ids.values {
+                Values::Utf8(ids) => Uuid::parse_str(ids[index].trim()).map_err(|_| format!("identifiant de document invalide '{}'", ids[index]))?,
+                Values::Binary(ids) => Uuid::from_slice(&ids[index])
+                    .or_else(|_| Uuid::try_parse_ascii(ids[index].trim_ascii()))
+                    .map_err(|_| "identifiant de document invalide : 16 octets ou un UUID en texte attendus".to_string())?,
+                Values::Int(ids) if ids[index] >= 0 => dataset_id(ids[index] as usize),
+                Values::Int(ids) => return Err(format!("identifiant de document négatif {}", ids[index])),
+                _ => unreachable!("type vérifié à l'ouverture"),
+            },
+            _ => Uuid::new_v4(),
+        };
+
+        let vectors = &columns[self.vector];
+        let Values::List { offsets, values } = &vectors.values else {
+            unreachable!("type vérifié à l'ouverture");
+        };
+        if !vectors.is_valid(index) {
+            return Err("vecteur absent".to_string());
+        }
+        let mut vector = Vec::with_capacity(offsets[index + 1] - offsets[index]);
+        for (position, item) in (offsets[index]..offsets[index + 1]).enumerate() {
+            if !values.is_valid(item) {
+                return Err(format!("coordonnée {} nulle", position));
+            }
+            vector.push(match &values.values {
+                Values::Float(values) => values[item] as f32,
+                Values::Int(values) => values[item] as f32,
+                _ => unreachable!("type vérifié à l'ouverture"),
+            });
+        }
+
+        let mut payload = Payload::new();
+        for (column, name, json) in &self.payload {
+            let value = match columns[*column].value(index) {
+                Some(Value::String(text)) if *json => json::parse(&text).map_err(|error| format!("colonne '{}' : {}", name, error))?,
+                Some(value) => value,
+                None => continue,
+            };
+            payload.insert(name.clone(), value);
+        }
+        Ok((key, vector, payload))
+    }
+}
+
+/// Le genre des valeurs d'un champ des métadonnées, qui décide du type de sa colonne à l'export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bool,
+    Number,
+    String,
+    Numbers,
+    Strings,
+    /// Un tableau vide, compatible avec les deux genres de listes.
+    EmptyList,
+    /// Des valeurs de genres différents, ou des objets : une colonne JSON.
+    Json,
+}
+
+impl Kind {
+    fn of(value: &Value) -> Option<Kind> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Bool(_) => Kind::Bool,
+            Value::Number(_) => Kind::Number,
+            Value::String(_) => Kind::String,
+            Value::Array(values) if values.is_empty() => Kind::EmptyList,
+            Value::Array(values) if values.iter().all(|value| matches!(value, Value::Number(_))) => Kind::Numbers,
+            Value::Array(values) if values.iter().all(|value| matches!(value, Value::String(_))) => Kind::Strings,
+            _ => Kind::Json,
+        })
+    }
+
+    fn merge(self, other: Kind) -> Kind {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Kind::EmptyList, list @ (Kind::Numbers | Kind::Strings)) | (list @ (Kind::Numbers | Kind::Strings), Kind::EmptyList) => list,
+            _ => Kind::Json,
+        }
+    }
+
+    fn data_type(self) -> DataType {
+        match self {
+            Kind::Bool => DataType::Bool,
+            Kind::Number => DataType::Float64,
+            Kind::String => DataType::Utf8,
+            Kind::Json => DataType::Json,
+            Kind::Numbers => DataType::List(Box::new(Field::new("item", DataType::Float64, true))),
+            Kind::Strings | Kind::EmptyList => DataType::List(Box::new(Field::new("item", DataType::Utf8, true))),
+        }
+    }
+}
+
+/// Les colonnes des métadonnées d'un export : une par champ présent dans au moins un document,
+/// triées par nom, avec le type qui convient à toutes ses valeurs.
+///
+/// # Exemple
+///
+/// ```
+/// let columns = columnar::payload_fields(collection.payloads.values());
+/// ```
+pub fn payload_fields<'a>(payloads: impl Iterator<Item = &'a Payload>) -> Vec<Field> {
+    let mut kinds: std::collections::BTreeMap<&String, Option<Kind>> = std::collections::BTreeMap::new();
+    for payload in payloads {
+        for (name, value) in payload {
+            let kind = kinds.entry(name).or_insert(None);
+            if let Some(other) = Kind::of(value) {
+                *kind = Some(kind.map_or(other, |kind| kind.merge(other)));
+            }
+        }
+    }
+    kinds
+        .into_iter()
+        .map(|(name, kind)| Field::new(name, kind.map_or(DataType::Utf8, Kind::data_type), true))
+        .collect()
+}
+
+/// Le fichier écrit par un [`TableWriter`].
+enum Sink<W: Write> {
+    Arrow(ArrowWriter<W>),
+    Parquet(ParquetWriter<W>),
+}
+
+/// # Structure: `TableWriter`
+///
+/// Écrit les documents d'une collection dans un fichier Parquet ou Arrow, par lots de lignes.
+pub struct TableWriter<W: Write> {
+    sink: Sink<W>,
+    fields: Vec<Field>,
+    batch_size: usize,
+    ids: Vec<String>,
+    /// Les coordonnées des vecteurs du lot, bout à bout.
+    vectors: Vec<f64>,
+    /// Les valeurs du lot pour chaque colonne des métadonnées.
+    payload: Vec<Vec<Option<Value>>>,
+}
+
+impl<W: Write> TableWriter<W> {
+    /// Prépare l'écriture d'un fichier.
+    ///
+    /// # Paramètres
+    /// - `writer`: La destination, de préférence dans un `BufWriter`.
+    /// - `options`: Le format du fichier, le nom des colonnes des identifiants et des vecteurs, et le nombre de lignes par lot.
+    /// - `dimension`: La dimension des vecteurs.
+    /// - `columns`: Les colonnes des métadonnées (voir [`payload_fields`]) ; les autres champs sont ignorés.
+    ///
+    /// # Retour
+    /// - `Result<TableWriter<W>, DbError>`: [`DbError::InvalidSchema`] si une colonne des métadonnées porte
+    ///   le nom de celle des identifiants ou des vecteurs, ou une erreur d'écriture.
+    pub fn new(writer: W, options: &TableOptions, dimension: usize, columns: Vec<Field>) -> Result<Self, DbError> {
+        if let Some(column) = columns.iter().find(|column| column.name == options.id_column || column.name == options.vector_column) {
+            return Err(DbError::InvalidSchema(format!(
+                "le champ des métadonnées '{}' porte le nom de la colonne des identifiants ou des vecteurs",
+                column.name
+            )));
+        }
+        let mut fields = vec![
+            Field::new(&options.id_column, DataType::Utf8, false),
+            Field::new(
+                &options.vector_column,
+                DataType::FixedSizeList(Box::new(Field::new("item", DataType::Float32, false)), dimension),
+                false,
+            ),
+        ];
+        let payload = vec![Vec::new(); columns.len()];
+        fields.extend(columns);
+        let sink = match options.format {
+            TableFormat::Arrow => Sink::Arrow(ArrowWriter::new(writer, &fields)?),
+            TableFormat::Parquet => Sink::Parquet(ParquetWriter::new(writer, &fields)?),
+        };
+        Ok(TableWriter {
+            sink,
+            fields,
+            batch_size: options.batch_size.max(1),
+            ids: Vec::new(),
+            vectors: Vec::new(),
+            payload,
+        })
+    }
+
+    /// Ajoute un document au lot, et écrit le lot s'il est complet.
+    pub fn write(&mut self, key: &DocumentId, vector: &[f32], payload: &Payload) -> Result<(), DbError> {
+        self.ids.push(key.to_string());
+        self.vectors.extend(vector.iter().map(|&value| value as f64));
+        for (values, field) in self.payload.iter_mut().zip(&self.fields[2..]) {
+            values.push(payload.get(&field.name).cloned());
+        }
+        if self.ids.len() == self.batch_size {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Écrit le dernier lot et la fin du fichier.
+    pub fn finish(mut self) -> Result<(), DbError> {
+        self.flush()?;
+        match self.sink {
+            Sink::Arrow(writer) => writer.finish(),
+            Sink::Parquet(writer) => writer.finish(),
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), DbError> {
+        if self.ids.is_empty() {
+            return Ok(());
+        }
+        let rows = self.ids.len();
+        let dimension = self.vectors.len() / rows;
+        let mut columns = vec![
+            Array::new(Values::Utf8(std::mem::take(&mut self.ids))),
+            Array::new(Values::List {
+                offsets: (0..=rows).map(|row| row * dimension).collect(),
+                values: Box::new(Array::new(Values::Float(std::mem::take(&mut self.vectors)))),
+            }),
+        ];
+        for (values, field) in self.payload.iter_mut().zip(&self.fields[2..]) {
+            columns.push(column(&field.data_type, &std::mem::take(values)));
+        }
+        let batch = RecordBatch { rows, columns };
+        match &mut self.sink {
+            Sink::Arrow(writer) => writer.write_batch(&batch),
+            Sink::Parquet(writer) => writer.write_batch(&batch),
+        }
+    }
+}
+
+/// La colonne de type `data_type` qui contient `values` ; une valeur d'un autre type est nulle,
+/// sauf dans une colonne de texte, où elle est écrite en JSON, comme toutes celles d'une colonne JSON.
+fn column(data_type: &DataType, values: &[Option<Value>]) -> Array {
+    let validity: Vec<bool> = values
+        .iter()
+        .map(|value| match (data_type, value) {
+            (_, None | Some(Value::Null)) => false,
+            (DataType::Bool, Some(value)) => matches!(value, Value::Bool(_)),
+            (DataType::Float64, Some(value)) => matches!(value, Value::Number(_)),
+            (DataType::List(_), Some(value)) => matches!(value, Value::Array(_)),
+            _ => true,
+        })
+        .collect();
+    let values = match data_type {
+        DataType::Bool => Values::Bool(values.iter().map(|value| matches!(value, Some(Value::Bool(true)))).collect()),
+        DataType::Float64 => Values::Float(
+            values
+                .iter()
+                .map(|value| match value {
+                    Some(Value::Number(number)) => *number,
+                    _ => 0.0,
+                })
+                .collect(),
+        ),
+        DataType::List(item) => {
+            let mut offsets = vec![0];
+            let mut items = Vec::new();
+            for value in values {
+                if let Some(Value::Array(values)) = value {
+                    items.extend(values.iter().cloned().map(Some));
+                }
+                offsets.push(items.len());
+            }
+            Values::List { offsets, values: Box::new(column(&item.data_type, &items)) }
+        }
+        DataType::Json => Values::Utf8(values.iter().map(|value| value.as_ref().map_or(String::new(), Value::to_string)).collect()),
+        _ => Values::Utf8(
+            values
+                .iter()
+                .map(|value| match value {
+                    Some(Value::String(text)) => text.clone(),
+                    Some(Value::Null) | None => String::new(),
+                    Some(value) => value.to_string(),
+                })
+                .collect(),
+        ),
+    };
+    Array {
+        validity: if validity.iter().all(|&valid| valid) { None } else { Some(validity) },
+        values,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    #[test]
+    fn round_trips_documents_through_both_formats() {
+        let documents: Vec<Document> = (0..5u128)
+            .map(|i| {
+                let mut payload = Payload::new();
+                payload.insert("rang".to_string(), Value::Number(i as f64));
+                if i % 2 == 0 {
+                    payload.insert("client".to_string(), Value::from("Dupont"));
+                    payload.insert("tags".to_string(), Value::Array(vec![Value::from("acte"); i as usize]));
+                }
+                let detail = match i % 3 {
+                    0 => json::parse(&format!(r#"{{"p":{},"q":[true,null,"x"]}}"#, i)).unwrap(),
+                    1 => Value::from("{\"p\":1}"),
+                    _ => Value::Number(i as f64),
+                };
+                payload.insert("o".to_string(), detail);
+                (Uuid::from_u128(i), vec![i as f32, -0.5, 1e-3], payload)
+            })
+            .collect();
+        for format in [TableFormat::Parquet, TableFormat::Arrow] {
+            let options = TableOptions { format, batch_size: 2, ..Default::default() };
+            let mut file = Vec::new();
+            let columns = payload_fields(documents.iter().map(|(_, _, payload)| payload));
+            assert!(columns.contains(&Field::new("o", DataType::Json, true)));
+            let mut writer = TableWriter::new(&mut file, &options, 3, columns).unwrap();
+            for (key, vector, payload) in &documents {
+                writer.write(key, vector, payload).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let mut reader = TableReader::new(Cursor::new(file), &options).unwrap();
+            for (line, document) in documents.iter().enumerate() {
+                assert_eq!(reader.next_document().unwrap(), Some((line + 1, document.clone())), "{:?}", format);
+            }
+            assert_eq!(reader.next_document().unwrap(), None);
+        }
+    }
+
+    /// Les documents des fichiers de `testdata`, écrits par `testdata/generate.py` avec la disposition de pyarrow.
+    fn reference_documents() -> Vec<Document> {
+        let ids = [
+            "0b4f5a8e-3c1d-4e9a-8f2b-6d7c1e0a9b31",
+            "5e2d8c47-91a3-4b6f-a0d4-2c8e7f1b3a55",
+            "9a7c3e21-4d8b-4f1e-b6a2-7e5d0c9f8b12",
+            "c1f0e9d8-7b6a-4c5d-8e4f-3a2b1c0d9e87",
+            "f3e2d1c0-b9a8-4f7e-9d6c-5b4a3f2e1d06",
+        ];
+        let vectors = [[0.5, -1.0, 2.0], [1.5, 0.0, -2.5], [3.0, 3.0, 3.0], [0.25, 0.125, -0.0625], [0.5, 1000.0, -7.0]];
+        let tags = [Some("vente"), None, Some("bail"), Some("vente"), None];
+        let scores = [0.5, 1.25, -3.0, 2.0, 0.0];
+        (0..5)
+            .map(|row| {
+                let mut payload = Payload::new();
+                payload.insert("rank".to_string(), Value::Number(row as f64 + 1.0));
+                payload.insert("score".to_string(), Value::Number(scores[row]));
+                if let Some(tag) = tags[row] {
+                    payload.insert("tag".to_string(), Value::from(tag));
+                }
+                (Uuid::parse_str(ids[row]).unwrap(), vectors[row].to_vec(), payload)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reads_reference_files_with_a_fixed_size_list_column() {
+        let files: [(TableFormat, &[u8]); 2] = [
+            (TableFormat::Parquet, include_bytes!("../testdata/reference.parquet")),
+            (TableFormat::Arrow, include_bytes!("../testdata/reference.arrow")),
+        ];
+        for (format, file) in files {
+            let options = TableOptions { format, ..Default::default() };
+            let mut reader = TableReader::new(Cursor::new(file), &options).unwrap();
+            for (line, document) in reference_documents().into_iter().enumerate() {
+                assert_eq!(reader.next_document().unwrap(), Some((line + 1, document)), "{:?}", format);
+            }
+            assert_eq!(reader.next_document().unwrap(), None);
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! # Module: `csv`
+//!
+//! Lecture et écriture de fichiers CSV au format de la RFC 4180 : des champs séparés par des virgules,
+//! un enregistrement par ligne, les champs qui contiennent une virgule, un guillemet ou un saut de ligne
+//! étant placés entre guillemets doubles (un guillemet s'y écrit `""`). Un champ entre guillemets peut
+//! donc s'étendre sur plusieurs lignes du fichier.
+//!
+//! Le fichier est lu un enregistrement à la fois : seul l'enregistrement en cours est en mémoire.
+
+use std::io::{self, BufRead, Write};
+
+use crate::error::DbError;
+
+/// Séparateur des champs.
+const DELIMITER: char = ',';
+
+/// # Structure: `CsvReader`
+///
+/// Lit les enregistrements d'un fichier CSV, en retenant le numéro de ligne où chacun commence.
+pub struct CsvReader<R> {
+    reader: R,
+    /// Nombre de lignes du fichier déjà lues.
+    line: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(reader: R) -> Self {
+        CsvReader {
+            reader,
+            line: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Lit l'enregistrement suivant ; les lignes vides sont ignorées.
+    ///
+    /// Un enregistrement mal formé (texte après un guillemet fermant, guillemet jamais refermé, texte
+    /// qui n'est pas de l'UTF-8) est lu jusqu'au bout avant d'être signalé : l'appel suivant reprend
+    /// à l'enregistrement d'après.
+    ///
+    /// # Retour
+    /// - `Result<Option<(usize, Vec<String>)>, DbError>`: Le numéro de la première ligne de l'enregistrement
+    ///   et ses champs, `None` à la fin du fichier, [`DbError::InvalidLine`] si l'enregistrement est mal
+    ///   formé ou [`DbError::Io`] si la lecture échoue.
+    pub fn next_record(&mut self) -> Result<Option<(usize, Vec<String>)>, DbError> {
+        let mut start = self.line + 1;
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut error = None;
+        loop {
+            self.buffer.clear();
+            if self.reader.read_until(b'\n', &mut self.buffer)? == 0 {
+                if !quoted {
+                    return Ok(None);
+                }
+                return Err(invalid(start, "guillemet jamais refermé"));
+            }
+            self.line += 1;
+            let text = match std::str::from_utf8(&self.buffer) {
+                Ok(text) => text,
+                Err(_) => {
+                    error.get_or_insert_with(|| "le texte n'est pas de l'UTF-8".to_string());
+                    ""
+                }
+            };
+            let text = text.strip_suffix('\n').unwrap_or(text);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            if !quoted && fields.is_empty() && field.is_empty() && text.trim().is_empty() && error.is_none() {
+                start = self.line + 1;
+                continue;
+            }
+
+            let mut chars = text.chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' if quoted && chars.peek() == Some(&'"') => {
+                        chars.next();
+                        field.push('"');
+                    }
+                    '"' if quoted => {
+                        quoted = false;
+                        if chars.peek().is_some_and(|&next| next != DELIMITER) {
+                            error.get_or_insert_with(|| format!("texte inattendu après le guillemet fermant du champ {}", fields.len() + 1));
+                        }
+                    }
+                    '"' if field.is_empty() => quoted = true,
+                    c if c == DELIMITER && !quoted => fields.push(std::mem::take(&mut field)),
+                    c => field.push(c),
+                }
+            }
+            if quoted {
+                field.push('\n');
+                continue;
+            }
+            fields.push(field);
+            return match error {
+                Some(message) => Err(invalid(start, &message)),
+                None => Ok(Some((start, fields))),
+            };
+        }
+    }
+}
+
+/// Écrit un enregistrement, en plaçant entre guillemets les champs qui le demandent.
+pub fn write_record<W: Write>(writer: &mut W, fields: &[&str]) -> io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            write!(writer, "{}", DELIMITER)?;
+        }
+        if field.contains([DELIMITER, '"', '\n', '\r']) {
+            write!(writer, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            writer.write_all(field.as_bytes())?;
+        }
+    }
+    writeln!(writer)
+}
+
+fn invalid(line: usize, message: &str) -> DbError {
+    DbError::InvalidLine {
+        line,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_quoted_fields_across_lines() {
+        let text = "id,vector,note\r\n\n1,\"0.5,1\",\"dit \"\"bonjour\"\"\nsur deux lignes\"\n2,3,\"x\"y\n3,4,\n";
+        let mut reader = CsvReader::new(text.as_bytes());
+        assert_eq!(reader.next_record().unwrap(), Some((1, vec!["id".into(), "vector".into(), "note".into()])));
+        assert_eq!(
+            reader.next_record().unwrap(),
+            Some((3, vec!["1".into(), "0.5,1".into(), "dit \"bonjour\"\nsur deux lignes".into()]))
+        );
+        assert!(matches!(reader.next_record(), Err(DbError::InvalidLine { line: 5, .. })));
+        assert_eq!(reader.next_record().unwrap(), Some((6, vec!["3".into(), "4".into(), String::new()])));
+        assert_eq!(reader.next_record().unwrap(), None);
+
+        let mut written = Vec::new();
+        write_record(&mut written, &["1", "0.5,1", "dit \"bonjour\""]).unwrap();
+        assert_eq!(String::from_utf8(written).unwrap(), "1,\"0.5,1\",\"dit \"\"bonjour\"\"\"\n");
+    }
+}
@@ -0,0 +1,87 @@
+//! # Module: `error`
+//!
+//! Les erreurs renvoyées par [`Database`](crate::Database) et [`Collection`](crate::Collection).
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// # Énumération: `DbError`
+///
+/// Les différentes raisons pour lesquelles une opération sur la base de données peut échouer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DbError {
+    /// Aucune collection ne porte ce nom.
+    CollectionNotFound(String),
+    /// Une collection porte déjà ce nom.
+    CollectionExists(String),
+    /// L'opération a besoin des documents de la collection, qui n'en contient aucun.
+    EmptyCollection(String),
+    /// Le vecteur n'a pas la dimension de la collection.
+    DimensionMismatch { expected: usize, found: usize },
+    /// Le vecteur contient une valeur `NaN` ou infinie.
+    InvalidValue { position: usize, value: f32 },
+    /// Le vecteur ne contient aucune coordonnée.
+    EmptyVector,
+    /// Le texte d'un filtre n'a pas pu être analysé.
+    InvalidFilter(String),
+    /// Le texte n'est pas un JSON valide.
+    InvalidJson(String),
+    /// Aucune métrique ne porte ce nom.
+    UnknownMetric(String),
+    /// Une requête d'une recherche groupée est invalide (`index` est sa position dans le lot).
+    InvalidQuery { index: usize, error: Box<DbError> },
+    /// Un lot d'écriture a été rejeté en entier : chaque élément invalide, avec sa position dans le lot.
+    InvalidBatch(Vec<(usize, DbError)>),
+    /// Une ligne d'un fichier importé est invalide (`line` est le numéro de la ligne, à partir de 1).
+    InvalidLine { line: usize, message: String },
+    /// Le fichier est valide, mais utilise une variante de son format qui n'est pas prise en charge.
+    Unsupported(String),
+    /// Les colonnes d'un fichier tabulaire ne correspondent pas à celles attendues (colonne absente ou de mauvais type).
+    InvalidSchema(String),
+    /// Une lecture ou une écriture sur disque a échoué.
+    Io(String),
+    /// Un fichier de données (instantané ou journal) est illisible.
+    Corrupted(String),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::CollectionNotFound(name) => write!(f, "la collection '{}' n'existe pas", name),
+            DbError::CollectionExists(name) => write!(f, "la collection '{}' existe déjà", name),
+            DbError::EmptyCollection(name) => write!(f, "la collection '{}' est vide", name),
+            DbError::DimensionMismatch { expected, found } => {
+                write!(f, "dimension invalide : {} attendue, {} reçue", expected, found)
+            }
+            DbError::InvalidValue { position, value } => {
+                write!(f, "valeur invalide {} à la position {}", value, position)
+            }
+            DbError::EmptyVector => write!(f, "le vecteur est vide"),
+            DbError::InvalidFilter(message) => write!(f, "filtre invalide : {}", message),
+            DbError::InvalidJson(message) => write!(f, "JSON invalide : {}", message),
+            DbError::UnknownMetric(name) => write!(f, "métrique inconnue '{}'", name),
+            DbError::InvalidQuery { index, error } => write!(f, "requête {} : {}", index, error),
+            DbError::InvalidBatch(errors) => {
+                write!(f, "lot rejeté, aucun document modifié")?;
+                for (index, error) in errors {
+                    write!(f, " ; document {} : {}", index, error)?;
+                }
+                Ok(())
+            }
+            DbError::InvalidLine { line, message } => write!(f, "ligne {} : {}", line, message),
+            DbError::Unsupported(message) => write!(f, "format non pris en charge : {}", message),
+            DbError::InvalidSchema(message) => write!(f, "schéma invalide : {}", message),
+            DbError::Io(message) => write!(f, "erreur d'entrée/sortie : {}", message),
+            DbError::Corrupted(message) => write!(f, "données corrompues : {}", message),
+        }
+    }
+}
+
+impl Error for DbError {}
+
+impl From<io::Error> for DbError {
+    fn from(error: io::Error) -> Self {
+        DbError::Io(error.to_string())
+    }
+}
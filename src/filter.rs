@@ -0,0 +1,726 @@
+//! # Module: `filter`
+//!
+//! Filtres sur les métadonnées des documents, utilisés pour restreindre une recherche.
+//!
+//! Un filtre est un arbre ([`Filter`]) que l'on peut construire directement ou obtenir à partir
+//! d'une syntaxe textuelle avec [`Filter::parse`] :
+//!
+//! ```text
+//! type == "acte" AND annee >= 2020 AND client IN ["Dupont", "Martin"]
+//! NOT (statut == "archivé" OR EXISTS supprime_le)
+//! ```
+//!
+//! - Comparaisons : `==`, `!=`, `<`, `<=`, `>`, `>=` entre un champ et une valeur (chaîne, nombre,
+//!   `true`, `false` ou `null`). Les comparaisons d'ordre portent sur deux nombres ou deux chaînes.
+//! - Appartenance : `champ IN [v1, v2, ...]` et `champ NOT IN [...]`.
+//! - Existence : `EXISTS champ` (le champ est présent et non `null`).
+//! - Combinaisons : `AND`, `OR`, `NOT` et parenthèses ; `AND` est prioritaire sur `OR`.
+//!
+//! Un champ peut désigner une valeur imbriquée avec des points (`client.ville`). Si le champ contient
+//! un tableau, une comparaison est vraie dès qu'un des éléments la satisfait.
+//!
+//! Le module fournit aussi [`PayloadIndex`], un index inversé optionnel sur certains champs, qui permet
+//! de trouver les documents satisfaisant un filtre très sélectif sans parcourir toute la collection.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::error::DbError;
+use crate::payload::{Payload, Value};
+use crate::DocumentId;
+
+/// # Énumération: `CompareOp`
+///
+/// Les opérateurs de comparaison entre un champ et une valeur.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Lte,
+    Gt,
+    Gte,
+}
+
+/// # Énumération: `Filter`
+///
+/// L'arbre syntaxique d'un filtre sur les métadonnées.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// Compare la valeur d'un champ à une constante. `!=` est vrai si le champ est absent.
+    Compare { field: String, op: CompareOp, value: Value },
+    /// Vrai si la valeur du champ est égale à l'une des valeurs de la liste.
+    In { field: String, values: Vec<Value> },
+    /// Vrai si le champ est présent et non `null`.
+    Exists(String),
+    And(Vec<Filter>),
+    Or(Vec<Filter>),
+    Not(Box<Filter>),
+}
+
+impl Filter {
+    /// Analyse la syntaxe textuelle d'un filtre.
+    ///
+    /// # Paramètres
+    /// - `text`: Le filtre, par exemple `type == "acte" AND annee >= 2020`.
+    ///
+    /// # Retour
+    /// - `Result<Filter, DbError>`: L'arbre du filtre, ou [`DbError::InvalidFilter`] avec la position de l'erreur.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let filter = Filter::parse(r#"type == "acte" AND client IN ["Dupont", "Martin"]"#)?;
+    /// ```
+    pub fn parse(text: &str) -> Result<Filter, DbError> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens,
+            position: 0,
+            end: text.len(),
+        };
+        let filter = parser.parse_or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some((offset, token)) => Err(syntax_error(*offset, &format!("jeton inattendu {:?}", token))),
+        }
+    }
+
+    /// Évalue le filtre sur les métadonnées d'un document.
+    ///
+    /// # Paramètres
+    /// - `payload`: Les métadonnées du document.
+    ///
+    /// # Retour
+    /// - `bool`: `true` si le document satisfait le filtre.
+    pub fn matches(&self, payload: &Payload) -> bool {
+        match self {
+            Filter::Compare { field, op: CompareOp::Ne, value } => {
+                !any_value(lookup(payload, field), |candidate| compare(candidate, CompareOp::Eq, value))
+            }
+            Filter::Compare { field, op, value } => {
+                any_value(lookup(payload, field), |candidate| compare(candidate, *op, value))
+            }
+            Filter::In { field, values } => any_value(lookup(payload, field), |candidate| {
+                values.iter().any(|value| compare(candidate, CompareOp::Eq, value))
+            }),
+            Filter::Exists(field) => !matches!(lookup(payload, field), None | Some(Value::Null)),
+            Filter::And(filters) => filters.iter().all(|filter| filter.matches(payload)),
+            Filter::Or(filters) => filters.iter().any(|filter| filter.matches(payload)),
+            Filter::Not(filter) => !filter.matches(payload),
+        }
+    }
+}
+
+/// Cherche un champ, éventuellement imbriqué (`client.ville`), dans les métadonnées.
+fn lookup<'a>(payload: &'a Payload, field: &str) -> Option<&'a Value> {
+    let mut parts = field.split('.');
+    let mut current = payload.get(parts.next()?)?;
+    for part in parts {
+        match current {
+            Value::Object(object) => current = object.get(part)?,
+            _ => return None,
+        }
+    }
+    Some(current)
+}
+
+/// Applique un prédicat à une valeur, ou à chacun de ses éléments s'il s'agit d'un tableau.
+fn any_value(value: Option<&Value>, predicate: impl Fn(&Value) -> bool) -> bool {
+    match value {
+        Some(Value::Array(values)) => values.iter().any(predicate),
+        Some(value) => predicate(value),
+        None => false,
+    }
+}
+
+/// Compare deux valeurs. Les comparaisons d'ordre n'ont de sens qu'entre deux nombres ou deux chaînes.
+fn compare(left: &Value, op: CompareOp, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (a, b) if op == CompareOp::Eq || op == CompareOp::Ne => {
+            return (a == b) == (op == CompareOp::Eq);
+        }
+        _ => None,
+    };
+    match (ordering, op) {
+        (Some(ordering), CompareOp::Eq) => ordering == Ordering::Equal,
+        (Some(ordering), CompareOp::Ne) => ordering != Ordering::Equal,
+        (Some(ordering), CompareOp::Lt) => ordering == Ordering::Less,
+        (Some(ordering), CompareOp::Lte) => ordering != Ordering::Greater,
+        (Some(ordering), CompareOp::Gt) => ordering == Ordering::Greater,
+        (Some(ordering), CompareOp::Gte) => ordering != Ordering::Less,
+        (None, CompareOp::Ne) => true,
+        (None, _) => false,
+    }
+}
+
+/// Un jeton de la syntaxe textuelle.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Number(f64),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    In,
+    Exists,
+    True,
+    False,
+    Null,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+fn syntax_error(offset: usize, message: &str) -> DbError {
+    DbError::InvalidFilter(format!("{} (position {})", message, offset))
+}
+
+/// Découpe le texte d'un filtre en jetons, chacun associé à sa position.
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, DbError> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let (offset, c) = chars[i];
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' | ')' | '[' | ']' | ',' => {
+                tokens.push((offset, match c {
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    _ => Token::Comma,
+                }));
+                i += 1;
+            }
+            '=' | '!' | '<' | '>' => {
+                let (op, len) = match (c, next) {
+                    ('=', Some('=')) => (CompareOp::Eq, 2),
+                    ('!', Some('=')) => (CompareOp::Ne, 2),
+                    ('<', Some('=')) => (CompareOp::Lte, 2),
+                    ('>', Some('=')) => (CompareOp::Gte, 2),
+                    ('<', _) => (CompareOp::Lt, 1),
+                    ('>', _) => (CompareOp::Gt, 1),
+                    _ => return Err(syntax_error(offset, &format!("opérateur inconnu '{}'", c))),
+                };
+                tokens.push((offset, Token::Op(op)));
+                i += len;
+            }
+            '"' => {
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(syntax_error(offset, "chaîne non terminée")),
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => {
+                            let escaped = chars.get(i + 1).map(|(_, c)| *c);
+                            match escaped {
+                                Some('n') => value.push('\n'),
+                                Some('t') => value.push('\t'),
+                                Some(c @ ('"' | '\\')) => value.push(c),
+                                _ => return Err(syntax_error(chars[i].0, "séquence d'échappement invalide")),
+                            }
+                            i += 2;
+                        }
+                        Some((_, c)) => {
+                            value.push(*c);
+                            i += 1;
+                        }
+                    }
+                }
+                tokens.push((offset, Token::Str(value)));
+                i += 1;
+            }
+            c if c.is_ascii_digit() || (c == '-' && next.is_some_and(|n| n.is_ascii_digit() || n == '.')) => {
+                let start = i;
+                i += 1;
+                while chars
+                    .get(i)
+                    .is_some_and(|(_, c)| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'))
+                {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().map(|(_, c)| c).collect();
+                let number = literal
+                    .parse()
+                    .map_err(|_| syntax_error(offset, &format!("nombre invalide '{}'", literal)))?;
+                tokens.push((offset, Token::Number(number)));
+            }
+            c if c.is_alphanumeric() || c == '_' => {
+                let start = i;
+                while chars
+                    .get(i)
+                    .is_some_and(|(_, c)| c.is_alphanumeric() || matches!(c, '_' | '.' | '-'))
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().map(|(_, c)| c).collect();
+                let token = match word.to_ascii_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "IN" => Token::In,
+                    "EXISTS" => Token::Exists,
+                    "TRUE" => Token::True,
+                    "FALSE" => Token::False,
+                    "NULL" => Token::Null,
+                    _ => Token::Ident(word),
+                };
+                tokens.push((offset, token));
+            }
+            _ => return Err(syntax_error(offset, &format!("caractère inattendu '{}'", c))),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Analyseur par descente récursive de la grammaire :
+///
+/// ```text
+/// or      := and ("OR" and)*
+/// and     := unary ("AND" unary)*
+/// unary   := "NOT" unary | primary
+/// primary := "(" or ")" | "EXISTS" champ | champ op valeur | champ ["NOT"] "IN" "[" valeurs "]"
+/// ```
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    /// Longueur du texte, position signalée lorsque le filtre se termine trop tôt.
+    end: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Result<(usize, Token), DbError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| syntax_error(self.end, "fin de filtre inattendue"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn accept(&mut self, expected: &Token) -> bool {
+        if self.peek().map(|(_, token)| token) == Some(expected) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), DbError> {
+        let (offset, token) = self.next()?;
+        if token == expected {
+            Ok(())
+        } else {
+            Err(syntax_error(offset, &format!("{:?} attendu, {:?} trouvé", expected, token)))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Filter, DbError> {
+        let mut filters = vec![self.parse_and()?];
+        while self.accept(&Token::Or) {
+            filters.push(self.parse_and()?);
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Filter::Or(filters) })
+    }
+
+    fn parse_and(&mut self) -> Result<Filter, DbError> {
+        let mut filters = vec![self.parse_unary()?];
+        while self.accept(&Token::And) {
+            filters.push(self.parse_unary()?);
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Filter::And(filters) })
+    }
+
+    fn parse_unary(&mut self) -> Result<Filter, DbError> {
+        if self.accept(&Token::Not) {
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Filter, DbError> {
+        let (offset, token) = self.next()?;
+        match token {
+            Token::LParen => {
+                let filter = self.parse_or()?;
+                self.expect(Token::RParen)?;
+                Ok(filter)
+            }
+            Token::Exists => match self.next()? {
+                (_, Token::Ident(field)) => Ok(Filter::Exists(field)),
+                (offset, token) => Err(syntax_error(offset, &format!("nom de champ attendu, {:?} trouvé", token))),
+            },
+            Token::Ident(field) => {
+                let (offset, token) = self.next()?;
+                match token {
+                    Token::Op(op) => Ok(Filter::Compare { field, op, value: self.parse_value()? }),
+                    Token::In => Ok(Filter::In { field, values: self.parse_list()? }),
+                    Token::Not => {
+                        self.expect(Token::In)?;
+                        Ok(Filter::Not(Box::new(Filter::In { field, values: self.parse_list()? })))
+                    }
+                    token => Err(syntax_error(offset, &format!("opérateur attendu, {:?} trouvé", token))),
+                }
+            }
+            token => Err(syntax_error(offset, &format!("condition attendue, {:?} trouvé", token))),
+        }
+    }
+
+    fn parse_list(&mut self) -> Result<Vec<Value>, DbError> {
+        self.expect(Token::LBracket)?;
+        let mut values = Vec::new();
+        if self.accept(&Token::RBracket) {
+            return Ok(values);
+        }
+        loop {
+            values.push(self.parse_value()?);
+            if self.accept(&Token::RBracket) {
+                return Ok(values);
+            }
+            self.expect(Token::Comma)?;
+        }
+    }
+
+    fn parse_value(&mut self) -> Result<Value, DbError> {
+        match self.next()? {
+            (_, Token::Str(value)) => Ok(Value::String(value)),
+            (_, Token::Number(value)) => Ok(Value::Number(value)),
+            (_, Token::True) => Ok(Value::Bool(true)),
+            (_, Token::False) => Ok(Value::Bool(false)),
+            (_, Token::Null) => Ok(Value::Null),
+            (offset, token) => Err(syntax_error(offset, &format!("valeur attendue, {:?} trouvé", token))),
+        }
+    }
+}
+
+/// Clé d'une valeur scalaire dans un [`PayloadIndex`] (les nombres sont comparés par leur représentation binaire).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum IndexKey {
+    Null,
+    Bool(bool),
+    Number(u64),
+    String(String),
+}
+
+impl IndexKey {
+    fn of(value: &Value) -> Option<IndexKey> {
+        match value {
+            Value::Null => Some(IndexKey::Null),
+            Value::Bool(value) => Some(IndexKey::Bool(*value)),
+            // `0.0 + x` ramène `-0.0` à `0.0`, qui sont égaux pour le filtre.
+            Value::Number(value) if !value.is_nan() => Some(IndexKey::Number((value + 0.0).to_bits())),
+            Value::String(value) => Some(IndexKey::String(value.clone())),
+            _ => None,
+        }
+    }
+}
+
+/// # Structure: `PayloadIndex`
+///
+/// Index inversé sur certains champs des métadonnées : pour chaque valeur d'un champ indexé,
+/// l'ensemble des documents qui la portent. Il sert à résoudre les égalités et les `IN` d'un filtre
+/// sans évaluer le filtre sur tous les documents.
+#[derive(Default)]
+pub struct PayloadIndex {
+    fields: HashMap<String, HashMap<IndexKey, HashSet<DocumentId>>>,
+}
+
+impl PayloadIndex {
+    /// Indexe un champ pour tous les documents existants.
+    ///
+    /// # Paramètres
+    /// - `field`: Le champ à indexer (éventuellement imbriqué : `client.ville`).
+    /// - `payloads`: Les métadonnées de tous les documents de la collection.
+    pub fn create_field(&mut self, field: &str, payloads: &HashMap<DocumentId, Payload>) {
+        let mut entries: HashMap<IndexKey, HashSet<DocumentId>> = HashMap::new();
+        for (key, payload) in payloads {
+            for index_key in keys_of(payload, field) {
+                entries.entry(index_key).or_default().insert(*key);
+            }
+        }
+        self.fields.insert(field.to_string(), entries);
+    }
+
+    /// Les champs indexés, par ordre alphabétique.
+    pub fn fields(&self) -> Vec<&str> {
+        let mut fields: Vec<&str> = self.fields.keys().map(String::as_str).collect();
+        fields.sort();
+        fields
+    }
+
+    /// Ajoute un document aux champs indexés.
+    pub fn insert(&mut self, key: DocumentId, payload: &Payload) {
+        for (field, entries) in &mut self.fields {
+            for index_key in keys_of(payload, field) {
+                entries.entry(index_key).or_default().insert(key);
+            }
+        }
+    }
+
+    /// Retire un document des champs indexés.
+    pub fn remove(&mut self, key: &DocumentId, payload: &Payload) {
+        for (field, entries) in &mut self.fields {
+            for index_key in keys_of(payload, field) {
+                if let Some(documents) = entries.get_mut(&index_key) {
+                    documents.remove(key);
+                    if documents.is_empty() {
+                        entries.remove(&index_key);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Calcule, si les champs indexés le permettent, un sur-ensemble des documents satisfaisant le filtre.
+    ///
+    /// # Retour
+    /// - `Option<HashSet<DocumentId>>`: Les documents candidats (le filtre doit encore être vérifié sur chacun),
+    ///   ou `None` si le filtre ne peut pas être résolu par l'index.
+    pub fn candidates(&self, filter: &Filter) -> Option<HashSet<DocumentId>> {
+        match filter {
+            Filter::Compare { field, op: CompareOp::Eq, value } => self.lookup(field, std::slice::from_ref(value)),
+            Filter::In { field, values } => self.lookup(field, values),
+            // Une conjonction est résolue par son terme le plus sélectif.
+            Filter::And(filters) => filters
+                .iter()
+                .filter_map(|filter| self.candidates(filter))
+                .min_by_key(HashSet::len),
+            // Une disjonction n'est résolue que si tous ses termes le sont.
+            Filter::Or(filters) => filters.iter().try_fold(HashSet::new(), |mut union, filter| {
+                union.extend(self.candidates(filter)?);
+                Some(union)
+            }),
+            _ => None,
+        }
+    }
+
+    fn lookup(&self, field: &str, values: &[Value]) -> Option<HashSet<DocumentId>> {
+        let entries = self.fields.get(field)?;
+        let mut documents = HashSet::new();
+        for value in values {
+            // Une valeur non scalaire (tableau, objet) ne peut pas être cherchée dans l'index.
+            let index_key = IndexKey::of(value)?;
+            if let Some(found) = entries.get(&index_key) {
+                documents.extend(found);
+            }
+        }
+        Some(documents)
+    }
+}
+
+/// Les clés d'index d'un champ d'un document (une par élément si le champ est un tableau).
+fn keys_of(payload: &Payload, field: &str) -> Vec<IndexKey> {
+    match lookup(payload, field) {
+        Some(Value::Array(values)) => values.iter().filter_map(IndexKey::of).collect(),
+        Some(value) => IndexKey::of(value).into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn compare(field: &str, op: CompareOp, value: Value) -> Filter {
+        Filter::Compare { field: field.to_string(), op, value }
+    }
+
+    fn eq(field: &str, value: f64) -> Filter {
+        compare(field, CompareOp::Eq, Value::Number(value))
+    }
+
+    fn payload(fields: Vec<(&str, Value)>) -> Payload {
+        fields.into_iter().map(|(field, value)| (field.to_string(), value)).collect()
+    }
+
+    fn strings(values: &[&str]) -> Value {
+        Value::Array(values.iter().map(|value| Value::from(*value)).collect())
+    }
+
+    /// Le message d'erreur de l'analyse d'un filtre invalide.
+    fn error(text: &str) -> String {
+        match Filter::parse(text) {
+            Err(DbError::InvalidFilter(message)) => message,
+            result => panic!("erreur attendue pour {:?} : {:?}", text, result),
+        }
+    }
+
+    /// Les documents candidats d'un filtre, triés.
+    fn candidates(index: &PayloadIndex, text: &str) -> Option<Vec<DocumentId>> {
+        index.candidates(&Filter::parse(text).unwrap()).map(|keys| {
+            let mut keys: Vec<DocumentId> = keys.into_iter().collect();
+            keys.sort();
+            keys
+        })
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_and() {
+        assert_eq!(
+            Filter::parse("a == 1 OR b == 2 AND c == 3").unwrap(),
+            Filter::Or(vec![eq("a", 1.0), Filter::And(vec![eq("b", 2.0), eq("c", 3.0)])])
+        );
+        assert_eq!(
+            Filter::parse("(a == 1 OR b == 2) AND c == 3").unwrap(),
+            Filter::And(vec![Filter::Or(vec![eq("a", 1.0), eq("b", 2.0)]), eq("c", 3.0)])
+        );
+        assert_eq!(
+            Filter::parse("NOT a == 1 AND b == 2").unwrap(),
+            Filter::And(vec![Filter::Not(Box::new(eq("a", 1.0))), eq("b", 2.0)])
+        );
+        assert_eq!(
+            Filter::parse("not not EXISTS a or a != 1").unwrap(),
+            Filter::Or(vec![
+                Filter::Not(Box::new(Filter::Not(Box::new(Filter::Exists("a".to_string()))))),
+                compare("a", CompareOp::Ne, Value::Number(1.0)),
+            ])
+        );
+
+        // Avec a = 1, b = 0, c = 0 : vrai si OR l'emporte sur AND, faux sinon.
+        let document = payload(vec![("a", Value::from(1.0)), ("b", Value::from(0.0)), ("c", Value::from(0.0))]);
+        assert!(Filter::parse("a == 1 OR b == 2 AND c == 3").unwrap().matches(&document));
+        assert!(!Filter::parse("(a == 1 OR b == 2) AND c == 3").unwrap().matches(&document));
+        assert!(!Filter::parse("NOT a == 1 OR b == 2").unwrap().matches(&document));
+        assert!(Filter::parse("NOT (a == 2 OR b == 2)").unwrap().matches(&document));
+    }
+
+    #[test]
+    fn in_lists() {
+        let document = payload(vec![
+            ("client", Value::from("Dupont")),
+            ("tags", strings(&["urgent", "acte"])),
+            ("annee", Value::from(2021.0)),
+            ("adresse", Value::Object(payload(vec![("ville", Value::from("Lyon"))]))),
+        ]);
+        let matches = |text: &str| Filter::parse(text).unwrap().matches(&document);
+
+        assert!(matches(r#"client IN ["Martin", "Dupont"]"#));
+        assert!(!matches(r#"client IN ["Martin"]"#));
+        assert!(!matches("client IN []"));
+        assert!(matches(r#"client NOT IN ["Martin"]"#));
+        assert!(!matches(r#"client not in ["Dupont"]"#));
+        // Un tableau satisfait la condition dès qu'un de ses éléments la satisfait.
+        assert!(matches(r#"tags IN ["acte", "bail"]"#));
+        assert!(matches(r#"tags == "urgent""#));
+        // Les valeurs sont comparées selon leur type : 2021 n'est pas "2021".
+        assert!(matches("annee IN [2020, 2021.0]"));
+        assert!(!matches(r#"annee IN ["2021"]"#));
+        assert!(matches(r#"adresse.ville IN ["Lyon", null]"#));
+        // Un champ absent n'appartient à aucune liste, même celle qui contient null.
+        assert!(!matches("absent IN [null, 1]"));
+        assert!(matches("absent NOT IN [1]"));
+
+        assert_eq!(
+            Filter::parse(r#"x IN [1, "a", true, null]"#).unwrap(),
+            Filter::In {
+                field: "x".to_string(),
+                values: vec![Value::Number(1.0), Value::from("a"), Value::Bool(true), Value::Null],
+            }
+        );
+    }
+
+    #[test]
+    fn comparisons_on_missing_fields_and_mixed_types() {
+        let document = payload(vec![
+            ("annee", Value::from(2021.0)),
+            ("nom", Value::from("Dupont")),
+            ("vide", Value::Null),
+            ("tags", Value::Array(vec![Value::from(1.0), Value::from(5.0)])),
+        ]);
+        let matches = |text: &str| Filter::parse(text).unwrap().matches(&document);
+
+        // `!=` est vrai si le champ est absent ; les autres comparaisons sont fausses.
+        assert!(matches("absent != 1"));
+        assert!(matches(r#"adresse.ville != "Lyon""#));
+        assert!(!matches("absent == 1"));
+        assert!(!matches("absent < 1"));
+        assert!(!matches("absent >= 1"));
+        assert!(!matches("EXISTS absent"));
+        assert!(!matches("EXISTS vide"));
+        assert!(matches("vide == null"));
+        assert!(!matches("vide != null"));
+
+        assert!(matches("annee >= 2021 AND annee < 2022 AND annee != 2020"));
+        assert!(matches(r#"nom > "Dup" AND nom <= "Dupont""#));
+        // Les comparaisons d'ordre entre un nombre et une chaîne sont fausses, `!=` est vrai.
+        assert!(!matches(r#"annee > "2000""#));
+        assert!(!matches(r#"annee < "3000""#));
+        assert!(matches(r#"annee != "2021""#));
+        // Un tableau est différent d'une valeur s'il ne la contient pas.
+        assert!(matches("tags > 4"));
+        assert!(!matches("tags != 5"));
+        assert!(matches("tags != 3"));
+    }
+
+    #[test]
+    fn syntax_errors_report_their_position() {
+        assert_eq!(error("annee >= "), "fin de filtre inattendue (position 9)");
+        assert_eq!(error(r#"type = "acte""#), "opérateur inconnu '=' (position 5)");
+        assert_eq!(error(r#"type == "acte"#), "chaîne non terminée (position 8)");
+        assert_eq!(error(r#"type == "a\q""#), "séquence d'échappement invalide (position 10)");
+        assert_eq!(error("a == 1 )"), "jeton inattendu RParen (position 7)");
+        assert_eq!(error("a == 1 AND"), "fin de filtre inattendue (position 10)");
+        assert_eq!(error("a 1"), "opérateur attendu, Number(1.0) trouvé (position 2)");
+        assert_eq!(error("a == b"), r#"valeur attendue, Ident("b") trouvé (position 5)"#);
+        assert_eq!(error("a IN [1 2]"), "Comma attendu, Number(2.0) trouvé (position 8)");
+        assert_eq!(error("(a == 1"), "fin de filtre inattendue (position 7)");
+        assert_eq!(error("EXISTS 3"), "nom de champ attendu, Number(3.0) trouvé (position 7)");
+        assert_eq!(error("a == 1 @"), "caractère inattendu '@' (position 7)");
+        assert_eq!(error("a == 1-"), "nombre invalide '1-' (position 5)");
+        // Les positions sont des positions d'octets dans le texte.
+        assert_eq!(error("é == 1 )"), "jeton inattendu RParen (position 8)");
+    }
+
+    #[test]
+    fn payload_index_candidates() {
+        let id = Uuid::from_u128;
+        let payloads = HashMap::from([
+            (id(1), payload(vec![("client", Value::from("Dupont")), ("tags", strings(&["a", "b"])), ("montant", Value::from(0.0))])),
+            (id(2), payload(vec![("client", Value::from("Martin")), ("tags", strings(&["b"])), ("montant", Value::from(-0.0))])),
+            (id(3), payload(vec![("client", Value::from("Dupont")), ("montant", Value::from(10.0))])),
+        ]);
+        let mut index = PayloadIndex::default();
+        for field in ["tags", "client", "montant"] {
+            index.create_field(field, &payloads);
+        }
+        assert_eq!(index.fields(), vec!["client", "montant", "tags"]);
+
+        assert_eq!(candidates(&index, r#"client == "Dupont""#), Some(vec![id(1), id(3)]));
+        assert_eq!(candidates(&index, r#"tags IN ["b", "z"]"#), Some(vec![id(1), id(2)]));
+        assert_eq!(candidates(&index, "montant == 0"), Some(vec![id(1), id(2)]));
+        // Une conjonction prend son terme le plus sélectif, même s'il porte sur un autre champ indexé.
+        assert_eq!(candidates(&index, r#"client == "Dupont" AND tags == "a" AND EXISTS x"#), Some(vec![id(1)]));
+        assert_eq!(candidates(&index, r#"client == "Martin" OR montant == 10"#), Some(vec![id(2), id(3)]));
+        // Ni une négation, ni un champ non indexé, ni une disjonction partiellement indexée.
+        assert_eq!(candidates(&index, r#"client != "Dupont""#), None);
+        assert_eq!(candidates(&index, r#"ville == "Lyon""#), None);
+        assert_eq!(candidates(&index, r#"client == "Martin" OR ville == "Lyon""#), None);
+
+        let added = payload(vec![("client", Value::from("Martin"))]);
+        index.insert(id(4), &added);
+        assert_eq!(candidates(&index, r#"client == "Martin""#), Some(vec![id(2), id(4)]));
+        index.remove(&id(2), &payloads[&id(2)]);
+        assert_eq!(candidates(&index, r#"client == "Martin""#), Some(vec![id(4)]));
+        assert_eq!(candidates(&index, "montant == 0"), Some(vec![id(1)]));
+    }
+}
@@ -0,0 +1,436 @@
+//! # Module: `flatbuffers`
+//!
+//! Lecture et écriture des tables FlatBuffers, dans lesquelles Arrow écrit les métadonnées de ses
+//! fichiers : schéma, description de chaque lot de lignes et pied de page.
+//!
+//! Une table commence par la distance (signée) vers sa *vtable*, qui donne la position de chacun de
+//! ses champs dans la table, ou 0 pour un champ absent. Les champs scalaires sont rangés dans la table ;
+//! les chaînes, vecteurs et sous-tables sont désignés par une distance (non signée) vers l'avant.
+
+use crate::error::DbError;
+
+/// # Structure: `Table`
+///
+/// Une table FlatBuffers en lecture ; les champs sont désignés par leur rang dans le schéma.
+#[derive(Debug, Clone, Copy)]
+pub struct Table<'a> {
+    buffer: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Table<'a> {
+    /// La table racine d'un tampon.
+    pub fn root(buffer: &'a [u8]) -> Result<Self, DbError> {
+        let position = read_u32(buffer, 0)? as usize;
+        Table::at(buffer, position)
+    }
+
+    fn at(buffer: &'a [u8], position: usize) -> Result<Self, DbError> {
+        if position + 4 > buffer.len() {
+            return Err(corrupted("table hors du tampon"));
+        }
+        Ok(Table { buffer, position })
+    }
+
+    /// La position absolue du champ de rang `slot`, s'il est présent.
+    fn field(&self, slot: usize) -> Result<Option<usize>, DbError> {
+        let vtable = self.position as i64 - read_u32(self.buffer, self.position)? as i32 as i64;
+        if vtable < 0 {
+            return Err(corrupted("vtable hors du tampon"));
+        }
+        let vtable = vtable as usize;
+        let size = read_u16(self.buffer, vtable)? as usize;
+        if 4 + 2 * slot + 2 > size {
+            return Ok(None);
+        }
+        match read_u16(self.buffer, vtable + 4 + 2 * slot)? as usize {
+            0 => Ok(None),
+            offset => Ok(Some(self.position + offset)),
+        }
+    }
+
+    /// Le champ de rang `slot` sur `N` octets, ou `None` s'il est absent.
+    fn scalar<const N: usize>(&self, slot: usize) -> Result<Option<[u8; N]>, DbError> {
+        match self.field(slot)? {
+            Some(position) => Ok(Some(
+                self.buffer
+                    .get(position..position + N)
+                    .ok_or_else(|| corrupted("champ hors du tampon"))?
+                    .try_into()
+                    .unwrap(),
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Le champ booléen de rang `slot`, faux s'il est absent.
+    pub fn bool(&self, slot: usize) -> Result<bool, DbError> {
+        Ok(self.scalar::<1>(slot)?.is_some_and(|value| value[0] != 0))
+    }
+
+    /// Le champ `u8` de rang `slot` (le type d'une union), 0 s'il est absent.
+    pub fn u8(&self, slot: usize) -> Result<u8, DbError> {
+        Ok(self.scalar::<1>(slot)?.map_or(0, |value| value[0]))
+    }
+
+    /// Le champ `i16` de rang `slot`, `default` s'il est absent.
+    pub fn i16(&self, slot: usize, default: i16) -> Result<i16, DbError> {
+        Ok(self.scalar(slot)?.map_or(default, i16::from_le_bytes))
+    }
+
+    pub fn i32(&self, slot: usize, default: i32) -> Result<i32, DbError> {
+        Ok(self.scalar(slot)?.map_or(default, i32::from_le_bytes))
+    }
+
+    pub fn i64(&self, slot: usize, default: i64) -> Result<i64, DbError> {
+        Ok(self.scalar(slot)?.map_or(default, i64::from_le_bytes))
+    }
+
+    /// La position désignée par le champ de rang `slot`, une distance vers l'avant.
+    fn target(&self, slot: usize) -> Result<Option<usize>, DbError> {
+        match self.field(slot)? {
+            Some(position) => Ok(Some(position + read_u32(self.buffer, position)? as usize)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn table(&self, slot: usize) -> Result<Option<Table<'a>>, DbError> {
+        self.target(slot)?.map(|position| Table::at(self.buffer, position)).transpose()
+    }
+
+    pub fn string(&self, slot: usize) -> Result<Option<&'a str>, DbError> {
+        match self.target(slot)? {
+            Some(position) => {
+                let length = read_u32(self.buffer, position)? as usize;
+                let bytes = self
+                    .buffer
+                    .get(position + 4..position + 4 + length)
+                    .ok_or_else(|| corrupted("chaîne hors du tampon"))?;
+                Ok(Some(std::str::from_utf8(bytes).map_err(|_| corrupted("chaîne invalide"))?))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Le vecteur de tables du champ de rang `slot` ; vide s'il est absent.
+    pub fn tables(&self, slot: usize) -> Result<Vec<Table<'a>>, DbError> {
+        let Some(position) = self.target(slot)? else {
+            return Ok(Vec::new());
+        };
+        let length = read_u32(self.buffer, position)? as usize;
+        (0..length)
+            .map(|index| {
+                let element = position + 4 + 4 * index;
+                Table::at(self.buffer, element + read_u32(self.buffer, element)? as usize)
+            })
+            .collect()
+    }
+
+    /// Le vecteur de structures de `size` octets du champ de rang `slot` ; vide s'il est absent.
+    pub fn structs(&self, slot: usize, size: usize) -> Result<Vec<&'a [u8]>, DbError> {
+        let Some(position) = self.target(slot)? else {
+            return Ok(Vec::new());
+        };
+        let length = read_u32(self.buffer, position)? as usize;
+        let bytes = length
+            .checked_mul(size)
+            .and_then(|bytes| self.buffer.get(position + 4..position + 4 + bytes))
+            .ok_or_else(|| corrupted("vecteur hors du tampon"))?;
+        Ok(bytes.chunks_exact(size).collect())
+    }
+}
+
+/// # Énumération: `Value`
+///
+/// La valeur d'un champ d'une table à écrire.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Bool(bool),
+    U8(u8),
+    I16(i16),
+    I32(i32),
+    I64(i64),
+    String(String),
+    Table(Builder),
+    Tables(Vec<Builder>),
+    /// Un vecteur de structures, déjà encodées bout à bout, alignées sur `align` octets.
+    Structs { bytes: Vec<u8>, size: usize, align: usize },
+}
+
+impl Value {
+    /// La taille du champ dans la table : celle du scalaire, ou celle d'une distance.
+    fn size(&self) -> usize {
+        match self {
+            Value::Bool(_) | Value::U8(_) => 1,
+            Value::I16(_) => 2,
+            Value::I32(_) => 4,
+            Value::I64(_) => 8,
+            _ => 4,
+        }
+    }
+}
+
+/// # Structure: `Builder`
+///
+/// Une table à écrire : ses champs, avec leur rang dans le schéma.
+///
+/// # Exemple
+///
+/// ```
+/// let field = Builder::new().add(0, Value::String("vector".to_string())).add(1, Value::Bool(false));
+/// let bytes = field.finish();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Builder {
+    fields: Vec<(usize, Value)>,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Builder::default()
+    }
+
+    pub fn add(mut self, slot: usize, value: Value) -> Self {
+        self.fields.push((slot, value));
+        self
+    }
+
+    /// Encode la table comme racine d'un tampon, dont la taille est un multiple de 8.
+    pub fn finish(&self) -> Vec<u8> {
+        let mut output = vec![0; 4];
+        let root = self.write(&mut output);
+        output[..4].copy_from_slice(&(root as u32).to_le_bytes());
+        pad(&mut output, 8);
+        output
+    }
+
+    /// Écrit la vtable, puis la table, puis les valeurs qu'elle désigne, et retourne la position de la table.
+    ///
+    /// Les valeurs désignées sont écrites après la table, si bien que toutes les distances sont positives.
+    fn write(&self, output: &mut Vec<u8>) -> usize {
+        let mut fields: Vec<&(usize, Value)> = self.fields.iter().collect();
+        fields.sort_by_key(|(_, value)| std::cmp::Reverse(value.size()));
+        let mut offsets = Vec::with_capacity(fields.len());
+        let mut size = 4usize;
+        for (_, value) in &fields {
+            size = size.next_multiple_of(value.size());
+            offsets.push(size);
+            size += value.size();
+        }
+
+        let slots = self.fields.iter().map(|(slot, _)| slot + 1).max().unwrap_or(0);
+        pad(output, 2);
+        let vtable = output.len();
+        output.extend(((4 + 2 * slots) as u16).to_le_bytes());
+        output.extend((size as u16).to_le_bytes());
+        let mut entries = vec![0u16; slots];
+        for ((slot, _), offset) in fields.iter().zip(&offsets) {
+            entries[*slot] = *offset as u16;
+        }
+        output.extend(entries.iter().flat_map(|entry| entry.to_le_bytes()));
+
+        pad(output, 8);
+        let table = output.len();
+        output.resize(table + size, 0);
+        output[table..table + 4].copy_from_slice(&((table - vtable) as i32).to_le_bytes());
+        let mut references = Vec::new();
+        for ((_, value), offset) in fields.iter().zip(&offsets) {
+            let position = table + offset;
+            match value {
+                Value::Bool(value) => output[position] = *value as u8,
+                Value::U8(value) => output[position] = *value,
+                Value::I16(value) => output[position..position + 2].copy_from_slice(&value.to_le_bytes()),
+                Value::I32(value) => output[position..position + 4].copy_from_slice(&value.to_le_bytes()),
+                Value::I64(value) => output[position..position + 8].copy_from_slice(&value.to_le_bytes()),
+                value => references.push((position, value)),
+            }
+        }
+        for (position, value) in references {
+            let target = match value {
+                Value::String(text) => {
+                    pad(output, 4);
+                    let target = output.len();
+                    output.extend((text.len() as u32).to_le_bytes());
+                    output.extend(text.as_bytes());
+                    output.push(0);
+                    target
+                }
+                Value::Table(table) => table.write(output),
+                Value::Tables(tables) => {
+                    pad(output, 4);
+                    let target = output.len();
+                    output.extend((tables.len() as u32).to_le_bytes());
+                    output.resize(target + 4 + 4 * tables.len(), 0);
+                    for (index, table) in tables.iter().enumerate() {
+                        let element = target + 4 + 4 * index;
+                        let position = table.write(output);
+                        patch(output, element, position);
+                    }
+                    target
+                }
+                Value::Structs { bytes, size, align } => {
+                    pad(output, 4);
+                    while !(output.len() + 4).is_multiple_of(*align) {
+                        output.push(0);
+                    }
+                    let target = output.len();
+                    output.extend(((bytes.len() / size) as u32).to_le_bytes());
+                    output.extend(bytes);
+                    target
+                }
+                _ => unreachable!("les scalaires sont rangés dans la table"),
+            };
+            patch(output, position, target);
+        }
+        table
+    }
+}
+
+/// Écrit à `position` la distance vers `target`.
+fn patch(output: &mut [u8], position: usize, target: usize) {
+    output[position..position + 4].copy_from_slice(&((target - position) as u32).to_le_bytes());
+}
+
+fn pad(output: &mut Vec<u8>, align: usize) {
+    output.resize(output.len().next_multiple_of(align), 0);
+}
+
+fn read_u16(buffer: &[u8], position: usize) -> Result<u16, DbError> {
+    Ok(u16::from_le_bytes(
+        buffer.get(position..position + 2).ok_or_else(|| corrupted("lecture hors du tampon"))?.try_into().unwrap(),
+    ))
+}
+
+fn read_u32(buffer: &[u8], position: usize) -> Result<u32, DbError> {
+    Ok(u32::from_le_bytes(
+        buffer.get(position..position + 4).ok_or_else(|| corrupted("lecture hors du tampon"))?.try_into().unwrap(),
+    ))
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("métadonnées Arrow : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Vec<u8> {
+        Builder::new()
+            .add(0, Value::Bool(true))
+            .add(1, Value::U8(3))
+            .add(2, Value::I16(-2))
+            .add(3, Value::I32(1 << 20))
+            .add(4, Value::I64(-1))
+            .add(5, Value::String("vecteur".to_string()))
+            .add(6, Value::Table(Builder::new().add(1, Value::String("item".to_string()))))
+            .add(7, Value::Tables(vec![Builder::new().add(0, Value::I32(1)), Builder::new()]))
+            .add(9, Value::Structs { bytes: (0u8..32).collect(), size: 16, align: 8 })
+            .finish()
+    }
+
+    /// Lit tous les champs des huit premiers rangs, et ceux des sous-tables, en ignorant les erreurs.
+    fn visit(table: Table, depth: usize) {
+        for slot in 0..10 {
+            let _ = (table.bool(slot), table.u8(slot), table.i16(slot, 0), table.i32(slot, 0), table.i64(slot, 0));
+            let _ = (table.string(slot), table.structs(slot, 16));
+            if depth < 3 {
+                table.table(slot).into_iter().flatten().for_each(|table| visit(table, depth + 1));
+                table.tables(slot).into_iter().flatten().for_each(|table| visit(table, depth + 1));
+            }
+        }
+    }
+
+    #[test]
+    fn reads_what_the_builder_writes() {
+        let bytes = sample();
+        assert_eq!(bytes.len() % 8, 0);
+        let table = Table::root(&bytes).unwrap();
+        assert!(table.bool(0).unwrap());
+        assert_eq!(table.u8(1).unwrap(), 3);
+        assert_eq!(table.i16(2, 0).unwrap(), -2);
+        assert_eq!(table.i32(3, 0).unwrap(), 1 << 20);
+        assert_eq!(table.i64(4, 0).unwrap(), -1);
+        assert_eq!(table.string(5).unwrap(), Some("vecteur"));
+        assert_eq!(table.table(6).unwrap().unwrap().string(1).unwrap(), Some("item"));
+        let tables = table.tables(7).unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].i32(0, 0).unwrap(), 1);
+        assert_eq!(tables[1].i32(0, 5).unwrap(), 5);
+        let structs = table.structs(9, 16).unwrap();
+        assert_eq!(structs, [&(0u8..16).collect::<Vec<_>>()[..], &(16u8..32).collect::<Vec<_>>()[..]]);
+        // Un champ absent, dans la vtable (rang 8) ou au-delà (rang 12), prend sa valeur par défaut.
+        assert_eq!(table.i32(8, 9).unwrap(), 9);
+        assert_eq!(table.i32(12, 9).unwrap(), 9);
+        assert_eq!(table.string(12).unwrap(), None);
+        assert!(table.tables(12).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_vtables_on_either_side_of_their_table() {
+        // La vtable avant la table, comme l'écrit le constructeur de référence pour une nouvelle vtable :
+        // { 0: i32 42, 1: "hi" }.
+        let before = [
+            12, 0, 0, 0, 8, 0, 12, 0, 4, 0, 8, 0, 8, 0, 0, 0, 42, 0, 0, 0, 4, 0, 0, 0, 2, 0, 0, 0, b'h', b'i', 0, 0,
+        ];
+        let table = Table::root(&before).unwrap();
+        assert_eq!(table.i32(0, 0).unwrap(), 42);
+        assert_eq!(table.string(1).unwrap(), Some("hi"));
+        // La vtable après la table (distance négative), comme pour une vtable partagée : { 0: i32 7 }.
+        let after = [4, 0, 0, 0, 0xf4, 0xff, 0xff, 0xff, 7, 0, 0, 0, 0, 0, 0, 0, 6, 0, 8, 0, 4, 0];
+        let table = Table::root(&after).unwrap();
+        assert_eq!(table.i32(0, 0).unwrap(), 7);
+        assert_eq!(table.i32(1, 9).unwrap(), 9);
+    }
+
+    #[test]
+    fn out_of_bounds_references_are_corrupted() {
+        fn corrupted<T>(result: Result<T, DbError>) -> bool {
+            matches!(result, Err(DbError::Corrupted(_)))
+        }
+        assert!(corrupted(Table::root(&[])));
+        assert!(corrupted(Table::root(&[0xff, 0xff, 0, 0])));
+        // Une vtable avant le début du tampon.
+        assert!(corrupted(Table::root(&[4, 0, 0, 0, 8, 0, 0, 0]).unwrap().i32(0, 0)));
+        // Une vtable après sa fin.
+        assert!(corrupted(Table::root(&[4, 0, 0, 0, 0xf0, 0xff, 0xff, 0xff]).unwrap().i32(0, 0)));
+        // Un champ qui dépasse la fin du tampon.
+        assert!(corrupted(Table::root(&[8, 0, 0, 0, 6, 0, 8, 0, 4, 0, 0, 0]).unwrap().i64(0, 0)));
+
+        let mut bytes = sample();
+        let table = Table::root(&bytes).unwrap();
+        let string = table.target(5).unwrap().unwrap();
+        bytes[string..string + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(corrupted(Table::root(&bytes).unwrap().string(5)));
+        bytes[string..string + 4].copy_from_slice(&1u32.to_le_bytes());
+        bytes[string + 4] = 0xff;
+        assert!(corrupted(Table::root(&bytes).unwrap().string(5)));
+
+        let mut bytes = sample();
+        let structs = Table::root(&bytes).unwrap().target(9).unwrap().unwrap();
+        bytes[structs..structs + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(corrupted(Table::root(&bytes).unwrap().structs(9, 16)));
+        let tables = Table::root(&bytes).unwrap().target(7).unwrap().unwrap();
+        bytes[tables..tables + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(corrupted(Table::root(&bytes).unwrap().tables(7)));
+    }
+
+    #[test]
+    fn truncated_or_corrupted_buffers_never_panic() {
+        let bytes = sample();
+        for length in 0..bytes.len() {
+            if let Ok(table) = Table::root(&bytes[..length]) {
+                visit(table, 0);
+            }
+        }
+        for position in 0..bytes.len() {
+            for byte in [0x00, 0x01, 0x7f, 0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[position] = byte;
+                if let Ok(table) = Table::root(&corrupted) {
+                    visit(table, 0);
+                }
+            }
+        }
+    }
+}
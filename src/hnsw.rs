@@ -0,0 +1,627 @@
+//! # Module: `hnsw`
+//!
+//! Index HNSW (*Hierarchical Navigable Small World*) pour la recherche approximative
+//! des plus proches voisins dans une [`Collection`](crate::Collection).
+//!
+//! L'index ne stocke pas de copie des vecteurs : il ne conserve que le graphe de voisinage
+//! et relit les vecteurs dans les données de la collection au moment des calculs, en comparant
+//! les vecteurs avec la [`Metric`] de la collection.
+//! Les documents supprimés deviennent des « pierres tombales » : leur nœud reste dans le graphe
+//! pour la navigation (avec une copie de leur ancien vecteur) mais n'apparaît plus dans les résultats.
+//! Lorsque les nœuds supprimés deviennent plus nombreux que les nœuds vivants, le graphe est reconstruit.
+//! Chaque nœud garde la norme de son vecteur, calculée une fois à l'insertion, pour que les distances
+//! cosinus se réduisent à un produit scalaire pendant le parcours.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::metric::Metric;
+use crate::rng::SplitMix64;
+use crate::topk::TopK;
+use crate::vectors::VectorStore;
+use crate::{simd, DocumentId, Vector};
+
+/// Graine du générateur pseudo-aléatoire utilisé pour tirer le niveau des nœuds.
+/// Une graine fixe rend la construction du graphe reproductible.
+const LEVEL_SEED: u64 = 0x5EED_4E53_0000_0001;
+
+/// # Structure: `HnswParams`
+///
+/// Paramètres de construction et de recherche d'un [`HnswIndex`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HnswParams {
+    /// Nombre maximal de voisins d'un nœud sur les couches supérieures (`M`).
+    /// La couche 0 en conserve jusqu'à `2 * M`.
+    pub m: usize,
+    /// Taille de la liste de candidats explorée lors d'une insertion (`ef_construction`).
+    pub ef_construction: usize,
+    /// Taille de la liste de candidats explorée lors d'une recherche (`ef_search`).
+    /// Elle est toujours au moins égale au `k` demandé.
+    pub ef_search: usize,
+}
+
+impl Default for HnswParams {
+    fn default() -> Self {
+        HnswParams {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 50,
+        }
+    }
+}
+
+/// Un nœud candidat lors du parcours du graphe, ordonné par distance croissante
+/// (puis par numéro de nœud pour obtenir un ordre total). Une distance NaN compte comme infinie.
+#[derive(Debug, Clone, Copy)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let distance = |candidate: &Candidate| {
+            if candidate.distance.is_nan() {
+                f32::INFINITY
+            } else {
+                candidate.distance
+            }
+        };
+        distance(self)
+            .total_cmp(&distance(other))
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// Un nœud du graphe : le document qu'il représente et ses voisins sur chaque couche.
+struct Node {
+    id: DocumentId,
+    /// `links[couche]` contient les numéros des nœuds voisins sur cette couche.
+    links: Vec<Vec<usize>>,
+    /// Norme du vecteur du document, calculée à l'insertion.
+    norm: f32,
+    deleted: bool,
+}
+
+/// Un vecteur comparé aux nœuds du graphe, accompagné de sa norme.
+#[derive(Clone, Copy)]
+struct Query<'a> {
+    vector: &'a [f32],
+    norm: f32,
+}
+
+impl<'a> Query<'a> {
+    fn new(vector: &'a [f32]) -> Self {
+        Query {
+            vector,
+            norm: simd::norm(vector),
+        }
+    }
+}
+
+/// # Structure: `HnswIndex`
+///
+/// Graphe HNSW construit au-dessus des vecteurs d'une [`Collection`](crate::Collection).
+pub struct HnswIndex {
+    params: HnswParams,
+    /// La métrique de la collection, convertie en distance pour la navigation dans le graphe.
+    metric: Metric,
+    nodes: Vec<Node>,
+    /// Correspondance entre un document vivant et son nœud.
+    ids: HashMap<DocumentId, usize>,
+    /// Vecteurs des nœuds supprimés, conservés tant que le nœud sert à la navigation.
+    removed: HashMap<usize, Vec<f32>>,
+    entry_point: Option<usize>,
+    level_mult: f64,
+    rng: SplitMix64,
+}
+
+impl HnswIndex {
+    /// Crée un index HNSW vide.
+    ///
+    /// # Paramètres
+    /// - `params`: Les paramètres `M`, `ef_construction` et `ef_search` de l'index.
+    /// - `metric`: La métrique de la collection indexée.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let index = HnswIndex::new(HnswParams::default(), Metric::Cosine);
+    /// ```
+    pub fn new(params: HnswParams, metric: Metric) -> Self {
+        let params = HnswParams {
+            m: params.m.max(2),
+            ef_construction: params.ef_construction.max(1),
+            ef_search: params.ef_search.max(1),
+        };
+        HnswIndex {
+            params,
+            metric,
+            nodes: Vec::new(),
+            ids: HashMap::new(),
+            removed: HashMap::new(),
+            entry_point: None,
+            level_mult: 1.0 / (params.m as f64).ln(),
+            rng: SplitMix64::new(LEVEL_SEED),
+        }
+    }
+
+    /// Construit un index HNSW contenant tous les vecteurs fournis.
+    ///
+    /// # Paramètres
+    /// - `params`: Les paramètres de l'index.
+    /// - `metric`: La métrique de la collection indexée.
+    /// - `vectors`: Les vecteurs de la collection à indexer.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let index = HnswIndex::build(HnswParams::default(), Metric::Cosine, &collection.data);
+    /// ```
+    pub fn build(params: HnswParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let mut index = HnswIndex::new(params, metric);
+        // Ordre d'insertion trié pour que la construction ne dépende pas de l'ordre du `HashMap`.
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        for key in keys {
+            index.insert(key, vectors);
+        }
+        index
+    }
+
+    /// Les paramètres de l'index, avec le `ef_search` actuel.
+    pub fn params(&self) -> HnswParams {
+        self.params
+    }
+
+    /// Modifie `ef_search` sans reconstruire le graphe.
+    pub fn set_ef_search(&mut self, ef_search: usize) {
+        self.params.ef_search = ef_search.max(1);
+    }
+
+    /// Ajoute au graphe le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    ///
+    /// Si le document était déjà indexé, il faut d'abord appeler [`HnswIndex::remove`]. Un document
+    /// encore indexé est ignoré : c'est le cas lorsque ce retrait a reconstruit le graphe à partir
+    /// de `vectors`, qui contenait déjà le nouveau vecteur.
+    ///
+    /// # Paramètres
+    /// - `key`: L'identifiant du document à indexer.
+    /// - `vectors`: Les vecteurs de la collection.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        if self.ids.contains_key(&key) {
+            return;
+        }
+        let query = match vectors.get(&key) {
+            Some(vector) => Query::new(vector),
+            None => return,
+        };
+        let level = (-self.rng.next_unit().ln() * self.level_mult).floor() as usize;
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            id: key,
+            links: vec![Vec::new(); level + 1],
+            norm: query.norm,
+            deleted: false,
+        });
+        self.ids.insert(key, node);
+
+        let entry = match self.entry_point {
+            Some(entry) => entry,
+            None => {
+                self.entry_point = Some(node);
+                return;
+            }
+        };
+        let top_level = self.nodes[entry].links.len() - 1;
+
+        // Descente gloutonne sur les couches situées au-dessus du niveau du nouveau nœud.
+        let mut nearest = Candidate {
+            distance: self.distance(&query, entry, vectors),
+            node: entry,
+        };
+        for layer in (level + 1..=top_level).rev() {
+            nearest = self.search_layer(&query, &[nearest], 1, layer, vectors, &|_| true)[0];
+        }
+
+        // Connexion du nœud sur chacune des couches qu'il partage avec le graphe existant.
+        let mut entry_points = vec![nearest];
+        for layer in (0..=level.min(top_level)).rev() {
+            let found = self.search_layer(&query, &entry_points, self.params.ef_construction, layer, vectors, &|_| true);
+            let neighbours = self.select_neighbours(&found, self.params.m, vectors);
+            self.nodes[node].links[layer] = neighbours.clone();
+
+            let max_links = self.max_links(layer);
+            for neighbour in neighbours {
+                self.nodes[neighbour].links[layer].push(node);
+                if self.nodes[neighbour].links[layer].len() > max_links {
+                    self.shrink_links(neighbour, layer, max_links, vectors);
+                }
+            }
+            entry_points = found;
+        }
+
+        if level > top_level {
+            self.entry_point = Some(node);
+        }
+    }
+
+    /// Ajoute au graphe un lot de documents, dont les vecteurs doivent déjà se trouver dans `vectors`.
+    ///
+    /// Les documents déjà indexés sont ignorés. Si le lot est plus grand que le graphe existant,
+    /// le graphe est reconstruit en une fois plutôt que d'y insérer les documents un à un.
+    ///
+    /// # Paramètres
+    /// - `keys`: Les identifiants des documents à indexer.
+    /// - `vectors`: Les vecteurs de la collection.
+    pub fn insert_many(&mut self, keys: &[DocumentId], vectors: &VectorStore) {
+        let mut keys: Vec<DocumentId> = keys.iter().copied().filter(|key| !self.ids.contains_key(key)).collect();
+        if keys.len() > self.ids.len() {
+            *self = HnswIndex::build(self.params, self.metric, vectors);
+            return;
+        }
+        // Ordre d'insertion trié, comme dans `build`, pour que le graphe ne dépende pas de l'ordre du lot.
+        keys.sort();
+        keys.dedup();
+        for key in keys {
+            self.insert(key, vectors);
+        }
+    }
+
+    /// Retire un document des résultats de l'index.
+    ///
+    /// Le nœud reste dans le graphe pour ne pas casser la navigation ; il garde pour cela
+    /// une copie de l'ancien vecteur. Le graphe est reconstruit dès que les nœuds supprimés
+    /// sont plus nombreux que les nœuds vivants.
+    ///
+    /// # Paramètres
+    /// - `key`: L'identifiant du document retiré.
+    /// - `old_vector`: Le vecteur que le document avait dans la collection.
+    /// - `vectors`: Les vecteurs restants de la collection.
+    pub fn remove(&mut self, key: &DocumentId, old_vector: Vec<f32>, vectors: &VectorStore) {
+        let node = match self.ids.remove(key) {
+            Some(node) => node,
+            None => return,
+        };
+        self.nodes[node].deleted = true;
+        self.removed.insert(node, old_vector);
+
+        if self.removed.len() > self.ids.len() {
+            *self = HnswIndex::build(self.params, self.metric, vectors);
+        }
+    }
+
+    /// Retire un lot de documents des résultats de l'index (voir [`HnswIndex::remove`]).
+    /// Le graphe est reconstruit au plus une fois, après le retrait de tout le lot.
+    ///
+    /// # Paramètres
+    /// - `removed`: Les documents retirés, avec le vecteur qu'ils avaient dans la collection.
+    /// - `vectors`: Les vecteurs restants de la collection.
+    pub fn remove_many(&mut self, removed: Vec<(DocumentId, Vec<f32>)>, vectors: &VectorStore) {
+        for (key, old_vector) in removed {
+            if let Some(node) = self.ids.remove(&key) {
+                self.nodes[node].deleted = true;
+                self.removed.insert(node, old_vector);
+            }
+        }
+
+        if self.removed.len() > self.ids.len() {
+            *self = HnswIndex::build(self.params, self.metric, vectors);
+        }
+    }
+
+    /// Recherche les `k` documents approximativement les plus similaires à `query`.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `vectors`: Les vecteurs de la collection.
+    /// - `accept`: Un prédicat optionnel sur les documents : le graphe est parcouru normalement,
+    ///   mais seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score, du plus proche au plus éloigné selon la métrique,
+    ///   les scores égaux étant départagés par identifiant comme pour la recherche exacte.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &VectorStore,
+        accept: Option<&dyn Fn(&DocumentId) -> bool>,
+    ) -> Vector {
+        let entry = match self.entry_point {
+            Some(entry) if k > 0 => entry,
+            _ => return Vec::new(),
+        };
+        let top_level = self.nodes[entry].links.len() - 1;
+        let query = Query::new(query);
+
+        let mut nearest = Candidate {
+            distance: self.distance(&query, entry, vectors),
+            node: entry,
+        };
+        for layer in (1..=top_level).rev() {
+            nearest = self.search_layer(&query, &[nearest], 1, layer, vectors, &|_| true)[0];
+        }
+
+        let ef = self.params.ef_search.max(k);
+        let accept_node = |node: usize| {
+            let node = &self.nodes[node];
+            !node.deleted && accept.is_none_or(|accept| accept(&node.id))
+        };
+        let mut top = TopK::new(self.metric, k);
+        for candidate in self.search_layer(&query, &[nearest], ef, 0, vectors, &accept_node) {
+            top.push(self.nodes[candidate.node].id, self.metric.distance_to_score(candidate.distance));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Distance utilisée dans le graphe entre `query` et un nœud : la distance de la métrique, ou l'opposé
+    /// de sa similarité, de sorte que le score d'un résultat se retrouve exactement à partir de la distance.
+    fn distance(&self, query: &Query, node: usize, vectors: &VectorStore) -> f32 {
+        let score = self
+            .metric
+            .score_with_norms(query.vector, query.norm, self.vector(node, vectors), self.nodes[node].norm);
+        self.metric.score_to_distance(score)
+    }
+
+    /// Vecteur d'un nœud et sa norme, pour le comparer aux autres nœuds.
+    fn query<'a>(&'a self, node: usize, vectors: &'a VectorStore) -> Query<'a> {
+        Query {
+            vector: self.vector(node, vectors),
+            norm: self.nodes[node].norm,
+        }
+    }
+
+    /// Nombre maximal de voisins d'un nœud sur la couche `layer`.
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 {
+            self.params.m * 2
+        } else {
+            self.params.m
+        }
+    }
+
+    /// Vecteur associé à un nœud, qu'il soit vivant ou supprimé.
+    fn vector<'a>(&'a self, node: usize, vectors: &'a VectorStore) -> &'a [f32] {
+        match self.removed.get(&node) {
+            Some(vector) => vector,
+            None => vectors.get(&self.nodes[node].id).unwrap_or(&[]),
+        }
+    }
+
+    /// Recherche gloutonne sur une couche (algorithme 2 de l'article HNSW).
+    ///
+    /// Tous les nœuds servent à la navigation, mais seuls ceux acceptés par `accept`
+    /// sont retenus dans les résultats.
+    ///
+    /// # Retour
+    /// - `Vec<Candidate>`: Au plus `ef` nœuds acceptés, triés par distance croissante à `query`.
+    fn search_layer(
+        &self,
+        query: &Query,
+        entry_points: &[Candidate],
+        ef: usize,
+        layer: usize,
+        vectors: &VectorStore,
+        accept: &dyn Fn(usize) -> bool,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().map(|c| c.node).collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> = entry_points.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Candidate> = entry_points.iter().copied().filter(|c| accept(c.node)).collect();
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+            if current.distance > furthest && found.len() >= ef {
+                break;
+            }
+            for &neighbour in &self.nodes[current.node].links[layer] {
+                if !visited.insert(neighbour) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: self.distance(query, neighbour, vectors),
+                    node: neighbour,
+                };
+                let furthest = found.peek().map_or(f32::INFINITY, |c| c.distance);
+                if found.len() < ef || candidate.distance < furthest {
+                    candidates.push(Reverse(candidate));
+                    if accept(neighbour) {
+                        found.push(candidate);
+                        if found.len() > ef {
+                            found.pop();
+                        }
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Sélectionne au plus `m` voisins parmi `candidates` (triés par distance croissante)
+    /// avec l'heuristique de diversité de l'article HNSW (algorithme 4) : un candidat n'est retenu
+    /// que s'il est plus proche du nœud que de tous les voisins déjà retenus. Les places restantes
+    /// sont complétées avec les candidats écartés.
+    fn select_neighbours(
+        &self,
+        candidates: &[Candidate],
+        m: usize,
+        vectors: &VectorStore,
+    ) -> Vec<usize> {
+        let mut selected: Vec<usize> = Vec::with_capacity(m);
+        let mut pruned: Vec<usize> = Vec::new();
+        for candidate in candidates {
+            if selected.len() >= m {
+                break;
+            }
+            let candidate_query = self.query(candidate.node, vectors);
+            let diverse = selected
+                .iter()
+                .all(|&kept| self.distance(&candidate_query, kept, vectors) > candidate.distance);
+            if diverse {
+                selected.push(candidate.node);
+            } else {
+                pruned.push(candidate.node);
+            }
+        }
+        let missing = m.saturating_sub(selected.len());
+        selected.extend(pruned.into_iter().take(missing));
+        selected
+    }
+
+    /// Réduit la liste de voisins de `node` sur `layer` à `max_links` éléments.
+    fn shrink_links(&mut self, node: usize, layer: usize, max_links: usize, vectors: &VectorStore) {
+        let origin = self.query(node, vectors);
+        let mut candidates: Vec<Candidate> = self.nodes[node].links[layer]
+            .iter()
+            .map(|&neighbour| Candidate {
+                distance: self.distance(&origin, neighbour, vectors),
+                node: neighbour,
+            })
+            .collect();
+        candidates.sort();
+        let kept = self.select_neighbours(&candidates, max_links, vectors);
+        self.nodes[node].links[layer] = kept;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    fn random_vectors(count: usize, dimension: usize, seed: u64) -> VectorStore {
+        let mut rng = SplitMix64::new(seed);
+        let mut vectors = VectorStore::default();
+        for n in 0..count {
+            let vector = (0..dimension).map(|_| rng.next_gaussian() as f32).collect();
+            vectors.insert(id(n as u128), vector).unwrap();
+        }
+        vectors
+    }
+
+    fn exact(metric: Metric, vectors: &VectorStore, query: &[f32], k: usize) -> Vector {
+        let mut top = TopK::new(metric, k);
+        for (key, vector) in vectors.iter() {
+            top.push(*key, metric.score(query, vector));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Proportion des `k` vrais plus proches voisins retrouvés par l'index, sur plusieurs requêtes.
+    fn recall(index: &HnswIndex, vectors: &VectorStore, k: usize) -> f64 {
+        let queries = random_vectors(20, 16, 99);
+        let mut found = 0;
+        for query in queries.values() {
+            let expected: HashSet<DocumentId> = exact(index.metric, vectors, query, k).into_iter().map(|(key, _)| key).collect();
+            found += index.search(query, k, vectors, None).iter().filter(|(key, _)| expected.contains(key)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn search_matches_the_exact_scan() {
+        let vectors = random_vectors(500, 16, 1);
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot] {
+            let index = HnswIndex::build(HnswParams::default(), metric, &vectors);
+            assert!(recall(&index, &vectors, 10) >= 0.95, "rappel trop faible pour {}", metric);
+
+            // Les scores sont ceux de la métrique, dans l'ordre de la recherche exacte.
+            let query = vectors.get(&id(7)).unwrap();
+            let hits = index.search(query, 5, &vectors, None);
+            assert_eq!(hits, exact(metric, &vectors, query, 5));
+        }
+    }
+
+    #[test]
+    fn larger_ef_search_improves_recall() {
+        let vectors = random_vectors(2_000, 16, 2);
+        let params = HnswParams { m: 4, ef_construction: 16, ef_search: 1 };
+        let mut index = HnswIndex::build(params, Metric::Euclidean, &vectors);
+        let narrow = recall(&index, &vectors, 10);
+        index.set_ef_search(200);
+        let wide = recall(&index, &vectors, 10);
+        assert!(wide > narrow, "ef_search 200 : {}, ef_search 1 : {}", wide, narrow);
+        assert!(wide >= 0.9);
+    }
+
+    #[test]
+    fn insert_and_remove_update_the_results() {
+        let mut vectors = random_vectors(100, 16, 3);
+        let mut index = HnswIndex::build(HnswParams::default(), Metric::Cosine, &vectors);
+
+        let query = vec![1.0; 16];
+        vectors.insert(id(1_000), query.clone()).unwrap();
+        index.insert(id(1_000), &vectors);
+        assert_eq!(index.search(&query, 1, &vectors, None)[0].0, id(1_000));
+
+        let old_vector = vectors.remove(&id(1_000)).unwrap();
+        index.remove(&id(1_000), old_vector, &vectors);
+        let hits = index.search(&query, 100, &vectors, None);
+        assert_eq!(hits.len(), 100);
+        assert!(hits.iter().all(|(key, _)| *key != id(1_000)));
+
+        let accept = |key: &DocumentId| key.as_u128().is_multiple_of(2);
+        let hits = index.search(&query, 10, &vectors, Some(&accept));
+        assert_eq!(hits.len(), 10);
+        assert!(hits.iter().all(|(key, _)| accept(key)));
+    }
+
+    #[test]
+    fn update_that_rebuilds_the_graph_keeps_a_single_node() {
+        let mut vectors = random_vectors(3, 4, 4);
+        let mut index = HnswIndex::build(HnswParams::default(), Metric::Euclidean, &vectors);
+        for n in [0, 1] {
+            let old_vector = vectors.remove(&id(n)).unwrap();
+            index.remove(&id(n), old_vector, &vectors);
+        }
+
+        // Mise à jour comme dans `Collection::insert` : le retrait reconstruit le graphe avec le nouveau vecteur.
+        let old_vector = vectors.insert(id(2), vec![1.0, 2.0, 3.0, 4.0]).unwrap().unwrap();
+        index.remove(&id(2), old_vector, &vectors);
+        index.insert(id(2), &vectors);
+        assert_eq!(index.search(&[1.0, 2.0, 3.0, 4.0], 10, &vectors, None), vec![(id(2), 0.0)]);
+    }
+
+    #[test]
+    fn remove_after_a_rebuild_hides_the_document() {
+        let mut vectors = random_vectors(4, 4, 5);
+        let mut index = HnswIndex::build(HnswParams::default(), Metric::Euclidean, &vectors);
+        for n in [0, 1, 2] {
+            let old_vector = vectors.remove(&id(n)).unwrap();
+            index.remove(&id(n), old_vector, &vectors);
+        }
+        let old_vector = vectors.insert(id(3), vec![0.0; 4]).unwrap().unwrap();
+        index.remove(&id(3), old_vector, &vectors);
+        index.insert(id(3), &vectors);
+        for n in 10..13 {
+            vectors.insert(id(n), vec![n as f32; 4]).unwrap();
+            index.insert(id(n), &vectors);
+        }
+
+        // Assez de nœuds vivants pour que ce retrait ne reconstruise pas le graphe.
+        let old_vector = vectors.remove(&id(3)).unwrap();
+        index.remove(&id(3), old_vector, &vectors);
+        let hits = index.search(&[0.0; 4], 10, &vectors, None);
+        assert_eq!(hits.iter().map(|(key, _)| *key).collect::<Vec<_>>(), vec![id(10), id(11), id(12)]);
+    }
+}
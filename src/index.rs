@@ -0,0 +1,151 @@
+//! # Module: `index`
+//!
+//! L'index de recherche approximative qu'une [`Collection`](crate::Collection) peut construire :
+//! un graphe [`HnswIndex`], des listes inversées [`IvfIndex`] ou des tables de hachage [`LshIndex`]. La collection maintient l'index
+//! à jour à chaque écriture et l'interroge à travers cette énumération.
+
+use crate::hnsw::{HnswIndex, HnswParams};
+use crate::ivf::{IvfIndex, IvfParams};
+use crate::lsh::{LshIndex, LshParams};
+use crate::metric::Metric;
+use crate::vectors::VectorStore;
+use crate::{DocumentId, Vector};
+
+/// # Énumération: `IndexConfig`
+///
+/// Le type d'un index et ses paramètres : de quoi le reconstruire à l'identique. C'est ce que le journal
+/// et les instantanés conservent d'un index, qui est reconstruit à l'ouverture de la base.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IndexConfig {
+    Hnsw(HnswParams),
+    Ivf(IvfParams),
+    Lsh(LshParams),
+}
+
+/// # Énumération: `VectorIndex`
+///
+/// Un index de recherche approximative des plus proches voisins.
+pub enum VectorIndex {
+    Hnsw(HnswIndex),
+    Ivf(IvfIndex),
+    Lsh(LshIndex),
+}
+
+impl IndexConfig {
+    /// Nom court du type d'index.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndexConfig::Hnsw(_) => "hnsw",
+            IndexConfig::Ivf(_) => "ivf",
+            IndexConfig::Lsh(_) => "lsh",
+        }
+    }
+}
+
+impl VectorIndex {
+    /// Construit un index selon `config` sur tous les vecteurs d'une collection.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let index = VectorIndex::build(IndexConfig::Hnsw(HnswParams::default()), Metric::Cosine, &collection.data);
+    /// ```
+    pub fn build(config: IndexConfig, metric: Metric, vectors: &VectorStore) -> Self {
+        match config {
+            IndexConfig::Hnsw(params) => VectorIndex::Hnsw(HnswIndex::build(params, metric, vectors)),
+            IndexConfig::Ivf(params) => VectorIndex::Ivf(IvfIndex::build(params, metric, vectors)),
+            IndexConfig::Lsh(params) => VectorIndex::Lsh(LshIndex::build(params, metric, vectors)),
+        }
+    }
+
+    /// Le type et les paramètres actuels de l'index, réglages de recherche compris.
+    pub fn config(&self) -> IndexConfig {
+        match self {
+            VectorIndex::Hnsw(index) => IndexConfig::Hnsw(index.params()),
+            VectorIndex::Ivf(index) => IndexConfig::Ivf(index.params()),
+            VectorIndex::Lsh(index) => IndexConfig::Lsh(index.params()),
+        }
+    }
+
+    /// Nom court du type d'index, tel qu'il est affiché par `stats`.
+    pub fn name(&self) -> &'static str {
+        self.config().name()
+    }
+
+    /// Modifie les réglages de recherche de l'index sans le reconstruire : `ef_search` pour un index HNSW,
+    /// le nombre de listes parcourues (IVF) ou de seaux visités par table (LSH) pour `nprobe`.
+    /// Un réglage qui ne concerne pas le type de l'index est ignoré.
+    pub fn tune(&mut self, ef_search: Option<usize>, nprobe: Option<usize>) {
+        match self {
+            VectorIndex::Hnsw(index) => ef_search.into_iter().for_each(|ef_search| index.set_ef_search(ef_search)),
+            VectorIndex::Ivf(index) => nprobe.into_iter().for_each(|nprobe| index.set_nprobe(nprobe)),
+            VectorIndex::Lsh(index) => nprobe.into_iter().for_each(|probes| index.set_probes(probes)),
+        }
+    }
+
+    /// Indexe le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    /// Si le document était déjà indexé, il faut d'abord appeler [`VectorIndex::remove`].
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        match self {
+            VectorIndex::Hnsw(index) => index.insert(key, vectors),
+            VectorIndex::Ivf(index) => index.insert(key, vectors),
+            VectorIndex::Lsh(index) => index.insert(key, vectors),
+        }
+    }
+
+    /// Indexe un lot de documents ; les documents déjà indexés sont ignorés.
+    pub fn insert_many(&mut self, keys: &[DocumentId], vectors: &VectorStore) {
+        match self {
+            VectorIndex::Hnsw(index) => index.insert_many(keys, vectors),
+            VectorIndex::Ivf(index) => index.insert_many(keys, vectors),
+            VectorIndex::Lsh(index) => index.insert_many(keys, vectors),
+        }
+    }
+
+    /// Retire un document de l'index.
+    ///
+    /// # Paramètres
+    /// - `key`: L'identifiant du document retiré.
+    /// - `old_vector`: Le vecteur que le document avait dans la collection.
+    /// - `vectors`: Les vecteurs restants de la collection.
+    pub fn remove(&mut self, key: &DocumentId, old_vector: Vec<f32>, vectors: &VectorStore) {
+        match self {
+            VectorIndex::Hnsw(index) => index.remove(key, old_vector, vectors),
+            VectorIndex::Ivf(index) => index.remove(key),
+            VectorIndex::Lsh(index) => index.remove(key),
+        }
+    }
+
+    /// Retire un lot de documents de l'index, avec le vecteur qu'ils avaient dans la collection.
+    pub fn remove_many(&mut self, removed: Vec<(DocumentId, Vec<f32>)>, vectors: &VectorStore) {
+        match self {
+            VectorIndex::Hnsw(index) => index.remove_many(removed, vectors),
+            VectorIndex::Ivf(index) => removed.iter().for_each(|(key, _)| index.remove(key)),
+            VectorIndex::Lsh(index) => removed.iter().for_each(|(key, _)| index.remove(key)),
+        }
+    }
+
+    /// Recherche les `k` documents approximativement les plus similaires à `query`.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `nprobe`: Pour un index IVF, le nombre de listes à parcourir ; pour un index LSH, le nombre
+    ///   de seaux à visiter par table (`None` : celui de l'index). Ignoré par l'index HNSW.
+    /// - `vectors`: Les vecteurs de la collection.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        nprobe: Option<usize>,
+        vectors: &VectorStore,
+        accept: Option<&dyn Fn(&DocumentId) -> bool>,
+    ) -> Vector {
+        match self {
+            VectorIndex::Hnsw(index) => index.search(query, k, vectors, accept),
+            VectorIndex::Ivf(index) => index.search(query, k, nprobe, vectors, accept),
+            VectorIndex::Lsh(index) => index.search(query, k, nprobe, vectors, accept),
+        }
+    }
+}
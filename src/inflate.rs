@@ -0,0 +1,475 @@
+//! # Module: `inflate`
+//!
+//! Décompression au fil de l'eau d'un flux *deflate* (RFC 1951), le format des entrées compressées
+//! d'une archive ZIP (et donc des fichiers `.npz` écrits par `numpy.savez_compressed`).
+//!
+//! Un flux deflate est une suite de blocs : recopiés tels quels, ou codés par des codes de Huffman
+//! (fixes ou transmis en tête du bloc) qui désignent soit un octet, soit la copie d'une suite d'octets
+//! déjà produits, au plus 32 Kio en arrière. Seuls ces 32 Kio et les octets pas encore lus sont gardés
+//! en mémoire.
+
+use std::io::{self, Read};
+
+/// Distance maximale d'une copie : les octets plus anciens ne sont plus nécessaires.
+const WINDOW_SIZE: usize = 32 * 1_024;
+
+/// Nombre de bits d'un code de Huffman décodés d'un coup à l'aide d'une table.
+const FAST_BITS: usize = 9;
+
+/// Longueur de base et nombre de bits supplémentaires des symboles de longueur 257 à 285.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+
+/// Distance de base et nombre de bits supplémentaires des symboles de distance 0 à 29.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145,
+    8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+/// Ordre de transmission des longueurs du code des longueurs, en tête d'un bloc à codes dynamiques.
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// # Structure: `Inflate`
+///
+/// Décompresse un flux deflate brut (sans en-tête zlib ni gzip) à mesure qu'il est lu.
+///
+/// # Exemple
+///
+/// ```
+/// let mut data = Vec::new();
+/// Inflate::new(compressed).read_to_end(&mut data)?;
+/// ```
+pub struct Inflate<R> {
+    input: BitReader<R>,
+    /// Les derniers octets produits (au moins 32 Kio, sauf au début), suivis de ceux pas encore lus.
+    window: Vec<u8>,
+    /// Position dans `window` du premier octet pas encore lu.
+    position: usize,
+    block: Block,
+    last_block: bool,
+}
+
+enum Block {
+    /// Entre deux blocs.
+    Header,
+    /// Bloc recopié tel quel, dont il reste `remaining` octets.
+    Stored { remaining: usize },
+    /// Bloc codé.
+    Huffman { literals: Huffman, distances: Huffman },
+    /// Fin du flux.
+    Done,
+}
+
+impl<R: Read> Inflate<R> {
+    pub fn new(input: R) -> Self {
+        Inflate {
+            input: BitReader::new(input),
+            window: Vec::new(),
+            position: 0,
+            block: Block::Header,
+            last_block: false,
+        }
+    }
+
+    /// Décode la suite du flux, jusqu'à disposer de 32 Kio pas encore lus ou jusqu'à la fin d'un bloc.
+    fn decode(&mut self) -> io::Result<()> {
+        match &mut self.block {
+            Block::Done => {}
+            Block::Header if self.last_block => self.block = Block::Done,
+            Block::Header => {
+                self.last_block = self.input.bits(1)? == 1;
+                self.block = match self.input.bits(2)? {
+                    0 => {
+                        self.input.align();
+                        let length = self.input.bits(16)?;
+                        if length != !self.input.bits(16)? & 0xffff {
+                            return Err(invalid("longueur de bloc non compressé incohérente"));
+                        }
+                        Block::Stored { remaining: length as usize }
+                    }
+                    1 => fixed_block(),
+                    2 => self.dynamic_block()?,
+                    _ => return Err(invalid("type de bloc inconnu")),
+                };
+            }
+            Block::Stored { remaining } => {
+                let count = (*remaining).min(WINDOW_SIZE);
+                for _ in 0..count {
+                    let byte = self.input.bits(8)? as u8;
+                    self.window.push(byte);
+                }
+                *remaining -= count;
+                if *remaining == 0 {
+                    self.block = Block::Header;
+                }
+            }
+            Block::Huffman { literals, distances } => {
+                while self.window.len() - self.position < WINDOW_SIZE {
+                    let symbol = literals.decode(&mut self.input)?;
+                    match symbol {
+                        0..=255 => self.window.push(symbol as u8),
+                        256 => {
+                            self.block = Block::Header;
+                            break;
+                        }
+                        257..=285 => {
+                            let index = symbol as usize - 257;
+                            let length = LENGTH_BASE[index] as usize + self.input.bits(LENGTH_EXTRA[index] as usize)? as usize;
+                            let index = distances.decode(&mut self.input)? as usize;
+                            if index >= DISTANCE_BASE.len() {
+                                return Err(invalid("symbole de distance invalide"));
+                            }
+                            let distance = DISTANCE_BASE[index] as usize + self.input.bits(DISTANCE_EXTRA[index] as usize)? as usize;
+                            if distance > self.window.len() {
+                                return Err(invalid("copie avant le début du flux"));
+                            }
+                            let start = self.window.len() - distance;
+                            for i in 0..length {
+                                let byte = self.window[start + i];
+                                self.window.push(byte);
+                            }
+                        }
+                        _ => return Err(invalid("symbole de longueur invalide")),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Lit les codes de Huffman transmis en tête d'un bloc à codes dynamiques.
+    fn dynamic_block(&mut self) -> io::Result<Block> {
+        let literal_count = self.input.bits(5)? as usize + 257;
+        let distance_count = self.input.bits(5)? as usize + 1;
+        let code_length_count = self.input.bits(4)? as usize + 4;
+        let mut code_lengths = [0u8; 19];
+        for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+            code_lengths[symbol] = self.input.bits(3)? as u8;
+        }
+        let code_lengths = Huffman::new(&code_lengths)?;
+
+        let mut lengths = Vec::with_capacity(literal_count + distance_count);
+        while lengths.len() < literal_count + distance_count {
+            let (value, repeat) = match code_lengths.decode(&mut self.input)? {
+                symbol @ 0..=15 => (symbol as u8, 1),
+                16 => {
+                    let previous = *lengths.last().ok_or_else(|| invalid("répétition sans longueur précédente"))?;
+                    (previous, 3 + self.input.bits(2)? as usize)
+                }
+                17 => (0, 3 + self.input.bits(3)? as usize),
+                _ => (0, 11 + self.input.bits(7)? as usize),
+            };
+            if lengths.len() + repeat > literal_count + distance_count {
+                return Err(invalid("trop de longueurs de code"));
+            }
+            lengths.extend(std::iter::repeat_n(value, repeat));
+        }
+        if lengths[256] == 0 {
+            return Err(invalid("bloc sans symbole de fin"));
+        }
+        Ok(Block::Huffman {
+            literals: Huffman::new(&lengths[..literal_count])?,
+            distances: Huffman::new(&lengths[literal_count..])?,
+        })
+    }
+}
+
+impl<R: Read> Read for Inflate<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        while self.position == self.window.len() && !matches!(self.block, Block::Done) {
+            self.decode()?;
+        }
+        let count = buffer.len().min(self.window.len() - self.position);
+        buffer[..count].copy_from_slice(&self.window[self.position..self.position + count]);
+        self.position += count;
+
+        // Les octets lus qui sont à plus de 32 Kio de la fin ne servent plus à aucune copie.
+        let obsolete = self.position.min(self.window.len().saturating_sub(WINDOW_SIZE));
+        if obsolete >= WINDOW_SIZE {
+            self.window.drain(..obsolete);
+            self.position -= obsolete;
+        }
+        Ok(count)
+    }
+}
+
+/// Les codes fixes des blocs de type 1.
+fn fixed_block() -> Block {
+    let mut literals = [8u8; 288];
+    literals[144..256].fill(9);
+    literals[256..280].fill(7);
+    Block::Huffman {
+        literals: Huffman::new(&literals).expect("code fixe valide"),
+        distances: Huffman::new(&[5; 30]).expect("code fixe valide"),
+    }
+}
+
+/// # Structure: `Huffman`
+///
+/// Un code de Huffman canonique, défini par la longueur du code de chaque symbole.
+struct Huffman {
+    /// Nombre de codes de chaque longueur (de 0 à 15 bits).
+    counts: [u16; 16],
+    /// Les symboles, triés par longueur de code puis par valeur.
+    symbols: Vec<u16>,
+    /// Pour chaque suite de `FAST_BITS` bits, `symbole << 4 | longueur` si un code d'au plus `FAST_BITS`
+    /// bits en est le début, 0 sinon.
+    fast: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> io::Result<Self> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols: Vec<u16> = (0..lengths.len() as u16).filter(|&symbol| lengths[symbol as usize] > 0).collect();
+        symbols.sort_by_key(|&symbol| lengths[symbol as usize]);
+
+        let mut fast = vec![0u16; 1 << FAST_BITS];
+        let mut code = 0u32;
+        let mut index = 0;
+        for length in 1..16 {
+            for &symbol in &symbols[index..index + counts[length] as usize] {
+                if code >= 1 << length {
+                    return Err(invalid("code de Huffman trop long"));
+                }
+                if length <= FAST_BITS {
+                    // Les codes sont transmis bit de poids fort en premier : on les retourne.
+                    let reversed = (code.reverse_bits() >> (32 - length)) as usize;
+                    for prefix in (reversed..1 << FAST_BITS).step_by(1 << length) {
+                        fast[prefix] = symbol << 4 | length as u16;
+                    }
+                }
+                code += 1;
+            }
+            index += counts[length] as usize;
+            code <<= 1;
+        }
+        Ok(Huffman { counts, symbols, fast })
+    }
+
+    /// Décode un symbole.
+    fn decode<R: Read>(&self, input: &mut BitReader<R>) -> io::Result<u16> {
+        let (bits, available) = input.peek(FAST_BITS)?;
+        let entry = self.fast[bits as usize];
+        let length = (entry & 0xf) as usize;
+        if entry != 0 && length <= available {
+            input.consume(length);
+            return Ok(entry >> 4);
+        }
+
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for length in 1..16 {
+            code |= input.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(invalid("code de Huffman invalide"))
+    }
+}
+
+/// # Structure: `BitReader`
+///
+/// Lit un flux bit par bit, en commençant par le bit de poids faible de chaque octet.
+struct BitReader<R> {
+    input: R,
+    buffer: Box<[u8; 4_096]>,
+    start: usize,
+    end: usize,
+    /// Les bits lus mais pas encore consommés, en commençant par le bit de poids faible.
+    bits: u64,
+    count: usize,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(input: R) -> Self {
+        BitReader {
+            input,
+            buffer: Box::new([0; 4_096]),
+            start: 0,
+            end: 0,
+            bits: 0,
+            count: 0,
+        }
+    }
+
+    /// Complète les bits en réserve jusqu'à au moins `count` bits, ou jusqu'à la fin du flux.
+    fn fill(&mut self, count: usize) -> io::Result<()> {
+        while self.count < count {
+            if self.start == self.end {
+                self.end = self.input.read(&mut self.buffer[..])?;
+                self.start = 0;
+                if self.end == 0 {
+                    return Ok(());
+                }
+            }
+            self.bits |= (self.buffer[self.start] as u64) << self.count;
+            self.start += 1;
+            self.count += 8;
+        }
+        Ok(())
+    }
+
+    /// Les `count` prochains bits sans les consommer, complétés par des zéros à la fin du flux,
+    /// avec le nombre de bits effectivement disponibles.
+    fn peek(&mut self, count: usize) -> io::Result<(u64, usize)> {
+        self.fill(count)?;
+        Ok((self.bits & ((1 << count) - 1), self.count.min(count)))
+    }
+
+    fn consume(&mut self, count: usize) {
+        self.bits >>= count;
+        self.count -= count;
+    }
+
+    /// Lit et consomme `count` bits (au plus 32).
+    fn bits(&mut self, count: usize) -> io::Result<u32> {
+        self.fill(count)?;
+        if self.count < count {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "flux deflate tronqué"));
+        }
+        let value = (self.bits & ((1 << count) - 1)) as u32;
+        self.consume(count);
+        Ok(value)
+    }
+
+    /// Abandonne les bits qui restent de l'octet en cours.
+    fn align(&mut self) {
+        self.consume(self.count % 8);
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("flux deflate invalide : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inflate(compressed: &[u8]) -> Vec<u8> {
+        let mut output = Vec::new();
+        Inflate::new(compressed).read_to_end(&mut output).unwrap();
+        output
+    }
+
+    fn inflate_error(compressed: &[u8]) -> io::Error {
+        Inflate::new(compressed).read_to_end(&mut Vec::new()).unwrap_err()
+    }
+
+    /// Le flux du test `inflates_fixed_and_dynamic_blocks`, fait d'un bloc dynamique.
+    fn dynamic_stream() -> Vec<u8> {
+        let hex = "a590890d442108055b99123ea0a8fd37b6a32d6c628cc73bf9080687687252cd38f46007450ec6622549059d1c11c5fa88\
+                   641647b45c053c0fe6c7a627b9d89b39c864aba6d42482ddb48c4d25219253ecc93a2cf77aeb5d7df4ab2f48a8046992e3\
+                   0929a7a8d21a68a39996fb9a1be1068917aa6f40631ad6c806ef57226f1d4b59ad6e49ab5ab85e7947709c45f0fd35931f";
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap()).collect()
+    }
+
+    /// Les données de `testdata/window.deflate`, recalculées comme dans `testdata/generate.py` : des lettres
+    /// pseudo-aléatoires, entrecoupées de copies de 300 octets pris 32 000 octets plus tôt.
+    fn window_data() -> Vec<u8> {
+        let (mut state, mut data) = (1u64, Vec::new());
+        while data.len() < 100_000 {
+            state = (state * 1_103_515_245 + 12_345) % (1 << 31);
+            let random = state >> 16;
+            if data.len() > 32_000 && random % 8 == 0 {
+                let start = data.len() - 32_000;
+                data.extend_from_within(start..start + 300);
+            } else {
+                data.push(b'a' + (random % 16) as u8);
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn inflates_fixed_and_dynamic_blocks() {
+        // Compressés par `zlib.compressobj(9, zlib.DEFLATED, -15)`.
+        assert_eq!(inflate(&[0x4b, 0x4c, 0x4a, 0x4e, 0x04, 0x23, 0x00]), b"abcabcabc");
+
+        let text: Vec<String> = (0..120).map(|i| (i * i % 97).to_string()).collect();
+        assert_eq!(inflate(&dynamic_stream()), text.join(" ").into_bytes());
+    }
+
+    #[test]
+    fn inflates_stored_blocks_longer_than_the_window() {
+        let data: Vec<u8> = (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let mut compressed = Vec::new();
+        let blocks: Vec<&[u8]> = data.chunks(u16::MAX as usize).collect();
+        for (index, block) in blocks.iter().enumerate() {
+            compressed.push((index + 1 == blocks.len()) as u8);
+            compressed.extend((block.len() as u16).to_le_bytes());
+            compressed.extend((!(block.len() as u16)).to_le_bytes());
+            compressed.extend_from_slice(block);
+        }
+        assert_eq!(inflate(&compressed), data);
+
+        // Un bloc vide suivi d'un bloc fixe : l'en-tête du second commence sur l'octet suivant.
+        assert_eq!(inflate(&[0x00, 0x00, 0x00, 0xff, 0xff, 0x4b, 0x4c, 0x4a, 0x4e, 0x04, 0x23, 0x00]), b"abcabcabc");
+    }
+
+    #[test]
+    fn copies_reach_back_a_full_window() {
+        // Compressé par `zlib.compressobj(9, zlib.DEFLATED, -15)`, en plusieurs blocs dynamiques.
+        let compressed = include_bytes!("../testdata/window.deflate");
+        assert_eq!(inflate(compressed), window_data());
+
+        // Lu par petits morceaux, comme le fait un `BufReader` de petite taille.
+        let mut reader = Inflate::new(compressed.as_slice());
+        let (mut output, mut buffer) = (Vec::new(), [0; 1000]);
+        loop {
+            match reader.read(&mut buffer).unwrap() {
+                0 => break,
+                read => output.extend_from_slice(&buffer[..read]),
+            }
+        }
+        assert_eq!(output, window_data());
+    }
+
+    #[test]
+    fn truncated_streams_are_errors() {
+        let stream = dynamic_stream();
+        for length in 0..stream.len() {
+            inflate_error(&stream[..length]);
+        }
+        let compressed = include_bytes!("../testdata/window.deflate");
+        for length in (0..compressed.len()).step_by(997) {
+            inflate_error(&compressed[..length]);
+        }
+    }
+
+    #[test]
+    fn corrupted_streams_never_panic() {
+        for stream in [dynamic_stream(), include_bytes!("../testdata/window.deflate")[..400].to_vec()] {
+            for position in 0..stream.len() {
+                for mask in [0x01, 0x10, 0xff] {
+                    let mut corrupted = stream.clone();
+                    corrupted[position] ^= mask;
+                    let _ = Inflate::new(corrupted.as_slice()).read_to_end(&mut Vec::new());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_blocks_are_errors() {
+        // Bloc final de type 3, réservé.
+        assert_eq!(inflate_error(&[0x07]).kind(), io::ErrorKind::InvalidData);
+        // Bloc non compressé dont la longueur ne correspond pas à son complément.
+        assert_eq!(inflate_error(&[0x01, 0x05, 0x00, 0x00, 0x00]).kind(), io::ErrorKind::InvalidData);
+        // Bloc fixe qui commence par une copie (longueur 3, distance 1) alors que rien n'a été produit.
+        assert_eq!(inflate_error(&[0x03, 0x02]).kind(), io::ErrorKind::InvalidData);
+    }
+}
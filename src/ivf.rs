@@ -0,0 +1,353 @@
+//! # Module: `ivf`
+//!
+//! Index IVF-Flat (*inverted file*) pour la recherche approximative des plus proches voisins
+//! dans une [`Collection`](crate::Collection).
+//!
+//! Un quantificateur grossier, appris par k-moyennes sur les vecteurs de la collection (ou sur un
+//! échantillon), découpe l'espace en `nlist` cellules représentées par leur centroïde. Chaque document
+//! est rangé dans la liste de son centroïde le plus proche selon la [`Metric`] de la collection
+//! (selon la distance euclidienne pour le produit scalaire, voir [`quantizer_metric`]).
+//! Une recherche ne compare la requête qu'aux documents des `nprobe` listes dont les centroïdes sont
+//! les plus proches d'elle : plus `nprobe` est grand, plus la recherche est exacte et lente.
+//!
+//! Comme l'index HNSW, l'index ne stocke pas de copie des vecteurs. Les nouveaux documents sont rangés
+//! dans la liste de leur centroïde le plus proche sans modifier les centroïdes : si la distribution des
+//! données dérive, les listes se déséquilibrent (voir [`IvfIndex::imbalance`]) et il faut réapprendre
+//! les centroïdes en reconstruisant l'index (`index <collection> rebuild`).
+
+use std::collections::HashMap;
+
+use crate::kmeans::{self, distance, nearest_centroid};
+use crate::metric::Metric;
+use crate::rng::SplitMix64;
+use crate::topk::TopK;
+use crate::vectors::VectorStore;
+use crate::{DocumentId, Vector};
+
+/// Graine du générateur pseudo-aléatoire utilisé pour l'échantillon et l'initialisation des k-moyennes.
+/// Une graine fixe rend l'apprentissage reproductible.
+const TRAINING_SEED: u64 = 0x5EED_1F00_0000_0001;
+
+/// # Structure: `IvfParams`
+///
+/// Paramètres d'apprentissage et de recherche d'un [`IvfIndex`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IvfParams {
+    /// Nombre de listes, c'est-à-dire de centroïdes du quantificateur grossier (`nlist`).
+    /// Il est ramené au nombre de vecteurs d'apprentissage s'il le dépasse.
+    pub nlist: usize,
+    /// Nombre de listes parcourues par défaut lors d'une recherche (`nprobe`).
+    pub nprobe: usize,
+    /// Nombre de vecteurs tirés au hasard pour apprendre les centroïdes ; `None` utilise toute la collection.
+    pub sample_size: Option<usize>,
+    /// Nombre maximal d'itérations des k-moyennes.
+    pub iterations: usize,
+}
+
+impl Default for IvfParams {
+    fn default() -> Self {
+        IvfParams {
+            nlist: 100,
+            nprobe: 8,
+            sample_size: None,
+            iterations: 25,
+        }
+    }
+}
+
+/// # Structure: `IvfIndex`
+///
+/// Listes inversées construites au-dessus des vecteurs d'une [`Collection`](crate::Collection).
+pub struct IvfIndex {
+    params: IvfParams,
+    metric: Metric,
+    centroids: Vec<Vec<f32>>,
+    /// `lists[i]` contient les documents rangés dans la cellule du centroïde `i`.
+    lists: Vec<Vec<DocumentId>>,
+    /// La liste de chaque document indexé.
+    assignments: HashMap<DocumentId, usize>,
+}
+
+impl IvfIndex {
+    /// Apprend les centroïdes sur `vectors` (ou sur un échantillon, selon `params`)
+    /// puis range chaque document dans la liste de son centroïde le plus proche.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let index = IvfIndex::build(IvfParams { nlist: 256, ..Default::default() }, Metric::Cosine, &vectors);
+    /// ```
+    pub fn build(params: IvfParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let mut index = IvfIndex {
+            params,
+            metric,
+            centroids: train(params, metric, vectors),
+            lists: Vec::new(),
+            assignments: HashMap::new(),
+        };
+        index.lists = vec![Vec::new(); index.centroids.len()];
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        index.insert_many(&keys, vectors);
+        index
+    }
+
+    /// Les paramètres de l'index, avec le `nprobe` actuel.
+    pub fn params(&self) -> IvfParams {
+        self.params
+    }
+
+    /// Modifie le `nprobe` par défaut sans réapprendre les centroïdes.
+    pub fn set_nprobe(&mut self, nprobe: usize) {
+        self.params.nprobe = nprobe.max(1);
+    }
+
+    /// Nombre de listes de l'index.
+    pub fn nlist(&self) -> usize {
+        self.centroids.len()
+    }
+
+    /// Facteur de déséquilibre des listes : 1.0 si tous les documents sont répartis également,
+    /// davantage si quelques listes concentrent les documents (ce qui ralentit les recherches).
+    /// Une valeur qui augmente au fil des insertions indique une dérive des données.
+    pub fn imbalance(&self) -> f64 {
+        let total = self.assignments.len() as f64;
+        if total == 0.0 {
+            return 1.0;
+        }
+        let squares: f64 = self.lists.iter().map(|list| (list.len() as f64).powi(2)).sum();
+        squares * self.lists.len() as f64 / (total * total)
+    }
+
+    /// Range le document `key`, dont le vecteur doit déjà se trouver dans `vectors`, dans la liste
+    /// de son centroïde le plus proche. Dans un index appris sur une collection vide, le premier
+    /// document inséré devient l'unique centroïde.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        let vector = match vectors.get(&key) {
+            Some(vector) => vector,
+            None => return,
+        };
+        if self.centroids.is_empty() {
+            self.centroids.push(vector.to_vec());
+            self.lists.push(Vec::new());
+        }
+        let list = nearest_centroid(quantizer_metric(self.metric), &self.centroids, vector).0;
+        self.lists[list].push(key);
+        self.assignments.insert(key, list);
+    }
+
+    /// Range un lot de documents ; les documents déjà indexés sont ignorés.
+    pub fn insert_many(&mut self, keys: &[DocumentId], vectors: &VectorStore) {
+        for key in keys {
+            if !self.assignments.contains_key(key) {
+                self.insert(*key, vectors);
+            }
+        }
+    }
+
+    /// Retire un document de sa liste.
+    pub fn remove(&mut self, key: &DocumentId) {
+        if let Some(list) = self.assignments.remove(key) {
+            let list = &mut self.lists[list];
+            if let Some(position) = list.iter().position(|candidate| candidate == key) {
+                list.swap_remove(position);
+            }
+        }
+    }
+
+    /// Recherche les `k` documents approximativement les plus similaires à `query`
+    /// parmi ceux des `nprobe` listes les plus proches.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `nprobe`: Le nombre de listes à parcourir pour cette requête ; `None` utilise celui des paramètres.
+    /// - `vectors`: Les vecteurs de la collection.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score, du plus proche au plus éloigné selon la métrique.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        nprobe: Option<usize>,
+        vectors: &VectorStore,
+        accept: Option<&dyn Fn(&DocumentId) -> bool>,
+    ) -> Vector {
+        let nprobe = nprobe.unwrap_or(self.params.nprobe).max(1);
+        let mut probes: Vec<(f32, usize)> = self
+            .centroids
+            .iter()
+            .enumerate()
+            .map(|(list, centroid)| (distance(self.metric, query, centroid), list))
+            .collect();
+        probes.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+
+        let mut top = TopK::new(self.metric, k);
+        for &(_, list) in probes.iter().take(nprobe) {
+            for key in &self.lists[list] {
+                if accept.is_some_and(|accept| !accept(key)) {
+                    continue;
+                }
+                if let Some(vector) = vectors.get(key) {
+                    top.push(*key, self.metric.score(query, vector));
+                }
+            }
+        }
+        top.into_sorted_vec()
+    }
+}
+
+/// Métrique selon laquelle les documents sont rangés dans les cellules.
+///
+/// Le produit scalaire n'est pas une distance : chaque vecteur y est « le plus proche » du centroïde de
+/// plus grande norme, si bien que les k-moyennes rangeraient presque tous les documents dans une seule
+/// liste. Les cellules sont donc apprises selon la distance euclidienne, et les listes parcourues lors
+/// d'une recherche restent celles dont le produit scalaire avec la requête est le plus grand.
+fn quantizer_metric(metric: Metric) -> Metric {
+    match metric {
+        Metric::Dot => Metric::Euclidean,
+        metric => metric,
+    }
+}
+
+/// Apprend les centroïdes du quantificateur grossier par k-moyennes sur un échantillon de `vectors`.
+fn train(params: IvfParams, metric: Metric, vectors: &VectorStore) -> Vec<Vec<f32>> {
+    let mut rng = SplitMix64::new(TRAINING_SEED);
+    let sample = kmeans::sample(&mut rng, vectors, params.sample_size);
+    kmeans::train(&mut rng, quantizer_metric(metric), &sample, params.nlist, params.iterations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    /// `count` vecteurs gaussiens centrés sur `offset`, d'identifiants `first..first + count`.
+    fn add_random_vectors(vectors: &mut VectorStore, first: usize, count: usize, offset: f32, seed: u64) {
+        let mut rng = SplitMix64::new(seed);
+        for n in first..first + count {
+            let vector = (0..16).map(|_| offset + rng.next_gaussian() as f32).collect();
+            vectors.insert(id(n as u128), vector).unwrap();
+        }
+    }
+
+    fn random_vectors(count: usize, seed: u64) -> VectorStore {
+        let mut vectors = VectorStore::default();
+        add_random_vectors(&mut vectors, 0, count, 0.0, seed);
+        vectors
+    }
+
+    fn exact(metric: Metric, vectors: &VectorStore, query: &[f32], k: usize) -> Vector {
+        let mut top = TopK::new(metric, k);
+        for (key, vector) in vectors.iter() {
+            top.push(*key, metric.score(query, vector));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Proportion des `k` vrais plus proches voisins retrouvés en parcourant `nprobe` listes.
+    fn recall(index: &IvfIndex, vectors: &VectorStore, k: usize, nprobe: usize) -> f64 {
+        let queries = random_vectors(20, 99);
+        let mut found = 0;
+        for query in queries.values() {
+            let expected: HashSet<DocumentId> = exact(index.metric, vectors, query, k).into_iter().map(|(key, _)| key).collect();
+            let hits = index.search(query, k, Some(nprobe), vectors, None);
+            found += hits.iter().filter(|(key, _)| expected.contains(key)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn search_of_every_list_matches_the_exact_scan() {
+        let vectors = random_vectors(500, 1);
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot] {
+            let index = IvfIndex::build(IvfParams { nlist: 16, ..Default::default() }, metric, &vectors);
+            assert_eq!(index.nlist(), 16);
+            for n in [3, 250, 499] {
+                let query = vectors.get(&id(n)).unwrap();
+                assert_eq!(index.search(query, 10, Some(16), &vectors, None), exact(metric, &vectors, query, 10));
+            }
+            assert_eq!(recall(&index, &vectors, 10, 16), 1.0);
+        }
+    }
+
+    #[test]
+    fn recall_grows_with_nprobe() {
+        let vectors = random_vectors(2_000, 2);
+        let mut index = IvfIndex::build(IvfParams { nlist: 32, nprobe: 1, ..Default::default() }, Metric::Euclidean, &vectors);
+        let narrow = recall(&index, &vectors, 10, 1);
+        let wide = recall(&index, &vectors, 10, 16);
+        assert!(wide > narrow, "nprobe 16 : {}, nprobe 1 : {}", wide, narrow);
+        assert!(wide >= 0.9, "nprobe 16 : {}", wide);
+
+        // Le nprobe des paramètres s'applique aux recherches qui n'en précisent pas.
+        let query = vectors.get(&id(5)).unwrap();
+        index.set_nprobe(16);
+        assert_eq!(index.search(query, 10, None, &vectors, None), index.search(query, 10, Some(16), &vectors, None));
+    }
+
+    #[test]
+    fn sample_and_small_collections_limit_the_lists() {
+        let vectors = random_vectors(50, 3);
+        let index = IvfIndex::build(IvfParams { nlist: 100, ..Default::default() }, Metric::Cosine, &vectors);
+        assert_eq!(index.nlist(), 50);
+        let index = IvfIndex::build(IvfParams { nlist: 100, sample_size: Some(20), ..Default::default() }, Metric::Cosine, &vectors);
+        assert_eq!(index.nlist(), 20);
+        assert_eq!(index.assignments.len(), 50);
+    }
+
+    #[test]
+    fn inserts_go_to_the_nearest_list() {
+        let mut vectors = VectorStore::default();
+        let mut index = IvfIndex::build(IvfParams::default(), Metric::Euclidean, &vectors);
+        assert_eq!(index.nlist(), 0);
+
+        // Dans un index appris sur une collection vide, le premier document devient l'unique centroïde.
+        add_random_vectors(&mut vectors, 0, 100, 0.0, 4);
+        index.insert_many(&vectors.keys().copied().collect::<Vec<_>>(), &vectors);
+        assert_eq!(index.nlist(), 1);
+        assert_eq!(index.lists[0].len(), 100);
+
+        let vectors = random_vectors(500, 5);
+        let mut index = IvfIndex::build(IvfParams { nlist: 8, ..Default::default() }, Metric::Euclidean, &vectors);
+        let mut vectors = vectors;
+        add_random_vectors(&mut vectors, 500, 20, 0.5, 6);
+        for n in 500..520 {
+            index.insert(id(n), &vectors);
+            let expected = nearest_centroid(Metric::Euclidean, &index.centroids, vectors.get(&id(n)).unwrap()).0;
+            assert_eq!(index.assignments[&id(n)], expected);
+            assert!(index.lists[expected].contains(&id(n)));
+        }
+
+        let query = vectors.get(&id(510)).unwrap().to_vec();
+        assert_eq!(index.search(&query, 1, Some(1), &vectors, None)[0].0, id(510));
+        index.remove(&id(510));
+        assert!(index.lists.iter().all(|list| !list.contains(&id(510))));
+        assert_ne!(index.search(&query, 1, Some(8), &vectors, None)[0].0, id(510));
+    }
+
+    #[test]
+    fn rebuilding_after_a_drift_rebalances_the_lists() {
+        let mut vectors = random_vectors(500, 7);
+        let params = IvfParams { nlist: 8, ..Default::default() };
+        let mut index = IvfIndex::build(params, Metric::Euclidean, &vectors);
+        let initial = index.imbalance();
+
+        // Les nouveaux documents, loin des anciens, s'entassent dans les listes des centroïdes du bord.
+        add_random_vectors(&mut vectors, 500, 2_000, 10.0, 8);
+        index.insert_many(&(500..2_500).map(id).collect::<Vec<_>>(), &vectors);
+        let drifted = index.imbalance();
+        assert!(drifted > 2.0 * initial, "avant : {}, après la dérive : {}", initial, drifted);
+
+        let rebuilt = IvfIndex::build(index.params(), Metric::Euclidean, &vectors);
+        assert!(rebuilt.imbalance() < drifted / 2.0, "après la dérive : {}, reconstruit : {}", drifted, rebuilt.imbalance());
+        assert!(recall(&rebuilt, &vectors, 10, 8) >= recall(&index, &vectors, 10, 8));
+    }
+}
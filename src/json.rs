@@ -0,0 +1,327 @@
+//! # Module: `json`
+//!
+//! Analyse d'un texte JSON en [`Value`], et conversion en [`Value`] des vecteurs, des collections et
+//! des résultats de recherche. L'écriture se fait avec l'implémentation de
+//! [`Display`](std::fmt::Display) de [`Value`].
+
+use crate::error::DbError;
+use crate::index::IndexConfig;
+use crate::payload::{Payload, Value};
+use crate::{Collection, SearchResult};
+
+/// Profondeur d'imbrication maximale acceptée, pour ne pas épuiser la pile sur une entrée hostile.
+const MAX_DEPTH: usize = 128;
+
+/// Analyse un texte JSON.
+///
+/// # Paramètres
+/// - `text`: Le texte à analyser ; il doit contenir exactement une valeur JSON.
+///
+/// # Retour
+/// - `Result<Value, DbError>`: La valeur, ou [`DbError::InvalidJson`] avec la position de l'erreur.
+///
+/// # Exemple
+///
+/// ```
+/// let value = json::parse(r#"{"vector": [1.0, 2.0], "payload": {"client": "Dupont"}}"#)?;
+/// ```
+pub fn parse(text: &str) -> Result<Value, DbError> {
+    let mut parser = JsonParser {
+        bytes: text.as_bytes(),
+        position: 0,
+    };
+    parser.skip_whitespace();
+    let value = parser.parse_value(0)?;
+    parser.skip_whitespace();
+    if parser.position != parser.bytes.len() {
+        return Err(parser.error("caractères en trop après la valeur"));
+    }
+    Ok(value)
+}
+
+/// Convertit un `f32` en [`Value::Number`] en conservant sa représentation décimale la plus courte
+/// (`0.1f32` s'écrit `0.1` et non `0.10000000149011612`).
+pub fn number_from_f32(value: f32) -> Value {
+    Value::Number(value.to_string().parse().unwrap_or(value as f64))
+}
+
+/// Construit un objet JSON à partir de couples (clé, valeur).
+///
+/// # Exemple
+///
+/// ```
+/// let body = json::object([("id", Value::from(key.to_string())), ("score", Value::from(0.5))]);
+/// ```
+pub fn object<const N: usize>(entries: [(&str, Value); N]) -> Value {
+    Value::Object(entries.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+/// Convertit un vecteur en tableau JSON.
+pub fn vector(vector: &[f32]) -> Value {
+    Value::Array(vector.iter().map(|&value| number_from_f32(value)).collect())
+}
+
+/// Lit un vecteur donné sous forme de tableau JSON de nombres.
+///
+/// # Retour
+/// - `Option<Vec<f32>>`: Le vecteur, ou `None` si la valeur n'est pas un tableau de nombres.
+pub fn parse_vector(value: &Value) -> Option<Vec<f32>> {
+    match value {
+        Value::Array(values) => values
+            .iter()
+            .map(|value| match value {
+                Value::Number(number) => Some(*number as f32),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// Décrit une collection : son nom, sa métrique, sa dimension, si elle normalise ses vecteurs,
+/// si elle les range dans un fichier projeté en mémoire, son nombre de documents, son index et les champs
+/// indexés de ses métadonnées.
+pub fn collection_info(name: &str, collection: &Collection) -> Value {
+    object([
+        ("name", Value::from(name)),
+        ("metric", Value::from(collection.metric.name())),
+        ("dimension", collection.dimension.map_or(Value::Null, |dimension| Value::Number(dimension as f64))),
+        ("normalize", Value::Bool(collection.normalize)),
+        ("mapped", Value::Bool(collection.data.is_mapped())),
+        ("documents", Value::Number(collection.len() as f64)),
+        ("index", collection.index.as_ref().map_or(Value::Null, |index| index_config(&index.config()))),
+        (
+            "payload_index",
+            Value::Array(collection.payload_index.fields().into_iter().map(Value::from).collect()),
+        ),
+    ])
+}
+
+/// Décrit un index de recherche approximative : `{"type": "hnsw", "m": 16, ...}`.
+pub fn index_config(config: &IndexConfig) -> Value {
+    let number = |value: usize| Value::Number(value as f64);
+    let mut fields = vec![("type", Value::from(config.name()))];
+    match config {
+        IndexConfig::Hnsw(params) => fields.extend([
+            ("m", number(params.m)),
+            ("ef_construction", number(params.ef_construction)),
+            ("ef_search", number(params.ef_search)),
+        ]),
+        IndexConfig::Ivf(params) => fields.extend([
+            ("nlist", number(params.nlist)),
+            ("nprobe", number(params.nprobe)),
+            ("sample_size", params.sample_size.map_or(Value::Null, number)),
+        ]),
+        IndexConfig::Lsh(params) => fields.extend([
+            ("bits", number(params.bits)),
+            ("tables", number(params.tables)),
+            ("probes", number(params.probes)),
+        ]),
+    }
+    Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())
+}
+
+/// Convertit un résultat de recherche en `{"metric": ..., "hits": [{"id", "score", "payload"?}]}`.
+/// Le champ `payload` n'est présent que si les métadonnées ont été ajoutées au résultat.
+pub fn search_result(result: &SearchResult) -> Value {
+    let mut payloads = result.payloads.iter().flatten();
+    let hits = result
+        .hits
+        .iter()
+        .map(|&(key, score)| {
+            let mut hit = Payload::from([
+                ("id".to_string(), Value::from(key.to_string())),
+                ("score".to_string(), number_from_f32(score)),
+            ]);
+            if let Some(payload) = payloads.next() {
+                hit.insert("payload".to_string(), Value::Object(payload.clone()));
+            }
+            Value::Object(hit)
+        })
+        .collect();
+    object([("metric", Value::from(result.metric.name())), ("hits", Value::Array(hits))])
+}
+
+struct JsonParser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, message: &str) -> DbError {
+        DbError::InvalidJson(format!("{} (position {})", message, self.position))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.bytes.get(self.position), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.position += 1;
+        }
+    }
+
+    fn expect_literal(&mut self, literal: &str, value: Value) -> Result<Value, DbError> {
+        if self.bytes[self.position..].starts_with(literal.as_bytes()) {
+            self.position += literal.len();
+            Ok(value)
+        } else {
+            Err(self.error("valeur inconnue"))
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Value, DbError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("imbrication trop profonde"));
+        }
+        match self.bytes.get(self.position) {
+            None => Err(self.error("fin de texte inattendue")),
+            Some(b'n') => self.expect_literal("null", Value::Null),
+            Some(b't') => self.expect_literal("true", Value::Bool(true)),
+            Some(b'f') => self.expect_literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.parse_string()?)),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'{') => self.parse_object(depth),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => Err(self.error("caractère inattendu")),
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Value, DbError> {
+        self.position += 1;
+        let mut values = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.position) == Some(&b']') {
+            self.position += 1;
+            return Ok(Value::Array(values));
+        }
+        loop {
+            self.skip_whitespace();
+            values.push(self.parse_value(depth + 1)?);
+            self.skip_whitespace();
+            match self.bytes.get(self.position) {
+                Some(b',') => self.position += 1,
+                Some(b']') => {
+                    self.position += 1;
+                    return Ok(Value::Array(values));
+                }
+                _ => return Err(self.error("',' ou ']' attendu")),
+            }
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Value, DbError> {
+        self.position += 1;
+        let mut object = Payload::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.position) == Some(&b'}') {
+            self.position += 1;
+            return Ok(Value::Object(object));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.position) != Some(&b'"') {
+                return Err(self.error("clé attendue"));
+            }
+            let key = self.parse_string()?;
+            self.skip_whitespace();
+            if self.bytes.get(self.position) != Some(&b':') {
+                return Err(self.error("':' attendu"));
+            }
+            self.position += 1;
+            self.skip_whitespace();
+            let value = self.parse_value(depth + 1)?;
+            object.insert(key, value);
+            self.skip_whitespace();
+            match self.bytes.get(self.position) {
+                Some(b',') => self.position += 1,
+                Some(b'}') => {
+                    self.position += 1;
+                    return Ok(Value::Object(object));
+                }
+                _ => return Err(self.error("',' ou '}' attendu")),
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Value, DbError> {
+        let start = self.position;
+        while matches!(
+            self.bytes.get(self.position),
+            Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        ) {
+            self.position += 1;
+        }
+        // Les octets parcourus sont tous ASCII.
+        let literal = std::str::from_utf8(&self.bytes[start..self.position]).unwrap_or_default();
+        literal
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| self.error(&format!("nombre invalide '{}'", literal)))
+    }
+
+    fn parse_string(&mut self) -> Result<String, DbError> {
+        self.position += 1;
+        let mut bytes = Vec::new();
+        loop {
+            match self.bytes.get(self.position) {
+                None => return Err(self.error("chaîne non terminée")),
+                Some(b'"') => {
+                    self.position += 1;
+                    return String::from_utf8(bytes).map_err(|_| self.error("chaîne UTF-8 invalide"));
+                }
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = match self.bytes.get(self.position) {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("séquence d'échappement invalide")),
+                    };
+                    self.position += 1;
+                    let mut buffer = [0u8; 4];
+                    bytes.extend_from_slice(escaped.encode_utf8(&mut buffer).as_bytes());
+                }
+                Some(&byte) if byte < 0x20 => return Err(self.error("caractère de contrôle dans une chaîne")),
+                Some(&byte) => {
+                    bytes.push(byte);
+                    self.position += 1;
+                }
+            }
+        }
+    }
+
+    /// Lit `\uXXXX` (la position pointe sur le `u`), y compris une paire de substitution UTF-16.
+    /// La position est laissée sur le dernier chiffre hexadécimal lu.
+    fn parse_unicode_escape(&mut self) -> Result<char, DbError> {
+        let high = self.read_hex4()?;
+        let code = if (0xD800..0xDC00).contains(&high) {
+            if self.bytes.get(self.position + 1..self.position + 3) != Some(b"\\u") {
+                return Err(self.error("paire de substitution incomplète"));
+            }
+            self.position += 2;
+            let low = self.read_hex4()?;
+            if !(0xDC00..0xE000).contains(&low) {
+                return Err(self.error("paire de substitution invalide"));
+            }
+            0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("caractère Unicode invalide"))
+    }
+
+    fn read_hex4(&mut self) -> Result<u32, DbError> {
+        let digits = self
+            .bytes
+            .get(self.position + 1..self.position + 5)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("séquence \\u invalide"))?;
+        self.position += 4;
+        Ok(digits)
+    }
+}
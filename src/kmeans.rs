@@ -0,0 +1,212 @@
+//! # Module: `kmeans`
+//!
+//! Apprentissage de centroïdes par k-moyennes, partagé par les index et les quantificateurs qui
+//! découpent l'espace des vecteurs en cellules : le quantificateur grossier de l'index IVF
+//! et les dictionnaires de la quantification par produit.
+//!
+//! L'apprentissage est reproductible : l'échantillon et les centroïdes initiaux ne dépendent que
+//! de la graine du générateur et des identifiants des documents.
+
+use crate::metric::Metric;
+use crate::pool::WorkerPool;
+use crate::rng::SplitMix64;
+use crate::vectors::VectorStore;
+use crate::{simd, DocumentId};
+
+/// Nombre de vecteurs d'apprentissage à partir duquel l'affectation aux centroïdes est répartie
+/// entre les threads du [`WorkerPool`].
+const PARALLEL_ASSIGN_THRESHOLD: usize = 4_096;
+
+/// Distance utilisée par le quantificateur : la distance de la métrique, ou l'opposé de sa similarité.
+/// La distance euclidienne est remplacée par son carré, qui classe les centroïdes dans le même ordre.
+/// Une distance NaN compte comme infinie.
+pub fn distance(metric: Metric, vector1: &[f32], vector2: &[f32]) -> f32 {
+    let distance = match metric {
+        Metric::Euclidean => simd::squared_l2(vector1, vector2),
+        _ => metric.score_to_distance(metric.score(vector1, vector2)),
+    };
+    if distance.is_nan() {
+        f32::INFINITY
+    } else {
+        distance
+    }
+}
+
+/// Le centroïde le plus proche d'un vecteur (le premier en cas d'égalité) et sa distance.
+pub fn nearest_centroid(metric: Metric, centroids: &[Vec<f32>], vector: &[f32]) -> (usize, f32) {
+    let mut nearest = (0, f32::INFINITY);
+    for (i, centroid) in centroids.iter().enumerate() {
+        let distance = distance(metric, vector, centroid);
+        if distance < nearest.1 {
+            nearest = (i, distance);
+        }
+    }
+    nearest
+}
+
+/// Tire au plus `size` vecteurs distincts, dans un ordre qui ne dépend que de la graine et des identifiants.
+pub fn sample<'a>(rng: &mut SplitMix64, vectors: &'a VectorStore, size: Option<usize>) -> Vec<&'a [f32]> {
+    let mut keys: Vec<&DocumentId> = vectors.keys().collect();
+    keys.sort();
+    let size = size.map_or(keys.len(), |size| size.min(keys.len()));
+    // Mélange de Fisher-Yates partiel : seuls les `size` premiers éléments sont tirés.
+    for i in 0..size {
+        let j = i + rng.below(keys.len() - i);
+        keys.swap(i, j);
+    }
+    keys.truncate(size);
+    keys.into_iter().map(|key| &vectors[key]).collect()
+}
+
+/// Choisit les centroïdes initiaux par k-means++ : chaque nouveau centroïde est tiré avec une probabilité
+/// proportionnelle au carré de la distance euclidienne au centroïde déjà choisi le plus proche.
+fn seed_centroids(rng: &mut SplitMix64, sample: &[&[f32]], count: usize) -> Vec<Vec<f32>> {
+    let mut centroids = vec![sample[rng.below(sample.len())].to_vec()];
+    let mut weights: Vec<f64> = sample
+        .iter()
+        .map(|vector| f64::from(simd::squared_l2(vector, &centroids[0])))
+        .collect();
+    while centroids.len() < count {
+        let total: f64 = weights.iter().sum();
+        let chosen = if total > 0.0 && total.is_finite() {
+            let mut target = rng.next_unit() * total;
+            weights
+                .iter()
+                .position(|&weight| {
+                    target -= weight;
+                    target <= 0.0
+                })
+                .unwrap_or(sample.len() - 1)
+        } else {
+            // Tous les vecteurs restants coïncident avec un centroïde : tirage uniforme.
+            rng.below(sample.len())
+        };
+        let centroid = sample[chosen];
+        centroids.push(centroid.to_vec());
+        for (weight, vector) in weights.iter_mut().zip(sample) {
+            *weight = weight.min(f64::from(simd::squared_l2(vector, centroid)));
+        }
+    }
+    centroids
+}
+
+/// Le centroïde le plus proche de chaque vecteur d'apprentissage, et sa distance.
+///
+/// Pour la distance euclidienne, les centroïdes sont d'abord rangés coordonnée par coordonnée :
+/// les distances d'un vecteur à tous les centroïdes se calculent alors en un seul parcours, que le
+/// compilateur vectorise, au lieu d'un appel par centroïde (coûteux pour les sous-vecteurs courts
+/// de la quantification par produit).
+fn assign(metric: Metric, centroids: &[Vec<f32>], sample: &[&[f32]]) -> Vec<(usize, f32)> {
+    let count = centroids.len();
+    let columns: Vec<f32> = match metric {
+        Metric::Euclidean => (0..sample[0].len())
+            .flat_map(|coordinate| centroids.iter().map(move |centroid| centroid[coordinate]))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let nearest = |vectors: &[&[f32]]| -> Vec<(usize, f32)> {
+        match metric {
+            Metric::Euclidean => {
+                let mut distances = vec![0.0; count];
+                vectors
+                    .iter()
+                    .map(|vector| nearest_column(&columns, vector, &mut distances))
+                    .collect()
+            }
+            _ => vectors.iter().map(|vector| nearest_centroid(metric, centroids, vector)).collect(),
+        }
+    };
+
+    let pool = WorkerPool::global();
+    if sample.len() < PARALLEL_ASSIGN_THRESHOLD || pool.workers() == 1 {
+        return nearest(sample);
+    }
+    let chunk_size = sample.len().div_ceil(pool.workers());
+    let chunks: Vec<&[&[f32]]> = sample.chunks(chunk_size).collect();
+    pool.map(chunks.len(), |i| nearest(chunks[i])).into_iter().flatten().collect()
+}
+
+/// Le centroïde le plus proche d'un vecteur selon le carré de la distance euclidienne, les centroïdes
+/// étant rangés coordonnée par coordonnée dans `columns` ; `distances` a une place par centroïde.
+fn nearest_column(columns: &[f32], vector: &[f32], distances: &mut [f32]) -> (usize, f32) {
+    distances.fill(0.0);
+    for (&value, column) in vector.iter().zip(columns.chunks_exact(distances.len())) {
+        for (distance, &centroid) in distances.iter_mut().zip(column) {
+            let difference = value - centroid;
+            *distance += difference * difference;
+        }
+    }
+    let mut nearest = (0, f32::INFINITY);
+    for (i, &distance) in distances.iter().enumerate() {
+        if distance < nearest.1 {
+            nearest = (i, distance);
+        }
+    }
+    nearest
+}
+
+/// Apprend les centroïdes par k-moyennes (algorithme de Lloyd, initialisé par k-means++).
+///
+/// Pour la similarité cosinus, les centroïdes sont normalisés à chaque itération (k-moyennes sphériques).
+/// Une cellule qui se vide est réinitialisée sur le vecteur d'apprentissage le plus éloigné de son centroïde.
+///
+/// # Paramètres
+/// - `rng`: Le générateur utilisé pour l'initialisation.
+/// - `metric`: La métrique selon laquelle chaque vecteur est affecté à son centroïde le plus proche.
+/// - `sample`: Les vecteurs d'apprentissage, tous de même dimension.
+/// - `count`: Le nombre de centroïdes, ramené au nombre de vecteurs d'apprentissage s'il le dépasse.
+/// - `iterations`: Le nombre maximal d'itérations ; l'apprentissage s'arrête plus tôt si plus aucun
+///   vecteur ne change de cellule.
+pub fn train(rng: &mut SplitMix64, metric: Metric, sample: &[&[f32]], count: usize, iterations: usize) -> Vec<Vec<f32>> {
+    let count = count.min(sample.len());
+    if count == 0 {
+        return Vec::new();
+    }
+    let dimension = sample[0].len();
+    let mut centroids = seed_centroids(rng, sample, count);
+
+    let mut labels: Vec<usize> = vec![usize::MAX; sample.len()];
+    for _ in 0..iterations.max(1) {
+        let assigned = assign(metric, &centroids, sample);
+        let changed = assigned.iter().zip(&labels).any(|(assigned, label)| assigned.0 != *label);
+        labels = assigned.iter().map(|assigned| assigned.0).collect();
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![vec![0.0f64; dimension]; count];
+        let mut counts = vec![0usize; count];
+        for (vector, &label) in sample.iter().zip(&labels) {
+            counts[label] += 1;
+            for (sum, value) in sums[label].iter_mut().zip(vector.iter()) {
+                *sum += f64::from(*value);
+            }
+        }
+
+        // Les vecteurs les plus éloignés de leur centroïde d'abord, pour réinitialiser les cellules vides.
+        let mut farthest: Vec<usize> = Vec::new();
+        if counts.contains(&0) {
+            farthest = (0..sample.len()).collect();
+            farthest.sort_by(|&a, &b| assigned[b].1.total_cmp(&assigned[a].1).then(a.cmp(&b)));
+        }
+        let mut farthest = farthest.into_iter();
+        for (list, centroid) in centroids.iter_mut().enumerate() {
+            if counts[list] == 0 {
+                if let Some(point) = farthest.next() {
+                    centroid.copy_from_slice(sample[point]);
+                }
+                continue;
+            }
+            for (value, sum) in centroid.iter_mut().zip(&sums[list]) {
+                *value = (sum / counts[list] as f64) as f32;
+            }
+            if metric == Metric::Cosine {
+                let norm = simd::norm(centroid);
+                if norm > 0.0 {
+                    centroid.iter_mut().for_each(|value| *value /= norm);
+                }
+            }
+        }
+    }
+    centroids
+}
@@ -0,0 +1,431 @@
+//! # Module: `lsh`
+//!
+//! Index LSH (*locality-sensitive hashing*) par hyperplans aléatoires, pour la recherche approximative
+//! des plus proches voisins selon la similarité cosinus dans une [`Collection`](crate::Collection).
+//!
+//! Chaque table tire `bits` hyperplans passant par l'origine ; la signature d'un vecteur dans une table
+//! est le côté de chaque hyperplan où il se trouve, un bit par hyperplan. Deux vecteurs séparés par un
+//! angle `θ` ont le même bit avec une probabilité `1 - θ/π` : les vecteurs proches tombent souvent dans
+//! le même seau. Une recherche ne compare la requête qu'aux documents des seaux qu'elle visite, dans
+//! chacune des `tables` tables.
+//!
+//! Plutôt que de multiplier les tables, une recherche peut visiter plusieurs seaux par table (*multi-probe*) :
+//! après le seau de la requête viennent ceux dont la signature ne diffère que par les bits des hyperplans
+//! les plus proches de la requête, là où un voisin a le plus de chances d'être tombé de l'autre côté.
+//!
+//! Il n'y a rien à apprendre : un document est indexé en calculant `tables × bits` produits scalaires,
+//! si bien que l'index se construit beaucoup plus vite qu'un graphe HNSW et que chaque ajout reste peu
+//! coûteux. Les hyperplans ne dépendent que de l'angle des vecteurs ; avec une autre métrique que la
+//! similarité cosinus, les candidats sont toujours classés selon la métrique de la collection, mais ils
+//! sont choisis selon leur angle avec la requête.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::metric::Metric;
+use crate::rng::SplitMix64;
+use crate::topk::TopK;
+use crate::vectors::VectorStore;
+use crate::{simd, DocumentId, Vector};
+
+/// Graine du générateur pseudo-aléatoire qui tire les hyperplans : un même index est reconstruit
+/// à l'identique d'une exécution à l'autre.
+const HYPERPLANE_SEED: u64 = 0x5EED_15A0_0000_0001;
+
+/// Nombre maximal de bits d'une signature : elle tient dans un `u64`.
+const MAX_BITS: usize = 64;
+
+/// # Structure: `LshParams`
+///
+/// Paramètres de construction et de recherche d'un [`LshIndex`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LshParams {
+    /// Nombre d'hyperplans par table, c'est-à-dire de bits par signature (entre 1 et 64).
+    /// Plus il y en a, plus les seaux sont petits : les recherches sont plus rapides mais manquent
+    /// davantage de voisins. Autour de `log2(nombre de documents)` est un bon point de départ.
+    pub bits: usize,
+    /// Nombre de tables, chacune avec ses propres hyperplans.
+    pub tables: usize,
+    /// Nombre de seaux visités par table lors d'une recherche, celui de la requête compris.
+    pub probes: usize,
+}
+
+impl Default for LshParams {
+    fn default() -> Self {
+        LshParams {
+            bits: 12,
+            tables: 8,
+            probes: 16,
+        }
+    }
+}
+
+/// # Structure: `LshIndex`
+///
+/// Tables de hachage construites au-dessus des vecteurs d'une [`Collection`](crate::Collection).
+pub struct LshIndex {
+    params: LshParams,
+    metric: Metric,
+    /// Dimension des hyperplans, fixée par le premier vecteur indexé.
+    dimension: usize,
+    /// Les hyperplans, bout à bout : l'hyperplan `b` de la table `t` commence à `(t * bits + b) * dimension`.
+    planes: Vec<f32>,
+    /// `tables[t]` associe à chaque signature les documents de ce seau.
+    tables: Vec<HashMap<u64, Vec<DocumentId>>>,
+    /// La signature de chaque document indexé dans chaque table.
+    signatures: HashMap<DocumentId, Vec<u64>>,
+}
+
+impl LshIndex {
+    /// Tire les hyperplans puis range chaque document de `vectors` dans son seau de chaque table.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let index = LshIndex::build(LshParams { bits: 14, tables: 12, probes: 16 }, Metric::Cosine, &vectors);
+    /// ```
+    pub fn build(params: LshParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let params = LshParams {
+            bits: params.bits.clamp(1, MAX_BITS),
+            tables: params.tables.max(1),
+            probes: params.probes.max(1),
+        };
+        let mut index = LshIndex {
+            params,
+            metric,
+            dimension: 0,
+            planes: Vec::new(),
+            tables: vec![HashMap::new(); params.tables],
+            signatures: HashMap::new(),
+        };
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        index.insert_many(&keys, vectors);
+        index
+    }
+
+    /// Les paramètres de l'index, avec le nombre actuel de seaux visités par table.
+    pub fn params(&self) -> LshParams {
+        self.params
+    }
+
+    /// Modifie le nombre de seaux visités par table lors d'une recherche.
+    pub fn set_probes(&mut self, probes: usize) {
+        self.params.probes = probes.max(1);
+    }
+
+    /// Nombre de tables de l'index.
+    pub fn tables(&self) -> usize {
+        self.params.tables
+    }
+
+    /// Nombre de bits d'une signature.
+    pub fn bits(&self) -> usize {
+        self.params.bits
+    }
+
+    /// Range le document `key`, dont le vecteur doit déjà se trouver dans `vectors`, dans son seau
+    /// de chaque table. Le premier document indexé fixe la dimension des hyperplans.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        let vector = match vectors.get(&key) {
+            Some(vector) => vector,
+            None => return,
+        };
+        if self.planes.is_empty() {
+            self.draw_planes(vector.len());
+        }
+        let signatures: Vec<u64> = (0..self.params.tables)
+            .map(|table| signature(&self.projections(table, vector)))
+            .collect();
+        for (table, signature) in self.tables.iter_mut().zip(&signatures) {
+            table.entry(*signature).or_default().push(key);
+        }
+        self.signatures.insert(key, signatures);
+    }
+
+    /// Range un lot de documents ; les documents déjà indexés sont ignorés.
+    pub fn insert_many(&mut self, keys: &[DocumentId], vectors: &VectorStore) {
+        for key in keys {
+            if !self.signatures.contains_key(key) {
+                self.insert(*key, vectors);
+            }
+        }
+    }
+
+    /// Retire un document de ses seaux.
+    pub fn remove(&mut self, key: &DocumentId) {
+        let signatures = match self.signatures.remove(key) {
+            Some(signatures) => signatures,
+            None => return,
+        };
+        for (table, signature) in self.tables.iter_mut().zip(signatures) {
+            if let Some(bucket) = table.get_mut(&signature) {
+                if let Some(position) = bucket.iter().position(|candidate| candidate == key) {
+                    bucket.swap_remove(position);
+                }
+                if bucket.is_empty() {
+                    table.remove(&signature);
+                }
+            }
+        }
+    }
+
+    /// Recherche les `k` documents approximativement les plus similaires à `query`
+    /// parmi ceux des seaux visités dans chaque table.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `probes`: Le nombre de seaux à visiter par table pour cette requête ; `None` utilise celui des paramètres.
+    /// - `vectors`: Les vecteurs de la collection.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score, du plus proche au plus éloigné selon la métrique.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        probes: Option<usize>,
+        vectors: &VectorStore,
+        accept: Option<&dyn Fn(&DocumentId) -> bool>,
+    ) -> Vector {
+        let mut top = TopK::new(self.metric, k);
+        if self.planes.is_empty() {
+            return top.into_sorted_vec();
+        }
+        let probes = probes.unwrap_or(self.params.probes).max(1);
+        let mut seen = HashSet::new();
+        for (table, buckets) in self.tables.iter().enumerate() {
+            let projections = self.projections(table, query);
+            let signature = signature(&projections);
+            for flips in probe_sequence(&projections, probes) {
+                for key in buckets.get(&(signature ^ flips)).into_iter().flatten() {
+                    if !seen.insert(*key) || accept.is_some_and(|accept| !accept(key)) {
+                        continue;
+                    }
+                    if let Some(vector) = vectors.get(key) {
+                        top.push(*key, self.metric.score(query, vector));
+                    }
+                }
+            }
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Tire les hyperplans de toutes les tables, de coordonnées gaussiennes : leur direction est
+    /// uniforme sur la sphère.
+    fn draw_planes(&mut self, dimension: usize) {
+        let mut rng = SplitMix64::new(HYPERPLANE_SEED);
+        self.dimension = dimension;
+        self.planes = (0..self.params.tables * self.params.bits * dimension)
+            .map(|_| rng.next_gaussian() as f32)
+            .collect();
+    }
+
+    /// Les produits scalaires d'un vecteur avec chaque hyperplan d'une table.
+    fn projections(&self, table: usize, vector: &[f32]) -> Vec<f32> {
+        (0..self.params.bits)
+            .map(|bit| {
+                let start = (table * self.params.bits + bit) * self.dimension;
+                simd::dot(vector, &self.planes[start..start + self.dimension])
+            })
+            .collect()
+    }
+}
+
+/// La signature d'un vecteur : le bit `b` vaut 1 s'il est du côté positif de l'hyperplan `b`.
+fn signature(projections: &[f32]) -> u64 {
+    projections
+        .iter()
+        .enumerate()
+        .filter(|(_, projection)| **projection > 0.0)
+        .fold(0, |signature, (bit, _)| signature | 1 << bit)
+}
+
+/// # Structure: `Probe`
+///
+/// Un ensemble de bits à inverser dans la signature de la requête, repérés par leur rang dans l'ordre
+/// croissant des distances de la requête aux hyperplans, avec son coût : la somme des carrés de ces
+/// distances.
+struct Probe {
+    cost: f32,
+    /// Rangs des bits inversés, par ordre croissant.
+    ranks: Vec<usize>,
+}
+
+impl PartialEq for Probe {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Probe {}
+
+impl PartialOrd for Probe {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Ordre inversé : le tas binaire de [`probe_sequence`] retourne d'abord le coût le plus faible.
+impl Ord for Probe {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.cost.total_cmp(&self.cost).then_with(|| other.ranks.cmp(&self.ranks))
+    }
+}
+
+/// Les `count` premiers masques de bits à inverser dans la signature de la requête, par coût croissant,
+/// en commençant par le masque vide (le seau de la requête).
+///
+/// Chaque ensemble de rangs est engendré une seule fois à partir d'un ensemble de coût inférieur :
+/// en décalant son dernier rang d'un cran, ou en lui ajoutant le rang suivant. Seuls les ensembles
+/// effectivement visités sont calculés.
+fn probe_sequence(projections: &[f32], count: usize) -> Vec<u64> {
+    let mut order: Vec<usize> = (0..projections.len()).collect();
+    order.sort_by(|&a, &b| projections[a].abs().total_cmp(&projections[b].abs()));
+    let cost = |rank: usize| projections[order[rank]] * projections[order[rank]];
+
+    let mut masks = vec![0];
+    let mut heap = BinaryHeap::new();
+    if !order.is_empty() {
+        heap.push(Probe { cost: cost(0), ranks: vec![0] });
+    }
+    while masks.len() < count {
+        let probe = match heap.pop() {
+            Some(probe) => probe,
+            None => break,
+        };
+        masks.push(probe.ranks.iter().fold(0, |mask, &rank| mask | 1 << order[rank]));
+        let last = *probe.ranks.last().unwrap();
+        if last + 1 < order.len() {
+            let mut shifted = probe.ranks.clone();
+            *shifted.last_mut().unwrap() = last + 1;
+            heap.push(Probe {
+                cost: probe.cost - cost(last) + cost(last + 1),
+                ranks: shifted,
+            });
+            let mut expanded = probe.ranks;
+            expanded.push(last + 1);
+            heap.push(Probe {
+                cost: probe.cost + cost(last + 1),
+                ranks: expanded,
+            });
+        }
+    }
+    masks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    fn random_vectors(count: usize, seed: u64) -> VectorStore {
+        let mut rng = SplitMix64::new(seed);
+        let mut vectors = VectorStore::default();
+        for n in 0..count {
+            let vector = (0..16).map(|_| rng.next_gaussian() as f32).collect();
+            vectors.insert(id(n as u128), vector).unwrap();
+        }
+        vectors
+    }
+
+    fn exact(metric: Metric, vectors: &VectorStore, query: &[f32], k: usize) -> Vector {
+        let mut top = TopK::new(metric, k);
+        for (key, vector) in vectors.iter() {
+            top.push(*key, metric.score(query, vector));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Proportion des `k` vrais plus proches voisins retrouvés en visitant `probes` seaux par table.
+    fn recall(index: &LshIndex, vectors: &VectorStore, k: usize, probes: usize) -> f64 {
+        let queries = random_vectors(20, 99);
+        let mut found = 0;
+        for query in queries.values() {
+            let expected: HashSet<DocumentId> = exact(index.metric, vectors, query, k).into_iter().map(|(key, _)| key).collect();
+            let hits = index.search(query, k, Some(probes), vectors, None);
+            found += hits.iter().filter(|(key, _)| expected.contains(key)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    /// Coût d'un masque : la somme des carrés des projections des bits inversés.
+    fn cost(projections: &[f32], mask: u64) -> f32 {
+        (0..projections.len()).filter(|bit| mask & 1 << bit != 0).map(|bit| projections[bit] * projections[bit]).sum()
+    }
+
+    #[test]
+    fn probe_sequence_is_ordered_by_cost() {
+        // Du plus proche au plus éloigné des hyperplans : les bits 1, 3, 0 puis 2.
+        let projections = [0.5, -0.1, 2.0, -0.3];
+        assert_eq!(probe_sequence(&projections, 8), vec![0b0000, 0b0010, 0b1000, 0b1010, 0b0001, 0b0011, 0b1001, 0b1011]);
+
+        // Au-delà du nombre de seaux, chaque masque n'apparaît qu'une fois.
+        let masks = probe_sequence(&projections, 100);
+        assert_eq!(masks.len(), 16);
+        assert_eq!(masks.iter().collect::<HashSet<_>>().len(), 16);
+        assert!(masks.windows(2).all(|pair| cost(&projections, pair[0]) <= cost(&projections, pair[1])));
+
+        assert_eq!(probe_sequence(&projections, 1), vec![0]);
+        assert_eq!(probe_sequence(&[], 5), vec![0]);
+    }
+
+    #[test]
+    fn probe_sequence_follows_the_cost_on_random_projections() {
+        let mut rng = SplitMix64::new(1);
+        let projections: Vec<f32> = (0..12).map(|_| rng.next_gaussian() as f32).collect();
+        let masks = probe_sequence(&projections, 200);
+        assert_eq!(masks.len(), 200);
+        assert_eq!(masks.iter().collect::<HashSet<_>>().len(), 200);
+        assert!(masks.windows(2).all(|pair| cost(&projections, pair[0]) <= cost(&projections, pair[1]) + 1e-6));
+
+        // Ce sont bien les 200 masques les moins coûteux.
+        let mut costs: Vec<f32> = (0..1u64 << 12).map(|mask| cost(&projections, mask)).collect();
+        costs.sort_by(f32::total_cmp);
+        assert!((cost(&projections, masks[199]) - costs[199]).abs() <= 1e-6);
+    }
+
+    #[test]
+    fn recall_grows_with_the_probes() {
+        let vectors = random_vectors(2_000, 2);
+        let index = LshIndex::build(LshParams { bits: 10, tables: 8, probes: 1 }, Metric::Cosine, &vectors);
+        let narrow = recall(&index, &vectors, 10, 1);
+        let wide = recall(&index, &vectors, 10, 32);
+        assert!(wide > narrow, "32 seaux : {}, 1 seau : {}", wide, narrow);
+        assert!(wide >= 0.8, "32 seaux : {}", wide);
+    }
+
+    #[test]
+    fn visiting_every_bucket_matches_the_exact_scan() {
+        let vectors = random_vectors(300, 3);
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot] {
+            let index = LshIndex::build(LshParams { bits: 3, tables: 1, probes: 8 }, metric, &vectors);
+            let query = vectors.get(&id(42)).unwrap();
+            assert_eq!(index.search(query, 10, None, &vectors, None), exact(metric, &vectors, query, 10));
+        }
+    }
+
+    #[test]
+    fn insert_and_remove_update_the_buckets() {
+        let mut vectors = random_vectors(200, 4);
+        let mut index = LshIndex::build(LshParams::default(), Metric::Cosine, &vectors);
+        // Les hyperplans sont tirés d'une graine fixe : l'index est reconstruit à l'identique.
+        assert_eq!(index.signatures, LshIndex::build(LshParams::default(), Metric::Cosine, &vectors).signatures);
+
+        let query = vec![1.0; 16];
+        vectors.insert(id(1_000), query.clone()).unwrap();
+        index.insert(id(1_000), &vectors);
+        assert_eq!(index.search(&query, 1, Some(1), &vectors, None)[0].0, id(1_000));
+
+        index.remove(&id(1_000));
+        assert!(!index.signatures.contains_key(&id(1_000)));
+        assert!(index.tables.iter().flat_map(|table| table.values()).all(|bucket| !bucket.is_empty() && !bucket.contains(&id(1_000))));
+        assert!(index.search(&query, 10, None, &vectors, None).iter().all(|(key, _)| *key != id(1_000)));
+    }
+}
@@ -1,7 +1,61 @@
-use std::collections::HashMap;
-use std::thread;
+mod arrow;
+mod bq;
+mod cli;
+mod columnar;
+mod csv;
+mod error;
+mod filter;
+mod flatbuffers;
+mod hnsw;
+mod index;
+mod inflate;
+mod ivf;
+mod json;
+mod kmeans;
+mod lsh;
+mod metric;
+mod npy;
+mod mmap;
+mod parquet;
+mod payload;
+mod pool;
+mod pq;
+mod quantizer;
+mod rng;
+mod server;
+mod simd;
+mod snappy;
+mod sq;
+mod storage;
+mod texmex;
+mod thrift;
+mod topk;
+mod transfer;
+mod vectors;
+mod zip;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, Read, Seek, Write};
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
+use columnar::{TableOptions, TableReader, TableWriter};
+use error::DbError;
+use filter::{Filter, PayloadIndex};
+use index::{IndexConfig, VectorIndex};
+use metric::Metric;
+use npy::{MatrixReader, NpyHeader};
+use payload::Payload;
+use pool::WorkerPool;
+use quantizer::{Quantizer, QuantizerConfig};
+use rng::SplitMix64;
+use storage::{Record, Recovered, Storage, StorageOptions};
+use texmex::{VecsFormat, VecsReader};
+use topk::TopK;
+use transfer::{DocumentReader, DocumentWriter, ErrorPolicy, Format, ImportOptions, ImportReport};
+use vectors::VectorStore;
+use zip::ZipArchive;
+
 /// # Type: `DocumentId`
 ///
 /// `DocumentId` est un alias pour [`Uuid`].  
@@ -14,161 +68,1995 @@ type DocumentId = Uuid;
 /// Il est utilisé pour représenter un ensemble de résultats de recherche (par exemple, un score de similarité associé à un identifiant de document).
 type Vector = Vec<(DocumentId, f32)>;
 
-/// # Type: `SearchResult`
+/// # Type: `Document`
+///
+/// Un document complet : son identifiant, son vecteur et ses métadonnées.
+/// Utilisé par les écritures groupées ([`Collection::upsert_many`]).
+type Document = (DocumentId, Vec<f32>, Payload);
+
+/// # Énumération: `ItemStatus`
+///
+/// Ce qu'une écriture groupée a fait de chaque élément de son lot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ItemStatus {
+    /// Le document n'existait pas et a été ajouté.
+    Inserted,
+    /// Le document existait et a été remplacé.
+    Updated,
+    /// Le document a été supprimé.
+    Removed,
+    /// Le document à supprimer n'existait pas.
+    NotFound,
+}
+
+impl ItemStatus {
+    /// Nom de l'état, tel qu'il apparaît dans les réponses JSON.
+    fn name(self) -> &'static str {
+        match self {
+            ItemStatus::Inserted => "inserted",
+            ItemStatus::Updated => "updated",
+            ItemStatus::Removed => "removed",
+            ItemStatus::NotFound => "not_found",
+        }
+    }
+}
+
+/// # Structure: `SearchOptions`
+///
+/// Les options d'une recherche par la [`Database`] ([`Database::search_in_collection`],
+/// [`Database::search_range`] et [`Database::search_batch`]).
+#[derive(Debug, Clone, Copy, Default)]
+struct SearchOptions<'a> {
+    /// Un filtre optionnel sur les métadonnées des documents.
+    filter: Option<&'a Filter>,
+    /// Compare la requête à tous les documents plutôt que de passer par l'index ou le quantificateur.
+    exact: bool,
+    /// Ajoute aux résultats les métadonnées des documents trouvés.
+    with_payload: bool,
+}
+
+/// # Structure: `SearchResult`
+///
+/// `SearchResult` désigne la liste finale de résultats d'une recherche.
+/// Il contient les couples (`DocumentId`, score) classés du plus proche au plus éloigné,
+/// ainsi que la [`Metric`] qui a produit les scores. Les métadonnées des documents trouvés
+/// peuvent y être ajoutées avec [`Collection::with_payloads`].
+struct SearchResult {
+    /// La métrique qui a produit les scores (similarité ou distance).
+    metric: Metric,
+    /// Les résultats, du meilleur score au moins bon.
+    hits: Vector,
+    /// Les métadonnées de chaque résultat, dans le même ordre que `hits`, si elles ont été demandées.
+    payloads: Option<Vec<Payload>>,
+}
+
+impl IntoIterator for SearchResult {
+    type Item = (DocumentId, f32);
+    type IntoIter = std::vec::IntoIter<(DocumentId, f32)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.hits.into_iter()
+    }
+}
+
+/// # Structure: `QuantizationReport`
+///
+/// Ce que coûte la quantification d'une collection, mesuré par [`Collection::quantization_report`].
+#[derive(Debug, Clone, Copy)]
+struct QuantizationReport {
+    /// Le type de quantification (voir [`Quantizer::name`]).
+    quantization: &'static str,
+    /// Taille du code d'un document, en octets.
+    code_bytes: usize,
+    /// Taille du vecteur d'origine d'un document, en octets.
+    vector_bytes: usize,
+    /// Mémoire occupée par le quantificateur (codes, identifiants et dictionnaires), en octets.
+    memory_bytes: usize,
+    /// Nombre de requêtes de mesure.
+    queries: usize,
+    /// Nombre de voisins comparés par requête.
+    k: usize,
+    /// Proportion des `k` plus proches voisins exacts retrouvée par la recherche sur les codes.
+    recall: f64,
+}
+
+/// Nombre de documents satisfaisant un filtre en dessous duquel une recherche filtrée
+/// compare directement la requête à ces documents plutôt que de parcourir l'index.
+const FILTER_BRUTE_FORCE_LIMIT: usize = 5_000;
+
+/// Nombre de documents à partir duquel une recherche exhaustive répartit le calcul des scores
+/// entre les threads du [`WorkerPool`]. En dessous, le coût de la répartition l'emporte.
+const PARALLEL_SCAN_THRESHOLD: usize = 2_048;
+
+/// Nombre maximal de requêtes d'une recherche groupée comparées ensemble à un même bloc de documents.
+const QUERY_BLOCK: usize = 16;
+
+/// Taille visée, en octets, d'un bloc de documents d'une recherche groupée exhaustive :
+/// le bloc reste dans le cache du processeur le temps d'y comparer toutes les requêtes d'un bloc.
+const DOCUMENT_BLOCK_BYTES: usize = 128 * 1_024;
+
+/// Nombre de voisins demandés à l'index au premier tour d'une recherche par seuil ;
+/// il est doublé à chaque tour tant que le moins bon voisin trouvé atteint encore le seuil.
+const RANGE_INITIAL_K: usize = 64;
+
+/// Graine du tirage des documents utilisés comme requêtes par [`Collection::quantization_report`].
+const REPORT_SEED: u64 = 0x5EED_4E90_0000_0001;
+
+/// # Structure: `CollectionConfig`
 ///
-/// `SearchResult` est un alias de [`Vector`].
-/// Il désigne la liste finale de résultats d'une recherche.
-type SearchResult = Vector;
+/// `CollectionConfig` regroupe les choix faits à la création d'une [`Collection`].
+#[derive(Debug, Clone, Copy, Default)]
+struct CollectionConfig {
+    /// La métrique utilisée pour comparer la requête aux documents.
+    metric: Metric,
+    /// La dimension des vecteurs de la collection. Si elle vaut `None`,
+    /// elle est fixée par le premier vecteur inséré.
+    dimension: Option<usize>,
+    /// Normalise chaque vecteur inséré (norme 1, un vecteur nul restant nul). Les similarités cosinus
+    /// sont inchangées à l'arrondi près, et le produit scalaire devient une similarité cosinus.
+    normalize: bool,
+    /// Range les vecteurs dans un fichier projeté en mémoire plutôt que dans le tas : le système ne garde
+    /// en mémoire que les pages utilisées, et la collection peut dépasser la mémoire vive (voir [`mmap`]).
+    mapped: bool,
+}
 
 /// # Structure: `Collection`
 ///
 /// `Collection` gère un ensemble de documents, identifiés par [`DocumentId`], et stocke leurs vecteurs (par exemple, leur représentation numérique).
 /// Elle offre des méthodes pour ajouter, mettre à jour, supprimer et rechercher des documents.
+/// Les documents sont comparés avec la [`Metric`] choisie à la création de la collection,
+/// et tous les vecteurs (documents comme requêtes) doivent avoir la même dimension.
 struct Collection {
-    /// Les données de la collection stockées sous forme de clé-valeur (`DocumentId`, vecteur).
-    data: HashMap<DocumentId, Vec<f32>>,
+    /// La métrique utilisée pour comparer la requête aux documents.
+    metric: Metric,
+    /// La dimension des vecteurs, fixée à la création ou lors de la première insertion.
+    dimension: Option<usize>,
+    /// Normalise les vecteurs à l'insertion (voir [`CollectionConfig::normalize`]).
+    normalize: bool,
+    /// Les vecteurs de la collection, en mémoire ou dans un fichier projeté en mémoire
+    /// (voir [`CollectionConfig::mapped`]).
+    data: VectorStore,
+    /// La norme de chaque vecteur, calculée à l'insertion : un score cosinus se réduit alors à un produit scalaire.
+    norms: HashMap<DocumentId, f32>,
+    /// Les métadonnées de chaque document (vides si aucune n'a été fournie).
+    payloads: HashMap<DocumentId, Payload>,
+    /// L'index inversé des champs de métadonnées indexés, utilisé par les recherches filtrées.
+    payload_index: PayloadIndex,
+    /// L'index de recherche approximative optionnel (HNSW, IVF ou LSH), maintenu à jour à chaque ajout ou suppression.
+    index: Option<VectorIndex>,
+    /// Le quantificateur optionnel (par produit, scalaire ou binaire), maintenu à jour à chaque ajout ou suppression.
+    quantizer: Option<Quantizer>,
 }
 
-impl Collection {
-    /// Crée une nouvelle instance de [`Collection`].
+impl Collection {
+    /// Crée une nouvelle instance de [`Collection`], dont les vecteurs sont gardés en mémoire
+    /// quel que soit [`CollectionConfig::mapped`] (voir [`Collection::open`]).
+    ///
+    /// # Paramètres
+    /// - `config`: La métrique et, éventuellement, la dimension des vecteurs de la collection.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let collection = Collection::new(CollectionConfig { metric: Metric::Cosine, dimension: Some(3), ..Default::default() });
+    /// ```
+    fn new(config: CollectionConfig) -> Self {
+        Collection {
+            metric: config.metric,
+            dimension: config.dimension,
+            normalize: config.normalize,
+            data: VectorStore::default(),
+            norms: HashMap::new(),
+            payloads: HashMap::new(),
+            payload_index: PayloadIndex::default(),
+            index: None,
+            quantizer: None,
+        }
+    }
+
+    /// Crée une collection selon `config`, y compris, si [`CollectionConfig::mapped`] est vrai,
+    /// le fichier projeté en mémoire où seront rangés ses vecteurs.
+    ///
+    /// # Paramètres
+    /// - `config`: Les choix faits à la création de la collection.
+    /// - `directory`: Le répertoire de données, où le fichier de vecteurs est conservé ; `None` crée un fichier
+    ///   temporaire dans le répertoire temporaire du système.
+    ///
+    /// # Retour
+    /// - `Result<Collection, DbError>`: [`DbError::Io`] si le fichier de vecteurs ne peut pas être créé.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let config = CollectionConfig { dimension: Some(768), mapped: true, ..Default::default() };
+    /// let collection = Collection::open(config, Some(Path::new("./data")))?;
+    /// ```
+    fn open(config: CollectionConfig, directory: Option<&Path>) -> Result<Self, DbError> {
+        let mut collection = Collection::new(config);
+        if config.mapped {
+            collection.data = VectorStore::mapped(directory)?;
+        }
+        Ok(collection)
+    }
+
+    /// Vérifie qu'un vecteur (document ou requête) peut être utilisé dans la collection :
+    /// il doit être non vide, ne contenir que des valeurs finies et avoir la dimension de la collection.
+    ///
+    /// # Paramètres
+    /// - `vector`: Le vecteur à vérifier.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: `Ok(())` si le vecteur est valide, l'erreur correspondante sinon.
+    fn validate(&self, vector: &[f32]) -> Result<(), DbError> {
+        self.validate_with_dimension(vector, self.dimension)
+    }
+
+    /// Comme [`Collection::validate`], avec la dimension attendue donnée explicitement.
+    fn validate_with_dimension(&self, vector: &[f32], dimension: Option<usize>) -> Result<(), DbError> {
+        if vector.is_empty() {
+            return Err(DbError::EmptyVector);
+        }
+        if let Some(expected) = dimension {
+            if vector.len() != expected {
+                return Err(DbError::DimensionMismatch {
+                    expected,
+                    found: vector.len(),
+                });
+            }
+        }
+        match vector.iter().position(|value| !value.is_finite()) {
+            Some(position) => Err(DbError::InvalidValue {
+                position,
+                value: vector[position],
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Ajoute ou met à jour le vecteur et les métadonnées associés à un [`DocumentId`].
+    ///
+    /// Si la collection n'a pas encore de dimension, celle du vecteur devient la dimension de la collection.
+    /// Une mise à jour remplace à la fois le vecteur et les métadonnées du document.
+    ///
+    /// # Paramètres
+    /// - `key`: L'identifiant unique du document.
+    /// - `vector`: Le vecteur associé au document (ex. représentation sémantique).
+    /// - `payload`: Les métadonnées du document (éventuellement vides).
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si le vecteur est vide, contient une valeur non finie
+    ///   ou n'a pas la dimension de la collection, ou [`DbError::Io`] si le fichier des vecteurs
+    ///   d'une collection projetée en mémoire n'a pas pu être agrandi.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut collection = Collection::new(CollectionConfig::default());
+    /// let doc_id = Uuid::new_v4();
+    /// let payload = Payload::from([("titre".to_string(), Value::from("Acte de vente"))]);
+    /// collection.add_or_update(doc_id, vec![1.0, 2.0, 3.0], payload)?;
+    /// ```
+    fn add_or_update(&mut self, key: DocumentId, vector: Vec<f32>, payload: Payload) -> Result<(), DbError> {
+        self.validate(&vector)?;
+        let vector = if self.normalize { normalized(vector) } else { vector };
+        self.insert(key, vector, payload)
+    }
+
+    /// Rattache à la collection un document dont le vecteur est déjà dans son fichier de vecteurs, rouvert
+    /// depuis un instantané : seules sa norme et ses métadonnées sont enregistrées.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::Corrupted`] si le fichier n'a pas de vecteur pour ce document,
+    ///   ou l'erreur de validation si son vecteur n'est pas valide pour la collection.
+    fn reattach(&mut self, key: DocumentId, payload: Payload) -> Result<(), DbError> {
+        let vector = self.data.get(&key).ok_or_else(|| DbError::Corrupted(format!("document {} sans vecteur", key)))?;
+        self.validate(vector)?;
+        let norm = simd::norm(vector);
+        self.norms.insert(key, norm);
+        self.payload_index.insert(key, &payload);
+        self.payloads.insert(key, payload);
+        Ok(())
+    }
+
+    /// Réinsère un document tel qu'il a été stocké, par exemple depuis un instantané :
+    /// contrairement à [`Collection::add_or_update`], le vecteur n'est pas normalisé une seconde fois.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: L'erreur de validation si le vecteur n'est pas valide pour la collection.
+    fn restore(&mut self, key: DocumentId, vector: Vec<f32>, payload: Payload) -> Result<(), DbError> {
+        self.validate(&vector)?;
+        self.insert(key, vector, payload)
+    }
+
+    /// Enregistre un document déjà validé et met à jour les index.
+    fn insert(&mut self, key: DocumentId, vector: Vec<f32>, payload: Payload) -> Result<(), DbError> {
+        let previous = self.store(key, vector, payload)?;
+        if let Some(index) = self.index.as_mut() {
+            if let Some(old_vector) = previous {
+                index.remove(&key, old_vector, &self.data);
+            }
+            index.insert(key, &self.data);
+        }
+        if let Some(quantizer) = self.quantizer.as_mut() {
+            quantizer.insert(key, &self.data);
+        }
+        Ok(())
+    }
+
+    /// Enregistre un document déjà validé sans toucher aux index.
+    ///
+    /// Le vecteur est écrit en premier : si son stockage échoue, la collection n'est pas modifiée.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<f32>>, DbError>`: L'ancien vecteur du document, s'il existait.
+    fn store(&mut self, key: DocumentId, vector: Vec<f32>, payload: Payload) -> Result<Option<Vec<f32>>, DbError> {
+        let dimension = vector.len();
+        let norm = simd::norm(&vector);
+        let previous = self.data.insert(key, vector)?;
+        self.dimension = Some(dimension);
+        if let Some(old_payload) = self.payloads.remove(&key) {
+            self.payload_index.remove(&key, &old_payload);
+        }
+        self.payload_index.insert(key, &payload);
+        self.payloads.insert(key, payload);
+        self.norms.insert(key, norm);
+        Ok(previous)
+    }
+
+    /// Ajoute ou met à jour un lot de documents de façon atomique : si un seul vecteur est invalide,
+    /// aucun document n'est modifié.
+    ///
+    /// Les vecteurs sont tous vérifiés avant la première écriture ; dans une collection sans dimension,
+    /// le premier vecteur du lot fixe celle des suivants. L'index est mis à jour une fois pour
+    /// tout le lot (et reconstruit d'un coup si le lot est plus grand que l'index).
+    /// Si un identifiant apparaît plusieurs fois, le dernier document du lot l'emporte.
+    ///
+    /// # Paramètres
+    /// - `documents`: Les documents à écrire, sous la forme (`DocumentId`, vecteur, métadonnées).
+    ///
+    /// # Retour
+    /// - `Result<Vec<ItemStatus>, DbError>`: L'état de chaque document du lot, dans l'ordre du lot
+    ///   ([`ItemStatus::Inserted`] ou [`ItemStatus::Updated`]), ou [`DbError::InvalidBatch`]
+    ///   avec la position et l'erreur de chaque document invalide.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let statuses = collection.upsert_many(vec![
+    ///     (Uuid::new_v4(), vec![1.0, 2.0, 3.0], Payload::new()),
+    ///     (doc_id, vec![0.5, 0.0, 1.0], Payload::new()),
+    /// ])?;
+    /// ```
+    fn upsert_many(&mut self, documents: Vec<Document>) -> Result<Vec<ItemStatus>, DbError> {
+        self.validate_batch(&documents)?;
+        let statuses = self.upsert_statuses(&documents);
+        // La place des vecteurs est prise avant la première écriture, pour que le lot ne puisse pas
+        // s'arrêter à mi-chemin faute de place dans un fichier projeté (où un vecteur remplacé prend
+        // lui aussi un nouvel emplacement).
+        if let Some((_, vector, _)) = documents.first() {
+            self.data.reserve(documents.len(), vector.len())?;
+        }
+
+        let mut keys = Vec::with_capacity(documents.len());
+        let mut replaced = Vec::new();
+        for (key, vector, payload) in documents {
+            let vector = if self.normalize { normalized(vector) } else { vector };
+            if let Some(old_vector) = self.store(key, vector, payload)? {
+                replaced.push((key, old_vector));
+            }
+            keys.push(key);
+        }
+        if let Some(index) = self.index.as_mut() {
+            index.remove_many(replaced, &self.data);
+            index.insert_many(&keys, &self.data);
+        }
+        if let Some(quantizer) = self.quantizer.as_mut() {
+            quantizer.insert_many(&keys, &self.data);
+        }
+        Ok(statuses)
+    }
+
+    /// Vérifie tous les vecteurs d'un lot, comme le ferait une suite d'appels à [`Collection::add_or_update`].
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::InvalidBatch`] avec chaque document invalide du lot.
+    fn validate_batch(&self, documents: &[Document]) -> Result<(), DbError> {
+        let mut dimension = self.dimension;
+        let mut errors = Vec::new();
+        for (position, (_, vector, _)) in documents.iter().enumerate() {
+            match self.validate_with_dimension(vector, dimension) {
+                Ok(()) => dimension = Some(vector.len()),
+                Err(error) => errors.push((position, error)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(DbError::InvalidBatch(errors))
+        }
+    }
+
+    /// L'état que produirait l'écriture de chaque document du lot.
+    fn upsert_statuses(&self, documents: &[Document]) -> Vec<ItemStatus> {
+        let mut written = HashSet::new();
+        documents
+            .iter()
+            .map(|(key, _, _)| {
+                if written.insert(*key) && !self.data.contains_key(key) {
+                    ItemStatus::Inserted
+                } else {
+                    ItemStatus::Updated
+                }
+            })
+            .collect()
+    }
+
+    /// Supprime un lot de documents. L'index est mis à jour une fois pour tout le lot.
+    ///
+    /// # Paramètres
+    /// - `keys`: Les identifiants des documents à supprimer.
+    ///
+    /// # Retour
+    /// - `Vec<ItemStatus>`: L'état de chaque identifiant, dans l'ordre du lot : [`ItemStatus::Removed`],
+    ///   ou [`ItemStatus::NotFound`] si le document n'existait pas (ou a déjà été supprimé plus tôt dans le lot).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let statuses = collection.remove_many(&[doc_id, other_id]);
+    /// ```
+    fn remove_many(&mut self, keys: &[DocumentId]) -> Vec<ItemStatus> {
+        let statuses = self.remove_statuses(keys);
+        let mut removed = Vec::new();
+        for key in keys {
+            if let Some(old_payload) = self.payloads.remove(key) {
+                self.payload_index.remove(key, &old_payload);
+            }
+            self.norms.remove(key);
+            if let Some(old_vector) = self.data.remove(key) {
+                removed.push((*key, old_vector));
+            }
+            if let Some(quantizer) = self.quantizer.as_mut() {
+                quantizer.remove(key);
+            }
+        }
+        if let Some(index) = self.index.as_mut() {
+            index.remove_many(removed, &self.data);
+        }
+        statuses
+    }
+
+    /// L'état que produirait la suppression de chaque identifiant du lot.
+    fn remove_statuses(&self, keys: &[DocumentId]) -> Vec<ItemStatus> {
+        let mut removed = HashSet::new();
+        keys.iter()
+            .map(|key| {
+                if self.data.contains_key(key) && removed.insert(*key) {
+                    ItemStatus::Removed
+                } else {
+                    ItemStatus::NotFound
+                }
+            })
+            .collect()
+    }
+
+    /// Récupère le vecteur associé à un [`DocumentId`], s'il existe.
+    ///
+    /// # Paramètres
+    /// - `key`: La référence à l'identifiant unique du document.
+    ///
+    /// # Retour
+    /// - `Option<&[f32]>`: Le vecteur si le document est trouvé, ou `None` sinon.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let doc = collection.get(&doc_id);
+    /// if let Some(vector) = doc {
+    ///     // Utiliser le vecteur
+    /// }
+    /// ```
+    fn get(&self, key: &DocumentId) -> Option<&[f32]> {
+        self.data.get(key)
+    }
+
+    /// Récupère les métadonnées associées à un [`DocumentId`], s'il existe.
+    ///
+    /// # Paramètres
+    /// - `key`: La référence à l'identifiant unique du document.
+    ///
+    /// # Retour
+    /// - `Option<&Payload>`: Les métadonnées (éventuellement vides) si le document est trouvé, ou `None` sinon.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// if let Some(payload) = collection.get_payload(&doc_id) {
+    ///     println!("Client: {:?}", payload.get("client"));
+    /// }
+    /// ```
+    fn get_payload(&self, key: &DocumentId) -> Option<&Payload> {
+        self.payloads.get(key)
+    }
+
+    /// Retourne le nombre de documents de la collection.
+    fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Retourne les identifiants des documents de la collection, dans un ordre quelconque.
+    fn keys(&self) -> impl Iterator<Item = &DocumentId> {
+        self.data.keys()
+    }
+
+    /// Ajoute à un résultat de recherche les métadonnées de chacun des documents trouvés.
+    ///
+    /// # Paramètres
+    /// - `result`: Un résultat obtenu par une recherche dans cette collection.
+    ///
+    /// # Retour
+    /// - [`SearchResult`]: Le même résultat, avec `payloads` renseigné dans l'ordre des `hits`.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let results = collection.with_payloads(collection.search(&query, 3)?);
+    /// ```
+    fn with_payloads(&self, mut result: SearchResult) -> SearchResult {
+        let payloads = result
+            .hits
+            .iter()
+            .map(|(key, _)| self.payloads.get(key).cloned().unwrap_or_default())
+            .collect();
+        result.payloads = Some(payloads);
+        result
+    }
+
+    /// Supprime le document (son vecteur et ses métadonnées) associé à un [`DocumentId`].
+    ///
+    /// # Paramètres
+    /// - `key`: La référence à l'identifiant unique du document.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// collection.remove(&doc_id);
+    /// ```
+    fn remove(&mut self, key: &DocumentId) {
+        if let Some(old_payload) = self.payloads.remove(key) {
+            self.payload_index.remove(key, &old_payload);
+        }
+        self.norms.remove(key);
+        if let Some(old_vector) = self.data.remove(key) {
+            if let Some(index) = self.index.as_mut() {
+                index.remove(key, old_vector, &self.data);
+            }
+        }
+        if let Some(quantizer) = self.quantizer.as_mut() {
+            quantizer.remove(key);
+        }
+    }
+
+    /// Construit un index de recherche approximative sur les documents de la collection.
+    ///
+    /// - Un index HNSW ([`IndexConfig::Hnsw`]) est un graphe de voisinage, parcouru à partir de la requête.
+    /// - Un index IVF-Flat ([`IndexConfig::Ivf`]) apprend ses centroïdes par k-moyennes sur les vecteurs de la
+    ///   collection (ou sur un échantillon, voir [`ivf::IvfParams::sample_size`]) et range chaque document dans
+    ///   la liste de son centroïde le plus proche ; une recherche parcourt les `nprobe` listes les plus proches.
+    /// - Un index LSH ([`IndexConfig::Lsh`]) range chaque document, dans chacune des `tables` tables, dans le seau
+    ///   de sa signature de `bits` bits. Il n'y a rien à apprendre : il se construit bien plus vite qu'un index
+    ///   HNSW, et chaque document ajouté ensuite ne coûte que `tables × bits` produits scalaires.
+    ///
+    /// Une fois l'index construit, [`Collection::search`] l'utilise pour une recherche approximative ;
+    /// il est ensuite maintenu à jour par [`Collection::add_or_update`] et [`Collection::remove`].
+    /// Un index existant est remplacé.
+    ///
+    /// # Paramètres
+    /// - `config`: Le type de l'index et ses paramètres.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// collection.build_index(IndexConfig::Hnsw(HnswParams { m: 16, ef_construction: 200, ef_search: 64 }));
+    /// collection.build_index(IndexConfig::Ivf(IvfParams { nlist: 256, nprobe: 16, sample_size: Some(50_000), iterations: 25 }));
+    /// ```
+    fn build_index(&mut self, config: IndexConfig) {
+        self.index = Some(VectorIndex::build(config, self.metric, &self.data));
+    }
+
+    /// Quantifie les vecteurs de la collection et encode chaque document.
+    ///
+    /// - Un quantificateur par produit ([`QuantizerConfig::Product`]) découpe les vecteurs en sous-espaces
+    ///   et apprend par k-moyennes un dictionnaire de centroïdes par sous-espace : un octet par sous-espace.
+    /// - Un quantificateur scalaire ([`QuantizerConfig::Scalar`]) code chaque coordonnée sur 8 ou 4 bits,
+    ///   entre les bornes relevées sur les vecteurs de la collection (par coordonnée ou globales).
+    /// - Un quantificateur binaire ([`QuantizerConfig::Binary`]) ne garde que le signe de chaque coordonnée,
+    ///   sur un bit, et compare les codes par distance de Hamming.
+    ///
+    /// Sans index de recherche approximative, [`Collection::search`] compare alors la requête aux codes
+    /// des documents plutôt qu'à leurs vecteurs, puis reclasse les meilleurs candidats avec leurs vecteurs
+    /// d'origine (toujours pour un quantificateur binaire, selon ses paramètres sinon). Les documents ajoutés
+    /// ensuite sont encodés avec les mêmes dictionnaires ou les mêmes bornes. Un quantificateur existant est remplacé.
+    ///
+    /// Sur une collection vide, un quantificateur par produit ou scalaire n'a rien sur quoi apprendre :
+    /// les recherches restent exhaustives tant qu'il n'a pas été reconstruit ([`Database::set_quantizer`]
+    /// le refuse). Un quantificateur binaire prend la dimension du premier document ajouté.
+    ///
+    /// # Paramètres
+    /// - `config`: Le type du quantificateur et ses paramètres.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// collection.build_quantizer(QuantizerConfig::Product(PqParams { subspaces: 16, rerank: Some(100), ..Default::default() }));
+    /// collection.build_quantizer(QuantizerConfig::Binary(BqParams { oversampling: 8.0 }));
+    /// let report = collection.quantization_report(100, 10);
+    /// ```
+    fn build_quantizer(&mut self, config: QuantizerConfig) {
+        self.quantizer = Some(Quantizer::build(config, self.metric, &self.data));
+    }
+
+    /// Supprime le quantificateur : les recherches comparent de nouveau la requête aux vecteurs d'origine.
+    fn drop_quantizer(&mut self) {
+        self.quantizer = None;
+    }
+
+    /// Mesure ce que coûte la quantification de la collection, en mémoire et en rappel.
+    ///
+    /// Le rappel est la proportion des `k` plus proches voisins exacts retrouvée par la recherche
+    /// sur les codes, en moyenne sur `queries` documents de la collection tirés au hasard
+    /// et utilisés comme requêtes.
+    ///
+    /// # Paramètres
+    /// - `queries`: Le nombre de requêtes de mesure.
+    /// - `k`: Le nombre de voisins comparés par requête.
+    ///
+    /// # Retour
+    /// - `Option<QuantizationReport>`: `None` si la collection n'est pas quantifiée.
+    fn quantization_report(&self, queries: usize, k: usize) -> Option<QuantizationReport> {
+        let quantizer = self.active_quantizer()?;
+        let mut keys: Vec<&DocumentId> = self.data.keys().collect();
+        keys.sort();
+        let mut rng = SplitMix64::new(REPORT_SEED);
+        let queries = queries.min(keys.len());
+        for i in 0..queries {
+            let j = i + rng.below(keys.len() - i);
+            keys.swap(i, j);
+        }
+
+        let mut found = 0;
+        let mut expected = 0;
+        for key in &keys[..queries] {
+            let query = &self.data[*key];
+            let exact = self.rank(query, k, self.data.keys());
+            let approximate = quantizer.search(query, k, &self.data, None);
+            expected += exact.len();
+            found += approximate
+                .iter()
+                .filter(|(key, _)| exact.iter().any(|(exact_key, _)| exact_key == key))
+                .count();
+        }
+        Some(QuantizationReport {
+            quantization: quantizer.name(),
+            code_bytes: quantizer.code_bytes(),
+            vector_bytes: self.dimension.unwrap_or(0) * std::mem::size_of::<f32>(),
+            memory_bytes: quantizer.memory_bytes(),
+            queries,
+            k,
+            recall: if expected == 0 { 1.0 } else { found as f64 / expected as f64 },
+        })
+    }
+
+    /// Indexe un champ des métadonnées pour accélérer les recherches filtrées sur ce champ
+    /// (égalités et `IN`). L'index est ensuite maintenu à jour à chaque ajout ou suppression.
+    ///
+    /// # Paramètres
+    /// - `field`: Le nom du champ, éventuellement imbriqué (`client.ville`).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// collection.create_payload_index("client");
+    /// ```
+    fn create_payload_index(&mut self, field: &str) {
+        self.payload_index.create_field(field, &self.payloads);
+    }
+
+    /// Supprime l'index de recherche approximative : [`Collection::search`] revient alors au parcours
+    /// exhaustif, ou au parcours des codes si la collection est quantifiée.
+    fn drop_index(&mut self) {
+        self.index = None;
+    }
+
+    /// Modifie les réglages de recherche de l'index, s'il existe, sans le reconstruire (voir [`VectorIndex::tune`]).
+    ///
+    /// # Paramètres
+    /// - `ef_search`: Pour un index HNSW, la taille de la liste de candidats explorée à chaque recherche.
+    ///   Une valeur plus grande améliore le rappel au prix de la latence.
+    /// - `nprobe`: Pour un index IVF, le nombre de listes parcourues ; pour un index LSH, le nombre
+    ///   de seaux visités par table.
+    fn tune_index(&mut self, ef_search: Option<usize>, nprobe: Option<usize>) {
+        if let Some(index) = self.index.as_mut() {
+            index.tune(ef_search, nprobe);
+        }
+    }
+
+    /// Recherche les documents les plus proches d'une requête donnée selon la [`Metric`] de la collection.
+    ///
+    /// Si un index (HNSW, IVF ou LSH) a été construit, la recherche est approximative et passe par l'index ;
+    /// sinon elle est déléguée à [`Collection::search_exact`].
+    ///
+    /// Avec un filtre, seuls les documents dont les métadonnées le satisfont sont classés.
+    /// Si ces documents sont peu nombreux, ils sont comparés directement à la requête ;
+    /// sinon l'index est parcouru en n'acceptant que les documents qui satisfont le filtre.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur représentant la requête de recherche.
+    /// - `k`: Le nombre maximal de résultats à retourner.
+    /// - `filter`: Un filtre optionnel sur les métadonnées des documents.
+    ///
+    /// # Retour
+    /// - `Result<SearchResult, DbError>`: Une liste de paires (`DocumentId`, score) classées du plus proche au plus éloigné :
+    ///   par score décroissant pour une similarité, par score croissant pour une distance.
+    ///   Une erreur si la requête n'est pas un vecteur valide pour la collection.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let filter = Filter::parse(r#"type == "acte" AND annee >= 2020"#)?;
+    /// let results = collection.search(&[1.0, 1.0, 1.0], 3, Some(&filter))?;
+    /// for (doc_id, score) in results {
+    ///     println!("DocID: {}, Score: {}", doc_id, score);
+    /// }
+    /// ```
+    fn search(&self, query: &[f32], k: usize, filter: Option<&Filter>) -> Result<SearchResult, DbError> {
+        self.search_with_nprobe(query, k, None, filter)
+    }
+
+    /// Comme [`Collection::search`], en précisant le nombre de listes parcourues par l'index IVF.
+    ///
+    /// # Paramètres
+    /// - `nprobe`: Le nombre de listes les plus proches de la requête à parcourir (index IVF) ou de seaux
+    ///   à visiter par table (index LSH) ; `None` utilise le `nprobe` de l'index. Sans effet sur l'index HNSW.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// // Parcourt toutes les listes : le résultat est celui de la recherche exacte.
+    /// let results = collection.search_with_nprobe(&query, 10, Some(nlist), None)?;
+    /// ```
+    fn search_with_nprobe(
+        &self,
+        query: &[f32],
+        k: usize,
+        nprobe: Option<usize>,
+        filter: Option<&Filter>,
+    ) -> Result<SearchResult, DbError> {
+        let index = match (&self.index, self.active_quantizer()) {
+            (Some(index), _) => index,
+            (None, Some(quantizer)) => return self.search_quantized(quantizer, query, k, filter),
+            (None, None) => return self.search_exact(query, k, filter),
+        };
+        self.validate(query)?;
+
+        let hits = match filter {
+            None => index.search(query, k, nprobe, &self.data, None),
+            Some(filter) => self.search_matching(index, query, k, nprobe, &self.matching_documents(filter)),
+        };
+        Ok(SearchResult {
+            metric: self.metric,
+            hits,
+            payloads: None,
+        })
+    }
+
+    /// Recherche par l'index parmi les documents qui satisfont un filtre.
+    fn search_matching(
+        &self,
+        index: &VectorIndex,
+        query: &[f32],
+        k: usize,
+        nprobe: Option<usize>,
+        matching: &HashSet<DocumentId>,
+    ) -> Vector {
+        if matching.len() <= FILTER_BRUTE_FORCE_LIMIT {
+            return self.rank(query, k, matching.iter());
+        }
+        let hits = index.search(query, k, nprobe, &self.data, Some(&|key| matching.contains(key)));
+        // Le parcours filtré du graphe peut s'arrêter trop tôt ; on se rabat alors sur le calcul exact.
+        if hits.len() < k.min(matching.len()) {
+            self.rank(query, k, matching.iter())
+        } else {
+            hits
+        }
+    }
+
+    /// Le quantificateur, s'il a pu être appris.
+    fn active_quantizer(&self) -> Option<&Quantizer> {
+        self.quantizer.as_ref().filter(|quantizer| quantizer.is_trained())
+    }
+
+    /// Recherche sur les codes des documents (voir [`Collection::build_quantizer`]).
+    fn search_quantized(
+        &self,
+        quantizer: &Quantizer,
+        query: &[f32],
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<SearchResult, DbError> {
+        self.validate(query)?;
+        let hits = match filter {
+            None => quantizer.search(query, k, &self.data, None),
+            Some(filter) => self.search_quantized_matching(quantizer, query, k, &self.matching_documents(filter)),
+        };
+        Ok(SearchResult {
+            metric: self.metric,
+            hits,
+            payloads: None,
+        })
+    }
+
+    /// Recherche sur les codes parmi les documents qui satisfont un filtre ; s'ils sont peu nombreux,
+    /// leurs vecteurs d'origine sont comparés directement à la requête.
+    fn search_quantized_matching(
+        &self,
+        quantizer: &Quantizer,
+        query: &[f32],
+        k: usize,
+        matching: &HashSet<DocumentId>,
+    ) -> Vector {
+        if matching.len() <= FILTER_BRUTE_FORCE_LIMIT {
+            return self.rank(query, k, matching.iter());
+        }
+        quantizer.search(query, k, &self.data, Some(&|key| matching.contains(key)))
+    }
+
+    /// Recherche les documents les plus proches de chacune des requêtes d'un lot.
+    ///
+    /// Le résultat de chaque requête est identique à celui de [`Collection::search`], mais le lot est
+    /// traité plus vite qu'une suite de recherches : le filtre n'est évalué qu'une fois, les requêtes
+    /// sont réparties entre les threads du [`WorkerPool`] et, pour le calcul exact, chaque bloc de
+    /// documents est comparé à plusieurs requêtes pendant qu'il se trouve dans le cache du processeur.
+    ///
+    /// # Paramètres
+    /// - `queries`: Les vecteurs des requêtes.
+    /// - `k`: Le nombre maximal de résultats par requête.
+    /// - `filter`: Un filtre optionnel sur les métadonnées, commun à toutes les requêtes.
+    ///
+    /// # Retour
+    /// - `Result<Vec<SearchResult>, DbError>`: Un résultat par requête, dans l'ordre des requêtes,
+    ///   ou [`DbError::InvalidQuery`] pour la première requête qui n'est pas un vecteur valide.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let queries = vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]];
+    /// for result in collection.search_batch(&queries, 3, None)? {
+    ///     println!("{:?}", result.hits);
+    /// }
+    /// ```
+    fn search_batch(&self, queries: &[Vec<f32>], k: usize, filter: Option<&Filter>) -> Result<Vec<SearchResult>, DbError> {
+        self.search_batch_with(true, queries, k, filter)
+    }
+
+    /// Recherche groupée exhaustive, même si un index ou un quantificateur est présent : l'équivalent de
+    /// [`Collection::search_exact`] pour un lot de requêtes (voir [`Collection::search_batch`]).
+    fn search_exact_batch(
+        &self,
+        queries: &[Vec<f32>],
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<SearchResult>, DbError> {
+        self.search_batch_with(false, queries, k, filter)
+    }
+
+    /// Recherche groupée, approximative (par l'index ou sur les codes) si `approximate` est vrai
+    /// et que la collection le permet, sinon exhaustive.
+    fn search_batch_with(
+        &self,
+        approximate: bool,
+        queries: &[Vec<f32>],
+        k: usize,
+        filter: Option<&Filter>,
+    ) -> Result<Vec<SearchResult>, DbError> {
+        for (position, query) in queries.iter().enumerate() {
+            self.validate(query).map_err(|error| DbError::InvalidQuery {
+                index: position,
+                error: Box::new(error),
+            })?;
+        }
+
+        let index = self.index.as_ref().filter(|_| approximate);
+        let quantizer = self.active_quantizer().filter(|_| approximate && index.is_none());
+        let matching = filter.map(|filter| self.matching_documents(filter));
+        let hits = match (index, quantizer, &matching) {
+            (Some(index), _, None) => map_query_blocks(queries, |block| {
+                block.iter().map(|query| index.search(query, k, None, &self.data, None)).collect()
+            }),
+            (Some(index), _, Some(matching)) if matching.len() > FILTER_BRUTE_FORCE_LIMIT => {
+                map_query_blocks(queries, |block| {
+                    block.iter().map(|query| self.search_matching(index, query, k, None, matching)).collect()
+                })
+            }
+            (None, Some(quantizer), None) => map_query_blocks(queries, |block| {
+                block.iter().map(|query| quantizer.search(query, k, &self.data, None)).collect()
+            }),
+            (None, Some(quantizer), Some(matching)) if matching.len() > FILTER_BRUTE_FORCE_LIMIT => {
+                map_query_blocks(queries, |block| {
+                    block
+                        .iter()
+                        .map(|query| self.search_quantized_matching(quantizer, query, k, matching))
+                        .collect()
+                })
+            }
+            (_, _, Some(matching)) => self.rank_batch(queries, k, matching.iter().collect()),
+            (_, _, None) => self.rank_batch(queries, k, self.data.keys().collect()),
+        };
+        Ok(hits
+            .into_iter()
+            .map(|hits| SearchResult {
+                metric: self.metric,
+                hits,
+                payloads: None,
+            })
+            .collect())
+    }
+
+    /// Recherche exhaustive : compare la requête à tous les documents de la collection,
+    /// même si un index ou un quantificateur est présent. Sert de référence pour évaluer la recherche approximative.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur représentant la requête de recherche.
+    /// - `k`: Le nombre maximal de résultats à retourner.
+    /// - `filter`: Un filtre optionnel sur les métadonnées : les documents qui ne le satisfont pas
+    ///   ne sont pas comparés à la requête.
+    ///
+    /// # Retour
+    /// - `Result<SearchResult, DbError>`: Une liste de paires (`DocumentId`, score) classées du plus proche au plus éloigné,
+    ///   ou une erreur si la requête n'est pas un vecteur valide pour la collection.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let exact = collection.search_exact(&[1.0, 1.0, 1.0], 3, None)?;
+    /// let approx = collection.search(&[1.0, 1.0, 1.0], 3, None)?;
+    /// ```
+    fn search_exact(&self, query: &[f32], k: usize, filter: Option<&Filter>) -> Result<SearchResult, DbError> {
+        self.validate(query)?;
+        let hits = match filter {
+            None => self.rank(query, k, self.data.keys()),
+            Some(filter) => match self.payload_index.candidates(filter) {
+                Some(candidates) => self.rank(query, k, candidates.iter().filter(|key| self.accepts(filter, key))),
+                None => self.rank(query, k, self.data.keys().filter(|key| self.accepts(filter, key))),
+            },
+        };
+        Ok(SearchResult {
+            metric: self.metric,
+            hits,
+            payloads: None,
+        })
+    }
+
+    /// Recherche tous les documents dont le score atteint un seuil : une similarité d'au moins
+    /// `threshold` (cosinus, produit scalaire) ou une distance d'au plus `threshold` (les autres métriques).
+    ///
+    /// Si un index (HNSW, IVF ou LSH) a été construit, la recherche passe par l'index : le nombre de voisins
+    /// demandés est doublé tant que le moins bon d'entre eux atteint encore le seuil, puis les voisins qui ne
+    /// l'atteignent pas sont écartés. Comme pour [`Collection::search`], le résultat est alors approximatif.
+    /// Le quantificateur éventuel n'est pas utilisé : les scores sont ceux des vecteurs d'origine.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur représentant la requête de recherche.
+    /// - `threshold`: Le score minimal (similarité) ou maximal (distance) des résultats.
+    /// - `limit`: Un nombre maximal facultatif de résultats ; les meilleurs sont retenus.
+    /// - `filter`: Un filtre optionnel sur les métadonnées des documents.
+    ///
+    /// # Retour
+    /// - `Result<SearchResult, DbError>`: Les documents qui atteignent le seuil, du plus proche au plus éloigné,
+    ///   ou une erreur si la requête n'est pas un vecteur valide pour la collection.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// // Tous les contrats dont la similarité cosinus avec le modèle est d'au moins 0.92.
+    /// let similar = collection.search_range(&template, 0.92, None, Some(&Filter::parse(r#"type == "contrat""#)?))?;
+    /// ```
+    fn search_range(
+        &self,
+        query: &[f32],
+        threshold: f32,
+        limit: Option<usize>,
+        filter: Option<&Filter>,
+    ) -> Result<SearchResult, DbError> {
+        let index = match &self.index {
+            Some(index) => index,
+            None => return self.search_range_exact(query, threshold, limit, filter),
+        };
+        self.validate(query)?;
+
+        let matching = filter.map(|filter| self.matching_documents(filter));
+        let candidates = matching.as_ref().map_or(self.len(), HashSet::len);
+        let cap = limit.map_or(candidates, |limit| limit.min(candidates));
+        let mut k = RANGE_INITIAL_K.min(cap);
+        let mut hits = loop {
+            let hits = match &matching {
+                None => index.search(query, k, None, &self.data, None),
+                Some(matching) => self.search_matching(index, query, k, None, matching),
+            };
+            let exhausted = hits.len() < k || k >= cap;
+            if exhausted || hits.last().is_some_and(|&(_, score)| !self.metric.within(score, threshold)) {
+                break hits;
+            }
+            k = k.saturating_mul(2).min(cap);
+        };
+        hits.retain(|&(_, score)| self.metric.within(score, threshold));
+        Ok(SearchResult {
+            metric: self.metric,
+            hits,
+            payloads: None,
+        })
+    }
+
+    /// Recherche par seuil exhaustive, même si un index ou un quantificateur est présent
+    /// (voir [`Collection::search_range`] et [`Collection::search_exact`]).
+    fn search_range_exact(
+        &self,
+        query: &[f32],
+        threshold: f32,
+        limit: Option<usize>,
+        filter: Option<&Filter>,
+    ) -> Result<SearchResult, DbError> {
+        self.validate(query)?;
+        let limit = limit.unwrap_or(usize::MAX);
+        let hits = match filter {
+            None => self.rank_within(query, limit, Some(threshold), self.data.keys()),
+            Some(filter) => match self.payload_index.candidates(filter) {
+                Some(candidates) => self.rank_within(
+                    query,
+                    limit,
+                    Some(threshold),
+                    candidates.iter().filter(|key| self.accepts(filter, key)),
+                ),
+                None => self.rank_within(
+                    query,
+                    limit,
+                    Some(threshold),
+                    self.data.keys().filter(|key| self.accepts(filter, key)),
+                ),
+            },
+        };
+        Ok(SearchResult {
+            metric: self.metric,
+            hits,
+            payloads: None,
+        })
+    }
+
+    /// Compare la requête aux documents donnés et retourne les `k` meilleurs.
+    fn rank<'a>(&self, query: &[f32], k: usize, keys: impl Iterator<Item = &'a DocumentId>) -> Vector {
+        self.rank_within(query, k, None, keys)
+    }
+
+    /// Compare la requête aux documents donnés et retourne les `k` meilleurs parmi ceux dont le score
+    /// atteint `threshold`, s'il est donné (voir [`Metric::within`]).
+    ///
+    /// Au-delà de [`PARALLEL_SCAN_THRESHOLD`] documents, les documents sont découpés en tranches
+    /// consécutives traitées en parallèle par le [`WorkerPool`] global ; chaque tranche retient ses `k`
+    /// meilleurs résultats, puis les résultats partiels sont fusionnés. L'ordre de classement étant total
+    /// (voir le module [`topk`]), le résultat est identique à celui d'un parcours séquentiel.
+    fn rank_within<'a>(
+        &self,
+        query: &[f32],
+        k: usize,
+        threshold: Option<f32>,
+        keys: impl Iterator<Item = &'a DocumentId>,
+    ) -> Vector {
+        let keys: Vec<&DocumentId> = keys.collect();
+        let pool = WorkerPool::global();
+        if keys.len() < PARALLEL_SCAN_THRESHOLD || pool.workers() == 1 {
+            return self.top_k(query, k, threshold, &keys);
+        }
+
+        let chunk_size = keys.len().div_ceil(pool.workers());
+        let chunks: Vec<&[&DocumentId]> = keys.chunks(chunk_size).collect();
+        let partials = pool.map(chunks.len(), |i| self.top_k(query, k, threshold, chunks[i]));
+        let mut top = TopK::new(self.metric, k);
+        for partial in partials {
+            top.extend(partial);
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Calcule séquentiellement les scores des documents donnés et retourne les `k` meilleurs,
+    /// retenus au fil du calcul dans un tas borné à `k` éléments. Avec un seuil, seuls les documents
+    /// qui l'atteignent sont candidats.
+    /// Les normes des documents étant connues, seule celle de la requête est calculée.
+    fn top_k(&self, query: &[f32], k: usize, threshold: Option<f32>, keys: &[&DocumentId]) -> Vector {
+        let query_norm = simd::norm(query);
+        let mut top = TopK::new(self.metric, k);
+        for &key in keys {
+            if let Some((vector, norm)) = self.data.get(key).zip(self.norms.get(key)) {
+                let score = self.metric.score_with_norms(query, query_norm, vector, *norm);
+                if threshold.is_none_or(|threshold| self.metric.within(score, threshold)) {
+                    top.push(*key, score);
+                }
+            }
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Calcul exact des `k` meilleurs documents de chaque requête d'un lot parmi les documents donnés.
+    /// Un lot trop petit pour occuper tous les threads est traité requête par requête, chacune
+    /// répartissant ses documents entre les threads (voir [`Collection::rank`]).
+    fn rank_batch(&self, queries: &[Vec<f32>], k: usize, keys: Vec<&DocumentId>) -> Vec<Vector> {
+        if queries.len() < WorkerPool::global().workers() {
+            return queries.iter().map(|query| self.rank(query, k, keys.iter().copied())).collect();
+        }
+        map_query_blocks(queries, |block| self.top_k_batch(block, k, &keys))
+    }
+
+    /// Calcule les `k` meilleurs documents de chacune des requêtes d'un bloc. Les documents sont parcourus
+    /// par blocs d'environ [`DOCUMENT_BLOCK_BYTES`] octets, chaque bloc étant comparé à toutes les requêtes
+    /// avant de passer au suivant.
+    fn top_k_batch(&self, queries: &[Vec<f32>], k: usize, keys: &[&DocumentId]) -> Vec<Vector> {
+        let query_norms: Vec<f32> = queries.iter().map(|query| simd::norm(query)).collect();
+        let mut tops: Vec<TopK> = queries.iter().map(|_| TopK::new(self.metric, k)).collect();
+        let vector_bytes = self.dimension.unwrap_or(1) * std::mem::size_of::<f32>();
+        let block_size = (DOCUMENT_BLOCK_BYTES / vector_bytes).max(1);
+
+        let mut block: Vec<(DocumentId, &[f32], f32)> = Vec::with_capacity(block_size);
+        for chunk in keys.chunks(block_size) {
+            block.clear();
+            block.extend(chunk.iter().filter_map(|&key| {
+                let (vector, norm) = self.data.get(key).zip(self.norms.get(key))?;
+                Some((*key, vector, *norm))
+            }));
+            for ((query, query_norm), top) in queries.iter().zip(&query_norms).zip(&mut tops) {
+                for &(key, vector, norm) in &block {
+                    top.push(key, self.metric.score_with_norms(query, *query_norm, vector, norm));
+                }
+            }
+        }
+        tops.into_iter().map(TopK::into_sorted_vec).collect()
+    }
+
+    /// Indique si les métadonnées d'un document satisfont un filtre.
+    fn accepts(&self, filter: &Filter, key: &DocumentId) -> bool {
+        self.payloads.get(key).is_some_and(|payload| filter.matches(payload))
+    }
+
+    /// Retourne l'ensemble des documents qui satisfont un filtre, en s'aidant de l'index des métadonnées
+    /// si le filtre porte sur des champs indexés.
+    fn matching_documents(&self, filter: &Filter) -> HashSet<DocumentId> {
+        match self.payload_index.candidates(filter) {
+            Some(candidates) => candidates.into_iter().filter(|key| self.accepts(filter, key)).collect(),
+            None => self
+                .payloads
+                .iter()
+                .filter(|(_, payload)| filter.matches(payload))
+                .map(|(key, _)| *key)
+                .collect(),
+        }
+    }
+}
+
+/// # Structure: `Database`
+///
+/// `Database` gère un ensemble de collections (instances de [`Collection`]) identifiées par un nom (`String`).
+/// Elle offre des méthodes pour ajouter une collection, obtenir une collection (en lecture seule ou mutable) et effectuer des recherches.
+///
+/// Une base créée avec [`Database::new`] vit uniquement en mémoire. Une base ouverte avec [`Database::open`]
+/// journalise chaque modification sur disque (voir le module [`storage`]).
+struct Database {
+    /// Les collections stockées sous forme de clé-valeur (`String`, [`Collection`]).
+    collections: HashMap<String, Collection>,
+    /// Le stockage sur disque, absent pour une base en mémoire.
+    storage: Option<Storage>,
+    /// Le répertoire de données d'une base persistante, où sont créés les fichiers de vecteurs
+    /// des collections projetées en mémoire.
+    directory: Option<PathBuf>,
+}
+
+impl Database {
+    /// Crée une nouvelle instance de [`Database`].
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let db = Database::new();
+    /// ```
+    fn new() -> Self {
+        Database {
+            collections: HashMap::new(),
+            storage: None,
+            directory: None,
+        }
+    }
+
+    /// Ouvre (ou crée) une base persistante dans le répertoire `path`, avec les réglages par défaut.
+    ///
+    /// L'état est reconstruit à partir du dernier instantané et du journal, y compris après un crash :
+    /// une opération dont l'écriture a été interrompue est ignorée.
+    ///
+    /// # Paramètres
+    /// - `path`: Le répertoire de données.
+    ///
+    /// # Retour
+    /// - `Result<Database, DbError>`: La base rechargée, ou une erreur d'entrée/sortie ou de corruption.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut db = Database::open("./data")?;
+    /// ```
+    fn open(path: impl AsRef<Path>) -> Result<Self, DbError> {
+        Database::open_with_options(path, StorageOptions::default())
+    }
+
+    /// Ouvre (ou crée) une base persistante en précisant les réglages du stockage.
+    ///
+    /// # Paramètres
+    /// - `path`: Le répertoire de données.
+    /// - `options`: La fréquence des instantanés et la politique de synchronisation sur disque.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let options = StorageOptions { snapshot_interval: 1_000, sync_writes: false };
+    /// let mut db = Database::open_with_options("./data", options)?;
+    /// ```
+    fn open_with_options(path: impl AsRef<Path>, options: StorageOptions) -> Result<Self, DbError> {
+        let Recovered { collections, settings, mut journal } = Storage::open(path.as_ref(), options)?;
+        let mut db = Database {
+            collections,
+            storage: None,
+            directory: Some(path.as_ref().to_path_buf()),
+        };
+        for record in settings {
+            db.apply(record)?;
+        }
+        while let Some(record) = journal.next_record()? {
+            db.apply(record)?;
+        }
+        db.storage = Some(journal.finish()?);
+        db.snapshot_if_due()?;
+        Ok(db)
+    }
+
+    /// Écrit un instantané de la base sur disque et vide le journal.
+    /// Sans effet pour une base en mémoire.
+    ///
+    /// # Retour
+    /// - `Result<bool, DbError>`: `true` si un instantané a été écrit, `false` pour une base en mémoire.
+    fn snapshot(&mut self) -> Result<bool, DbError> {
+        match self.storage.as_mut() {
+            Some(storage) => storage.snapshot(&mut self.collections).map(|()| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Écrit un instantané si le journal a atteint la taille prévue par les réglages.
+    fn snapshot_if_due(&mut self) -> Result<(), DbError> {
+        match self.storage.as_mut() {
+            Some(storage) if storage.snapshot_due() => storage.snapshot(&mut self.collections),
+            _ => Ok(()),
+        }
+    }
+
+    /// Journalise une opération avant son application (base persistante uniquement).
+    fn log(&mut self, record: &Record) -> Result<(), DbError> {
+        match self.storage.as_mut() {
+            Some(storage) => storage.append(record),
+            None => Ok(()),
+        }
+    }
+
+    /// Journalise une opération puis l'applique en mémoire. Si l'application échoue, l'opération est
+    /// retirée du journal, pour qu'elle ne soit pas rejouée (et ne fasse pas échouer) la prochaine ouverture.
+    fn commit(&mut self, record: Record) -> Result<(), DbError> {
+        self.log(&record)?;
+        if let Err(error) = self.apply(record) {
+            if let Some(storage) = self.storage.as_mut() {
+                storage.undo_append()?;
+            }
+            return Err(error);
+        }
+        self.snapshot_if_due()
+    }
+
+    /// Applique une opération en mémoire : utilisé après la journalisation et lors du rejeu du journal.
+    fn apply(&mut self, record: Record) -> Result<(), DbError> {
+        match record {
+            Record::AddCollection { name, config } => {
+                if self.collections.contains_key(&name) {
+                    return Err(DbError::CollectionExists(name));
+                }
+                let collection = Collection::open(config, self.directory.as_deref())?;
+                self.collections.insert(name, collection);
+            }
+            Record::DropCollection { name } => {
+                self.collections
+                    .remove(&name)
+                    .ok_or(DbError::CollectionNotFound(name))?;
+            }
+            Record::Upsert { collection, key, vector, payload } => {
+                self.get_collection_mut(&collection)?.add_or_update(key, vector, payload)?;
+            }
+            Record::Remove { collection, key } => {
+                self.get_collection_mut(&collection)?.remove(&key);
+            }
+            Record::SetIndex { collection, config } => {
+                let collection = self.get_collection_mut(&collection)?;
+                match config {
+                    Some(config) => collection.build_index(config),
+                    None => collection.drop_index(),
+                }
+            }
+            Record::TuneIndex { collection, ef_search, nprobe } => {
+                self.get_collection_mut(&collection)?.tune_index(ef_search, nprobe);
+            }
+            Record::CreatePayloadIndex { collection, field } => {
+                self.get_collection_mut(&collection)?.create_payload_index(&field);
+            }
+            Record::UpsertMany { collection, documents } => {
+                self.get_collection_mut(&collection)?.upsert_many(documents)?;
+            }
+            Record::RemoveMany { collection, keys } => {
+                self.get_collection_mut(&collection)?.remove_many(&keys);
+            }
+            Record::SetQuantizer { collection, config } => {
+                self.get_collection_mut(&collection)?.build_quantizer(config);
+            }
+            Record::DropQuantizer { collection } => {
+                self.get_collection_mut(&collection)?.drop_quantizer();
+            }
+        }
+        Ok(())
+    }
+
+    /// Ajoute une nouvelle [`Collection`] dans la base de données.
+    ///
+    /// # Paramètres
+    /// - `name`: Le nom de la collection (unique).
+    /// - `config`: La métrique et, éventuellement, la dimension des vecteurs de cette collection.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::CollectionExists`] si une collection porte déjà ce nom.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut db = Database::new();
+    /// db.add_collection("NotaryDocuments".to_string(), CollectionConfig { metric: Metric::Cosine, dimension: Some(3), ..Default::default() })?;
+    /// ```
+    fn add_collection(&mut self, name: String, config: CollectionConfig) -> Result<(), DbError> {
+        if self.collections.contains_key(&name) {
+            return Err(DbError::CollectionExists(name));
+        }
+        let record = Record::AddCollection { name, config };
+        self.commit(record)
+    }
+
+    /// Supprime une [`Collection`] et tous ses documents.
+    ///
+    /// # Paramètres
+    /// - `name`: Le nom de la collection.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::CollectionNotFound`] si aucune collection ne porte ce nom.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.drop_collection("LegalFiles")?;
+    /// ```
+    fn drop_collection(&mut self, name: &str) -> Result<(), DbError> {
+        self.get_collection(name)?;
+        let record = Record::DropCollection { name: name.to_string() };
+        self.commit(record)
+    }
+
+    /// Ajoute ou met à jour un document (vecteur et métadonnées) d'une collection.
+    ///
+    /// Pour une base persistante, l'opération est journalisée avant d'être appliquée.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `key`: L'identifiant unique du document.
+    /// - `vector`: Le vecteur associé au document.
+    /// - `payload`: Les métadonnées du document (éventuellement vides).
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas, si le vecteur est invalide
+    ///   ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.add_or_update("NotaryDocuments", Uuid::new_v4(), vec![1.0, 2.0, 3.0], Payload::new())?;
+    /// ```
+    fn add_or_update(
+        &mut self,
+        collection_name: &str,
+        key: DocumentId,
+        vector: Vec<f32>,
+        payload: Payload,
+    ) -> Result<(), DbError> {
+        self.get_collection(collection_name)?.validate(&vector)?;
+        let record = Record::Upsert {
+            collection: collection_name.to_string(),
+            key,
+            vector,
+            payload,
+        };
+        self.commit(record)
+    }
+
+    /// Supprime un document d'une collection.
+    ///
+    /// Pour une base persistante, l'opération est journalisée avant d'être appliquée.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `key`: L'identifiant unique du document.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.remove("NotaryDocuments", &doc_id)?;
+    /// ```
+    fn remove(&mut self, collection_name: &str, key: &DocumentId) -> Result<(), DbError> {
+        if self.get_collection(collection_name)?.get(key).is_none() {
+            return Ok(());
+        }
+        let record = Record::Remove {
+            collection: collection_name.to_string(),
+            key: *key,
+        };
+        self.commit(record)
+    }
+
+    /// Construit (ou remplace) l'index de recherche approximative d'une collection, ou le supprime
+    /// (voir [`Collection::build_index`]).
+    ///
+    /// Pour une base persistante, seuls le type et les paramètres de l'index sont journalisés :
+    /// l'index est reconstruit sur les documents de la collection à l'ouverture de la base.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `config`: Le type de l'index et ses paramètres, ou `None` pour supprimer l'index.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.set_index("NotaryDocuments", Some(IndexConfig::Hnsw(HnswParams::default())))?;
+    /// ```
+    fn set_index(&mut self, collection_name: &str, config: Option<IndexConfig>) -> Result<(), DbError> {
+        self.get_collection(collection_name)?;
+        self.commit(Record::SetIndex {
+            collection: collection_name.to_string(),
+            config,
+        })
+    }
+
+    /// Reconstruit l'index d'une collection avec ses paramètres actuels, sur les documents actuels.
+    ///
+    /// Pour un index IVF, les centroïdes sont réappris : c'est ce qu'il faut faire lorsque les documents
+    /// ajoutés depuis sa construction ont déséquilibré les listes (voir [`ivf::IvfIndex::imbalance`]).
+    /// Pour un index HNSW, le graphe est débarrassé des nœuds des documents supprimés.
+    ///
+    /// # Retour
+    /// - `Result<Option<IndexConfig>, DbError>`: Les paramètres de l'index reconstruit, `None` si la collection
+    ///   n'a pas d'index, ou une erreur si elle n'existe pas ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.rebuild_index("NotaryDocuments")?;
+    /// ```
+    fn rebuild_index(&mut self, collection_name: &str) -> Result<Option<IndexConfig>, DbError> {
+        let config = self.get_collection(collection_name)?.index.as_ref().map(VectorIndex::config);
+        if let Some(config) = config {
+            self.set_index(collection_name, Some(config))?;
+        }
+        Ok(config)
+    }
+
+    /// Modifie les réglages de recherche de l'index d'une collection sans le reconstruire
+    /// (voir [`Collection::tune_index`]).
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.tune_index("NotaryDocuments", Some(128), None)?;
+    /// ```
+    fn tune_index(&mut self, collection_name: &str, ef_search: Option<usize>, nprobe: Option<usize>) -> Result<(), DbError> {
+        self.get_collection(collection_name)?;
+        self.commit(Record::TuneIndex {
+            collection: collection_name.to_string(),
+            ef_search,
+            nprobe,
+        })
+    }
+
+    /// Indexe un champ des métadonnées d'une collection (voir [`Collection::create_payload_index`]).
+    /// L'index est reconstruit à chaque ouverture d'une base persistante.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `field`: Le nom du champ, éventuellement imbriqué (`client.ville`).
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas ou si l'écriture du journal échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.create_payload_index("NotaryDocuments", "client")?;
+    /// ```
+    fn create_payload_index(&mut self, collection_name: &str, field: &str) -> Result<(), DbError> {
+        self.get_collection(collection_name)?;
+        self.commit(Record::CreatePayloadIndex {
+            collection: collection_name.to_string(),
+            field: field.to_string(),
+        })
+    }
+
+    /// Quantifie les vecteurs d'une collection (voir [`Collection::build_quantizer`]), ou supprime son quantificateur.
+    ///
+    /// Pour une base persistante, seuls le type et les paramètres du quantificateur sont journalisés :
+    /// il est réappris sur les documents de la collection à l'ouverture de la base.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `config`: Le type du quantificateur et ses paramètres, ou `None` pour supprimer le quantificateur.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: Une erreur si la collection n'existe pas ou si l'écriture du journal échoue, ou
+    ///   [`DbError::EmptyCollection`] pour un quantificateur par produit ou scalaire sur une collection vide,
+    ///   qui n'aurait rien sur quoi apprendre.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// db.set_quantizer("NotaryDocuments", Some(QuantizerConfig::Scalar(SqParams::default())))?;
+    /// ```
+    fn set_quantizer(&mut self, collection_name: &str, config: Option<QuantizerConfig>) -> Result<(), DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let collection_name = collection_name.to_string();
+        match config {
+            Some(QuantizerConfig::Product(_) | QuantizerConfig::Scalar(_)) if collection.len() == 0 => {
+                Err(DbError::EmptyCollection(collection_name))
+            }
+            Some(config) => self.commit(Record::SetQuantizer { collection: collection_name, config }),
+            None => self.commit(Record::DropQuantizer { collection: collection_name }),
+        }
+    }
+
+    /// Ajoute ou met à jour un lot de documents d'une collection de façon atomique
+    /// (voir [`Collection::upsert_many`]).
+    ///
+    /// Le lot est vérifié avant d'être journalisé, puis journalisé en une seule opération :
+    /// après un crash, il est rejoué entièrement ou pas du tout.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `documents`: Les documents à écrire, sous la forme (`DocumentId`, vecteur, métadonnées).
+    ///
+    /// # Retour
+    /// - `Result<Vec<ItemStatus>, DbError>`: L'état de chaque document du lot, [`DbError::InvalidBatch`]
+    ///   si un vecteur est invalide, ou une erreur si la collection n'existe pas ou si l'écriture du journal échoue.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let collection = Collection::new();
+    /// let documents = vec![(Uuid::new_v4(), vec![1.0, 2.0, 3.0], Payload::new())];
+    /// let statuses = db.upsert_many("NotaryDocuments", documents)?;
     /// ```
-    fn new() -> Self {
-        Collection {
-            data: HashMap::new(),
+    fn upsert_many(&mut self, collection_name: &str, documents: Vec<Document>) -> Result<Vec<ItemStatus>, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        collection.validate_batch(&documents)?;
+        let statuses = collection.upsert_statuses(&documents);
+        if !documents.is_empty() {
+            let record = Record::UpsertMany {
+                collection: collection_name.to_string(),
+                documents,
+            };
+            self.commit(record)?;
         }
+        Ok(statuses)
     }
 
-    /// Ajoute ou met à jour le vecteur associé à un [`DocumentId`].
+    /// Supprime un lot de documents d'une collection (voir [`Collection::remove_many`]),
+    /// journalisé en une seule opération.
     ///
     /// # Paramètres
-    /// - `key`: L'identifiant unique du document.
-    /// - `vector`: Le vecteur associé au document (ex. représentation sémantique).
+    /// - `collection_name`: Le nom de la collection.
+    /// - `keys`: Les identifiants des documents à supprimer.
+    ///
+    /// # Retour
+    /// - `Result<Vec<ItemStatus>, DbError>`: L'état de chaque identifiant du lot, ou une erreur
+    ///   si la collection n'existe pas ou si l'écriture du journal échoue.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let mut collection = Collection::new();
-    /// let doc_id = Uuid::new_v4();
-    /// collection.add_or_update(doc_id, vec![1.0, 2.0, 3.0]);
+    /// let statuses = db.remove_many("NotaryDocuments", &[doc_id, other_id])?;
     /// ```
-    fn add_or_update(&mut self, key: DocumentId, vector: Vec<f32>) {
-        self.data.insert(key, vector);
+    fn remove_many(&mut self, collection_name: &str, keys: &[DocumentId]) -> Result<Vec<ItemStatus>, DbError> {
+        let statuses = self.get_collection(collection_name)?.remove_statuses(keys);
+        if statuses.contains(&ItemStatus::Removed) {
+            let record = Record::RemoveMany {
+                collection: collection_name.to_string(),
+                keys: keys.to_vec(),
+            };
+            self.commit(record)?;
+        }
+        Ok(statuses)
     }
 
-    /// Récupère le vecteur associé à un [`DocumentId`], s'il existe.
+    /// Importe les documents d'un fichier CSV ou JSON Lines dans une collection (voir le module [`transfer`]).
+    ///
+    /// Le fichier est lu au fil de l'eau et les documents sont enregistrés par lots de `options.batch_size`,
+    /// chaque lot journalisé en une seule opération ([`Database::upsert_many`]) : seul un lot est en mémoire.
+    /// Une ligne est invalide si elle est mal formée ou si son vecteur est refusé par la collection ;
+    /// selon `options.on_error`, l'import s'arrête ou l'ignore.
     ///
     /// # Paramètres
-    /// - `key`: La référence à l'identifiant unique du document.
+    /// - `collection_name`: Le nom de la collection.
+    /// - `reader`: Le contenu du fichier.
+    /// - `options`: Le format du fichier, la conduite à tenir en cas de ligne invalide et la taille des lots.
     ///
     /// # Retour
-    /// - `Option<&Vec<f32>>`: Le vecteur si le document est trouvé, ou `None` sinon.
+    /// - `Result<ImportReport, DbError>`: Le nombre de documents importés et les lignes ignorées, ou
+    ///   [`DbError::InvalidLine`] avec le numéro de la première ligne invalide (avec [`ErrorPolicy::Abort`],
+    ///   les documents des lignes précédentes restent enregistrés), ou une erreur de lecture ou d'écriture.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let doc = collection.get(&doc_id);
-    /// if let Some(vector) = doc {
-    ///     // Utiliser le vecteur
-    /// }
+    /// let options = ImportOptions { format: Format::Csv, on_error: ErrorPolicy::Skip, ..Default::default() };
+    /// let report = db.import("NotaryDocuments", BufReader::new(File::open("documents.csv")?), options)?;
+    /// println!("{} documents importés, {} lignes ignorées", report.imported, report.skipped);
     /// ```
-    #[allow(unused)]
-    fn get(&self, key: &DocumentId) -> Option<&Vec<f32>> {
-        self.data.get(key)
+    fn import(&mut self, collection_name: &str, reader: impl BufRead, options: ImportOptions) -> Result<ImportReport, DbError> {
+        let mut documents = DocumentReader::new(reader, options.format)?;
+        self.import_documents(collection_name, || documents.next_document(), options.on_error, options.batch_size)
     }
 
-    /// Supprime le document (et son vecteur) associé à un [`DocumentId`].
+    /// Importe dans une collection les lignes d'un fichier Parquet ou Arrow IPC (voir le module [`columnar`]).
+    ///
+    /// Le fichier est lu un lot de lignes à la fois, et les documents sont enregistrés par lots
+    /// comme par [`Database::import`]. Une ligne sans identifiant (colonne absente ou valeur nulle)
+    /// reçoit un identifiant aléatoire.
     ///
     /// # Paramètres
-    /// - `key`: La référence à l'identifiant unique du document.
+    /// - `collection_name`: Le nom de la collection.
+    /// - `reader`: Le contenu du fichier.
+    /// - `options`: Le format du fichier, les colonnes des identifiants et des vecteurs, la conduite
+    ///   à tenir en cas de ligne invalide et la taille des lots.
+    ///
+    /// # Retour
+    /// - `Result<ImportReport, DbError>`: Le nombre de documents importés et les lignes ignorées, ou
+    ///   [`DbError::InvalidSchema`] si la colonne des vecteurs est absente ou si une colonne n'a pas le type
+    ///   attendu, [`DbError::Unsupported`] si le fichier utilise un encodage ou une compression non pris
+    ///   en charge, ou une erreur comme pour [`Database::import`] (les lignes sont numérotées à partir de 1).
     ///
     /// # Exemple
     ///
     /// ```
-    /// collection.remove(&doc_id);
+    /// let options = TableOptions { vector_column: "embedding".to_string(), ..Default::default() };
+    /// let report = db.import_table("NotaryDocuments", File::open("documents.parquet")?, &options)?;
     /// ```
-    #[allow(unused)]
-    fn remove(&mut self, key: &DocumentId) {
-        self.data.remove(key);
+    fn import_table(&mut self, collection_name: &str, reader: impl Read + Seek, options: &TableOptions) -> Result<ImportReport, DbError> {
+        self.get_collection(collection_name)?;
+        let mut documents = TableReader::new(reader, options)?;
+        self.import_documents(collection_name, || documents.next_document(), options.on_error, options.batch_size)
+    }
+
+    /// Enregistre par lots les documents donnés par `next`, avec leur numéro de ligne, pour
+    /// [`Database::import`] et [`Database::import_table`].
+    fn import_documents(
+        &mut self,
+        collection_name: &str,
+        mut next: impl FnMut() -> Result<Option<(usize, Document)>, DbError>,
+        on_error: ErrorPolicy,
+        batch_size: usize,
+    ) -> Result<ImportReport, DbError> {
+        let mut dimension = self.get_collection(collection_name)?.dimension;
+        let batch_size = batch_size.max(1);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut report = ImportReport::default();
+        loop {
+            let document = next().and_then(|document| match document {
+                Some((line, document)) => {
+                    self.get_collection(collection_name)?
+                        .validate_with_dimension(&document.1, dimension)
+                        .map_err(|error| DbError::InvalidLine {
+                            line,
+                            message: error.to_string(),
+                        })?;
+                    Ok(Some(document))
+                }
+                None => Ok(None),
+            });
+            match document {
+                Ok(Some(document)) => {
+                    dimension = Some(document.1.len());
+                    batch.push(document);
+                    if batch.len() == batch_size {
+                        report.imported += batch.len();
+                        self.upsert_many(collection_name, std::mem::take(&mut batch))?;
+                    }
+                }
+                Ok(None) => break,
+                Err(DbError::InvalidLine { line, message }) if on_error == ErrorPolicy::Skip => {
+                    report.skip(line, message);
+                }
+                Err(error) => {
+                    self.upsert_many(collection_name, batch)?;
+                    return Err(error);
+                }
+            }
+        }
+        report.imported += batch.len();
+        self.upsert_many(collection_name, batch)?;
+        Ok(report)
     }
 
-    /// Recherche les documents les plus proches d'une requête donnée en utilisant la **similarité cosinus**.
+    /// Exporte les documents d'une collection dans un fichier CSV ou JSON Lines (voir le module [`transfer`]),
+    /// triés par identifiant. Chaque document est écrit dès qu'il est lu : les vecteurs ne sont pas copiés en mémoire.
+    ///
+    /// Un fichier CSV a une colonne par coordonnée et une colonne typée par champ des métadonnées
+    /// présent dans au moins un document : réimporté, il redonne exactement les mêmes métadonnées.
     ///
     /// # Paramètres
-    /// - `query`: Le vecteur représentant la requête de recherche.
-    /// - `k`: Le nombre maximal de résultats à retourner.
+    /// - `collection_name`: Le nom de la collection.
+    /// - `writer`: La destination, de préférence dans un `BufWriter`.
+    /// - `format`: Le format du fichier.
     ///
     /// # Retour
-    /// - [`SearchResult`]: Une liste de paires (`DocumentId`, score_de_similarité) classées par ordre décroissant de similarité.
+    /// - `Result<usize, DbError>`: Le nombre de documents exportés, ou une erreur si la collection
+    ///   n'existe pas ou si l'écriture échoue.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let results = collection.search(&[1.0, 1.0, 1.0], 3);
-    /// for (doc_id, similarity) in results {
-    ///     println!("DocID: {}, Similarité: {}", doc_id, similarity);
-    /// }
+    /// let exported = db.export("NotaryDocuments", BufWriter::new(File::create("documents.csv")?), Format::Csv)?;
     /// ```
-    fn search(&self, query: &[f32], k: usize) -> SearchResult {
-        let mut results: SearchResult = self
-            .data
-            .iter()
-            .filter_map(|(key, vector)| {
-                // On ignore les documents dont la dimension du vecteur ne correspond pas à la requête
-                if vector.len() != query.len() {
-                    return None;
-                }
-                let similarity = cosine_similarity(query, vector);
-                Some((*key, similarity))
-            })
-            .collect();
+    fn export(&self, collection_name: &str, writer: impl Write, format: Format) -> Result<usize, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let mut keys: Vec<&DocumentId> = collection.keys().collect();
+        keys.sort_unstable();
+        let columns = match format {
+            Format::Csv => transfer::payload_columns(collection.payloads.values()),
+            Format::JsonLines => Vec::new(),
+        };
+        let mut documents = DocumentWriter::new(writer, format, collection.dimension.unwrap_or(0), columns)?;
+        let empty = Payload::new();
+        for key in &keys {
+            documents.write(key, &collection.data[key], collection.get_payload(key).unwrap_or(&empty))?;
+        }
+        documents.finish()?;
+        Ok(keys.len())
+    }
 
-        // Tri par ordre décroissant de la similarité
-        results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-        results.into_iter().take(k).collect()
+    /// Exporte les documents d'une collection dans un fichier Parquet ou Arrow IPC (voir le module [`columnar`]),
+    /// triés par identifiant, un lot de lignes à la fois.
+    ///
+    /// Le fichier a une colonne d'identifiants (en texte), une colonne de vecteurs (des listes de `float32`
+    /// de taille fixe), et une colonne par champ des métadonnées présent dans au moins un document.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `writer`: La destination, de préférence dans un `BufWriter`.
+    /// - `options`: Le format du fichier, le nom des colonnes des identifiants et des vecteurs, et la taille des lots.
+    ///
+    /// # Retour
+    /// - `Result<usize, DbError>`: Le nombre de documents exportés, ou une erreur si la collection n'existe pas,
+    ///   si un champ des métadonnées porte le nom de la colonne des identifiants ou des vecteurs
+    ///   ([`DbError::InvalidSchema`]), ou si l'écriture échoue.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let options = TableOptions { format: TableFormat::Arrow, ..Default::default() };
+    /// let exported = db.export_table("NotaryDocuments", BufWriter::new(File::create("documents.arrow")?), &options)?;
+    /// ```
+    fn export_table(&self, collection_name: &str, writer: impl Write, options: &TableOptions) -> Result<usize, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let mut keys: Vec<&DocumentId> = collection.keys().collect();
+        keys.sort_unstable();
+        let columns = columnar::payload_fields(collection.payloads.values());
+        let mut documents = TableWriter::new(writer, options, collection.dimension.unwrap_or(0), columns)?;
+        let empty = Payload::new();
+        for key in &keys {
+            documents.write(key, &collection.data[key], collection.get_payload(key).unwrap_or(&empty))?;
+        }
+        documents.finish()?;
+        Ok(keys.len())
     }
-}
 
-/// # Structure: `Database`
-///
-/// `Database` gère un ensemble de collections (instances de [`Collection`]) identifiées par un nom (`String`).
-/// Elle offre des méthodes pour ajouter une collection, obtenir une collection (en lecture seule ou mutable) et effectuer des recherches.
-struct Database {
-    /// Les collections stockées sous forme de clé-valeur (`String`, [`Collection`]).
-    collections: HashMap<String, Collection>,
-}
+    /// Charge dans une collection la base d'un jeu de données au format TEXMEX (voir le module [`texmex`]).
+    ///
+    /// Le fichier est lu au fil de l'eau et les vecteurs sont enregistrés par lots, comme par
+    /// [`Database::import`]. Le vecteur à la position `i` du fichier devient le document
+    /// [`texmex::dataset_id(i)`](texmex::dataset_id), sans métadonnées.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `reader`: Le contenu du fichier.
+    /// - `format`: Le type des composantes du fichier.
+    /// - `limit`: Le nombre maximal de vecteurs à charger, depuis le début du fichier ; `None` charge tout le fichier.
+    ///
+    /// # Retour
+    /// - `Result<usize, DbError>`: Le nombre de vecteurs chargés, ou une erreur si le fichier est invalide,
+    ///   ou si un vecteur est refusé par la collection ([`DbError::InvalidLine`], avec le numéro du vecteur),
+    ///   ou si une écriture échoue. Les vecteurs des lots précédents restent alors enregistrés.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let file = BufReader::new(File::open("sift_base.fvecs")?);
+    /// let loaded = db.load_vecs("sift", file, VecsFormat::Fvecs, Some(100_000))?;
+    /// ```
+    fn load_vecs(&mut self, collection_name: &str, reader: impl Read, format: VecsFormat, limit: Option<usize>) -> Result<usize, DbError> {
+        self.get_collection(collection_name)?;
+        let mut vectors = VecsReader::new(reader, format);
+        self.load_documents(collection_name, |position| match position < limit.unwrap_or(usize::MAX) {
+            true => Ok(vectors.next_vector()?.map(|vector| (texmex::dataset_id(position), vector, Payload::new()))),
+            false => Ok(None),
+        })
+    }
 
-impl Database {
-    /// Crée une nouvelle instance de [`Database`].
+    /// Charge dans une collection une matrice de vecteurs NumPy `.npy` (voir le module [`npy`]).
+    ///
+    /// La matrice est lue ligne par ligne et les vecteurs sont enregistrés par lots, comme par
+    /// [`Database::load_vecs`]. Sans identifiants, la ligne `i` devient le document
+    /// [`texmex::dataset_id(i)`](texmex::dataset_id) ; aucun document n'a de métadonnées.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `vectors`: Le contenu du fichier `.npy`, en `float32` ou `float16`, de forme (nombre de vecteurs, dimension).
+    /// - `ids`: Les identifiants des documents, un par ligne de la matrice (voir [`npy::read_id_lines`]).
+    ///
+    /// # Retour
+    /// - `Result<usize, DbError>`: Le nombre de vecteurs chargés, ou une erreur si le fichier est invalide,
+    ///   si son type ou sa forme ne sont pas pris en charge ([`DbError::Unsupported`]), si sa dimension n'est pas
+    ///   celle de la collection ([`DbError::DimensionMismatch`]), si le nombre d'identifiants n'est pas celui des
+    ///   lignes, ou si un vecteur est refusé ([`DbError::InvalidLine`], avec le numéro de sa ligne dans la matrice).
+    ///   Rien n'est enregistré si la forme ne convient pas.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let db = Database::new();
+    /// let ids = npy::read_id_lines(BufReader::new(File::open("embeddings.ids")?))?;
+    /// let loaded = db.import_npy("NotaryDocuments", BufReader::new(File::open("embeddings.npy")?), Some(ids))?;
     /// ```
-    fn new() -> Self {
-        Database {
-            collections: HashMap::new(),
+    fn import_npy(&mut self, collection_name: &str, vectors: impl Read, ids: Option<Vec<DocumentId>>) -> Result<usize, DbError> {
+        self.get_collection(collection_name)?;
+        self.import_matrix(collection_name, MatrixReader::new(vectors)?, ids)
+    }
+
+    /// Charge dans une collection les vecteurs et les identifiants d'une archive NumPy `.npz`, telle qu'écrite
+    /// par `numpy.savez(chemin, ids=ids, vectors=vectors)` ou `numpy.savez_compressed`.
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection.
+    /// - `archive`: Le contenu de l'archive.
+    /// - `ids_name`: Le nom du tableau des identifiants ([`npy::NPZ_IDS`] par défaut) : des chaînes qui contiennent
+    ///   un UUID, ou des entiers.
+    /// - `vectors_name`: Le nom de la matrice des vecteurs ([`npy::NPZ_VECTORS`] par défaut).
+    ///
+    /// # Retour
+    /// - `Result<usize, DbError>`: Le nombre de vecteurs chargés, ou une erreur comme pour [`Database::import_npy`],
+    ///   ou si l'archive est invalide ou ne contient pas l'un des tableaux.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let loaded = db.import_npz("NotaryDocuments", File::open("embeddings.npz")?, npy::NPZ_IDS, npy::NPZ_VECTORS)?;
+    /// ```
+    fn import_npz(&mut self, collection_name: &str, archive: impl Read + Seek, ids_name: &str, vectors_name: &str) -> Result<usize, DbError> {
+        self.get_collection(collection_name)?;
+        let mut archive = ZipArchive::open(archive)?;
+        let ids = npy::read_ids(archive.open_entry(&format!("{}.npy", ids_name))?)?;
+        let matrix = MatrixReader::new(archive.open_entry(&format!("{}.npy", vectors_name))?)?;
+        let loaded = self.import_matrix(collection_name, matrix, Some(ids))?;
+        Ok(loaded)
+    }
+
+    /// Vérifie la forme d'une matrice avant de charger ses lignes, pour [`Database::import_npy`] et [`Database::import_npz`].
+    fn import_matrix(&mut self, collection_name: &str, mut matrix: MatrixReader<impl Read>, ids: Option<Vec<DocumentId>>) -> Result<usize, DbError> {
+        if let Some(expected) = self.get_collection(collection_name)?.dimension {
+            if matrix.columns() != expected {
+                return Err(DbError::DimensionMismatch { expected, found: matrix.columns() });
+            }
         }
+        if let Some(ids) = &ids {
+            if ids.len() != matrix.rows() {
+                return Err(DbError::Corrupted(format!("{} identifiants pour {} vecteurs", ids.len(), matrix.rows())));
+            }
+        }
+        self.load_documents(collection_name, |position| {
+            let id = ids.as_ref().map_or_else(|| texmex::dataset_id(position), |ids| ids.get(position).copied().unwrap_or_default());
+            Ok(matrix.next_row()?.map(|vector| (id, vector, Payload::new())))
+        })
     }
 
-    /// Ajoute une nouvelle [`Collection`] dans la base de données.
+    /// Exporte les vecteurs d'une collection dans une matrice NumPy `.npy` en `float32` (petit-boutiste),
+    /// et leurs identifiants dans un fichier texte, un par ligne, dans le même ordre (celui des identifiants).
+    /// Les métadonnées ne sont pas exportées.
     ///
     /// # Paramètres
-    /// - `name`: Le nom de la collection (unique).
+    /// - `collection_name`: Le nom de la collection.
+    /// - `vectors`: La destination de la matrice, de préférence dans un `BufWriter`.
+    /// - `ids`: La destination des identifiants, de préférence dans un `BufWriter`.
+    ///
+    /// # Retour
+    /// - `Result<usize, DbError>`: Le nombre de vecteurs exportés, ou une erreur si la collection
+    ///   n'existe pas ou si l'écriture échoue.
     ///
     /// # Exemple
     ///
     /// ```
-    /// let mut db = Database::new();
-    /// db.add_collection("NotaryDocuments".to_string());
+    /// let vectors = BufWriter::new(File::create("embeddings.npy")?);
+    /// let ids = BufWriter::new(File::create("embeddings.ids")?);
+    /// let exported = db.export_npy("NotaryDocuments", vectors, ids)?;
     /// ```
-    fn add_collection(&mut self, name: String) {
-        self.collections.insert(name, Collection::new());
+    fn export_npy(&self, collection_name: &str, mut vectors: impl Write, mut ids: impl Write) -> Result<usize, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let mut keys: Vec<&DocumentId> = collection.keys().collect();
+        keys.sort_unstable();
+        NpyHeader::write(&mut vectors, "<f4", &[keys.len(), collection.dimension.unwrap_or(0)])?;
+        let mut row = Vec::new();
+        for key in &keys {
+            row.clear();
+            row.extend(collection.data[key].iter().flat_map(|value| value.to_le_bytes()));
+            vectors.write_all(&row)?;
+            writeln!(ids, "{}", key)?;
+        }
+        vectors.flush()?;
+        ids.flush()?;
+        Ok(keys.len())
+    }
+
+    /// Enregistre par lots les documents donnés par `next`, appelée avec la position du document suivant
+    /// jusqu'à ce qu'elle retourne `None`, pour [`Database::load_vecs`] et [`Database::import_npy`].
+    fn load_documents(&mut self, collection_name: &str, mut next: impl FnMut(usize) -> Result<Option<Document>, DbError>) -> Result<usize, DbError> {
+        let batch_size = ImportOptions::default().batch_size;
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut loaded = 0;
+        while let Some(document) = next(loaded + batch.len())? {
+            batch.push(document);
+            if batch.len() == batch_size {
+                loaded += self.load_batch(collection_name, loaded, std::mem::take(&mut batch))?;
+            }
+        }
+        loaded += self.load_batch(collection_name, loaded, batch)?;
+        Ok(loaded)
+    }
+
+    /// Enregistre un lot de [`Database::load_documents`], dont le premier vecteur est à la position `start` du fichier.
+    fn load_batch(&mut self, collection_name: &str, start: usize, batch: Vec<Document>) -> Result<usize, DbError> {
+        let count = batch.len();
+        self.upsert_many(collection_name, batch).map_err(|error| match error {
+            DbError::InvalidBatch(mut errors) => {
+                let (index, error) = errors.swap_remove(0);
+                DbError::InvalidLine { line: start + index + 1, message: error.to_string() }
+            }
+            error => error,
+        })?;
+        Ok(count)
     }
 
     /// Récupère une [`Collection`] en lecture seule depuis la base de données, si elle existe.
@@ -177,64 +2065,195 @@ impl Database {
     /// - `name`: Le nom de la collection.
     ///
     /// # Retour
-    /// - `Option<&Collection>`: La collection si elle est trouvée, ou `None` sinon.
+    /// - `Result<&Collection, DbError>`: La collection si elle est trouvée, [`DbError::CollectionNotFound`] sinon.
     ///
     /// # Exemple
     ///
     /// ```
-    /// if let Some(collection) = db.get_collection("NotaryDocuments") {
+    /// if let Ok(collection) = db.get_collection("NotaryDocuments") {
     ///     // Utiliser la collection
     /// }
     /// ```
-    #[allow(unused)]
-    fn get_collection(&self, name: &str) -> Option<&Collection> {
-        self.collections.get(name)
+    fn get_collection(&self, name: &str) -> Result<&Collection, DbError> {
+        self.collections
+            .get(name)
+            .ok_or_else(|| DbError::CollectionNotFound(name.to_string()))
+    }
+
+    /// Retourne les noms des collections, triés par ordre alphabétique.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// for name in db.collection_names() {
+    ///     println!("{}", name);
+    /// }
+    /// ```
+    fn collection_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.collections.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
     }
 
     /// Récupère une [`Collection`] en écriture depuis la base de données, si elle existe.
     ///
+    /// Les documents ajoutés ou supprimés directement sur la collection ne sont pas journalisés :
+    /// pour une base persistante, utiliser [`Database::add_or_update`] et [`Database::remove`].
+    ///
     /// # Paramètres
     /// - `name`: Le nom de la collection.
     ///
     /// # Retour
-    /// - `Option<&mut Collection>`: La collection si elle est trouvée, ou `None` sinon.
+    /// - `Result<&mut Collection, DbError>`: La collection si elle est trouvée, [`DbError::CollectionNotFound`] sinon.
     ///
     /// # Exemple
     ///
     /// ```
-    /// if let Some(collection) = db.get_collection_mut("NotaryDocuments") {
-    ///     // Ajouter ou modifier des documents
+    /// if let Ok(collection) = db.get_collection_mut("NotaryDocuments") {
+    ///     collection.build_index(IndexConfig::Hnsw(HnswParams::default()));
     /// }
     /// ```
-    fn get_collection_mut(&mut self, name: &str) -> Option<&mut Collection> {
-        self.collections.get_mut(name)
+    fn get_collection_mut(&mut self, name: &str) -> Result<&mut Collection, DbError> {
+        self.collections
+            .get_mut(name)
+            .ok_or_else(|| DbError::CollectionNotFound(name.to_string()))
     }
 
-    /// Effectue une recherche dans une [`Collection`] spécifiée par son nom.
+    /// Effectue une recherche dans une [`Collection`] spécifiée par son nom
+    /// (voir [`Collection::search`] et [`Collection::search_exact`]).
     ///
     /// # Paramètres
     /// - `collection_name`: Le nom de la collection dans laquelle effectuer la recherche.
     /// - `query`: Le vecteur de la requête.
     /// - `k`: Le nombre de résultats maximal à retourner.
+    /// - `options`: Le filtre, la recherche exacte et l'ajout des métadonnées aux résultats.
     ///
     /// # Retour
-    /// - `Option<SearchResult>`: Les résultats de recherche (liste de (`DocumentId`, score_de_similarité)) si la collection est trouvée, `None` sinon.
+    /// - `Result<SearchResult, DbError>`: Les résultats de recherche (liste de (`DocumentId`, score)),
+    ///   [`DbError::CollectionNotFound`] si la collection n'existe pas, ou l'erreur de validation de la requête.
     ///
     /// # Exemple
     ///
     /// ```
     /// let query = vec![1.0, 1.0, 1.0];
-    /// if let Some(results) = db.search_in_collection("NotaryDocuments", &query, 3) {
-    ///     for (doc_id, similarity) in results {
-    ///         println!("DocID: {}, Similarité: {}", doc_id, similarity);
+    /// match db.search_in_collection("NotaryDocuments", &query, 3, SearchOptions::default()) {
+    ///     Ok(results) => {
+    ///         for (doc_id, score) in results {
+    ///             println!("DocID: {}, Score: {}", doc_id, score);
+    ///         }
     ///     }
+    ///     Err(error) => println!("Erreur: {}", error),
+    /// }
+    /// ```
+    fn search_in_collection(
+        &self,
+        collection_name: &str,
+        query: &[f32],
+        k: usize,
+        options: SearchOptions,
+    ) -> Result<SearchResult, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let results = if options.exact {
+            collection.search_exact(query, k, options.filter)?
+        } else {
+            collection.search(query, k, options.filter)?
+        };
+        Ok(if options.with_payload { collection.with_payloads(results) } else { results })
+    }
+
+    /// Recherche dans une [`Collection`] spécifiée par son nom tous les documents dont le score
+    /// atteint un seuil (voir [`Collection::search_range`] et [`Collection::search_range_exact`]).
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection dans laquelle effectuer la recherche.
+    /// - `query`: Le vecteur de la requête.
+    /// - `threshold`: Le score minimal (similarité) ou maximal (distance) des résultats, inclus.
+    /// - `limit`: Un nombre maximal facultatif de résultats.
+    /// - `options`: Le filtre, la recherche exacte et l'ajout des métadonnées aux résultats.
+    ///
+    /// # Retour
+    /// - `Result<SearchResult, DbError>`: Les documents qui atteignent le seuil, du plus proche au plus éloigné,
+    ///   [`DbError::CollectionNotFound`] si la collection n'existe pas, ou l'erreur de validation de la requête.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let results = db.search_range("NotaryDocuments", &[1.0, 1.0, 1.0], 0.92, Some(100), SearchOptions::default())?;
+    /// ```
+    fn search_range(
+        &self,
+        collection_name: &str,
+        query: &[f32],
+        threshold: f32,
+        limit: Option<usize>,
+        options: SearchOptions,
+    ) -> Result<SearchResult, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let results = if options.exact {
+            collection.search_range_exact(query, threshold, limit, options.filter)?
+        } else {
+            collection.search_range(query, threshold, limit, options.filter)?
+        };
+        Ok(if options.with_payload { collection.with_payloads(results) } else { results })
+    }
+
+    /// Effectue une recherche groupée dans une [`Collection`] spécifiée par son nom
+    /// (voir [`Collection::search_batch`] et [`Collection::search_exact_batch`]).
+    ///
+    /// # Paramètres
+    /// - `collection_name`: Le nom de la collection dans laquelle effectuer les recherches.
+    /// - `queries`: Les vecteurs des requêtes.
+    /// - `k`: Le nombre de résultats maximal par requête.
+    /// - `options`: Le filtre, commun à toutes les requêtes, la recherche exacte et l'ajout des métadonnées.
+    ///
+    /// # Retour
+    /// - `Result<Vec<SearchResult>, DbError>`: Un résultat par requête, dans l'ordre des requêtes,
+    ///   [`DbError::CollectionNotFound`] si la collection n'existe pas, ou [`DbError::InvalidQuery`].
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let queries = vec![vec![1.0, 1.0, 1.0], vec![0.5, 0.0, 1.0]];
+    /// let results = db.search_batch("NotaryDocuments", &queries, 3, SearchOptions::default())?;
+    /// for (query, results) in results.into_iter().enumerate() {
+    ///     println!("Requête {} : {:?}", query, results.hits);
     /// }
     /// ```
-    fn search_in_collection(&self, collection_name: &str, query: &[f32], k: usize) -> Option<SearchResult> {
-        self.collections.get(collection_name).map(|collection| collection.search(query, k))
+    fn search_batch(
+        &self,
+        collection_name: &str,
+        queries: &[Vec<f32>],
+        k: usize,
+        options: SearchOptions,
+    ) -> Result<Vec<SearchResult>, DbError> {
+        let collection = self.get_collection(collection_name)?;
+        let results = if options.exact {
+            collection.search_exact_batch(queries, k, options.filter)?
+        } else {
+            collection.search_batch(queries, k, options.filter)?
+        };
+        Ok(if options.with_payload {
+            results.into_iter().map(|results| collection.with_payloads(results)).collect()
+        } else {
+            results
+        })
     }
 }
 
+/// Répartit un lot de requêtes en blocs de requêtes consécutives traités en parallèle par le
+/// [`WorkerPool`] global, et retourne les résultats de `task` dans l'ordre des requêtes.
+/// Les blocs comptent au plus [`QUERY_BLOCK`] requêtes, et moins si le lot est trop petit
+/// pour occuper tous les threads.
+fn map_query_blocks<F>(queries: &[Vec<f32>], task: F) -> Vec<Vector>
+where
+    F: Fn(&[Vec<f32>]) -> Vec<Vector> + Sync,
+{
+    let pool = WorkerPool::global();
+    let block_size = queries.len().div_ceil(pool.workers()).clamp(1, QUERY_BLOCK);
+    let blocks: Vec<&[Vec<f32>]> = queries.chunks(block_size).collect();
+    pool.map(blocks.len(), |i| task(blocks[i])).into_iter().flatten().collect()
+}
+
 /// Calcule la similarité cosinus entre deux vecteurs.
 ///
 /// # Paramètres
@@ -252,142 +2271,574 @@ impl Database {
 /// println!("Similarité: {}", similarity);
 /// ```
 fn cosine_similarity(vector1: &[f32], vector2: &[f32]) -> f32 {
-    let (dot_product, (magnitude1, magnitude2)) = parallel_calculate(vector1, vector2);
-
-    if magnitude1 == 0.0 || magnitude2 == 0.0 {
-        0.0
-    } else {
-        dot_product / (magnitude1 * magnitude2)
-    }
+    let (dot_product, (magnitude1, magnitude2)) = calculate_dot_and_magnitudes(vector1, vector2);
+    cosine_from_dot_product(dot_product, magnitude1, magnitude2)
 }
 
-/// Calcule le produit scalaire (dot product) et les normes des deux vecteurs en parallèle.
-///
-/// # Paramètres
-/// - `vector1`: Le premier vecteur.
-/// - `vector2`: Le second vecteur.
+/// Termine le calcul de la similarité cosinus à partir du produit scalaire et des deux normes,
+/// calculées ensemble ou séparément (par exemple une norme mise en cache à l'insertion).
 ///
 /// # Retour
-/// - `(f32, (f32, f32))`: Un tuple contenant le produit scalaire, et le couple de normes (norme de `vector1`, norme de `vector2`).
+/// - `f32`: `dot_product / (magnitude1 * magnitude2)`, ou 0.0 si l'une des normes est nulle.
 ///
 /// # Exemple
 ///
 /// ```
-/// let (dot, (mag1, mag2)) = parallel_calculate(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+/// let similarity = cosine_from_dot_product(simd::dot(&query, &vector), simd::norm(&query), cached_norm);
 /// ```
-fn parallel_calculate(vector1: &[f32], vector2: &[f32]) -> (f32, (f32, f32)) {
-
-    // Clones nécessaires pour les threads
-    let vector1_clone = vector1.to_vec();
-    let vector2_clone = vector2.to_vec();
-    let vector1_clone_again = vector1.to_vec();
-    let vector2_clone_again = vector2.to_vec();
-    
-    // Calcul du produit scalaire dans un thread
-    let dot_product_handle = thread::spawn(move || calculate_dot_product(vector1_clone, vector2_clone));
-    // Calcul de la norme du premier vecteur dans un thread
-    let magnitude1_handle = thread::spawn(move || calculate_magnitude(&vector1_clone_again));
-    // Calcul de la norme du second vecteur dans un thread
-    let magnitude2_handle = thread::spawn(move || calculate_magnitude(&vector2_clone_again));
-
-    let dot_product = dot_product_handle.join().unwrap();
-    let magnitude1 = magnitude1_handle.join().unwrap();
-    let magnitude2 = magnitude2_handle.join().unwrap();
-
-    (dot_product, (magnitude1, magnitude2))
+fn cosine_from_dot_product(dot_product: f32, magnitude1: f32, magnitude2: f32) -> f32 {
+    if magnitude1 == 0.0 || magnitude2 == 0.0 {
+        0.0
+    } else {
+        dot_product / (magnitude1 * magnitude2)
+    }
 }
 
-/// Calcule le produit scalaire de deux vecteurs.
-///
-/// # Paramètres
-/// - `vector1`: Le premier vecteur.
-/// - `vector2`: Le second vecteur.
-///
-/// # Retour
-/// - `f32`: La somme des multiplications coordonnées à coordonnées.
+/// Divise un vecteur par sa norme ; un vecteur nul est retourné tel quel.
 ///
 /// # Exemple
 ///
 /// ```
-/// let dot = calculate_dot_product(vec![1.0, 2.0], vec![3.0, 4.0]);
-/// assert_eq!(dot, 11.0);
+/// assert_eq!(normalized(vec![3.0, 4.0]), vec![0.6, 0.8]);
 /// ```
-fn calculate_dot_product(vector1: Vec<f32>, vector2: Vec<f32>) -> f32 {
-    vector1.iter().zip(vector2).map(|(x, y)| x * y).sum()
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = simd::norm(&vector);
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
 }
 
-/// Calcule la norme (euclidienne) d'un vecteur.
+/// Calcule le produit scalaire et les normes des deux vecteurs en un seul parcours,
+/// avec les instructions vectorielles du processeur (voir [`simd`]).
 ///
 /// # Paramètres
-/// - `vector`: Le vecteur à évaluer.
+/// - `vector1`: Le premier vecteur.
+/// - `vector2`: Le second vecteur.
 ///
 /// # Retour
-/// - `f32`: La norme du vecteur.
+/// - `(f32, (f32, f32))`: Un tuple contenant le produit scalaire, et le couple de normes (norme de `vector1`, norme de `vector2`).
 ///
 /// # Exemple
 ///
 /// ```
-/// let mag = calculate_magnitude(&[3.0, 4.0]);
-/// assert_eq!(mag, 5.0);
+/// let (dot, (mag1, mag2)) = calculate_dot_and_magnitudes(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
 /// ```
-fn calculate_magnitude(vector: &[f32]) -> f32 {
-    vector.iter().map(|x| x * x).sum::<f32>().sqrt()
+fn calculate_dot_and_magnitudes(vector1: &[f32], vector2: &[f32]) -> (f32, (f32, f32)) {
+    let (dot_product, squared_norm1, squared_norm2) = simd::dot_and_norms(vector1, vector2);
+    (dot_product, (squared_norm1.sqrt(), squared_norm2.sqrt()))
 }
 
 fn main() {
-    use colored::*;
+    std::process::exit(cli::run(std::env::args().skip(1).collect()));
+}
 
-    // Création d'une nouvelle base de données
-    println!("{}", "\n=== Bienvenue dans le Moteur de Recherche Documentaire ===\n".bold().truecolor(135, 206, 250));
-    let mut db = Database::new();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bq::BqParams;
+    use crate::payload::Value;
+    use std::path::PathBuf;
 
-    // Ajout des collections
-    println!("{}", "Ajout des collections...".bold().bright_green());
-    db.add_collection("NotaryDocuments".to_string());
-    db.add_collection("LegalFiles".to_string());
+    /// Un répertoire de données temporaire, supprimé à la fin du test.
+    struct DataDir(PathBuf);
 
-    // Ajouter des documents dans "NotaryDocuments"
-    if let Some(collection) = db.get_collection_mut("NotaryDocuments") {
-        println!("{}", "\nAjout de documents à la collection 'NotaryDocuments'...".bold().yellow());
-        collection.add_or_update(Uuid::new_v4(), vec![1.0, 2.0, 3.0]);
-        collection.add_or_update(Uuid::new_v4(), vec![4.0, 5.0, 6.0]);
-        println!("{}", "Documents ajoutés avec succès !".bright_green());
+    impl DataDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("projet-test-{}", Uuid::new_v4()));
+            std::fs::create_dir_all(&path).unwrap();
+            DataDir(path)
+        }
     }
 
-    // Ajouter des documents dans "LegalFiles"
-    if let Some(collection) = db.get_collection_mut("LegalFiles") {
-        println!("{}", "\nAjout de documents à la collection 'LegalFiles'...".bold().yellow());
-        collection.add_or_update(Uuid::new_v4(), vec![1.0, 0.0, 0.0]);
-        collection.add_or_update(Uuid::new_v4(), vec![0.0, 1.0, 0.0]);
-        println!("{}", "Documents ajoutés avec succès !".bright_green());
+    impl Drop for DataDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
     }
 
-    // Début de la recherche
-    let query = vec![1.0, 1.0, 1.0];
-    println!("\n{}", "=== Recherche avec la requête: [1.0, 1.0, 1.0] ===".bold().truecolor(255, 215, 0));
+    #[test]
+    fn failed_operation_is_not_replayed() {
+        let dir = DataDir::new();
+        let mapped = CollectionConfig { mapped: true, ..Default::default() };
+        {
+            let mut db = Database::open(&dir.0).unwrap();
+            // Le fichier de vecteurs de la collection ne peut pas être créé dans un répertoire absent.
+            db.directory = Some(dir.0.join("absent"));
+            assert!(matches!(db.add_collection("docs".to_string(), mapped), Err(DbError::Io(_))));
+            assert!(db.get_collection("docs").is_err());
+            db.directory = Some(dir.0.clone());
+            db.add_collection("docs".to_string(), mapped).unwrap();
+            db.add_or_update("docs", Uuid::from_u128(1), vec![1.0, 2.0], Payload::new()).unwrap();
+        }
+        let db = Database::open(&dir.0).unwrap();
+        assert_eq!(db.collection_names(), vec!["docs".to_string()]);
+        assert_eq!(db.get_collection("docs").unwrap().get(&Uuid::from_u128(1)), Some([1.0, 2.0].as_slice()));
+    }
 
-    // Recherche dans "NotaryDocuments"
-    if let Some(results) = db.search_in_collection("NotaryDocuments", &query, 3) {
-        println!("\n{}", "Résultats de recherche dans 'NotaryDocuments':".bright_blue().bold());
-        for (key, similarity) in results {
-            println!("{} {} {} {:.4}",
-                "Document ID:".bright_magenta(), key.to_string().bright_white(), "- Similarité:".bright_magenta(), similarity);
+    #[test]
+    fn rebuild_index_keeps_the_parameters() {
+        let mut db = Database::new();
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        for n in 0..20u128 {
+            db.add_or_update("docs", Uuid::from_u128(n), vec![n as f32, 1.0], Payload::new()).unwrap();
         }
-    } else {
-        println!("{}", "Aucun résultat trouvé dans 'NotaryDocuments'.".red().bold());
+        assert_eq!(db.rebuild_index("docs").unwrap(), None);
+
+        let config = IndexConfig::Ivf(ivf::IvfParams { nlist: 4, nprobe: 2, ..Default::default() });
+        db.set_index("docs", Some(config)).unwrap();
+        db.tune_index("docs", None, Some(3)).unwrap();
+        let tuned = IndexConfig::Ivf(ivf::IvfParams { nlist: 4, nprobe: 3, ..Default::default() });
+        assert_eq!(db.rebuild_index("docs").unwrap(), Some(tuned));
+        assert_eq!(db.get_collection("docs").unwrap().index.as_ref().map(VectorIndex::config), Some(tuned));
+        assert!(matches!(db.rebuild_index("absent"), Err(DbError::CollectionNotFound(_))));
+    }
+
+    fn number(n: f64) -> Payload {
+        Payload::from([("n".to_string(), Value::Number(n))])
     }
 
-    // Recherche dans "LegalFiles"
-    if let Some(results) = db.search_in_collection("LegalFiles", &query, 3) {
-        println!("\n{}", "Résultats de recherche dans 'LegalFiles':".bright_blue().bold());
-        for (key, similarity) in results {
-            println!("{} {} {} {:.4}",
-                "Document ID:".bright_magenta(), key.to_string().bright_white(), "- Similarité:".bright_magenta(), similarity);
+    /// Une collection `docs` de dimension 2 avec un index HNSW, le champ `n` indexé et les documents `1..=3`.
+    fn indexed_collection(db: &mut Database) {
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        db.set_index("docs", Some(IndexConfig::Hnsw(hnsw::HnswParams::default()))).unwrap();
+        db.create_payload_index("docs", "n").unwrap();
+        for n in 1..=3u128 {
+            db.add_or_update("docs", Uuid::from_u128(n), vec![n as f32, 1.0], number(n as f64)).unwrap();
         }
-    } else {
-        println!("{}", "Aucun résultat trouvé dans 'LegalFiles'.".red().bold());
     }
 
-    // Fin
-    println!("\n{}", "=== Fin de la recherche ===".bold().truecolor(135, 206, 250));
+    /// Les résultats d'une recherche approximative et d'une recherche filtrée par l'index des métadonnées.
+    fn results(db: &Database) -> (Vector, Vector) {
+        let collection = db.get_collection("docs").unwrap();
+        let filter = Filter::parse("n IN [1, 2, 10]").unwrap();
+        (
+            collection.search(&[1.0, 1.0], 10, None).unwrap().hits,
+            collection.search(&[1.0, 1.0], 10, Some(&filter)).unwrap().hits,
+        )
+    }
+
+    #[test]
+    fn invalid_batch_leaves_the_collection_unchanged() {
+        let dir = DataDir::new();
+        let before;
+        {
+            let mut db = Database::open(&dir.0).unwrap();
+            indexed_collection(&mut db);
+            before = results(&db);
+            let wal_len = std::fs::metadata(dir.0.join("wal.log")).unwrap().len();
+
+            let batch = vec![
+                (Uuid::from_u128(1), vec![-1.0, 5.0], number(10.0)),
+                (Uuid::from_u128(10), vec![10.0, 1.0], number(10.0)),
+                (Uuid::from_u128(11), vec![1.0, 2.0, 3.0], number(10.0)),
+                (Uuid::from_u128(12), vec![f32::NAN, 1.0], number(10.0)),
+            ];
+            match db.upsert_many("docs", batch) {
+                Err(DbError::InvalidBatch(errors)) => {
+                    let positions: Vec<usize> = errors.iter().map(|(position, _)| *position).collect();
+                    assert_eq!(positions, vec![2, 3]);
+                    assert!(matches!(errors[0].1, DbError::DimensionMismatch { expected: 2, found: 3 }));
+                    assert!(matches!(errors[1].1, DbError::InvalidValue { position: 0, .. }));
+                }
+                result => panic!("lot invalide accepté : {:?}", result),
+            }
+
+            let collection = db.get_collection("docs").unwrap();
+            assert_eq!(collection.len(), 3);
+            assert_eq!(collection.get(&Uuid::from_u128(1)), Some([1.0, 1.0].as_slice()));
+            assert_eq!(collection.get_payload(&Uuid::from_u128(1)), Some(&number(1.0)));
+            assert_eq!(collection.get(&Uuid::from_u128(10)), None);
+            assert_eq!(results(&db), before);
+            assert_eq!(std::fs::metadata(dir.0.join("wal.log")).unwrap().len(), wal_len);
+        }
+        let db = Database::open(&dir.0).unwrap();
+        assert_eq!(db.get_collection("docs").unwrap().len(), 3);
+        assert_eq!(results(&db), before);
+    }
+
+    #[test]
+    fn batch_statuses_follow_the_order_of_the_batch() {
+        let dir = DataDir::new();
+        let (a, b, c) = (Uuid::from_u128(1), Uuid::from_u128(10), Uuid::from_u128(11));
+        {
+            let mut db = Database::open(&dir.0).unwrap();
+            indexed_collection(&mut db);
+            assert_eq!(db.upsert_many("docs", Vec::new()).unwrap(), Vec::new());
+
+            let batch = vec![
+                (b, vec![10.0, 1.0], number(10.0)),
+                (a, vec![-1.0, 1.0], number(10.0)),
+                (b, vec![11.0, 1.0], number(11.0)),
+                (c, vec![12.0, 1.0], number(10.0)),
+            ];
+            let statuses = db.upsert_many("docs", batch).unwrap();
+            assert_eq!(statuses, vec![ItemStatus::Inserted, ItemStatus::Updated, ItemStatus::Updated, ItemStatus::Inserted]);
+            // Le dernier document d'un identifiant répété l'emporte.
+            assert_eq!(db.get_collection("docs").unwrap().get(&b), Some([11.0, 1.0].as_slice()));
+
+            let statuses = db.remove_many("docs", &[a, Uuid::from_u128(99), a, c]).unwrap();
+            assert_eq!(statuses, vec![ItemStatus::Removed, ItemStatus::NotFound, ItemStatus::NotFound, ItemStatus::Removed]);
+            assert!(matches!(db.remove_many("absent", &[a]), Err(DbError::CollectionNotFound(_))));
+
+            // Une suppression qui ne trouve aucun document n'est pas journalisée.
+            let wal_len = std::fs::metadata(dir.0.join("wal.log")).unwrap().len();
+            assert_eq!(db.remove_many("docs", &[a, c]).unwrap(), vec![ItemStatus::NotFound; 2]);
+            assert_eq!(std::fs::metadata(dir.0.join("wal.log")).unwrap().len(), wal_len);
+        }
+        let db = Database::open(&dir.0).unwrap();
+        let collection = db.get_collection("docs").unwrap();
+        let mut keys: Vec<DocumentId> = collection.data.keys().copied().collect();
+        keys.sort();
+        assert_eq!(keys, vec![Uuid::from_u128(2), Uuid::from_u128(3), b]);
+        // L'index et l'index des métadonnées ont oublié les documents supprimés.
+        let (hits, filtered) = results(&db);
+        assert_eq!(hits.len(), 3);
+        assert!(hits.iter().all(|(key, _)| *key != a && *key != c));
+        assert_eq!(filtered.iter().map(|(key, _)| *key).collect::<Vec<_>>(), vec![Uuid::from_u128(2)]);
+    }
+
+    /// Une collection `docs` de 200 documents de dimension 8 selon `metric`, aux composantes pseudo-aléatoires
+    /// reproductibles (binaires pour la distance de Hamming) ; la métadonnée `pair` indique la parité de l'identifiant.
+    fn random_collection(metric: Metric) -> Database {
+        let mut db = Database::new();
+        db.add_collection("docs".to_string(), CollectionConfig { metric, ..Default::default() }).unwrap();
+        for n in 0..200u128 {
+            let vector = (0..8)
+                .map(|j| ((n * 8 + j) as f32 * 12.9898).sin())
+                .map(|value| if metric == Metric::Hamming { (value > 0.0) as u8 as f32 } else { value })
+                .collect();
+            let payload = Payload::from([("pair".to_string(), Value::Bool(n % 2 == 0))]);
+            db.add_or_update("docs", Uuid::from_u128(n), vector, payload).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn binary_quantizer_recall_grows_with_oversampling() {
+        let mut db = random_collection(Metric::Cosine);
+        let mut rng = SplitMix64::new(11);
+        let queries: Vec<Vec<f32>> = (0..20).map(|_| (0..8).map(|_| (rng.next_unit() * 2.0 - 1.0) as f32).collect()).collect();
+        let k = 10;
+        let mut recalls = Vec::new();
+        for oversampling in [1.0, 4.0, 20.0] {
+            db.set_quantizer("docs", Some(QuantizerConfig::Binary(BqParams { oversampling }))).unwrap();
+            let collection = db.get_collection("docs").unwrap();
+            let mut found = 0;
+            for query in &queries {
+                let expected = collection.search_exact(query, k, None).unwrap().hits;
+                let hits = collection.search(query, k, None).unwrap().hits;
+                assert_eq!(hits.len(), k);
+                // Les candidats sont reclassés : les scores sont ceux des vecteurs d'origine.
+                for (key, score) in &hits {
+                    assert_eq!(*score, Metric::Cosine.score(query, collection.get(key).unwrap()));
+                }
+                found += hits.iter().filter(|hit| expected.contains(hit)).count();
+            }
+            recalls.push(found as f64 / (queries.len() * k) as f64);
+        }
+        assert!(recalls.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", recalls);
+        assert!(recalls[1] >= 0.6, "{:?}", recalls);
+        // 20 × k candidats couvrent les 200 documents : le reclassement redonne la recherche exacte.
+        assert_eq!(recalls[2], 1.0);
+    }
+
+    #[test]
+    fn range_threshold_is_inclusive_for_every_metric() {
+        let exact = SearchOptions { exact: true, ..Default::default() };
+        for metric in [Metric::Cosine, Metric::Dot, Metric::Euclidean, Metric::Manhattan, Metric::Hamming] {
+            let db = random_collection(metric);
+            let query = db.get_collection("docs").unwrap().get(&Uuid::from_u128(0)).unwrap().to_vec();
+            let all = db.search_in_collection("docs", &query, 200, exact).unwrap().hits;
+            let threshold = all[20].1;
+            let expected: Vector = all.iter().copied().filter(|(_, score)| metric.within(*score, threshold)).collect();
+            assert!(expected.len() > 20, "{}", metric);
+
+            // Le document dont le score est exactement le seuil fait partie des résultats.
+            let results = db.search_range("docs", &query, threshold, None, exact).unwrap();
+            assert_eq!(results.metric, metric);
+            assert_eq!(results.hits, expected, "{}", metric);
+            let stricter = if metric.higher_is_better() { threshold + 1e-3 } else { threshold - 1e-3 };
+            let results = db.search_range("docs", &query, stricter, None, exact).unwrap().hits;
+            assert!(results.len() < expected.len() && !results.contains(&all[20]), "{}", metric);
+
+            // Le plafond garde les meilleurs résultats.
+            let capped = db.search_range("docs", &query, threshold, Some(5), exact).unwrap().hits;
+            assert_eq!(capped, expected[..5]);
+            assert!(db.search_range("docs", &query, threshold, Some(0), exact).unwrap().hits.is_empty());
+        }
+    }
+
+    #[test]
+    fn range_search_through_an_index_grows_past_the_first_candidates() {
+        let mut db = random_collection(Metric::Euclidean);
+        let params = hnsw::HnswParams { ef_search: 200, ..Default::default() };
+        db.set_index("docs", Some(IndexConfig::Hnsw(params))).unwrap();
+        let query = vec![0.0; 8];
+        let exact = SearchOptions { exact: true, ..Default::default() };
+        let all = db.search_in_collection("docs", &query, 200, exact).unwrap().hits;
+
+        // Bien plus de documents que le premier nombre de voisins demandé à l'index.
+        for rank in [0, 60, 150] {
+            let threshold = all[rank].1;
+            let expected = db.search_range("docs", &query, threshold, None, exact).unwrap().hits;
+            let approximate = db.search_range("docs", &query, threshold, None, SearchOptions::default()).unwrap().hits;
+            assert_eq!(approximate, expected, "seuil du rang {}", rank);
+        }
+
+        let filter = Filter::parse("pair == true").unwrap();
+        let options = SearchOptions { filter: Some(&filter), with_payload: true, ..Default::default() };
+        let results = db.search_range("docs", &query, all[100].1, Some(30), options).unwrap();
+        assert_eq!(results.hits.len(), 30);
+        let payloads = results.payloads.unwrap();
+        assert!(payloads.iter().all(|payload| payload["pair"] == Value::Bool(true)));
+    }
+
+    #[test]
+    fn batch_search_matches_single_searches() {
+        let mut db = random_collection(Metric::Cosine);
+        let collection = db.get_collection("docs").unwrap();
+        let queries: Vec<Vec<f32>> = (0..40u128).map(|n| collection.get(&Uuid::from_u128(n * 5)).unwrap().to_vec()).collect();
+        let filter = Filter::parse("pair == false").unwrap();
+
+        for indexed in [false, true] {
+            if indexed {
+                db.set_index("docs", Some(IndexConfig::Lsh(lsh::LshParams::default()))).unwrap();
+            }
+            for exact in [false, true] {
+                let options = SearchOptions { filter: Some(&filter), exact, with_payload: true };
+                let batch = db.search_batch("docs", &queries, 5, options).unwrap();
+                assert_eq!(batch.len(), queries.len());
+                for (query, results) in queries.iter().zip(batch) {
+                    let single = db.search_in_collection("docs", query, 5, options).unwrap();
+                    assert_eq!(results.hits, single.hits);
+                    assert_eq!(results.payloads, single.payloads);
+                }
+            }
+        }
+
+        let mut queries = queries;
+        queries[3] = vec![1.0; 3];
+        assert!(matches!(
+            db.search_batch("docs", &queries, 5, SearchOptions::default()),
+            Err(DbError::InvalidQuery { index: 3, .. })
+        ));
+        assert!(matches!(
+            db.search_in_collection("absent", &queries[0], 5, SearchOptions::default()),
+            Err(DbError::CollectionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_vectors_are_rejected_with_typed_errors() {
+        let mut collection = Collection::new(CollectionConfig::default());
+        let key = Uuid::from_u128(1);
+        assert!(matches!(collection.add_or_update(key, vec![], Payload::new()), Err(DbError::EmptyVector)));
+        // Un vecteur refusé ne fixe pas la dimension de la collection.
+        assert_eq!(collection.dimension, None);
+
+        collection.add_or_update(key, vec![1.0, 2.0, 3.0], Payload::new()).unwrap();
+        assert_eq!(collection.dimension, Some(3));
+        assert!(matches!(
+            collection.add_or_update(key, vec![1.0, 2.0], Payload::new()),
+            Err(DbError::DimensionMismatch { expected: 3, found: 2 })
+        ));
+        assert!(matches!(
+            collection.add_or_update(Uuid::from_u128(2), vec![1.0, f32::NAN, 3.0], Payload::new()),
+            Err(DbError::InvalidValue { position: 1, value }) if value.is_nan()
+        ));
+        assert!(matches!(
+            collection.add_or_update(key, vec![1.0, 2.0, f32::NEG_INFINITY], Payload::new()),
+            Err(DbError::InvalidValue { position: 2, value: f32::NEG_INFINITY })
+        ));
+        assert_eq!(collection.data.len(), 1);
+        assert_eq!(collection.get(&key), Some([1.0, 2.0, 3.0].as_slice()));
+
+        // Les requêtes sont validées comme les documents.
+        assert!(matches!(
+            collection.search_exact(&[1.0; 4], 1, None),
+            Err(DbError::DimensionMismatch { expected: 3, found: 4 })
+        ));
+        assert!(matches!(collection.search(&[f32::INFINITY, 0.0, 0.0], 1, None), Err(DbError::InvalidValue { position: 0, .. })));
+
+        // Une dimension imposée s'applique dès le premier document.
+        let mut collection = Collection::new(CollectionConfig { dimension: Some(2), ..Default::default() });
+        assert!(matches!(
+            collection.add_or_update(key, vec![1.0, 2.0, 3.0], Payload::new()),
+            Err(DbError::DimensionMismatch { expected: 2, found: 3 })
+        ));
+        assert_eq!(collection.len(), 0);
+    }
+
+    /// Le même fichier de quatre documents dans les deux formats : la ligne 3 a un vecteur de dimension 3
+    /// et la ligne 4 n'est pas lisible. Pour un fichier CSV, la ligne 1 est l'en-tête.
+    fn import_files() -> [(Format, String, [usize; 2]); 2] {
+        let id = |n: u128| Uuid::from_u128(n).to_string();
+        let csv = format!(
+            "id,vector,n:json\n{},\"[1, 0]\",1\n{},\"[1, 0, 0]\",2\n{},\"[0, 1]\",pas du json\n{},\"[1, 1]\",4\n",
+            id(1), id(2), id(3), id(4)
+        );
+        let jsonl = format!(
+            "{{\"id\":\"{}\",\"vector\":[1,0],\"payload\":{{\"n\":1}}}}\n{{\"id\":\"{}\",\"vector\":[1,0,0]}}\n{{\"id\":\n{{\"id\":\"{}\",\"vector\":[1,1],\"payload\":{{\"n\":4}}}}\n",
+            id(1), id(2), id(4)
+        );
+        [(Format::Csv, csv, [3, 4]), (Format::JsonLines, jsonl, [2, 3])]
+    }
+
+    #[test]
+    fn import_skips_invalid_lines_with_their_number() {
+        for (format, text, lines) in import_files() {
+            let mut db = Database::new();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            let options = ImportOptions { format, on_error: ErrorPolicy::Skip, batch_size: 1 };
+            let report = db.import("docs", text.as_bytes(), options).unwrap();
+            assert_eq!((report.imported, report.skipped), (2, 2), "{:?}", format);
+            assert_eq!(report.errors.iter().map(|(line, _)| *line).collect::<Vec<_>>(), lines, "{:?}", format);
+            let collection = db.get_collection("docs").unwrap();
+            assert_eq!(collection.len(), 2);
+            assert_eq!(collection.payloads[&Uuid::from_u128(4)]["n"], Value::Number(4.0));
+        }
+    }
+
+    #[test]
+    fn import_aborts_at_the_first_invalid_line() {
+        for (format, text, lines) in import_files() {
+            let mut db = Database::new();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            let options = ImportOptions { format, ..Default::default() };
+            let error = db.import("docs", text.as_bytes(), options).unwrap_err();
+            assert!(matches!(error, DbError::InvalidLine { line, .. } if line == lines[0]), "{:?}: {}", format, error);
+            // Les documents lus avant la ligne invalide restent importés.
+            let collection = db.get_collection("docs").unwrap();
+            assert_eq!(collection.len(), 1);
+            assert_eq!(collection.get(&Uuid::from_u128(1)), Some([1.0, 0.0].as_slice()));
+        }
+    }
+
+    #[test]
+    fn exported_documents_import_unchanged() {
+        let mut db = Database::new();
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        let payload = Payload::from([
+            ("code".to_string(), Value::from("00123")),
+            ("actif".to_string(), Value::from("true")),
+            ("n".to_string(), Value::Number(2.5)),
+        ]);
+        db.add_or_update("docs", Uuid::from_u128(1), vec![0.25, -3.0], payload).unwrap();
+        db.add_or_update("docs", Uuid::from_u128(2), vec![1.0, 2.0], number(7.0)).unwrap();
+        for format in [Format::Csv, Format::JsonLines] {
+            let mut file = Vec::new();
+            assert_eq!(db.export("docs", &mut file, format).unwrap(), 2);
+            let mut copy = Database::new();
+            copy.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            let options = ImportOptions { format, ..Default::default() };
+            assert_eq!(copy.import("docs", file.as_slice(), options).unwrap().imported, 2);
+            let (original, imported) = (db.get_collection("docs").unwrap(), copy.get_collection("docs").unwrap());
+            assert_eq!(imported.payloads, original.payloads, "{:?}", format);
+            for n in 1..=2 {
+                assert_eq!(imported.get(&Uuid::from_u128(n)), original.get(&Uuid::from_u128(n)));
+            }
+        }
+    }
+
+    #[test]
+    fn loaded_base_is_found_by_exact_search() {
+        let mut base = Vec::new();
+        for n in 0..5 {
+            base.extend(2i32.to_le_bytes());
+            base.extend([n as f32, 1.0].iter().flat_map(|value| value.to_le_bytes()));
+        }
+        let mut db = Database::new();
+        db.add_collection("sift".to_string(), CollectionConfig::default()).unwrap();
+        assert_eq!(db.load_vecs("sift", base.as_slice(), VecsFormat::Fvecs, Some(4)).unwrap(), 4);
+        let collection = db.get_collection("sift").unwrap();
+        assert_eq!(collection.len(), 4);
+        assert_eq!(collection.get(&texmex::dataset_id(3)), Some([3.0, 1.0].as_slice()));
+
+        let queries = texmex::QuerySet { queries: vec![vec![0.1, 1.0], vec![2.9, 1.0]] };
+        let truth = texmex::GroundTruth { neighbours: vec![vec![0, 1], vec![3, 2]] };
+        let report = texmex::evaluate(collection, &queries, &truth, 2).unwrap();
+        assert_eq!((report.queries, report.recall), (2, 1.0));
+        assert!(matches!(db.load_vecs("absent", base.as_slice(), VecsFormat::Fvecs, None), Err(DbError::CollectionNotFound(_))));
+    }
+
+    /// Les identifiants et les vecteurs de `testdata/vectors.npz`, écrits comme par `numpy.savez`.
+    fn npz_documents() -> Vec<(DocumentId, Vec<f32>)> {
+        let ids = [
+            "1c9d6bcd-bbfd-4b2d-9b5d-ab8dfbbd4bed",
+            "6ec0bd7f-11c0-43da-975e-2a8ad9ebae0b",
+            "a3bb189e-8bf9-3888-9912-ace4e6543002",
+            "e4d909c2-90d0-fb1c-a068-ffaddf22cbd0",
+        ];
+        let vectors = [[1.0, -2.0, 0.5], [0.25, 3.0, -1.5], [8.0, 0.0, -0.125], [2.5, 4.0, 6.0]];
+        ids.iter().zip(vectors).map(|(id, vector)| (Uuid::parse_str(id).unwrap(), vector.to_vec())).collect()
+    }
+
+    #[test]
+    fn imports_numpy_archives() {
+        let saved: &[u8] = include_bytes!("../testdata/vectors.npz");
+        let compressed: &[u8] = include_bytes!("../testdata/vectors_compressed.npz");
+        // Float32 petit- et gros-boutiste, float16 et archive compressée donnent les mêmes documents.
+        for (archive, vectors_name) in [(saved, npy::NPZ_VECTORS), (saved, "vectors_be"), (saved, "vectors_f2"), (compressed, npy::NPZ_VECTORS)] {
+            let mut db = Database::new();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            let loaded = db.import_npz("docs", std::io::Cursor::new(archive), npy::NPZ_IDS, vectors_name).unwrap();
+            assert_eq!(loaded, 4, "{}", vectors_name);
+            let collection = db.get_collection("docs").unwrap();
+            for (id, vector) in npz_documents() {
+                assert_eq!(collection.get(&id), Some(vector.as_slice()), "{}", vectors_name);
+            }
+        }
+
+        // Des identifiants entiers donnent les identifiants des jeux de données.
+        let mut db = Database::new();
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        db.import_npz("docs", std::io::Cursor::new(saved), "ids_int", npy::NPZ_VECTORS).unwrap();
+        let collection = db.get_collection("docs").unwrap();
+        for (position, (_, vector)) in npz_documents().into_iter().enumerate() {
+            assert_eq!(collection.get(&texmex::dataset_id(position)), Some(vector.as_slice()));
+        }
+    }
+
+    #[test]
+    fn numpy_arrays_of_the_wrong_type_or_shape_are_refused() {
+        let saved: &[u8] = include_bytes!("../testdata/vectors.npz");
+        let import = |dimension: Option<usize>, ids_name: &str, vectors_name: &str| {
+            let mut db = Database::new();
+            db.add_collection("docs".to_string(), CollectionConfig { dimension, ..Default::default() }).unwrap();
+            let result = db.import_npz("docs", std::io::Cursor::new(saved), ids_name, vectors_name);
+            assert_eq!(db.get_collection("docs").unwrap().len(), 0, "{}", vectors_name);
+            result.unwrap_err()
+        };
+        for vectors_name in ["vectors_f8", "vectors_fortran", "vectors_3d"] {
+            assert!(matches!(import(None, npy::NPZ_IDS, vectors_name), DbError::Unsupported(_)), "{}", vectors_name);
+        }
+        assert!(matches!(import(None, "vectors", npy::NPZ_VECTORS), DbError::Unsupported(_)));
+        assert!(matches!(import(None, "ids_short", npy::NPZ_VECTORS), DbError::Corrupted(_)));
+        assert!(matches!(import(None, "absent", npy::NPZ_VECTORS), DbError::Corrupted(_)));
+        assert!(matches!(
+            import(Some(2), npy::NPZ_IDS, npy::NPZ_VECTORS),
+            DbError::DimensionMismatch { expected: 2, found: 3 }
+        ));
+    }
+
+    #[test]
+    fn refused_vectors_report_their_position() {
+        let rows = [[1.0, 0.0], [0.0, 1.0], [f32::NAN, 1.0]];
+        let mut matrix = Vec::new();
+        NpyHeader::write(&mut matrix, "<f4", &[3, 2]).unwrap();
+        let mut fvecs = Vec::new();
+        for row in rows {
+            matrix.extend(row.iter().flat_map(|value| value.to_le_bytes()));
+            fvecs.extend(2i32.to_le_bytes());
+            fvecs.extend(row.iter().flat_map(|value| value.to_le_bytes()));
+        }
+
+        let mut db = Database::new();
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        let error = db.import_npy("docs", matrix.as_slice(), None).unwrap_err();
+        assert!(matches!(error, DbError::InvalidLine { line: 3, .. }), "{}", error);
+        let error = db.load_vecs("docs", fvecs.as_slice(), VecsFormat::Fvecs, None).unwrap_err();
+        assert!(matches!(error, DbError::InvalidLine { line: 3, .. }), "{}", error);
+    }
 }
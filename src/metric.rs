@@ -0,0 +1,272 @@
+//! # Module: `metric`
+//!
+//! Les métriques de comparaison de vecteurs qu'une [`Collection`](crate::Collection) peut utiliser.
+//! Certaines sont des **similarités** (plus le score est grand, plus les documents sont proches),
+//! les autres des **distances** (plus le score est petit, plus les documents sont proches).
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{cosine_from_dot_product, cosine_similarity};
+use crate::error::DbError;
+use crate::simd;
+
+/// # Énumération: `Metric`
+///
+/// La métrique utilisée par une collection pour classer ses documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Metric {
+    /// Similarité cosinus, comprise entre -1.0 et 1.0 (similarité).
+    #[default]
+    Cosine,
+    /// Produit scalaire brut, sans normalisation (similarité).
+    Dot,
+    /// Distance euclidienne ou L2 (distance).
+    Euclidean,
+    /// Distance de Manhattan ou L1 (distance).
+    Manhattan,
+    /// Distance de Hamming entre vecteurs binaires : nombre de coordonnées dont l'une est nulle
+    /// et l'autre non (distance).
+    Hamming,
+}
+
+impl Metric {
+    /// Calcule le score de la métrique entre deux vecteurs de même dimension.
+    ///
+    /// # Paramètres
+    /// - `vector1`: Le premier vecteur.
+    /// - `vector2`: Le second vecteur.
+    ///
+    /// # Retour
+    /// - `f32`: Une similarité ou une distance selon la métrique (voir [`Metric::higher_is_better`]).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let distance = Metric::Euclidean.score(&[0.0, 0.0], &[3.0, 4.0]);
+    /// assert_eq!(distance, 5.0);
+    /// ```
+    pub fn score(self, vector1: &[f32], vector2: &[f32]) -> f32 {
+        match self {
+            Metric::Cosine => cosine_similarity(vector1, vector2),
+            Metric::Dot => simd::dot(vector1, vector2),
+            Metric::Euclidean => simd::squared_l2(vector1, vector2).sqrt(),
+            Metric::Manhattan => vector1.iter().zip(vector2).map(|(x, y)| (x - y).abs()).sum(),
+            Metric::Hamming => vector1
+                .iter()
+                .zip(vector2)
+                .filter(|(x, y)| (**x != 0.0) != (**y != 0.0))
+                .count() as f32,
+        }
+    }
+
+    /// Calcule le score entre deux vecteurs dont les normes sont déjà connues.
+    ///
+    /// Pour [`Metric::Cosine`], le calcul se réduit à un produit scalaire, et le résultat est identique,
+    /// au bit près, à celui de [`Metric::score`]. Les autres métriques n'utilisent pas les normes.
+    ///
+    /// # Paramètres
+    /// - `vector1`, `norm1`: Le premier vecteur et sa norme.
+    /// - `vector2`, `norm2`: Le second vecteur et sa norme.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let score = Metric::Cosine.score_with_norms(&query, simd::norm(&query), &vector, cached_norm);
+    /// ```
+    pub fn score_with_norms(self, vector1: &[f32], norm1: f32, vector2: &[f32], norm2: f32) -> f32 {
+        match self {
+            Metric::Cosine => cosine_from_dot_product(simd::dot(vector1, vector2), norm1, norm2),
+            _ => self.score(vector1, vector2),
+        }
+    }
+
+    /// Nom court de la métrique, tel qu'il est accepté par [`Metric::from_str`].
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// assert_eq!(Metric::Euclidean.name(), "euclidean");
+    /// ```
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Cosine => "cosine",
+            Metric::Dot => "dot",
+            Metric::Euclidean => "euclidean",
+            Metric::Manhattan => "manhattan",
+            Metric::Hamming => "hamming",
+        }
+    }
+
+    /// Indique si un score plus grand signifie des documents plus proches.
+    ///
+    /// # Retour
+    /// - `bool`: `true` pour les similarités (cosinus, produit scalaire), `false` pour les distances.
+    pub fn higher_is_better(self) -> bool {
+        matches!(self, Metric::Cosine | Metric::Dot)
+    }
+
+    /// Compare deux scores dans l'ordre de classement de la métrique : le meilleur score vient en premier.
+    /// Un score NaN (par exemple issu d'un débordement du calcul) est classé après tous les autres.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// results.sort_by(|a, b| Metric::Euclidean.rank(a.1, b.1));
+    /// ```
+    pub fn rank(self, score1: f32, score2: f32) -> Ordering {
+        match (score1.is_nan(), score2.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => {
+                let ordering = score1.partial_cmp(&score2).unwrap_or(Ordering::Equal);
+                if self.higher_is_better() {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            }
+        }
+    }
+
+    /// Indique si un score atteint un seuil : au moins `threshold` pour une similarité,
+    /// au plus `threshold` pour une distance. Un score NaN n'atteint jamais le seuil.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// assert!(Metric::Cosine.within(0.95, 0.92));
+    /// assert!(!Metric::Euclidean.within(0.95, 0.92));
+    /// ```
+    pub fn within(self, score: f32, threshold: f32) -> bool {
+        if self.higher_is_better() {
+            score >= threshold
+        } else {
+            score <= threshold
+        }
+    }
+
+    /// Convertit un score en distance, c'est-à-dire en une valeur d'autant plus petite
+    /// que les documents sont proches. Utilisé par les index qui minimisent une distance.
+    pub fn score_to_distance(self, score: f32) -> f32 {
+        if self.higher_is_better() {
+            -score
+        } else {
+            score
+        }
+    }
+
+    /// Opération inverse de [`Metric::score_to_distance`].
+    pub fn distance_to_score(self, distance: f32) -> f32 {
+        self.score_to_distance(distance)
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Metric::Cosine => "Similarité cosinus",
+            Metric::Dot => "Produit scalaire",
+            Metric::Euclidean => "Distance euclidienne",
+            Metric::Manhattan => "Distance de Manhattan",
+            Metric::Hamming => "Distance de Hamming",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Reconnaît le nom court d'une métrique (`cosine`, `dot`, `euclidean` ou `l2`, `manhattan` ou `l1`, `hamming`).
+impl FromStr for Metric {
+    type Err = DbError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "cosine" => Ok(Metric::Cosine),
+            "dot" => Ok(Metric::Dot),
+            "euclidean" | "l2" => Ok(Metric::Euclidean),
+            "manhattan" | "l1" => Ok(Metric::Manhattan),
+            "hamming" => Ok(Metric::Hamming),
+            _ => Err(DbError::UnknownMetric(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const METRICS: [Metric; 5] = [Metric::Cosine, Metric::Dot, Metric::Euclidean, Metric::Manhattan, Metric::Hamming];
+
+    #[test]
+    fn scores_of_known_vectors() {
+        let (vector1, vector2) = ([1.0, 0.0, 2.0, 0.0], [3.0, 0.0, 0.0, 4.0]);
+        assert_eq!(Metric::Cosine.score(&vector1, &vector2), 3.0 / (5f32.sqrt() * 5.0));
+        assert_eq!(Metric::Dot.score(&vector1, &vector2), 3.0);
+        assert_eq!(Metric::Euclidean.score(&vector1, &vector2), 24f32.sqrt());
+        assert_eq!(Metric::Manhattan.score(&vector1, &vector2), 8.0);
+        assert_eq!(Metric::Hamming.score(&vector1, &vector2), 2.0);
+
+        assert_eq!(Metric::Cosine.score(&[3.0, 4.0], &[-6.0, -8.0]), -1.0);
+        assert_eq!(Metric::Cosine.score(&[0.0, 0.0], &[1.0, 2.0]), 0.0);
+        assert_eq!(Metric::Euclidean.score(&[0.0, 0.0], &[3.0, 4.0]), 5.0);
+        for metric in [Metric::Euclidean, Metric::Manhattan, Metric::Hamming] {
+            assert_eq!(metric.score(&vector2, &vector2), 0.0);
+        }
+    }
+
+    #[test]
+    fn scores_with_norms_match_the_scores_bit_for_bit() {
+        let mut seed = 1u64;
+        // Une dimension qui n'est pas multiple de la largeur des registres SIMD.
+        let mut random = || {
+            (0..37)
+                .map(|_| {
+                    seed = seed.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+                    (seed >> 40) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+                })
+                .collect::<Vec<f32>>()
+        };
+        for _ in 0..20 {
+            let (vector1, vector2) = (random(), random());
+            for metric in METRICS {
+                let score = metric.score_with_norms(&vector1, simd::norm(&vector1), &vector2, simd::norm(&vector2));
+                assert_eq!(score.to_bits(), metric.score(&vector1, &vector2).to_bits(), "{}", metric);
+            }
+        }
+    }
+
+    #[test]
+    fn ranking_and_thresholds_follow_the_direction_of_the_metric() {
+        assert_eq!(Metric::Cosine.rank(0.9, 0.5), Ordering::Less);
+        assert_eq!(Metric::Dot.rank(-1.0, 2.0), Ordering::Greater);
+        assert_eq!(Metric::Euclidean.rank(0.9, 0.5), Ordering::Greater);
+        assert_eq!(Metric::Manhattan.rank(1.0, 1.0), Ordering::Equal);
+        for metric in METRICS {
+            assert_eq!(metric.rank(f32::NAN, f32::INFINITY), Ordering::Greater);
+            assert_eq!(metric.rank(f32::NEG_INFINITY, f32::NAN), Ordering::Less);
+            assert_eq!(metric.rank(f32::NAN, f32::NAN), Ordering::Equal);
+
+            // Le seuil est inclusif, NaN ne l'atteint jamais.
+            assert!(metric.within(0.5, 0.5));
+            assert!(!metric.within(f32::NAN, 0.5));
+            assert_eq!(metric.within(0.9, 0.5), metric.higher_is_better());
+            assert_eq!(metric.distance_to_score(metric.score_to_distance(0.25)), 0.25);
+            assert_eq!(metric.score_to_distance(0.9) < metric.score_to_distance(0.5), metric.higher_is_better());
+        }
+    }
+
+    #[test]
+    fn names_round_trip() {
+        for metric in METRICS {
+            assert_eq!(metric.name().parse::<Metric>().unwrap(), metric);
+            assert_eq!(metric.name().to_uppercase().parse::<Metric>().unwrap(), metric);
+        }
+        assert_eq!("l2".parse::<Metric>().unwrap(), Metric::Euclidean);
+        assert_eq!("L1".parse::<Metric>().unwrap(), Metric::Manhattan);
+        assert!(matches!("jaccard".parse::<Metric>(), Err(DbError::UnknownMetric(name)) if name == "jaccard"));
+        assert_eq!(Metric::default(), Metric::Cosine);
+        assert_eq!(Metric::Euclidean.to_string(), "Distance euclidienne");
+    }
+}
@@ -0,0 +1,614 @@
+//! # Module: `mmap`
+//!
+//! Stockage des vecteurs d'une [`Collection`](crate::Collection) dans un fichier projeté en mémoire
+//! (`mmap`), pour les collections plus grandes que la mémoire vive.
+//!
+//! Les vecteurs sont rangés dans des emplacements de taille fixe, alignés sur 64 octets : un vecteur
+//! commence toujours au début d'une ligne de cache. Le système décide seul des pages qui restent en
+//! mémoire : une recherche exhaustive lit le fichier à peu près séquentiellement, une recherche par
+//! index ne touche que les pages des vecteurs qu'elle compare. Seuls les identifiants et l'emplacement
+//! de chaque document restent en mémoire.
+//!
+//! Dans une base persistante, le fichier (`vectors-<uuid>.bin`, dans le répertoire de données) est
+//! le stockage durable des vecteurs : l'instantané ne contient que l'emplacement de chaque document,
+//! et le fichier est rouvert tel quel, sans être recopié. Pour qu'un crash ne puisse pas abîmer les
+//! vecteurs de l'instantané, les emplacements qu'il référence ne sont jamais réécrits : un vecteur
+//! modifié prend un nouvel emplacement, et les emplacements libérés ne sont réutilisés qu'après
+//! l'instantané suivant ([`MappedVectors::checkpoint`]). Le fichier ne rétrécit jamais, et n'a pas
+//! de somme de contrôle : seules les valeurs non finies sont détectées à l'ouverture.
+//!
+//! Sans répertoire de données, le fichier est temporaire : il est supprimé du répertoire dès son
+//! ouverture, si bien qu'il disparaît avec la collection (même après un crash), et ses emplacements
+//! sont réécrits sur place.
+
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use uuid::Uuid;
+
+use crate::error::DbError;
+use crate::DocumentId;
+
+/// Alignement des emplacements, en octets : une ligne de cache, et la largeur d'un registre AVX-512.
+const SLOT_ALIGNMENT: usize = 64;
+
+/// Nombre d'emplacements du fichier à la première insertion ; il double à chaque agrandissement.
+const INITIAL_CAPACITY: usize = 1_024;
+
+/// Début et fin du nom des fichiers de vecteurs d'un répertoire de données.
+pub const FILE_PREFIX: &str = "vectors-";
+pub const FILE_EXTENSION: &str = ".bin";
+
+/// # Structure: `MappedVectors`
+///
+/// Les vecteurs d'une collection, dans un fichier projeté en mémoire.
+pub struct MappedVectors {
+    file: File,
+    /// Le chemin d'un fichier durable, `None` pour un fichier temporaire.
+    path: Option<PathBuf>,
+    /// Dimension des vecteurs, fixée par le premier vecteur inséré.
+    dimension: usize,
+    /// Taille d'un emplacement, en `f32` : la dimension arrondie au multiple de 16 supérieur.
+    stride: usize,
+    /// Début de la projection du fichier, nul tant que le fichier est vide.
+    map: *mut f32,
+    /// Nombre d'emplacements du fichier.
+    capacity: usize,
+    /// Les documents stockés, dans un ordre quelconque.
+    keys: Vec<DocumentId>,
+    /// L'emplacement de chaque document, et sa position dans `keys`.
+    slots: HashMap<DocumentId, (usize, usize)>,
+    /// Nombre d'emplacements déjà attribués au moins une fois : les suivants n'ont jamais servi.
+    used: usize,
+    /// Les emplacements libres, en dessous de `used`.
+    free: Vec<usize>,
+    /// Les emplacements libérés depuis le dernier instantané, qui peut encore les référencer.
+    released: Vec<usize>,
+}
+
+// SAFETY: la projection n'appartient qu'à cette structure et n'est modifiée qu'à travers `&mut self` :
+// les règles d'emprunt protègent les vecteurs comme s'ils étaient dans un `Vec<f32>`.
+unsafe impl Send for MappedVectors {}
+unsafe impl Sync for MappedVectors {}
+
+impl MappedVectors {
+    /// Crée un fichier de vecteurs temporaire et vide dans `directory`, supprimé du répertoire
+    /// dès sa création.
+    ///
+    /// # Retour
+    /// - `Result<MappedVectors, DbError>`: [`DbError::Io`] si le fichier ne peut pas être créé,
+    ///   ou si le système ne permet pas de le projeter en mémoire.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let vectors = MappedVectors::temporary(&std::env::temp_dir())?;
+    /// ```
+    pub fn temporary(directory: &Path) -> Result<Self, DbError> {
+        let path = directory.join(format!("{}{}.tmp", FILE_PREFIX, Uuid::new_v4()));
+        let mut vectors = MappedVectors::create_file(&path)?;
+        fs::remove_file(&path)?;
+        vectors.path = None;
+        Ok(vectors)
+    }
+
+    /// Crée un fichier de vecteurs durable et vide dans le répertoire de données `directory`.
+    ///
+    /// # Retour
+    /// - `Result<MappedVectors, DbError>`: [`DbError::Io`] si le fichier ne peut pas être créé,
+    ///   ou si le système ne permet pas de le projeter en mémoire.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let vectors = MappedVectors::create(Path::new("./data"))?;
+    /// ```
+    pub fn create(directory: &Path) -> Result<Self, DbError> {
+        MappedVectors::create_file(&directory.join(format!("{}{}{}", FILE_PREFIX, Uuid::new_v4(), FILE_EXTENSION)))
+    }
+
+    fn create_file(path: &Path) -> Result<Self, DbError> {
+        if cfg!(not(unix)) {
+            return Err(DbError::Io("la projection de fichiers en mémoire n'est pas prise en charge sur ce système".to_string()));
+        }
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(path)?;
+        Ok(MappedVectors::new(file, Some(path.to_path_buf())))
+    }
+
+    fn new(file: File, path: Option<PathBuf>) -> Self {
+        MappedVectors {
+            file,
+            path,
+            dimension: 0,
+            stride: 0,
+            map: ptr::null_mut(),
+            capacity: 0,
+            keys: Vec::new(),
+            slots: HashMap::new(),
+            used: 0,
+            free: Vec::new(),
+            released: Vec::new(),
+        }
+    }
+
+    /// Rouvre, sans le recopier, un fichier de vecteurs durable dont l'instantané donne l'emplacement
+    /// de chaque document. Les autres emplacements sont libres.
+    ///
+    /// # Paramètres
+    /// - `path`: Le chemin du fichier.
+    /// - `dimension`: La dimension des vecteurs, `None` si aucun vecteur n'a encore été inséré.
+    /// - `slots`: Les documents et leur emplacement.
+    ///
+    /// # Retour
+    /// - `Result<MappedVectors, DbError>`: [`DbError::Io`] si le fichier ne peut pas être ouvert ou projeté,
+    ///   ou [`DbError::Corrupted`] si sa taille ou les emplacements ne correspondent pas.
+    pub fn open(path: &Path, dimension: Option<usize>, slots: Vec<(DocumentId, usize)>) -> Result<Self, DbError> {
+        let corrupted = |message: &str| DbError::Corrupted(format!("fichier de vecteurs '{}' : {}", path.display(), message));
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let length = file.metadata()?.len();
+        let mut vectors = MappedVectors::new(file, Some(path.to_path_buf()));
+        match dimension {
+            Some(dimension) if length > 0 => {
+                vectors.set_dimension(dimension)?;
+                let slot_bytes = (vectors.stride * std::mem::size_of::<f32>()) as u64;
+                if length % slot_bytes != 0 {
+                    return Err(corrupted("taille incohérente avec la dimension"));
+                }
+                let capacity = usize::try_from(length / slot_bytes).map_err(|_| corrupted("fichier trop grand"))?;
+                vectors.map = map(&vectors.file, length as usize, true)?.cast();
+                vectors.capacity = capacity;
+            }
+            // Aucun vecteur dans l'instantané : ce que contient le fichier vient d'après.
+            _ if slots.is_empty() => vectors.file.set_len(0)?,
+            _ => return Err(corrupted("fichier vide")),
+        }
+
+        let mut occupied = vec![false; vectors.capacity];
+        for (key, slot) in slots {
+            if slot >= vectors.capacity || std::mem::replace(&mut occupied[slot], true) {
+                return Err(corrupted(&format!("emplacement {} invalide", slot)));
+            }
+            if vectors.slots.insert(key, (slot, vectors.keys.len())).is_some() {
+                return Err(corrupted(&format!("document {} en double", key)));
+            }
+            vectors.keys.push(key);
+        }
+        vectors.used = occupied.iter().rposition(|&occupied| occupied).map_or(0, |slot| slot + 1);
+        vectors.free = (0..vectors.used).rev().filter(|&slot| !occupied[slot]).collect();
+        Ok(vectors)
+    }
+
+    /// Le nom du fichier, dans le répertoire de données ; `None` pour un fichier temporaire.
+    pub fn file_name(&self) -> Option<&str> {
+        self.path.as_deref()?.file_name()?.to_str()
+    }
+
+    /// Nombre de documents stockés.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    /// Les identifiants des documents.
+    pub fn keys(&self) -> std::slice::Iter<'_, DocumentId> {
+        self.keys.iter()
+    }
+
+    /// Les documents et leur emplacement, dans l'ordre de [`MappedVectors::keys`].
+    pub fn slots(&self) -> impl Iterator<Item = (&DocumentId, usize)> {
+        self.keys.iter().map(|key| (key, self.slots[key].0))
+    }
+
+    /// Indique si le document `key` est stocké.
+    pub fn contains_key(&self, key: &DocumentId) -> bool {
+        self.slots.contains_key(key)
+    }
+
+    /// Le vecteur du document `key`, lu directement dans la projection du fichier.
+    pub fn get(&self, key: &DocumentId) -> Option<&[f32]> {
+        self.slots.get(key).map(|&(slot, _)| self.slot(slot))
+    }
+
+    /// Écrit le vecteur d'un document. Tous les vecteurs doivent avoir la dimension du premier.
+    ///
+    /// Dans un fichier durable, le vecteur prend toujours un emplacement libre, et l'ancien emplacement
+    /// du document est libéré ; dans un fichier temporaire, il remplace l'ancien vecteur sur place.
+    /// Un fichier plein est d'abord agrandi : si le système refuse de l'agrandir (disque plein),
+    /// l'insertion échoue sans rien modifier.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<f32>>, DbError>`: L'ancien vecteur du document, s'il existait ;
+    ///   [`DbError::DimensionMismatch`] si le vecteur n'a pas la dimension du fichier, ou
+    ///   [`DbError::Io`] si le fichier n'a pas pu être agrandi.
+    pub fn insert(&mut self, key: DocumentId, vector: &[f32]) -> Result<Option<Vec<f32>>, DbError> {
+        self.set_dimension(vector.len())?;
+        let previous = self.slots.get(&key).copied();
+        let old_vector = previous.map(|(slot, _)| self.slot(slot).to_vec());
+        if let (Some((slot, _)), None) = (previous, &self.path) {
+            self.slot_mut(slot).copy_from_slice(vector);
+            return Ok(old_vector);
+        }
+        self.reserve(1, vector.len())?;
+        let slot = self.free.pop().unwrap_or_else(|| {
+            self.used += 1;
+            self.used - 1
+        });
+        self.slot_mut(slot).copy_from_slice(vector);
+        let position = match previous {
+            Some((old_slot, position)) => {
+                self.release(old_slot);
+                position
+            }
+            None => {
+                self.keys.push(key);
+                self.keys.len() - 1
+            }
+        };
+        self.slots.insert(key, (slot, position));
+        Ok(old_vector)
+    }
+
+    /// Agrandit le fichier pour qu'il puisse recevoir `additional` vecteurs de dimension `dimension`,
+    /// nouveaux ou remplacés : les insertions suivantes ne peuvent alors plus échouer faute de place.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::DimensionMismatch`] si la dimension n'est pas celle du fichier,
+    ///   ou [`DbError::Io`] si le système refuse d'agrandir le fichier.
+    pub fn reserve(&mut self, additional: usize, dimension: usize) -> Result<(), DbError> {
+        self.set_dimension(dimension)?;
+        while self.used + additional.saturating_sub(self.free.len()) > self.capacity {
+            self.grow()?;
+        }
+        Ok(())
+    }
+
+    /// Vérifie la dimension d'un vecteur ; tant que le fichier est vide, elle devient celle du fichier.
+    fn set_dimension(&mut self, dimension: usize) -> Result<(), DbError> {
+        if self.capacity == 0 {
+            self.dimension = dimension;
+            self.stride = dimension.next_multiple_of(SLOT_ALIGNMENT / std::mem::size_of::<f32>());
+        }
+        if dimension != self.dimension {
+            return Err(DbError::DimensionMismatch { expected: self.dimension, found: dimension });
+        }
+        Ok(())
+    }
+
+    /// Retire le vecteur d'un document et libère son emplacement.
+    ///
+    /// # Retour
+    /// - `Option<Vec<f32>>`: Le vecteur retiré, si le document existait.
+    pub fn remove(&mut self, key: &DocumentId) -> Option<Vec<f32>> {
+        let (slot, position) = self.slots.remove(key)?;
+        let old_vector = self.slot(slot).to_vec();
+        self.keys.swap_remove(position);
+        if let Some(moved) = self.keys.get(position) {
+            self.slots.get_mut(moved).expect("document sans emplacement").1 = position;
+        }
+        self.release(slot);
+        Some(old_vector)
+    }
+
+    /// Rend un emplacement libre : aussitôt dans un fichier temporaire, après le prochain instantané
+    /// dans un fichier durable.
+    fn release(&mut self, slot: usize) {
+        match self.path {
+            Some(_) => self.released.push(slot),
+            None => self.free.push(slot),
+        }
+    }
+
+    /// Force l'écriture sur disque de tout le fichier, avant l'écriture d'un instantané qui le référence.
+    ///
+    /// # Retour
+    /// - `Result<(), DbError>`: [`DbError::Io`] si le système n'a pas pu écrire le fichier.
+    pub fn sync(&self) -> Result<(), DbError> {
+        if !self.map.is_null() {
+            sync(self.map.cast(), self.capacity * self.stride * std::mem::size_of::<f32>())?;
+        }
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// Rend réutilisables les emplacements libérés, une fois écrit l'instantané qui ne les référence plus.
+    pub fn checkpoint(&mut self) {
+        self.free.append(&mut self.released);
+    }
+
+    fn slot(&self, slot: usize) -> &[f32] {
+        debug_assert!(slot < self.capacity);
+        // SAFETY: l'emplacement est dans la projection, qui vit tant que `self` n'est pas modifié.
+        unsafe { std::slice::from_raw_parts(self.map.add(slot * self.stride), self.dimension) }
+    }
+
+    fn slot_mut(&mut self, slot: usize) -> &mut [f32] {
+        debug_assert!(slot < self.capacity);
+        // SAFETY: comme pour `slot`, avec un emprunt exclusif de `self`.
+        unsafe { std::slice::from_raw_parts_mut(self.map.add(slot * self.stride), self.dimension) }
+    }
+
+    /// Double la taille du fichier et le projette de nouveau. Les deux projections partagent les pages
+    /// du fichier : ce qui a été écrit dans l'ancienne se retrouve dans la nouvelle.
+    fn grow(&mut self) -> io::Result<()> {
+        let capacity = (self.capacity * 2).max(INITIAL_CAPACITY);
+        let bytes = capacity * self.stride * std::mem::size_of::<f32>();
+        self.file.set_len(bytes as u64)?;
+        let map = map(&self.file, bytes, true)?;
+        self.unmap();
+        self.map = map.cast();
+        self.capacity = capacity;
+        Ok(())
+    }
+
+    fn unmap(&mut self) {
+        if !self.map.is_null() {
+            unmap(self.map.cast(), self.capacity * self.stride * std::mem::size_of::<f32>());
+            self.map = ptr::null_mut();
+        }
+    }
+}
+
+impl Drop for MappedVectors {
+    fn drop(&mut self) {
+        self.unmap();
+    }
+}
+
+/// # Structure: `MappedFile`
+///
+/// Un fichier projeté en lecture seule, lu comme une tranche d'octets sans être copié en mémoire.
+pub struct MappedFile {
+    map: *mut u8,
+    len: usize,
+}
+
+impl MappedFile {
+    /// Projette tout le contenu d'un fichier ouvert en lecture.
+    pub fn open(file: &File) -> io::Result<Self> {
+        let len = file.metadata()?.len() as usize;
+        let map = if len == 0 { ptr::null_mut() } else { map(file, len, false)? };
+        Ok(MappedFile { map, len })
+    }
+}
+
+impl Deref for MappedFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        if self.map.is_null() {
+            return &[];
+        }
+        // SAFETY: `map` est une projection de `len` octets, valide jusqu'à `drop`.
+        unsafe { std::slice::from_raw_parts(self.map, self.len) }
+    }
+}
+
+impl Drop for MappedFile {
+    fn drop(&mut self) {
+        if !self.map.is_null() {
+            unmap(self.map, self.len);
+        }
+    }
+}
+
+/// Projette les `len` premiers octets d'un fichier, en lecture seule ou en lecture et écriture partagées.
+#[cfg(unix)]
+fn map(file: &File, len: usize, writable: bool) -> io::Result<*mut u8> {
+    use std::os::unix::io::AsRawFd;
+
+    let (protection, flags) = match writable {
+        true => (libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED),
+        false => (libc::PROT_READ, libc::MAP_PRIVATE),
+    };
+    // SAFETY: projection d'un descripteur ouvert ; le système vérifie le mode d'ouverture et la longueur.
+    let map = unsafe { libc::mmap(ptr::null_mut(), len, protection, flags, file.as_raw_fd(), 0) };
+    if map == libc::MAP_FAILED {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(map.cast())
+}
+
+#[cfg(not(unix))]
+fn map(_file: &File, _len: usize, _writable: bool) -> io::Result<*mut u8> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "projection de fichier non prise en charge"))
+}
+
+/// Écrit sur disque les pages modifiées d'une projection partagée créée par [`map`].
+#[cfg(unix)]
+fn sync(map: *mut u8, len: usize) -> io::Result<()> {
+    // SAFETY: `map` et `len` décrivent une projection créée par `map`, toujours en place.
+    if unsafe { libc::msync(map.cast(), len, libc::MS_SYNC) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn sync(_map: *mut u8, _len: usize) -> io::Result<()> {
+    Ok(())
+}
+
+/// Supprime une projection créée par [`map`].
+#[cfg(unix)]
+fn unmap(map: *mut u8, len: usize) {
+    // SAFETY: `map` et `len` décrivent une projection créée par `map`, qui n'est plus utilisée.
+    unsafe { libc::munmap(map.cast(), len) };
+}
+
+#[cfg(not(unix))]
+fn unmap(_map: *mut u8, _len: usize) {}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    fn vector(n: usize) -> Vec<f32> {
+        (0..5).map(|i| (n * 10 + i) as f32).collect()
+    }
+
+    #[test]
+    fn vectors_survive_growing_the_file() {
+        let mut vectors = MappedVectors::temporary(&std::env::temp_dir()).unwrap();
+        let count = INITIAL_CAPACITY * 2 + 1;
+        for n in 0..count {
+            assert_eq!(vectors.insert(id(n as u128), &vector(n)).unwrap(), None);
+        }
+        assert_eq!(vectors.len(), count);
+        assert_eq!(vectors.capacity, INITIAL_CAPACITY * 4);
+        for n in 0..count {
+            assert_eq!(vectors.get(&id(n as u128)), Some(vector(n).as_slice()));
+        }
+        // Chaque emplacement commence sur une ligne de cache.
+        assert_eq!(vectors.stride, 16);
+        assert_eq!(vectors.get(&id(1)).unwrap().as_ptr() as usize % SLOT_ALIGNMENT, 0);
+    }
+
+    #[test]
+    fn update_overwrites_the_slot() {
+        let mut vectors = MappedVectors::temporary(&std::env::temp_dir()).unwrap();
+        vectors.insert(id(1), &vector(1)).unwrap();
+        assert_eq!(vectors.insert(id(1), &vector(2)).unwrap(), Some(vector(1)));
+        assert_eq!(vectors.len(), 1);
+        assert_eq!(vectors.get(&id(1)), Some(vector(2).as_slice()));
+    }
+
+    #[test]
+    fn removed_slot_is_reused() {
+        let mut vectors = MappedVectors::temporary(&std::env::temp_dir()).unwrap();
+        for n in 0..4 {
+            vectors.insert(id(n), &vector(n as usize)).unwrap();
+        }
+        assert_eq!(vectors.remove(&id(1)), Some(vector(1)));
+        assert_eq!(vectors.remove(&id(1)), None);
+        assert_eq!(vectors.keys().copied().collect::<Vec<_>>(), vec![id(0), id(3), id(2)]);
+        assert_eq!(vectors.get(&id(3)), Some(vector(3).as_slice()));
+        assert!(!vectors.contains_key(&id(1)));
+
+        vectors.insert(id(9), &vector(9)).unwrap();
+        assert_eq!(vectors.slots[&id(9)].0, 1);
+        assert_eq!(vectors.len(), 4);
+        for n in [0, 2, 3, 9] {
+            assert_eq!(vectors.get(&id(n)), Some(vector(n as usize).as_slice()));
+        }
+    }
+
+    #[test]
+    fn wrong_dimension_is_rejected_without_change() {
+        let mut vectors = MappedVectors::temporary(&std::env::temp_dir()).unwrap();
+        vectors.insert(id(1), &vector(1)).unwrap();
+        assert_eq!(
+            vectors.insert(id(2), &[1.0, 2.0]),
+            Err(DbError::DimensionMismatch { expected: 5, found: 2 })
+        );
+        assert_eq!(vectors.reserve(10, 3), Err(DbError::DimensionMismatch { expected: 5, found: 3 }));
+        assert_eq!(vectors.len(), 1);
+        assert!(!vectors.contains_key(&id(2)));
+    }
+
+    #[test]
+    fn reserve_grows_the_file_ahead_of_inserts() {
+        let mut vectors = MappedVectors::temporary(&std::env::temp_dir()).unwrap();
+        vectors.reserve(INITIAL_CAPACITY + 1, 5).unwrap();
+        assert_eq!(vectors.capacity, INITIAL_CAPACITY * 2);
+        vectors.reserve(10, 5).unwrap();
+        assert_eq!(vectors.capacity, INITIAL_CAPACITY * 2);
+    }
+
+    /// Un répertoire temporaire, supprimé à la fin du test.
+    struct Directory(PathBuf);
+
+    impl Directory {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("projet-mmap-{}", Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            Directory(path)
+        }
+    }
+
+    impl Drop for Directory {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn reopen(directory: &Directory, vectors: &MappedVectors) -> MappedVectors {
+        vectors.sync().unwrap();
+        let slots = vectors.slots().map(|(key, slot)| (*key, slot)).collect();
+        MappedVectors::open(&directory.0.join(vectors.file_name().unwrap()), Some(5), slots).unwrap()
+    }
+
+    #[test]
+    fn durable_file_is_reopened_in_place() {
+        let directory = Directory::new();
+        let mut vectors = MappedVectors::create(&directory.0).unwrap();
+        assert!(vectors.file_name().unwrap().starts_with(FILE_PREFIX));
+        for n in 0..4 {
+            vectors.insert(id(n), &vector(n as usize)).unwrap();
+        }
+        vectors.remove(&id(1));
+        vectors.insert(id(2), &vector(7)).unwrap();
+
+        let reopened = reopen(&directory, &vectors);
+        assert_eq!(reopened.len(), 3);
+        assert_eq!(reopened.get(&id(0)), Some(vector(0).as_slice()));
+        assert_eq!(reopened.get(&id(2)), Some(vector(7).as_slice()));
+        assert_eq!(reopened.get(&id(3)), Some(vector(3).as_slice()));
+        assert!(!reopened.contains_key(&id(1)));
+        // Les emplacements 1 (document retiré) et 2 (ancien vecteur) sont libres.
+        assert_eq!((reopened.used, reopened.free.clone()), (5, vec![2, 1]));
+        assert_eq!(reopened.map.cast_const(), reopened.slot(0).as_ptr());
+    }
+
+    #[test]
+    fn slots_of_the_snapshot_are_not_overwritten_before_the_next_one() {
+        let directory = Directory::new();
+        let mut vectors = MappedVectors::create(&directory.0).unwrap();
+        vectors.insert(id(1), &vector(1)).unwrap();
+        vectors.insert(id(2), &vector(2)).unwrap();
+        let snapshot: Vec<(DocumentId, usize)> = vectors.slots().map(|(key, slot)| (*key, slot)).collect();
+
+        // Après l'instantané : une mise à jour, une suppression puis un ajout.
+        assert_eq!(vectors.insert(id(1), &vector(8)).unwrap(), Some(vector(1)));
+        vectors.remove(&id(2));
+        vectors.insert(id(3), &vector(3)).unwrap();
+        assert_eq!(vectors.slots[&id(3)].0, 3);
+        vectors.sync().unwrap();
+
+        // Un crash : l'instantané et ses emplacements sont intacts.
+        let path = directory.0.join(vectors.file_name().unwrap());
+        let reopened = MappedVectors::open(&path, Some(5), snapshot).unwrap();
+        assert_eq!(reopened.get(&id(1)), Some(vector(1).as_slice()));
+        assert_eq!(reopened.get(&id(2)), Some(vector(2).as_slice()));
+
+        // Une fois l'instantané suivant écrit, les emplacements libérés servent de nouveau.
+        vectors.checkpoint();
+        vectors.insert(id(4), &vector(4)).unwrap();
+        assert!(vectors.slots[&id(4)].0 < 2);
+    }
+
+    #[test]
+    fn inconsistent_files_are_corrupted() {
+        let directory = Directory::new();
+        let mut vectors = MappedVectors::create(&directory.0).unwrap();
+        vectors.insert(id(1), &vector(1)).unwrap();
+        vectors.sync().unwrap();
+        let path = directory.0.join(vectors.file_name().unwrap());
+        for (dimension, slots) in [
+            (Some(5), vec![(id(1), INITIAL_CAPACITY)]),
+            (Some(5), vec![(id(1), 0), (id(2), 0)]),
+            (Some(5), vec![(id(1), 0), (id(1), 1)]),
+            (Some(40), vec![(id(1), 0)]),
+            (None, vec![(id(1), 0)]),
+        ] {
+            assert!(matches!(MappedVectors::open(&path, dimension, slots.clone()), Err(DbError::Corrupted(_))), "{:?}", slots);
+        }
+        assert!(matches!(MappedVectors::open(&directory.0.join("absent.bin"), None, Vec::new()), Err(DbError::Io(_))));
+    }
+}
@@ -0,0 +1,445 @@
+//! # Module: `npy`
+//!
+//! Lecture et écriture des tableaux NumPy au format `.npy` : une chaîne magique, un en-tête qui décrit
+//! le tableau sous forme de dictionnaire Python (`{'descr': '<f4', 'fortran_order': False, 'shape': (1000, 768), }`),
+//! puis les valeurs, bout à bout.
+//!
+//! Une matrice de vecteurs doit être de dimension 2, rangée ligne par ligne (`fortran_order` faux), en
+//! `float32` ou `float16` de l'un ou l'autre boutisme ; elle est lue une ligne à la fois. Les identifiants
+//! des documents sont un tableau de dimension 1 : des chaînes (`str` ou `bytes`) qui contiennent un UUID,
+//! ou des entiers, l'entier `n` devenant l'identifiant [`dataset_id(n)`](crate::texmex::dataset_id).
+//! Une matrice exportée est accompagnée d'un fichier texte qui donne l'identifiant de chaque ligne,
+//! un par ligne (`numpy.loadtxt(chemin, dtype=str)`).
+
+use std::io::{BufRead, Read, Write};
+
+use uuid::Uuid;
+
+use crate::error::DbError;
+use crate::texmex::{dataset_id, MAX_DIMENSION};
+use crate::DocumentId;
+
+/// Chaîne magique au début de tout fichier `.npy`.
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+/// Alignement du début des données, en octets, comme les écrit NumPy.
+const HEADER_ALIGNMENT: usize = 64;
+
+/// Taille maximale acceptée pour l'en-tête, pour ne pas allouer des gigaoctets sur la foi d'un fichier corrompu.
+const MAX_HEADER_LENGTH: usize = 1 << 20;
+
+/// Taille maximale acceptée pour un élément (une chaîne `U` ou `S`), en octets.
+const MAX_ELEMENT_SIZE: usize = 1 << 16;
+
+/// Noms des tableaux d'une archive `.npz` lus par défaut.
+pub const NPZ_IDS: &str = "ids";
+pub const NPZ_VECTORS: &str = "vectors";
+
+/// # Énumération: `Kind`
+///
+/// Le type des éléments d'un tableau, d'après le caractère de type de son `descr` (`f`, `i`, `u`, `U`, `S`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Float,
+    Signed,
+    Unsigned,
+    /// Chaîne Unicode, sur 4 octets par caractère.
+    Unicode,
+    /// Chaîne d'octets.
+    Bytes,
+    Other,
+}
+
+/// # Structure: `NpyHeader`
+///
+/// L'en-tête d'un fichier `.npy`.
+#[derive(Debug, Clone)]
+pub struct NpyHeader {
+    /// Le type des éléments, tel qu'écrit par NumPy (`<f4`, `>f2`, `<U36`, etc.).
+    pub descr: String,
+    pub fortran_order: bool,
+    pub shape: Vec<usize>,
+    kind: Kind,
+    /// Taille d'un élément, en octets.
+    size: usize,
+    big_endian: bool,
+}
+
+impl NpyHeader {
+    /// Lit l'en-tête au début d'un fichier `.npy` ; le lecteur est ensuite placé au début des valeurs.
+    ///
+    /// # Retour
+    /// - `Result<NpyHeader, DbError>`: L'en-tête, ou [`DbError::Corrupted`] si le fichier n'est pas un `.npy` valide.
+    pub fn read(reader: &mut impl Read) -> Result<Self, DbError> {
+        let mut preamble = [0u8; 8];
+        reader.read_exact(&mut preamble).map_err(|_| corrupted("fichier trop court"))?;
+        if &preamble[..6] != MAGIC {
+            return Err(corrupted("ce n'est pas un fichier .npy"));
+        }
+        let length = match preamble[6] {
+            1 => {
+                let mut length = [0u8; 2];
+                reader.read_exact(&mut length)?;
+                u16::from_le_bytes(length) as usize
+            }
+            2 | 3 => {
+                let mut length = [0u8; 4];
+                reader.read_exact(&mut length)?;
+                u32::from_le_bytes(length) as usize
+            }
+            version => return Err(DbError::Unsupported(format!("version {} du format .npy", version))),
+        };
+        if length > MAX_HEADER_LENGTH {
+            return Err(corrupted("en-tête trop long"));
+        }
+        let mut text = vec![0u8; length];
+        reader.read_exact(&mut text).map_err(|_| corrupted("en-tête tronqué"))?;
+        let text = String::from_utf8(text).map_err(|_| corrupted("en-tête illisible"))?;
+        NpyHeader::parse(&text)
+    }
+
+    /// Analyse le dictionnaire Python de l'en-tête.
+    fn parse(text: &str) -> Result<Self, DbError> {
+        let descr = match dictionary_value(text, "descr")? {
+            value if value.starts_with(['\'', '"']) => value[1..]
+                .split(&value[..1])
+                .next()
+                .ok_or_else(|| corrupted("'descr' invalide"))?
+                .to_string(),
+            _ => return Err(DbError::Unsupported("tableau structuré : un type simple ('<f4', '<U36', etc.) est attendu".to_string())),
+        };
+        let fortran_order = match dictionary_value(text, "fortran_order")? {
+            value if value.starts_with("True") => true,
+            value if value.starts_with("False") => false,
+            _ => return Err(corrupted("'fortran_order' invalide")),
+        };
+        let shape = dictionary_value(text, "shape")?;
+        let shape = shape
+            .strip_prefix('(')
+            .and_then(|shape| shape.split(')').next())
+            .ok_or_else(|| corrupted("'shape' invalide"))?
+            .split(',')
+            .map(str::trim)
+            .filter(|dimension| !dimension.is_empty())
+            .map(|dimension| dimension.parse().map_err(|_| corrupted("'shape' invalide")))
+            .collect::<Result<_, _>>()?;
+
+        let (order, rest) = descr.split_at(descr.chars().next().map_or(0, char::len_utf8));
+        let (order, rest) = if matches!(order, "<" | ">" | "|" | "=") { (order, rest) } else { ("=", descr.as_str()) };
+        let kind = match rest.chars().next() {
+            Some('f') => Kind::Float,
+            Some('i') => Kind::Signed,
+            Some('u') => Kind::Unsigned,
+            Some('U') => Kind::Unicode,
+            Some('S') => Kind::Bytes,
+            _ => Kind::Other,
+        };
+        let count: usize = rest.get(1..).and_then(|count| count.parse().ok()).unwrap_or(0);
+        let size = if kind == Kind::Unicode { count.saturating_mul(4) } else { count };
+        if size > MAX_ELEMENT_SIZE {
+            return Err(corrupted(&format!("'descr' invalide : éléments de {} octets", size)));
+        }
+        Ok(NpyHeader {
+            fortran_order,
+            shape,
+            kind,
+            size,
+            big_endian: order == ">" || (order == "=" && cfg!(target_endian = "big")),
+            descr,
+        })
+    }
+
+    /// Écrit l'en-tête d'un tableau rangé ligne par ligne, complété par des espaces pour que les valeurs
+    /// commencent à une position multiple de 64.
+    pub fn write(writer: &mut impl Write, descr: &str, shape: &[usize]) -> Result<(), DbError> {
+        let shape = match shape {
+            [length] => format!("({},)", length),
+            _ => format!("({})", shape.iter().map(usize::to_string).collect::<Vec<_>>().join(", ")),
+        };
+        let mut text = format!("{{'descr': '{}', 'fortran_order': False, 'shape': {}, }}", descr, shape);
+        let (version, prefix) = if text.len() + HEADER_ALIGNMENT <= u16::MAX as usize { (1, 10) } else { (2, 12) };
+        let padding = (HEADER_ALIGNMENT - (prefix + text.len() + 1) % HEADER_ALIGNMENT) % HEADER_ALIGNMENT;
+        text.extend(std::iter::repeat_n(' ', padding));
+        text.push('\n');
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[version, 0])?;
+        if version == 1 {
+            writer.write_all(&(text.len() as u16).to_le_bytes())?;
+        } else {
+            writer.write_all(&(text.len() as u32).to_le_bytes())?;
+        }
+        writer.write_all(text.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Le texte de la valeur associée à `key` dans le dictionnaire de l'en-tête, jusqu'à la fin du dictionnaire.
+fn dictionary_value<'a>(text: &'a str, key: &str) -> Result<&'a str, DbError> {
+    ["'", "\""]
+        .iter()
+        .find_map(|quote| text.find(&format!("{}{}{}", quote, key, quote)).map(|position| position + key.len() + 2))
+        .and_then(|position| text[position..].trim_start().strip_prefix(':'))
+        .map(str::trim_start)
+        .ok_or_else(|| corrupted(&format!("clé '{}' absente de l'en-tête", key)))
+}
+
+/// # Structure: `MatrixReader`
+///
+/// Lit une matrice de vecteurs `.npy` ligne par ligne.
+pub struct MatrixReader<R> {
+    reader: R,
+    header: NpyHeader,
+    /// Nombre de lignes déjà lues.
+    row: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> MatrixReader<R> {
+    /// Lit l'en-tête et vérifie que le fichier contient une matrice lisible.
+    ///
+    /// # Retour
+    /// - `Result<MatrixReader<R>, DbError>`: [`DbError::Unsupported`] si la matrice n'est pas de dimension 2,
+    ///   est rangée colonne par colonne ou n'est ni en `float32` ni en `float16`, [`DbError::Corrupted`]
+    ///   si le fichier n'est pas un `.npy` valide ou si ses vecteurs dépassent la dimension maximale.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut matrix = MatrixReader::new(BufReader::new(File::open("embeddings.npy")?))?;
+    /// while let Some(vector) = matrix.next_row()? {
+    ///     // Utiliser le vecteur
+    /// }
+    /// ```
+    pub fn new(mut reader: R) -> Result<Self, DbError> {
+        let header = NpyHeader::read(&mut reader)?;
+        if header.shape.len() != 2 {
+            return Err(DbError::Unsupported(format!(
+                "tableau de forme {:?} : une matrice de dimension 2 (nombre de vecteurs, dimension) est attendue",
+                header.shape
+            )));
+        }
+        if header.fortran_order {
+            return Err(DbError::Unsupported(
+                "matrice rangée colonne par colonne (fortran_order) : l'enregistrer avec numpy.ascontiguousarray".to_string(),
+            ));
+        }
+        if header.kind != Kind::Float || !matches!(header.size, 2 | 4) {
+            return Err(DbError::Unsupported(format!(
+                "type '{}' : float32 ou float16 attendu (convertir avec astype(numpy.float32))",
+                header.descr
+            )));
+        }
+        if header.shape[1] > MAX_DIMENSION {
+            return Err(corrupted(&format!("dimension {} invalide (au plus {})", header.shape[1], MAX_DIMENSION)));
+        }
+        Ok(MatrixReader {
+            reader,
+            header,
+            row: 0,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Nombre de lignes (de vecteurs) de la matrice.
+    pub fn rows(&self) -> usize {
+        self.header.shape[0]
+    }
+
+    /// Nombre de colonnes (dimension des vecteurs) de la matrice.
+    pub fn columns(&self) -> usize {
+        self.header.shape[1]
+    }
+
+    /// Lit la ligne suivante, convertie en `f32`.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<f32>>, DbError>`: Le vecteur, `None` après la dernière ligne, ou [`DbError::Corrupted`]
+    ///   si le fichier s'arrête avant.
+    pub fn next_row(&mut self) -> Result<Option<Vec<f32>>, DbError> {
+        if self.row == self.rows() {
+            return Ok(None);
+        }
+        self.buffer.resize(self.columns() * self.header.size, 0);
+        self.reader
+            .read_exact(&mut self.buffer)
+            .map_err(|_| corrupted(&format!("fichier tronqué à la ligne {} sur {}", self.row + 1, self.rows())))?;
+        self.row += 1;
+        let big_endian = self.header.big_endian;
+        let vector = match self.header.size {
+            4 => self
+                .buffer
+                .chunks_exact(4)
+                .map(|value| {
+                    let bytes = value.try_into().unwrap();
+                    if big_endian { f32::from_be_bytes(bytes) } else { f32::from_le_bytes(bytes) }
+                })
+                .collect(),
+            _ => self
+                .buffer
+                .chunks_exact(2)
+                .map(|value| {
+                    let bytes = value.try_into().unwrap();
+                    f16_to_f32(if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+                })
+                .collect(),
+        };
+        Ok(Some(vector))
+    }
+}
+
+/// Convertit un nombre `float16` (IEEE 754 demi-précision) en `f32`, sans perte.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = (bits >> 10) & 0x1f;
+    let mantissa = (bits & 0x3ff) as u32;
+    match exponent {
+        // Nombre dénormalisé : mantisse × 2^-24.
+        0 => sign * mantissa as f32 / (1 << 24) as f32,
+        0x1f => f32::from_bits(((bits as u32 & 0x8000) << 16) | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(((bits as u32 & 0x8000) << 16) | ((exponent as u32 + 112) << 23) | (mantissa << 13)),
+    }
+}
+
+/// Lit un tableau `.npy` d'identifiants de documents.
+///
+/// # Retour
+/// - `Result<Vec<DocumentId>, DbError>`: Les identifiants, [`DbError::Unsupported`] si le tableau n'est pas
+///   de dimension 1 ou si ses éléments ne sont ni des chaînes ni des entiers, ou [`DbError::Corrupted`]
+///   si un identifiant est invalide.
+pub fn read_ids(mut reader: impl Read) -> Result<Vec<DocumentId>, DbError> {
+    let header = NpyHeader::read(&mut reader)?;
+    let length = match header.shape[..] {
+        [length] => length,
+        _ => {
+            return Err(DbError::Unsupported(format!(
+                "identifiants de forme {:?} : un tableau de dimension 1 est attendu",
+                header.shape
+            )))
+        }
+    };
+    let supported = match header.kind {
+        Kind::Unicode | Kind::Bytes => header.size > 0,
+        Kind::Signed | Kind::Unsigned => matches!(header.size, 1 | 2 | 4 | 8),
+        Kind::Float | Kind::Other => false,
+    };
+    if !supported {
+        return Err(DbError::Unsupported(format!(
+            "identifiants de type '{}' : des chaînes (dtype 'U36' ou 'S36') ou des entiers sont attendus",
+            header.descr
+        )));
+    }
+
+    let mut element = vec![0u8; header.size];
+    let mut ids = Vec::new();
+    for index in 0..length {
+        reader
+            .read_exact(&mut element)
+            .map_err(|_| corrupted(&format!("fichier tronqué à l'identifiant {} sur {}", index + 1, length)))?;
+        let id = match header.kind {
+            Kind::Unicode => {
+                let text: String = element
+                    .chunks_exact(4)
+                    .map(|c| {
+                        let bytes = c.try_into().unwrap();
+                        if header.big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+                    })
+                    .take_while(|&c| c != 0)
+                    .map(|c| char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect();
+                parse_id(&text, index)?
+            }
+            Kind::Bytes => {
+                let end = element.iter().position(|&byte| byte == 0).unwrap_or(element.len());
+                parse_id(&String::from_utf8_lossy(&element[..end]), index)?
+            }
+            _ => {
+                let mut bytes = [0u8; 8];
+                if header.big_endian {
+                    bytes[8 - header.size..].copy_from_slice(&element);
+                    bytes.reverse();
+                } else {
+                    bytes[..header.size].copy_from_slice(&element);
+                }
+                let negative = header.kind == Kind::Signed && element[if header.big_endian { 0 } else { header.size - 1 }] & 0x80 != 0;
+                if negative {
+                    return Err(corrupted(&format!("identifiant {} négatif", index + 1)));
+                }
+                dataset_id(u64::from_le_bytes(bytes) as usize)
+            }
+        };
+        ids.push(id);
+    }
+    Ok(ids)
+}
+
+/// Lit un fichier d'identifiants tel qu'écrit à côté d'une matrice exportée : un UUID par ligne.
+///
+/// # Retour
+/// - `Result<Vec<DocumentId>, DbError>`: Les identifiants, ou [`DbError::InvalidLine`] avec le numéro
+///   de la première ligne invalide.
+pub fn read_id_lines(reader: impl BufRead) -> Result<Vec<DocumentId>, DbError> {
+    let mut ids = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        ids.push(Uuid::parse_str(line.trim()).map_err(|_| DbError::InvalidLine {
+            line: number + 1,
+            message: format!("identifiant de document invalide '{}'", line.trim()),
+        })?);
+    }
+    Ok(ids)
+}
+
+fn parse_id(text: &str, index: usize) -> Result<DocumentId, DbError> {
+    Uuid::parse_str(text.trim()).map_err(|_| corrupted(&format!("identifiant {} invalide '{}'", index + 1, text)))
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!(".npy : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_header_and_half_precision_rows() {
+        let mut file = Vec::new();
+        NpyHeader::write(&mut file, ">f2", &[2, 3]).unwrap();
+        assert_eq!(file.len() % HEADER_ALIGNMENT, 0);
+        for value in [0x3c00u16, 0xc000, 0x3555, 0x0001, 0x7bff, 0x0000] {
+            file.extend(value.to_be_bytes());
+        }
+        let mut matrix = MatrixReader::new(file.as_slice()).unwrap();
+        assert_eq!((matrix.rows(), matrix.columns()), (2, 3));
+        assert_eq!(matrix.next_row().unwrap(), Some(vec![1.0, -2.0, 0.333_251_95]));
+        assert_eq!(matrix.next_row().unwrap(), Some(vec![2f32.powi(-24), 65_504.0, 0.0]));
+        assert_eq!(matrix.next_row().unwrap(), None);
+
+        let mut file = Vec::new();
+        NpyHeader::write(&mut file, "<f8", &[2, 3]).unwrap();
+        assert!(matches!(MatrixReader::new(file.as_slice()), Err(DbError::Unsupported(_))));
+    }
+
+    #[test]
+    fn oversized_shapes_and_elements_are_corrupted() {
+        // Une dimension dont la taille de ligne déborde, puis une dimension simplement trop grande.
+        for shape in [[2, usize::MAX / 2 + 1], [2, MAX_DIMENSION + 1]] {
+            let mut file = Vec::new();
+            NpyHeader::write(&mut file, "<f4", &shape).unwrap();
+            assert!(matches!(MatrixReader::new(file.as_slice()), Err(DbError::Corrupted(_))), "{:?}", shape);
+        }
+        let mut file = Vec::new();
+        NpyHeader::write(&mut file, "<f4", &[1, MAX_DIMENSION]).unwrap();
+        assert_eq!(MatrixReader::new(file.as_slice()).unwrap().columns(), MAX_DIMENSION);
+
+        for descr in ["<U4611686018427387904", "|S100000"] {
+            let mut file = Vec::new();
+            NpyHeader::write(&mut file, descr, &[1]).unwrap();
+            assert!(matches!(read_ids(file.as_slice()), Err(DbError::Corrupted(_))), "{}", descr);
+        }
+    }
+}
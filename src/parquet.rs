@@ -0,0 +1,1136 @@
+//! # Module: `parquet`
+//!
+//! Lecture et écriture des fichiers Apache Parquet.
+//!
+//! Un fichier Parquet est découpé en groupes de lignes (*row groups*), eux-mêmes découpés en une
+//! colonne par feuille du schéma, faite de pages : une page de dictionnaire facultative, puis des pages
+//! de données. Chaque valeur d'une page est précédée de ses niveaux de répétition et de définition,
+//! qui disent où commencent les listes et quelles valeurs sont nulles. Les métadonnées (le pied de page
+//! et l'en-tête de chaque page) sont écrites avec le protocole compact de Thrift (module [`thrift`](crate::thrift)).
+//!
+//! Sont pris en charge à la lecture : les pages de données des versions 1 et 2, les encodages `PLAIN`,
+//! par dictionnaire, `RLE` (booléens) et `BYTE_STREAM_SPLIT`, les compressions Snappy et gzip, et les
+//! colonnes simples ou listes de valeurs simples. Les fichiers sont écrits sans compression, une page
+//! par colonne et par groupe de lignes.
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::columnar::{Array, DataType, Field, RecordBatch, Values};
+use crate::error::DbError;
+use crate::inflate::Inflate;
+use crate::npy::f16_to_f32;
+use crate::snappy;
+use crate::thrift::Thrift;
+
+/// Chaîne magique au début et à la fin d'un fichier ; `PARE` termine un fichier au pied de page chiffré.
+const MAGIC: &[u8] = b"PAR1";
+const ENCRYPTED_MAGIC: &[u8] = b"PARE";
+
+/// Taille maximale acceptée pour le pied de page et pour une page, pour ne pas allouer des gigaoctets
+/// sur la foi d'un fichier corrompu.
+const MAX_SIZE: usize = 1 << 30;
+/// Nombre maximal de valeurs (niveaux compris) d'une colonne d'un groupe de lignes ou d'un dictionnaire,
+/// pour la même raison : les niveaux encodés en RLE en annoncent beaucoup en très peu d'octets.
+const MAX_VALUES: usize = 1 << 30;
+
+/// Types physiques des valeurs.
+const BOOLEAN: i64 = 0;
+const INT32: i64 = 1;
+const INT64: i64 = 2;
+const FLOAT: i64 = 4;
+const DOUBLE: i64 = 5;
+const BYTE_ARRAY: i64 = 6;
+const FIXED_LEN_BYTE_ARRAY: i64 = 7;
+
+/// Répétition d'un nœud du schéma.
+const REQUIRED: i64 = 0;
+const OPTIONAL: i64 = 1;
+const REPEATED: i64 = 2;
+
+/// Types convertis (anciens types logiques) et champs de l'union `LogicalType` utilisés.
+const CONVERTED_UTF8: i64 = 0;
+const CONVERTED_LIST: i64 = 3;
+const CONVERTED_ENUM: i64 = 4;
+const CONVERTED_JSON: i64 = 19;
+const LOGICAL_STRING: i16 = 1;
+const LOGICAL_LIST: i16 = 3;
+const LOGICAL_ENUM: i16 = 4;
+const LOGICAL_JSON: i16 = 12;
+const LOGICAL_FLOAT16: i16 = 15;
+
+/// Encodages.
+const PLAIN: i64 = 0;
+const PLAIN_DICTIONARY: i64 = 2;
+const RLE: i64 = 3;
+const RLE_DICTIONARY: i64 = 8;
+const BYTE_STREAM_SPLIT: i64 = 9;
+const ENCODING_NAMES: [&str; 10] = [
+    "PLAIN", "GROUP_VAR_INT", "PLAIN_DICTIONARY", "RLE", "BIT_PACKED", "DELTA_BINARY_PACKED", "DELTA_LENGTH_BYTE_ARRAY",
+    "DELTA_BYTE_ARRAY", "RLE_DICTIONARY", "BYTE_STREAM_SPLIT",
+];
+
+/// Compressions.
+const UNCOMPRESSED: i64 = 0;
+const SNAPPY: i64 = 1;
+const GZIP: i64 = 2;
+const CODEC_NAMES: [&str; 8] = ["UNCOMPRESSED", "SNAPPY", "GZIP", "LZO", "BROTLI", "LZ4", "ZSTD", "LZ4_RAW"];
+
+/// Types de page.
+const DATA_PAGE: i64 = 0;
+const DICTIONARY_PAGE: i64 = 2;
+const DATA_PAGE_V2: i64 = 3;
+
+/// # Structure: `Node`
+///
+/// Un nœud du schéma : une feuille (une colonne de valeurs) ou un groupe.
+#[derive(Debug)]
+struct Node {
+    name: String,
+    repetition: i64,
+    /// Le type physique, pour une feuille.
+    physical: Option<i64>,
+    type_length: usize,
+    /// Pour un tableau d'octets, s'il contient du texte, et si ce texte est du JSON.
+    text: bool,
+    json: bool,
+    /// Pour un tableau d'octets de taille fixe, s'il contient des `float16`.
+    float16: bool,
+    children: Vec<Node>,
+}
+
+impl Node {
+    /// Lit le nœud à la position `position` de la liste à plat des éléments du schéma, et ses descendants.
+    fn read(elements: &[Thrift], position: &mut usize, depth: usize) -> Result<Node, DbError> {
+        let element = elements.get(*position).ok_or_else(|| corrupted("schéma tronqué"))?;
+        *position += 1;
+        if depth > 32 {
+            return Err(corrupted("schéma trop imbriqué"));
+        }
+        let converted = element.int(6);
+        let logical = |id| element.field(10).and_then(|logical| logical.field(id)).is_some();
+        let children = (0..element.int(5).unwrap_or(0))
+            .map(|_| Node::read(elements, position, depth + 1))
+            .collect::<Result<_, _>>()?;
+        Ok(Node {
+            name: String::from_utf8_lossy(element.binary(4).unwrap_or_default()).into_owned(),
+            repetition: element.int(3).unwrap_or(REQUIRED),
+            physical: element.int(1).filter(|_| element.int(5).unwrap_or(0) == 0),
+            type_length: element.int(2).unwrap_or(0).max(0) as usize,
+            text: matches!(converted, Some(CONVERTED_UTF8 | CONVERTED_ENUM | CONVERTED_JSON))
+                || logical(LOGICAL_STRING)
+                || logical(LOGICAL_ENUM)
+                || logical(LOGICAL_JSON),
+            json: converted == Some(CONVERTED_JSON) || logical(LOGICAL_JSON),
+            float16: logical(LOGICAL_FLOAT16),
+            children,
+        })
+    }
+
+    fn leaves(&self) -> usize {
+        match self.physical {
+            Some(_) => 1,
+            None => self.children.iter().map(Node::leaves).sum(),
+        }
+    }
+}
+
+/// # Structure: `Column`
+///
+/// Une colonne du fichier (un nœud du premier niveau du schéma) et ce qu'il faut pour lire sa feuille.
+#[derive(Debug, Clone)]
+struct Column {
+    field: Field,
+    /// Le rang de la feuille parmi les colonnes d'un groupe de lignes.
+    leaf: usize,
+    physical: i64,
+    type_length: usize,
+    float16: bool,
+    max_definition: u8,
+    max_repetition: u8,
+    /// Pour une liste, le niveau de définition à partir duquel la liste existe (vide),
+    /// et celui à partir duquel elle a un élément.
+    list: Option<(u8, u8)>,
+}
+
+impl Column {
+    /// Décrit le nœud `node` du premier niveau, dont la première feuille a le rang `leaf`.
+    fn new(node: &Node, leaf: usize) -> Result<Column, DbError> {
+        let unsupported = |what: &str| DbError::Unsupported(format!("colonne '{}' : {}", node.name, what));
+        let mut definition = 0u8;
+        let mut list = None;
+        let mut current = node;
+        loop {
+            match current.repetition {
+                OPTIONAL => definition += 1,
+                REPEATED if list.is_some() => return Err(unsupported("listes imbriquées")),
+                REPEATED => {
+                    list = Some((definition, definition + 1));
+                    definition += 1;
+                }
+                _ => {}
+            }
+            if current.physical.is_some() {
+                break;
+            }
+            match current.children.as_slice() {
+                [child] => current = child,
+                _ => return Err(unsupported("structure ou table associative")),
+            }
+        }
+        if !std::ptr::eq(current, node) && list.is_none() {
+            return Err(unsupported("structure"));
+        }
+
+        let physical = current.physical.unwrap_or_default();
+        let data_type = match physical {
+            BOOLEAN => DataType::Bool,
+            INT32 | INT64 => DataType::Int,
+            FLOAT => DataType::Float32,
+            DOUBLE => DataType::Float64,
+            BYTE_ARRAY if current.json => DataType::Json,
+            BYTE_ARRAY if current.text => DataType::Utf8,
+            BYTE_ARRAY => DataType::Binary,
+            FIXED_LEN_BYTE_ARRAY if current.float16 && current.type_length == 2 => DataType::Float32,
+            FIXED_LEN_BYTE_ARRAY => DataType::Binary,
+            _ => return Err(unsupported("type INT96 (horodatage obsolète) : le convertir en INT64")),
+        };
+        let data_type = match list {
+            Some(_) => DataType::List(Box::new(Field::new(&current.name, data_type, current.repetition == OPTIONAL))),
+            None => data_type,
+        };
+        Ok(Column {
+            field: Field::new(&node.name, data_type, node.repetition == OPTIONAL),
+            leaf,
+            physical,
+            type_length: current.type_length,
+            float16: current.float16,
+            max_definition: definition,
+            max_repetition: list.is_some() as u8,
+            list,
+        })
+    }
+}
+
+/// # Énumération: `Leaf`
+///
+/// Les valeurs non nulles d'une feuille, dans leur type physique.
+#[derive(Debug)]
+enum Leaf {
+    Bool(Vec<bool>),
+    Int(Vec<i64>),
+    Float(Vec<f64>),
+    Bytes(Vec<Vec<u8>>),
+}
+
+impl Leaf {
+    fn len(&self) -> usize {
+        match self {
+            Leaf::Bool(values) => values.len(),
+            Leaf::Int(values) => values.len(),
+            Leaf::Float(values) => values.len(),
+            Leaf::Bytes(values) => values.len(),
+        }
+    }
+
+    fn append(&mut self, other: Leaf) {
+        match (self, other) {
+            (Leaf::Bool(values), Leaf::Bool(other)) => values.extend(other),
+            (Leaf::Int(values), Leaf::Int(other)) => values.extend(other),
+            (Leaf::Float(values), Leaf::Float(other)) => values.extend(other),
+            (Leaf::Bytes(values), Leaf::Bytes(other)) => values.extend(other),
+            _ => unreachable!("une feuille a un seul type physique"),
+        }
+    }
+
+    fn take(&self, indices: &[u32]) -> Result<Leaf, DbError> {
+        if indices.iter().any(|&index| index as usize >= self.len()) {
+            return Err(corrupted("indice hors du dictionnaire"));
+        }
+        Ok(match self {
+            Leaf::Bool(values) => Leaf::Bool(indices.iter().map(|&index| values[index as usize]).collect()),
+            Leaf::Int(values) => Leaf::Int(indices.iter().map(|&index| values[index as usize]).collect()),
+            Leaf::Float(values) => Leaf::Float(indices.iter().map(|&index| values[index as usize]).collect()),
+            Leaf::Bytes(values) => Leaf::Bytes(indices.iter().map(|&index| values[index as usize].clone()).collect()),
+        })
+    }
+
+    /// Les valeurs d'une colonne de type `data_type` : une par élément de `validity`, les valeurs non
+    /// nulles prises dans l'ordre et les valeurs nulles remplacées par une valeur quelconque.
+    fn expand(self, data_type: &DataType, validity: &[bool]) -> Result<Values, DbError> {
+        fn spread<T: Clone + Default>(values: Vec<T>, validity: &[bool]) -> Vec<T> {
+            if values.len() == validity.len() {
+                return values;
+            }
+            let mut values = values.into_iter();
+            validity.iter().map(|&valid| if valid { values.next().unwrap_or_default() } else { T::default() }).collect()
+        }
+        Ok(match self {
+            Leaf::Bool(values) => Values::Bool(spread(values, validity)),
+            Leaf::Int(values) => Values::Int(spread(values, validity)),
+            Leaf::Float(values) => Values::Float(spread(values, validity)),
+            Leaf::Bytes(values) if matches!(data_type, DataType::Utf8 | DataType::Json) => Values::Utf8(spread(
+                values
+                    .into_iter()
+                    .map(|value| String::from_utf8(value).map_err(|_| corrupted("texte invalide")))
+                    .collect::<Result<_, _>>()?,
+                validity,
+            )),
+            Leaf::Bytes(values) => Values::Binary(spread(values, validity)),
+        })
+    }
+}
+
+/// # Structure: `ParquetReader`
+///
+/// Lit un fichier Parquet, un groupe de lignes à la fois.
+pub struct ParquetReader<R> {
+    reader: R,
+    columns: Vec<Column>,
+    fields: Vec<Field>,
+    row_groups: Vec<Thrift>,
+    /// Le rang du prochain groupe de lignes à lire.
+    next: usize,
+}
+
+impl<R: Read + Seek> ParquetReader<R> {
+    /// Lit le pied de page : le schéma et la position des groupes de lignes.
+    ///
+    /// # Retour
+    /// - `Result<ParquetReader<R>, DbError>`: [`DbError::Corrupted`] si le fichier n'est pas un fichier Parquet
+    ///   valide, ou [`DbError::Unsupported`] s'il est chiffré ou contient une colonne d'un type non pris en charge.
+    pub fn open(mut reader: R) -> Result<Self, DbError> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        if length < 12 {
+            return Err(corrupted("fichier trop court"));
+        }
+        reader.seek(SeekFrom::End(-8))?;
+        let mut tail = [0u8; 8];
+        reader.read_exact(&mut tail)?;
+        if &tail[4..] == ENCRYPTED_MAGIC {
+            return Err(DbError::Unsupported("fichier Parquet chiffré".to_string()));
+        }
+        if &tail[4..] != MAGIC {
+            return Err(corrupted("ce n'est pas un fichier Parquet"));
+        }
+        let footer_length = u32::from_le_bytes(tail[..4].try_into().unwrap()) as u64;
+        if footer_length + 12 > length || footer_length as usize > MAX_SIZE {
+            return Err(corrupted("pied de page invalide"));
+        }
+        reader.seek(SeekFrom::Start(length - 8 - footer_length))?;
+        let mut footer = vec![0; footer_length as usize];
+        reader.read_exact(&mut footer)?;
+        let (metadata, _) = Thrift::read_struct(&footer)?;
+
+        let elements = metadata.list(2);
+        let mut position = 0;
+        let root = Node::read(elements, &mut position, 0)?;
+        let mut columns = Vec::with_capacity(root.children.len());
+        let mut leaf = 0;
+        for node in &root.children {
+            columns.push(Column::new(node, leaf)?);
+            leaf += node.leaves();
+        }
+        Ok(ParquetReader {
+            reader,
+            fields: columns.iter().map(|column| column.field.clone()).collect(),
+            columns,
+            row_groups: metadata.list(4).to_vec(),
+            next: 0,
+        })
+    }
+
+    /// Les colonnes du fichier.
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+
+    /// Lit le groupe de lignes suivant.
+    ///
+    /// # Retour
+    /// - `Result<Option<RecordBatch>, DbError>`: Le lot de lignes, `None` après le dernier groupe, ou une erreur de lecture.
+    pub fn next_batch(&mut self) -> Result<Option<RecordBatch>, DbError> {
+        let Some(row_group) = self.row_groups.get(self.next) else {
+            return Ok(None);
+        };
+        self.next += 1;
+        let rows = row_group.int(3).filter(|&rows| rows >= 0).ok_or_else(|| corrupted("nombre de lignes invalide"))? as usize;
+        let chunks = row_group.list(1);
+        let mut columns = Vec::with_capacity(self.columns.len());
+        for column in &self.columns {
+            let chunk = chunks.get(column.leaf).ok_or_else(|| corrupted("colonne absente du groupe de lignes"))?;
+            columns.push(read_chunk(&mut self.reader, chunk, column, rows)?);
+        }
+        Ok(Some(RecordBatch { rows, columns }))
+    }
+}
+
+/// Lit les pages d'une colonne d'un groupe de lignes.
+fn read_chunk(reader: &mut (impl Read + Seek), chunk: &Thrift, column: &Column, rows: usize) -> Result<Array, DbError> {
+    let name = &column.field.name;
+    if chunk.binary(1).is_some() {
+        return Err(DbError::Unsupported(format!("colonne '{}' rangée dans un autre fichier", name)));
+    }
+    let metadata = chunk
+        .field(3)
+        .ok_or_else(|| DbError::Unsupported(format!("colonne '{}' chiffrée", name)))?;
+    let codec = metadata.int(4).unwrap_or(UNCOMPRESSED);
+    // Chaque ligne a au moins une valeur dans chaque colonne, exactement une si la colonne n'est pas une liste.
+    let values = metadata.int(5).unwrap_or(0).max(0) as usize;
+    if values > MAX_VALUES || values < rows || (column.max_repetition == 0 && values != rows) {
+        return Err(corrupted(&format!("nombre de valeurs de la colonne '{}' invalide", name)));
+    }
+    let data_offset = metadata.int(9).unwrap_or(0);
+    let start = metadata.int(11).filter(|&offset| offset > 0 && offset < data_offset).unwrap_or(data_offset);
+    let size = metadata.int(7).unwrap_or(0);
+    if start < 0 || !(0..MAX_SIZE as i64).contains(&size) {
+        return Err(corrupted(&format!("position de la colonne '{}' invalide", name)));
+    }
+    reader.seek(SeekFrom::Start(start as u64))?;
+    let mut data = Vec::new();
+    reader.take(size as u64).read_to_end(&mut data)?;
+    if data.len() != size as usize {
+        return Err(corrupted(&format!("colonne '{}' tronquée", name)));
+    }
+
+    let mut pages = Pages {
+        column,
+        codec,
+        values,
+        dictionary: None,
+        repetitions: Vec::new(),
+        definitions: Vec::new(),
+        leaf: match column.physical {
+            BOOLEAN => Leaf::Bool(Vec::new()),
+            INT32 | INT64 => Leaf::Int(Vec::new()),
+            FLOAT | DOUBLE => Leaf::Float(Vec::new()),
+            _ if column.float16 => Leaf::Float(Vec::new()),
+            _ => Leaf::Bytes(Vec::new()),
+        },
+    };
+    let mut position = 0;
+    while pages.definitions.len() < values && position < data.len() {
+        let (header, used) = Thrift::read_struct(&data[position..])?;
+        position += used;
+        let compressed = header.int(3).unwrap_or(-1);
+        let page = usize::try_from(compressed)
+            .ok()
+            .and_then(|compressed| data.get(position..position + compressed))
+            .ok_or_else(|| corrupted(&format!("page de la colonne '{}' tronquée", name)))?;
+        position += page.len();
+        pages.read(&header, page)?;
+    }
+    pages.assemble(rows).map_err(|error| match error {
+        DbError::Corrupted(message) => DbError::Corrupted(format!("{} (colonne '{}')", message, name)),
+        error => error,
+    })
+}
+
+/// Les niveaux et les valeurs des pages d'une colonne, à mesure qu'elles sont lues.
+struct Pages<'a> {
+    column: &'a Column,
+    codec: i64,
+    /// Le nombre de valeurs de la colonne annoncé par ses métadonnées, que ses pages ne peuvent pas dépasser.
+    values: usize,
+    dictionary: Option<Leaf>,
+    repetitions: Vec<u8>,
+    definitions: Vec<u8>,
+    leaf: Leaf,
+}
+
+impl Pages<'_> {
+    fn read(&mut self, header: &Thrift, page: &[u8]) -> Result<(), DbError> {
+        let size = header.int(2).filter(|&size| (0..MAX_SIZE as i64).contains(&size)).ok_or_else(|| corrupted("taille de page invalide"))? as usize;
+        match header.int(1) {
+            Some(DICTIONARY_PAGE) => {
+                let dictionary = header.field(7).ok_or_else(|| corrupted("en-tête de dictionnaire absent"))?;
+                let count = dictionary.int(1).unwrap_or(0).max(0) as usize;
+                if count > MAX_VALUES {
+                    return Err(corrupted("nombre de valeurs du dictionnaire invalide"));
+                }
+                let bytes = decompress(self.codec, page, size)?;
+                self.dictionary = Some(self.plain(&bytes, count)?);
+            }
+            Some(DATA_PAGE) => {
+                let data = header.field(5).ok_or_else(|| corrupted("en-tête de page absent"))?;
+                let count = self.page_values(data)?;
+                let bytes = decompress(self.codec, page, size)?;
+                let mut position = 0;
+                for (maximum, encoding, levels) in [
+                    (self.column.max_repetition, data.int(4), &mut self.repetitions),
+                    (self.column.max_definition, data.int(3), &mut self.definitions),
+                ] {
+                    if maximum == 0 {
+                        levels.extend(std::iter::repeat_n(0, count));
+                        continue;
+                    }
+                    if encoding.unwrap_or(RLE) != RLE {
+                        return Err(DbError::Unsupported("niveaux encodés en BIT_PACKED".to_string()));
+                    }
+                    let length = bytes.get(position..position + 4).ok_or_else(|| corrupted("niveaux tronqués"))?;
+                    let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                    let encoded = bytes.get(position + 4..position + 4 + length).ok_or_else(|| corrupted("niveaux tronqués"))?;
+                    levels.extend(hybrid(encoded, bit_width(maximum), count)?.into_iter().map(|level| level as u8));
+                    position += 4 + length;
+                }
+                self.values(data.int(2).unwrap_or(PLAIN), &bytes[position..], count)?;
+            }
+            Some(DATA_PAGE_V2) => {
+                let data = header.field(8).ok_or_else(|| corrupted("en-tête de page absent"))?;
+                let count = self.page_values(data)?;
+                let definition_length = data.int(5).unwrap_or(0).max(0) as usize;
+                let repetition_length = data.int(6).unwrap_or(0).max(0) as usize;
+                let levels_length = definition_length + repetition_length;
+                if levels_length > page.len() || levels_length > size {
+                    return Err(corrupted("niveaux tronqués"));
+                }
+                for (maximum, encoded, levels) in [
+                    (self.column.max_repetition, &page[..repetition_length], &mut self.repetitions),
+                    (self.column.max_definition, &page[repetition_length..levels_length], &mut self.definitions),
+                ] {
+                    match maximum {
+                        0 => levels.extend(std::iter::repeat_n(0, count)),
+                        _ => levels.extend(hybrid(encoded, bit_width(maximum), count)?.into_iter().map(|level| level as u8)),
+                    }
+                }
+                let bytes = match data.bool(7).unwrap_or(true) {
+                    true => decompress(self.codec, &page[levels_length..], size - levels_length)?,
+                    false => page[levels_length..].to_vec(),
+                };
+                self.values(data.int(4).unwrap_or(PLAIN), &bytes, count)?;
+            }
+            // Les pages d'index ne servent pas à la lecture.
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Le nombre de valeurs (niveaux compris) d'une page de données, qui ne peut pas dépasser celui
+    /// des valeurs de la colonne qui restent à lire.
+    fn page_values(&self, data: &Thrift) -> Result<usize, DbError> {
+        let count = data.int(1).unwrap_or(0).max(0) as usize;
+        if count > self.values - self.definitions.len() {
+            return Err(corrupted("la page contient plus de valeurs que la colonne"));
+        }
+        Ok(count)
+    }
+
+    /// Décode les valeurs non nulles d'une page de `count` niveaux, dont les niveaux viennent d'être lus.
+    fn values(&mut self, encoding: i64, bytes: &[u8], count: usize) -> Result<(), DbError> {
+        let start = self.definitions.len() - count;
+        let present = self.definitions[start..].iter().filter(|&&level| level == self.column.max_definition).count();
+        let values = match encoding {
+            PLAIN => self.plain(bytes, present)?,
+            PLAIN_DICTIONARY | RLE_DICTIONARY => {
+                let dictionary = self.dictionary.as_ref().ok_or_else(|| corrupted("page de dictionnaire absente"))?;
+                let width = *bytes.first().ok_or_else(|| corrupted("page tronquée"))?;
+                if width > 32 {
+                    return Err(corrupted("largeur d'indices invalide"));
+                }
+                dictionary.take(&hybrid(&bytes[1..], width, present)?)?
+            }
+            RLE if self.column.physical == BOOLEAN => {
+                let encoded = bytes.get(4..).ok_or_else(|| corrupted("page tronquée"))?;
+                Leaf::Bool(hybrid(encoded, 1, present)?.into_iter().map(|value| value == 1).collect())
+            }
+            BYTE_STREAM_SPLIT => {
+                let width = match self.column.physical {
+                    INT32 | FLOAT => 4,
+                    INT64 | DOUBLE => 8,
+                    FIXED_LEN_BYTE_ARRAY => self.column.type_length,
+                    _ => return Err(corrupted("BYTE_STREAM_SPLIT sur un type qui ne le permet pas")),
+                };
+                let bytes = bytes.get(..present * width).ok_or_else(|| corrupted("page tronquée"))?;
+                let mut joined = vec![0u8; bytes.len()];
+                for (index, byte) in joined.iter_mut().enumerate() {
+                    *byte = bytes[(index % width) * present + index / width];
+                }
+                self.plain(&joined, present)?
+            }
+            encoding => {
+                let name = ENCODING_NAMES.get(encoding as usize).copied().unwrap_or("inconnu");
+                return Err(DbError::Unsupported(format!("colonne '{}' : encodage {}", self.column.field.name, name)));
+            }
+        };
+        if values.len() != present {
+            return Err(corrupted("nombre de valeurs inexact"));
+        }
+        self.leaf.append(values);
+        Ok(())
+    }
+
+    /// Décode `count` valeurs encodées en `PLAIN`.
+    fn plain(&self, bytes: &[u8], count: usize) -> Result<Leaf, DbError> {
+        let fixed = |width: usize| bytes.get(..count * width).map(|bytes| bytes.chunks_exact(width)).ok_or_else(|| corrupted("page tronquée"));
+        Ok(match self.column.physical {
+            BOOLEAN => {
+                if bytes.len() * 8 < count {
+                    return Err(corrupted("page tronquée"));
+                }
+                Leaf::Bool((0..count).map(|index| bytes[index / 8] >> (index % 8) & 1 == 1).collect())
+            }
+            INT32 => Leaf::Int(fixed(4)?.map(|value| i32::from_le_bytes(value.try_into().unwrap()) as i64).collect()),
+            INT64 => Leaf::Int(fixed(8)?.map(|value| i64::from_le_bytes(value.try_into().unwrap())).collect()),
+            FLOAT => Leaf::Float(fixed(4)?.map(|value| f32::from_le_bytes(value.try_into().unwrap()) as f64).collect()),
+            DOUBLE => Leaf::Float(fixed(8)?.map(|value| f64::from_le_bytes(value.try_into().unwrap())).collect()),
+            FIXED_LEN_BYTE_ARRAY if self.column.float16 => {
+                Leaf::Float(fixed(2)?.map(|value| f16_to_f32(u16::from_le_bytes(value.try_into().unwrap())) as f64).collect())
+            }
+            FIXED_LEN_BYTE_ARRAY => Leaf::Bytes(fixed(self.column.type_length.max(1))?.map(<[u8]>::to_vec).collect()),
+            _ => {
+                let mut values = Vec::with_capacity(count.min(bytes.len() / 4));
+                let mut position = 0;
+                for _ in 0..count {
+                    let length = bytes.get(position..position + 4).ok_or_else(|| corrupted("page tronquée"))?;
+                    let length = u32::from_le_bytes(length.try_into().unwrap()) as usize;
+                    values.push(bytes.get(position + 4..position + 4 + length).ok_or_else(|| corrupted("page tronquée"))?.to_vec());
+                    position += 4 + length;
+                }
+                Leaf::Bytes(values)
+            }
+        })
+    }
+
+    /// Forme la colonne à partir des niveaux et des valeurs de toutes ses pages.
+    fn assemble(self, rows: usize) -> Result<Array, DbError> {
+        let maximum = self.column.max_definition;
+        let Some((present, element)) = self.column.list else {
+            if self.definitions.len() != rows {
+                return Err(corrupted("nombre de lignes inexact"));
+            }
+            let validity: Vec<bool> = self.definitions.iter().map(|&level| level == maximum).collect();
+            let values = self.leaf.expand(&self.column.field.data_type, &validity)?;
+            return Ok(Array { validity: validity.contains(&false).then_some(validity), values });
+        };
+
+        let mut offsets = Vec::with_capacity(rows + 1);
+        let mut row_validity = Vec::with_capacity(rows);
+        let mut item_validity = Vec::with_capacity(self.definitions.len());
+        for (&repetition, &definition) in self.repetitions.iter().zip(&self.definitions) {
+            if repetition == 0 {
+                offsets.push(item_validity.len());
+                row_validity.push(definition >= present);
+            }
+            if definition >= element {
+                item_validity.push(definition == maximum);
+            }
+        }
+        offsets.push(item_validity.len());
+        if row_validity.len() != rows {
+            return Err(corrupted("nombre de lignes inexact"));
+        }
+        let item = self.column.field.data_type.item().expect("une liste a un type d'éléments");
+        let values = self.leaf.expand(&item.data_type, &item_validity)?;
+        Ok(Array {
+            validity: row_validity.contains(&false).then_some(row_validity),
+            values: Values::List {
+                offsets,
+                values: Box::new(Array { validity: item_validity.contains(&false).then_some(item_validity), values }),
+            },
+        })
+    }
+}
+
+/// Décompresse une page.
+fn decompress(codec: i64, bytes: &[u8], size: usize) -> Result<Vec<u8>, DbError> {
+    match codec {
+        UNCOMPRESSED => Ok(bytes.to_vec()),
+        SNAPPY => snappy::decompress(bytes, size),
+        GZIP => gunzip(bytes, size),
+        codec => {
+            let name = CODEC_NAMES.get(codec as usize).copied().unwrap_or("inconnue");
+            Err(DbError::Unsupported(format!("compression {} : réécrire le fichier en Snappy, gzip ou sans compression", name)))
+        }
+    }
+}
+
+/// Décompresse un flux gzip : un en-tête, puis un flux *deflate*.
+fn gunzip(bytes: &[u8], size: usize) -> Result<Vec<u8>, DbError> {
+    if bytes.len() < 10 || bytes[..3] != [0x1f, 0x8b, 8] {
+        return Err(corrupted("page gzip invalide"));
+    }
+    let flags = bytes[3];
+    let mut position = 10;
+    if flags & 4 != 0 {
+        let extra = bytes.get(position..position + 2).ok_or_else(|| corrupted("page gzip tronquée"))?;
+        position += 2 + u16::from_le_bytes(extra.try_into().unwrap()) as usize;
+    }
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            let end = bytes.get(position..).and_then(|rest| rest.iter().position(|&byte| byte == 0)).ok_or_else(|| corrupted("page gzip tronquée"))?;
+            position += end + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        position += 2;
+    }
+    let mut output = Vec::with_capacity(size);
+    Inflate::new(bytes.get(position..).ok_or_else(|| corrupted("page gzip tronquée"))?)
+        .take(size as u64)
+        .read_to_end(&mut output)?;
+    if output.len() != size {
+        return Err(corrupted("page gzip tronquée"));
+    }
+    Ok(output)
+}
+
+/// Nombre de bits pour écrire les niveaux de 0 à `maximum`.
+fn bit_width(maximum: u8) -> u8 {
+    (u8::BITS - maximum.leading_zeros()) as u8
+}
+
+/// Décode `count` valeurs de `width` bits de l'encodage hybride RLE / *bit-packing* : une suite de répétitions
+/// d'une même valeur et de groupes de 8 valeurs rangées bit à bit.
+fn hybrid(bytes: &[u8], width: u8, count: usize) -> Result<Vec<u32>, DbError> {
+    let width = width as usize;
+    let mut values = Vec::with_capacity(count);
+    let mut position = 0;
+    while values.len() < count {
+        let mut header = 0usize;
+        for shift in (0..35).step_by(7) {
+            let byte = *bytes.get(position).ok_or_else(|| corrupted("niveaux ou indices tronqués"))?;
+            position += 1;
+            header |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        let remaining = count - values.len();
+        if header & 1 == 1 {
+            let groups = header >> 1;
+            let packed = bytes.get(position..position + groups * width).ok_or_else(|| corrupted("niveaux ou indices tronqués"))?;
+            position += packed.len();
+            for index in 0..(groups * 8).min(remaining) {
+                let bit = index * width;
+                let mut value = 0u64;
+                for (shift, byte) in packed[bit / 8..(bit + width).div_ceil(8)].iter().enumerate() {
+                    value |= (*byte as u64) << (8 * shift);
+                }
+                values.push(((value >> (bit % 8)) & ((1u64 << width) - 1)) as u32);
+            }
+        } else {
+            let bytes_per_value = width.div_ceil(8);
+            let value = bytes.get(position..position + bytes_per_value).ok_or_else(|| corrupted("niveaux ou indices tronqués"))?;
+            position += bytes_per_value;
+            let value = value.iter().rev().fold(0u32, |value, &byte| value << 8 | byte as u32);
+            values.extend(std::iter::repeat_n(value, (header >> 1).min(remaining)));
+        }
+    }
+    Ok(values)
+}
+
+/// Encode des niveaux en répétitions RLE, précédées de leur taille (pages de version 1).
+fn encode_levels(levels: &[u8], width: u8, output: &mut Vec<u8>) {
+    let start = output.len();
+    output.extend([0; 4]);
+    let mut index = 0;
+    while index < levels.len() {
+        let run = levels[index..].iter().take_while(|&&level| level == levels[index]).count();
+        let mut header = run << 1;
+        while header >= 0x80 {
+            output.push(header as u8 | 0x80);
+            header >>= 7;
+        }
+        output.push(header as u8);
+        output.extend(&[levels[index]][..(width as usize).div_ceil(8)]);
+        index += run;
+    }
+    let length = (output.len() - start - 4) as u32;
+    output[start..start + 4].copy_from_slice(&length.to_le_bytes());
+}
+
+/// # Structure: `ParquetWriter`
+///
+/// Écrit un fichier Parquet, un groupe de lignes par lot.
+pub struct ParquetWriter<W: Write> {
+    writer: W,
+    /// Nombre d'octets écrits.
+    position: u64,
+    fields: Vec<Field>,
+    row_groups: Vec<Thrift>,
+    rows: i64,
+}
+
+impl<W: Write> ParquetWriter<W> {
+    /// Écrit le début du fichier.
+    ///
+    /// # Retour
+    /// - `Result<ParquetWriter<W>, DbError>`: [`DbError::Unsupported`] si une colonne est d'un type
+    ///   qui ne peut pas être écrit (liste de listes), ou une erreur d'écriture.
+    pub fn new(mut writer: W, fields: &[Field]) -> Result<Self, DbError> {
+        for field in fields {
+            if field.data_type.item().is_some_and(|item| item.data_type.item().is_some()) || field.data_type == DataType::Null {
+                return Err(DbError::Unsupported(format!("colonne '{}' : type {:?}", field.name, field.data_type)));
+            }
+        }
+        writer.write_all(MAGIC)?;
+        Ok(ParquetWriter {
+            writer,
+            position: MAGIC.len() as u64,
+            fields: fields.to_vec(),
+            row_groups: Vec::new(),
+            rows: 0,
+        })
+    }
+
+    /// Écrit un lot de lignes comme un groupe de lignes.
+    pub fn write_batch(&mut self, batch: &RecordBatch) -> Result<(), DbError> {
+        let start = self.position;
+        let mut chunks = Vec::with_capacity(self.fields.len());
+        for (field, array) in self.fields.iter().zip(&batch.columns) {
+            let (repetitions, definitions, values) = encode_column(field, array)?;
+            let (max_repetition, max_definition) = max_levels(field);
+            let mut page = Vec::new();
+            if max_repetition > 0 {
+                encode_levels(&repetitions, bit_width(max_repetition), &mut page);
+            }
+            if max_definition > 0 {
+                encode_levels(&definitions, bit_width(max_definition), &mut page);
+            }
+            page.extend(values);
+            let page_size = i32::try_from(page.len()).map_err(|_| DbError::Unsupported("page de plus de 2 Gio : réduire la taille des lots".to_string()))?;
+
+            let mut header = Vec::new();
+            Thrift::Struct(vec![
+                (1, Thrift::I32(DATA_PAGE as i32)),
+                (2, Thrift::I32(page_size)),
+                (3, Thrift::I32(page_size)),
+                (
+                    5,
+                    Thrift::Struct(vec![
+                        (1, Thrift::I32(definitions.len() as i32)),
+                        (2, Thrift::I32(PLAIN as i32)),
+                        (3, Thrift::I32(RLE as i32)),
+                        (4, Thrift::I32(RLE as i32)),
+                    ]),
+                ),
+            ])
+            .write_struct(&mut header);
+            let offset = self.position as i64;
+            self.writer.write_all(&header)?;
+            self.writer.write_all(&page)?;
+            let size = (header.len() + page.len()) as i64;
+            self.position += size as u64;
+
+            let path = match field.data_type.item() {
+                Some(_) => vec![field.name.as_str(), "list", "element"],
+                None => vec![field.name.as_str()],
+            };
+            let metadata = Thrift::Struct(vec![
+                (1, Thrift::I32(physical_type(field.data_type.item().map_or(&field.data_type, |item| &item.data_type)) as i32)),
+                (2, Thrift::List(vec![Thrift::I32(PLAIN as i32), Thrift::I32(RLE as i32)])),
+                (3, Thrift::List(path.iter().map(|name| Thrift::Binary(name.as_bytes().to_vec())).collect())),
+                (4, Thrift::I32(UNCOMPRESSED as i32)),
+                (5, Thrift::I64(definitions.len() as i64)),
+                (6, Thrift::I64(size)),
+                (7, Thrift::I64(size)),
+                (9, Thrift::I64(offset)),
+            ]);
+            chunks.push(Thrift::Struct(vec![(2, Thrift::I64(offset)), (3, metadata)]));
+        }
+        let size = (self.position - start) as i64;
+        self.row_groups.push(Thrift::Struct(vec![
+            (1, Thrift::List(chunks)),
+            (2, Thrift::I64(size)),
+            (3, Thrift::I64(batch.rows as i64)),
+            (5, Thrift::I64(start as i64)),
+            (6, Thrift::I64(size)),
+        ]));
+        self.rows += batch.rows as i64;
+        Ok(())
+    }
+
+    /// Écrit le pied de page : le schéma et la position des groupes de lignes.
+    pub fn finish(mut self) -> Result<(), DbError> {
+        let mut schema = vec![Thrift::Struct(vec![
+            (4, Thrift::Binary(b"schema".to_vec())),
+            (5, Thrift::I32(self.fields.len() as i32)),
+        ])];
+        for field in &self.fields {
+            let repetition = Thrift::I32(if field.nullable { OPTIONAL } else { REQUIRED } as i32);
+            match field.data_type.item() {
+                Some(item) => {
+                    schema.push(Thrift::Struct(vec![
+                        (3, repetition),
+                        (4, Thrift::Binary(field.name.as_bytes().to_vec())),
+                        (5, Thrift::I32(1)),
+                        (6, Thrift::I32(CONVERTED_LIST as i32)),
+                        (10, Thrift::Struct(vec![(LOGICAL_LIST, Thrift::Struct(Vec::new()))])),
+                    ]));
+                    schema.push(Thrift::Struct(vec![
+                        (3, Thrift::I32(REPEATED as i32)),
+                        (4, Thrift::Binary(b"list".to_vec())),
+                        (5, Thrift::I32(1)),
+                    ]));
+                    schema.push(leaf_element("element", &item.data_type, item.nullable));
+                }
+                None => schema.push(leaf_element(&field.name, &field.data_type, field.nullable)),
+            }
+        }
+        let mut footer = Vec::new();
+        Thrift::Struct(vec![
+            (1, Thrift::I32(1)),
+            (2, Thrift::List(schema)),
+            (3, Thrift::I64(self.rows)),
+            (4, Thrift::List(std::mem::take(&mut self.row_groups))),
+            (6, Thrift::Binary(concat!("projet version ", env!("CARGO_PKG_VERSION")).as_bytes().to_vec())),
+        ])
+        .write_struct(&mut footer);
+        self.writer.write_all(&footer)?;
+        self.writer.write_all(&(footer.len() as u32).to_le_bytes())?;
+        self.writer.write_all(MAGIC)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// L'élément du schéma d'une feuille.
+fn leaf_element(name: &str, data_type: &DataType, nullable: bool) -> Thrift {
+    let mut element = vec![
+        (1, Thrift::I32(physical_type(data_type) as i32)),
+        (3, Thrift::I32(if nullable { OPTIONAL } else { REQUIRED } as i32)),
+        (4, Thrift::Binary(name.as_bytes().to_vec())),
+    ];
+    match data_type {
+        DataType::Utf8 => {
+            element.push((6, Thrift::I32(CONVERTED_UTF8 as i32)));
+            element.push((10, Thrift::Struct(vec![(LOGICAL_STRING, Thrift::Struct(Vec::new()))])));
+        }
+        DataType::Json => {
+            element.push((6, Thrift::I32(CONVERTED_JSON as i32)));
+            element.push((10, Thrift::Struct(vec![(LOGICAL_JSON, Thrift::Struct(Vec::new()))])));
+        }
+        _ => {}
+    }
+    Thrift::Struct(element)
+}
+
+fn physical_type(data_type: &DataType) -> i64 {
+    match data_type {
+        DataType::Bool => BOOLEAN,
+        DataType::Int => INT64,
+        DataType::Float32 => FLOAT,
+        DataType::Float64 => DOUBLE,
+        _ => BYTE_ARRAY,
+    }
+}
+
+/// Les niveaux maximaux de répétition et de définition d'une colonne écrite : une liste est écrite
+/// en trois niveaux (`<nom> (LIST)`, `repeated list`, `element`).
+fn max_levels(field: &Field) -> (u8, u8) {
+    match field.data_type.item() {
+        Some(item) => (1, field.nullable as u8 + 1 + item.nullable as u8),
+        None => (0, field.nullable as u8),
+    }
+}
+
+/// Les niveaux de répétition et de définition d'une colonne, et ses valeurs non nulles encodées en `PLAIN`.
+type EncodedColumn = (Vec<u8>, Vec<u8>, Vec<u8>);
+
+fn encode_column(field: &Field, array: &Array) -> Result<EncodedColumn, DbError> {
+    let (_, maximum) = max_levels(field);
+    let mut repetitions = Vec::new();
+    let mut definitions = Vec::new();
+    let mut present = Vec::new();
+    let values = match (&field.data_type, &array.values) {
+        (DataType::List(item) | DataType::FixedSizeList(item, _), Values::List { offsets, values }) => {
+            let list = field.nullable as u8;
+            for row in 0..array.len() {
+                if !array.is_valid(row) {
+                    repetitions.push(0);
+                    definitions.push(0);
+                } else if offsets[row] == offsets[row + 1] {
+                    repetitions.push(0);
+                    definitions.push(list);
+                } else {
+                    for index in offsets[row]..offsets[row + 1] {
+                        repetitions.push((index > offsets[row]) as u8);
+                        definitions.push(if values.is_valid(index) { maximum } else { list + 1 });
+                        if values.is_valid(index) {
+                            present.push(index);
+                        }
+                    }
+                }
+            }
+            encode_plain(&item.data_type, values, &present)
+        }
+        (data_type, _) => {
+            for row in 0..array.len() {
+                definitions.push(if array.is_valid(row) { maximum } else { 0 });
+                if array.is_valid(row) {
+                    present.push(row);
+                }
+            }
+            encode_plain(data_type, array, &present)
+        }
+    };
+    let values = values.ok_or_else(|| DbError::InvalidSchema(format!("colonne '{}' : valeurs d'un autre type que {:?}", field.name, field.data_type)))?;
+    Ok((repetitions, definitions, values))
+}
+
+/// Les valeurs de positions `present` de `array`, encodées en `PLAIN` ; `None` si elles ne sont pas du type `data_type`.
+fn encode_plain(data_type: &DataType, array: &Array, present: &[usize]) -> Option<Vec<u8>> {
+    let mut output = Vec::new();
+    match (data_type, &array.values) {
+        (DataType::Bool, Values::Bool(values)) => {
+            output.resize(present.len().div_ceil(8), 0);
+            for (bit, _) in present.iter().enumerate().filter(|(_, &index)| values[index]) {
+                output[bit / 8] |= 1 << (bit % 8);
+            }
+        }
+        (DataType::Int, Values::Int(values)) => output.extend(present.iter().flat_map(|&index| values[index].to_le_bytes())),
+        (DataType::Float32, Values::Float(values)) => output.extend(present.iter().flat_map(|&index| (values[index] as f32).to_le_bytes())),
+        (DataType::Float64, Values::Float(values)) => output.extend(present.iter().flat_map(|&index| values[index].to_le_bytes())),
+        (DataType::Utf8 | DataType::Json, Values::Utf8(values)) => {
+            for &index in present {
+                output.extend((values[index].len() as u32).to_le_bytes());
+                output.extend(values[index].as_bytes());
+            }
+        }
+        (DataType::Binary, Values::Binary(values)) => {
+            for &index in present {
+                output.extend((values[index].len() as u32).to_le_bytes());
+                output.extend(&values[index]);
+            }
+        }
+        _ => return None,
+    }
+    Some(output)
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("fichier Parquet : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Un fichier d'une colonne d'entiers facultatifs, en un seul groupe de lignes d'une seule page.
+    fn file() -> Vec<u8> {
+        let mut file = Vec::new();
+        let mut writer = ParquetWriter::new(&mut file, &[Field::new("n", DataType::Int, true)]).unwrap();
+        let column = Array { validity: Some(vec![true, false, true]), values: Values::Int(vec![1, 0, 3]) };
+        writer.write_batch(&RecordBatch { rows: 3, columns: vec![column] }).unwrap();
+        writer.finish().unwrap();
+        file
+    }
+
+    fn field_mut(value: &mut Thrift, id: i16) -> &mut Thrift {
+        match value {
+            Thrift::Struct(fields) => &mut fields.iter_mut().find(|(field, _)| *field == id).unwrap().1,
+            _ => panic!("structure attendue"),
+        }
+    }
+
+    fn first_mut(value: &mut Thrift) -> &mut Thrift {
+        match value {
+            Thrift::List(values) => &mut values[0],
+            _ => panic!("liste attendue"),
+        }
+    }
+
+    /// Réécrit le fichier de [`file`] après avoir modifié l'en-tête de sa page et son pied de page.
+    fn rewrite(file: &[u8], edit: impl FnOnce(&mut Thrift, &mut Thrift)) -> Vec<u8> {
+        let (mut header, used) = Thrift::read_struct(&file[MAGIC.len()..]).unwrap();
+        let page_size = header.int(3).unwrap() as usize;
+        let page = &file[MAGIC.len() + used..MAGIC.len() + used + page_size];
+        let footer_length = u32::from_le_bytes(file[file.len() - 8..file.len() - 4].try_into().unwrap()) as usize;
+        let (mut footer, _) = Thrift::read_struct(&file[file.len() - 8 - footer_length..]).unwrap();
+        edit(&mut header, &mut footer);
+
+        let mut encoded = Vec::new();
+        header.write_struct(&mut encoded);
+        let chunk_size = Thrift::I64((encoded.len() + page.len()) as i64);
+        let metadata = field_mut(first_mut(field_mut(first_mut(field_mut(&mut footer, 4)), 1)), 3);
+        *field_mut(metadata, 6) = chunk_size.clone();
+        *field_mut(metadata, 7) = chunk_size;
+
+        let mut output = MAGIC.to_vec();
+        output.extend(encoded);
+        output.extend(page);
+        let start = output.len();
+        footer.write_struct(&mut output);
+        let footer_length = (output.len() - start) as u32;
+        output.extend(footer_length.to_le_bytes());
+        output.extend(MAGIC);
+        output
+    }
+
+    fn read(file: Vec<u8>) -> Result<Option<RecordBatch>, DbError> {
+        ParquetReader::open(Cursor::new(file))?.next_batch()
+    }
+
+    #[test]
+    fn reads_the_file_it_writes() {
+        let batch = read(rewrite(&file(), |_, _| {})).unwrap().unwrap();
+        assert_eq!(batch.rows, 3);
+        assert_eq!(batch.columns[0].validity, Some(vec![true, false, true]));
+        match &batch.columns[0].values {
+            Values::Int(values) => assert_eq!([values[0], values[2]], [1, 3]),
+            values => panic!("entiers attendus : {:?}", values),
+        }
+    }
+
+    #[test]
+    fn reads_the_reference_file() {
+        let mut reader = ParquetReader::open(Cursor::new(include_bytes!("../testdata/reference.parquet"))).unwrap();
+        let element = Field::new("element", DataType::Float32, true);
+        assert_eq!(
+            reader.fields(),
+            [
+                Field::new("id", DataType::Utf8, true),
+                Field::new("vector", DataType::List(Box::new(element)), true),
+                Field::new("rank", DataType::Int, true),
+                Field::new("tag", DataType::Utf8, true),
+                Field::new("score", DataType::Float64, true),
+            ]
+        );
+        let first = reader.next_batch().unwrap().unwrap();
+        let Values::List { offsets, values } = &first.columns[1].values else {
+            panic!("liste attendue : {:?}", first.columns[1].values);
+        };
+        assert_eq!(offsets, &[0, 3, 6, 9]);
+        assert_eq!(values.values, Values::Float(vec![0.5, -1.0, 2.0, 1.5, 0.0, -2.5, 3.0, 3.0, 3.0]));
+        // Le second groupe de lignes n'a qu'une valeur dans le dictionnaire de `tag` : des indices sur 0 bit.
+        let second = reader.next_batch().unwrap().unwrap();
+        assert_eq!(second.rows, 2);
+        assert_eq!(second.columns[3].validity, Some(vec![true, false]));
+        assert!(reader.next_batch().unwrap().is_none());
+    }
+
+    #[test]
+    fn page_with_too_many_values_is_corrupted() {
+        for count in [4, 1 << 40] {
+            let file = rewrite(&file(), |header, _| *field_mut(field_mut(header, 5), 1) = Thrift::I64(count));
+            match read(file) {
+                Err(DbError::Corrupted(message)) => assert!(message.contains("plus de valeurs que la colonne"), "{}", message),
+                result => panic!("page de {} valeurs acceptée : {:?}", count, result.map(|batch| batch.map(|batch| batch.rows))),
+            }
+        }
+    }
+
+    #[test]
+    fn chunk_and_row_group_counts_are_bounded() {
+        let chunk_values = |count: i64| {
+            move |_: &mut Thrift, footer: &mut Thrift| {
+                let chunk = first_mut(field_mut(first_mut(field_mut(footer, 4)), 1));
+                *field_mut(field_mut(chunk, 3), 5) = Thrift::I64(count);
+            }
+        };
+        let row_group_rows = |count: i64| move |_: &mut Thrift, footer: &mut Thrift| *field_mut(first_mut(field_mut(footer, 4)), 3) = Thrift::I64(count);
+
+        for file in [
+            rewrite(&file(), chunk_values(1 << 40)),
+            rewrite(&file(), chunk_values(2)),
+            rewrite(&file(), row_group_rows(1 << 40)),
+            rewrite(&file(), row_group_rows(4)),
+        ] {
+            match read(file) {
+                Err(DbError::Corrupted(message)) => assert!(message.contains("nombre de valeurs de la colonne 'n' invalide"), "{}", message),
+                result => panic!("métadonnées invalides acceptées : {:?}", result.map(|batch| batch.map(|batch| batch.rows))),
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! # Module: `payload`
+//!
+//! Les métadonnées (*payload*) stockées avec le vecteur de chaque document :
+//! une table clé-valeur dont les valeurs suivent le modèle de données de JSON.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// # Type: `Payload`
+///
+/// Les métadonnées d'un document, par exemple `{"titre": "Acte de vente", "client": "Dupont", "annee": 2024}`.
+/// Les clés sont triées, ce qui rend l'affichage et la sérialisation déterministes.
+pub type Payload = BTreeMap<String, Value>;
+
+/// # Énumération: `Value`
+///
+/// Une valeur de métadonnée, sur le modèle des valeurs JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Payload),
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(values: Vec<Value>) -> Self {
+        Value::Array(values)
+    }
+}
+
+impl From<Payload> for Value {
+    fn from(payload: Payload) -> Self {
+        Value::Object(payload)
+    }
+}
+
+/// Affiche la valeur au format JSON.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            // JSON ne connaît ni `NaN` ni l'infini.
+            Value::Number(value) if !value.is_finite() => write!(f, "null"),
+            Value::Number(value) => write!(f, "{}", value),
+            Value::String(value) => write_json_string(f, value),
+            Value::Array(values) => {
+                write!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", value)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(payload) => write!(f, "{}", PayloadDisplay(payload)),
+        }
+    }
+}
+
+/// # Structure: `PayloadDisplay`
+///
+/// Adaptateur affichant un [`Payload`] au format JSON.
+///
+/// # Exemple
+///
+/// ```
+/// println!("{}", PayloadDisplay(&payload));
+/// ```
+pub struct PayloadDisplay<'a>(pub &'a Payload);
+
+impl fmt::Display for PayloadDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{{")?;
+        for (i, (key, value)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write_json_string(f, key)?;
+            write!(f, ":{}", value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+/// Écrit une chaîne entre guillemets en échappant les caractères spéciaux de JSON.
+fn write_json_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in value.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
@@ -0,0 +1,221 @@
+//! # Module: `pool`
+//!
+//! Un pool de threads créé une seule fois et réutilisé par toutes les recherches, pour répartir
+//! le parcours d'une collection entre les cœurs sans créer de thread à chaque requête.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+/// # Structure: `WorkerPool`
+///
+/// Un ensemble fixe de threads qui exécutent les tâches déposées dans une file partagée.
+pub struct WorkerPool {
+    sender: Sender<Job>,
+    receiver: Arc<Mutex<Receiver<Job>>>,
+    workers: usize,
+}
+
+impl WorkerPool {
+    /// Crée un pool de `workers` threads (au moins un).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let pool = WorkerPool::new(4);
+    /// let squares = pool.map(8, |i| i * i);
+    /// ```
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                match job {
+                    Ok(job) => job(),
+                    Err(_) => break,
+                }
+            });
+        }
+        WorkerPool {
+            sender,
+            receiver,
+            workers,
+        }
+    }
+
+    /// Le pool partagé par tout le programme, dimensionné selon le nombre de cœurs disponibles.
+    pub fn global() -> &'static WorkerPool {
+        static POOL: OnceLock<WorkerPool> = OnceLock::new();
+        POOL.get_or_init(|| WorkerPool::new(thread::available_parallelism().map_or(1, |n| n.get())))
+    }
+
+    /// Le nombre de threads du pool.
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// Exécute `task(0)`, ..., `task(count - 1)` en parallèle et retourne leurs résultats dans l'ordre.
+    ///
+    /// Les tâches peuvent emprunter des données de l'appelant : la fonction ne rend la main qu'une fois
+    /// toutes les tâches terminées. En attendant, le thread appelant exécute lui-même des tâches de la file,
+    /// ce qui permet d'appeler `map` depuis une tâche du pool sans interblocage.
+    /// Si une tâche panique, la panique est propagée à l'appelant une fois toutes les tâches terminées.
+    ///
+    /// # Paramètres
+    /// - `count`: Le nombre de tâches.
+    /// - `task`: La tâche, appelée avec son numéro.
+    ///
+    /// # Retour
+    /// - `Vec<T>`: Le résultat de chaque tâche, dans l'ordre des numéros.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let sums = WorkerPool::global().map(chunks.len(), |i| chunks[i].iter().sum::<f32>());
+    /// ```
+    pub fn map<T, F>(&self, count: usize, task: F) -> Vec<T>
+    where
+        T: Send,
+        F: Fn(usize) -> T + Sync,
+    {
+        if count <= 1 || self.workers <= 1 {
+            return (0..count).map(task).collect();
+        }
+
+        let latch = Arc::new(Latch::new(count));
+        let slots: Vec<Mutex<Option<thread::Result<T>>>> = (0..count).map(|_| Mutex::new(None)).collect();
+        for (i, slot) in slots.iter().enumerate() {
+            let task = &task;
+            let latch = Arc::clone(&latch);
+            let job: Box<dyn FnOnce() + Send + '_> = Box::new(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| task(i)));
+                *slot.lock().unwrap_or_else(PoisonError::into_inner) = Some(result);
+                // Après ce décompte, la tâche ne touche plus qu'au compte à rebours, qu'elle possède.
+                latch.count_down();
+            });
+            // SAFETY: la tâche n'emprunte que `task` et `slot`, qui vivent jusqu'à la fin de `map`,
+            // et `map` attend que le décompte de `latch` atteigne zéro avant de rendre la main :
+            // chaque tâche y contribue exactement une fois, après son dernier accès aux données empruntées.
+            let job: Job = unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + '_>, Job>(job) };
+            if let Err(mpsc::SendError(job)) = self.sender.send(job) {
+                job();
+            }
+        }
+
+        while !latch.is_done() {
+            match self.try_take_job() {
+                Some(job) => job(),
+                None => latch.wait(Duration::from_millis(1)),
+            }
+        }
+
+        slots
+            .into_iter()
+            .map(|slot| match slot.into_inner().unwrap_or_else(PoisonError::into_inner) {
+                Some(Ok(result)) => result,
+                Some(Err(payload)) => panic::resume_unwind(payload),
+                None => unreachable!("toutes les tâches ont été exécutées"),
+            })
+            .collect()
+    }
+
+    /// Prend une tâche en attente dans la file, si la file est libre et non vide.
+    fn try_take_job(&self) -> Option<Job> {
+        self.receiver.try_lock().ok()?.try_recv().ok()
+    }
+}
+
+/// Un compte à rebours sur lequel l'appelant de [`WorkerPool::map`] attend la fin des tâches.
+struct Latch {
+    remaining: Mutex<usize>,
+    done: Condvar,
+}
+
+impl Latch {
+    fn new(count: usize) -> Self {
+        Latch {
+            remaining: Mutex::new(count),
+            done: Condvar::new(),
+        }
+    }
+
+    fn count_down(&self) {
+        let mut remaining = self.remaining.lock().unwrap_or_else(PoisonError::into_inner);
+        *remaining -= 1;
+        if *remaining == 0 {
+            self.done.notify_all();
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        *self.remaining.lock().unwrap_or_else(PoisonError::into_inner) == 0
+    }
+
+    /// Attend la fin des tâches, au plus `timeout`.
+    fn wait(&self, timeout: Duration) {
+        let remaining = self.remaining.lock().unwrap_or_else(PoisonError::into_inner);
+        if *remaining > 0 {
+            let _ = self.done.wait_timeout(remaining, timeout);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn results_follow_the_task_numbers() {
+        let pool = WorkerPool::new(4);
+        // Les premières tâches se terminent en dernier.
+        let results = pool.map(16, |i| {
+            thread::sleep(Duration::from_millis(16 - i as u64));
+            i * i
+        });
+        assert_eq!(results, (0..16).map(|i| i * i).collect::<Vec<_>>());
+
+        // Les tâches empruntent les données de l'appelant.
+        let chunks: Vec<Vec<u32>> = (0..10).map(|i| (0..=i).collect()).collect();
+        let sums = pool.map(chunks.len(), |i| chunks[i].iter().sum::<u32>());
+        assert_eq!(sums, (0..10).map(|i| i * (i + 1) / 2).collect::<Vec<_>>());
+
+        assert!(pool.map(0, |i| i).is_empty());
+        assert_eq!(WorkerPool::new(1).map(5, |i| i + 1), [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn nested_maps_do_not_deadlock() {
+        let pool = WorkerPool::new(2);
+        let results = pool.map(8, |i| pool.map(8, |j| i * 8 + j).into_iter().sum::<usize>());
+        assert_eq!(results, (0..8).map(|i| (0..8).map(|j| i * 8 + j).sum()).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn panic_is_propagated_after_every_task_has_finished() {
+        let pool = WorkerPool::new(4);
+        let finished = AtomicUsize::new(0);
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            pool.map(12, |i| {
+                if i == 3 {
+                    panic!("tâche {} en échec", i);
+                }
+                thread::sleep(Duration::from_millis(5));
+                finished.fetch_add(1, Ordering::SeqCst);
+            })
+        }));
+        let payload = result.unwrap_err();
+        assert_eq!(payload.downcast_ref::<String>().map(String::as_str), Some("tâche 3 en échec"));
+        assert_eq!(finished.load(Ordering::SeqCst), 11);
+
+        // Les threads du pool ont survécu à la panique.
+        assert_eq!(pool.map(6, |i| i), [0, 1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,370 @@
+//! # Module: `pq`
+//!
+//! Quantification par produit (*product quantization*) des vecteurs d'une [`Collection`](crate::Collection).
+//!
+//! Chaque vecteur est découpé en `subspaces` sous-vecteurs consécutifs ; dans chaque sous-espace,
+//! un dictionnaire de `centroids` centroïdes (au plus 256) est appris par k-moyennes, et le sous-vecteur
+//! est remplacé par le numéro de son centroïde le plus proche. Un document n'occupe alors que
+//! `subspaces` octets au lieu de quatre octets par coordonnée.
+//!
+//! Une recherche calcule d'abord, pour chaque sous-espace, le score partiel de la requête avec chacun
+//! des centroïdes (tables de distances asymétriques) : le score approché d'un document n'est plus que
+//! la somme de `subspaces` valeurs lues dans ces tables. Les meilleurs candidats peuvent ensuite être
+//! reclassés avec leurs vecteurs d'origine (voir [`PqParams::rerank`]).
+//!
+//! Comme les index, le quantificateur est maintenu à jour à chaque ajout ou suppression : les nouveaux
+//! documents sont encodés avec les dictionnaires existants, qui ne sont réappris qu'en reconstruisant
+//! le quantificateur.
+
+use crate::kmeans::{self, nearest_centroid};
+use crate::metric::Metric;
+use crate::quantizer::{self, Codes};
+use crate::rng::SplitMix64;
+use crate::vectors::VectorStore;
+use crate::{cosine_from_dot_product, simd, DocumentId, Vector};
+
+/// Graine du générateur pseudo-aléatoire utilisé pour l'échantillon et l'initialisation des k-moyennes.
+const TRAINING_SEED: u64 = 0x5EED_90A0_0000_0001;
+
+/// Nombre maximal de centroïdes par sous-espace : un code tient sur un octet.
+const MAX_CENTROIDS: usize = 256;
+
+/// # Structure: `PqParams`
+///
+/// Paramètres d'apprentissage et de recherche d'un [`ProductQuantizer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PqParams {
+    /// Nombre de sous-espaces, c'est-à-dire d'octets par document. Il est ramené à la dimension
+    /// des vecteurs s'il la dépasse ; les sous-espaces n'ont pas besoin d'avoir la même taille.
+    pub subspaces: usize,
+    /// Nombre de centroïdes par sous-espace, entre 1 et 256.
+    pub centroids: usize,
+    /// Nombre de vecteurs tirés au hasard pour apprendre les dictionnaires ; `None` utilise toute la collection.
+    pub sample_size: Option<usize>,
+    /// Nombre maximal d'itérations des k-moyennes.
+    pub iterations: usize,
+    /// Nombre de candidats reclassés avec leurs vecteurs d'origine (au moins `k`) ;
+    /// `None` retourne directement les scores approchés.
+    pub rerank: Option<usize>,
+}
+
+impl Default for PqParams {
+    fn default() -> Self {
+        PqParams {
+            subspaces: 8,
+            centroids: MAX_CENTROIDS,
+            sample_size: Some(65_536),
+            iterations: 20,
+            rerank: None,
+        }
+    }
+}
+
+/// # Structure: `ProductQuantizer`
+///
+/// Les dictionnaires appris et le code de chaque document d'une collection.
+pub struct ProductQuantizer {
+    params: PqParams,
+    metric: Metric,
+    /// Les bornes des sous-espaces : le sous-espace `j` couvre les coordonnées `bounds[j]..bounds[j + 1]`.
+    bounds: Vec<usize>,
+    /// `codebooks[j][c]` est le centroïde `c` du sous-espace `j`.
+    codebooks: Vec<Vec<Vec<f32>>>,
+    /// Le code de chaque document : `subspaces` octets.
+    codes: Codes,
+}
+
+impl ProductQuantizer {
+    /// Apprend les dictionnaires sur `vectors` (ou sur un échantillon, selon `params`) puis encode
+    /// chaque document. Pour la similarité cosinus, ce sont les vecteurs normalisés qui sont encodés.
+    ///
+    /// Sans aucun vecteur, aucun dictionnaire ne peut être appris : le quantificateur n'encode
+    /// alors rien (voir [`ProductQuantizer::is_trained`]).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let pq = ProductQuantizer::train(PqParams { subspaces: 16, ..Default::default() }, Metric::Cosine, &vectors);
+    /// ```
+    pub fn train(params: PqParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let mut rng = SplitMix64::new(TRAINING_SEED);
+        let sample = kmeans::sample(&mut rng, vectors, params.sample_size);
+        let dimension = sample.first().map_or(0, |vector| vector.len());
+        let subspaces = params.subspaces.clamp(1, dimension.max(1));
+        let bounds: Vec<usize> = (0..=subspaces).map(|j| j * dimension / subspaces).collect();
+
+        let normalized: Vec<Vec<f32>> = match metric {
+            Metric::Cosine => sample.iter().map(|vector| crate::normalized(vector.to_vec())).collect(),
+            _ => Vec::new(),
+        };
+        let training: Vec<&[f32]> = match metric {
+            Metric::Cosine => normalized.iter().map(Vec::as_slice).collect(),
+            _ => sample,
+        };
+        let centroids = params.centroids.clamp(1, MAX_CENTROIDS);
+        let codebooks = if dimension == 0 {
+            Vec::new()
+        } else {
+            bounds
+                .windows(2)
+                .map(|bound| {
+                    let subvectors: Vec<&[f32]> = training.iter().map(|vector| &vector[bound[0]..bound[1]]).collect();
+                    kmeans::train(&mut rng, Metric::Euclidean, &subvectors, centroids, params.iterations)
+                })
+                .collect()
+        };
+
+        let mut quantizer = ProductQuantizer {
+            params,
+            metric,
+            codes: Codes::new(bounds.len() - 1),
+            bounds,
+            codebooks,
+        };
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        for key in keys {
+            quantizer.insert(key, vectors);
+        }
+        quantizer
+    }
+
+    /// Les paramètres du quantificateur.
+    pub fn params(&self) -> PqParams {
+        self.params
+    }
+
+    /// Indique si les dictionnaires ont pu être appris, c'est-à-dire si la collection contenait
+    /// au moins un vecteur lors de l'apprentissage.
+    pub fn is_trained(&self) -> bool {
+        !self.codebooks.is_empty()
+    }
+
+    /// Nombre de sous-espaces, c'est-à-dire d'octets occupés par le code d'un document.
+    pub fn code_bytes(&self) -> usize {
+        self.codes.size()
+    }
+
+    /// Mémoire occupée par le quantificateur, en octets : les codes, l'identifiant et la position
+    /// de chaque document, et les dictionnaires.
+    pub fn memory_bytes(&self) -> usize {
+        let codebooks: usize = self.codebooks.iter().flatten().map(|centroid| centroid.len()).sum();
+        self.codes.memory_bytes() + codebooks * std::mem::size_of::<f32>()
+    }
+
+    /// Encode le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    /// Le code d'un document déjà encodé est remplacé.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        let vector = match vectors.get(&key) {
+            Some(vector) if self.is_trained() => vector,
+            _ => return,
+        };
+        let code = self.encode(vector);
+        self.codes.insert(key, &code);
+    }
+
+    /// Retire le code d'un document.
+    pub fn remove(&mut self, key: &DocumentId) {
+        self.codes.remove(key);
+    }
+
+    /// Recherche les `k` documents dont le score approché avec `query` est le meilleur, puis,
+    /// si [`PqParams::rerank`] est donné, reclasse les meilleurs candidats avec leurs vecteurs d'origine.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `vectors`: Les vecteurs de la collection, utilisés pour le reclassement.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score (approché, ou exact s'ils ont été reclassés),
+    ///   du plus proche au plus éloigné selon la métrique.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &VectorStore,
+        accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>,
+    ) -> Vector {
+        let candidates = self.params.rerank.map_or(k, |rerank| rerank.max(k));
+        let table = self.distance_table(query);
+        let query_norm = simd::norm(query);
+        let hits = self.codes.top_k(self.metric, candidates, accept, |code| {
+            let sum: f32 = code
+                .iter()
+                .enumerate()
+                .map(|(j, &c)| table[j * MAX_CENTROIDS + c as usize])
+                .sum();
+            match self.metric {
+                Metric::Cosine => cosine_from_dot_product(sum, query_norm, 1.0),
+                Metric::Euclidean => sum.max(0.0).sqrt(),
+                Metric::Dot | Metric::Manhattan | Metric::Hamming => sum,
+            }
+        });
+        match self.params.rerank {
+            Some(_) => quantizer::rescore(self.metric, query, k, hits, vectors),
+            None => hits,
+        }
+    }
+
+    /// Remplace chaque sous-vecteur par le numéro de son centroïde le plus proche.
+    fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        let normalized;
+        let vector = match self.metric {
+            Metric::Cosine => {
+                normalized = crate::normalized(vector.to_vec());
+                normalized.as_slice()
+            }
+            _ => vector,
+        };
+        self.bounds
+            .windows(2)
+            .zip(&self.codebooks)
+            .map(|(bound, codebook)| nearest_centroid(Metric::Euclidean, codebook, &vector[bound[0]..bound[1]]).0 as u8)
+            .collect()
+    }
+
+    /// Les scores partiels de la requête avec chaque centroïde : `table[j * MAX_CENTROIDS + c]`
+    /// pour le centroïde `c` du sous-espace `j`.
+    fn distance_table(&self, query: &[f32]) -> Vec<f32> {
+        let mut table = vec![0.0; self.codebooks.len() * MAX_CENTROIDS];
+        for (j, (bound, codebook)) in self.bounds.windows(2).zip(&self.codebooks).enumerate() {
+            let subquery = &query[bound[0]..bound[1]];
+            for (c, centroid) in codebook.iter().enumerate() {
+                table[j * MAX_CENTROIDS + c] = match self.metric {
+                    Metric::Cosine | Metric::Dot => simd::dot(subquery, centroid),
+                    Metric::Euclidean => simd::squared_l2(subquery, centroid),
+                    Metric::Manhattan | Metric::Hamming => self.metric.score(subquery, centroid),
+                };
+            }
+        }
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::topk::TopK;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    fn random_vectors(count: usize, dimension: usize, seed: u64) -> VectorStore {
+        let mut rng = SplitMix64::new(seed);
+        let mut vectors = VectorStore::default();
+        for n in 0..count {
+            let vector = (0..dimension).map(|_| rng.next_gaussian() as f32).collect();
+            vectors.insert(id(n as u128), vector).unwrap();
+        }
+        vectors
+    }
+
+    fn exact(metric: Metric, vectors: &VectorStore, query: &[f32], k: usize) -> Vector {
+        let mut top = TopK::new(metric, k);
+        for (key, vector) in vectors.iter() {
+            top.push(*key, metric.score(query, vector));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Reconstruit un vecteur à partir de son code : la suite des centroïdes de ses sous-espaces.
+    fn decode(quantizer: &ProductQuantizer, code: &[u8]) -> Vec<f32> {
+        code.iter()
+            .zip(&quantizer.codebooks)
+            .flat_map(|(&c, codebook)| codebook[c as usize].iter().copied())
+            .collect()
+    }
+
+    /// Proportion des `k` vrais plus proches voisins retrouvés.
+    fn recall(quantizer: &ProductQuantizer, vectors: &VectorStore, k: usize) -> f64 {
+        let queries = random_vectors(20, 16, 99);
+        let mut found = 0;
+        for query in queries.values() {
+            let expected: HashSet<DocumentId> =
+                exact(quantizer.metric, vectors, query, k).into_iter().map(|(key, _)| key).collect();
+            let hits = quantizer.search(query, k, vectors, None);
+            found += hits.iter().filter(|(key, _)| expected.contains(key)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn codes_of_the_training_vectors_decode_exactly() {
+        // Pas plus de vecteurs que de centroïdes : chaque sous-vecteur appris devient un centroïde.
+        let vectors = random_vectors(40, 7, 1);
+        let quantizer = ProductQuantizer::train(PqParams { subspaces: 3, ..Default::default() }, Metric::Euclidean, &vectors);
+        assert_eq!(quantizer.bounds, [0, 2, 4, 7]);
+        assert_eq!(quantizer.code_bytes(), 3);
+        for (_, vector) in vectors.iter() {
+            assert_eq!(decode(&quantizer, &quantizer.encode(vector)), vector);
+        }
+
+        // Les scores approchés sont alors les scores exacts.
+        let query = vectors.get(&id(3)).unwrap();
+        let hits = quantizer.search(query, 10, &vectors, None);
+        for (hit, expected) in hits.iter().zip(exact(Metric::Euclidean, &vectors, query, 10)) {
+            assert_eq!(hit.0, expected.0);
+            assert!((hit.1 - expected.1).abs() < 1e-4, "{:?} {:?}", hit, expected);
+        }
+    }
+
+    #[test]
+    fn reconstruction_error_shrinks_with_the_centroids() {
+        let vectors = random_vectors(1_000, 16, 2);
+        let error = |centroids| {
+            let quantizer = ProductQuantizer::train(PqParams { centroids, ..Default::default() }, Metric::Euclidean, &vectors);
+            let total: f32 = vectors
+                .values()
+                .map(|vector| simd::squared_l2(vector, &decode(&quantizer, &quantizer.encode(vector))))
+                .sum();
+            total / vectors.len() as f32
+        };
+        let (coarse, fine) = (error(4), error(64));
+        assert!(fine < coarse, "64 centroïdes : {}, 4 centroïdes : {}", fine, coarse);
+        // Une coordonnée gaussienne a une variance de 1 : l'erreur reste bien en dessous de la dimension.
+        assert!(fine < 8.0, "{}", fine);
+    }
+
+    #[test]
+    fn reranking_restores_the_recall_and_the_exact_scores() {
+        let vectors = random_vectors(2_000, 16, 3);
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot] {
+            let params = PqParams { centroids: 64, ..Default::default() };
+            let approximate = recall(&ProductQuantizer::train(params, metric, &vectors), &vectors, 10);
+            let reranked = ProductQuantizer::train(PqParams { rerank: Some(200), ..params }, metric, &vectors);
+            let rerank = recall(&reranked, &vectors, 10);
+            assert!(rerank >= approximate && rerank >= 0.9, "{} : {} puis {}", metric, approximate, rerank);
+            assert!(approximate >= 0.3, "{} : {}", metric, approximate);
+
+            let query = vectors.get(&id(7)).unwrap();
+            for (key, score) in reranked.search(query, 10, &vectors, None) {
+                assert_eq!(score, metric.score(query, vectors.get(&key).unwrap()));
+            }
+        }
+    }
+
+    #[test]
+    fn inserts_and_removals_update_the_codes() {
+        let mut vectors = random_vectors(300, 16, 4);
+        let mut quantizer = ProductQuantizer::train(PqParams { rerank: Some(50), ..Default::default() }, Metric::Euclidean, &vectors);
+        let query: Vec<f32> = vec![25.0; 16];
+        vectors.insert(id(1_000), query.clone()).unwrap();
+        quantizer.insert(id(1_000), &vectors);
+        assert_eq!(quantizer.search(&query, 1, &vectors, None), [(id(1_000), 0.0)]);
+
+        quantizer.remove(&id(1_000));
+        assert!(quantizer.search(&query, 300, &vectors, None).iter().all(|(key, _)| *key != id(1_000)));
+        let accept = |key: &DocumentId| key.as_u128().is_multiple_of(2);
+        let hits = quantizer.search(&query, 20, &vectors, Some(&accept));
+        assert!(hits.len() == 20 && hits.iter().all(|(key, _)| accept(key)));
+
+        let empty = ProductQuantizer::train(PqParams::default(), Metric::Euclidean, &VectorStore::default());
+        assert!(!empty.is_trained());
+        assert!(empty.search(&query, 5, &vectors, None).is_empty());
+    }
+}
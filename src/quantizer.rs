@@ -0,0 +1,279 @@
+//! # Module: `quantizer`
+//!
+//! La quantification qu'une [`Collection`](crate::Collection) peut appliquer à ses vecteurs :
+//! par produit ([`ProductQuantizer`]), scalaire ([`ScalarQuantizer`]) ou binaire ([`BinaryQuantizer`]). La collection maintient
+//! les codes à jour à chaque écriture et les interroge à travers l'énumération [`Quantizer`].
+//!
+//! Les quantificateurs rangent leurs codes dans un [`Codes`] : des codes de taille fixe,
+//! bout à bout, parcourus en parallèle lors d'une recherche.
+
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::bq::{BinaryQuantizer, BqParams};
+use crate::metric::Metric;
+use crate::pool::WorkerPool;
+use crate::pq::{PqParams, ProductQuantizer};
+use crate::sq::{ScalarQuantizer, SqParams};
+use crate::topk::TopK;
+use crate::vectors::VectorStore;
+use crate::{DocumentId, Vector, PARALLEL_SCAN_THRESHOLD};
+
+/// # Énumération: `QuantizerConfig`
+///
+/// Le type d'un quantificateur et ses paramètres : de quoi le reconstruire. C'est ce que le journal
+/// et les instantanés conservent d'un quantificateur, qui est réappris à l'ouverture de la base.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum QuantizerConfig {
+    Product(PqParams),
+    Scalar(SqParams),
+    Binary(BqParams),
+}
+
+/// # Énumération: `Quantizer`
+///
+/// Un quantificateur : une représentation compacte des vecteurs, sur laquelle les scores sont approchés.
+pub enum Quantizer {
+    Product(ProductQuantizer),
+    Scalar(ScalarQuantizer),
+    Binary(BinaryQuantizer),
+}
+
+impl QuantizerConfig {
+    /// Nom court du type de quantification, tel qu'il est affiché par `stats`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            QuantizerConfig::Product(_) => "pq",
+            QuantizerConfig::Scalar(params) => params.bits.name(),
+            QuantizerConfig::Binary(_) => "binary",
+        }
+    }
+}
+
+impl Quantizer {
+    /// Apprend un quantificateur selon `config` sur tous les vecteurs d'une collection et encode chaque document.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let quantizer = Quantizer::build(QuantizerConfig::Product(PqParams::default()), Metric::Cosine, &vectors);
+    /// ```
+    pub fn build(config: QuantizerConfig, metric: Metric, vectors: &VectorStore) -> Self {
+        match config {
+            QuantizerConfig::Product(params) => Quantizer::Product(ProductQuantizer::train(params, metric, vectors)),
+            QuantizerConfig::Scalar(params) => Quantizer::Scalar(ScalarQuantizer::train(params, metric, vectors)),
+            QuantizerConfig::Binary(params) => Quantizer::Binary(BinaryQuantizer::build(params, metric, vectors)),
+        }
+    }
+
+    /// Le type et les paramètres du quantificateur.
+    pub fn config(&self) -> QuantizerConfig {
+        match self {
+            Quantizer::Product(quantizer) => QuantizerConfig::Product(quantizer.params()),
+            Quantizer::Scalar(quantizer) => QuantizerConfig::Scalar(quantizer.params()),
+            Quantizer::Binary(quantizer) => QuantizerConfig::Binary(quantizer.params()),
+        }
+    }
+
+    /// Nom court du type de quantification, tel qu'il est affiché par `stats`.
+    pub fn name(&self) -> &'static str {
+        self.config().name()
+    }
+
+    /// Indique si le quantificateur a pu être appris (sur une collection non vide).
+    pub fn is_trained(&self) -> bool {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.is_trained(),
+            Quantizer::Scalar(quantizer) => quantizer.is_trained(),
+            Quantizer::Binary(quantizer) => quantizer.is_trained(),
+        }
+    }
+
+    /// Taille du code d'un document, en octets.
+    pub fn code_bytes(&self) -> usize {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.code_bytes(),
+            Quantizer::Scalar(quantizer) => quantizer.code_bytes(),
+            Quantizer::Binary(quantizer) => quantizer.code_bytes(),
+        }
+    }
+
+    /// Mémoire occupée par le quantificateur, en octets.
+    pub fn memory_bytes(&self) -> usize {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.memory_bytes(),
+            Quantizer::Scalar(quantizer) => quantizer.memory_bytes(),
+            Quantizer::Binary(quantizer) => quantizer.memory_bytes(),
+        }
+    }
+
+    /// Encode le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.insert(key, vectors),
+            Quantizer::Scalar(quantizer) => quantizer.insert(key, vectors),
+            Quantizer::Binary(quantizer) => quantizer.insert(key, vectors),
+        }
+    }
+
+    /// Encode un lot de documents.
+    pub fn insert_many(&mut self, keys: &[DocumentId], vectors: &VectorStore) {
+        for key in keys {
+            self.insert(*key, vectors);
+        }
+    }
+
+    /// Retire le code d'un document.
+    pub fn remove(&mut self, key: &DocumentId) {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.remove(key),
+            Quantizer::Scalar(quantizer) => quantizer.remove(key),
+            Quantizer::Binary(quantizer) => quantizer.remove(key),
+        }
+    }
+
+    /// Recherche les `k` documents les plus proches de `query` à partir de leurs codes
+    /// (voir [`ProductQuantizer::search`], [`ScalarQuantizer::search`] et [`BinaryQuantizer::search`]).
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &VectorStore,
+        accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>,
+    ) -> Vector {
+        match self {
+            Quantizer::Product(quantizer) => quantizer.search(query, k, vectors, accept),
+            Quantizer::Scalar(quantizer) => quantizer.search(query, k, vectors, accept),
+            Quantizer::Binary(quantizer) => quantizer.search(query, k, vectors, accept),
+        }
+    }
+}
+
+/// # Structure: `Codes`
+///
+/// Les codes des documents, tous de la même taille, rangés bout à bout. Supprimer un code
+/// déplace le dernier à sa place : les codes restent contigus.
+pub struct Codes {
+    /// Taille d'un code, en octets.
+    size: usize,
+    bytes: Vec<u8>,
+    /// Le document de chaque code, dans le même ordre que `bytes`.
+    keys: Vec<DocumentId>,
+    /// La position de chaque document dans `keys`.
+    positions: HashMap<DocumentId, usize>,
+}
+
+impl Codes {
+    /// Crée un ensemble vide de codes de `size` octets.
+    pub fn new(size: usize) -> Self {
+        Codes {
+            size,
+            bytes: Vec::new(),
+            keys: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+
+    /// Taille d'un code, en octets.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Mémoire occupée par les codes, l'identifiant et la position de chaque document, en octets.
+    pub fn memory_bytes(&self) -> usize {
+        self.bytes.len()
+            + self.keys.len() * std::mem::size_of::<DocumentId>()
+            + self.positions.len() * std::mem::size_of::<(DocumentId, usize)>()
+    }
+
+    /// Enregistre le code d'un document, en remplaçant son ancien code s'il en avait un.
+    pub fn insert(&mut self, key: DocumentId, code: &[u8]) {
+        match self.positions.get(&key) {
+            Some(&position) => self.bytes[position * self.size..(position + 1) * self.size].copy_from_slice(code),
+            None => {
+                self.positions.insert(key, self.keys.len());
+                self.keys.push(key);
+                self.bytes.extend_from_slice(code);
+            }
+        }
+    }
+
+    /// Retire le code d'un document ; le dernier code prend sa place.
+    pub fn remove(&mut self, key: &DocumentId) {
+        let position = match self.positions.remove(key) {
+            Some(position) => position,
+            None => return,
+        };
+        let last = self.keys.len() - 1;
+        if position != last {
+            self.bytes
+                .copy_within(last * self.size..(last + 1) * self.size, position * self.size);
+            self.keys[position] = self.keys[last];
+            self.positions.insert(self.keys[position], position);
+        }
+        self.keys.pop();
+        self.bytes.truncate(last * self.size);
+    }
+
+    /// Les `k` meilleurs documents selon le score que `score` calcule à partir de leur code.
+    /// Les codes sont répartis entre les threads du [`WorkerPool`] s'ils sont nombreux.
+    ///
+    /// # Paramètres
+    /// - `metric`: La métrique qui classe les scores.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés sont évalués.
+    /// - `score`: Le score approché d'un document à partir de son code.
+    pub fn top_k<F>(&self, metric: Metric, k: usize, accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>, score: F) -> Vector
+    where
+        F: Fn(&[u8]) -> f32 + Sync,
+    {
+        let pool = WorkerPool::global();
+        if self.keys.len() < PARALLEL_SCAN_THRESHOLD || pool.workers() == 1 {
+            return self.scan(metric, k, 0..self.keys.len(), accept, &score);
+        }
+        let chunk_size = self.keys.len().div_ceil(pool.workers());
+        let partials = pool.map(self.keys.len().div_ceil(chunk_size), |i| {
+            let end = ((i + 1) * chunk_size).min(self.keys.len());
+            self.scan(metric, k, i * chunk_size..end, accept, &score)
+        });
+        let mut top = TopK::new(metric, k);
+        for partial in partials {
+            top.extend(partial);
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Calcule séquentiellement les scores des codes des positions données et retourne les `k` meilleurs.
+    fn scan<F>(
+        &self,
+        metric: Metric,
+        k: usize,
+        positions: Range<usize>,
+        accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>,
+        score: &F,
+    ) -> Vector
+    where
+        F: Fn(&[u8]) -> f32,
+    {
+        let mut top = TopK::new(metric, k);
+        for position in positions {
+            let key = &self.keys[position];
+            if accept.is_some_and(|accept| !accept(key)) {
+                continue;
+            }
+            top.push(*key, score(&self.bytes[position * self.size..(position + 1) * self.size]));
+        }
+        top.into_sorted_vec()
+    }
+}
+
+/// Reclasse des candidats trouvés sur les codes avec leurs vecteurs d'origine et garde les `k` meilleurs.
+pub fn rescore(metric: Metric, query: &[f32], k: usize, candidates: Vector, vectors: &VectorStore) -> Vector {
+    let mut top = TopK::new(metric, k);
+    for (key, _) in candidates {
+        if let Some(vector) = vectors.get(&key) {
+            top.push(key, metric.score(query, vector));
+        }
+    }
+    top.into_sorted_vec()
+}
@@ -0,0 +1,41 @@
+//! # Module: `rng`
+//!
+//! Petit générateur pseudo-aléatoire *SplitMix64*, partagé par les index qui ont besoin de hasard
+//! (niveaux des nœuds HNSW, échantillons et initialisation des k-moyennes, hyperplans LSH).
+//! Avec une graine fixe, la construction d'un index est reproductible d'une exécution à l'autre.
+
+/// # Structure: `SplitMix64`
+///
+/// Générateur rapide et de bonne qualité statistique, mais qui n'est pas cryptographique.
+pub struct SplitMix64(u64);
+
+impl SplitMix64 {
+    /// Crée un générateur à partir d'une graine.
+    pub fn new(seed: u64) -> Self {
+        SplitMix64(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Retourne un flottant uniforme dans l'intervalle `]0, 1]`.
+    pub fn next_unit(&mut self) -> f64 {
+        ((self.next_u64() >> 11) + 1) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Retourne un entier uniforme dans l'intervalle `[0, bound[` (`bound` doit être non nul).
+    pub fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+
+    /// Retourne un flottant tiré selon la loi normale centrée réduite (méthode de Box-Muller).
+    pub fn next_gaussian(&mut self) -> f64 {
+        let radius = (-2.0 * self.next_unit().ln()).sqrt();
+        radius * (std::f64::consts::TAU * self.next_unit()).cos()
+    }
+}
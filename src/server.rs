@@ -0,0 +1,846 @@
+//! # Module: `server`
+//!
+//! Un serveur HTTP/1.1 minimal exposant la [`Database`] sous forme d'API REST/JSON.
+//!
+//! | Méthode  | Chemin                                   | Action                                   |
+//! |----------|------------------------------------------|------------------------------------------|
+//! | `GET`    | `/collections`                           | Liste les collections                    |
+//! | `POST`   | `/collections`                           | Crée une collection                      |
+//! | `GET`    | `/collections/{nom}`                     | Décrit une collection                    |
+//! | `DELETE` | `/collections/{nom}`                     | Supprime une collection                  |
+//! | `POST`   | `/collections/{nom}/documents`           | Ajoute un document (identifiant généré)  |
+//! | `DELETE` | `/collections/{nom}/documents`           | Supprime un lot de documents (`ids`)     |
+//! | `PUT`    | `/collections/{nom}/documents/{id}`      | Ajoute ou met à jour un document         |
+//! | `GET`    | `/collections/{nom}/documents/{id}`      | Récupère un document                     |
+//! | `DELETE` | `/collections/{nom}/documents/{id}`      | Supprime un document                     |
+//! | `POST`   | `/collections/{nom}/search`              | Recherche les plus proches voisins       |
+//! | `POST`   | `/collections/{nom}/search/batch`        | Recherche groupée (une requête par ligne de `queries`) |
+//! | `PUT`    | `/collections/{nom}/index`               | Construit ou remplace l'index (`type` : `hnsw`, `ivf` ou `lsh`) |
+//! | `PATCH`  | `/collections/{nom}/index`               | Règle les recherches de l'index (`ef_search`, `nprobe`) |
+//! | `DELETE` | `/collections/{nom}/index`               | Supprime l'index                         |
+//! | `POST`   | `/collections/{nom}/index/rebuild`       | Reconstruit l'index avec ses paramètres  |
+//! | `PUT`    | `/collections/{nom}/payload-index/{champ}` | Indexe un champ des métadonnées        |
+//!
+//! Les connexions sont servies par un nombre fixe de threads, qui partagent la base derrière un
+//! [`RwLock`] : les lectures et les recherches s'exécutent en parallèle, les écritures une à une.
+//! Chaque réponse ferme la connexion. Les erreurs sont renvoyées sous la forme `{"error": "..."}`
+//! avec le code HTTP correspondant (404, 409, 400...).
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::thread;
+use std::time::Duration;
+
+use uuid::Uuid;
+
+use crate::error::DbError;
+use crate::filter::Filter;
+use crate::hnsw::HnswParams;
+use crate::index::IndexConfig;
+use crate::ivf::IvfParams;
+use crate::json::{self, object};
+use crate::lsh::LshParams;
+use crate::payload::{Payload, Value};
+use crate::{CollectionConfig, Database, DocumentId, SearchOptions};
+
+/// Taille maximale acceptée pour le corps d'une requête.
+const MAX_BODY_SIZE: usize = 64 * 1024 * 1024;
+/// Taille maximale acceptée pour la ligne de requête et les en-têtes réunis.
+const MAX_HEADER_SIZE: u64 = 64 * 1024;
+/// Délai au-delà duquel une connexion silencieuse est abandonnée.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// Nombre de résultats renvoyés par une recherche qui ne précise pas `k`.
+const DEFAULT_K: usize = 10;
+
+/// Sert la base de données sur une socket déjà ouverte, jusqu'à l'arrêt du processus.
+///
+/// # Paramètres
+/// - `listener`: La socket d'écoute.
+/// - `db`: La base de données servie (en mémoire ou persistante).
+/// - `workers`: Le nombre de threads traitant les connexions (au moins un).
+///
+/// # Exemple
+///
+/// ```
+/// let listener = TcpListener::bind("127.0.0.1:8080")?;
+/// server::serve(listener, Database::open("data")?, 4);
+/// ```
+pub fn serve(listener: TcpListener, db: Database, workers: usize) {
+    let db = Arc::new(RwLock::new(db));
+    let (sender, receiver) = mpsc::channel::<TcpStream>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    let handles: Vec<_> = (0..workers.max(1))
+        .map(|_| {
+            let db = Arc::clone(&db);
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let stream = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                match stream {
+                    Ok(stream) => handle_connection(stream, &db),
+                    Err(_) => break,
+                }
+            })
+        })
+        .collect();
+
+    // Une erreur d'acceptation ne concerne que la connexion en cours : elle est ignorée.
+    for stream in listener.incoming().flatten() {
+        if sender.send(stream).is_err() {
+            break;
+        }
+    }
+
+    drop(sender);
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+/// Lit une requête, la traite et écrit la réponse. Une panique pendant le traitement
+/// est convertie en réponse 500 pour ne pas perdre le thread.
+fn handle_connection(mut stream: TcpStream, db: &RwLock<Database>) {
+    let _ = stream.set_read_timeout(Some(READ_TIMEOUT));
+    let response = panic::catch_unwind(AssertUnwindSafe(|| {
+        Request::read(&stream)
+            .and_then(|request| route(&request, db))
+            .unwrap_or_else(HttpError::into_response)
+    }))
+    .unwrap_or_else(|_| HttpError::new(500, "erreur interne du serveur").into_response());
+    let _ = response.write_to(&mut stream);
+}
+
+/// Aiguille une requête vers le traitement correspondant à sa méthode et à son chemin.
+fn route(request: &Request, db: &RwLock<Database>) -> Result<Response, HttpError> {
+    let segments = request.segments()?;
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+
+    match (request.method.as_str(), segments.as_slice()) {
+        ("GET", ["collections"]) => {
+            let db = read(db);
+            let collections = db
+                .collection_names()
+                .into_iter()
+                .map(|name| Ok(json::collection_info(name, db.get_collection(name)?)))
+                .collect::<Result<Vec<_>, DbError>>()?;
+            Ok(Response::new(200, object([("collections", Value::Array(collections))])))
+        }
+        ("POST", ["collections"]) => {
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let name = match body.get("name") {
+                Some(Value::String(name)) if !name.is_empty() => name.clone(),
+                _ => return Err(HttpError::new(400, "le champ 'name' doit être une chaîne non vide")),
+            };
+            let metric = match optional_str(body, "metric")? {
+                Some(metric) => metric.parse()?,
+                None => Default::default(),
+            };
+            let dimension = optional_usize(body, "dimension")?;
+            if dimension == Some(0) {
+                return Err(HttpError::new(400, "le champ 'dimension' doit être strictement positif"));
+            }
+
+            let normalize = optional_bool(body, "normalize")?.unwrap_or(false);
+            let mapped = optional_bool(body, "mapped")?.unwrap_or(false);
+
+            let mut db = write(db);
+            db.add_collection(name.clone(), CollectionConfig { metric, dimension, normalize, mapped })?;
+            Ok(Response::new(201, json::collection_info(&name, db.get_collection(&name)?)))
+        }
+        ("GET", ["collections", name]) => {
+            let db = read(db);
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        ("DELETE", ["collections", name]) => {
+            write(db).drop_collection(name)?;
+            Ok(Response::new(200, object([("deleted", Value::from(*name))])))
+        }
+        ("POST", ["collections", name, "documents"]) => {
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let key = match optional_str(body, "id")? {
+                Some(id) => parse_id(id)?,
+                None => Uuid::new_v4(),
+            };
+            let (vector, payload) = (vector_field(body, "vector")?, payload_field(body)?);
+            write(db).add_or_update(name, key, vector, payload)?;
+            Ok(Response::new(201, object([("id", Value::from(key.to_string()))])))
+        }
+        ("PUT", ["collections", name, "documents", id]) => {
+            let key = parse_id(id)?;
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let (vector, payload) = (vector_field(body, "vector")?, payload_field(body)?);
+            write(db).add_or_update(name, key, vector, payload)?;
+            Ok(Response::new(200, object([("id", Value::from(key.to_string()))])))
+        }
+        ("GET", ["collections", name, "documents", id]) => {
+            let key = parse_id(id)?;
+            let db = read(db);
+            let collection = db.get_collection(name)?;
+            let vector = collection.get(&key).ok_or_else(|| document_not_found(&key))?;
+            Ok(Response::new(
+                200,
+                object([
+                    ("id", Value::from(key.to_string())),
+                    ("vector", json::vector(vector)),
+                    ("payload", Value::Object(collection.get_payload(&key).cloned().unwrap_or_default())),
+                ]),
+            ))
+        }
+        ("DELETE", ["collections", name, "documents"]) => {
+            let body = request.json()?;
+            let keys = ids_field(as_object(&body)?)?;
+            let statuses = write(db).remove_many(name, &keys)?;
+            let results = keys
+                .iter()
+                .zip(statuses)
+                .map(|(key, status)| object([("id", Value::from(key.to_string())), ("status", Value::from(status.name()))]))
+                .collect();
+            Ok(Response::new(200, object([("results", Value::Array(results))])))
+        }
+        ("DELETE", ["collections", name, "documents", id]) => {
+            let key = parse_id(id)?;
+            let mut db = write(db);
+            if db.get_collection(name)?.get(&key).is_none() {
+                return Err(document_not_found(&key));
+            }
+            db.remove(name, &key)?;
+            Ok(Response::new(200, object([("deleted", Value::from(key.to_string()))])))
+        }
+        ("POST", ["collections", name, "search"]) => {
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let query = vector_field(body, "vector")?;
+            let k = optional_usize(body, "k")?;
+            let threshold = optional_f32(body, "threshold")?;
+            let filter = optional_str(body, "filter")?.map(Filter::parse).transpose()?;
+            let options = search_options(body, filter.as_ref())?;
+
+            let db = read(db);
+            // Avec un seuil, `k` devient un plafond facultatif sur le nombre de résultats.
+            let results = match threshold {
+                Some(threshold) => db.search_range(name, &query, threshold, k, options)?,
+                None => db.search_in_collection(name, &query, k.unwrap_or(DEFAULT_K), options)?,
+            };
+            Ok(Response::new(200, json::search_result(&results)))
+        }
+        ("POST", ["collections", name, "search", "batch"]) => {
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let queries = queries_field(body)?;
+            let k = optional_usize(body, "k")?.unwrap_or(DEFAULT_K);
+            let filter = optional_str(body, "filter")?.map(Filter::parse).transpose()?;
+            let options = search_options(body, filter.as_ref())?;
+
+            let results = read(db).search_batch(name, &queries, k, options)?;
+            let results = results.iter().map(json::search_result).collect();
+
+            Ok(Response::new(200, object([("results", Value::Array(results))])))
+        }
+        ("PUT", ["collections", name, "index"]) => {
+            let body = request.json()?;
+            let config = index_config(as_object(&body)?)?;
+            let mut db = write(db);
+            db.set_index(name, Some(config))?;
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        ("PATCH", ["collections", name, "index"]) => {
+            let body = request.json()?;
+            let body = as_object(&body)?;
+            let (ef_search, nprobe) = (optional_usize_at_least(body, "ef_search", 1)?, optional_usize_at_least(body, "nprobe", 1)?);
+            if ef_search.is_none() && nprobe.is_none() {
+                return Err(HttpError::new(400, "le champ 'ef_search' ou 'nprobe' est obligatoire"));
+            }
+            let mut db = write(db);
+            if db.get_collection(name)?.index.is_none() {
+                return Err(HttpError::new(409, &format!("la collection '{}' n'a pas d'index à régler", name)));
+            }
+            db.tune_index(name, ef_search, nprobe)?;
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        ("DELETE", ["collections", name, "index"]) => {
+            let mut db = write(db);
+            db.set_index(name, None)?;
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        ("POST", ["collections", name, "index", "rebuild"]) => {
+            let mut db = write(db);
+            if db.rebuild_index(name)?.is_none() {
+                return Err(HttpError::new(409, &format!("la collection '{}' n'a pas d'index à reconstruire", name)));
+            }
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        ("PUT", ["collections", name, "payload-index", field]) => {
+            let mut db = write(db);
+            db.create_payload_index(name, field)?;
+            Ok(Response::new(200, json::collection_info(name, db.get_collection(name)?)))
+        }
+        (_, ["collections"])
+        | (_, ["collections", _])
+        | (_, ["collections", _, "index"])
+        | (_, ["collections", _, "index", "rebuild"])
+        | (_, ["collections", _, "payload-index", _])
+        | (_, ["collections", _, "documents"])
+        | (_, ["collections", _, "documents", _])
+        | (_, ["collections", _, "search"])
+        | (_, ["collections", _, "search", "batch"]) => Err(HttpError::new(405, "méthode non autorisée")),
+        _ => Err(HttpError::new(404, "route inconnue")),
+    }
+}
+
+/// Verrouille la base en lecture. Un verrou empoisonné par une panique est récupéré :
+/// chaque opération de la base laisse les données dans un état cohérent avant de pouvoir paniquer.
+fn read(db: &RwLock<Database>) -> RwLockReadGuard<'_, Database> {
+    db.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Verrouille la base en écriture (voir [`read`]).
+fn write(db: &RwLock<Database>) -> RwLockWriteGuard<'_, Database> {
+    db.write().unwrap_or_else(PoisonError::into_inner)
+}
+
+fn document_not_found(key: &DocumentId) -> HttpError {
+    HttpError::new(404, &format!("le document '{}' n'existe pas", key))
+}
+
+fn parse_id(id: &str) -> Result<DocumentId, HttpError> {
+    Uuid::parse_str(id).map_err(|_| HttpError::new(400, &format!("identifiant de document invalide '{}'", id)))
+}
+
+fn as_object(body: &Value) -> Result<&Payload, HttpError> {
+    match body {
+        Value::Object(object) => Ok(object),
+        _ => Err(HttpError::new(400, "le corps de la requête doit être un objet JSON")),
+    }
+}
+
+/// Lit un vecteur obligatoire, donné sous forme de tableau de nombres.
+fn vector_field(body: &Payload, field: &str) -> Result<Vec<f32>, HttpError> {
+    body.get(field)
+        .and_then(json::parse_vector)
+        .ok_or_else(|| HttpError::new(400, &format!("le champ '{}' doit être un tableau de nombres", field)))
+}
+
+/// Lit le champ `ids` d'une suppression groupée : un tableau d'identifiants.
+fn ids_field(body: &Payload) -> Result<Vec<DocumentId>, HttpError> {
+    match body.get("ids") {
+        Some(Value::Array(ids)) => ids
+            .iter()
+            .map(|id| match id {
+                Value::String(id) => parse_id(id),
+                _ => Err(HttpError::new(400, "le champ 'ids' doit être un tableau de chaînes")),
+            })
+            .collect(),
+        _ => Err(HttpError::new(400, "le champ 'ids' doit être un tableau de chaînes")),
+    }
+}
+
+/// Lit le champ `queries` d'une recherche groupée : un tableau de vecteurs.
+fn queries_field(body: &Payload) -> Result<Vec<Vec<f32>>, HttpError> {
+    let invalid = || HttpError::new(400, "le champ 'queries' doit être un tableau de tableaux de nombres");
+    match body.get("queries") {
+        Some(Value::Array(queries)) => queries.iter().map(|query| json::parse_vector(query).ok_or_else(invalid)).collect(),
+        _ => Err(invalid()),
+    }
+}
+
+/// Lit le type d'un index (`type`) et ses paramètres ; un paramètre absent prend sa valeur par défaut.
+fn index_config(body: &Payload) -> Result<IndexConfig, HttpError> {
+    match optional_str(body, "type")? {
+        Some("hnsw") => {
+            let defaults = HnswParams::default();
+            Ok(IndexConfig::Hnsw(HnswParams {
+                m: optional_usize_at_least(body, "m", 2)?.unwrap_or(defaults.m),
+                ef_construction: optional_usize_at_least(body, "ef_construction", 1)?.unwrap_or(defaults.ef_construction),
+                ef_search: optional_usize_at_least(body, "ef_search", 1)?.unwrap_or(defaults.ef_search),
+            }))
+        }
+        Some("ivf") => {
+            let defaults = IvfParams::default();
+            Ok(IndexConfig::Ivf(IvfParams {
+                nlist: optional_usize_at_least(body, "nlist", 1)?.unwrap_or(defaults.nlist),
+                nprobe: optional_usize_at_least(body, "nprobe", 1)?.unwrap_or(defaults.nprobe),
+                sample_size: optional_usize_at_least(body, "sample_size", 1)?.or(defaults.sample_size),
+                iterations: defaults.iterations,
+            }))
+        }
+        Some("lsh") => {
+            let defaults = LshParams::default();
+            let bits = optional_usize(body, "bits")?.unwrap_or(defaults.bits);
+            if !(1..=64).contains(&bits) {
+                return Err(HttpError::new(400, "le champ 'bits' doit être un entier entre 1 et 64"));
+            }
+            Ok(IndexConfig::Lsh(LshParams {
+                bits,
+                tables: optional_usize_at_least(body, "tables", 1)?.unwrap_or(defaults.tables),
+                probes: optional_usize_at_least(body, "probes", 1)?.unwrap_or(defaults.probes),
+            }))
+        }
+        _ => Err(HttpError::new(400, "le champ 'type' doit valoir 'hnsw', 'ivf' ou 'lsh'")),
+    }
+}
+
+/// Lit les options communes aux recherches : `exact` et `with_payload`, faux par défaut.
+fn search_options<'a>(body: &Payload, filter: Option<&'a Filter>) -> Result<SearchOptions<'a>, HttpError> {
+    Ok(SearchOptions {
+        filter,
+        exact: optional_bool(body, "exact")?.unwrap_or(false),
+        with_payload: optional_bool(body, "with_payload")?.unwrap_or(false),
+    })
+}
+
+/// Lit les métadonnées facultatives d'un document ; un champ absent ou `null` donne des métadonnées vides.
+fn payload_field(body: &Payload) -> Result<Payload, HttpError> {
+    match body.get("payload") {
+        None | Some(Value::Null) => Ok(Payload::new()),
+        Some(Value::Object(payload)) => Ok(payload.clone()),
+        Some(_) => Err(HttpError::new(400, "le champ 'payload' doit être un objet JSON")),
+    }
+}
+
+fn optional_str<'a>(body: &'a Payload, field: &str) -> Result<Option<&'a str>, HttpError> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(HttpError::new(400, &format!("le champ '{}' doit être une chaîne", field))),
+    }
+}
+
+fn optional_bool(body: &Payload, field: &str) -> Result<Option<bool>, HttpError> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Bool(value)) => Ok(Some(*value)),
+        Some(_) => Err(HttpError::new(400, &format!("le champ '{}' doit être un booléen", field))),
+    }
+}
+
+fn optional_f32(body: &Payload, field: &str) -> Result<Option<f32>, HttpError> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(value)) if value.is_finite() => Ok(Some(*value as f32)),
+        Some(_) => Err(HttpError::new(400, &format!("le champ '{}' doit être un nombre", field))),
+    }
+}
+
+fn optional_usize(body: &Payload, field: &str) -> Result<Option<usize>, HttpError> {
+    match body.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Number(value)) if value.fract() == 0.0 && *value >= 0.0 && *value <= u32::MAX as f64 => {
+            Ok(Some(*value as usize))
+        }
+        Some(_) => Err(HttpError::new(400, &format!("le champ '{}' doit être un entier positif", field))),
+    }
+}
+
+/// Comme [`optional_usize`], en refusant une valeur inférieure à `minimum`.
+fn optional_usize_at_least(body: &Payload, field: &str, minimum: usize) -> Result<Option<usize>, HttpError> {
+    match optional_usize(body, field)? {
+        Some(value) if value < minimum => Err(HttpError::new(
+            400,
+            &format!("le champ '{}' doit être un entier d'au moins {}", field, minimum),
+        )),
+        value => Ok(value),
+    }
+}
+
+/// # Structure: `Request`
+///
+/// Une requête HTTP lue sur la connexion : méthode, cible (chemin et éventuelle chaîne de requête) et corps.
+struct Request {
+    method: String,
+    target: String,
+    body: Vec<u8>,
+}
+
+impl Request {
+    /// Lit la ligne de requête, les en-têtes et le corps (délimité par `Content-Length`).
+    fn read(stream: &TcpStream) -> Result<Request, HttpError> {
+        let mut reader = BufReader::new(stream);
+        let mut header_budget = MAX_HEADER_SIZE;
+
+        let request_line = read_line(&mut reader, &mut header_budget)?;
+        let mut parts = request_line.split(' ');
+        let (method, target) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(method), Some(target), Some(version), None) if version.starts_with("HTTP/1.") => {
+                (method.to_string(), target.to_string())
+            }
+            _ => return Err(HttpError::new(400, "ligne de requête invalide")),
+        };
+
+        let mut content_length = 0;
+        let mut expect_continue = false;
+        loop {
+            let line = read_line(&mut reader, &mut header_budget)?;
+            if line.is_empty() {
+                break;
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| HttpError::new(400, "en-tête invalide"))?;
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse()
+                        .map_err(|_| HttpError::new(400, "en-tête Content-Length invalide"))?;
+                }
+                "transfer-encoding" => {
+                    return Err(HttpError::new(411, "le corps doit être envoyé avec un en-tête Content-Length"));
+                }
+                "expect" => expect_continue = value.eq_ignore_ascii_case("100-continue"),
+                _ => {}
+            }
+        }
+
+        if content_length > MAX_BODY_SIZE {
+            return Err(HttpError::new(413, "corps de requête trop volumineux"));
+        }
+        if expect_continue && content_length > 0 {
+            let mut writer = stream;
+            writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").map_err(io_error)?;
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).map_err(io_error)?;
+
+        Ok(Request { method, target, body })
+    }
+
+    /// Découpe le chemin (sans la chaîne de requête) en segments décodés.
+    fn segments(&self) -> Result<Vec<String>, HttpError> {
+        let path = self.target.split('?').next().unwrap_or_default();
+        path.split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(|segment| percent_decode(segment).ok_or_else(|| HttpError::new(400, "chemin invalide")))
+            .collect()
+    }
+
+    /// Analyse le corps de la requête en JSON.
+    fn json(&self) -> Result<Value, HttpError> {
+        let text = std::str::from_utf8(&self.body)
+            .map_err(|_| HttpError::new(400, "le corps de la requête n'est pas en UTF-8"))?;
+        Ok(json::parse(text)?)
+    }
+}
+
+/// Lit une ligne terminée par `\r\n` (ou `\n`) en décomptant sa taille du budget des en-têtes.
+fn read_line(reader: &mut impl BufRead, budget: &mut u64) -> Result<String, HttpError> {
+    let mut line = Vec::new();
+    let read = reader.take(*budget).read_until(b'\n', &mut line).map_err(io_error)?;
+    *budget -= read as u64;
+    if line.last() != Some(&b'\n') {
+        return Err(if *budget == 0 {
+            HttpError::new(431, "en-têtes trop volumineux")
+        } else {
+            HttpError::new(400, "requête incomplète")
+        });
+    }
+    line.pop();
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line).map_err(|_| HttpError::new(400, "en-têtes invalides"))
+}
+
+/// Décode les séquences `%XX` d'un segment de chemin.
+fn percent_decode(segment: &str) -> Option<String> {
+    let bytes = segment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn io_error(error: io::Error) -> HttpError {
+    match error.kind() {
+        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut => HttpError::new(408, "délai de lecture dépassé"),
+        _ => HttpError::new(400, "requête incomplète"),
+    }
+}
+
+/// # Structure: `Response`
+///
+/// Une réponse HTTP dont le corps est un document JSON.
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn new(status: u16, body: Value) -> Self {
+        Response { status, body }
+    }
+
+    fn write_to(&self, stream: &mut impl Write) -> io::Result<()> {
+        let body = self.body.to_string();
+        let response = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason_phrase(self.status),
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes())?;
+        stream.flush()
+    }
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        411 => "Length Required",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+/// # Structure: `HttpError`
+///
+/// Une erreur à renvoyer au client : un code HTTP et un message.
+struct HttpError {
+    status: u16,
+    message: String,
+}
+
+impl HttpError {
+    fn new(status: u16, message: &str) -> Self {
+        HttpError {
+            status,
+            message: message.to_string(),
+        }
+    }
+
+    fn into_response(self) -> Response {
+        Response::new(self.status, object([("error", Value::from(self.message))]))
+    }
+}
+
+/// Associe à chaque [`DbError`] le code HTTP correspondant.
+impl From<DbError> for HttpError {
+    fn from(error: DbError) -> Self {
+        let status = match error {
+            DbError::CollectionNotFound(_) => 404,
+            DbError::CollectionExists(_) => 409,
+            DbError::EmptyCollection(_)
+            | DbError::DimensionMismatch { .. }
+            | DbError::InvalidValue { .. }
+            | DbError::EmptyVector
+            | DbError::InvalidFilter(_)
+            | DbError::InvalidJson(_)
+            | DbError::UnknownMetric(_)
+            | DbError::InvalidQuery { .. }
+            | DbError::InvalidBatch(_)
+            | DbError::InvalidLine { .. }
+            | DbError::Unsupported(_)
+            | DbError::InvalidSchema(_) => 400,
+            DbError::Io(_) | DbError::Corrupted(_) => 500,
+        };
+        HttpError {
+            status,
+            message: error.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+
+    /// Démarre un serveur sur un port libre, servant une base en mémoire.
+    fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, Database::new(), 2));
+        address
+    }
+
+    /// Envoie une requête brute et renvoie le code de la réponse et son corps JSON.
+    fn send(address: SocketAddr, request: &[u8]) -> (u16, Value) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, json::parse(body).unwrap())
+    }
+
+    fn request(address: SocketAddr, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let request = format!("{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}", method, path, body.len(), body);
+        send(address, request.as_bytes())
+    }
+
+    /// Suit un chemin de clés et d'indices dans un document JSON.
+    fn get<'a>(value: &'a Value, path: &[&str]) -> &'a Value {
+        path.iter().fold(value, |value, step| match value {
+            Value::Object(fields) => &fields[*step],
+            Value::Array(items) => &items[step.parse::<usize>().unwrap()],
+            _ => panic!("pas de '{}' dans {}", step, value),
+        })
+    }
+
+    fn ids(result: &Value) -> Vec<String> {
+        match get(result, &["hits"]) {
+            Value::Array(hits) => hits
+                .iter()
+                .map(|hit| match get(hit, &["id"]) {
+                    Value::String(id) => id.clone(),
+                    other => panic!("identifiant invalide : {}", other),
+                })
+                .collect(),
+            other => panic!("résultat invalide : {}", other),
+        }
+    }
+
+    fn id(n: u128) -> String {
+        Uuid::from_u128(n).to_string()
+    }
+
+    #[test]
+    fn collections_documents_and_searches() {
+        let server = start();
+        let (status, body) = request(server, "POST", "/collections", r#"{"name": "docs", "metric": "euclidean", "dimension": 2}"#);
+        assert_eq!(status, 201);
+        assert_eq!(get(&body, &["metric"]), &Value::from("euclidean"));
+
+        for n in 1..=4u128 {
+            let document = format!(r#"{{"vector": [{}, 0], "payload": {{"n": {}}}}}"#, n, n);
+            let (status, body) = request(server, "PUT", &format!("/collections/docs/documents/{}", id(n)), &document);
+            assert_eq!((status, get(&body, &["id"])), (200, &Value::from(id(n))));
+        }
+        let (status, body) = request(server, "POST", "/collections/docs/documents", r#"{"vector": [10, 0]}"#);
+        assert_eq!(status, 201);
+        let generated = get(&body, &["id"]).clone();
+
+        let (status, body) = request(server, "GET", &format!("/collections/docs/documents/{}", id(2)), "");
+        assert_eq!(status, 200);
+        assert_eq!(get(&body, &["vector", "0"]), &Value::Number(2.0));
+        assert_eq!(get(&body, &["payload", "n"]), &Value::Number(2.0));
+
+        let (status, body) = request(server, "POST", "/collections/docs/search", r#"{"vector": [0, 0], "k": 2}"#);
+        assert_eq!(status, 200);
+        assert_eq!(ids(&body), [id(1), id(2)]);
+        assert_eq!(get(&body, &["hits", "1", "score"]), &Value::Number(2.0));
+
+        // Avec un seuil inclusif, un filtre et les métadonnées.
+        let search = r#"{"vector": [0, 0], "threshold": 3, "filter": "n >= 2", "with_payload": true}"#;
+        let (status, body) = request(server, "POST", "/collections/docs/search", search);
+        assert_eq!(status, 200);
+        assert_eq!(ids(&body), [id(2), id(3)]);
+        assert_eq!(get(&body, &["hits", "1", "payload", "n"]), &Value::Number(3.0));
+
+        let (status, body) = request(server, "PUT", "/collections/docs/index", r#"{"type": "hnsw", "m": 8}"#);
+        assert_eq!(status, 200);
+        assert_eq!(get(&body, &["index", "m"]), &Value::Number(8.0));
+        let (status, body) = request(server, "PATCH", "/collections/docs/index", r#"{"ef_search": 20}"#);
+        assert_eq!((status, get(&body, &["index", "ef_search"])), (200, &Value::Number(20.0)));
+        let (status, body) = request(server, "PUT", "/collections/docs/index", r#"{"type": "ivf", "nlist": 2, "nprobe": 1}"#);
+        assert_eq!((status, get(&body, &["index", "nlist"])), (200, &Value::Number(2.0)));
+        let (status, body) = request(server, "PATCH", "/collections/docs/index", r#"{"nprobe": 2}"#);
+        assert_eq!((status, get(&body, &["index", "nprobe"])), (200, &Value::Number(2.0)));
+        let (status, body) = request(server, "POST", "/collections/docs/index/rebuild", "");
+        assert_eq!((status, get(&body, &["index", "type"])), (200, &Value::from("ivf")));
+        let (status, body) = request(server, "POST", "/collections/docs/search", r#"{"vector": [0, 0], "k": 1}"#);
+        assert_eq!((status, ids(&body)), (200, vec![id(1)]));
+        let (status, body) = request(server, "DELETE", "/collections/docs/index", "");
+        assert_eq!((status, get(&body, &["index"])), (200, &Value::Null));
+
+        let batch = r#"{"queries": [[0, 0], [10, 0]], "k": 1}"#;
+        let (status, body) = request(server, "POST", "/collections/docs/search/batch", batch);
+        assert_eq!(status, 200);
+        assert_eq!(ids(get(&body, &["results", "0"])), [id(1)]);
+        assert_eq!(Value::from(ids(get(&body, &["results", "1"])).remove(0)), generated);
+
+        let (status, body) = request(server, "PUT", "/collections/docs/payload-index/n", "");
+        assert_eq!((status, get(&body, &["payload_index", "0"])), (200, &Value::from("n")));
+
+        let deletion = format!(r#"{{"ids": ["{}", "{}"]}}"#, id(4), id(9));
+        let (status, body) = request(server, "DELETE", "/collections/docs/documents", &deletion);
+        assert_eq!(status, 200);
+        assert_eq!(get(&body, &["results", "0", "status"]), &Value::from("removed"));
+        assert_eq!(get(&body, &["results", "1", "status"]), &Value::from("not_found"));
+
+        let (status, _) = request(server, "DELETE", &format!("/collections/docs/documents/{}", id(1)), "");
+        assert_eq!(status, 200);
+        let (status, body) = request(server, "GET", "/collections/docs", "");
+        assert_eq!((status, get(&body, &["documents"])), (200, &Value::Number(3.0)));
+
+        let (status, body) = request(server, "GET", "/collections", "");
+        assert_eq!((status, get(&body, &["collections", "0", "name"])), (200, &Value::from("docs")));
+        let (status, _) = request(server, "DELETE", "/collections/docs", "");
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn errors_carry_their_status_code() {
+        let server = start();
+        let (status, _) = request(server, "POST", "/collections", r#"{"name": "docs", "dimension": 2}"#);
+        assert_eq!(status, 201);
+
+        let cases = [
+            ("GET", "/collections/absent".to_string(), "", 404),
+            ("GET", format!("/collections/docs/documents/{}", id(1)), "", 404),
+            ("GET", "/inconnue".to_string(), "", 404),
+            ("POST", "/collections".to_string(), r#"{"name": "docs"}"#, 409),
+            ("PATCH", "/collections/docs/index".to_string(), r#"{"ef_search": 10}"#, 409),
+            ("PATCH", "/collections/docs/index".to_string(), "{}", 400),
+            ("POST", "/collections/docs/index/rebuild".to_string(), "", 409),
+            ("PATCH", "/collections".to_string(), "", 405),
+            ("GET", "/collections/docs/search".to_string(), "", 405),
+            ("POST", "/collections/docs/search/batch".to_string(), r#"{"queries": [[1, 2], "x"]}"#, 400),
+            ("DELETE", "/collections/docs/documents".to_string(), r#"{"ids": [1]}"#, 400),
+            ("PUT", format!("/collections/docs/documents/{}", id(1)), r#"{"vector": [1, 2, 3]}"#, 400),
+            ("PUT", "/collections/docs/documents/pas-un-uuid".to_string(), r#"{"vector": [1, 2]}"#, 400),
+            ("POST", "/collections/docs/search".to_string(), r#"{"vector": [1, 2], "filter": "n >"}"#, 400),
+            ("POST", "/collections/docs/search".to_string(), r#"{"vector": [1, 2], "threshold": "x"}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "arbre"}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "hnsw", "m": 1}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "hnsw", "ef_search": 0}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "ivf", "nlist": 0}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "ivf", "nprobe": 0}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "lsh", "tables": 0}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "lsh", "probes": 0}"#, 400),
+            ("PUT", "/collections/docs/index".to_string(), r#"{"type": "lsh", "bits": 65}"#, 400),
+            ("POST", "/collections".to_string(), r#"{"name": "x", "metric": 3"#, 400),
+            ("POST", "/collections".to_string(), "[1, 2]", 400),
+        ];
+        for (method, path, body, expected) in cases {
+            let (status, body) = request(server, method, &path, body);
+            assert_eq!(status, expected, "{} {}", method, path);
+            assert!(matches!(get(&body, &["error"]), Value::String(message) if !message.is_empty()));
+        }
+
+        // Le corps annoncé est refusé avant d'être lu.
+        let oversized = format!("POST /collections HTTP/1.1\r\nContent-Length: {}\r\n\r\n", MAX_BODY_SIZE + 1);
+        assert_eq!(send(server, oversized.as_bytes()).0, 413);
+        assert_eq!(send(server, b"GET /collections\r\n\r\n").0, 400);
+        assert_eq!(send(server, b"GET /collections HTTP/1.1\r\nContent-Length: abc\r\n\r\n").0, 400);
+        assert_eq!(send(server, b"POST /collections HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n").0, 411);
+
+        // Le serveur répond toujours après les requêtes refusées.
+        let (status, body) = request(server, "GET", "/collections/docs", "");
+        assert_eq!((status, get(&body, &["documents"])), (200, &Value::Number(0.0)));
+    }
+}
@@ -0,0 +1,690 @@
+//! # Module: `simd`
+//!
+//! Les noyaux de calcul des métriques (produit scalaire, distance euclidienne au carré, normes),
+//! écrits avec les instructions vectorielles du processeur : SSE, AVX2 ou AVX-512 sur x86_64,
+//! NEON sur aarch64. Le jeu d'instructions est choisi une seule fois, au premier appel, d'après les
+//! capacités du processeur ; la version scalaire sert sur les autres architectures.
+//!
+//! Les versions vectorielles additionnent les termes dans un autre ordre que la version scalaire :
+//! leurs résultats peuvent différer de quelques ulp. Pour un même jeu d'instructions, en revanche,
+//! [`dot_and_norms`] accumule chacune de ses trois sommes exactement comme [`dot`] : une norme
+//! calculée à l'avance avec [`norm`] donne le même score cosinus, au bit près, que le calcul fusionné.
+
+use std::fmt;
+use std::sync::OnceLock;
+
+/// # Énumération: `Backend`
+///
+/// Le jeu d'instructions utilisé par les noyaux de calcul. Seuls les jeux d'instructions
+/// de l'architecture cible sont définis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Scalar,
+    #[cfg(target_arch = "x86_64")]
+    Sse,
+    #[cfg(target_arch = "x86_64")]
+    Avx2,
+    #[cfg(target_arch = "x86_64")]
+    Avx512,
+    #[cfg(target_arch = "aarch64")]
+    Neon,
+}
+
+impl Backend {
+    /// Le meilleur jeu d'instructions disponible sur le processeur courant.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// println!("Instructions vectorielles : {}", Backend::detect());
+    /// ```
+    pub fn detect() -> Backend {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx512f") {
+                return Backend::Avx512;
+            }
+            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma") {
+                return Backend::Avx2;
+            }
+            if is_x86_feature_detected!("sse") {
+                return Backend::Sse;
+            }
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            if std::arch::is_aarch64_feature_detected!("neon") {
+                return Backend::Neon;
+            }
+        }
+        Backend::Scalar
+    }
+
+    /// Nom court du jeu d'instructions (`scalar`, `sse`, `avx2`, `avx512` ou `neon`).
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Scalar => "scalar",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse => "sse",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => "avx2",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => "avx512",
+            #[cfg(target_arch = "aarch64")]
+            Backend::Neon => "neon",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Backend::Scalar => "scalaire",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse => "SSE",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => "AVX2",
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => "AVX-512",
+            #[cfg(target_arch = "aarch64")]
+            Backend::Neon => "NEON",
+        };
+        write!(f, "{}", label)
+    }
+}
+
+/// Un noyau retournant le produit scalaire et le carré des deux normes.
+type DotAndNorms = fn(&[f32], &[f32]) -> (f32, f32, f32);
+
+/// # Structure: `Kernels`
+///
+/// Les noyaux d'un jeu d'instructions. Chaque noyau ne parcourt que la partie commune des deux vecteurs.
+struct Kernels {
+    backend: Backend,
+    dot: fn(&[f32], &[f32]) -> f32,
+    squared_l2: fn(&[f32], &[f32]) -> f32,
+    dot_and_norms: DotAndNorms,
+}
+
+const SCALAR: Kernels = Kernels {
+    backend: Backend::Scalar,
+    dot: scalar::dot,
+    squared_l2: scalar::squared_l2,
+    dot_and_norms: scalar::dot_and_norms,
+};
+
+/// Construit la table des noyaux d'un module dont les fonctions exigent un jeu d'instructions.
+macro_rules! kernels {
+    ($backend:expr, $module:ident) => {
+        Kernels {
+            backend: $backend,
+            // SAFETY: `Kernels::for_backend` ne retourne cette table que si le processeur dispose
+            // des instructions requises par les fonctions de `$module`.
+            dot: |a, b| unsafe { $module::dot(a, b) },
+            squared_l2: |a, b| unsafe { $module::squared_l2(a, b) },
+            dot_and_norms: |a, b| unsafe { $module::dot_and_norms(a, b) },
+        }
+    };
+}
+
+#[cfg(target_arch = "x86_64")]
+const SSE: Kernels = kernels!(Backend::Sse, sse);
+#[cfg(target_arch = "x86_64")]
+const AVX2: Kernels = kernels!(Backend::Avx2, avx2);
+#[cfg(target_arch = "x86_64")]
+const AVX512: Kernels = kernels!(Backend::Avx512, avx512);
+#[cfg(target_arch = "aarch64")]
+const NEON: Kernels = kernels!(Backend::Neon, neon);
+
+impl Kernels {
+    /// Les noyaux d'un jeu d'instructions, s'il est disponible sur le processeur courant.
+    fn for_backend(backend: Backend) -> Option<&'static Kernels> {
+        match backend {
+            Backend::Scalar => Some(&SCALAR),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Sse => is_x86_feature_detected!("sse").then_some(&SSE),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx2 => (is_x86_feature_detected!("avx2") && is_x86_feature_detected!("fma")).then_some(&AVX2),
+            #[cfg(target_arch = "x86_64")]
+            Backend::Avx512 => is_x86_feature_detected!("avx512f").then_some(&AVX512),
+            #[cfg(target_arch = "aarch64")]
+            Backend::Neon => std::arch::is_aarch64_feature_detected!("neon").then_some(&NEON),
+        }
+    }
+
+    /// Les noyaux choisis pour le processeur courant.
+    fn active() -> &'static Kernels {
+        static ACTIVE: OnceLock<&'static Kernels> = OnceLock::new();
+        ACTIVE.get_or_init(|| Kernels::for_backend(Backend::detect()).unwrap_or(&SCALAR))
+    }
+}
+
+/// Le jeu d'instructions utilisé par les noyaux de calcul.
+pub fn backend() -> Backend {
+    Kernels::active().backend
+}
+
+/// Calcule le produit scalaire de deux vecteurs.
+///
+/// # Exemple
+///
+/// ```
+/// assert_eq!(simd::dot(&[1.0, 2.0], &[3.0, 4.0]), 11.0);
+/// ```
+pub fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+    (Kernels::active().dot)(vector1, vector2)
+}
+
+/// Calcule le carré de la distance euclidienne entre deux vecteurs.
+///
+/// # Exemple
+///
+/// ```
+/// assert_eq!(simd::squared_l2(&[0.0, 0.0], &[3.0, 4.0]), 25.0);
+/// ```
+pub fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+    (Kernels::active().squared_l2)(vector1, vector2)
+}
+
+/// Calcule la norme (euclidienne) d'un vecteur.
+///
+/// # Exemple
+///
+/// ```
+/// assert_eq!(simd::norm(&[3.0, 4.0]), 5.0);
+/// ```
+pub fn norm(vector: &[f32]) -> f32 {
+    dot(vector, vector).sqrt()
+}
+
+/// Calcule en un seul parcours le produit scalaire et le carré des normes de deux vecteurs.
+///
+/// # Retour
+/// - `(f32, f32, f32)`: Le produit scalaire, le carré de la norme de `vector1` et celui de la norme de `vector2`.
+///
+/// # Exemple
+///
+/// ```
+/// let (dot, squared_norm1, squared_norm2) = simd::dot_and_norms(&[1.0, 2.0], &[3.0, 4.0]);
+/// ```
+pub fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+    (Kernels::active().dot_and_norms)(vector1, vector2)
+}
+
+/// Les noyaux de référence, sans instruction vectorielle.
+mod scalar {
+    pub fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let mut total = 0.0;
+        for (x, y) in vector1.iter().zip(vector2) {
+            total += x * y;
+        }
+        total
+    }
+
+    pub fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+        vector1.iter().zip(vector2).map(|(x, y)| (x - y) * (x - y)).sum()
+    }
+
+    pub fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+        let mut dot_product = 0.0;
+        let mut squared_norm1 = 0.0;
+        let mut squared_norm2 = 0.0;
+        for (x, y) in vector1.iter().zip(vector2) {
+            dot_product += x * y;
+            squared_norm1 += x * x;
+            squared_norm2 += y * y;
+        }
+        (dot_product, squared_norm1, squared_norm2)
+    }
+}
+
+/// SSE : 4 flottants par instruction, disponible sur tous les processeurs x86_64.
+#[cfg(target_arch = "x86_64")]
+mod sse {
+    use std::arch::x86_64::*;
+
+    unsafe fn sum(v: __m128) -> f32 {
+        let mut lanes = [0.0f32; 4];
+        _mm_storeu_ps(lanes.as_mut_ptr(), v);
+        (lanes[0] + lanes[1]) + (lanes[2] + lanes[3])
+    }
+
+    #[target_feature(enable = "sse")]
+    pub unsafe fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm_setzero_ps(), _mm_setzero_ps());
+        let mut i = 0;
+        while i + 8 <= n {
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(_mm_loadu_ps(a.add(i)), _mm_loadu_ps(b.add(i))));
+            acc1 = _mm_add_ps(acc1, _mm_mul_ps(_mm_loadu_ps(a.add(i + 4)), _mm_loadu_ps(b.add(i + 4))));
+            i += 8;
+        }
+        if i + 4 <= n {
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(_mm_loadu_ps(a.add(i)), _mm_loadu_ps(b.add(i))));
+            i += 4;
+        }
+        let mut total = sum(_mm_add_ps(acc0, acc1));
+        for j in i..n {
+            total += vector1[j] * vector2[j];
+        }
+        total
+    }
+
+    #[target_feature(enable = "sse")]
+    pub unsafe fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm_setzero_ps(), _mm_setzero_ps());
+        let mut i = 0;
+        while i + 8 <= n {
+            let d0 = _mm_sub_ps(_mm_loadu_ps(a.add(i)), _mm_loadu_ps(b.add(i)));
+            let d1 = _mm_sub_ps(_mm_loadu_ps(a.add(i + 4)), _mm_loadu_ps(b.add(i + 4)));
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(d0, d0));
+            acc1 = _mm_add_ps(acc1, _mm_mul_ps(d1, d1));
+            i += 8;
+        }
+        if i + 4 <= n {
+            let d = _mm_sub_ps(_mm_loadu_ps(a.add(i)), _mm_loadu_ps(b.add(i)));
+            acc0 = _mm_add_ps(acc0, _mm_mul_ps(d, d));
+            i += 4;
+        }
+        let mut total = sum(_mm_add_ps(acc0, acc1));
+        for j in i..n {
+            let d = vector1[j] - vector2[j];
+            total += d * d;
+        }
+        total
+    }
+
+    /// Mêmes accumulateurs, dans le même ordre, que [`dot`] pour chacune des trois sommes.
+    #[target_feature(enable = "sse")]
+    pub unsafe fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let mut dot = [_mm_setzero_ps(); 2];
+        let mut norm1 = [_mm_setzero_ps(); 2];
+        let mut norm2 = [_mm_setzero_ps(); 2];
+        let mut i = 0;
+        while i + 4 <= n {
+            // Les blocs pairs d'une itération de `dot` vont dans le premier accumulateur, les impairs dans le second ;
+            // un dernier bloc isolé va dans le premier.
+            let lane = usize::from(i % 8 == 4 && i + 4 <= n - n % 8);
+            let (x, y) = (_mm_loadu_ps(a.add(i)), _mm_loadu_ps(b.add(i)));
+            dot[lane] = _mm_add_ps(dot[lane], _mm_mul_ps(x, y));
+            norm1[lane] = _mm_add_ps(norm1[lane], _mm_mul_ps(x, x));
+            norm2[lane] = _mm_add_ps(norm2[lane], _mm_mul_ps(y, y));
+            i += 4;
+        }
+        let mut dot = sum(_mm_add_ps(dot[0], dot[1]));
+        let mut norm1 = sum(_mm_add_ps(norm1[0], norm1[1]));
+        let mut norm2 = sum(_mm_add_ps(norm2[0], norm2[1]));
+        for j in i..n {
+            let (x, y) = (vector1[j], vector2[j]);
+            dot += x * y;
+            norm1 += x * x;
+            norm2 += y * y;
+        }
+        (dot, norm1, norm2)
+    }
+}
+
+/// AVX2 avec FMA : 8 flottants par instruction, multiplication et addition fusionnées.
+#[cfg(target_arch = "x86_64")]
+mod avx2 {
+    use std::arch::x86_64::*;
+
+    #[target_feature(enable = "avx")]
+    unsafe fn sum(v: __m256) -> f32 {
+        let mut lanes = [0.0f32; 8];
+        _mm256_storeu_ps(lanes.as_mut_ptr(), v);
+        ((lanes[0] + lanes[1]) + (lanes[2] + lanes[3])) + ((lanes[4] + lanes[5]) + (lanes[6] + lanes[7]))
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm256_setzero_ps(), _mm256_setzero_ps());
+        let mut i = 0;
+        while i + 16 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(a.add(i)), _mm256_loadu_ps(b.add(i)), acc0);
+            acc1 = _mm256_fmadd_ps(_mm256_loadu_ps(a.add(i + 8)), _mm256_loadu_ps(b.add(i + 8)), acc1);
+            i += 16;
+        }
+        if i + 8 <= n {
+            acc0 = _mm256_fmadd_ps(_mm256_loadu_ps(a.add(i)), _mm256_loadu_ps(b.add(i)), acc0);
+            i += 8;
+        }
+        let mut total = sum(_mm256_add_ps(acc0, acc1));
+        for j in i..n {
+            total += vector1[j] * vector2[j];
+        }
+        total
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm256_setzero_ps(), _mm256_setzero_ps());
+        let mut i = 0;
+        while i + 16 <= n {
+            let d0 = _mm256_sub_ps(_mm256_loadu_ps(a.add(i)), _mm256_loadu_ps(b.add(i)));
+            let d1 = _mm256_sub_ps(_mm256_loadu_ps(a.add(i + 8)), _mm256_loadu_ps(b.add(i + 8)));
+            acc0 = _mm256_fmadd_ps(d0, d0, acc0);
+            acc1 = _mm256_fmadd_ps(d1, d1, acc1);
+            i += 16;
+        }
+        if i + 8 <= n {
+            let d = _mm256_sub_ps(_mm256_loadu_ps(a.add(i)), _mm256_loadu_ps(b.add(i)));
+            acc0 = _mm256_fmadd_ps(d, d, acc0);
+            i += 8;
+        }
+        let mut total = sum(_mm256_add_ps(acc0, acc1));
+        for j in i..n {
+            let d = vector1[j] - vector2[j];
+            total += d * d;
+        }
+        total
+    }
+
+    /// Mêmes accumulateurs, dans le même ordre, que [`dot`] pour chacune des trois sommes.
+    #[target_feature(enable = "avx2,fma")]
+    pub unsafe fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let mut dot = [_mm256_setzero_ps(); 2];
+        let mut norm1 = [_mm256_setzero_ps(); 2];
+        let mut norm2 = [_mm256_setzero_ps(); 2];
+        let mut i = 0;
+        while i + 8 <= n {
+            let lane = usize::from(i % 16 == 8 && i + 8 <= n - n % 16);
+            let (x, y) = (_mm256_loadu_ps(a.add(i)), _mm256_loadu_ps(b.add(i)));
+            dot[lane] = _mm256_fmadd_ps(x, y, dot[lane]);
+            norm1[lane] = _mm256_fmadd_ps(x, x, norm1[lane]);
+            norm2[lane] = _mm256_fmadd_ps(y, y, norm2[lane]);
+            i += 8;
+        }
+        let mut dot = sum(_mm256_add_ps(dot[0], dot[1]));
+        let mut norm1 = sum(_mm256_add_ps(norm1[0], norm1[1]));
+        let mut norm2 = sum(_mm256_add_ps(norm2[0], norm2[1]));
+        for j in i..n {
+            let (x, y) = (vector1[j], vector2[j]);
+            dot += x * y;
+            norm1 += x * x;
+            norm2 += y * y;
+        }
+        (dot, norm1, norm2)
+    }
+}
+
+/// AVX-512 : 16 flottants par instruction ; la fin des vecteurs est chargée avec un masque.
+#[cfg(target_arch = "x86_64")]
+mod avx512 {
+    use std::arch::x86_64::*;
+
+    /// Le masque des `remaining` premières voies (`remaining < 16`).
+    fn tail_mask(remaining: usize) -> __mmask16 {
+        ((1u32 << remaining) - 1) as __mmask16
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm512_setzero_ps(), _mm512_setzero_ps());
+        let mut i = 0;
+        while i + 32 <= n {
+            acc0 = _mm512_fmadd_ps(_mm512_loadu_ps(a.add(i)), _mm512_loadu_ps(b.add(i)), acc0);
+            acc1 = _mm512_fmadd_ps(_mm512_loadu_ps(a.add(i + 16)), _mm512_loadu_ps(b.add(i + 16)), acc1);
+            i += 32;
+        }
+        if i + 16 <= n {
+            acc0 = _mm512_fmadd_ps(_mm512_loadu_ps(a.add(i)), _mm512_loadu_ps(b.add(i)), acc0);
+            i += 16;
+        }
+        if i < n {
+            let mask = tail_mask(n - i);
+            let (x, y) = (_mm512_maskz_loadu_ps(mask, a.add(i)), _mm512_maskz_loadu_ps(mask, b.add(i)));
+            acc1 = _mm512_fmadd_ps(x, y, acc1);
+        }
+        _mm512_reduce_add_ps(_mm512_add_ps(acc0, acc1))
+    }
+
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (_mm512_setzero_ps(), _mm512_setzero_ps());
+        let mut i = 0;
+        while i + 32 <= n {
+            let d0 = _mm512_sub_ps(_mm512_loadu_ps(a.add(i)), _mm512_loadu_ps(b.add(i)));
+            let d1 = _mm512_sub_ps(_mm512_loadu_ps(a.add(i + 16)), _mm512_loadu_ps(b.add(i + 16)));
+            acc0 = _mm512_fmadd_ps(d0, d0, acc0);
+            acc1 = _mm512_fmadd_ps(d1, d1, acc1);
+            i += 32;
+        }
+        if i + 16 <= n {
+            let d = _mm512_sub_ps(_mm512_loadu_ps(a.add(i)), _mm512_loadu_ps(b.add(i)));
+            acc0 = _mm512_fmadd_ps(d, d, acc0);
+            i += 16;
+        }
+        if i < n {
+            let mask = tail_mask(n - i);
+            let d = _mm512_sub_ps(_mm512_maskz_loadu_ps(mask, a.add(i)), _mm512_maskz_loadu_ps(mask, b.add(i)));
+            acc1 = _mm512_fmadd_ps(d, d, acc1);
+        }
+        _mm512_reduce_add_ps(_mm512_add_ps(acc0, acc1))
+    }
+
+    /// Mêmes accumulateurs, dans le même ordre, que [`dot`] pour chacune des trois sommes.
+    #[target_feature(enable = "avx512f")]
+    pub unsafe fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let mut dot = [_mm512_setzero_ps(); 2];
+        let mut norm1 = [_mm512_setzero_ps(); 2];
+        let mut norm2 = [_mm512_setzero_ps(); 2];
+        let mut i = 0;
+        while i < n {
+            // Comme dans `dot` : blocs impairs d'une paire et bloc partiel final dans le second accumulateur.
+            let (x, y, lane) = if i + 16 <= n {
+                let lane = usize::from(i % 32 == 16 && i + 16 <= n - n % 32);
+                (_mm512_loadu_ps(a.add(i)), _mm512_loadu_ps(b.add(i)), lane)
+            } else {
+                let mask = tail_mask(n - i);
+                (_mm512_maskz_loadu_ps(mask, a.add(i)), _mm512_maskz_loadu_ps(mask, b.add(i)), 1)
+            };
+            dot[lane] = _mm512_fmadd_ps(x, y, dot[lane]);
+            norm1[lane] = _mm512_fmadd_ps(x, x, norm1[lane]);
+            norm2[lane] = _mm512_fmadd_ps(y, y, norm2[lane]);
+            i += 16;
+        }
+        (
+            _mm512_reduce_add_ps(_mm512_add_ps(dot[0], dot[1])),
+            _mm512_reduce_add_ps(_mm512_add_ps(norm1[0], norm1[1])),
+            _mm512_reduce_add_ps(_mm512_add_ps(norm2[0], norm2[1])),
+        )
+    }
+}
+
+/// NEON : 4 flottants par instruction, multiplication et addition fusionnées.
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (vdupq_n_f32(0.0), vdupq_n_f32(0.0));
+        let mut i = 0;
+        while i + 8 <= n {
+            acc0 = vfmaq_f32(acc0, vld1q_f32(a.add(i)), vld1q_f32(b.add(i)));
+            acc1 = vfmaq_f32(acc1, vld1q_f32(a.add(i + 4)), vld1q_f32(b.add(i + 4)));
+            i += 8;
+        }
+        if i + 4 <= n {
+            acc0 = vfmaq_f32(acc0, vld1q_f32(a.add(i)), vld1q_f32(b.add(i)));
+            i += 4;
+        }
+        let mut total = vaddvq_f32(vaddq_f32(acc0, acc1));
+        for j in i..n {
+            total += vector1[j] * vector2[j];
+        }
+        total
+    }
+
+    #[target_feature(enable = "neon")]
+    pub unsafe fn squared_l2(vector1: &[f32], vector2: &[f32]) -> f32 {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let (mut acc0, mut acc1) = (vdupq_n_f32(0.0), vdupq_n_f32(0.0));
+        let mut i = 0;
+        while i + 8 <= n {
+            let d0 = vsubq_f32(vld1q_f32(a.add(i)), vld1q_f32(b.add(i)));
+            let d1 = vsubq_f32(vld1q_f32(a.add(i + 4)), vld1q_f32(b.add(i + 4)));
+            acc0 = vfmaq_f32(acc0, d0, d0);
+            acc1 = vfmaq_f32(acc1, d1, d1);
+            i += 8;
+        }
+        if i + 4 <= n {
+            let d = vsubq_f32(vld1q_f32(a.add(i)), vld1q_f32(b.add(i)));
+            acc0 = vfmaq_f32(acc0, d, d);
+            i += 4;
+        }
+        let mut total = vaddvq_f32(vaddq_f32(acc0, acc1));
+        for j in i..n {
+            let d = vector1[j] - vector2[j];
+            total += d * d;
+        }
+        total
+    }
+
+    /// Mêmes accumulateurs, dans le même ordre, que [`dot`] pour chacune des trois sommes.
+    #[target_feature(enable = "neon")]
+    pub unsafe fn dot_and_norms(vector1: &[f32], vector2: &[f32]) -> (f32, f32, f32) {
+        let n = vector1.len().min(vector2.len());
+        let (a, b) = (vector1.as_ptr(), vector2.as_ptr());
+        let mut dot = [vdupq_n_f32(0.0); 2];
+        let mut norm1 = [vdupq_n_f32(0.0); 2];
+        let mut norm2 = [vdupq_n_f32(0.0); 2];
+        let mut i = 0;
+        while i + 4 <= n {
+            let lane = usize::from(i % 8 == 4 && i + 4 <= n - n % 8);
+            let (x, y) = (vld1q_f32(a.add(i)), vld1q_f32(b.add(i)));
+            dot[lane] = vfmaq_f32(dot[lane], x, y);
+            norm1[lane] = vfmaq_f32(norm1[lane], x, x);
+            norm2[lane] = vfmaq_f32(norm2[lane], y, y);
+            i += 4;
+        }
+        let mut dot = vaddvq_f32(vaddq_f32(dot[0], dot[1]));
+        let mut norm1 = vaddvq_f32(vaddq_f32(norm1[0], norm1[1]));
+        let mut norm2 = vaddvq_f32(vaddq_f32(norm2[0], norm2[1]));
+        for j in i..n {
+            let (x, y) = (vector1[j], vector2[j]);
+            dot += x * y;
+            norm1 += x * x;
+            norm2 += y * y;
+        }
+        (dot, norm1, norm2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(target_arch = "x86_64")]
+    const BACKENDS: &[Backend] = &[Backend::Scalar, Backend::Sse, Backend::Avx2, Backend::Avx512];
+    #[cfg(target_arch = "aarch64")]
+    const BACKENDS: &[Backend] = &[Backend::Scalar, Backend::Neon];
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+    const BACKENDS: &[Backend] = &[Backend::Scalar];
+
+    /// Vecteurs pseudo-aléatoires reproductibles, de valeurs comprises entre -1 et 1.
+    fn random_vector(seed: &mut u64, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|_| {
+                *seed ^= *seed << 13;
+                *seed ^= *seed >> 7;
+                *seed ^= *seed << 17;
+                (*seed % 20_001) as f32 / 10_000.0 - 1.0
+            })
+            .collect()
+    }
+
+    /// Écart toléré : proportionnel à la somme des valeurs absolues des termes additionnés.
+    fn assert_close(fast: f32, reference: f32, magnitude: f32, context: &str) {
+        let tolerance = 1e-5 * magnitude + 1e-6;
+        assert!(
+            (fast - reference).abs() <= tolerance,
+            "{} : {} au lieu de {} (tolérance {})",
+            context,
+            fast,
+            reference,
+            tolerance
+        );
+    }
+
+    #[test]
+    fn fast_kernels_match_scalar_kernels() {
+        let mut seed = 0x9E37_79B9_7F4A_7C15;
+        let lengths = (0..=70).chain([127, 128, 129, 768, 1536, 4099]);
+        for len in lengths {
+            let a = random_vector(&mut seed, len);
+            let b = random_vector(&mut seed, len);
+            let abs_dot: f32 = a.iter().zip(&b).map(|(x, y)| (x * y).abs()).sum();
+            let (reference_dot, reference_norm1, reference_norm2) = scalar::dot_and_norms(&a, &b);
+            let reference_l2 = scalar::squared_l2(&a, &b);
+
+            for kernels in BACKENDS.iter().copied().filter_map(Kernels::for_backend) {
+                let context = format!("{} (longueur {})", kernels.backend, len);
+                assert_close((kernels.dot)(&a, &b), scalar::dot(&a, &b), abs_dot, &context);
+                assert_close((kernels.squared_l2)(&a, &b), reference_l2, reference_l2, &context);
+                let (dot, norm1, norm2) = (kernels.dot_and_norms)(&a, &b);
+                assert_close(dot, reference_dot, abs_dot, &context);
+                assert_close(norm1, reference_norm1, reference_norm1, &context);
+                assert_close(norm2, reference_norm2, reference_norm2, &context);
+            }
+        }
+    }
+
+    #[test]
+    fn kernels_only_read_the_common_prefix() {
+        let a = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0];
+        let b = [1.0; 5];
+        for kernels in BACKENDS.iter().copied().filter_map(Kernels::for_backend) {
+            assert_eq!((kernels.dot)(&a, &b), 15.0, "{}", kernels.backend);
+            assert_eq!((kernels.dot)(&[], &[]), 0.0, "{}", kernels.backend);
+            assert_eq!((kernels.dot_and_norms)(&b, &a), (15.0, 5.0, 55.0), "{}", kernels.backend);
+        }
+    }
+
+    #[test]
+    fn fused_kernel_matches_separate_dot_products_exactly() {
+        let mut seed = 0x2545_F491_4F6C_DD1D;
+        for len in (0..=70).chain([129, 1000]) {
+            let a = random_vector(&mut seed, len);
+            let b = random_vector(&mut seed, len);
+            for kernels in BACKENDS.iter().copied().filter_map(Kernels::for_backend) {
+                let separate = ((kernels.dot)(&a, &b), (kernels.dot)(&a, &a), (kernels.dot)(&b, &b));
+                let fused = (kernels.dot_and_norms)(&a, &b);
+                assert_eq!(
+                    (fused.0.to_bits(), fused.1.to_bits(), fused.2.to_bits()),
+                    (separate.0.to_bits(), separate.1.to_bits(), separate.2.to_bits()),
+                    "{} (longueur {})",
+                    kernels.backend,
+                    len
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn detected_backend_is_available() {
+        assert!(Kernels::for_backend(Backend::detect()).is_some());
+        assert_eq!(backend(), Backend::detect());
+    }
+}
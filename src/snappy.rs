@@ -0,0 +1,90 @@
+//! # Module: `snappy`
+//!
+//! Décompression des blocs Snappy, la compression par défaut des pages Parquet.
+//!
+//! Un bloc commence par sa taille décompressée, puis alterne des littéraux (octets recopiés tels quels)
+//! et des copies (`longueur` octets repris `distance` octets plus tôt dans la sortie).
+
+use crate::error::DbError;
+
+/// Décompresse un bloc Snappy.
+///
+/// # Paramètres
+/// - `input`: Le bloc compressé.
+/// - `limit`: La taille décompressée maximale acceptée.
+///
+/// # Retour
+/// - `Result<Vec<u8>, DbError>`: Les octets décompressés, ou [`DbError::Corrupted`] si le bloc est invalide.
+pub fn decompress(input: &[u8], limit: usize) -> Result<Vec<u8>, DbError> {
+    let mut position = 0;
+    let mut length = 0usize;
+    for shift in (0..35).step_by(7) {
+        let byte = *input.get(position).ok_or_else(|| corrupted("bloc tronqué"))?;
+        position += 1;
+        length |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+    if length > limit {
+        return Err(corrupted("taille décompressée invalide"));
+    }
+
+    let mut output = Vec::with_capacity(length);
+    let read = |position: &mut usize, count: usize| -> Result<usize, DbError> {
+        let bytes = input.get(*position..*position + count).ok_or_else(|| corrupted("bloc tronqué"))?;
+        *position += count;
+        Ok(bytes.iter().rev().fold(0, |value, &byte| value << 8 | byte as usize))
+    };
+    while position < input.len() {
+        let tag = input[position] as usize;
+        position += 1;
+        let (count, distance) = match tag & 3 {
+            0 => {
+                let count = match tag >> 2 {
+                    count @ 0..=59 => count + 1,
+                    extra => read(&mut position, extra - 59)? + 1,
+                };
+                let literal = input.get(position..position + count).ok_or_else(|| corrupted("littéral tronqué"))?;
+                output.extend_from_slice(literal);
+                position += count;
+                continue;
+            }
+            1 => (4 + (tag >> 2 & 7), (tag >> 5) << 8 | read(&mut position, 1)?),
+            2 => (1 + (tag >> 2), read(&mut position, 2)?),
+            _ => (1 + (tag >> 2), read(&mut position, 4)?),
+        };
+        if distance == 0 || distance > output.len() {
+            return Err(corrupted("distance de copie invalide"));
+        }
+        let start = output.len() - distance;
+        for index in start..start + count {
+            output.push(output[index]);
+        }
+        if output.len() > length {
+            break;
+        }
+    }
+    if output.len() != length {
+        return Err(corrupted("taille décompressée inexacte"));
+    }
+    Ok(output)
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("bloc Snappy : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_literals_and_overlapping_copies() {
+        // "abcd" en littéral, puis une copie de 8 octets à distance 4, et une de 2 octets à distance 1.
+        let block = [14, 3 << 2, b'a', b'b', b'c', b'd', 1 | (8 - 4) << 2, 4, 2 | (2 - 1) << 2, 1, 0];
+        assert_eq!(decompress(&block, 100).unwrap(), b"abcdabcdabcddd");
+        assert!(decompress(&block, 10).is_err());
+        assert!(decompress(&block[..5], 100).is_err());
+    }
+}
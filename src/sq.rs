@@ -0,0 +1,455 @@
+//! # Module: `sq`
+//!
+//! Quantification scalaire des vecteurs d'une [`Collection`](crate::Collection) : chaque coordonnée
+//! est ramenée à un entier de 8 bits (`int8`, quatre fois moins de mémoire que les `f32`) ou de 4 bits
+//! (`int4`, huit fois moins), entre les bornes relevées lors d'une calibration.
+//!
+//! La calibration relève le minimum et le maximum de chaque coordonnée sur les vecteurs de la
+//! collection ([`Calibration::PerDimension`]), ou un seul minimum et un seul maximum pour toutes les
+//! coordonnées ([`Calibration::Global`]). Les valeurs ajoutées ensuite hors de ces bornes sont ramenées
+//! à la borne la plus proche.
+//!
+//! Les scores sont calculés directement sur les entiers des codes, sans reconstruire les vecteurs ;
+//! avec un facteur de suréchantillonnage, les meilleurs candidats sont reclassés avec leurs vecteurs
+//! d'origine (voir [`SqParams::oversampling`]).
+
+use crate::metric::Metric;
+use crate::quantizer::{self, Codes};
+use crate::vectors::VectorStore;
+use crate::{cosine_from_dot_product, simd, DocumentId, Vector};
+
+/// # Énumération: `ScalarBits`
+///
+/// Le nombre de bits du code de chaque coordonnée.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScalarBits {
+    /// Un octet par coordonnée, 256 niveaux.
+    Int8,
+    /// Deux coordonnées par octet, 16 niveaux.
+    Int4,
+}
+
+impl ScalarBits {
+    /// Nom court, tel qu'il est affiché par `stats`.
+    pub fn name(self) -> &'static str {
+        match self {
+            ScalarBits::Int8 => "int8",
+            ScalarBits::Int4 => "int4",
+        }
+    }
+
+    /// Le plus grand entier d'un code : une coordonnée est codée par un entier de 0 à `max_level`.
+    fn max_level(self) -> f32 {
+        match self {
+            ScalarBits::Int8 => 255.0,
+            ScalarBits::Int4 => 15.0,
+        }
+    }
+
+    /// Taille, en octets, du code d'un vecteur de dimension `dimension`.
+    fn code_bytes(self, dimension: usize) -> usize {
+        match self {
+            ScalarBits::Int8 => dimension,
+            ScalarBits::Int4 => dimension.div_ceil(2),
+        }
+    }
+}
+
+/// # Énumération: `Calibration`
+///
+/// Les bornes entre lesquelles les coordonnées sont quantifiées.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Calibration {
+    /// Un minimum et un maximum par coordonnée : plus précis quand les coordonnées n'ont pas la même étendue.
+    PerDimension,
+    /// Un minimum et un maximum communs à toutes les coordonnées.
+    Global,
+}
+
+/// # Structure: `SqParams`
+///
+/// Paramètres d'un [`ScalarQuantizer`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SqParams {
+    /// Le nombre de bits par coordonnée.
+    pub bits: ScalarBits,
+    /// Les bornes de la quantification.
+    pub calibration: Calibration,
+    /// Facteur de suréchantillonnage : les `k × oversampling` meilleurs candidats trouvés sur les codes
+    /// sont reclassés avec leurs vecteurs d'origine. `None` retourne directement les scores approchés.
+    pub oversampling: Option<f32>,
+}
+
+impl Default for SqParams {
+    fn default() -> Self {
+        SqParams {
+            bits: ScalarBits::Int8,
+            calibration: Calibration::PerDimension,
+            oversampling: None,
+        }
+    }
+}
+
+/// # Structure: `ScalarQuantizer`
+///
+/// Les bornes de calibration et le code de chaque document d'une collection.
+pub struct ScalarQuantizer {
+    params: SqParams,
+    metric: Metric,
+    /// La valeur représentée par le niveau 0 de chaque coordonnée (son minimum lors de la calibration).
+    offsets: Vec<f32>,
+    /// L'écart entre deux niveaux successifs de chaque coordonnée, nul si la coordonnée était constante.
+    steps: Vec<f32>,
+    /// Le code de chaque document.
+    codes: Codes,
+}
+
+/// Une requête préparée pour être comparée aux codes : la valeur de chaque coordonnée d'un document
+/// étant `offset + step × niveau`, les termes qui ne dépendent que de la requête sont calculés une fois.
+struct PreparedQuery<'a> {
+    query: &'a [f32],
+    norm: f32,
+    /// `query - offset`, pour les distances.
+    shifted: Vec<f32>,
+    /// `query × step`, pour le produit scalaire.
+    scaled: Vec<f32>,
+    /// `Σ query × offset`, la part du produit scalaire qui ne dépend pas du document.
+    base: f32,
+}
+
+impl ScalarQuantizer {
+    /// Calibre le quantificateur sur `vectors` puis encode chaque document.
+    /// Pour la similarité cosinus, ce sont les vecteurs normalisés qui sont calibrés et encodés.
+    ///
+    /// Sans aucun vecteur, aucune borne ne peut être relevée : le quantificateur n'encode alors rien
+    /// (voir [`ScalarQuantizer::is_trained`]).
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let sq = ScalarQuantizer::train(SqParams { oversampling: Some(3.0), ..Default::default() }, Metric::Cosine, &vectors);
+    /// ```
+    pub fn train(params: SqParams, metric: Metric, vectors: &VectorStore) -> Self {
+        let dimension = vectors.values().next().map_or(0, <[f32]>::len);
+        let mut minimums = vec![f32::INFINITY; dimension];
+        let mut maximums = vec![f32::NEG_INFINITY; dimension];
+        for vector in vectors.values() {
+            let scale = normalization_scale(metric, vector);
+            for ((minimum, maximum), value) in minimums.iter_mut().zip(maximums.iter_mut()).zip(vector) {
+                *minimum = minimum.min(value * scale);
+                *maximum = maximum.max(value * scale);
+            }
+        }
+        if params.calibration == Calibration::Global {
+            let minimum = minimums.iter().copied().fold(f32::INFINITY, f32::min);
+            let maximum = maximums.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            minimums.fill(minimum);
+            maximums.fill(maximum);
+        }
+        let steps = minimums
+            .iter()
+            .zip(&maximums)
+            .map(|(minimum, maximum)| {
+                let step = (maximum - minimum) / params.bits.max_level();
+                if step.is_finite() && step > 0.0 {
+                    step
+                } else {
+                    0.0
+                }
+            })
+            .collect();
+
+        let mut quantizer = ScalarQuantizer {
+            params,
+            metric,
+            offsets: minimums,
+            steps,
+            codes: Codes::new(params.bits.code_bytes(dimension)),
+        };
+        let mut keys: Vec<DocumentId> = vectors.keys().copied().collect();
+        keys.sort();
+        for key in keys {
+            quantizer.insert(key, vectors);
+        }
+        quantizer
+    }
+
+    /// Les paramètres du quantificateur.
+    pub fn params(&self) -> SqParams {
+        self.params
+    }
+
+    /// Indique si les bornes ont pu être relevées, c'est-à-dire si la collection contenait
+    /// au moins un vecteur lors de la calibration.
+    pub fn is_trained(&self) -> bool {
+        !self.offsets.is_empty()
+    }
+
+    /// Taille du code d'un document, en octets.
+    pub fn code_bytes(&self) -> usize {
+        self.codes.size()
+    }
+
+    /// Mémoire occupée par le quantificateur, en octets : les codes, l'identifiant et la position
+    /// de chaque document, et les bornes de calibration.
+    pub fn memory_bytes(&self) -> usize {
+        self.codes.memory_bytes() + (self.offsets.len() + self.steps.len()) * std::mem::size_of::<f32>()
+    }
+
+    /// Encode le document `key`, dont le vecteur doit déjà se trouver dans `vectors`.
+    /// Le code d'un document déjà encodé est remplacé.
+    pub fn insert(&mut self, key: DocumentId, vectors: &VectorStore) {
+        let vector = match vectors.get(&key) {
+            Some(vector) if self.is_trained() => vector,
+            _ => return,
+        };
+        let code = self.encode(vector);
+        self.codes.insert(key, &code);
+    }
+
+    /// Retire le code d'un document.
+    pub fn remove(&mut self, key: &DocumentId) {
+        self.codes.remove(key);
+    }
+
+    /// Recherche les `k` documents dont le score calculé sur les codes est le meilleur, puis,
+    /// si [`SqParams::oversampling`] est donné, reclasse les meilleurs candidats avec leurs vecteurs d'origine.
+    ///
+    /// # Paramètres
+    /// - `query`: Le vecteur de la requête.
+    /// - `k`: Le nombre maximal de résultats.
+    /// - `vectors`: Les vecteurs de la collection, utilisés pour le reclassement.
+    /// - `accept`: Un prédicat optionnel : seuls les documents acceptés peuvent figurer dans les résultats.
+    ///
+    /// # Retour
+    /// - [`Vector`]: Les documents trouvés avec leur score (approché, ou exact s'ils ont été reclassés),
+    ///   du plus proche au plus éloigné selon la métrique.
+    pub fn search(
+        &self,
+        query: &[f32],
+        k: usize,
+        vectors: &VectorStore,
+        accept: Option<&(dyn Fn(&DocumentId) -> bool + Sync)>,
+    ) -> Vector {
+        let candidates = self
+            .params
+            .oversampling
+            .map_or(k, |factor| ((k as f32 * factor).ceil() as usize).max(k));
+        let prepared = PreparedQuery {
+            query,
+            norm: simd::norm(query),
+            shifted: query.iter().zip(&self.offsets).map(|(value, offset)| value - offset).collect(),
+            scaled: query.iter().zip(&self.steps).map(|(value, step)| value * step).collect(),
+            base: simd::dot(query, &self.offsets),
+        };
+        let hits = self.codes.top_k(self.metric, candidates, accept, |code| match self.params.bits {
+            ScalarBits::Int8 => self.score(&prepared, code.iter().map(|&level| f32::from(level))),
+            ScalarBits::Int4 => self.score(
+                &prepared,
+                code.iter().flat_map(|&pair| [pair & 0x0F, pair >> 4]).map(f32::from),
+            ),
+        });
+        match self.params.oversampling {
+            Some(_) => quantizer::rescore(self.metric, query, k, hits, vectors),
+            None => hits,
+        }
+    }
+
+    /// Ramène chaque coordonnée au niveau le plus proche, entre 0 et le niveau maximal.
+    fn encode(&self, vector: &[f32]) -> Vec<u8> {
+        let scale = normalization_scale(self.metric, vector);
+        let max_level = self.params.bits.max_level();
+        let levels = vector.iter().zip(&self.offsets).zip(&self.steps).map(|((value, offset), step)| {
+            if *step == 0.0 {
+                0
+            } else {
+                ((value * scale - offset) / step).round().clamp(0.0, max_level) as u8
+            }
+        });
+        match self.params.bits {
+            ScalarBits::Int8 => levels.collect(),
+            ScalarBits::Int4 => {
+                let mut code = vec![0u8; self.code_bytes()];
+                for (i, level) in levels.enumerate() {
+                    code[i / 2] |= level << (4 * (i % 2));
+                }
+                code
+            }
+        }
+    }
+
+    /// Le score d'un document à partir des niveaux de ses coordonnées.
+    fn score(&self, query: &PreparedQuery, levels: impl Iterator<Item = f32>) -> f32 {
+        match self.metric {
+            // La norme du vecteur reconstruit n'est pas exactement 1 : elle est calculée dans le même parcours.
+            Metric::Cosine => {
+                let (dot, squared_norm) = query
+                    .scaled
+                    .iter()
+                    .zip(self.offsets.iter().zip(&self.steps))
+                    .zip(levels)
+                    .fold((query.base, 0.0), |(dot, squared_norm), ((scaled, (offset, step)), level)| {
+                        let value = offset + step * level;
+                        (dot + scaled * level, squared_norm + value * value)
+                    });
+                cosine_from_dot_product(dot, query.norm, squared_norm.sqrt())
+            }
+            Metric::Dot => query.base + query.scaled.iter().zip(levels).map(|(scaled, level)| scaled * level).sum::<f32>(),
+            Metric::Euclidean => query
+                .shifted
+                .iter()
+                .zip(&self.steps)
+                .zip(levels)
+                .map(|((shifted, step), level)| (shifted - step * level).powi(2))
+                .sum::<f32>()
+                .sqrt(),
+            Metric::Manhattan => query
+                .shifted
+                .iter()
+                .zip(&self.steps)
+                .zip(levels)
+                .map(|((shifted, step), level)| (shifted - step * level).abs())
+                .sum(),
+            Metric::Hamming => query
+                .query
+                .iter()
+                .zip(self.offsets.iter().zip(&self.steps))
+                .zip(levels)
+                .filter(|((value, (offset, step)), level)| (**value != 0.0) != (*offset + *step * level != 0.0))
+                .count() as f32,
+        }
+    }
+}
+
+/// Le facteur qui normalise un vecteur pour la similarité cosinus (un vecteur nul reste nul), 1 sinon.
+fn normalization_scale(metric: Metric, vector: &[f32]) -> f32 {
+    match metric {
+        Metric::Cosine => {
+            let norm = simd::norm(vector);
+            if norm > 0.0 {
+                1.0 / norm
+            } else {
+                0.0
+            }
+        }
+        _ => 1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rng::SplitMix64;
+    use crate::topk::TopK;
+    use std::collections::HashSet;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    fn random_vectors(count: usize, seed: u64) -> VectorStore {
+        let mut rng = SplitMix64::new(seed);
+        let mut vectors = VectorStore::default();
+        for n in 0..count {
+            let vector = (0..16).map(|_| rng.next_gaussian() as f32).collect();
+            vectors.insert(id(n as u128), vector).unwrap();
+        }
+        vectors
+    }
+
+    fn exact(metric: Metric, vectors: &VectorStore, query: &[f32], k: usize) -> Vector {
+        let mut top = TopK::new(metric, k);
+        for (key, vector) in vectors.iter() {
+            top.push(*key, metric.score(query, vector));
+        }
+        top.into_sorted_vec()
+    }
+
+    /// Reconstruit un vecteur à partir des niveaux de son code.
+    fn decode(quantizer: &ScalarQuantizer, code: &[u8]) -> Vec<f32> {
+        let levels: Vec<u8> = match quantizer.params().bits {
+            ScalarBits::Int8 => code.to_vec(),
+            ScalarBits::Int4 => code.iter().flat_map(|&pair| [pair & 0x0F, pair >> 4]).collect(),
+        };
+        quantizer
+            .offsets
+            .iter()
+            .zip(&quantizer.steps)
+            .zip(levels)
+            .map(|((offset, step), level)| offset + step * f32::from(level))
+            .collect()
+    }
+
+    /// Proportion des `k` vrais plus proches voisins retrouvés.
+    fn recall(quantizer: &ScalarQuantizer, vectors: &VectorStore, k: usize) -> f64 {
+        let queries = random_vectors(20, 99);
+        let mut found = 0;
+        for query in queries.values() {
+            let expected: HashSet<DocumentId> =
+                exact(quantizer.metric, vectors, query, k).into_iter().map(|(key, _)| key).collect();
+            let hits = quantizer.search(query, k, vectors, None);
+            found += hits.iter().filter(|(key, _)| expected.contains(key)).count();
+        }
+        found as f64 / (queries.len() * k) as f64
+    }
+
+    #[test]
+    fn decoded_coordinates_are_within_half_a_step() {
+        let vectors = random_vectors(500, 1);
+        for bits in [ScalarBits::Int8, ScalarBits::Int4] {
+            for calibration in [Calibration::PerDimension, Calibration::Global] {
+                let params = SqParams { bits, calibration, ..Default::default() };
+                let quantizer = ScalarQuantizer::train(params, Metric::Euclidean, &vectors);
+                assert_eq!(quantizer.code_bytes(), bits.code_bytes(16));
+                for vector in vectors.values() {
+                    let decoded = decode(&quantizer, &quantizer.encode(vector));
+                    for ((value, decoded), step) in vector.iter().zip(decoded).zip(&quantizer.steps) {
+                        assert!((value - decoded).abs() <= step / 2.0 + 1e-5, "{} : {} {}", bits.name(), value, decoded);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn values_outside_the_calibration_are_clamped() {
+        let mut vectors = VectorStore::default();
+        vectors.insert(id(1), vec![0.0, -1.0, 5.0]).unwrap();
+        vectors.insert(id(2), vec![1.0, 1.0, 5.0]).unwrap();
+        let quantizer = ScalarQuantizer::train(SqParams::default(), Metric::Euclidean, &vectors);
+        assert_eq!(quantizer.encode(&[1.0, -1.0, 5.0]), [255, 0, 0]);
+        assert_eq!(quantizer.encode(&[-3.0, 7.0, 9.0]), [0, 255, 0]);
+        assert_eq!(decode(&quantizer, &quantizer.encode(&[-3.0, 7.0, 9.0])), [0.0, 1.0, 5.0]);
+    }
+
+    #[test]
+    fn int8_recall_is_near_identical_to_the_exact_search() {
+        let vectors = random_vectors(2_000, 2);
+        for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot, Metric::Manhattan] {
+            let int8 = recall(&ScalarQuantizer::train(SqParams::default(), metric, &vectors), &vectors, 10);
+            assert!(int8 >= 0.95, "{} : {}", metric, int8);
+
+            // Les scores calculés sur les codes restent proches des scores exacts.
+            let quantizer = ScalarQuantizer::train(SqParams::default(), metric, &vectors);
+            let query = vectors.get(&id(11)).unwrap();
+            for (key, score) in quantizer.search(query, 10, &vectors, None) {
+                let expected = metric.score(query, vectors.get(&key).unwrap());
+                assert!((score - expected).abs() < 0.15, "{} : {} {}", metric, score, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn oversampling_recovers_the_int4_recall_with_exact_scores() {
+        let vectors = random_vectors(2_000, 3);
+        let params = SqParams { bits: ScalarBits::Int4, ..Default::default() };
+        let approximate = recall(&ScalarQuantizer::train(params, Metric::Euclidean, &vectors), &vectors, 10);
+        let oversampled = ScalarQuantizer::train(SqParams { oversampling: Some(8.0), ..params }, Metric::Euclidean, &vectors);
+        let rescored = recall(&oversampled, &vectors, 10);
+        assert!(rescored > approximate && rescored >= 0.95, "{} puis {}", approximate, rescored);
+
+        let query = vectors.get(&id(5)).unwrap();
+        assert_eq!(oversampled.search(query, 10, &vectors, None), exact(Metric::Euclidean, &vectors, query, 10));
+    }
+}
@@ -0,0 +1,1652 @@
+//! # Module: `storage`
+//!
+//! Moteur de stockage sur disque d'une [`Database`](crate::Database).
+//!
+//! Un répertoire de données contient :
+//! - `wal.log` : le journal d'écriture anticipée (*write-ahead log*). Chaque opération
+//!   (`add_collection`, `drop_collection`, `add_or_update` avec les métadonnées du document, `remove`,
+//!   `upsert_many` / `remove_many` dont tout le lot tient dans une seule opération, construction et réglage
+//!   d'un index, indexation d'un champ des métadonnées, construction et suppression d'un quantificateur) y est ajoutée **avant** d'être appliquée en mémoire,
+//!   sous la forme `[longueur: u32][crc32: u32][numéro de séquence: u64][opération]`, puis en est retirée si elle n'a pas pu l'être.
+//! - `snapshot.bin` : un instantané complet de la base, associé au numéro de séquence de la dernière
+//!   opération qu'il contient. Écrire un instantané permet de vider le journal. Les index (de recherche
+//!   et des champs des métadonnées) et les quantificateurs n'y sont conservés que par leurs paramètres,
+//!   sous la forme d'opérations rejouées après le chargement des documents : ils sont reconstruits
+//!   (les quantificateurs réappris sur tous les documents) à l'ouverture.
+//! - `vectors-<uuid>.bin` : les vecteurs de chaque collection projetée en mémoire (voir [`mmap`](crate::mmap)).
+//!   L'instantané n'en garde que l'emplacement de chaque document ; le fichier est synchronisé sur disque
+//!   avant l'écriture de l'instantané, puis rouvert tel quel. Les fichiers qu'aucun instantané ne référence
+//!   (collections supprimées, ou créées depuis le dernier instantané) sont supprimés à l'ouverture et après
+//!   chaque instantané.
+//!
+//! À l'ouverture, l'instantané est chargé puis les opérations plus récentes du journal sont rejouées,
+//! au fil de la lecture ([`Replay`]) : seule l'opération en cours est en mémoire, si bien qu'un journal
+//! peut dépasser la mémoire vive, mais pas une opération (un lot d'`upsert_many` est lu d'un bloc).
+//! Une fin de journal incomplète ou corrompue (écriture interrompue par un crash) est ignorée puis tronquée.
+//!
+//! Les vecteurs des collections qui ne sont pas projetées en mémoire sont réécrits dans chaque instantané
+//! et relus entièrement à l'ouverture.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::bq::BqParams;
+use crate::error::DbError;
+use crate::hnsw::HnswParams;
+use crate::index::IndexConfig;
+use crate::ivf::IvfParams;
+use crate::lsh::LshParams;
+use crate::metric::Metric;
+#[cfg(unix)]
+use crate::mmap::MappedFile;
+use crate::mmap::{FILE_EXTENSION, FILE_PREFIX};
+use crate::payload::{Payload, Value};
+use crate::pq::PqParams;
+use crate::quantizer::QuantizerConfig;
+use crate::sq::{Calibration, ScalarBits, SqParams};
+use crate::vectors::VectorStore;
+use crate::{Collection, CollectionConfig, Document, DocumentId};
+
+const WAL_FILE: &str = "wal.log";
+const SNAPSHOT_FILE: &str = "snapshot.bin";
+const SNAPSHOT_TMP_FILE: &str = "snapshot.tmp";
+const SNAPSHOT_MAGIC: &[u8; 8] = b"VDBSNAP2";
+/// En-tête des instantanés écrits avant les collections projetées en mémoire : tous les vecteurs y sont recopiés.
+const SNAPSHOT_MAGIC_V1: &[u8; 8] = b"VDBSNAP1";
+
+/// Taille de l'en-tête d'un enregistrement du journal : longueur puis crc32.
+const RECORD_HEADER_LEN: usize = 8;
+
+/// Taille des morceaux dans lesquels un instantané est encodé avant d'être écrit.
+const SNAPSHOT_CHUNK_LEN: usize = 1 << 20;
+
+/// # Structure: `StorageOptions`
+///
+/// Réglages du moteur de stockage.
+#[derive(Debug, Clone, Copy)]
+pub struct StorageOptions {
+    /// Nombre d'opérations journalisées après lequel un instantané est écrit et le journal vidé.
+    /// `0` désactive les instantanés automatiques.
+    pub snapshot_interval: usize,
+    /// Force l'écriture sur disque (`fsync`) après chaque opération journalisée.
+    pub sync_writes: bool,
+}
+
+impl Default for StorageOptions {
+    fn default() -> Self {
+        StorageOptions {
+            snapshot_interval: 10_000,
+            sync_writes: true,
+        }
+    }
+}
+
+/// # Énumération: `Record`
+///
+/// Une opération de modification de la base, telle qu'elle est écrite dans le journal.
+#[derive(Debug, Clone)]
+pub enum Record {
+    AddCollection { name: String, config: CollectionConfig },
+    DropCollection { name: String },
+    Upsert { collection: String, key: DocumentId, vector: Vec<f32>, payload: Payload },
+    Remove { collection: String, key: DocumentId },
+    /// La construction (ou la suppression, avec `None`) de l'index d'une collection.
+    SetIndex { collection: String, config: Option<IndexConfig> },
+    /// Les réglages de recherche de l'index d'une collection.
+    TuneIndex { collection: String, ef_search: Option<usize>, nprobe: Option<usize> },
+    /// L'indexation d'un champ des métadonnées d'une collection.
+    CreatePayloadIndex { collection: String, field: String },
+    /// Un lot de documents ajoutés ou mis à jour ensemble : rejoué entièrement ou pas du tout.
+    UpsertMany { collection: String, documents: Vec<Document> },
+    /// Un lot de documents supprimés ensemble.
+    RemoveMany { collection: String, keys: Vec<DocumentId> },
+    /// La construction (ou le remplacement) du quantificateur d'une collection.
+    SetQuantizer { collection: String, config: QuantizerConfig },
+    /// La suppression du quantificateur d'une collection.
+    DropQuantizer { collection: String },
+}
+
+const TAG_ADD_COLLECTION: u8 = 1;
+const TAG_UPSERT: u8 = 2;
+const TAG_REMOVE: u8 = 3;
+const TAG_SET_INDEX: u8 = 4;
+const TAG_TUNE_INDEX: u8 = 5;
+const TAG_CREATE_PAYLOAD_INDEX: u8 = 6;
+const TAG_DROP_COLLECTION: u8 = 7;
+const TAG_UPSERT_MANY: u8 = 8;
+const TAG_REMOVE_MANY: u8 = 9;
+const TAG_SET_QUANTIZER: u8 = 10;
+const TAG_DROP_QUANTIZER: u8 = 11;
+
+impl Record {
+    fn encode(&self, encoder: &mut Encoder) {
+        match self {
+            Record::AddCollection { name, config } => {
+                encoder.put_u8(TAG_ADD_COLLECTION);
+                encoder.put_str(name);
+                encoder.put_config(config);
+            }
+            Record::DropCollection { name } => {
+                encoder.put_u8(TAG_DROP_COLLECTION);
+                encoder.put_str(name);
+            }
+            Record::Upsert { collection, key, vector, payload } => {
+                encoder.put_u8(TAG_UPSERT);
+                encoder.put_str(collection);
+                encoder.put_id(key);
+                encoder.put_vector(vector);
+                encoder.put_payload(payload);
+            }
+            Record::Remove { collection, key } => {
+                encoder.put_u8(TAG_REMOVE);
+                encoder.put_str(collection);
+                encoder.put_id(key);
+            }
+            Record::SetIndex { collection, config } => {
+                encoder.put_u8(TAG_SET_INDEX);
+                encoder.put_str(collection);
+                encoder.put_index_config(config.as_ref());
+            }
+            Record::TuneIndex { collection, ef_search, nprobe } => {
+                encoder.put_u8(TAG_TUNE_INDEX);
+                encoder.put_str(collection);
+                encoder.put_option(*ef_search);
+                encoder.put_option(*nprobe);
+            }
+            Record::CreatePayloadIndex { collection, field } => {
+                encoder.put_u8(TAG_CREATE_PAYLOAD_INDEX);
+                encoder.put_str(collection);
+                encoder.put_str(field);
+            }
+            Record::UpsertMany { collection, documents } => {
+                encoder.put_u8(TAG_UPSERT_MANY);
+                encoder.put_str(collection);
+                encoder.put_u64(documents.len() as u64);
+                for (key, vector, payload) in documents {
+                    encoder.put_id(key);
+                    encoder.put_vector(vector);
+                    encoder.put_payload(payload);
+                }
+            }
+            Record::RemoveMany { collection, keys } => {
+                encoder.put_u8(TAG_REMOVE_MANY);
+                encoder.put_str(collection);
+                encoder.put_u64(keys.len() as u64);
+                for key in keys {
+                    encoder.put_id(key);
+                }
+            }
+            Record::SetQuantizer { collection, config } => {
+                encoder.put_u8(TAG_SET_QUANTIZER);
+                encoder.put_str(collection);
+                encoder.put_quantizer_config(config);
+            }
+            Record::DropQuantizer { collection } => {
+                encoder.put_u8(TAG_DROP_QUANTIZER);
+                encoder.put_str(collection);
+            }
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Record, DbError> {
+        match decoder.get_u8()? {
+            TAG_ADD_COLLECTION => Ok(Record::AddCollection {
+                name: decoder.get_str()?,
+                config: decoder.get_config()?,
+            }),
+            TAG_DROP_COLLECTION => Ok(Record::DropCollection { name: decoder.get_str()? }),
+            TAG_UPSERT => Ok(Record::Upsert {
+                collection: decoder.get_str()?,
+                key: decoder.get_id()?,
+                vector: decoder.get_vector()?,
+                payload: decoder.get_payload()?,
+            }),
+            TAG_REMOVE => Ok(Record::Remove {
+                collection: decoder.get_str()?,
+                key: decoder.get_id()?,
+            }),
+            TAG_SET_INDEX => Ok(Record::SetIndex {
+                collection: decoder.get_str()?,
+                config: decoder.get_index_config()?,
+            }),
+            TAG_TUNE_INDEX => Ok(Record::TuneIndex {
+                collection: decoder.get_str()?,
+                ef_search: decoder.get_option()?,
+                nprobe: decoder.get_option()?,
+            }),
+            TAG_CREATE_PAYLOAD_INDEX => Ok(Record::CreatePayloadIndex {
+                collection: decoder.get_str()?,
+                field: decoder.get_str()?,
+            }),
+            TAG_UPSERT_MANY => {
+                let collection = decoder.get_str()?;
+                let count = decoder.get_u64()?;
+                let mut documents = Vec::new();
+                for _ in 0..count {
+                    documents.push((decoder.get_id()?, decoder.get_vector()?, decoder.get_payload()?));
+                }
+                Ok(Record::UpsertMany { collection, documents })
+            }
+            TAG_REMOVE_MANY => {
+                let collection = decoder.get_str()?;
+                let count = decoder.get_u64()?;
+                let mut keys = Vec::new();
+                for _ in 0..count {
+                    keys.push(decoder.get_id()?);
+                }
+                Ok(Record::RemoveMany { collection, keys })
+            }
+            TAG_SET_QUANTIZER => Ok(Record::SetQuantizer {
+                collection: decoder.get_str()?,
+                config: decoder.get_quantizer_config()?,
+            }),
+            TAG_DROP_QUANTIZER => Ok(Record::DropQuantizer { collection: decoder.get_str()? }),
+            tag => Err(DbError::Corrupted(format!("type d'opération inconnu : {}", tag))),
+        }
+    }
+}
+
+/// # Structure: `Storage`
+///
+/// Le journal ouvert en écriture, rattaché à une [`Database`](crate::Database) persistante.
+pub struct Storage {
+    dir: PathBuf,
+    wal: File,
+    /// Longueur du journal après la dernière opération écrite entièrement.
+    wal_len: u64,
+    /// Longueur du journal avant la dernière opération écrite, pour pouvoir l'en retirer.
+    previous_len: u64,
+    options: StorageOptions,
+    /// Numéro de séquence de la prochaine opération journalisée.
+    next_lsn: u64,
+    /// Nombre d'opérations écrites dans le journal depuis le dernier instantané.
+    pending: usize,
+}
+
+/// Contenu d'un répertoire de données relu à l'ouverture.
+pub struct Recovered {
+    /// Les collections de l'instantané, s'il en existe un.
+    pub collections: HashMap<String, Collection>,
+    /// Les opérations qui reconstruisent les index de l'instantané, à appliquer en premier.
+    pub settings: Vec<Record>,
+    /// Les opérations du journal postérieures à l'instantané, à rejouer ensuite.
+    pub journal: Replay,
+}
+
+impl Storage {
+    /// Ouvre (ou crée) un répertoire de données et relit son instantané ; le journal est ensuite relu
+    /// par [`Replay::next_record`], puis [`Replay::finish`] donne le stockage.
+    ///
+    /// # Paramètres
+    /// - `dir`: Le répertoire de données.
+    /// - `options`: Les réglages du moteur de stockage.
+    ///
+    /// # Retour
+    /// - `Result<Recovered, DbError>`: L'instantané, les opérations qui reconstruisent ses index et le journal.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let Recovered { collections, settings, mut journal } = Storage::open(Path::new("./data"), StorageOptions::default())?;
+    /// while let Some(record) = journal.next_record()? {
+    ///     // Appliquer l'opération
+    /// }
+    /// let storage = journal.finish()?;
+    /// ```
+    pub fn open(dir: &Path, options: StorageOptions) -> Result<Recovered, DbError> {
+        fs::create_dir_all(dir)?;
+        // Un instantané temporaire est le reste d'une écriture interrompue : il n'a jamais été validé.
+        let _ = fs::remove_file(dir.join(SNAPSHOT_TMP_FILE));
+
+        let (snapshot_lsn, collections, settings) = read_snapshot(dir)?.unwrap_or_default();
+        remove_unused_files(dir, &collections)?;
+
+        let wal_path = dir.join(WAL_FILE);
+        let reader = match File::open(&wal_path) {
+            Ok(file) => Some((file.metadata()?.len(), BufReader::new(file))),
+            Err(error) if error.kind() == ErrorKind::NotFound => None,
+            Err(error) => return Err(error.into()),
+        };
+        let journal = Replay {
+            dir: dir.to_path_buf(),
+            options,
+            reader,
+            valid_len: 0,
+            snapshot_lsn,
+            last_lsn: snapshot_lsn,
+            replayed: 0,
+        };
+        Ok(Recovered { collections, settings, journal })
+    }
+
+    /// Ajoute une opération à la fin du journal.
+    ///
+    /// # Paramètres
+    /// - `record`: L'opération à journaliser.
+    pub fn append(&mut self, record: &Record) -> Result<(), DbError> {
+        let mut encoder = Encoder::default();
+        encoder.put_u64(self.next_lsn);
+        record.encode(&mut encoder);
+        let payload = encoder.0;
+
+        let mut frame = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+        frame.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        frame.extend_from_slice(&crc32(&payload).to_le_bytes());
+        frame.extend_from_slice(&payload);
+        let written = self.wal.write_all(&frame).and_then(|_| {
+            if self.options.sync_writes {
+                self.wal.sync_data()
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(error) = written {
+            // Une trame écrite partiellement masquerait les opérations suivantes lors du rejeu.
+            let _ = self.wal.set_len(self.wal_len);
+            return Err(error.into());
+        }
+
+        self.previous_len = self.wal_len;
+        self.wal_len += frame.len() as u64;
+        self.next_lsn += 1;
+        self.pending += 1;
+        Ok(())
+    }
+
+    /// Retire du journal la dernière opération ajoutée par [`Storage::append`], lorsqu'elle
+    /// n'a pas pu être appliquée en mémoire.
+    pub fn undo_append(&mut self) -> Result<(), DbError> {
+        self.wal.set_len(self.previous_len)?;
+        if self.options.sync_writes {
+            self.wal.sync_data()?;
+        }
+        self.wal_len = self.previous_len;
+        self.next_lsn -= 1;
+        self.pending = self.pending.saturating_sub(1);
+        Ok(())
+    }
+
+    /// Indique si le nombre d'opérations journalisées justifie l'écriture d'un instantané.
+    pub fn snapshot_due(&self) -> bool {
+        self.options.snapshot_interval > 0 && self.pending >= self.options.snapshot_interval
+    }
+
+    /// Écrit un instantané complet des collections puis vide le journal.
+    ///
+    /// L'instantané est d'abord écrit dans un fichier temporaire puis renommé, de sorte qu'un crash
+    /// pendant l'écriture laisse l'ancien instantané et le journal intacts. Les fichiers de vecteurs
+    /// sont synchronisés sur disque avant, et leurs emplacements libérés ne sont réutilisés qu'après.
+    ///
+    /// # Paramètres
+    /// - `collections`: L'état courant de la base.
+    pub fn snapshot(&mut self, collections: &mut HashMap<String, Collection>) -> Result<(), DbError> {
+        for vectors in collections.values().filter_map(|collection| collection.data.as_mapped()) {
+            vectors.sync()?;
+        }
+        let tmp_path = self.dir.join(SNAPSHOT_TMP_FILE);
+        write_snapshot(&tmp_path, self.next_lsn - 1, collections)?;
+        fs::rename(&tmp_path, self.dir.join(SNAPSHOT_FILE))?;
+        sync_dir(&self.dir)?;
+        for vectors in collections.values_mut().filter_map(|collection| collection.data.as_mapped_mut()) {
+            vectors.checkpoint();
+        }
+        remove_unused_files(&self.dir, collections)?;
+
+        self.wal.set_len(0)?;
+        self.wal.sync_all()?;
+        self.wal_len = 0;
+        self.previous_len = 0;
+        self.pending = 0;
+        Ok(())
+    }
+}
+
+/// # Structure: `Replay`
+///
+/// Le journal d'un répertoire de données, relu opération par opération à l'ouverture.
+pub struct Replay {
+    dir: PathBuf,
+    options: StorageOptions,
+    /// La taille du journal et sa lecture, `None` une fois la fin de sa partie valide atteinte.
+    reader: Option<(u64, BufReader<File>)>,
+    /// Longueur de la partie du journal déjà relue et valide.
+    valid_len: u64,
+    /// Numéro de séquence de la dernière opération de l'instantané.
+    snapshot_lsn: u64,
+    /// Numéro de séquence de la dernière opération relue.
+    last_lsn: u64,
+    /// Nombre d'opérations relues postérieures à l'instantané.
+    replayed: usize,
+}
+
+impl Replay {
+    /// Relit l'opération suivante du journal, en passant celles que l'instantané contient déjà.
+    ///
+    /// # Retour
+    /// - `Result<Option<Record>, DbError>`: L'opération, `None` à la fin de la partie valide du journal,
+    ///   ou [`DbError::Io`] si le journal ne peut pas être lu.
+    pub fn next_record(&mut self) -> Result<Option<Record>, DbError> {
+        while let Some((lsn, record)) = self.next_entry()? {
+            self.last_lsn = self.last_lsn.max(lsn);
+            if lsn > self.snapshot_lsn {
+                self.replayed += 1;
+                return Ok(Some(record));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Lit la trame suivante du journal et son numéro de séquence.
+    fn next_entry(&mut self) -> Result<Option<(u64, Record)>, DbError> {
+        let Some((file_len, reader)) = self.reader.as_mut() else {
+            return Ok(None);
+        };
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        if read_full(reader, &mut header)? {
+            let len = u32::from_le_bytes([header[0], header[1], header[2], header[3]]) as u64;
+            let checksum = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+            if self.valid_len + RECORD_HEADER_LEN as u64 + len <= *file_len {
+                let mut payload = vec![0u8; len as usize];
+                if read_full(reader, &mut payload)? && crc32(&payload) == checksum {
+                    let mut decoder = Decoder::new(&payload);
+                    if let Ok(entry) = decoder.get_u64().and_then(|lsn| Ok((lsn, Record::decode(&mut decoder)?))) {
+                        self.valid_len += RECORD_HEADER_LEN as u64 + len;
+                        return Ok(Some(entry));
+                    }
+                }
+            }
+        }
+        self.reader = None;
+        Ok(None)
+    }
+
+    /// Termine la relecture du journal, tronque sa partie invalide et l'ouvre en écriture.
+    ///
+    /// # Retour
+    /// - `Result<Storage, DbError>`: Le journal prêt à recevoir de nouvelles opérations.
+    pub fn finish(mut self) -> Result<Storage, DbError> {
+        while self.next_record()?.is_some() {}
+        let wal = OpenOptions::new().create(true).append(true).open(self.dir.join(WAL_FILE))?;
+        // Suppression de la fin du journal qui n'a pas pu être relue.
+        if wal.metadata()?.len() != self.valid_len {
+            wal.set_len(self.valid_len)?;
+            wal.sync_all()?;
+        }
+        Ok(Storage {
+            dir: self.dir,
+            wal,
+            wal_len: self.valid_len,
+            previous_len: self.valid_len,
+            options: self.options,
+            next_lsn: self.last_lsn + 1,
+            pending: self.replayed,
+        })
+    }
+}
+
+/// Lit exactement `buffer.len()` octets. Retourne `false` si le fichier se termine avant.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> Result<bool, DbError> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(true),
+        Err(error) if error.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(error) => Err(error.into()),
+    }
+}
+
+/// Écrit un instantané : en-tête, numéro de séquence, collections (avec, pour une collection projetée
+/// en mémoire, le nom de son fichier de vecteurs et l'emplacement de chaque document à la place de son
+/// vecteur), opérations qui reconstruisent leurs index, puis crc32 de l'ensemble.
+///
+/// L'instantané est encodé et écrit par morceaux d'environ [`SNAPSHOT_CHUNK_LEN`] octets, la somme
+/// de contrôle étant calculée au fil de l'écriture : il n'est jamais entièrement en mémoire.
+fn write_snapshot(path: &Path, lsn: u64, collections: &HashMap<String, Collection>) -> Result<(), DbError> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut crc = CRC32_INIT;
+    let mut encoder = Encoder::default();
+    let mut flush = |encoder: &mut Encoder| -> io::Result<()> {
+        crc = crc32_update(crc, &encoder.0);
+        writer.write_all(&encoder.0)?;
+        encoder.0.clear();
+        Ok(())
+    };
+
+    encoder.0.extend_from_slice(SNAPSHOT_MAGIC);
+    encoder.put_u64(lsn);
+    encoder.put_u64(collections.len() as u64);
+    for (name, collection) in collections {
+        encoder.put_str(name);
+        encoder.put_config(&CollectionConfig {
+            metric: collection.metric,
+            dimension: collection.dimension,
+            normalize: collection.normalize,
+            mapped: collection.data.is_mapped(),
+        });
+        encoder.put_u64(collection.data.len() as u64);
+        let empty = Payload::new();
+        let payload = |key| collection.payloads.get(key).unwrap_or(&empty);
+        match collection.data.as_mapped().and_then(|vectors| Some((vectors.file_name()?, vectors))) {
+            // Les vecteurs restent dans leur fichier : seul leur emplacement est écrit.
+            Some((file_name, vectors)) => {
+                encoder.put_str(file_name);
+                for (key, slot) in vectors.slots() {
+                    encoder.put_id(key);
+                    encoder.put_u64(slot as u64);
+                    encoder.put_payload(payload(key));
+                    if encoder.0.len() >= SNAPSHOT_CHUNK_LEN {
+                        flush(&mut encoder)?;
+                    }
+                }
+            }
+            None => {
+                if collection.data.is_mapped() {
+                    // Un fichier temporaire : les vecteurs sont recopiés, comme ceux d'une collection en mémoire.
+                    encoder.put_str("");
+                }
+                for (key, vector) in collection.data.iter() {
+                    encoder.put_id(key);
+                    encoder.put_vector(vector);
+                    encoder.put_payload(payload(key));
+                    if encoder.0.len() >= SNAPSHOT_CHUNK_LEN {
+                        flush(&mut encoder)?;
+                    }
+                }
+            }
+        }
+    }
+    let mut settings = Vec::new();
+    for (name, collection) in collections {
+        if let Some(index) = &collection.index {
+            settings.push(Record::SetIndex { collection: name.clone(), config: Some(index.config()) });
+        }
+        for field in collection.payload_index.fields() {
+            settings.push(Record::CreatePayloadIndex { collection: name.clone(), field: field.to_string() });
+        }
+        if let Some(quantizer) = &collection.quantizer {
+            settings.push(Record::SetQuantizer { collection: name.clone(), config: quantizer.config() });
+        }
+    }
+    encoder.put_u64(settings.len() as u64);
+    for record in &settings {
+        record.encode(&mut encoder);
+    }
+    flush(&mut encoder)?;
+    writer.write_all(&(!crc).to_le_bytes())?;
+    writer.into_inner().map_err(|error| error.into_error())?.sync_all()?;
+    Ok(())
+}
+
+/// Un instantané relu : le numéro de séquence de sa dernière opération, les collections
+/// et les opérations qui reconstruisent leurs index.
+type Snapshot = (u64, HashMap<String, Collection>, Vec<Record>);
+
+/// Relit l'instantané du répertoire de données, s'il existe. Les fichiers de vecteurs des collections
+/// projetées en mémoire sont rouverts dans ce même répertoire.
+fn read_snapshot(dir: &Path) -> Result<Option<Snapshot>, DbError> {
+    let file = match File::open(dir.join(SNAPSHOT_FILE)) {
+        Ok(file) => file,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(error.into()),
+    };
+    // Projeté plutôt que lu : l'instantané d'une base plus grande que la mémoire vive n'a pas à y tenir.
+    #[cfg(unix)]
+    let bytes = MappedFile::open(&file)?;
+    #[cfg(not(unix))]
+    let bytes = {
+        let mut bytes = Vec::new();
+        BufReader::new(file).read_to_end(&mut bytes)?;
+        bytes
+    };
+    let magic = bytes.get(..SNAPSHOT_MAGIC.len()).filter(|_| bytes.len() >= SNAPSHOT_MAGIC.len() + 4);
+    let with_files = match magic {
+        Some(magic) if magic == SNAPSHOT_MAGIC => true,
+        Some(magic) if magic == SNAPSHOT_MAGIC_V1 => false,
+        _ => return Err(DbError::Corrupted("en-tête d'instantané invalide".to_string())),
+    };
+    let (content, checksum) = bytes.split_at(bytes.len() - 4);
+    if crc32(content).to_le_bytes() != checksum {
+        return Err(DbError::Corrupted("somme de contrôle de l'instantané invalide".to_string()));
+    }
+
+    let mut decoder = Decoder::new(&content[SNAPSHOT_MAGIC.len()..]);
+    let lsn = decoder.get_u64()?;
+    let count = decoder.get_u64()?;
+    let mut collections = HashMap::new();
+    for _ in 0..count {
+        let name = decoder.get_str()?;
+        let config = decoder.get_config()?;
+        let length = decoder.get_u64()?;
+        let file_name = if with_files && config.mapped { decoder.get_str()? } else { String::new() };
+        let collection = if file_name.is_empty() {
+            let mut collection = Collection::open(config, Some(dir))?;
+            for _ in 0..length {
+                let key = decoder.get_id()?;
+                let vector = decoder.get_vector()?;
+                collection.restore(key, vector, decoder.get_payload()?)?;
+            }
+            collection
+        } else {
+            if !is_vector_file(&file_name) {
+                return Err(DbError::Corrupted(format!("fichier de vecteurs '{}' invalide", file_name)));
+            }
+            let mut slots = Vec::new();
+            let mut payloads = Vec::new();
+            for _ in 0..length {
+                let key = decoder.get_id()?;
+                slots.push((key, decoder.get_usize()?));
+                payloads.push((key, decoder.get_payload()?));
+            }
+            let mut collection = Collection::new(config);
+            collection.data = VectorStore::open_mapped(&dir.join(&file_name), config.dimension, slots)?;
+            for (key, payload) in payloads {
+                collection.reattach(key, payload)?;
+            }
+            collection
+        };
+        collections.insert(name, collection);
+    }
+    let mut settings = Vec::new();
+    for _ in 0..decoder.get_u64()? {
+        settings.push(Record::decode(&mut decoder)?);
+    }
+    Ok(Some((lsn, collections, settings)))
+}
+
+/// Indique si `name` est le nom d'un fichier de vecteurs de collection projetée en mémoire.
+fn is_vector_file(name: &str) -> bool {
+    name.starts_with(FILE_PREFIX) && name.ends_with(FILE_EXTENSION) && !name.contains(['/', '\\'])
+}
+
+/// Supprime les fichiers de vecteurs du répertoire de données qu'aucune collection n'utilise.
+fn remove_unused_files(dir: &Path, collections: &HashMap<String, Collection>) -> Result<(), DbError> {
+    let used: HashSet<&str> = collections
+        .values()
+        .filter_map(|collection| collection.data.as_mapped()?.file_name())
+        .collect();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name();
+        match name.to_str() {
+            Some(name) if is_vector_file(name) && !used.contains(name) => fs::remove_file(dir.join(name))?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Force l'écriture sur disque d'un répertoire (pour rendre un renommage durable).
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    File::open(dir)?.sync_all()?;
+    #[cfg(not(unix))]
+    let _ = dir;
+    Ok(())
+}
+
+/// Table du CRC-32 (polynôme IEEE 802.3, forme réfléchie), calculée à la compilation.
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// Calcule la somme de contrôle CRC-32 d'une suite d'octets.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    !crc32_update(CRC32_INIT, bytes)
+}
+
+/// État initial d'un CRC-32 calculé par morceaux : [`crc32_update`] y ajoute chaque morceau,
+/// et la somme de contrôle est le complément de l'état final.
+const CRC32_INIT: u32 = !0;
+
+/// Ajoute des octets à un CRC-32 calculé par morceaux.
+fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    bytes.iter().fold(crc, |crc, &byte| CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+/// Encodeur binaire petit-boutiste utilisé par le journal et les instantanés.
+#[derive(Default)]
+struct Encoder(Vec<u8>);
+
+impl Encoder {
+    fn put_u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn put_u32(&mut self, value: u32) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u64(&mut self, value: u64) {
+        self.0.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_str(&mut self, value: &str) {
+        self.put_u32(value.len() as u32);
+        self.0.extend_from_slice(value.as_bytes());
+    }
+
+    fn put_id(&mut self, key: &DocumentId) {
+        self.0.extend_from_slice(key.as_bytes());
+    }
+
+    fn put_vector(&mut self, vector: &[f32]) {
+        self.put_u32(vector.len() as u32);
+        for value in vector {
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    fn put_payload(&mut self, payload: &Payload) {
+        self.put_u32(payload.len() as u32);
+        for (key, value) in payload {
+            self.put_str(key);
+            self.put_value(value);
+        }
+    }
+
+    fn put_value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.put_u8(0),
+            Value::Bool(value) => {
+                self.put_u8(1);
+                self.put_u8(*value as u8);
+            }
+            Value::Number(value) => {
+                self.put_u8(2);
+                self.0.extend_from_slice(&value.to_le_bytes());
+            }
+            Value::String(value) => {
+                self.put_u8(3);
+                self.put_str(value);
+            }
+            Value::Array(values) => {
+                self.put_u8(4);
+                self.put_u32(values.len() as u32);
+                for value in values {
+                    self.put_value(value);
+                }
+            }
+            Value::Object(payload) => {
+                self.put_u8(5);
+                self.put_payload(payload);
+            }
+        }
+    }
+
+    /// Écrit un entier facultatif : un octet de présence, suivi de la valeur si elle est présente.
+    fn put_option(&mut self, value: Option<usize>) {
+        self.put_u8(u8::from(value.is_some()));
+        if let Some(value) = value {
+            self.put_u64(value as u64);
+        }
+    }
+
+    /// Écrit un réel facultatif : un octet de présence, suivi de la valeur si elle est présente.
+    fn put_option_f32(&mut self, value: Option<f32>) {
+        self.put_u8(u8::from(value.is_some()));
+        if let Some(value) = value {
+            self.0.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+
+    /// Écrit le type d'un quantificateur suivi de ses paramètres.
+    fn put_quantizer_config(&mut self, config: &QuantizerConfig) {
+        match config {
+            QuantizerConfig::Product(params) => {
+                self.put_u8(1);
+                self.put_u64(params.subspaces as u64);
+                self.put_u64(params.centroids as u64);
+                self.put_option(params.sample_size);
+                self.put_u64(params.iterations as u64);
+                self.put_option(params.rerank);
+            }
+            QuantizerConfig::Scalar(params) => {
+                self.put_u8(2);
+                self.put_u8(match params.bits {
+                    ScalarBits::Int8 => 8,
+                    ScalarBits::Int4 => 4,
+                });
+                self.put_u8(u8::from(params.calibration == Calibration::Global));
+                self.put_option_f32(params.oversampling);
+            }
+            QuantizerConfig::Binary(params) => {
+                self.put_u8(3);
+                self.0.extend_from_slice(&params.oversampling.to_le_bytes());
+            }
+        }
+    }
+
+    /// Écrit le type d'un index (0 s'il n'y en a pas) suivi de ses paramètres.
+    fn put_index_config(&mut self, config: Option<&IndexConfig>) {
+        match config {
+            None => self.put_u8(0),
+            Some(IndexConfig::Hnsw(params)) => {
+                self.put_u8(1);
+                self.put_u64(params.m as u64);
+                self.put_u64(params.ef_construction as u64);
+                self.put_u64(params.ef_search as u64);
+            }
+            Some(IndexConfig::Ivf(params)) => {
+                self.put_u8(2);
+                self.put_u64(params.nlist as u64);
+                self.put_u64(params.nprobe as u64);
+                self.put_option(params.sample_size);
+                self.put_u64(params.iterations as u64);
+            }
+            Some(IndexConfig::Lsh(params)) => {
+                self.put_u8(3);
+                self.put_u64(params.bits as u64);
+                self.put_u64(params.tables as u64);
+                self.put_u64(params.probes as u64);
+            }
+        }
+    }
+
+    /// Écrit la métrique, puis un octet d'options (bit 0 : dimension fixée, bit 1 : normalisation)
+    /// suivi de la dimension si elle est fixée.
+    fn put_config(&mut self, config: &CollectionConfig) {
+        self.put_u8(metric_tag(config.metric));
+        let flags = u8::from(config.dimension.is_some()) | u8::from(config.normalize) << 1 | u8::from(config.mapped) << 2;
+        self.put_u8(flags);
+        if let Some(dimension) = config.dimension {
+            self.put_u64(dimension as u64);
+        }
+    }
+}
+
+/// Décodeur correspondant à [`Encoder`] ; toute lecture hors limites est une corruption.
+struct Decoder<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Decoder { bytes, position: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DbError> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| DbError::Corrupted("enregistrement tronqué".to_string()))?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn get_u8(&mut self) -> Result<u8, DbError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn get_u32(&mut self) -> Result<u32, DbError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn get_u64(&mut self) -> Result<u64, DbError> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn get_str(&mut self) -> Result<String, DbError> {
+        let len = self.get_u32()? as usize;
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| DbError::Corrupted("chaîne UTF-8 invalide".to_string()))
+    }
+
+    fn get_id(&mut self) -> Result<DocumentId, DbError> {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(self.take(16)?);
+        Ok(DocumentId::from_bytes(bytes))
+    }
+
+    fn get_vector(&mut self) -> Result<Vec<f32>, DbError> {
+        let len = self.get_u32()? as usize;
+        let bytes = self.take(len.saturating_mul(4))?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect())
+    }
+
+    fn get_payload(&mut self) -> Result<Payload, DbError> {
+        let len = self.get_u32()?;
+        let mut payload = Payload::new();
+        for _ in 0..len {
+            let key = self.get_str()?;
+            payload.insert(key, self.get_value()?);
+        }
+        Ok(payload)
+    }
+
+    fn get_value(&mut self) -> Result<Value, DbError> {
+        match self.get_u8()? {
+            0 => Ok(Value::Null),
+            1 => Ok(Value::Bool(self.get_u8()? != 0)),
+            2 => Ok(Value::Number(f64::from_bits(self.get_u64()?))),
+            3 => Ok(Value::String(self.get_str()?)),
+            4 => {
+                let len = self.get_u32()?;
+                let values = (0..len).map(|_| self.get_value()).collect::<Result<_, _>>()?;
+                Ok(Value::Array(values))
+            }
+            5 => Ok(Value::Object(self.get_payload()?)),
+            tag => Err(DbError::Corrupted(format!("type de valeur inconnu : {}", tag))),
+        }
+    }
+
+    fn get_usize(&mut self) -> Result<usize, DbError> {
+        Ok(self.get_u64()? as usize)
+    }
+
+    fn get_option(&mut self) -> Result<Option<usize>, DbError> {
+        match self.get_u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.get_usize()?)),
+        }
+    }
+
+    fn get_f32(&mut self) -> Result<f32, DbError> {
+        let bytes = self.take(4)?;
+        Ok(f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn get_option_f32(&mut self) -> Result<Option<f32>, DbError> {
+        match self.get_u8()? {
+            0 => Ok(None),
+            _ => Ok(Some(self.get_f32()?)),
+        }
+    }
+
+    fn get_quantizer_config(&mut self) -> Result<QuantizerConfig, DbError> {
+        match self.get_u8()? {
+            1 => Ok(QuantizerConfig::Product(PqParams {
+                subspaces: self.get_usize()?,
+                centroids: self.get_usize()?,
+                sample_size: self.get_option()?,
+                iterations: self.get_usize()?,
+                rerank: self.get_option()?,
+            })),
+            2 => Ok(QuantizerConfig::Scalar(SqParams {
+                bits: match self.get_u8()? {
+                    8 => ScalarBits::Int8,
+                    4 => ScalarBits::Int4,
+                    bits => return Err(DbError::Corrupted(format!("nombre de bits de quantification inconnu : {}", bits))),
+                },
+                calibration: match self.get_u8()? {
+                    0 => Calibration::PerDimension,
+                    _ => Calibration::Global,
+                },
+                oversampling: self.get_option_f32()?,
+            })),
+            3 => Ok(QuantizerConfig::Binary(BqParams { oversampling: self.get_f32()? })),
+            tag => Err(DbError::Corrupted(format!("type de quantificateur inconnu : {}", tag))),
+        }
+    }
+
+    fn get_index_config(&mut self) -> Result<Option<IndexConfig>, DbError> {
+        match self.get_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(IndexConfig::Hnsw(HnswParams {
+                m: self.get_usize()?,
+                ef_construction: self.get_usize()?,
+                ef_search: self.get_usize()?,
+            }))),
+            2 => Ok(Some(IndexConfig::Ivf(IvfParams {
+                nlist: self.get_usize()?,
+                nprobe: self.get_usize()?,
+                sample_size: self.get_option()?,
+                iterations: self.get_usize()?,
+            }))),
+            3 => Ok(Some(IndexConfig::Lsh(LshParams {
+                bits: self.get_usize()?,
+                tables: self.get_usize()?,
+                probes: self.get_usize()?,
+            }))),
+            tag => Err(DbError::Corrupted(format!("type d'index inconnu : {}", tag))),
+        }
+    }
+
+    fn get_config(&mut self) -> Result<CollectionConfig, DbError> {
+        let metric = metric_from_tag(self.get_u8()?)?;
+        let flags = self.get_u8()?;
+        let dimension = match flags & 1 {
+            0 => None,
+            _ => Some(self.get_u64()? as usize),
+        };
+        Ok(CollectionConfig {
+            metric,
+            dimension,
+            normalize: flags & 2 != 0,
+            mapped: flags & 4 != 0,
+        })
+    }
+}
+
+fn metric_tag(metric: Metric) -> u8 {
+    match metric {
+        Metric::Cosine => 0,
+        Metric::Dot => 1,
+        Metric::Euclidean => 2,
+        Metric::Manhattan => 3,
+        Metric::Hamming => 4,
+    }
+}
+
+fn metric_from_tag(tag: u8) -> Result<Metric, DbError> {
+    match tag {
+        0 => Ok(Metric::Cosine),
+        1 => Ok(Metric::Dot),
+        2 => Ok(Metric::Euclidean),
+        3 => Ok(Metric::Manhattan),
+        4 => Ok(Metric::Hamming),
+        _ => Err(DbError::Corrupted(format!("métrique inconnue : {}", tag))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{CompareOp, Filter};
+    use crate::index::VectorIndex;
+    use crate::quantizer::Quantizer;
+    use std::collections::HashSet;
+    use crate::Database;
+    use uuid::Uuid;
+
+    /// Un répertoire de données temporaire, supprimé à la fin du test.
+    struct DataDir(PathBuf);
+
+    impl DataDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("projet-test-{}", Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            DataDir(path)
+        }
+
+        fn wal(&self) -> PathBuf {
+            self.0.join(WAL_FILE)
+        }
+    }
+
+    impl Drop for DataDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    const OPTIONS: StorageOptions = StorageOptions { snapshot_interval: 0, sync_writes: false };
+
+    /// Crée la collection `docs` puis y écrit les documents `1..=count`, une opération chacun.
+    fn write_documents(dir: &DataDir, count: u128) {
+        let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+        for n in 1..=count {
+            let payload = Payload::from([("n".to_string(), Value::Number(n as f64))]);
+            db.add_or_update("docs", id(n), vec![n as f32, 1.0], payload).unwrap();
+        }
+    }
+
+    /// Les identifiants des documents de `docs`, triés.
+    fn documents(db: &Database) -> Vec<DocumentId> {
+        let mut keys: Vec<DocumentId> = db.get_collection("docs").unwrap().data.keys().copied().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Relit le répertoire de données : les collections de l'instantané, les opérations du journal
+    /// postérieures à l'instantané, et le stockage prêt à en recevoir d'autres.
+    fn reopen(dir: &DataDir, options: StorageOptions) -> (HashMap<String, Collection>, Vec<Record>, Storage) {
+        let Recovered { collections, mut journal, .. } = Storage::open(&dir.0, options).unwrap();
+        let mut records = Vec::new();
+        while let Some(record) = journal.next_record().unwrap() {
+            records.push(record);
+        }
+        (collections, records, journal.finish().unwrap())
+    }
+
+    /// Positions de début des trames du journal.
+    fn frame_offsets(wal: &[u8]) -> Vec<usize> {
+        let mut offsets = Vec::new();
+        let mut offset = 0;
+        while offset < wal.len() {
+            offsets.push(offset);
+            let len = u32::from_le_bytes(wal[offset..offset + 4].try_into().unwrap()) as usize;
+            offset += RECORD_HEADER_LEN + len;
+        }
+        offsets
+    }
+
+    #[test]
+    fn records_are_encoded_and_decoded() {
+        let payload = Payload::from([
+            ("titre".to_string(), Value::String("Acte".to_string())),
+            ("tags".to_string(), Value::Array(vec![Value::Bool(true), Value::Null, Value::Number(-2.5)])),
+            ("auteur".to_string(), Value::Object(Payload::from([("nom".to_string(), Value::from("Durand"))]))),
+        ]);
+        let records = vec![
+            Record::AddCollection {
+                name: "docs".to_string(),
+                config: CollectionConfig { metric: Metric::Manhattan, dimension: Some(3), normalize: true, mapped: false },
+            },
+            Record::Upsert { collection: "docs".to_string(), key: id(1), vector: vec![1.0, -2.0, 3.5], payload },
+            Record::Remove { collection: "docs".to_string(), key: id(3) },
+            Record::UpsertMany {
+                collection: "docs".to_string(),
+                documents: vec![(id(4), vec![0.5, 1.0, 1.5], Payload::new()), (id(5), vec![-1.0, 0.0, 2.0], Payload::new())],
+            },
+            Record::RemoveMany { collection: "docs".to_string(), keys: vec![id(4), id(5)] },
+            Record::SetIndex { collection: "docs".to_string(), config: Some(IndexConfig::Hnsw(HnswParams { m: 4, ..Default::default() })) },
+            Record::SetIndex {
+                collection: "docs".to_string(),
+                config: Some(IndexConfig::Ivf(IvfParams { sample_size: Some(500), ..Default::default() })),
+            },
+            Record::SetIndex { collection: "docs".to_string(), config: Some(IndexConfig::Lsh(LshParams::default())) },
+            Record::SetIndex { collection: "docs".to_string(), config: None },
+            Record::TuneIndex { collection: "docs".to_string(), ef_search: Some(64), nprobe: None },
+            Record::CreatePayloadIndex { collection: "docs".to_string(), field: "client.ville".to_string() },
+            Record::SetQuantizer {
+                collection: "docs".to_string(),
+                config: QuantizerConfig::Product(PqParams { sample_size: None, rerank: Some(40), ..Default::default() }),
+            },
+            Record::SetQuantizer {
+                collection: "docs".to_string(),
+                config: QuantizerConfig::Scalar(SqParams {
+                    bits: ScalarBits::Int4,
+                    calibration: Calibration::Global,
+                    oversampling: Some(2.5),
+                }),
+            },
+            Record::SetQuantizer { collection: "docs".to_string(), config: QuantizerConfig::Binary(BqParams { oversampling: 6.0 }) },
+            Record::DropQuantizer { collection: "docs".to_string() },
+            Record::DropCollection { name: "docs".to_string() },
+        ];
+        for record in records {
+            let mut encoder = Encoder::default();
+            record.encode(&mut encoder);
+            let decoded = Record::decode(&mut Decoder::new(&encoder.0)).unwrap();
+            assert_eq!(format!("{:?}", decoded), format!("{:?}", record));
+        }
+        assert!(matches!(Record::decode(&mut Decoder::new(&[42])), Err(DbError::Corrupted(_))));
+        assert!(matches!(Record::decode(&mut Decoder::new(&[TAG_REMOVE, 1, 0])), Err(DbError::Corrupted(_))));
+    }
+
+    #[test]
+    fn journal_is_replayed_after_a_crash() {
+        let dir = DataDir::new();
+        write_documents(&dir, 3);
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.remove("docs", &id(2)).unwrap();
+        }
+
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(1), id(3)]);
+        let collection = db.get_collection("docs").unwrap();
+        assert_eq!(collection.get(&id(3)), Some([3.0, 1.0].as_slice()));
+        assert_eq!(collection.get_payload(&id(3)).unwrap()["n"], Value::Number(3.0));
+    }
+
+    #[test]
+    fn truncated_last_frame_is_dropped() {
+        let dir = DataDir::new();
+        write_documents(&dir, 3);
+        let wal = fs::read(dir.wal()).unwrap();
+        let last = *frame_offsets(&wal).last().unwrap();
+
+        for cut in [last + 1, last + RECORD_HEADER_LEN, wal.len() - 1] {
+            fs::write(dir.wal(), &wal[..cut]).unwrap();
+            let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(documents(&db), vec![id(1), id(2)]);
+            // La fin illisible est retirée du fichier.
+            assert_eq!(fs::metadata(dir.wal()).unwrap().len(), last as u64);
+        }
+
+        // Les opérations suivantes sont écrites à la place de la trame perdue et relues normalement.
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_or_update("docs", id(4), vec![4.0, 1.0], Payload::new()).unwrap();
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(1), id(2), id(4)]);
+    }
+
+    #[test]
+    fn corrupted_frame_ends_the_journal() {
+        let dir = DataDir::new();
+        write_documents(&dir, 4);
+        let wal = fs::read(dir.wal()).unwrap();
+        let offsets = frame_offsets(&wal);
+
+        // Un octet modifié dans la trame du document 2 : seules les opérations qui la précèdent sont gardées.
+        for position in [offsets[2] + 4, offsets[2] + RECORD_HEADER_LEN + 3, offsets[3] - 1] {
+            let mut corrupted = wal.clone();
+            corrupted[position] ^= 0x40;
+            fs::write(dir.wal(), &corrupted).unwrap();
+            let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(documents(&db), vec![id(1)]);
+            assert_eq!(fs::metadata(dir.wal()).unwrap().len(), offsets[2] as u64);
+        }
+    }
+
+    #[test]
+    fn snapshot_empties_the_journal() {
+        let dir = DataDir::new();
+        write_documents(&dir, 3);
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.snapshot().unwrap();
+            assert_eq!(fs::metadata(dir.wal()).unwrap().len(), 0);
+            db.add_or_update("docs", id(4), vec![4.0, 1.0], Payload::new()).unwrap();
+        }
+
+        let (collections, records, storage) = reopen(&dir, OPTIONS);
+        assert_eq!(collections["docs"].len(), 3);
+        assert_eq!(records.len(), 1);
+        assert_eq!(storage.next_lsn, 6);
+        drop(storage);
+
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(1), id(2), id(3), id(4)]);
+    }
+
+    #[test]
+    fn payloads_round_trip_through_the_journal_and_the_snapshot() {
+        let dir = DataDir::new();
+        let nested = Payload::from([
+            ("vide".to_string(), Value::Object(Payload::new())),
+            ("liste".to_string(), Value::Array(vec![Value::Null, Value::from(-0.5), Value::Array(vec![])])),
+        ]);
+        let payload = Payload::from([
+            ("nul".to_string(), Value::Null),
+            ("vrai".to_string(), Value::Bool(true)),
+            ("nombre".to_string(), Value::Number(-1.25e300)),
+            ("texte".to_string(), Value::from("élan \"cité\"\n")),
+            ("".to_string(), Value::Object(nested)),
+        ]);
+        let payloads = |db: &Database| {
+            let collection = db.get_collection("docs").unwrap();
+            [id(1), id(2)].map(|key| collection.get_payload(&key).cloned())
+        };
+        let expected = [Some(payload.clone()), Some(Payload::new())];
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            db.add_or_update("docs", id(1), vec![1.0], payload.clone()).unwrap();
+            db.add_or_update("docs", id(2), vec![2.0], payload.clone()).unwrap();
+            // Une mise à jour remplace les métadonnées, même par des métadonnées vides.
+            db.add_or_update("docs", id(2), vec![2.0], Payload::new()).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(payloads(&db), expected);
+            db.snapshot().unwrap();
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(fs::metadata(dir.wal()).unwrap().len(), 0);
+        assert_eq!(payloads(&db), expected);
+    }
+
+    #[test]
+    fn index_settings_survive_the_journal_and_the_snapshot() {
+        let dir = DataDir::new();
+        write_documents(&dir, 20);
+        let hnsw = HnswParams { m: 8, ..Default::default() };
+        let tuned = IndexConfig::Hnsw(HnswParams { ef_search: 77, ..hnsw });
+        let index_config = |db: &Database| db.get_collection("docs").unwrap().index.as_ref().map(VectorIndex::config);
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.set_index("docs", Some(IndexConfig::Hnsw(hnsw))).unwrap();
+            db.tune_index("docs", Some(77), None).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(index_config(&db), Some(tuned));
+            db.snapshot().unwrap();
+            db.add_or_update("docs", id(21), vec![21.0, 1.0], Payload::new()).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(index_config(&db), Some(tuned));
+            // Le document journalisé après l'instantané est dans l'index reconstruit.
+            let collection = db.get_collection("docs").unwrap();
+            assert_eq!(collection.search(&[21.0, 1.0], 1, None).unwrap().hits[0].0, id(21));
+            db.set_index("docs", None).unwrap();
+            db.snapshot().unwrap();
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(index_config(&db), None);
+        assert_eq!(documents(&db).len(), 21);
+    }
+
+    #[test]
+    fn payload_index_survives_the_journal_and_the_snapshot() {
+        let dir = DataDir::new();
+        write_documents(&dir, 5);
+        let candidates = |db: &Database, n: f64| {
+            let filter = Filter::Compare { field: "n".to_string(), op: CompareOp::Eq, value: Value::Number(n) };
+            db.get_collection("docs").unwrap().payload_index.candidates(&filter)
+        };
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.create_payload_index("docs", "n").unwrap();
+            assert!(matches!(db.create_payload_index("absent", "n"), Err(DbError::CollectionNotFound(_))));
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(db.get_collection("docs").unwrap().payload_index.fields(), vec!["n"]);
+            assert_eq!(candidates(&db, 3.0), Some(HashSet::from([id(3)])));
+            db.snapshot().unwrap();
+            let payload = Payload::from([("n".to_string(), Value::Number(3.0))]);
+            db.add_or_update("docs", id(6), vec![6.0, 1.0], payload).unwrap();
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(db.get_collection("docs").unwrap().payload_index.fields(), vec!["n"]);
+        assert_eq!(candidates(&db, 3.0), Some(HashSet::from([id(3), id(6)])));
+    }
+
+    /// Installe un quantificateur sur `docs` et vérifie qu'il est rétabli, avec ses paramètres, par le rejeu
+    /// du journal puis par l'instantané, et que les documents ajoutés ensuite sont encodés.
+    fn quantizer_survives_the_journal_and_the_snapshot(config: QuantizerConfig) {
+        let dir = DataDir::new();
+        write_documents(&dir, 20);
+        let quantizer_config = |db: &Database| db.get_collection("docs").unwrap().quantizer.as_ref().map(Quantizer::config);
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.set_quantizer("docs", Some(config)).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(quantizer_config(&db), Some(config));
+            db.snapshot().unwrap();
+            db.add_or_update("docs", id(21), vec![21.0, 1.0], Payload::new()).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(quantizer_config(&db), Some(config));
+            let collection = db.get_collection("docs").unwrap();
+            let quantizer = collection.quantizer.as_ref().unwrap();
+            assert!(quantizer.is_trained());
+            // Les 21 documents, dont celui journalisé après l'instantané, sont trouvés par la recherche sur les codes.
+            assert_eq!(quantizer.search(&[21.0, 1.0], 21, &collection.data, None).len(), 21);
+            db.set_quantizer("docs", None).unwrap();
+            db.snapshot().unwrap();
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(quantizer_config(&db), None);
+        assert_eq!(documents(&db).len(), 21);
+    }
+
+    #[test]
+    fn product_quantizer_survives_the_journal_and_the_snapshot() {
+        let params = PqParams { subspaces: 2, centroids: 4, rerank: Some(10), ..Default::default() };
+        quantizer_survives_the_journal_and_the_snapshot(QuantizerConfig::Product(params));
+    }
+
+    #[test]
+    fn scalar_quantizers_survive_the_journal_and_the_snapshot() {
+        for bits in [ScalarBits::Int8, ScalarBits::Int4] {
+            let params = SqParams { bits, calibration: Calibration::Global, oversampling: Some(2.0) };
+            quantizer_survives_the_journal_and_the_snapshot(QuantizerConfig::Scalar(params));
+        }
+    }
+
+    #[test]
+    fn binary_quantizer_survives_the_journal_and_the_snapshot() {
+        quantizer_survives_the_journal_and_the_snapshot(QuantizerConfig::Binary(BqParams { oversampling: 3.0 }));
+    }
+
+    #[test]
+    fn quantizer_needs_documents_to_learn_from() {
+        let dir = DataDir::new();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            for config in [QuantizerConfig::Product(PqParams::default()), QuantizerConfig::Scalar(SqParams::default())] {
+                assert_eq!(db.set_quantizer("docs", Some(config)), Err(DbError::EmptyCollection("docs".to_string())));
+                assert!(db.get_collection("docs").unwrap().quantizer.is_none());
+            }
+            assert!(matches!(db.set_quantizer("absent", None), Err(DbError::CollectionNotFound(_))));
+            // Un quantificateur binaire n'apprend rien : il prend la dimension du premier document ajouté.
+            db.set_quantizer("docs", Some(QuantizerConfig::Binary(BqParams::default()))).unwrap();
+        }
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            for n in 1..=3 {
+                db.add_or_update("docs", id(n), vec![n as f32, 1.0], Payload::new()).unwrap();
+            }
+        }
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        let collection = db.get_collection("docs").unwrap();
+        assert!(collection.active_quantizer().is_some());
+        assert_eq!(collection.search(&[3.0, 1.0], 3, None).unwrap().hits.len(), 3);
+    }
+
+    #[test]
+    fn version_1_snapshot_is_still_read() {
+        let dir = DataDir::new();
+        write_documents(&dir, 3);
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.set_index("docs", Some(IndexConfig::Hnsw(HnswParams::default()))).unwrap();
+            db.snapshot().unwrap();
+        }
+
+        // Sans collection projetée en mémoire, seul l'en-tête distingue la version 1.
+        let path = dir.0.join(SNAPSHOT_FILE);
+        let bytes = fs::read(&path).unwrap();
+        let mut old = bytes[..bytes.len() - 4].to_vec();
+        old[..SNAPSHOT_MAGIC_V1.len()].copy_from_slice(SNAPSHOT_MAGIC_V1);
+        let checksum = crc32(&old);
+        old.extend_from_slice(&checksum.to_le_bytes());
+        fs::write(&path, old).unwrap();
+
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(1), id(2), id(3)]);
+        assert!(db.get_collection("docs").unwrap().index.is_some());
+    }
+
+    /// Les fichiers de vecteurs du répertoire de données.
+    fn vector_files(dir: &DataDir) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(&dir.0)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| is_vector_file(name))
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn vector(n: u128) -> Vec<f32> {
+        (0..64).map(|i| (n * 100 + i) as f32).collect()
+    }
+
+    const MAPPED: CollectionConfig = CollectionConfig { metric: Metric::Euclidean, dimension: None, normalize: false, mapped: true };
+
+    #[test]
+    fn mapped_vectors_are_reopened_from_their_file() {
+        let dir = DataDir::new();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_collection("docs".to_string(), MAPPED).unwrap();
+            for n in 1..=100 {
+                db.add_or_update("docs", id(n), vector(n), Payload::new()).unwrap();
+            }
+            db.snapshot().unwrap();
+            db.add_or_update("docs", id(1), vector(101), Payload::new()).unwrap();
+            db.remove("docs", &id(2)).unwrap();
+            db.add_or_update("docs", id(102), vector(102), Payload::new()).unwrap();
+        }
+        // L'instantané ne contient que les emplacements, pas les 100 vecteurs de 256 octets.
+        assert!(fs::metadata(dir.0.join(SNAPSHOT_FILE)).unwrap().len() < 100 * 64);
+        let files = vector_files(&dir);
+        assert_eq!(files.len(), 1);
+
+        for _ in 0..2 {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            let collection = db.get_collection("docs").unwrap();
+            assert_eq!(collection.len(), 100);
+            assert!(collection.data.is_mapped());
+            assert_eq!(collection.get(&id(1)), Some(vector(101).as_slice()));
+            assert_eq!(collection.get(&id(2)), None);
+            assert_eq!(collection.get(&id(50)), Some(vector(50).as_slice()));
+            assert_eq!(collection.get(&id(102)), Some(vector(102).as_slice()));
+            db.snapshot().unwrap();
+            assert_eq!(vector_files(&dir), files);
+        }
+    }
+
+    #[test]
+    fn snapshot_vectors_survive_a_lost_journal() {
+        let dir = DataDir::new();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_collection("docs".to_string(), MAPPED).unwrap();
+            for n in 1..=3 {
+                db.add_or_update("docs", id(n), vector(n), Payload::new()).unwrap();
+            }
+            db.snapshot().unwrap();
+            // Des modifications écrites dans le fichier de vecteurs, mais dont le journal est perdu
+            // (`sync_writes` désactivé puis un crash).
+            db.add_or_update("docs", id(1), vector(7), Payload::new()).unwrap();
+            db.remove("docs", &id(2)).unwrap();
+            db.add_or_update("docs", id(4), vector(4), Payload::new()).unwrap();
+            db.add_or_update("docs", id(5), vector(5), Payload::new()).unwrap();
+        }
+        fs::write(dir.wal(), b"").unwrap();
+
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(1), id(2), id(3)]);
+        let collection = db.get_collection("docs").unwrap();
+        for n in 1..=3 {
+            assert_eq!(collection.get(&id(n)), Some(vector(n).as_slice()));
+        }
+    }
+
+    #[test]
+    fn unused_vector_files_are_removed() {
+        let dir = DataDir::new();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.add_collection("docs".to_string(), MAPPED).unwrap();
+            db.add_or_update("docs", id(1), vector(1), Payload::new()).unwrap();
+        }
+        let before = vector_files(&dir);
+        assert_eq!(before.len(), 1);
+        {
+            // Le fichier créé avant l'ouverture n'est référencé par aucun instantané : le rejeu du journal
+            // en crée un autre.
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(db.get_collection("docs").unwrap().get(&id(1)), Some(vector(1).as_slice()));
+            let after = vector_files(&dir);
+            assert_eq!(after.len(), 1);
+            assert_ne!(after, before);
+
+            db.drop_collection("docs").unwrap();
+            assert_eq!(vector_files(&dir).len(), 1);
+            db.snapshot().unwrap();
+            assert!(vector_files(&dir).is_empty());
+        }
+        assert!(Database::open_with_options(&dir.0, OPTIONS).unwrap().collection_names().is_empty());
+    }
+
+    #[test]
+    fn automatic_snapshot_after_the_interval() {
+        let dir = DataDir::new();
+        let options = StorageOptions { snapshot_interval: 3, sync_writes: false };
+        {
+            let mut db = Database::open_with_options(&dir.0, options).unwrap();
+            db.add_collection("docs".to_string(), CollectionConfig::default()).unwrap();
+            for n in 1..=4 {
+                db.add_or_update("docs", id(n), vec![n as f32], Payload::new()).unwrap();
+            }
+        }
+        let (collections, records, _) = reopen(&dir, options);
+        assert_eq!(collections["docs"].len(), 2);
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn records_already_in_the_snapshot_are_skipped() {
+        let dir = DataDir::new();
+        write_documents(&dir, 2);
+        let wal = fs::read(dir.wal()).unwrap();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            db.snapshot().unwrap();
+        }
+        // Crash entre le renommage de l'instantané et le vidage du journal : le journal contient
+        // encore les opérations de l'instantané, qui ne doivent pas être appliquées deux fois.
+        fs::write(dir.wal(), &wal).unwrap();
+        {
+            let mut db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+            assert_eq!(documents(&db), vec![id(1), id(2)]);
+            db.remove("docs", &id(1)).unwrap();
+        }
+
+        let (_, records, _) = reopen(&dir, OPTIONS);
+        assert_eq!(records.len(), 1);
+        assert!(matches!(&records[0], Record::Remove { key, .. } if *key == id(1)));
+        let db = Database::open_with_options(&dir.0, OPTIONS).unwrap();
+        assert_eq!(documents(&db), vec![id(2)]);
+    }
+
+    #[test]
+    fn undone_append_is_not_replayed() {
+        let dir = DataDir::new();
+        {
+            let (_, _, mut storage) = reopen(&dir, OPTIONS);
+            storage.append(&Record::DropCollection { name: "a".to_string() }).unwrap();
+            storage.append(&Record::DropCollection { name: "b".to_string() }).unwrap();
+            storage.undo_append().unwrap();
+            storage.append(&Record::DropCollection { name: "c".to_string() }).unwrap();
+        }
+        let (_, records, storage) = reopen(&dir, OPTIONS);
+        let names: Vec<String> = records
+            .into_iter()
+            .map(|record| match record {
+                Record::DropCollection { name } => name,
+                record => panic!("opération inattendue : {:?}", record),
+            })
+            .collect();
+        assert_eq!(names, vec!["a", "c"]);
+        assert_eq!(storage.next_lsn, 3);
+    }
+
+    #[test]
+    fn corrupted_snapshot_is_an_error() {
+        let dir = DataDir::new();
+        write_documents(&dir, 2);
+        Database::open_with_options(&dir.0, OPTIONS).unwrap().snapshot().unwrap();
+        let path = dir.0.join(SNAPSHOT_FILE);
+        let mut snapshot = fs::read(&path).unwrap();
+        let middle = snapshot.len() / 2;
+        snapshot[middle] ^= 1;
+        fs::write(&path, &snapshot).unwrap();
+        assert!(matches!(Database::open_with_options(&dir.0, OPTIONS), Err(DbError::Corrupted(_))));
+    }
+
+    #[test]
+    fn crc32_matches_the_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32_update(crc32_update(CRC32_INIT, b"1234"), b"56789"), !0xCBF4_3926);
+    }
+}
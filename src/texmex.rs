@@ -0,0 +1,350 @@
+//! # Module: `texmex`
+//!
+//! Lecture des jeux de données de référence pour la recherche des plus proches voisins, au format du
+//! corpus TEXMEX (SIFT1M, GIST1M, SIFT1B, etc.) : `.fvecs` (vecteurs de `f32`), `.bvecs` (vecteurs
+//! d'octets) et `.ivecs` (vecteurs d'entiers, qui donnent les plus proches voisins exacts de chaque requête).
+//!
+//! Les vecteurs sont écrits bout à bout : la dimension, sur un entier de 32 bits petit-boutiste, puis les
+//! composantes, sur 4 octets (`f32` ou `i32`) ou sur un octet (`u8`) chacune. Un fichier est lu un vecteur
+//! à la fois, sans jamais tenir en mémoire.
+//!
+//! Un jeu de données comprend une base, chargée dans une collection par [`Database::load_vecs`](crate::Database::load_vecs),
+//! des requêtes ([`QuerySet`]) et, pour chaque requête, la position dans la base de ses plus proches voisins
+//! exacts ([`GroundTruth`]). Le vecteur à la position `i` de la base devient le document [`dataset_id(i)`](dataset_id) :
+//! [`evaluate`] retrouve ainsi les voisins attendus parmi les résultats de [`Collection::search`].
+
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use uuid::Uuid;
+
+use crate::error::DbError;
+use crate::{Collection, DocumentId};
+
+/// Dimension maximale acceptée, pour ne pas allouer des gigaoctets sur la foi d'un fichier corrompu.
+pub const MAX_DIMENSION: usize = 1 << 20;
+
+/// # Énumération: `VecsFormat`
+///
+/// Le type des composantes d'un fichier TEXMEX.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VecsFormat {
+    /// `.fvecs` : des `f32`.
+    Fvecs,
+    /// `.ivecs` : des `i32`, le plus souvent des positions dans la base.
+    Ivecs,
+    /// `.bvecs` : des `u8`.
+    Bvecs,
+}
+
+impl VecsFormat {
+    /// Le format d'un fichier d'après son extension (`.fvecs`, `.ivecs` ou `.bvecs`).
+    pub fn from_path(path: &Path) -> Option<VecsFormat> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "fvecs" => Some(VecsFormat::Fvecs),
+            "ivecs" => Some(VecsFormat::Ivecs),
+            "bvecs" => Some(VecsFormat::Bvecs),
+            _ => None,
+        }
+    }
+
+    /// Taille d'une composante, en octets.
+    fn component_bytes(self) -> usize {
+        match self {
+            VecsFormat::Fvecs | VecsFormat::Ivecs => 4,
+            VecsFormat::Bvecs => 1,
+        }
+    }
+}
+
+/// L'identifiant du document chargé depuis la position `position` d'une base.
+pub fn dataset_id(position: usize) -> DocumentId {
+    Uuid::from_u128(position as u128)
+}
+
+/// # Structure: `VecsReader`
+///
+/// Lit les vecteurs d'un fichier TEXMEX, un à la fois.
+pub struct VecsReader<R> {
+    reader: R,
+    format: VecsFormat,
+    /// Nombre de vecteurs déjà lus.
+    count: usize,
+    /// Les composantes du dernier vecteur lu, telles qu'elles sont écrites dans le fichier.
+    buffer: Vec<u8>,
+}
+
+impl<R: Read> VecsReader<R> {
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut reader = VecsReader::new(BufReader::new(File::open("sift_base.fvecs")?), VecsFormat::Fvecs);
+    /// while let Some(vector) = reader.next_vector()? {
+    ///     // Utiliser le vecteur
+    /// }
+    /// ```
+    pub fn new(reader: R, format: VecsFormat) -> Self {
+        VecsReader {
+            reader,
+            format,
+            count: 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Lit le vecteur suivant, converti en `f32` quel que soit le format.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<f32>>, DbError>`: Le vecteur, `None` à la fin du fichier, [`DbError::Corrupted`]
+    ///   si le fichier est tronqué ou si une dimension est invalide, ou [`DbError::Io`] si la lecture échoue.
+    pub fn next_vector(&mut self) -> Result<Option<Vec<f32>>, DbError> {
+        if !self.next_record()? {
+            return Ok(None);
+        }
+        let vector = match self.format {
+            VecsFormat::Fvecs => self.words().map(f32::from_le_bytes).collect(),
+            VecsFormat::Ivecs => self.words().map(|word| i32::from_le_bytes(word) as f32).collect(),
+            VecsFormat::Bvecs => self.buffer.iter().map(|&byte| byte as f32).collect(),
+        };
+        Ok(Some(vector))
+    }
+
+    /// Lit le vecteur suivant d'un fichier `.ivecs`, sous forme de positions dans une base.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<usize>>, DbError>`: Les positions, `None` à la fin du fichier, ou
+    ///   [`DbError::Corrupted`] si le fichier n'est pas un `.ivecs` valide ou contient une position négative.
+    pub fn next_positions(&mut self) -> Result<Option<Vec<usize>>, DbError> {
+        if self.format != VecsFormat::Ivecs {
+            return Err(DbError::Corrupted("les positions se lisent dans un fichier .ivecs".to_string()));
+        }
+        if !self.next_record()? {
+            return Ok(None);
+        }
+        let index = self.count;
+        self.words()
+            .map(|word| {
+                usize::try_from(i32::from_le_bytes(word))
+                    .map_err(|_| DbError::Corrupted(format!("vecteur {} : position négative", index)))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some)
+    }
+
+    /// Lit la dimension et les composantes du vecteur suivant dans `buffer`.
+    ///
+    /// # Retour
+    /// - `Result<bool, DbError>`: `false` à la fin du fichier.
+    fn next_record(&mut self) -> Result<bool, DbError> {
+        let index = self.count + 1;
+        let mut header = [0u8; 4];
+        match read_full(&mut self.reader, &mut header)? {
+            0 => return Ok(false),
+            4 => {}
+            _ => return Err(DbError::Corrupted(format!("vecteur {} : fichier tronqué", index))),
+        }
+        let dimension = i32::from_le_bytes(header);
+        let dimension = match usize::try_from(dimension) {
+            Ok(dimension) if (1..=MAX_DIMENSION).contains(&dimension) => dimension,
+            _ => return Err(DbError::Corrupted(format!("vecteur {} : dimension invalide {}", index, dimension))),
+        };
+        self.buffer.resize(dimension * self.format.component_bytes(), 0);
+        if read_full(&mut self.reader, &mut self.buffer)? != self.buffer.len() {
+            return Err(DbError::Corrupted(format!("vecteur {} : fichier tronqué", index)));
+        }
+        self.count = index;
+        Ok(true)
+    }
+
+    /// Les composantes de 4 octets du dernier vecteur lu.
+    fn words(&self) -> impl Iterator<Item = [u8; 4]> + '_ {
+        self.buffer.chunks_exact(4).map(|word| word.try_into().unwrap())
+    }
+}
+
+/// Remplit `buffer` autant que le permet le fichier.
+///
+/// # Retour
+/// - `io::Result<usize>`: Le nombre d'octets lus, inférieur à la taille de `buffer` à la fin du fichier.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(filled)
+}
+
+/// # Structure: `QuerySet`
+///
+/// Les requêtes d'un jeu de données, dans l'ordre du fichier.
+#[derive(Debug, Clone, Default)]
+pub struct QuerySet {
+    pub queries: Vec<Vec<f32>>,
+}
+
+impl QuerySet {
+    /// Lit au plus `limit` requêtes d'un fichier `.fvecs`, `.bvecs` ou `.ivecs`.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let queries = QuerySet::load(BufReader::new(File::open("sift_query.fvecs")?), VecsFormat::Fvecs, Some(1_000))?;
+    /// ```
+    pub fn load(reader: impl Read, format: VecsFormat, limit: Option<usize>) -> Result<Self, DbError> {
+        let mut reader = VecsReader::new(reader, format);
+        let mut queries = Vec::new();
+        while queries.len() < limit.unwrap_or(usize::MAX) {
+            match reader.next_vector()? {
+                Some(query) => queries.push(query),
+                None => break,
+            }
+        }
+        Ok(QuerySet { queries })
+    }
+}
+
+/// # Structure: `GroundTruth`
+///
+/// Les plus proches voisins exacts de chaque requête d'un [`QuerySet`], du plus proche au plus éloigné,
+/// donnés par leur position dans la base.
+#[derive(Debug, Clone, Default)]
+pub struct GroundTruth {
+    pub neighbours: Vec<Vec<usize>>,
+}
+
+impl GroundTruth {
+    /// Lit les voisins d'au plus `limit` requêtes d'un fichier `.ivecs`.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let truth = GroundTruth::load(BufReader::new(File::open("sift_groundtruth.ivecs")?), Some(1_000))?;
+    /// ```
+    pub fn load(reader: impl Read, limit: Option<usize>) -> Result<Self, DbError> {
+        let mut reader = VecsReader::new(reader, VecsFormat::Ivecs);
+        let mut neighbours = Vec::new();
+        while neighbours.len() < limit.unwrap_or(usize::MAX) {
+            match reader.next_positions()? {
+                Some(positions) => neighbours.push(positions),
+                None => break,
+            }
+        }
+        Ok(GroundTruth { neighbours })
+    }
+}
+
+/// # Structure: `RecallReport`
+///
+/// Le rappel et la vitesse des recherches d'une collection, mesurés par [`evaluate`].
+#[derive(Debug, Clone, Copy)]
+pub struct RecallReport {
+    /// Nombre de requêtes évaluées.
+    pub queries: usize,
+    /// Nombre de voisins demandés par requête.
+    pub k: usize,
+    /// Proportion des `k` plus proches voisins exacts retrouvée par les recherches.
+    pub recall: f64,
+    /// Durée totale des recherches.
+    pub elapsed: Duration,
+}
+
+impl RecallReport {
+    /// Nombre moyen de requêtes traitées par seconde.
+    pub fn queries_per_second(&self) -> f64 {
+        self.queries as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Recherche les `k` plus proches voisins de chaque requête avec [`Collection::search`] (index ou
+/// quantificateur compris), une requête à la fois, et compare les résultats aux voisins exacts.
+///
+/// La collection doit avoir été chargée par [`Database::load_vecs`](crate::Database::load_vecs) depuis
+/// la base du jeu de données. Si seule une partie de la base a été chargée, les voisins qui n'en font
+/// pas partie ne peuvent pas être retrouvés.
+///
+/// # Paramètres
+/// - `collection`: La collection qui contient la base.
+/// - `queries`: Les requêtes.
+/// - `truth`: Les voisins exacts de chaque requête ; seules les requêtes qui en ont sont évaluées.
+/// - `k`: Le nombre de voisins recherchés (limité au nombre de voisins exacts connus).
+///
+/// # Retour
+/// - `Result<RecallReport, DbError>`: Le rappel@k et la durée des recherches, ou l'erreur de la première
+///   requête invalide.
+///
+/// # Exemple
+///
+/// ```
+/// let report = texmex::evaluate(db.get_collection("sift")?, &queries, &truth, 10)?;
+/// println!("rappel@10 : {:.3}, {:.0} requêtes/s", report.recall, report.queries_per_second());
+/// ```
+pub fn evaluate(collection: &Collection, queries: &QuerySet, truth: &GroundTruth, k: usize) -> Result<RecallReport, DbError> {
+    let mut found = 0;
+    let mut expected = 0;
+    let mut elapsed = Duration::ZERO;
+    let count = queries.queries.len().min(truth.neighbours.len());
+    for (query, neighbours) in queries.queries.iter().zip(&truth.neighbours) {
+        let neighbours: Vec<DocumentId> = neighbours.iter().take(k).map(|&position| dataset_id(position)).collect();
+        let start = Instant::now();
+        let result = collection.search(query, k, None)?;
+        elapsed += start.elapsed();
+        expected += neighbours.len();
+        found += result.hits.iter().filter(|(key, _)| neighbours.contains(key)).count();
+    }
+    Ok(RecallReport {
+        queries: count,
+        k,
+        recall: if expected == 0 { 1.0 } else { found as f64 / expected as f64 },
+        elapsed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_vectors_and_rejects_truncated_files() {
+        let mut bytes = Vec::new();
+        for vector in [[1.5f32, -2.0], [0.0, 4.25]] {
+            bytes.extend(2i32.to_le_bytes());
+            bytes.extend(vector.iter().flat_map(|value| value.to_le_bytes()));
+        }
+        let mut reader = VecsReader::new(bytes.as_slice(), VecsFormat::Fvecs);
+        assert_eq!(reader.next_vector().unwrap(), Some(vec![1.5, -2.0]));
+        assert_eq!(reader.next_vector().unwrap(), Some(vec![0.0, 4.25]));
+        assert_eq!(reader.next_vector().unwrap(), None);
+
+        let mut reader = VecsReader::new(&bytes[..bytes.len() - 1], VecsFormat::Fvecs);
+        assert!(reader.next_vector().unwrap().is_some());
+        assert!(matches!(reader.next_vector(), Err(DbError::Corrupted(_))));
+
+        let bytes = [3i32.to_le_bytes().as_slice(), &[7, 0, 255]].concat();
+        assert_eq!(VecsReader::new(bytes.as_slice(), VecsFormat::Bvecs).next_vector().unwrap(), Some(vec![7.0, 0.0, 255.0]));
+    }
+
+    #[test]
+    fn ground_truth_stops_at_the_limit_and_rejects_negative_positions() {
+        let mut bytes = Vec::new();
+        for positions in [[2i32, 0], [1, 3], [0, 1]] {
+            bytes.extend(2i32.to_le_bytes());
+            bytes.extend(positions.iter().flat_map(|position| position.to_le_bytes()));
+        }
+        let truth = GroundTruth::load(bytes.as_slice(), Some(2)).unwrap();
+        assert_eq!(truth.neighbours, vec![vec![2, 0], vec![1, 3]]);
+        assert_eq!(GroundTruth::load(bytes.as_slice(), None).unwrap().neighbours.len(), 3);
+        assert_eq!(QuerySet::load(bytes.as_slice(), VecsFormat::Ivecs, Some(1)).unwrap().queries, vec![vec![2.0, 0.0]]);
+
+        let bytes = [1i32.to_le_bytes(), (-1i32).to_le_bytes()].concat();
+        assert!(matches!(GroundTruth::load(bytes.as_slice(), None), Err(DbError::Corrupted(_))));
+        assert!(matches!(VecsReader::new(bytes.as_slice(), VecsFormat::Fvecs).next_positions(), Err(DbError::Corrupted(_))));
+        assert_eq!(VecsFormat::from_path(Path::new("sift_base.FVECS")), Some(VecsFormat::Fvecs));
+        assert_eq!(VecsFormat::from_path(Path::new("sift_base.csv")), None);
+    }
+}
@@ -0,0 +1,399 @@
+//! # Module: `thrift`
+//!
+//! Le protocole compact de Thrift, dans lequel Parquet écrit ses métadonnées : le pied de page du
+//! fichier et l'en-tête de chaque page.
+//!
+//! Une structure est lue en entier sous forme d'arbre de [`Thrift`], sans schéma : ses champs sont
+//! ensuite retrouvés par leur numéro. À l'écriture, l'arbre est construit puis encodé de la même façon.
+
+use crate::error::DbError;
+
+/// Types des valeurs, dans les en-têtes de champs et de listes.
+const STOP: u8 = 0;
+const BOOL_TRUE: u8 = 1;
+const BOOL_FALSE: u8 = 2;
+const BYTE: u8 = 3;
+const I16: u8 = 4;
+const I32: u8 = 5;
+const I64: u8 = 6;
+const DOUBLE: u8 = 7;
+const BINARY: u8 = 8;
+const LIST: u8 = 9;
+const SET: u8 = 10;
+const MAP: u8 = 11;
+const STRUCT: u8 = 12;
+
+/// Profondeur maximale des structures imbriquées, pour qu'un fichier corrompu n'épuise pas la pile.
+const MAX_DEPTH: usize = 64;
+
+/// # Énumération: `Thrift`
+///
+/// Une valeur Thrift. Les entiers lus sont tous rangés dans `I64`, quelle que soit leur taille ;
+/// à l'écriture, la variante choisit le type annoncé.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Thrift {
+    Bool(bool),
+    I32(i32),
+    I64(i64),
+    Double(f64),
+    Binary(Vec<u8>),
+    List(Vec<Thrift>),
+    Map(Vec<(Thrift, Thrift)>),
+    /// Les champs présents, avec leur numéro.
+    Struct(Vec<(i16, Thrift)>),
+}
+
+impl Thrift {
+    /// Le champ numéro `id` d'une structure.
+    pub fn field(&self, id: i16) -> Option<&Thrift> {
+        match self {
+            Thrift::Struct(fields) => fields.iter().find(|(field, _)| *field == id).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Le champ entier numéro `id` d'une structure.
+    pub fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Thrift::I32(value) => Some(*value as i64),
+            Thrift::I64(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Le champ booléen numéro `id` d'une structure.
+    pub fn bool(&self, id: i16) -> Option<bool> {
+        match self.field(id)? {
+            Thrift::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Le champ binaire (ou chaîne) numéro `id` d'une structure.
+    pub fn binary(&self, id: i16) -> Option<&[u8]> {
+        match self.field(id)? {
+            Thrift::Binary(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Le champ liste numéro `id` d'une structure ; une liste vide s'il est absent.
+    pub fn list(&self, id: i16) -> &[Thrift] {
+        match self.field(id) {
+            Some(Thrift::List(values)) => values,
+            _ => &[],
+        }
+    }
+
+    /// Lit une structure au début de `bytes`.
+    ///
+    /// # Retour
+    /// - `Result<(Thrift, usize), DbError>`: La structure et le nombre d'octets qu'elle occupe,
+    ///   ou [`DbError::Corrupted`] si elle est tronquée ou mal formée.
+    pub fn read_struct(bytes: &[u8]) -> Result<(Thrift, usize), DbError> {
+        let mut input = Input { bytes, position: 0 };
+        let value = input.read_value(STRUCT, 0)?;
+        Ok((value, input.position))
+    }
+
+    /// Encode une structure à la fin de `output`.
+    pub fn write_struct(&self, output: &mut Vec<u8>) {
+        let Thrift::Struct(fields) = self else {
+            panic!("seule une structure s'encode à la racine");
+        };
+        let mut last = 0;
+        for (id, value) in fields {
+            let kind = match value {
+                Thrift::Bool(true) => BOOL_TRUE,
+                Thrift::Bool(false) => BOOL_FALSE,
+                value => value.kind(),
+            };
+            if *id > last && id - last <= 15 {
+                output.push(((id - last) as u8) << 4 | kind);
+            } else {
+                output.push(kind);
+                write_varint(output, zigzag(*id as i64));
+            }
+            last = *id;
+            if !matches!(value, Thrift::Bool(_)) {
+                value.write_value(output);
+            }
+        }
+        output.push(STOP);
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Thrift::Bool(_) => BOOL_TRUE,
+            Thrift::I32(_) => I32,
+            Thrift::I64(_) => I64,
+            Thrift::Double(_) => DOUBLE,
+            Thrift::Binary(_) => BINARY,
+            Thrift::List(_) => LIST,
+            Thrift::Map(_) => MAP,
+            Thrift::Struct(_) => STRUCT,
+        }
+    }
+
+    fn write_value(&self, output: &mut Vec<u8>) {
+        match self {
+            Thrift::Bool(value) => output.push(if *value { BOOL_TRUE } else { BOOL_FALSE }),
+            Thrift::I32(value) => write_varint(output, zigzag(*value as i64)),
+            Thrift::I64(value) => write_varint(output, zigzag(*value)),
+            Thrift::Double(value) => output.extend(value.to_le_bytes()),
+            Thrift::Binary(value) => {
+                write_varint(output, value.len() as u64);
+                output.extend(value);
+            }
+            Thrift::List(values) => {
+                let kind = values.first().map_or(STRUCT, Thrift::kind);
+                if values.len() < 15 {
+                    output.push((values.len() as u8) << 4 | kind);
+                } else {
+                    output.push(0xf0 | kind);
+                    write_varint(output, values.len() as u64);
+                }
+                for value in values {
+                    value.write_value(output);
+                }
+            }
+            Thrift::Map(entries) => {
+                write_varint(output, entries.len() as u64);
+                if let Some((key, value)) = entries.first() {
+                    output.push(key.kind() << 4 | value.kind());
+                }
+                for (key, value) in entries {
+                    key.write_value(output);
+                    value.write_value(output);
+                }
+            }
+            Thrift::Struct(_) => self.write_struct(output),
+        }
+    }
+}
+
+/// Les octets à décoder et la position de lecture.
+struct Input<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Input<'_> {
+    fn byte(&mut self) -> Result<u8, DbError> {
+        let byte = *self.bytes.get(self.position).ok_or_else(|| corrupted("structure tronquée"))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u64, DbError> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(corrupted("entier trop long"))
+    }
+
+    fn signed(&mut self) -> Result<i64, DbError> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn length(&mut self) -> Result<usize, DbError> {
+        let length = self.varint()? as usize;
+        if length > self.bytes.len() - self.position {
+            return Err(corrupted("longueur invalide"));
+        }
+        Ok(length)
+    }
+
+    fn read_value(&mut self, kind: u8, depth: usize) -> Result<Thrift, DbError> {
+        if depth > MAX_DEPTH {
+            return Err(corrupted("structures trop imbriquées"));
+        }
+        Ok(match kind {
+            BOOL_TRUE | BOOL_FALSE => Thrift::Bool(self.byte()? == BOOL_TRUE),
+            BYTE => Thrift::I64(self.byte()? as i8 as i64),
+            I16 | I32 | I64 => Thrift::I64(self.signed()?),
+            DOUBLE => {
+                let end = self.position + 8;
+                let bytes = self.bytes.get(self.position..end).ok_or_else(|| corrupted("structure tronquée"))?;
+                self.position = end;
+                Thrift::Double(f64::from_le_bytes(bytes.try_into().unwrap()))
+            }
+            BINARY => {
+                let length = self.length()?;
+                self.position += length;
+                Thrift::Binary(self.bytes[self.position - length..self.position].to_vec())
+            }
+            LIST | SET => {
+                let header = self.byte()?;
+                let length = match header >> 4 {
+                    15 => self.length()?,
+                    length => length as usize,
+                };
+                let mut values = Vec::with_capacity(length.min(self.bytes.len()));
+                for _ in 0..length {
+                    values.push(self.read_value(header & 0x0f, depth + 1)?);
+                }
+                Thrift::List(values)
+            }
+            MAP => {
+                let length = self.length()?;
+                let types = if length > 0 { self.byte()? } else { 0 };
+                let mut entries = Vec::with_capacity(length);
+                for _ in 0..length {
+                    let key = self.read_value(types >> 4, depth + 1)?;
+                    entries.push((key, self.read_value(types & 0x0f, depth + 1)?));
+                }
+                Thrift::Map(entries)
+            }
+            STRUCT => {
+                let mut fields = Vec::new();
+                let mut last = 0i16;
+                loop {
+                    let header = self.byte()?;
+                    if header == STOP {
+                        break;
+                    }
+                    let id = match header >> 4 {
+                        0 => self.signed()? as i16,
+                        delta => last.wrapping_add(delta as i16),
+                    };
+                    last = id;
+                    let value = match header & 0x0f {
+                        // Dans une structure, la valeur d'un booléen est son type.
+                        BOOL_TRUE => Thrift::Bool(true),
+                        BOOL_FALSE => Thrift::Bool(false),
+                        kind => self.read_value(kind, depth + 1)?,
+                    };
+                    fields.push((id, value));
+                }
+                Thrift::Struct(fields)
+            }
+            kind => return Err(corrupted(&format!("type {} inconnu", kind))),
+        })
+    }
+}
+
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn write_varint(output: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        output.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    output.push(value as u8);
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("métadonnées Thrift : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Une structure qui utilise chaque type : tous les entiers sont relus en `I64`.
+    fn sample() -> Thrift {
+        Thrift::Struct(vec![
+            (1, Thrift::I32(-7)),
+            (2, Thrift::Bool(true)),
+            (3, Thrift::Bool(false)),
+            (4, Thrift::I64(i64::MIN)),
+            (5, Thrift::Double(-1.5)),
+            (6, Thrift::Binary(b"parquet-cpp-arrow".to_vec())),
+            (7, Thrift::List((0..20).map(Thrift::I64).collect())),
+            (8, Thrift::List(vec![Thrift::Bool(true), Thrift::Bool(false)])),
+            (9, Thrift::Map(vec![(Thrift::Binary(b"clef".to_vec()), Thrift::I32(1))])),
+            (40, Thrift::Struct(vec![(1, Thrift::Struct(Vec::new())), (300, Thrift::Binary(Vec::new()))])),
+            (2, Thrift::List(vec![Thrift::Struct(vec![(1, Thrift::I64(1))])])),
+        ])
+    }
+
+    fn encode(value: &Thrift) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        value.write_struct(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn round_trips_every_type() {
+        let bytes = encode(&sample());
+        let (value, length) = Thrift::read_struct(&bytes).unwrap();
+        assert_eq!(length, bytes.len());
+        let Thrift::Struct(fields) = sample() else { unreachable!() };
+        let expected: Vec<(i16, Thrift)> = fields
+            .into_iter()
+            .map(|(id, value)| match value {
+                Thrift::I32(value) => (id, Thrift::I64(value as i64)),
+                Thrift::Map(entries) => (id, Thrift::Map(entries.into_iter().map(|(key, _)| (key, Thrift::I64(1))).collect())),
+                value => (id, value),
+            })
+            .collect();
+        assert_eq!(value, Thrift::Struct(expected));
+    }
+
+    #[test]
+    fn reads_the_bytes_of_the_reference_encoding() {
+        // { 1: i32 1, 2: string "ab", 4: list<i32> [1, -1], 20: bool true }, encodé selon la spécification du protocole compact.
+        let bytes = [0x15, 0x02, 0x18, 0x02, b'a', b'b', 0x29, 0x25, 0x02, 0x01, 0x01, 0x28, 0x00];
+        let (value, length) = Thrift::read_struct(&bytes).unwrap();
+        assert_eq!(length, bytes.len());
+        assert_eq!(value.int(1), Some(1));
+        assert_eq!(value.binary(2), Some(&b"ab"[..]));
+        assert_eq!(value.list(4), &[Thrift::I64(1), Thrift::I64(-1)]);
+        assert_eq!(value.bool(20), Some(true));
+        assert_eq!(encode(&Thrift::Struct(vec![
+            (1, Thrift::I32(1)),
+            (2, Thrift::Binary(b"ab".to_vec())),
+            (4, Thrift::List(vec![Thrift::I32(1), Thrift::I32(-1)])),
+            (20, Thrift::Bool(true)),
+        ])), bytes);
+    }
+
+    #[test]
+    fn truncated_structures_are_corrupted() {
+        let bytes = encode(&sample());
+        for length in 0..bytes.len() {
+            assert!(matches!(Thrift::read_struct(&bytes[..length]), Err(DbError::Corrupted(_))), "{} octets", length);
+        }
+    }
+
+    #[test]
+    fn corrupted_bytes_never_panic() {
+        let bytes = encode(&sample());
+        for position in 0..bytes.len() {
+            for byte in [0x00, 0x0f, 0x7f, 0x80, 0xff] {
+                let mut corrupted = bytes.clone();
+                corrupted[position] = byte;
+                let _ = Thrift::read_struct(&corrupted);
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_structures_are_rejected() {
+        let rejected = |bytes: &[u8]| matches!(Thrift::read_struct(bytes), Err(DbError::Corrupted(_)));
+        // Une chaîne plus longue que ce qui reste.
+        assert!(rejected(&[0x18, 0x10, b'a', 0x00]));
+        // Une liste annoncée de 2³² éléments.
+        assert!(rejected(&[0x19, 0xf5, 0x80, 0x80, 0x80, 0x80, 0x10]));
+        // Un type inconnu.
+        assert!(rejected(&[0x1d, 0x00]));
+        // Un entier sur plus de 10 octets.
+        assert!(rejected(&[0x15, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01, 0x00]));
+        // Des structures imbriquées plus profondément que MAX_DEPTH.
+        let mut nested = vec![0x1c; MAX_DEPTH + 1];
+        nested.extend(vec![0x00; MAX_DEPTH + 2]);
+        assert!(rejected(&nested));
+        let mut shallow = vec![0x1c; MAX_DEPTH - 1];
+        shallow.extend(vec![0x00; MAX_DEPTH]);
+        assert!(Thrift::read_struct(&shallow).is_ok());
+    }
+}
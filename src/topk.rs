@@ -0,0 +1,171 @@
+//! # Module: `topk`
+//!
+//! Sélection des `k` meilleurs résultats d'une recherche au fil du calcul des scores, sans trier
+//! l'ensemble des documents : un tas borné à `k` éléments garde les meilleurs candidats vus
+//! jusqu'ici et écarte les autres dès leur arrivée.
+//!
+//! L'ordre de classement est total : les scores sont comparés selon la [`Metric`], un score NaN
+//! est classé après tous les autres, et les scores égaux sont départagés par [`DocumentId`].
+//! Un même ensemble de documents donne donc toujours les mêmes résultats, dans le même ordre,
+//! quel que soit l'ordre dans lequel ils sont parcourus.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use crate::metric::Metric;
+use crate::{DocumentId, Vector};
+
+/// Compare deux résultats dans l'ordre de classement : le meilleur vient en premier.
+///
+/// # Exemple
+///
+/// ```
+/// hits.sort_by(|a, b| topk::compare(Metric::Cosine, a, b));
+/// ```
+pub fn compare(metric: Metric, hit1: &(DocumentId, f32), hit2: &(DocumentId, f32)) -> Ordering {
+    metric.rank(hit1.1, hit2.1).then_with(|| hit1.0.cmp(&hit2.0))
+}
+
+/// # Structure: `TopK`
+///
+/// Les `k` meilleurs résultats parmi ceux qui lui ont été présentés.
+/// Chaque ajout coûte au plus `O(log k)`, et la mémoire utilisée est bornée par `k`.
+pub struct TopK {
+    metric: Metric,
+    k: usize,
+    /// Tas dont la racine est le moins bon des résultats retenus, premier à être évincé.
+    heap: BinaryHeap<Hit>,
+}
+
+impl TopK {
+    /// Crée une sélection vide des `k` meilleurs résultats selon `metric`.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut top = TopK::new(Metric::Cosine, 3);
+    /// top.push(key, 0.9);
+    /// let hits = top.into_sorted_vec();
+    /// ```
+    pub fn new(metric: Metric, k: usize) -> Self {
+        TopK {
+            metric,
+            k,
+            heap: BinaryHeap::new(),
+        }
+    }
+
+    /// Présente un résultat : il est retenu s'il fait partie des `k` meilleurs vus jusqu'ici.
+    pub fn push(&mut self, key: DocumentId, score: f32) {
+        let hit = Hit {
+            metric: self.metric,
+            key,
+            score,
+        };
+        if self.heap.len() < self.k {
+            self.heap.push(hit);
+        } else if let Some(mut worst) = self.heap.peek_mut() {
+            if hit < *worst {
+                *worst = hit;
+            }
+        }
+    }
+
+    /// Présente plusieurs résultats, par exemple les meilleurs résultats d'une autre sélection.
+    pub fn extend(&mut self, hits: Vector) {
+        for (key, score) in hits {
+            self.push(key, score);
+        }
+    }
+
+    /// Les résultats retenus, du meilleur au moins bon.
+    pub fn into_sorted_vec(self) -> Vector {
+        self.heap.into_sorted_vec().into_iter().map(|hit| (hit.key, hit.score)).collect()
+    }
+}
+
+/// Un résultat retenu, ordonné du meilleur (le plus petit) au moins bon selon [`compare`].
+struct Hit {
+    metric: Metric,
+    key: DocumentId,
+    score: f32,
+}
+
+impl Ord for Hit {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare(self.metric, &(self.key, self.score), &(other.key, other.score))
+    }
+}
+
+impl PartialOrd for Hit {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Hit {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hit {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn id(n: u128) -> DocumentId {
+        Uuid::from_u128(n)
+    }
+
+    #[test]
+    fn keeps_the_best_scores_in_order() {
+        let mut top = TopK::new(Metric::Cosine, 3);
+        for (n, score) in [0.1, 0.9, 0.5, 0.7, 0.3].into_iter().enumerate() {
+            top.push(id(n as u128), score);
+        }
+        assert_eq!(top.into_sorted_vec(), vec![(id(1), 0.9), (id(3), 0.7), (id(2), 0.5)]);
+
+        let mut top = TopK::new(Metric::Euclidean, 2);
+        top.extend(vec![(id(1), 3.0), (id(2), 1.0), (id(3), 2.0)]);
+        assert_eq!(top.into_sorted_vec(), vec![(id(2), 1.0), (id(3), 2.0)]);
+    }
+
+    #[test]
+    fn nan_scores_come_last() {
+        for metric in [Metric::Cosine, Metric::Euclidean] {
+            let mut top = TopK::new(metric, 3);
+            top.extend(vec![(id(1), f32::NAN), (id(2), 1.0), (id(3), f32::NAN), (id(4), 2.0)]);
+            let hits = top.into_sorted_vec();
+            assert_eq!(hits.len(), 3);
+            assert!(!hits[0].1.is_nan() && !hits[1].1.is_nan());
+            assert_eq!(hits[2].0, id(1));
+        }
+    }
+
+    #[test]
+    fn ties_are_broken_by_document_id_whatever_the_order() {
+        let hits: Vector = (0..50).map(|n| (id(n), (n % 3) as f32)).collect();
+        let mut expected = hits.clone();
+        expected.sort_by(|a, b| compare(Metric::Dot, a, b));
+        expected.truncate(10);
+
+        for rotation in [0, 17, 33] {
+            let mut rotated = hits.clone();
+            rotated.rotate_left(rotation);
+            rotated.reverse();
+            let mut top = TopK::new(Metric::Dot, 10);
+            top.extend(rotated);
+            assert_eq!(top.into_sorted_vec(), expected);
+        }
+    }
+
+    #[test]
+    fn zero_k_keeps_nothing() {
+        let mut top = TopK::new(Metric::Cosine, 0);
+        top.push(id(1), 1.0);
+        assert!(top.into_sorted_vec().is_empty());
+    }
+}
@@ -0,0 +1,642 @@
+//! # Module: `transfer`
+//!
+//! Import et export des documents d'une collection dans des fichiers texte, au format CSV ou JSON Lines.
+//!
+//! Les fichiers sont lus et écrits au fil de l'eau : [`DocumentReader`] lit un document à la fois,
+//! [`Database::import`](crate::Database::import) les enregistre par lots de taille fixe, et
+//! [`DocumentWriter`] écrit chaque document sans attendre les suivants. Un fichier de plusieurs
+//! gigaoctets n'a donc jamais besoin de tenir en mémoire.
+//!
+//! - **JSON Lines** : un objet par ligne, `{"id": "...", "vector": [1, 2, 3], "payload": {"client": "Dupont"}}` ;
+//!   `id` et `payload` sont facultatifs.
+//! - **CSV** : une ligne d'en-tête, puis un document par ligne. La colonne `id`, facultative, contient
+//!   l'identifiant ; le vecteur occupe soit une colonne `vector` (`"1,2,3"` ou `"[1, 2, 3]"`), soit une
+//!   colonne par coordonnée, nommées `v0`, `v1`, etc. ; chacune des autres colonnes est un champ des
+//!   métadonnées. Une cellule vide est un champ absent. Le nom d'une colonne de métadonnées peut
+//!   préciser le type de ses cellules ([`CellType`]) : `dossier:string` pour des chaînes lues telles
+//!   quelles, `montant:json` pour des valeurs JSON (les chaînes entre guillemets). Sans type, comme dans
+//!   un tableur, `true`, `false`, un nombre ou un tableau/objet JSON sont lus comme tels, et tout autre
+//!   texte comme une chaîne. L'export type chaque colonne, si bien qu'un fichier exporté puis réimporté
+//!   redonne exactement les mêmes métadonnées.
+//!
+//! Sans identifiant (champ absent ou cellule vide), un document reçoit un nouvel identifiant aléatoire.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use uuid::Uuid;
+
+use crate::csv::{self, CsvReader};
+use crate::error::DbError;
+use crate::json::{self, object};
+use crate::payload::{Payload, Value};
+use crate::{Document, DocumentId};
+
+/// Nombre de documents enregistrés ensemble par défaut lors d'un import.
+const IMPORT_BATCH_SIZE: usize = 1_000;
+
+/// Nombre maximal d'erreurs détaillées dans un [`ImportReport`] ; les suivantes sont seulement comptées.
+const MAX_REPORTED_ERRORS: usize = 100;
+
+/// # Énumération: `Format`
+///
+/// Le format d'un fichier de documents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    Csv,
+    #[default]
+    JsonLines,
+}
+
+impl Format {
+    /// Le format désigné par son nom : `csv`, ou `jsonl` (aussi `ndjson`) pour JSON Lines.
+    pub fn parse(name: &str) -> Option<Format> {
+        match name.to_ascii_lowercase().as_str() {
+            "csv" => Some(Format::Csv),
+            "jsonl" | "ndjson" => Some(Format::JsonLines),
+            _ => None,
+        }
+    }
+
+    /// Le format d'un fichier d'après son extension : CSV pour `.csv`, JSON Lines sinon.
+    pub fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => Format::Csv,
+            _ => Format::JsonLines,
+        }
+    }
+
+    /// Le nom du format, tel qu'accepté par [`Format::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// # Énumération: `CellType`
+///
+/// Le type des cellules d'une colonne de métadonnées d'un fichier CSV, donné par le suffixe
+/// de son nom dans l'en-tête.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellType {
+    /// Sans suffixe : le type de chaque cellule est deviné (booléen, nombre, tableau ou objet JSON, sinon chaîne).
+    Guessed,
+    /// `:string` : chaque cellule est une chaîne, lue telle quelle.
+    String,
+    /// `:json` : chaque cellule est une valeur JSON quelconque.
+    Json,
+}
+
+impl CellType {
+    /// Sépare le nom d'une colonne de l'en-tête en nom du champ et type des cellules.
+    fn parse(name: &str) -> (&str, CellType) {
+        match name.rsplit_once(':') {
+            Some((field, "string")) => (field, CellType::String),
+            Some((field, "json")) => (field, CellType::Json),
+            _ => (name, CellType::Guessed),
+        }
+    }
+
+    /// Le type des cellules qui écrit exactement les valeurs d'un champ : `String` si toutes sont des chaînes
+    /// non vides (une cellule vide serait lue comme un champ absent), `Json` sinon.
+    fn of<'a>(mut values: impl Iterator<Item = &'a Value>) -> CellType {
+        if values.all(|value| matches!(value, Value::String(text) if !text.is_empty())) {
+            CellType::String
+        } else {
+            CellType::Json
+        }
+    }
+
+    /// La valeur d'une cellule non vide.
+    fn read(self, text: &str) -> Result<Value, String> {
+        match self {
+            CellType::Guessed => Ok(guess_value(text)),
+            CellType::String => Ok(Value::from(text)),
+            CellType::Json => json::parse(text).map_err(|error| error.to_string()),
+        }
+    }
+
+    /// Le texte d'une cellule, vide pour un champ absent.
+    fn write(self, value: Option<&Value>) -> String {
+        match (self, value) {
+            (_, None) => String::new(),
+            (CellType::String, Some(Value::String(text))) => text.clone(),
+            (_, Some(value)) => value.to_string(),
+        }
+    }
+}
+
+/// Les colonnes de métadonnées d'un export CSV : chaque champ présent dans au moins un document,
+/// par ordre alphabétique, avec le type qui écrit exactement toutes ses valeurs.
+pub fn payload_columns<'a>(payloads: impl Iterator<Item = &'a Payload>) -> Vec<(String, CellType)> {
+    let mut fields: BTreeMap<&String, Vec<&Value>> = BTreeMap::new();
+    for payload in payloads {
+        for (field, value) in payload {
+            fields.entry(field).or_default().push(value);
+        }
+    }
+    fields
+        .into_iter()
+        .map(|(field, values)| (field.clone(), CellType::of(values.into_iter())))
+        .collect()
+}
+
+/// # Énumération: `ErrorPolicy`
+///
+/// Ce que fait un import d'une ligne invalide (mal formée, ou dont le vecteur est refusé par la collection).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorPolicy {
+    /// L'import s'arrête sur l'erreur ; les documents des lignes précédentes restent enregistrés.
+    #[default]
+    Abort,
+    /// La ligne est ignorée et l'import continue ; l'erreur figure dans l'[`ImportReport`].
+    Skip,
+}
+
+/// # Structure: `ImportOptions`
+///
+/// Paramètres d'un import ([`Database::import`](crate::Database::import)).
+#[derive(Debug, Clone, Copy)]
+pub struct ImportOptions {
+    /// Le format du fichier.
+    pub format: Format,
+    /// Ce que fait l'import d'une ligne invalide.
+    pub on_error: ErrorPolicy,
+    /// Nombre de documents enregistrés ensemble, en une seule opération du journal (au moins 1).
+    pub batch_size: usize,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            format: Format::default(),
+            on_error: ErrorPolicy::default(),
+            batch_size: IMPORT_BATCH_SIZE,
+        }
+    }
+}
+
+/// # Structure: `ImportReport`
+///
+/// Le bilan d'un import.
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// Nombre de documents enregistrés.
+    pub imported: usize,
+    /// Nombre de lignes ignorées (avec [`ErrorPolicy::Skip`]).
+    pub skipped: usize,
+    /// Le numéro et l'erreur des premières lignes ignorées.
+    pub errors: Vec<(usize, String)>,
+}
+
+impl ImportReport {
+    /// Compte une ligne ignorée, et garde son erreur tant qu'il n'y en a pas trop.
+    pub fn skip(&mut self, line: usize, message: String) {
+        self.skipped += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push((line, message));
+        }
+    }
+}
+
+/// # Structure: `DocumentReader`
+///
+/// Lit les documents d'un fichier CSV ou JSON Lines, un à la fois.
+pub struct DocumentReader<R> {
+    source: Source<R>,
+}
+
+enum Source<R> {
+    JsonLines {
+        reader: R,
+        /// Nombre de lignes du fichier déjà lues.
+        line: usize,
+        buffer: Vec<u8>,
+    },
+    Csv {
+        reader: CsvReader<R>,
+        /// Le rôle de chaque colonne, `None` si le fichier est vide.
+        columns: Option<CsvColumns>,
+    },
+}
+
+impl<R: BufRead> DocumentReader<R> {
+    /// Prépare la lecture d'un fichier ; pour un fichier CSV, lit et vérifie la ligne d'en-tête.
+    ///
+    /// # Retour
+    /// - `Result<DocumentReader<R>, DbError>`: [`DbError::InvalidLine`] si l'en-tête est invalide : aucune
+    ///   ligne du fichier ne pourrait être lue, si bien que cette erreur interrompt toujours un import.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut reader = DocumentReader::new(BufReader::new(File::open("documents.csv")?), Format::Csv)?;
+    /// while let Some((line, (key, vector, payload))) = reader.next_document()? {
+    ///     // Utiliser le document
+    /// }
+    /// ```
+    pub fn new(reader: R, format: Format) -> Result<Self, DbError> {
+        let source = match format {
+            Format::JsonLines => Source::JsonLines {
+                reader,
+                line: 0,
+                buffer: Vec::new(),
+            },
+            Format::Csv => {
+                let mut reader = CsvReader::new(reader);
+                let columns = match reader.next_record()? {
+                    Some((line, header)) => Some(CsvColumns::parse(header).map_err(|message| invalid(line, message))?),
+                    None => None,
+                };
+                Source::Csv { reader, columns }
+            }
+        };
+        Ok(DocumentReader { source })
+    }
+
+    /// Lit le document suivant ; les lignes vides sont ignorées.
+    ///
+    /// Après une ligne invalide, l'appel suivant reprend à la ligne d'après.
+    ///
+    /// # Retour
+    /// - `Result<Option<(usize, Document)>, DbError>`: Le numéro de la ligne et le document, `None` à la fin
+    ///   du fichier, [`DbError::InvalidLine`] si la ligne est invalide ou [`DbError::Io`] si la lecture échoue.
+    pub fn next_document(&mut self) -> Result<Option<(usize, Document)>, DbError> {
+        match &mut self.source {
+            Source::JsonLines { reader, line, buffer } => loop {
+                buffer.clear();
+                if reader.read_until(b'\n', buffer)? == 0 {
+                    return Ok(None);
+                }
+                *line += 1;
+                let text = std::str::from_utf8(buffer).map_err(|_| invalid(*line, "le texte n'est pas de l'UTF-8".to_string()))?;
+                if text.trim().is_empty() {
+                    continue;
+                }
+                return match json_document(text) {
+                    Ok(document) => Ok(Some((*line, document))),
+                    Err(message) => Err(invalid(*line, message)),
+                };
+            },
+            Source::Csv { reader, columns } => {
+                let columns = match columns {
+                    Some(columns) => columns,
+                    None => return Ok(None),
+                };
+                match reader.next_record()? {
+                    Some((line, fields)) => match columns.document(fields) {
+                        Ok(document) => Ok(Some((line, document))),
+                        Err(message) => Err(invalid(line, message)),
+                    },
+                    None => Ok(None),
+                }
+            }
+        }
+    }
+}
+
+/// # Structure: `DocumentWriter`
+///
+/// Écrit des documents dans un fichier CSV ou JSON Lines, un à la fois.
+pub struct DocumentWriter<W: Write> {
+    writer: W,
+    format: Format,
+    /// Les champs des métadonnées qui ont une colonne et le type de leurs cellules (CSV uniquement).
+    columns: Vec<(String, CellType)>,
+}
+
+impl<W: Write> DocumentWriter<W> {
+    /// Prépare l'écriture d'un fichier ; pour un fichier CSV, écrit la ligne d'en-tête : `id`, une colonne
+    /// par coordonnée (`v0` à `v{dimension - 1}`), puis une colonne par champ de `columns`, suivi de son type
+    /// (`client:string`). Une colonne de type [`CellType::Guessed`] est écrite comme une colonne JSON.
+    ///
+    /// # Paramètres
+    /// - `writer`: La destination, de préférence dans un `BufWriter`.
+    /// - `format`: Le format du fichier.
+    /// - `dimension`: La dimension des vecteurs (CSV uniquement).
+    /// - `columns`: Les champs des métadonnées à écrire et leur type (CSV uniquement, voir [`payload_columns`]) ;
+    ///   les autres champs sont ignorés.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let columns = vec![("client".to_string(), CellType::String)];
+    /// let mut writer = DocumentWriter::new(BufWriter::new(File::create("documents.csv")?), Format::Csv, 3, columns)?;
+    /// writer.write(&doc_id, &[1.0, 2.0, 3.0], &payload)?;
+    /// writer.finish()?;
+    /// ```
+    pub fn new(mut writer: W, format: Format, dimension: usize, columns: Vec<(String, CellType)>) -> Result<Self, DbError> {
+        let columns: Vec<(String, CellType)> = columns
+            .into_iter()
+            .map(|(field, cell_type)| match cell_type {
+                CellType::Guessed => (field, CellType::Json),
+                _ => (field, cell_type),
+            })
+            .collect();
+        if format == Format::Csv {
+            let header: Vec<String> = std::iter::once("id".to_string())
+                .chain((0..dimension).map(|i| format!("v{}", i)))
+                .chain(columns.iter().map(|(field, cell_type)| match cell_type {
+                    CellType::String => format!("{}:string", field),
+                    _ => format!("{}:json", field),
+                }))
+                .collect();
+            let header: Vec<&str> = header.iter().map(String::as_str).collect();
+            csv::write_record(&mut writer, &header)?;
+        }
+        Ok(DocumentWriter { writer, format, columns })
+    }
+
+    /// Écrit un document.
+    pub fn write(&mut self, key: &DocumentId, vector: &[f32], payload: &Payload) -> Result<(), DbError> {
+        match self.format {
+            Format::JsonLines => {
+                let document = object([
+                    ("id", Value::from(key.to_string())),
+                    ("vector", json::vector(vector)),
+                    ("payload", Value::Object(payload.clone())),
+                ]);
+                writeln!(self.writer, "{}", document)?;
+            }
+            Format::Csv => {
+                let cells: Vec<String> = std::iter::once(key.to_string())
+                    .chain(vector.iter().map(f32::to_string))
+                    .chain(self.columns.iter().map(|(field, cell_type)| cell_type.write(payload.get(field))))
+                    .collect();
+                let cells: Vec<&str> = cells.iter().map(String::as_str).collect();
+                csv::write_record(&mut self.writer, &cells)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Termine l'écriture en vidant les tampons de la destination.
+    pub fn finish(mut self) -> Result<(), DbError> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// # Structure: `CsvColumns`
+///
+/// Le rôle de chaque colonne d'un fichier CSV, lu dans sa ligne d'en-tête.
+struct CsvColumns {
+    /// Nombre de colonnes.
+    count: usize,
+    /// La colonne `id`, si elle existe.
+    id: Option<usize>,
+    vector: VectorColumns,
+    /// Les autres colonnes, avec le nom du champ des métadonnées qu'elles contiennent et le type de leurs cellules.
+    payload: Vec<(usize, String, CellType)>,
+}
+
+/// Les colonnes qui contiennent le vecteur.
+enum VectorColumns {
+    /// Une colonne `vector`, qui contient toutes les coordonnées.
+    Single(usize),
+    /// Les colonnes `v0`, `v1`, etc., dans l'ordre des coordonnées.
+    Spread(Vec<usize>),
+}
+
+impl CsvColumns {
+    fn parse(header: Vec<String>) -> Result<Self, String> {
+        let count = header.len();
+        let mut names = HashSet::new();
+        let mut id = None;
+        let mut single = None;
+        let mut spread = Vec::new();
+        let mut payload = Vec::new();
+        for (column, name) in header.into_iter().enumerate() {
+            let name = name.trim().to_string();
+            if !names.insert(name.clone()) {
+                return Err(format!("colonne '{}' en double dans l'en-tête", name));
+            }
+            // Une colonne typée est toujours un champ des métadonnées, même nommée `id` ou `v0`.
+            match (name.as_str(), CellType::parse(&name)) {
+                (_, (field, cell_type @ (CellType::String | CellType::Json))) => payload.push((column, field.to_string(), cell_type)),
+                ("id", _) => id = Some(column),
+                ("vector", _) => single = Some(column),
+                _ => match coordinate(&name) {
+                    Some(coordinate) => spread.push((coordinate, column)),
+                    None => payload.push((column, name, CellType::Guessed)),
+                },
+            }
+        }
+        spread.sort_unstable();
+        if spread.iter().enumerate().any(|(i, (coordinate, _))| *coordinate != i) {
+            return Err("les colonnes de coordonnées doivent être numérotées v0, v1, etc. sans trou".to_string());
+        }
+        let vector = match (single, spread.is_empty()) {
+            (Some(column), true) => VectorColumns::Single(column),
+            (None, false) => VectorColumns::Spread(spread.into_iter().map(|(_, column)| column).collect()),
+            (Some(_), false) => return Err("le vecteur est donné à la fois par la colonne 'vector' et par des colonnes v0, v1, etc.".to_string()),
+            (None, true) => return Err("aucune colonne de vecteur dans l'en-tête ('vector', ou v0, v1, etc.)".to_string()),
+        };
+        Ok(CsvColumns { count, id, vector, payload })
+    }
+
+    fn document(&self, fields: Vec<String>) -> Result<Document, String> {
+        if fields.len() != self.count {
+            return Err(format!("{} colonne(s) attendue(s), {} trouvée(s)", self.count, fields.len()));
+        }
+        let key = match self.id.map(|column| fields[column].trim()) {
+            None | Some("") => Uuid::new_v4(),
+            Some(id) => parse_id(id)?,
+        };
+        let vector = match &self.vector {
+            VectorColumns::Single(column) => parse_vector(&fields[*column])?,
+            VectorColumns::Spread(columns) => columns
+                .iter()
+                .map(|&column| parse_coordinate(&fields[column]))
+                .collect::<Result<_, _>>()?,
+        };
+        let payload = self
+            .payload
+            .iter()
+            .filter(|(column, _, _)| !fields[*column].is_empty())
+            .map(|(column, name, cell_type)| {
+                let value = cell_type
+                    .read(&fields[*column])
+                    .map_err(|message| format!("colonne '{}' : {}", name, message))?;
+                Ok((name.clone(), value))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok((key, vector, payload))
+    }
+}
+
+/// Le rang de la coordonnée d'une colonne nommée `v0`, `v1`, etc.
+fn coordinate(name: &str) -> Option<usize> {
+    let digits = name.strip_prefix('v')?;
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Lit un document d'une ligne JSON Lines.
+fn json_document(text: &str) -> Result<Document, String> {
+    let document = match json::parse(text).map_err(|error| error.to_string())? {
+        Value::Object(document) => document,
+        _ => return Err("un objet JSON est attendu".to_string()),
+    };
+    let key = match document.get("id") {
+        None | Some(Value::Null) => Uuid::new_v4(),
+        Some(Value::String(id)) => parse_id(id)?,
+        Some(_) => return Err("le champ 'id' doit être une chaîne".to_string()),
+    };
+    let vector = document
+        .get("vector")
+        .and_then(json::parse_vector)
+        .ok_or("le champ 'vector' doit être un tableau de nombres")?;
+    let payload = match document.get("payload") {
+        None | Some(Value::Null) => Payload::new(),
+        Some(Value::Object(payload)) => payload.clone(),
+        Some(_) => return Err("le champ 'payload' doit être un objet JSON".to_string()),
+    };
+    Ok((key, vector, payload))
+}
+
+fn parse_id(id: &str) -> Result<DocumentId, String> {
+    Uuid::parse_str(id).map_err(|_| format!("identifiant de document invalide '{}'", id))
+}
+
+/// Lit un vecteur écrit `1,2,3`, `1 2 3` ou `[1, 2, 3]` dans une seule cellule.
+fn parse_vector(text: &str) -> Result<Vec<f32>, String> {
+    let text = text.trim();
+    let inner = text
+        .strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(text);
+    if inner.contains(',') {
+        inner.split(',').map(parse_coordinate).collect()
+    } else {
+        inner.split_whitespace().map(parse_coordinate).collect()
+    }
+}
+
+fn parse_coordinate(text: &str) -> Result<f32, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("coordonnée invalide '{}'", text.trim()))
+}
+
+/// La valeur d'une cellule de métadonnées d'une colonne sans type : booléen, nombre, tableau ou objet JSON
+/// s'il y a lieu, chaîne sinon.
+fn guess_value(text: &str) -> Value {
+    match text {
+        "true" => return Value::Bool(true),
+        "false" => return Value::Bool(false),
+        _ => {}
+    }
+    if let Ok(number) = text.parse::<f64>() {
+        if number.is_finite() {
+            return Value::Number(number);
+        }
+    }
+    if text.starts_with(['[', '{']) {
+        if let Ok(value) = json::parse(text) {
+            return value;
+        }
+    }
+    Value::from(text)
+}
+
+fn invalid(line: usize, message: String) -> DbError {
+    DbError::InvalidLine { line, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// Des métadonnées qu'une lecture devinant les types ne redonnerait pas telles quelles.
+    fn documents() -> Vec<Document> {
+        let nested = Value::Object(Payload::from([("p".to_string(), Value::Number(1.0))]));
+        vec![
+            (
+                Uuid::from_u128(1),
+                vec![0.5, -1.25],
+                Payload::from([
+                    ("code".to_string(), Value::from("00123")),
+                    ("actif".to_string(), Value::from("true")),
+                    ("liste".to_string(), Value::from("[1, 2]")),
+                    ("mixte".to_string(), Value::Number(1.5)),
+                    ("vide".to_string(), Value::from("")),
+                    ("id".to_string(), Value::from("client-1")),
+                    ("v0".to_string(), Value::Bool(false)),
+                    ("note:json".to_string(), Value::from("a")),
+                ]),
+            ),
+            (
+                Uuid::from_u128(2),
+                vec![3.0, 4.0],
+                Payload::from([
+                    ("code".to_string(), Value::from("7")),
+                    ("mixte".to_string(), Value::from("1.5")),
+                    ("nul".to_string(), Value::Null),
+                    ("objet".to_string(), nested),
+                    ("tableau".to_string(), Value::from(vec![Value::from("x"), Value::Null])),
+                ]),
+            ),
+            (Uuid::from_u128(3), vec![0.0, 1.0], Payload::new()),
+        ]
+    }
+
+    fn write(documents: &[Document], format: Format) -> String {
+        let columns = payload_columns(documents.iter().map(|(_, _, payload)| payload));
+        let mut writer = DocumentWriter::new(Vec::new(), format, 2, columns).unwrap();
+        for (key, vector, payload) in documents {
+            writer.write(key, vector, payload).unwrap();
+        }
+        String::from_utf8(writer.writer).unwrap()
+    }
+
+    fn read(text: &str, format: Format) -> Result<Vec<Document>, DbError> {
+        let mut reader = DocumentReader::new(Cursor::new(text), format)?;
+        let mut documents = Vec::new();
+        while let Some((_, document)) = reader.next_document()? {
+            documents.push(document);
+        }
+        Ok(documents)
+    }
+
+    #[test]
+    fn export_then_import_round_trips_exactly() {
+        for format in [Format::Csv, Format::JsonLines] {
+            let text = write(&documents(), format);
+            assert_eq!(read(&text, format).unwrap(), documents(), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn exported_csv_header_types_every_payload_column() {
+        let text = write(&documents(), Format::Csv);
+        assert_eq!(
+            text.lines().next().unwrap(),
+            "id,v0,v1,actif:string,code:string,id:string,liste:string,mixte:json,note:json:string,nul:json,objet:json,tableau:json,v0:json,vide:json"
+        );
+    }
+
+    #[test]
+    fn untyped_csv_columns_guess_the_type_of_each_cell() {
+        let text = "id,v0,v1,a,b,c,d\n00000000-0000-0000-0000-000000000001,1,2,00123,true,\"{\"\"p\"\":1}\",texte\n";
+        let (_, _, payload) = read(text, Format::Csv).unwrap().remove(0);
+        assert_eq!(payload["a"], Value::Number(123.0));
+        assert_eq!(payload["b"], Value::Bool(true));
+        assert_eq!(payload["c"], Value::Object(Payload::from([("p".to_string(), Value::Number(1.0))])));
+        assert_eq!(payload["d"], Value::from("texte"));
+    }
+
+    #[test]
+    fn invalid_json_cell_is_an_invalid_line() {
+        let text = "id,v0,n:json\n00000000-0000-0000-0000-000000000001,1,1\n00000000-0000-0000-0000-000000000002,1,texte\n";
+        let mut reader = DocumentReader::new(Cursor::new(text), Format::Csv).unwrap();
+        assert!(reader.next_document().unwrap().is_some());
+        assert!(matches!(reader.next_document(), Err(DbError::InvalidLine { line: 3, .. })));
+    }
+}
@@ -0,0 +1,179 @@
+//! # Module: `vectors`
+//!
+//! Le stockage des vecteurs d'une [`Collection`](crate::Collection) : en mémoire, dans une table de
+//! hachage, ou dans un fichier projeté en mémoire ([`MappedVectors`]) pour les collections plus grandes
+//! que la mémoire vive. Les recherches, les index et les quantificateurs lisent les vecteurs à travers
+//! l'énumération [`VectorStore`], sans savoir où ils se trouvent.
+
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::ops::Index;
+use std::path::Path;
+use std::slice;
+
+use crate::error::DbError;
+use crate::mmap::MappedVectors;
+use crate::DocumentId;
+
+/// # Énumération: `VectorStore`
+///
+/// Les vecteurs des documents d'une collection, indexés par leur identifiant.
+pub enum VectorStore {
+    Memory(HashMap<DocumentId, Vec<f32>>),
+    Mapped(MappedVectors),
+}
+
+impl Default for VectorStore {
+    fn default() -> Self {
+        VectorStore::Memory(HashMap::new())
+    }
+}
+
+impl VectorStore {
+    /// Crée un stockage vide dans un fichier projeté en mémoire : un fichier durable dans le répertoire
+    /// de données `directory` (voir [`MappedVectors::create`]), ou, sans répertoire, un fichier temporaire
+    /// (voir [`MappedVectors::temporary`]).
+    pub fn mapped(directory: Option<&Path>) -> Result<Self, DbError> {
+        let vectors = match directory {
+            Some(directory) => MappedVectors::create(directory)?,
+            None => MappedVectors::temporary(&std::env::temp_dir())?,
+        };
+        Ok(VectorStore::Mapped(vectors))
+    }
+
+    /// Rouvre le fichier de vecteurs durable d'un instantané (voir [`MappedVectors::open`]).
+    pub fn open_mapped(path: &Path, dimension: Option<usize>, slots: Vec<(DocumentId, usize)>) -> Result<Self, DbError> {
+        Ok(VectorStore::Mapped(MappedVectors::open(path, dimension, slots)?))
+    }
+
+    /// Indique si les vecteurs sont dans un fichier projeté en mémoire.
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, VectorStore::Mapped(_))
+    }
+
+    /// Le fichier projeté en mémoire des vecteurs, s'ils y sont.
+    pub fn as_mapped(&self) -> Option<&MappedVectors> {
+        match self {
+            VectorStore::Memory(_) => None,
+            VectorStore::Mapped(vectors) => Some(vectors),
+        }
+    }
+
+    /// Comme [`VectorStore::as_mapped`], en écriture.
+    pub fn as_mapped_mut(&mut self) -> Option<&mut MappedVectors> {
+        match self {
+            VectorStore::Memory(_) => None,
+            VectorStore::Mapped(vectors) => Some(vectors),
+        }
+    }
+
+    /// Nombre de documents stockés.
+    pub fn len(&self) -> usize {
+        match self {
+            VectorStore::Memory(vectors) => vectors.len(),
+            VectorStore::Mapped(vectors) => vectors.len(),
+        }
+    }
+
+    /// Les identifiants des documents, dans un ordre quelconque.
+    pub fn keys(&self) -> Keys<'_> {
+        match self {
+            VectorStore::Memory(vectors) => Keys::Memory(vectors.keys()),
+            VectorStore::Mapped(vectors) => Keys::Mapped(vectors.keys()),
+        }
+    }
+
+    /// Les documents et leur vecteur, dans le même ordre que [`VectorStore::keys`].
+    pub fn iter(&self) -> impl Iterator<Item = (&DocumentId, &[f32])> {
+        self.keys().filter_map(|key| Some((key, self.get(key)?)))
+    }
+
+    /// Les vecteurs, dans le même ordre que [`VectorStore::keys`].
+    pub fn values(&self) -> impl Iterator<Item = &[f32]> {
+        self.iter().map(|(_, vector)| vector)
+    }
+
+    /// Indique si le document `key` est stocké.
+    pub fn contains_key(&self, key: &DocumentId) -> bool {
+        match self {
+            VectorStore::Memory(vectors) => vectors.contains_key(key),
+            VectorStore::Mapped(vectors) => vectors.contains_key(key),
+        }
+    }
+
+    /// Le vecteur du document `key`, s'il est stocké.
+    pub fn get(&self, key: &DocumentId) -> Option<&[f32]> {
+        match self {
+            VectorStore::Memory(vectors) => vectors.get(key).map(Vec::as_slice),
+            VectorStore::Mapped(vectors) => vectors.get(key),
+        }
+    }
+
+    /// Enregistre le vecteur d'un document et retourne son ancien vecteur, s'il en avait un.
+    ///
+    /// # Retour
+    /// - `Result<Option<Vec<f32>>, DbError>`: L'ancien vecteur, ou l'erreur du fichier projeté
+    ///   qui n'a pas pu recevoir le vecteur (voir [`MappedVectors::insert`]) ; rien n'est alors modifié.
+    pub fn insert(&mut self, key: DocumentId, vector: Vec<f32>) -> Result<Option<Vec<f32>>, DbError> {
+        match self {
+            VectorStore::Memory(vectors) => Ok(vectors.insert(key, vector)),
+            VectorStore::Mapped(vectors) => vectors.insert(key, &vector),
+        }
+    }
+
+    /// Prépare la place de `additional` vecteurs de dimension `dimension`, nouveaux ou remplacés,
+    /// pour qu'un lot puisse ensuite être écrit sans échouer à mi-chemin.
+    pub fn reserve(&mut self, additional: usize, dimension: usize) -> Result<(), DbError> {
+        match self {
+            VectorStore::Memory(vectors) => {
+                vectors.reserve(additional);
+                Ok(())
+            }
+            VectorStore::Mapped(vectors) => vectors.reserve(additional, dimension),
+        }
+    }
+
+    /// Retire le vecteur d'un document et le retourne, si le document existait.
+    pub fn remove(&mut self, key: &DocumentId) -> Option<Vec<f32>> {
+        match self {
+            VectorStore::Memory(vectors) => vectors.remove(key),
+            VectorStore::Mapped(vectors) => vectors.remove(key),
+        }
+    }
+}
+
+/// Le vecteur d'un document, comme pour une `HashMap` : le document doit être stocké.
+impl Index<&DocumentId> for VectorStore {
+    type Output = [f32];
+
+    fn index(&self, key: &DocumentId) -> &[f32] {
+        self.get(key).expect("document absent du stockage des vecteurs")
+    }
+}
+
+/// # Énumération: `Keys`
+///
+/// Itérateur sur les identifiants des documents d'un [`VectorStore`].
+#[derive(Clone)]
+pub enum Keys<'a> {
+    Memory(hash_map::Keys<'a, DocumentId, Vec<f32>>),
+    Mapped(slice::Iter<'a, DocumentId>),
+}
+
+impl<'a> Iterator for Keys<'a> {
+    type Item = &'a DocumentId;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            Keys::Memory(keys) => keys.next(),
+            Keys::Mapped(keys) => keys.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self {
+            Keys::Memory(keys) => keys.size_hint(),
+            Keys::Mapped(keys) => keys.size_hint(),
+        }
+    }
+}
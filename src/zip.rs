@@ -0,0 +1,324 @@
+//! # Module: `zip`
+//!
+//! Lecture des entrées d'une archive ZIP, le format des fichiers `.npz` de NumPy : une entrée par
+//! tableau, recopiée telle quelle (`numpy.savez`) ou compressée par *deflate* (`numpy.savez_compressed`).
+//!
+//! La liste des entrées est lue dans le répertoire central, à la fin de l'archive (extensions ZIP64
+//! comprises, pour les archives de plus de 4 Gio) ; chaque entrée est ensuite lue au fil de l'eau.
+
+use std::io::{BufReader, Read, Seek, SeekFrom};
+
+use crate::error::DbError;
+use crate::inflate::Inflate;
+
+/// Signatures des structures de l'archive.
+const LOCAL_HEADER: u32 = 0x0403_4b50;
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const ZIP64_LOCATOR: u32 = 0x0706_4b50;
+
+/// Taille de la fin du répertoire central, sans le commentaire de l'archive (au plus 65 535 octets).
+const END_OF_CENTRAL_DIRECTORY_SIZE: u64 = 22;
+
+/// Méthodes de compression prises en charge.
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// # Structure: `ZipEntry`
+///
+/// Une entrée de l'archive, décrite par le répertoire central.
+#[derive(Debug, Clone)]
+struct ZipEntry {
+    name: String,
+    method: u16,
+    flags: u16,
+    compressed_size: u64,
+    /// Position de l'en-tête local de l'entrée dans l'archive.
+    offset: u64,
+}
+
+/// # Structure: `ZipArchive`
+///
+/// Une archive ZIP ouverte en lecture.
+pub struct ZipArchive<R> {
+    reader: R,
+    entries: Vec<ZipEntry>,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    /// Lit le répertoire central de l'archive.
+    ///
+    /// # Retour
+    /// - `Result<ZipArchive<R>, DbError>`: [`DbError::Corrupted`] si le fichier n'est pas une archive ZIP valide.
+    ///
+    /// # Exemple
+    ///
+    /// ```
+    /// let mut archive = ZipArchive::open(File::open("embeddings.npz")?)?;
+    /// let vectors = archive.open_entry("vectors.npy")?;
+    /// ```
+    pub fn open(mut reader: R) -> Result<Self, DbError> {
+        let length = reader.seek(SeekFrom::End(0))?;
+        let tail_length = length.min(END_OF_CENTRAL_DIRECTORY_SIZE + u16::MAX as u64);
+        reader.seek(SeekFrom::Start(length - tail_length))?;
+        let mut tail = vec![0; tail_length as usize];
+        reader.read_exact(&mut tail)?;
+        let end = (0..tail.len().saturating_sub(END_OF_CENTRAL_DIRECTORY_SIZE as usize - 1))
+            .rev()
+            .find(|&position| u32_at(&tail, position) == END_OF_CENTRAL_DIRECTORY)
+            .ok_or_else(|| corrupted("fin du répertoire central introuvable"))?;
+        let end_position = length - tail_length + end as u64;
+
+        let mut count = u16_at(&tail, end + 10) as u64;
+        let mut directory_offset = u32_at(&tail, end + 16) as u64;
+        if count == u16::MAX as u64 || directory_offset == u32::MAX as u64 {
+            // Archive ZIP64 : le localisateur précède immédiatement la fin du répertoire central.
+            let mut locator = [0; 20];
+            reader.seek(SeekFrom::Start(end_position.checked_sub(20).ok_or_else(|| corrupted("localisateur ZIP64 absent"))?))?;
+            reader.read_exact(&mut locator)?;
+            if u32_at(&locator, 0) != ZIP64_LOCATOR {
+                return Err(corrupted("localisateur ZIP64 absent"));
+            }
+            let mut end64 = [0; 56];
+            reader.seek(SeekFrom::Start(u64_at(&locator, 8)))?;
+            reader.read_exact(&mut end64)?;
+            if u32_at(&end64, 0) != ZIP64_END_OF_CENTRAL_DIRECTORY {
+                return Err(corrupted("fin du répertoire central ZIP64 invalide"));
+            }
+            count = u64_at(&end64, 32);
+            directory_offset = u64_at(&end64, 48);
+        }
+
+        reader.seek(SeekFrom::Start(directory_offset))?;
+        let mut directory = BufReader::new(&mut reader);
+        let mut entries = Vec::new();
+        for _ in 0..count {
+            entries.push(read_central_header(&mut directory)?);
+        }
+        Ok(ZipArchive { reader, entries })
+    }
+
+    /// Ouvre une entrée en lecture ; son contenu est décompressé à mesure qu'il est lu.
+    ///
+    /// # Retour
+    /// - `Result<Box<dyn Read + '_>, DbError>`: Le contenu de l'entrée, [`DbError::Corrupted`] si elle
+    ///   n'existe pas ou si son en-tête est invalide, ou [`DbError::Unsupported`] si elle est chiffrée
+    ///   ou compressée par une autre méthode que *deflate*.
+    pub fn open_entry(&mut self, name: &str) -> Result<Box<dyn Read + '_>, DbError> {
+        let entry = self
+            .entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .cloned()
+            .ok_or_else(|| {
+                let names: Vec<&str> = self.entries.iter().map(|entry| entry.name.as_str()).collect();
+                corrupted(&format!("entrée '{}' absente de l'archive (entrées : {})", name, names.join(", ")))
+            })?;
+        if entry.flags & 1 != 0 {
+            return Err(DbError::Unsupported(format!("l'entrée '{}' est chiffrée", name)));
+        }
+
+        let mut header = [0; 30];
+        self.reader.seek(SeekFrom::Start(entry.offset))?;
+        self.reader.read_exact(&mut header)?;
+        if u32_at(&header, 0) != LOCAL_HEADER {
+            return Err(corrupted(&format!("en-tête local de l'entrée '{}' invalide", name)));
+        }
+        let skipped = u16_at(&header, 26) as i64 + u16_at(&header, 28) as i64;
+        self.reader.seek(SeekFrom::Current(skipped))?;
+
+        let data = BufReader::new((&mut self.reader).take(entry.compressed_size));
+        match entry.method {
+            STORED => Ok(Box::new(data)),
+            DEFLATED => Ok(Box::new(Inflate::new(data))),
+            method => Err(DbError::Unsupported(format!(
+                "l'entrée '{}' est compressée par la méthode {} (seules l'absence de compression et deflate sont prises en charge)",
+                name, method
+            ))),
+        }
+    }
+}
+
+/// Lit une entrée du répertoire central.
+fn read_central_header(reader: &mut impl Read) -> Result<ZipEntry, DbError> {
+    let mut header = [0; 46];
+    reader.read_exact(&mut header)?;
+    if u32_at(&header, 0) != CENTRAL_HEADER {
+        return Err(corrupted("entrée du répertoire central invalide"));
+    }
+    let mut variable = vec![0; u16_at(&header, 28) as usize + u16_at(&header, 30) as usize + u16_at(&header, 32) as usize];
+    reader.read_exact(&mut variable)?;
+    let (name, rest) = variable.split_at(u16_at(&header, 28) as usize);
+    let extra = &rest[..u16_at(&header, 30) as usize];
+
+    let mut uncompressed_size = u32_at(&header, 24) as u64;
+    let mut compressed_size = u32_at(&header, 20) as u64;
+    let mut offset = u32_at(&header, 42) as u64;
+    // Les champs qui valent 0xFFFFFFFF sont donnés, dans cet ordre, par l'extension ZIP64 (identifiant 1).
+    let mut position = 0;
+    while position + 4 <= extra.len() {
+        let (id, size) = (u16_at(extra, position), u16_at(extra, position + 2) as usize);
+        let field = extra.get(position + 4..position + 4 + size).ok_or_else(|| corrupted("champ supplémentaire tronqué"))?;
+        if id == 1 {
+            let mut values = field.chunks_exact(8).map(|value| u64_at(value, 0));
+            for value in [&mut uncompressed_size, &mut compressed_size, &mut offset] {
+                if *value == u32::MAX as u64 {
+                    *value = values.next().ok_or_else(|| corrupted("extension ZIP64 incomplète"))?;
+                }
+            }
+        }
+        position += 4 + size;
+    }
+    Ok(ZipEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        method: u16_at(&header, 10),
+        flags: u16_at(&header, 8),
+        compressed_size,
+        offset,
+    })
+}
+
+fn u16_at(bytes: &[u8], position: usize) -> u16 {
+    u16::from_le_bytes(bytes[position..position + 2].try_into().unwrap())
+}
+
+fn u32_at(bytes: &[u8], position: usize) -> u32 {
+    u32::from_le_bytes(bytes[position..position + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], position: usize) -> u64 {
+    u64::from_le_bytes(bytes[position..position + 8].try_into().unwrap())
+}
+
+fn corrupted(message: &str) -> DbError {
+    DbError::Corrupted(format!("archive ZIP : {}", message))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    /// Écrites par `testdata/generate.py` comme le font `numpy.savez` (entrées recopiées) et
+    /// `numpy.savez_compressed` (entrées *deflate*).
+    const SAVED: &[u8] = include_bytes!("../testdata/vectors.npz");
+    const COMPRESSED: &[u8] = include_bytes!("../testdata/vectors_compressed.npz");
+
+    fn entry(archive: &[u8], name: &str) -> Result<Vec<u8>, DbError> {
+        let mut archive = ZipArchive::open(Cursor::new(archive))?;
+        let mut data = Vec::new();
+        archive.open_entry(name)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Une archive ZIP64 d'une entrée recopiée : tailles et position dans l'extension du répertoire central,
+    /// nombre d'entrées et position du répertoire dans la fin de répertoire ZIP64.
+    fn zip64_archive(name: &str, data: &[u8]) -> Vec<u8> {
+        let mut archive = Vec::new();
+        archive.extend(LOCAL_HEADER.to_le_bytes());
+        archive.extend([45, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        archive.extend([0xff; 8]);
+        archive.extend((name.len() as u16).to_le_bytes());
+        archive.extend(0u16.to_le_bytes());
+        archive.extend(name.as_bytes());
+        archive.extend_from_slice(data);
+
+        let directory = archive.len() as u64;
+        archive.extend(CENTRAL_HEADER.to_le_bytes());
+        archive.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        archive.extend([0xff; 8]);
+        archive.extend((name.len() as u16).to_le_bytes());
+        archive.extend(28u16.to_le_bytes());
+        archive.extend([0; 10]);
+        archive.extend(u32::MAX.to_le_bytes());
+        archive.extend(name.as_bytes());
+        archive.extend(1u16.to_le_bytes());
+        archive.extend(24u16.to_le_bytes());
+        for value in [data.len() as u64, data.len() as u64, 0] {
+            archive.extend(value.to_le_bytes());
+        }
+
+        let end64 = archive.len() as u64;
+        archive.extend(ZIP64_END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend(44u64.to_le_bytes());
+        archive.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        for value in [1, 1, end64 - directory, directory] {
+            archive.extend(value.to_le_bytes());
+        }
+        archive.extend(ZIP64_LOCATOR.to_le_bytes());
+        archive.extend(0u32.to_le_bytes());
+        archive.extend(end64.to_le_bytes());
+        archive.extend(1u32.to_le_bytes());
+
+        archive.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        archive.extend([0; 4]);
+        archive.extend([0xff; 12]);
+        archive.extend(0u16.to_le_bytes());
+        archive
+    }
+
+    /// Position du premier en-tête du répertoire central, celui de `ids.npy`.
+    fn central_header(archive: &[u8]) -> usize {
+        (0..archive.len() - 4).find(|&position| u32_at(archive, position) == CENTRAL_HEADER).unwrap()
+    }
+
+    #[test]
+    fn reads_stored_and_deflated_entries() {
+        for name in ["ids.npy", "vectors.npy"] {
+            let data = entry(SAVED, name).unwrap();
+            assert!(data.starts_with(b"\x93NUMPY"), "{}", name);
+            assert_eq!(entry(COMPRESSED, name).unwrap(), data, "{}", name);
+        }
+        assert_eq!(entry(SAVED, "vectors_3d.npy").unwrap().len(), 128 + 12 * 4);
+
+        let data: Vec<u8> = (0..1000u32).map(|i| i as u8).collect();
+        assert_eq!(entry(&zip64_archive("vectors.npy", &data), "vectors.npy").unwrap(), data);
+
+        // Le commentaire de l'archive n'empêche pas de trouver la fin du répertoire central.
+        let mut commented = COMPRESSED.to_vec();
+        let length = commented.len();
+        commented[length - 2..].copy_from_slice(&5u16.to_le_bytes());
+        commented.extend(b"numpy");
+        assert_eq!(entry(&commented, "vectors.npy").unwrap(), entry(SAVED, "vectors.npy").unwrap());
+    }
+
+    #[test]
+    fn missing_entries_are_corrupted() {
+        match entry(COMPRESSED, "embeddings.npy") {
+            Err(DbError::Corrupted(message)) => assert!(message.contains("ids.npy, vectors.npy"), "{}", message),
+            other => panic!("{:?}", other.map(|data| data.len())),
+        }
+        assert!(matches!(entry(b"PK\x03\x04 pas une archive", "ids.npy"), Err(DbError::Corrupted(_))));
+    }
+
+    #[test]
+    fn encrypted_entries_and_other_methods_are_unsupported() {
+        let position = central_header(SAVED);
+        let mut encrypted = SAVED.to_vec();
+        encrypted[position + 8] |= 1;
+        assert!(matches!(entry(&encrypted, "ids.npy"), Err(DbError::Unsupported(_))));
+        // Méthode 12 : bzip2.
+        let mut bzip2 = SAVED.to_vec();
+        bzip2[position + 10] = 12;
+        assert!(matches!(entry(&bzip2, "ids.npy"), Err(DbError::Unsupported(_))));
+        assert!(entry(&bzip2, "vectors.npy").is_ok());
+    }
+
+    #[test]
+    fn truncated_or_corrupted_archives_never_panic() {
+        for archive in [COMPRESSED, &zip64_archive("ids.npy", &[1, 2, 3])] {
+            for length in 0..archive.len() {
+                assert!(entry(&archive[..length], "ids.npy").is_err(), "{}", length);
+            }
+            for position in 0..archive.len() {
+                for mask in [0x01, 0x80, 0xff] {
+                    let mut corrupted = archive.to_vec();
+                    corrupted[position] ^= mask;
+                    let _ = entry(&corrupted, "ids.npy");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,581 @@
+"""Écrit les fichiers de référence des tests de lecture Parquet, Arrow, NumPy et deflate.
+
+    python3 testdata/generate.py testdata
+
+`reference.parquet` et `reference.arrow` suivent la disposition de ceux qu'écrit pyarrow 15 pour la table ci-dessous, avec
+`pq.write_table(table, path, row_group_size=3)` et `pa.ipc.new_file(path, schema)` (deux lots de 3
+et 2 lignes), mais ne sont pas écrits par pyarrow :
+
+    id: string, vector: fixed_size_list<item: float>[3], rank: int64, tag: string, score: double
+
+ce script ne dépend que de la bibliothèque standard, et les écrit indépendamment du code de lecture
+et d'écriture de la base, pour le mettre à l'épreuve de ce qu'il n'écrit pas lui-même :
+
+- FlatBuffers construits de la fin vers le début comme le fait la bibliothèque officielle : vtables
+  placées avant leur table et partagées entre tables identiques (distance négative), champs égaux à
+  leur valeur par défaut omis, champs rangés du plus grand au plus petit ;
+- Parquet en version 2.6 avec des pages de données de version 1 : chaque colonne, y compris les
+  coordonnées des vecteurs, encodée par dictionnaire (page `PLAIN` puis indices `RLE_DICTIONARY`),
+  compressée en Snappy avec des copies, statistiques, `ARROW:schema` dans les métadonnées, et la liste
+  de taille fixe écrite en liste à trois niveaux (`vector` / `list` / `element`).
+
+`vectors.npz` et `vectors_compressed.npz` sont écrits comme le font `numpy.savez` et
+`numpy.savez_compressed` (NumPy 1.26), mais pas par NumPy : le module `zipfile`, appelé avec les mêmes
+arguments, et des en-têtes `.npy` construits comme `numpy.lib.format.write_array`, date des entrées
+exceptée. La première archive contient aussi des tableaux qui ne conviennent pas (type, boutisme,
+forme, ordre, longueur).
+
+`window.deflate` est un flux deflate brut (`zlib`, niveau 9) de données dont les copies vont jusqu'au
+bout de la fenêtre de 32 Kio ; le test qui le lit les recalcule avec le même générateur.
+"""
+
+import base64
+import os
+import struct
+import sys
+import zipfile
+import zlib
+
+IDS = [
+    "0b4f5a8e-3c1d-4e9a-8f2b-6d7c1e0a9b31",
+    "5e2d8c47-91a3-4b6f-a0d4-2c8e7f1b3a55",
+    "9a7c3e21-4d8b-4f1e-b6a2-7e5d0c9f8b12",
+    "c1f0e9d8-7b6a-4c5d-8e4f-3a2b1c0d9e87",
+    "f3e2d1c0-b9a8-4f7e-9d6c-5b4a3f2e1d06",
+]
+VECTORS = [[0.5, -1.0, 2.0], [1.5, 0.0, -2.5], [3.0, 3.0, 3.0], [0.25, 0.125, -0.0625], [0.5, 1000.0, -7.0]]
+RANKS = [1, 2, 3, 4, 5]
+TAGS = ["vente", None, "bail", "vente", None]
+SCORES = [0.5, 1.25, -3.0, 2.0, 0.0]
+BATCHES = [(0, 3), (3, 5)]
+
+
+# FlatBuffers, construit de la fin vers le début.
+
+class Builder:
+    def __init__(self):
+        self.buffer = bytearray()  # les octets écrits, à l'envers : buffer[::-1] est le tampon final
+        self.minalign = 1
+        self.vtable = None
+        self.object_end = 0
+        self.vtables = []
+
+    def offset(self):
+        return len(self.buffer)
+
+    def pad(self, count):
+        self.buffer += b"\0" * count
+
+    def prep(self, size, additional):
+        self.minalign = max(self.minalign, size)
+        self.pad((-(len(self.buffer) + additional)) % size)
+
+    def place(self, data):
+        self.buffer += data[::-1]
+
+    def prepend(self, fmt, value):
+        size = struct.calcsize(fmt)
+        self.prep(size, 0)
+        self.place(struct.pack("<" + fmt, value))
+
+    def prepend_offset(self, offset):
+        self.prep(4, 0)
+        self.place(struct.pack("<I", self.offset() - offset + 4))
+
+    def string(self, text):
+        data = text.encode()
+        self.prep(4, len(data) + 1)
+        self.place(data + b"\0")
+        self.place(struct.pack("<I", len(data)))
+        return self.offset()
+
+    def vector_of_offsets(self, offsets):
+        self.prep(4, 4 * len(offsets))
+        for offset in reversed(offsets):
+            self.prepend_offset(offset)
+        self.place(struct.pack("<I", len(offsets)))
+        return self.offset()
+
+    def vector_of_structs(self, structs, size, align):
+        self.prep(4, size * len(structs))
+        self.prep(align, size * len(structs))
+        for data in reversed(structs):
+            self.place(data)
+        self.place(struct.pack("<I", len(structs)))
+        return self.offset()
+
+    def start(self, fields):
+        self.vtable = [0] * fields
+        self.object_end = self.offset()
+
+    def add(self, slot, fmt, value, default=0):
+        if value != default:
+            self.prepend(fmt, value)
+            self.vtable[slot] = self.offset()
+
+    def add_offset(self, slot, offset):
+        self.prepend_offset(offset)
+        self.vtable[slot] = self.offset()
+
+    def end(self):
+        self.prepend("i", 0)
+        table = self.offset()
+        entries = list(self.vtable)
+        while entries and entries[-1] == 0:
+            entries.pop()
+        encoded = [len(entries) * 2 + 4, table - self.object_end] + [table - entry if entry else 0 for entry in entries]
+        vtable = b"".join(struct.pack("<H", value) for value in encoded)
+        for existing, data in self.vtables:
+            if data == vtable:
+                self.set_soffset(table, existing - table)
+                break
+        else:
+            for value in reversed(encoded):
+                self.place(struct.pack("<H", value))
+            self.vtables.append((self.offset(), vtable))
+            self.set_soffset(table, self.offset() - table)
+        self.vtable = None
+        return table
+
+    def set_soffset(self, table, value):
+        # buffer est à l'envers : l'octet de poids faible de la table est à l'indice table - 1.
+        data = struct.pack("<i", value)
+        for index, byte in enumerate(data):
+            self.buffer[table - 1 - index] = byte
+
+    def finish(self, root):
+        self.prep(self.minalign, 4)
+        self.prepend_offset(root)
+        return bytes(self.buffer[::-1])
+
+
+# Messages Arrow IPC.
+
+UTF8, INT, FLOATING_POINT, FIXED_SIZE_LIST = 5, 2, 3, 16
+
+
+def arrow_field(builder, name, type_id, type_fields, nullable=True, children=()):
+    children = [arrow_field(builder, *child) for child in children]
+    children_vector = builder.vector_of_offsets(children)
+    builder.start(len(type_fields) if type_fields else 0)
+    for slot, fmt, value in type_fields:
+        builder.add(slot, fmt, value)
+    type_table = builder.end()
+    name = builder.string(name)
+    builder.start(7)
+    builder.add_offset(5, children_vector)
+    builder.add_offset(3, type_table)
+    builder.add_offset(0, name)
+    builder.add(2, "B", type_id)
+    builder.add(1, "?", nullable, False)
+    return builder.end()
+
+
+def arrow_schema(builder):
+    fields = [
+        arrow_field(builder, "id", UTF8, []),
+        arrow_field(builder, "vector", FIXED_SIZE_LIST, [(0, "i", 3)], True, [("item", FLOATING_POINT, [(0, "h", 1)])]),
+        arrow_field(builder, "rank", INT, [(0, "i", 64), (1, "?", True)]),
+        arrow_field(builder, "tag", UTF8, []),
+        arrow_field(builder, "score", FLOATING_POINT, [(0, "h", 2)]),
+    ]
+    fields = builder.vector_of_offsets(fields)
+    builder.start(4)
+    builder.add_offset(1, fields)
+    return builder.end()
+
+
+def message(header_type, build_header, body_length):
+    builder = Builder()
+    header = build_header(builder)
+    builder.start(5)
+    builder.add(3, "q", body_length)
+    builder.add_offset(2, header)
+    builder.add(0, "h", 4)
+    builder.add(1, "B", header_type)
+    metadata = builder.finish(builder.end())
+    metadata += b"\0" * (-(len(metadata) + 8) % 8)
+    return struct.pack("<Ii", 0xFFFFFFFF, len(metadata)) + metadata
+
+
+def validity(values):
+    if all(value is not None for value in values):
+        return b""
+    bits = bytearray((len(values) + 7) // 8)
+    for index, value in enumerate(values):
+        if value is not None:
+            bits[index // 8] |= 1 << (index % 8)
+    return bytes(bits)
+
+
+def utf8_buffers(values):
+    offsets, data = [0], b""
+    for value in values:
+        data += (value or "").encode()
+        offsets.append(len(data))
+    return [validity(values), b"".join(struct.pack("<i", offset) for offset in offsets), data]
+
+
+def record_batch(lo, hi):
+    rows = hi - lo
+    nodes = []
+    buffers = []
+    for values, arrays in [
+        (IDS[lo:hi], [utf8_buffers(IDS[lo:hi])]),
+        (VECTORS[lo:hi], [[b""], [b"", b"".join(struct.pack("<f", x) for vector in VECTORS[lo:hi] for x in vector)]]),
+        (RANKS[lo:hi], [[b"", b"".join(struct.pack("<q", rank) for rank in RANKS[lo:hi])]]),
+        (TAGS[lo:hi], [utf8_buffers(TAGS[lo:hi])]),
+        (SCORES[lo:hi], [[b"", b"".join(struct.pack("<d", score) for score in SCORES[lo:hi])]]),
+    ]:
+        nodes.append((rows, sum(value is None for value in values)))
+        if len(arrays) == 2:
+            nodes.append((rows * 3, 0))
+        for array in arrays:
+            buffers.extend(array)
+    body = b""
+    descriptions = []
+    for buffer in buffers:
+        descriptions.append(struct.pack("<qq", len(body), len(buffer)))
+        body += buffer + b"\0" * (-len(buffer) % 8)
+
+    def header(builder):
+        buffer_vector = builder.vector_of_structs(descriptions, 16, 8)
+        node_vector = builder.vector_of_structs([struct.pack("<qq", *node) for node in nodes], 16, 8)
+        builder.start(5)
+        builder.add(0, "q", rows)
+        builder.add_offset(2, buffer_vector)
+        builder.add_offset(1, node_vector)
+        return builder.end()
+
+    return message(3, header, len(body)), body
+
+
+def arrow_file():
+    output = bytearray(b"ARROW1\0\0")
+    output += message(1, arrow_schema, 0)
+    blocks = []
+    for lo, hi in BATCHES:
+        metadata, body = record_batch(lo, hi)
+        blocks.append(struct.pack("<qi4xq", len(output), len(metadata), len(body)))
+        output += metadata + body
+    output += struct.pack("<Ii", 0xFFFFFFFF, 0)
+    builder = Builder()
+    batches = builder.vector_of_structs(blocks, 24, 8)
+    dictionaries = builder.vector_of_structs([], 24, 8)
+    schema = arrow_schema(builder)
+    builder.start(5)
+    builder.add_offset(3, batches)
+    builder.add_offset(2, dictionaries)
+    builder.add_offset(1, schema)
+    builder.add(0, "h", 4)
+    footer = builder.finish(builder.end())
+    output += footer + struct.pack("<i", len(footer)) + b"ARROW1"
+    return bytes(output)
+
+
+# Thrift compact et Parquet.
+
+I32, I64, BINARY, LIST, STRUCT = 5, 6, 8, 9, 12
+
+
+def varint(value):
+    output = bytearray()
+    while value >= 0x80:
+        output.append(value & 0x7F | 0x80)
+        value >>= 7
+    output.append(value)
+    return bytes(output)
+
+
+def zigzag(value):
+    return (value << 1) ^ (value >> 63)
+
+
+def thrift_value(kind, value):
+    if kind in (I32, I64):
+        return varint(zigzag(value))
+    if kind == BINARY:
+        return varint(len(value)) + value
+    if kind == STRUCT:
+        return thrift_struct(value)
+    element, items = value
+    head = bytes([len(items) << 4 | element]) if len(items) < 15 else bytes([0xF0 | element]) + varint(len(items))
+    return head + b"".join(thrift_value(element, item) for item in items)
+
+
+def thrift_struct(fields):
+    output, last = bytearray(), 0
+    for field, kind, value in fields:
+        if kind == "bool":
+            kind, body = (1 if value else 2), b""
+        else:
+            body = thrift_value(kind, value)
+        delta = field - last
+        output += bytes([delta << 4 | kind]) if 0 < delta <= 15 else bytes([kind]) + varint(zigzag(field))
+        output += body
+        last = field
+    return bytes(output + b"\0")
+
+
+def hybrid(values, width):
+    """Encodage hybride RLE / bit-packing : une répétition si les valeurs sont toutes égales, des groupes de 8 sinon."""
+    if len(set(values)) == 1:
+        return varint(len(values) << 1) + values[0].to_bytes((width + 7) // 8, "little")
+    padded = values + [0] * (-len(values) % 8)
+    bits, count, output = 0, 0, bytearray()
+    for value in padded:
+        bits |= value << count
+        count += width
+        while count >= 8:
+            output.append(bits & 0xFF)
+            bits >>= 8
+            count -= 8
+    return varint((len(padded) // 8) << 1 | 1) + bytes(output)
+
+
+def bit_width(value):
+    return value.bit_length()
+
+
+def snappy(data):
+    """Un bloc Snappy : des littéraux, et des copies des séquences de 4 octets déjà vues."""
+    output = bytearray(varint(len(data)))
+    seen = {}
+    literal = 0
+    position = 0
+
+    def flush(end):
+        nonlocal literal
+        while literal < end:
+            count = min(end - literal, 60)
+            output.append((count - 1) << 2)
+            output.extend(data[literal : literal + count])
+            literal += count
+
+    while position + 4 <= len(data):
+        key = data[position : position + 4]
+        candidate = seen.get(key)
+        seen[key] = position
+        if candidate is not None and position - candidate < 65536:
+            length = 4
+            while position + length < len(data) and length < 64 and data[candidate + length] == data[position + length]:
+                length += 1
+            flush(position)
+            output.append((length - 1) << 2 | 2)
+            output.extend(struct.pack("<H", position - candidate))
+            position += length
+            literal = position
+        else:
+            position += 1
+    flush(len(data))
+    return bytes(output)
+
+
+def plain(physical, values):
+    if physical == 1:
+        return b"".join(struct.pack("<i", value) for value in values)
+    if physical == 2:
+        return b"".join(struct.pack("<q", value) for value in values)
+    if physical == 4:
+        return b"".join(struct.pack("<f", value) for value in values)
+    if physical == 5:
+        return b"".join(struct.pack("<d", value) for value in values)
+    return b"".join(struct.pack("<I", len(value)) + value for value in values)
+
+
+def statistics(physical, values, nulls):
+    fields = [(3, I64, nulls)]
+    if values:
+        key = (lambda value: value) if physical != 6 else bytes
+        fields += [(5, BINARY, plain(physical, [max(values, key=key)])[4 if physical == 6 else 0 :]),
+                   (6, BINARY, plain(physical, [min(values, key=key)])[4 if physical == 6 else 0 :])]
+    return fields
+
+
+def column_chunk(output, path, physical, values, definitions, max_definition, repetitions, rows):
+    """Écrit la page du dictionnaire et la page de données d'une colonne, et retourne sa description."""
+    dictionary = []
+    for value in values:
+        if value not in dictionary:
+            dictionary.append(value)
+    dictionary_bytes = plain(physical, dictionary)
+    dictionary_page = snappy(dictionary_bytes)
+    dictionary_header = thrift_struct([
+        (1, I32, 2), (2, I32, len(dictionary_bytes)), (3, I32, len(dictionary_page)),
+        (7, STRUCT, [(1, I32, len(dictionary)), (2, I32, 0)]),
+    ])
+    body = b""
+    if repetitions is not None:
+        levels = hybrid(repetitions, 1)
+        body += struct.pack("<I", len(levels)) + levels
+    levels = hybrid(definitions, bit_width(max_definition))
+    body += struct.pack("<I", len(levels)) + levels
+    width = bit_width(len(dictionary) - 1)
+    body += bytes([width]) + (hybrid([dictionary.index(value) for value in values], width) if values else b"")
+    data_page = snappy(body)
+    nulls = sum(definition < max_definition for definition in definitions)
+    data_header = thrift_struct([
+        (1, I32, 0), (2, I32, len(body)), (3, I32, len(data_page)),
+        (5, STRUCT, [(1, I32, len(definitions)), (2, I32, 8), (3, I32, 3), (4, I32, 3),
+                     (5, STRUCT, statistics(physical, values, nulls))]),
+    ])
+    start = len(output)
+    output += dictionary_header + dictionary_page
+    data_offset = len(output)
+    output += data_header + data_page
+    size = len(output) - start
+    uncompressed = len(dictionary_header) + len(dictionary_bytes) + len(data_header) + len(body)
+    metadata = [
+        (1, I32, physical), (2, LIST, (I32, [0, 3, 8])), (3, LIST, (BINARY, [name.encode() for name in path])),
+        (4, I32, 1), (5, I64, len(definitions)), (6, I64, uncompressed), (7, I64, size), (9, I64, data_offset),
+        (11, I64, start), (12, STRUCT, statistics(physical, values, nulls)),
+        (13, LIST, (STRUCT, [[(1, I32, 2), (2, I32, 0), (3, I32, 1)], [(1, I32, 0), (2, I32, 8), (3, I32, 1)]])),
+    ]
+    return [(2, I64, start), (3, STRUCT, metadata)], size, uncompressed
+
+
+def parquet_file():
+    output = bytearray(b"PAR1")
+    row_groups = []
+    for ordinal, (lo, hi) in enumerate(BATCHES):
+        rows = hi - lo
+        start = len(output)
+        chunks, compressed, uncompressed = [], 0, 0
+
+        def add(*arguments):
+            nonlocal compressed, uncompressed
+            chunk, size, raw = column_chunk(output, *arguments)
+            chunks.append(chunk)
+            compressed += size
+            uncompressed += raw
+
+        add(["id"], 6, [value.encode() for value in IDS[lo:hi]], [1] * rows, 1, None, rows)
+        coordinates = [x for vector in VECTORS[lo:hi] for x in vector]
+        repetitions = [0 if index % 3 == 0 else 1 for index in range(len(coordinates))]
+        add(["vector", "list", "element"], 4, coordinates, [3] * len(coordinates), 3, repetitions, rows)
+        add(["rank"], 2, RANKS[lo:hi], [1] * rows, 1, None, rows)
+        tags = TAGS[lo:hi]
+        add(["tag"], 6, [tag.encode() for tag in tags if tag is not None], [int(tag is not None) for tag in tags], 1, None, rows)
+        add(["score"], 5, SCORES[lo:hi], [1] * rows, 1, None, rows)
+        row_groups.append([
+            (1, LIST, (STRUCT, chunks)), (2, I64, uncompressed), (3, I64, rows),
+            (5, I64, start), (6, I64, compressed), (7, I32, ordinal),
+        ])
+
+    string = [(6, I32, 0), (10, STRUCT, [(1, STRUCT, [])])]
+    schema = [
+        [(4, BINARY, b"schema"), (5, I32, 5)],
+        [(1, I32, 6), (3, I32, 1), (4, BINARY, b"id")] + string,
+        [(3, I32, 1), (4, BINARY, b"vector"), (5, I32, 1), (6, I32, 3), (10, STRUCT, [(3, STRUCT, [])])],
+        [(3, I32, 2), (4, BINARY, b"list"), (5, I32, 1)],
+        [(1, I32, 4), (3, I32, 1), (4, BINARY, b"element")],
+        [(1, I32, 2), (3, I32, 1), (4, BINARY, b"rank")],
+        [(1, I32, 6), (3, I32, 1), (4, BINARY, b"tag")] + string,
+        [(1, I32, 5), (3, I32, 1), (4, BINARY, b"score")],
+    ]
+    arrow_schema_message = message(1, arrow_schema, 0)
+    footer = thrift_struct([
+        (1, I32, 2), (2, LIST, (STRUCT, schema)), (3, I64, len(IDS)), (4, LIST, (STRUCT, row_groups)),
+        (5, LIST, (STRUCT, [[(1, BINARY, b"ARROW:schema"), (2, BINARY, base64.b64encode(arrow_schema_message))]])),
+        (6, BINARY, b"parquet-cpp-arrow version 15.0.2"),
+        (7, LIST, (STRUCT, [[(1, STRUCT, [])] for _ in range(5)])),
+    ])
+    output += footer + struct.pack("<I", len(footer)) + b"PAR1"
+    return bytes(output)
+
+
+# NumPy.
+
+NPZ_IDS = [
+    "1c9d6bcd-bbfd-4b2d-9b5d-ab8dfbbd4bed",
+    "6ec0bd7f-11c0-43da-975e-2a8ad9ebae0b",
+    "a3bb189e-8bf9-3888-9912-ace4e6543002",
+    "e4d909c2-90d0-fb1c-a068-ffaddf22cbd0",
+]
+NPZ_VECTORS = [[1.0, -2.0, 0.5], [0.25, 3.0, -1.5], [8.0, 0.0, -0.125], [2.5, 4.0, 6.0]]
+
+
+def npy(descr, shape, data, fortran_order=False):
+    """Un fichier `.npy` de version 1.0, comme l'écrit `numpy.lib.format.write_array`."""
+    header = "{'descr': %r, 'fortran_order': %r, 'shape': %r, }" % (descr, fortran_order, tuple(shape))
+    header += " " * (21 - len(repr(shape[-1 if fortran_order else 0])))
+    length = len(header) + 1
+    padding = 64 - (8 + 2 + length) % 64
+    return b"\x93NUMPY\x01\x00" + struct.pack("<H", length + padding) + header.encode("latin1") + b" " * padding + b"\n" + data
+
+
+def floats(fmt, rows):
+    return b"".join(struct.pack(fmt, value) for row in rows for value in row)
+
+
+def unicode(ids):
+    return b"".join(text.encode("utf-32-le").ljust(4 * 36, b"\0") for text in ids)
+
+
+def npz(arrays, compression):
+    """Une archive comme celle de `numpy.savez` : `zipfile.ZipFile(..., allowZip64=True)` et `open(nom, "w", force_zip64=True)`."""
+    path = os.path.join(os.environ.get("TMPDIR", "/tmp"), "generate.npz")
+    with zipfile.ZipFile(path, mode="w", compression=compression, allowZip64=True) as archive:
+        for name, data in arrays:
+            info = zipfile.ZipInfo(name + ".npy", date_time=(2024, 1, 1, 0, 0, 0))
+            info.compress_type = compression
+            with archive.open(info, "w", force_zip64=True) as entry:
+                entry.write(data)
+    with open(path, "rb") as file:
+        data = file.read()
+    os.remove(path)
+    return data
+
+
+def npz_files():
+    vectors = npy("<f4", (4, 3), floats("<f", NPZ_VECTORS))
+    ids = npy("<U36", (4,), unicode(NPZ_IDS))
+    columns = [list(column) for column in zip(*NPZ_VECTORS)]
+    saved = npz([
+        ("ids", ids),
+        ("vectors", vectors),
+        ("vectors_be", npy(">f4", (4, 3), floats(">f", NPZ_VECTORS))),
+        ("vectors_f2", npy("<f2", (4, 3), floats("<e", NPZ_VECTORS))),
+        ("vectors_f8", npy("<f8", (4, 3), floats("<d", NPZ_VECTORS))),
+        ("vectors_fortran", npy("<f4", (4, 3), floats("<f", columns), fortran_order=True)),
+        ("vectors_3d", npy("<f4", (2, 2, 3), floats("<f", NPZ_VECTORS))),
+        ("ids_int", npy("<i8", (4,), b"".join(struct.pack("<q", index) for index in range(4)))),
+        ("ids_short", npy("<U36", (3,), unicode(NPZ_IDS[:3]))),
+    ], zipfile.ZIP_STORED)
+    compressed = npz([("ids", ids), ("vectors", vectors)], zipfile.ZIP_DEFLATED)
+    return saved, compressed
+
+
+# Deflate.
+
+def window_data():
+    """Des lettres pseudo-aléatoires, entrecoupées de copies de 300 octets pris 32 000 octets plus tôt."""
+    state, data = 1, bytearray()
+    while len(data) < 100_000:
+        state = (state * 1103515245 + 12345) % 2**31
+        if len(data) > 32_000 and (state >> 16) % 8 == 0:
+            start = len(data) - 32_000
+            data += data[start : start + 300]
+        else:
+            data.append(97 + (state >> 16) % 16)
+    return bytes(data)
+
+
+def window_deflate():
+    compressor = zlib.compressobj(9, zlib.DEFLATED, -15)
+    return compressor.compress(window_data()) + compressor.flush()
+
+
+if __name__ == "__main__":
+    directory = sys.argv[1] if len(sys.argv) > 1 else os.path.dirname(os.path.abspath(__file__))
+    with open(os.path.join(directory, "reference.arrow"), "wb") as file:
+        file.write(arrow_file())
+    with open(os.path.join(directory, "reference.parquet"), "wb") as file:
+        file.write(parquet_file())
+    saved, compressed = npz_files()
+    with open(os.path.join(directory, "vectors.npz"), "wb") as file:
+        file.write(saved)
+    with open(os.path.join(directory, "vectors_compressed.npz"), "wb") as file:
+        file.write(compressed)
+    with open(os.path.join(directory, "window.deflate"), "wb") as file:
+        file.write(window_deflate())